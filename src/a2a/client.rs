@@ -1,7 +1,9 @@
+use crate::protocol::agent_card::AgentCard;
 use crate::protocol::manifest::A2AManifest;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
@@ -12,12 +14,17 @@ pub enum A2AError {
     Json(#[from] serde_json::Error),
     #[error("Protocol error: {0}")]
     Protocol(String),
+    #[error("Task {0} was cancelled")]
+    Cancelled(Uuid),
 }
 
 #[derive(Debug, Clone)]
 pub struct A2AClient {
     pub base_url: String,
     pub client: reqwest::Client,
+    /// Sent as `Authorization: Bearer <token>` with every request; the peer
+    /// accepts a JWT or an API key.
+    pub auth_token: Option<String>,
 }
 
 impl A2AClient {
@@ -25,12 +32,34 @@ impl A2AClient {
         Self {
             base_url,
             client: reqwest::Client::new(),
+            auth_token: None,
+        }
+    }
+
+    /// Authenticates requests to the peer with `token`.
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.client.get(url))
+    }
+
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.client.post(url))
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.auth_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
         }
     }
 
     pub async fn fetch_manifest(&self) -> Result<crate::protocol::manifest::A2AManifest, A2AError> {
         let url = format!("{}/manifest", self.base_url);
-        let response = self.client.get(&url).send().await?;
+        let response = self.get(&url).send().await?;
 
         if !response.status().is_success() {
             return Err(A2AError::Protocol(format!(
@@ -43,10 +72,28 @@ impl A2AClient {
         Ok(manifest)
     }
 
+    /// Fetches the peer's agent card from `/.well-known/agent.json`, falling
+    /// back to `/agent.json`.
+    pub async fn fetch_agent_card(&self) -> Result<AgentCard, A2AError> {
+        let base_url = self.base_url.trim_end_matches('/');
+        let mut status = None;
+        for path in ["/.well-known/agent.json", "/agent.json"] {
+            let response = self.get(&format!("{base_url}{path}")).send().await?;
+            if response.status().is_success() {
+                return Ok(response.json().await?);
+            }
+            status = Some(response.status());
+        }
+        Err(A2AError::Protocol(format!(
+            "Failed to fetch agent card: {}",
+            status.map(|s| s.to_string()).unwrap_or_default()
+        )))
+    }
+
     pub async fn send_message(&self, message: A2AMessage) -> Result<A2AMessage, A2AError> {
         let url = format!("{}/messages", self.base_url);
 
-        let response = self.client.post(&url).json(&message).send().await?;
+        let response = self.post(&url).json(&message).send().await?;
 
         if !response.status().is_success() {
             return Err(A2AError::Protocol(format!(
@@ -58,6 +105,45 @@ impl A2AClient {
         let response_message: A2AMessage = response.json().await?;
         Ok(response_message)
     }
+
+    /// Sends a message, asking the peer to cancel the task (identified by the
+    /// message id) when `cancel` fires.
+    pub async fn send_message_with_cancel(
+        &self,
+        message: A2AMessage,
+        cancel: &CancellationToken,
+    ) -> Result<A2AMessage, A2AError> {
+        let task_id = message.id;
+        tokio::select! {
+            result = self.send_message(message) => result,
+            _ = cancel.cancelled() => {
+                if let Err(e) = self.cancel_task(&task_id.to_string()).await {
+                    tracing::warn!("Failed to cancel A2A task {}: {}", task_id, e);
+                }
+                Err(A2AError::Cancelled(task_id))
+            }
+        }
+    }
+
+    /// Calls the peer's `tasks/cancel` method for `task_id`.
+    pub async fn cancel_task(&self, task_id: &str) -> Result<(), A2AError> {
+        let url = format!("{}/tasks/cancel", self.base_url);
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": Uuid::new_v4(),
+            "method": "tasks/cancel",
+            "params": { "id": task_id }
+        });
+
+        let response = self.post(&url).json(&request).send().await?;
+        if !response.status().is_success() {
+            return Err(A2AError::Protocol(format!(
+                "Failed to cancel task: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
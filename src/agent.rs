@@ -1,20 +1,126 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::a2a::client::A2AClient;
-use crate::agent::state::StateMachine;
+use crate::agent::session::SessionManager;
+use crate::agent::state::AgentState;
+use crate::core::capabilities::{Capability, CapabilityManager, Provenance};
+use crate::core::state::user_memory::{self, MemoryFilter, UserMemory};
+use crate::core::state::{BufferedMessage, MemoryScope, TieredMemory, TurnMemory};
 use crate::llm::providers::ProviderConfig;
 use crate::llm::LLMConfig;
 use crate::llm::LLMService;
-use crate::mcp::client::MCPClient;
-use crate::protocol::manifest::Manifest;
+use crate::mcp::client::{MCPClient, MCPError};
+use crate::protocol::manifest::{Manifest, ToolAnnotations};
+use crate::protocol::message::{Message, MessageContent};
 use crate::protocol::agent_card::{AgentCard, AgentSkill};
+use crate::services::audit::AuditTrail;
+use crate::services::embeddings::{EmbeddingProvider, HashingEmbedder};
+use crate::services::knowledge::{self, KnowledgeBase};
+use crate::services::memory::MemoryService;
+use crate::services::security::Permission;
+use crate::services::tools::{ApprovalPolicy, Tool, ToolContext, ToolExecutionEngine};
+use crate::services::vector_store::{IndexKind, LocalVectorStore, VectorStore};
 
+pub mod approval;
+pub use approval::{
+    ApprovalDecision, ApprovalError, ApprovalRegistry, ApprovalRequest, PendingApproval,
+};
 pub mod builder;
 pub use builder::AgentBuilder;
+pub mod limiter;
+pub use limiter::{Admission, ConcurrencyLimiter, LimiterConfig, LimiterError, LimiterStats};
+use limiter::WorkerSlot;
+pub mod session;
+pub use session::{Session, SessionConfig, SessionInfo};
 pub mod state;
+pub mod task;
+pub use task::{TaskError, TaskInfo, TaskRegistry, TaskStatus};
+
+/// Deadline of a turn when the caller doesn't set one.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Tool calls a single turn may make before it is stopped.
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 8;
+
+/// Message metadata keys that identify the conversation a message belongs to,
+/// in order of precedence.
+const SESSION_METADATA_KEYS: [&str; 4] = ["session_id", "context_id", "contextId", "conversation_id"];
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AgentError {
+    #[error("agent overloaded: {0}")]
+    Overloaded(#[from] LimiterError),
+    #[error("{0}")]
+    Processing(String),
+    #[error("task {0} was canceled")]
+    Canceled(String),
+    #[error("task {task_id} exceeded its {timeout:?} deadline")]
+    Timeout { task_id: String, timeout: Duration },
+    #[error("tool loop stopped after {0} iterations")]
+    ToolLoopLimit(usize),
+    #[error("call to tool {tool} was denied: {reason}")]
+    ApprovalDenied { tool: String, reason: String },
+    #[error("caller lacks permission {0}")]
+    Forbidden(Permission),
+    #[error(transparent)]
+    Task(#[from] TaskError),
+}
+
+/// Per-request settings of [`Agent::run_turn`].
+#[derive(Debug, Clone, Default)]
+pub struct TurnOptions {
+    /// Id under which the turn is registered, e.g. an A2A task id. Random
+    /// when unset.
+    pub task_id: Option<String>,
+    /// Overrides [`Agent::request_timeout`]. Time spent waiting for a tool
+    /// approval counts towards it.
+    pub timeout: Option<Duration>,
+    /// Permissions of the caller. `ToolApprove` and `Admin` pre-approve
+    /// sensitive tool calls; `ToolAccess` alone does not.
+    pub permissions: Vec<Permission>,
+    /// Stops the turn with [`AgentError::Forbidden`] when the LLM calls a
+    /// tool the caller holds neither `ToolAccess` nor `Admin` for.
+    pub restrict_tools: bool,
+    /// Authenticated user the task and its approvals belong to.
+    pub owner: Option<String>,
+}
+
+/// Where a tool the LLM asked for runs.
+enum ToolHandle {
+    Mcp(MCPClient),
+    Local(Arc<dyn Tool>),
+}
+
+/// Adds `citations` to the metadata of `reply`.
+fn with_citations(mut reply: Message, citations: Vec<serde_json::Value>) -> Message {
+    if citations.is_empty() {
+        return reply;
+    }
+    let mut metadata = reply.metadata.take().unwrap_or_else(|| serde_json::json!({}));
+    if let Some(metadata) = metadata.as_object_mut() {
+        knowledge::merge_citations(metadata, citations);
+    }
+    reply.metadata = Some(metadata);
+    reply
+}
+
+/// Who a turn runs for.
+struct Caller<'a> {
+    /// Sender of the message; memories and local tool calls belong to this user.
+    user_id: &'a str,
+    /// Permissions granted to the caller; `ToolApprove` skips tool approvals.
+    permissions: &'a [Permission],
+    /// Whether tools need a `ToolAccess` permission.
+    restrict_tools: bool,
+    /// Authenticated user the turn's task belongs to.
+    owner: Option<&'a str>,
+    /// LLM worker slot of the turn, given back while waiting for approval.
+    worker: &'a WorkerSlot<'a>,
+}
 
 #[derive(Debug, Clone)]
 pub struct AgentConfig {
@@ -30,8 +136,34 @@ pub struct Agent {
     pub mcp_clients: HashMap<String, MCPClient>,
     pub a2a_clients: HashMap<String, A2AClient>,
     pub manifests: Arc<RwLock<HashMap<String, Manifest>>>,
-    pub state_machine: Arc<RwLock<StateMachine>>,
+    /// Capabilities of connected MCP servers, A2A peers and local tools.
+    /// The router and the agent card draw from here.
+    pub capabilities: Arc<CapabilityManager>,
+    pub sessions: Arc<SessionManager>,
+    pub limiter: Arc<ConcurrencyLimiter>,
+    pub tasks: Arc<TaskRegistry>,
+    pub request_timeout: Duration,
+    pub max_tool_iterations: usize,
+    pub approvals: Arc<ApprovalRegistry>,
+    pub approval_policy: ApprovalPolicy,
     pub llm: Arc<RwLock<LLMService>>,
+    /// Compresses a session's context when it nears the model's context window.
+    pub memory: Arc<MemoryService>,
+    /// Turns text into vectors for retrieval and embedding-based routing.
+    pub embeddings: Arc<dyn EmbeddingProvider>,
+    /// Stores vectors for retrieval.
+    pub vectors: Arc<dyn VectorStore>,
+    /// Local tools the LLM may call besides the tools of MCP servers.
+    pub tools: Arc<ToolExecutionEngine>,
+    /// Documents the agent answers from, when a knowledge base is configured.
+    pub knowledge: Option<Arc<KnowledgeBase>>,
+    /// Facts the agent remembers about its users, when configured.
+    pub user_memory: Option<Arc<UserMemory>>,
+    /// Summaries of messages that left a session's context, kept per user
+    /// and session and recalled in the session's later turns, when configured.
+    pub conversation_memory: Option<Arc<TieredMemory>>,
+    /// Records LLM calls, tool calls and A2A delegations.
+    pub audit: Arc<AuditTrail>,
 }
 
 impl Agent {
@@ -42,7 +174,14 @@ impl Agent {
             mcp_clients: HashMap::new(),
             a2a_clients: HashMap::new(),
             manifests: Arc::new(RwLock::new(HashMap::new())),
-            state_machine: Arc::new(RwLock::new(StateMachine::new(100))),
+            capabilities: Arc::new(CapabilityManager::new()),
+            sessions: Arc::new(SessionManager::default()),
+            limiter: Arc::new(ConcurrencyLimiter::default()),
+            tasks: Arc::new(TaskRegistry::new()),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            max_tool_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            approvals: Arc::new(ApprovalRegistry::new()),
+            approval_policy: ApprovalPolicy::default(),
             llm: Arc::new(RwLock::new(LLMService::new(
                 LLMConfig::default(),
                 ProviderConfig {
@@ -51,44 +190,571 @@ impl Agent {
                     google: None,
                 },
             ))),
+            memory: Arc::new(MemoryService::new()),
+            embeddings: Arc::new(HashingEmbedder::default()),
+            vectors: Arc::new(LocalVectorStore::new(IndexKind::Flat)),
+            tools: Arc::new(ToolExecutionEngine::new()),
+            knowledge: None,
+            user_memory: None,
+            conversation_memory: None,
+            audit: Arc::new(AuditTrail::default()),
         }
     }
 
-    pub async fn process_message(
+    /// Returns the session key of a message: an explicit session/context id
+    /// from its metadata, falling back to the sender.
+    pub fn session_key(message: &Message) -> String {
+        message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| {
+                SESSION_METADATA_KEYS
+                    .iter()
+                    .find_map(|key| metadata.get(*key).and_then(|v| v.as_str()))
+            })
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| message.sender.clone())
+    }
+
+    pub async fn process_message(&self, message: Message) -> Result<Message, AgentError> {
+        let session_id = Self::session_key(&message);
+        self.process_in_session(&session_id, message).await
+    }
+
+    pub async fn process_in_session(
         &self,
-        message: crate::protocol::message::Message,
-    ) -> Result<crate::protocol::message::Message, String> {
-        let mut state_machine = self.state_machine.write().await;
-        state_machine.add_message(message.clone());
-        state_machine.transition(crate::agent::state::AgentState::Processing);
+        session_id: &str,
+        message: Message,
+    ) -> Result<Message, AgentError> {
+        self.run_turn(session_id, message, TurnOptions::default())
+            .await
+    }
 
-        // Use LLM to process the message
-        let llm = self.llm.read().await;
-        let context = state_machine.get_context();
+    /// Runs one turn of `session_id` as a cancellable task. Turns of the same
+    /// session run one after another; turns of different sessions run
+    /// concurrently, bounded by [`Agent::limiter`]. A turn is admitted to the
+    /// limiter's queue up front but only takes a worker slot once it holds the
+    /// session's turn lock, and gives the slot back while it waits for an
+    /// approval. Locks are only held to read or update the session, never
+    /// across LLM or tool calls.
+    ///
+    /// The turn stops when its task is canceled through [`Agent::tasks`] or
+    /// its deadline passes; in-flight provider requests are dropped and
+    /// in-flight MCP tool calls are cancelled on the server. Calls to tools
+    /// flagged by [`Agent::approval_policy`] pause the task in the
+    /// `input-required` state until they are approved or denied through
+    /// [`Agent::approvals`].
+    pub async fn run_turn(
+        &self,
+        session_id: &str,
+        message: Message,
+        options: TurnOptions,
+    ) -> Result<Message, AgentError> {
+        // Reject before touching the session so an overloaded request leaves no trace
+        let admission = self.limiter.admit()?;
 
-        let response = match message.content {
-            crate::protocol::message::MessageContent::Text { ref text } => {
-                llm.process_message(text, &context).await?
+        let (task_id, cancel) = self
+            .tasks
+            .start(options.task_id.clone(), session_id, options.owner.as_deref())
+            .await?;
+        let timeout = options.timeout.unwrap_or(self.request_timeout);
+        let watchdog = {
+            let tasks = self.tasks.clone();
+            let task_id = task_id.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                let _ = tasks.expire(&task_id).await;
+            })
+        };
+
+        let result = self
+            .execute_turn(session_id, &task_id, message, &options, admission, &cancel)
+            .await;
+        watchdog.abort();
+
+        match result {
+            Ok(response) => {
+                self.tasks.finish(&task_id, TaskStatus::Completed, None).await;
+                Ok(response)
+            }
+            Err(AgentError::Canceled(_)) => {
+                let expired = self
+                    .tasks
+                    .get(&task_id)
+                    .await
+                    .is_some_and(|task| task.status == TaskStatus::Failed);
+                if expired {
+                    Err(AgentError::Timeout { task_id, timeout })
+                } else {
+                    Err(AgentError::Canceled(task_id))
+                }
+            }
+            Err(e @ (AgentError::ApprovalDenied { .. } | AgentError::Forbidden(_))) => {
+                self.tasks
+                    .finish(&task_id, TaskStatus::Rejected, Some(e.to_string()))
+                    .await;
+                Err(e)
+            }
+            Err(e) => {
+                self.tasks
+                    .finish(&task_id, TaskStatus::Failed, Some(e.to_string()))
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn execute_turn(
+        &self,
+        session_id: &str,
+        task_id: &str,
+        message: Message,
+        options: &TurnOptions,
+        admission: Admission<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<Message, AgentError> {
+        let canceled = || AgentError::Canceled(task_id.to_string());
+
+        let session = self.sessions.get_or_create(session_id).await;
+        let _turn = tokio::select! {
+            turn = session.lock_turn() => turn,
+            _ = cancel.cancelled() => return Err(canceled()),
+        };
+        let worker = tokio::select! {
+            permit = admission.acquire() => WorkerSlot::new(&self.limiter, permit),
+            _ = cancel.cancelled() => return Err(canceled()),
+        };
+
+        let mut history = session.state_machine.read().await.get_context();
+        let tokens = MemoryService::count_tokens(&history)
+            + MemoryService::count_tokens(std::slice::from_ref(&message));
+        if self.memory.should_compress(tokens) {
+            // The turn lock keeps other turns from touching the context meanwhile
+            let llm = self.llm.read().await.clone();
+            let compressed = tokio::select! {
+                compressed = self.memory.compress_messages_with(&history, Some(&llm)) => compressed,
+                _ = cancel.cancelled() => return Err(canceled()),
+            };
+            if let Some(call) = &compressed.llm_call {
+                self.audit_llm_call(
+                    &message.sender,
+                    &llm.config,
+                    &call.prompt,
+                    &[],
+                    &call.reply,
+                    serde_json::json!({
+                        "session_id": session.id,
+                        "task_id": task_id,
+                        "purpose": "context_compression"
+                    }),
+                )
+                .await;
+            }
+            // Nothing left to fold in when the previous pass already reached its minimum size
+            if compressed.summary.is_some() {
+                tracing::info!(
+                    "Compressed context of session {} from {} to {} tokens",
+                    session_id,
+                    compressed.original_token_count,
+                    compressed.compressed_token_count
+                );
+                history = compressed.messages;
+                session
+                    .state_machine
+                    .write()
+                    .await
+                    .replace_context(history.clone());
+            }
+        }
+        {
+            let mut state_machine = session.state_machine.write().await;
+            state_machine.add_message(message.clone());
+            state_machine.transition(AgentState::Processing);
+        }
+        self.sessions.record_message(&session, &message).await;
+
+        let result = match message.content {
+            MessageContent::Text { ref text } => {
+                let caller = Caller {
+                    user_id: &message.sender,
+                    permissions: &options.permissions,
+                    restrict_tools: options.restrict_tools,
+                    owner: options.owner.as_deref(),
+                    worker: &worker,
+                };
+                self.run_llm_loop(&session, task_id, text, history, &caller, cancel)
+                    .await
             }
             _ => {
                 // Handle other message types directly
-                crate::protocol::message::Message::new(
+                Ok(Message::new(
                     self.config.name.clone(),
                     message.sender.clone(),
-                    crate::protocol::message::MessageContent::Text {
+                    MessageContent::Text {
                         text: "Processed".to_string(),
                     },
                     None,
-                )
+                ))
+            }
+        };
+
+        let mut response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                let state = match e {
+                    AgentError::Canceled(_)
+                    | AgentError::ApprovalDenied { .. }
+                    | AgentError::Forbidden(_) => AgentState::Idle,
+                    ref e => AgentState::Error(e.to_string()),
+                };
+                session.state_machine.write().await.transition(state);
+                self.age_evicted(&session, &message.sender).await;
+                session.touch().await;
+                return Err(e);
             }
         };
+        response.sender = self.config.name.clone();
+        response.recipient = message.sender.clone();
+        let mut metadata = response.metadata.take().unwrap_or_else(|| serde_json::json!({}));
+        if let Some(metadata) = metadata.as_object_mut() {
+            metadata.insert("role".to_string(), serde_json::json!("assistant"));
+            metadata.insert("session_id".to_string(), serde_json::json!(session_id));
+            metadata.insert("task_id".to_string(), serde_json::json!(task_id));
+        }
+        response.metadata = Some(metadata);
 
-        state_machine.add_message(response.clone());
-        state_machine.transition(crate::agent::state::AgentState::Idle);
+        {
+            let mut state_machine = session.state_machine.write().await;
+            state_machine.add_message(response.clone());
+            state_machine.transition(AgentState::Idle);
+        }
+        self.sessions.record_message(&session, &response).await;
+        self.age_evicted(&session, &message.sender).await;
+        session.touch().await;
 
         Ok(response)
     }
 
+    /// Hands the messages that left the session's context during the turn to
+    /// the conversation memory of `user_id` in that session.
+    async fn age_evicted(&self, session: &Session, user_id: &str) {
+        let evicted = session.state_machine.write().await.take_evicted();
+        let Some(memory) = &self.conversation_memory else {
+            return;
+        };
+        let scope = MemoryScope::new(user_id, session.id.clone());
+        for message in &evicted {
+            memory.age(&scope, BufferedMessage::from(message)).await;
+        }
+    }
+
+    /// Asks the LLM to answer `input`, executing the MCP tools it calls and
+    /// feeding their results back until it produces a final reply. Stops with
+    /// [`AgentError::ToolLoopLimit`] after [`Agent::max_tool_iterations`] calls.
+    async fn run_llm_loop(
+        &self,
+        session: &Session,
+        task_id: &str,
+        input: &str,
+        mut history: Vec<Message>,
+        caller: &Caller<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<Message, AgentError> {
+        let canceled = || AgentError::Canceled(task_id.to_string());
+        // Clone the service so a reconfiguration doesn't wait on running calls
+        let llm = self.llm.read().await.clone();
+        let overrides = session.llm_overrides().await;
+        let mut input = input.to_string();
+        let mut iterations = 0;
+        let mut citations = Vec::new();
+
+        if let Some(knowledge) = self.knowledge.as_ref().filter(|k| k.config().auto_retrieve) {
+            let hits = tokio::select! {
+                hits = knowledge.search(&input, knowledge.config().top_k) => hits,
+                _ = cancel.cancelled() => return Err(canceled()),
+            };
+            match hits {
+                Ok(hits) if !hits.is_empty() => {
+                    history.push(Message::new(
+                        "knowledge".to_string(),
+                        self.config.name.clone(),
+                        MessageContent::Text {
+                            text: knowledge::format_hits(&hits),
+                        },
+                        Some(serde_json::json!({ "role": "system" })),
+                    ));
+                    citations.extend(knowledge::citation_values(&hits));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Knowledge base search failed: {}", e),
+            }
+        }
+        if let Some(memory) = self.user_memory.as_ref().filter(|m| m.config().auto_recall) {
+            let scope = MemoryFilter::scope(caller.user_id, &self.config.name);
+            match memory.recall(&scope, &input, memory.config().recall_limit).await {
+                Ok(hits) if !hits.is_empty() => history.push(Message::new(
+                    "memory".to_string(),
+                    self.config.name.clone(),
+                    MessageContent::Text {
+                        text: user_memory::format_memories(&hits),
+                    },
+                    Some(serde_json::json!({ "role": "system" })),
+                )),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Recalling memories of {} failed: {}", caller.user_id, e),
+            }
+        }
+        if let Some(memory) = &self.conversation_memory {
+            let scope = MemoryScope::new(caller.user_id, session.id.clone());
+            let turn = TurnMemory {
+                recalled: memory.recall(&scope, &input, memory.config().recall_limit).await,
+                summaries: memory.medium_term(&scope).await,
+                recent: Vec::new(),
+            };
+            if let Some(text) = turn.context() {
+                history.push(Message::new(
+                    "memory".to_string(),
+                    self.config.name.clone(),
+                    MessageContent::Text { text },
+                    Some(serde_json::json!({ "role": "system" })),
+                ));
+            }
+        }
+
+        let scope = serde_json::json!({ "session_id": session.id, "task_id": task_id });
+        loop {
+            // Dropping the provider future aborts its HTTP request
+            let reply = tokio::select! {
+                reply = llm.process_message_with_overrides(&input, &history, &overrides) => reply,
+                _ = cancel.cancelled() => return Err(canceled()),
+            };
+            self.audit_llm_call(
+                caller.user_id,
+                &overrides.apply(&llm.config),
+                &input,
+                &history,
+                &reply,
+                scope.clone(),
+            )
+            .await;
+            let reply = reply.map_err(AgentError::Processing)?;
+
+            let MessageContent::ToolCall { tool, parameters } = &reply.content else {
+                return Ok(with_citations(reply, citations));
+            };
+            let Some((handle, annotations)) = self.find_tool(tool).await else {
+                return Ok(with_citations(reply, citations));
+            };
+            if iterations >= self.max_tool_iterations {
+                tracing::warn!("Stopping tool loop after {} iterations", iterations);
+                return Err(AgentError::ToolLoopLimit(iterations));
+            }
+            iterations += 1;
+
+            let required = Permission::ToolAccess(tool.clone());
+            if caller.restrict_tools && !required.granted_by(caller.permissions) {
+                tracing::warn!("{} may not call tool {}", caller.user_id, tool);
+                return Err(AgentError::Forbidden(required));
+            }
+
+            let mut parameters = parameters.clone();
+            if let Some(reason) =
+                self.approval_policy
+                    .requires_approval(tool, annotations.as_ref(), caller.permissions)
+            {
+                let pending = PendingApproval {
+                    task_id: task_id.to_string(),
+                    session_id: session.id.clone(),
+                    owner: caller.owner.map(str::to_string),
+                    tool: tool.clone(),
+                    arguments: parameters,
+                    reason,
+                    created_at: chrono::Utc::now(),
+                };
+                caller.worker.release();
+                parameters = self.await_approval(session, pending, cancel).await?;
+                tokio::select! {
+                    _ = caller.worker.reacquire() => {}
+                    _ = cancel.cancelled() => return Err(canceled()),
+                }
+            }
+
+            session
+                .state_machine
+                .write()
+                .await
+                .transition(AgentState::WaitingForTool);
+            let arguments = parameters.clone();
+            let (source, result) = match handle {
+                ToolHandle::Mcp(client) => match client
+                    .call_tool_with_cancel(tool, parameters, cancel)
+                    .await
+                {
+                    Err(MCPError::Cancelled(_)) => return Err(canceled()),
+                    result => ("mcp", result.map_err(|e| e.to_string())),
+                },
+                ToolHandle::Local(local) => tokio::select! {
+                    result = ToolContext {
+                        user_id: Some(caller.user_id.to_string()),
+                        session_id: Some(session.id.clone()),
+                    }
+                    .scope(local.execute(parameters)) => ("local", result.map_err(|e| e.to_string())),
+                    _ = cancel.cancelled() => return Err(canceled()),
+                },
+            };
+            self.audit_tool_call(caller.user_id, tool, source, &arguments, &result, scope.clone())
+                .await;
+            let result = result.unwrap_or_else(|e| serde_json::json!({ "error": e }));
+            if let Some(serde_json::Value::Array(found)) =
+                result.get(knowledge::CITATIONS_METADATA_KEY)
+            {
+                citations.extend(found.iter().cloned());
+            }
+            session
+                .state_machine
+                .write()
+                .await
+                .transition(AgentState::Processing);
+
+            let tool_result = Message::new(
+                tool.clone(),
+                self.config.name.clone(),
+                MessageContent::ToolResult {
+                    tool: tool.clone(),
+                    result: result.clone(),
+                },
+                None,
+            );
+            session.state_machine.write().await.add_message(tool_result.clone());
+            self.sessions.record_message(session, &tool_result).await;
+
+            history.push(Message::new(
+                "user".to_string(),
+                self.config.name.clone(),
+                MessageContent::Text { text: input },
+                None,
+            ));
+            input = format!("Tool {tool} returned: {result}");
+        }
+    }
+
+    /// Records an LLM call in the audit trail, see [`AuditTrail::llm_call`].
+    pub(crate) async fn audit_llm_call(
+        &self,
+        user_id: &str,
+        config: &LLMConfig,
+        input: &str,
+        context: &[Message],
+        reply: &Result<Message, String>,
+        scope: serde_json::Value,
+    ) {
+        self.audit
+            .llm_call(user_id, config, input, context, reply, scope)
+            .await;
+    }
+
+    /// Records a tool call in the audit trail, see [`AuditTrail::tool_call`].
+    pub(crate) async fn audit_tool_call(
+        &self,
+        user_id: &str,
+        tool: &str,
+        source: &str,
+        arguments: &serde_json::Value,
+        result: &Result<serde_json::Value, String>,
+        scope: serde_json::Value,
+    ) {
+        self.audit
+            .tool_call(user_id, tool, source, arguments, result, scope)
+            .await;
+    }
+
+    /// Records a delegation to the A2A peer `peer` in the audit trail, see
+    /// [`AuditTrail::delegation`].
+    pub(crate) async fn audit_delegation<T: serde::Serialize, E: std::fmt::Display>(
+        &self,
+        user_id: &str,
+        peer: &str,
+        request: &serde_json::Value,
+        reply: &Result<T, E>,
+        scope: serde_json::Value,
+    ) {
+        self.audit
+            .delegation(user_id, peer, request, reply, scope)
+            .await;
+    }
+
+    /// Pauses the task until `pending` is approved or denied and returns the
+    /// arguments to call the tool with.
+    async fn await_approval(
+        &self,
+        session: &Session,
+        pending: PendingApproval,
+        cancel: &CancellationToken,
+    ) -> Result<serde_json::Value, AgentError> {
+        let task_id = pending.task_id.clone();
+        let canceled = || AgentError::Canceled(task_id.clone());
+        let tool = pending.tool.clone();
+        let arguments = pending.arguments.clone();
+        let decision = self
+            .approvals
+            .request(pending)
+            .await
+            .map_err(|e| AgentError::Processing(e.to_string()))?;
+
+        session
+            .state_machine
+            .write()
+            .await
+            .transition(AgentState::InputRequired);
+        self.tasks.set_status(&task_id, TaskStatus::InputRequired).await;
+
+        let decision = tokio::select! {
+            decision = decision => decision.map_err(|_| canceled())?,
+            _ = cancel.cancelled() => {
+                self.approvals.withdraw(&task_id).await;
+                return Err(canceled());
+            }
+        };
+        self.tasks.set_status(&task_id, TaskStatus::Working).await;
+
+        match decision {
+            ApprovalDecision::Approve { arguments: edited } => Ok(edited.unwrap_or(arguments)),
+            ApprovalDecision::Deny { reason } => Err(AgentError::ApprovalDenied {
+                tool,
+                reason: reason.unwrap_or_else(|| "denied by reviewer".to_string()),
+            }),
+        }
+    }
+
+    /// Finds `tool` among the tools of MCP servers, then among the local
+    /// tools, along with the tool's annotations.
+    async fn find_tool(&self, tool: &str) -> Option<(ToolHandle, Option<ToolAnnotations>)> {
+        if let Some((client, annotations)) = self.mcp_client_for_tool(tool).await {
+            return Some((ToolHandle::Mcp(client), annotations));
+        }
+        let local = self.tools.get_tool(tool).await?;
+        let annotations = local.annotations();
+        Some((ToolHandle::Local(local), annotations))
+    }
+
+    /// Finds the MCP client whose manifest lists `tool`, along with the
+    /// tool's annotations.
+    pub(crate) async fn mcp_client_for_tool(
+        &self,
+        tool: &str,
+    ) -> Option<(MCPClient, Option<ToolAnnotations>)> {
+        let manifests = self.manifests.read().await;
+        manifests.iter().find_map(|(name, manifest)| match manifest {
+            Manifest::MCP(m) => {
+                let entry = m.tools.iter().find(|t| t.name == tool)?;
+                let client = self.mcp_clients.get(name)?.clone();
+                Some((client, entry.annotations.clone()))
+            }
+            _ => None,
+        })
+    }
+
     pub async fn add_mcp_client(&mut self, name: String, client: MCPClient) -> Result<(), String> {
         self.mcp_clients.insert(name, client);
         Ok(())
@@ -99,49 +765,100 @@ impl Agent {
         Ok(())
     }
 
+    /// Fetches the manifest of every MCP server and A2A peer and registers
+    /// their capabilities. Fails on the first peer that can't be reached.
     pub async fn fetch_manifests(&self) -> Result<(), String> {
-        // Fetch MCP manifests
+        match self.refresh_capabilities().await.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Re-fetches every manifest and syncs the capabilities it lists. A peer
+    /// that can't be reached keeps its last known capabilities, marked
+    /// unhealthy; the failures are returned as `(peer name, error)` pairs.
+    pub async fn refresh_capabilities(&self) -> Vec<(String, String)> {
+        let mut failures = Vec::new();
+
         for (name, client) in &self.mcp_clients {
-            let manifest = client
-                .fetch_manifest()
-                .await
-                .map_err(|e| format!("Failed to fetch MCP manifest for {name}: {e}"))?;
-            self.manifests
-                .write()
-                .await
-                .insert(name.clone(), Manifest::MCP(manifest));
+            let provenance = Provenance::Mcp {
+                server: name.clone(),
+            };
+            match client.fetch_manifest().await {
+                Ok(manifest) => {
+                    self.capabilities
+                        .sync(&provenance, Capability::from_mcp_manifest(name, &manifest))
+                        .await;
+                    self.manifests
+                        .write()
+                        .await
+                        .insert(name.clone(), Manifest::MCP(manifest));
+                }
+                Err(e) => {
+                    let error = format!("Failed to fetch MCP manifest for {name}: {e}");
+                    self.capabilities.mark_unhealthy(&provenance, &error).await;
+                    failures.push((name.clone(), error));
+                }
+            }
         }
 
-        // Fetch A2A manifests
         for (name, client) in &self.a2a_clients {
-            let manifest = client
-                .fetch_manifest()
-                .await
-                .map_err(|e| format!("Failed to fetch A2A manifest for {name}: {e}"))?;
-            self.manifests
-                .write()
-                .await
-                .insert(name.clone(), Manifest::A2A(manifest));
+            let provenance = Provenance::A2a {
+                agent: name.clone(),
+            };
+            match client.fetch_manifest().await {
+                Ok(manifest) => {
+                    // The card only adds skills and modes, so peers without one still work
+                    let card = match client.fetch_agent_card().await {
+                        Ok(card) => Some(card),
+                        Err(e) => {
+                            tracing::debug!("No agent card for A2A peer {}: {}", name, e);
+                            None
+                        }
+                    };
+                    self.capabilities
+                        .sync(
+                            &provenance,
+                            Capability::from_a2a_agent(name, &manifest, card.as_ref()),
+                        )
+                        .await;
+                    self.manifests
+                        .write()
+                        .await
+                        .insert(name.clone(), Manifest::A2A(manifest));
+                }
+                Err(e) => {
+                    let error = format!("Failed to fetch A2A manifest for {name}: {e}");
+                    self.capabilities.mark_unhealthy(&provenance, &error).await;
+                    failures.push((name.clone(), error));
+                }
+            }
         }
 
-        Ok(())
+        failures
     }
 
-    pub async fn get_capabilities(&self) -> Vec<String> {
-        let manifests = self.manifests.read().await;
-        manifests
-            .values()
-            .flat_map(|manifest| match manifest {
-                Manifest::MCP(m) => m.capabilities.clone(),
-                Manifest::A2A(m) => m.capabilities.clone(),
-            })
-            .collect()
+    /// Registers the tools of `engine` as capabilities, replacing the local
+    /// tools registered before. Returns the number of tools.
+    pub async fn register_tools(&self, engine: &ToolExecutionEngine) -> usize {
+        let tools = engine
+            .list_tools()
+            .await
+            .iter()
+            .map(|tool| Capability::from_tool(tool.as_ref()))
+            .collect();
+        self.capabilities.sync(&Provenance::LocalTool, tools).await
+    }
+
+    /// All registered capabilities, sorted by id.
+    pub async fn get_capabilities(&self) -> Vec<Capability> {
+        self.capabilities.get_all_capabilities().await
     }
 
     pub async fn get_agent_card(&self, base_url: String) -> AgentCard {
-        let capabilities = self.get_capabilities().await;
-        
-        // Convert capabilities into agent skills, or provide default skills if empty
+        let capabilities = self.capabilities.available().await;
+
+        // Advertise the available capabilities as skills, or default skills if there are none
         let skills = if capabilities.is_empty() {
             vec![
                 AgentSkill {
@@ -165,15 +882,25 @@ impl Agent {
             ]
         } else {
             capabilities
-                .iter()
+                .into_iter()
                 .map(|capability| AgentSkill {
-                    id: capability.to_lowercase().replace(' ', "_"),
-                    name: capability.clone(),
-                    description: format!("Agent capability: {}", capability),
-                    tags: vec![capability.to_lowercase()],
+                    tags: if capability.tags.is_empty() {
+                        vec![capability.category.clone()]
+                    } else {
+                        capability.tags
+                    },
+                    description: if capability.description.is_empty() {
+                        capability.name.clone()
+                    } else {
+                        capability.description
+                    },
+                    id: capability.id,
+                    name: capability.name,
                     examples: None,
-                    input_modes: None,
-                    output_modes: None,
+                    input_modes: (!capability.input_modes.is_empty())
+                        .then_some(capability.input_modes),
+                    output_modes: (!capability.output_modes.is_empty())
+                        .then_some(capability.output_modes),
                 })
                 .collect()
         };
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{oneshot, RwLock};
+
+/// A sensitive tool call waiting for a human decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub task_id: String,
+    pub session_id: String,
+    /// Owner of the task, see [`super::TaskInfo::owner`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub tool: String,
+    pub arguments: Value,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Decision on a pending tool call. An approval may replace the arguments
+/// the LLM proposed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApprovalDecision {
+    Approve { arguments: Option<Value> },
+    Deny { reason: Option<String> },
+}
+
+/// Body of the approve/deny endpoints.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApprovalRequest {
+    #[serde(default)]
+    pub arguments: Option<Value>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ApprovalError {
+    #[error("no approval pending for task {0}")]
+    NotFound(String),
+    #[error("task {0} already waits for an approval")]
+    AlreadyPending(String),
+}
+
+#[derive(Debug)]
+struct ApprovalEntry {
+    pending: PendingApproval,
+    decision: oneshot::Sender<ApprovalDecision>,
+}
+
+/// Tool calls paused until someone approves or denies them, keyed by task
+/// id. A task waits for at most one approval at a time.
+#[derive(Debug, Default)]
+pub struct ApprovalRegistry {
+    pending: RwLock<HashMap<String, ApprovalEntry>>,
+}
+
+impl ApprovalRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a pending call and returns the receiver its decision will
+    /// arrive on.
+    pub async fn request(
+        &self,
+        pending: PendingApproval,
+    ) -> Result<oneshot::Receiver<ApprovalDecision>, ApprovalError> {
+        let mut entries = self.pending.write().await;
+        if entries.contains_key(&pending.task_id) {
+            return Err(ApprovalError::AlreadyPending(pending.task_id));
+        }
+
+        let (decision, receiver) = oneshot::channel();
+        tracing::info!(
+            "Task {} waits for approval of tool {}",
+            pending.task_id,
+            pending.tool
+        );
+        entries.insert(pending.task_id.clone(), ApprovalEntry { pending, decision });
+        Ok(receiver)
+    }
+
+    /// Delivers a decision to the waiting task.
+    pub async fn resolve(
+        &self,
+        task_id: &str,
+        decision: ApprovalDecision,
+    ) -> Result<PendingApproval, ApprovalError> {
+        let entry = self
+            .pending
+            .write()
+            .await
+            .remove(task_id)
+            .ok_or_else(|| ApprovalError::NotFound(task_id.to_string()))?;
+        tracing::info!(
+            "Tool {} of task {}: {:?}",
+            entry.pending.tool,
+            task_id,
+            decision
+        );
+        // The task may have been canceled in the meantime; nothing to do then
+        let _ = entry.decision.send(decision);
+        Ok(entry.pending)
+    }
+
+    /// Drops the pending call of a task that stopped waiting.
+    pub async fn withdraw(&self, task_id: &str) {
+        self.pending.write().await.remove(task_id);
+    }
+
+    pub async fn get(&self, task_id: &str) -> Option<PendingApproval> {
+        self.pending
+            .read()
+            .await
+            .get(task_id)
+            .map(|entry| entry.pending.clone())
+    }
+
+    pub async fn list(&self) -> Vec<PendingApproval> {
+        let mut pending: Vec<PendingApproval> = self
+            .pending
+            .read()
+            .await
+            .values()
+            .map(|entry| entry.pending.clone())
+            .collect();
+        pending.sort_by_key(|entry| entry.created_at);
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pending(task_id: &str) -> PendingApproval {
+        PendingApproval {
+            task_id: task_id.to_string(),
+            session_id: "s1".to_string(),
+            owner: None,
+            tool: "delete_file".to_string(),
+            arguments: json!({"path": "/tmp/a"}),
+            reason: "test".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_delivers_decision_once() {
+        let registry = ApprovalRegistry::new();
+        let receiver = registry.request(pending("t1")).await.unwrap();
+        assert_eq!(
+            registry.request(pending("t1")).await.unwrap_err(),
+            ApprovalError::AlreadyPending("t1".to_string())
+        );
+        assert_eq!(registry.list().await.len(), 1);
+
+        let decision = ApprovalDecision::Approve {
+            arguments: Some(json!({"path": "/tmp/b"})),
+        };
+        registry.resolve("t1", decision.clone()).await.unwrap();
+        assert_eq!(receiver.await.unwrap(), decision);
+
+        assert!(registry.get("t1").await.is_none());
+        assert_eq!(
+            registry
+                .resolve("t1", ApprovalDecision::Deny { reason: None })
+                .await
+                .unwrap_err(),
+            ApprovalError::NotFound("t1".to_string())
+        );
+    }
+}
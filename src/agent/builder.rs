@@ -1,12 +1,43 @@
 use crate::a2a::client::A2AClient;
-use crate::agent::{Agent, AgentConfig};
+use crate::agent::limiter::{ConcurrencyLimiter, LimiterConfig};
+use crate::agent::session::{SessionConfig, SessionManager};
+use crate::agent::{Agent, AgentConfig, DEFAULT_MAX_TOOL_ITERATIONS, DEFAULT_REQUEST_TIMEOUT};
+use crate::core::state::{
+    ForgetTool, ListMemoriesTool, MemoryConfig, RecallTool, RememberTool, TieredMemory, UserMemory,
+    UserMemoryConfig,
+};
+use crate::integrations::database::DataRepository;
 use crate::mcp::client::MCPClient;
+use crate::services::audit::AuditTrail;
+use crate::services::embeddings::EmbeddingProvider;
+use crate::services::knowledge::{KbIngestTool, KbSearchTool, KnowledgeBase, KnowledgeConfig};
+use crate::services::memory::{CompressionConfig, MemoryService};
+use crate::services::tools::{ApprovalPolicy, Tool, ToolExecutionEngine};
+use crate::services::vector_store::VectorStore;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub struct AgentBuilder {
     config: AgentConfig,
     mcp_endpoints: HashMap<String, String>,
-    a2a_endpoints: HashMap<String, String>,
+    a2a_endpoints: HashMap<String, A2AClient>,
+    session_config: SessionConfig,
+    session_store: Option<Arc<dyn DataRepository>>,
+    limiter_config: LimiterConfig,
+    request_timeout: Duration,
+    max_tool_iterations: usize,
+    approval_policy: ApprovalPolicy,
+    tools: Option<Arc<ToolExecutionEngine>>,
+    compression: CompressionConfig,
+    embeddings: Option<Arc<dyn EmbeddingProvider>>,
+    vectors: Option<Arc<dyn VectorStore>>,
+    knowledge: Option<KnowledgeConfig>,
+    user_memory: Option<UserMemoryConfig>,
+    memory_store: Option<Arc<dyn DataRepository>>,
+    conversation_memory: Option<MemoryConfig>,
+    conversation_memory_store: Option<Arc<dyn DataRepository>>,
+    audit: Option<Arc<AuditTrail>>,
 }
 
 impl AgentBuilder {
@@ -19,6 +50,22 @@ impl AgentBuilder {
             },
             mcp_endpoints: HashMap::new(),
             a2a_endpoints: HashMap::new(),
+            session_config: SessionConfig::default(),
+            session_store: None,
+            limiter_config: LimiterConfig::default(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            max_tool_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            approval_policy: ApprovalPolicy::default(),
+            tools: None,
+            compression: CompressionConfig::default(),
+            embeddings: None,
+            vectors: None,
+            knowledge: None,
+            user_memory: None,
+            memory_store: None,
+            conversation_memory: None,
+            conversation_memory_store: None,
+            audit: None,
         }
     }
 
@@ -33,12 +80,140 @@ impl AgentBuilder {
     }
 
     pub fn add_a2a(mut self, name: &str, url: &str) -> Self {
-        self.a2a_endpoints.insert(name.to_string(), url.to_string());
+        self.a2a_endpoints
+            .insert(name.to_string(), A2AClient::new(url.to_string()));
+        self
+    }
+
+    /// Adds an A2A peer that requires authentication; `token` is sent as a
+    /// bearer token with every request to it.
+    pub fn add_a2a_with_auth(mut self, name: &str, url: &str, token: &str) -> Self {
+        self.a2a_endpoints.insert(
+            name.to_string(),
+            A2AClient::new(url.to_string()).with_auth_token(token),
+        );
+        self
+    }
+
+    pub fn session_config(mut self, config: SessionConfig) -> Self {
+        self.session_config = config;
+        self
+    }
+
+    /// Persist session history through `store` so conversations survive restarts.
+    pub fn session_store(mut self, store: Arc<dyn DataRepository>) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+
+    /// Bounds concurrent LLM calls and the queue of requests waiting for one.
+    pub fn concurrency(mut self, config: LimiterConfig) -> Self {
+        self.limiter_config = config;
+        self
+    }
+
+    /// Deadline of a turn unless the request sets its own.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Maximum tool calls per turn before the loop is stopped.
+    pub fn max_tool_iterations(mut self, iterations: usize) -> Self {
+        self.max_tool_iterations = iterations;
+        self
+    }
+
+    /// Decides which tool calls wait for human approval.
+    pub fn approval_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval_policy = policy;
+        self
+    }
+
+    /// When and how far a session's context is compressed.
+    pub fn context_compression(mut self, config: CompressionConfig) -> Self {
+        self.compression = config;
+        self
+    }
+
+    /// Embedding provider used for retrieval; defaults to the local hashing embedder.
+    pub fn embeddings(mut self, provider: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embeddings = Some(provider);
+        self
+    }
+
+    /// Vector store used for retrieval; defaults to an in-memory store.
+    pub fn vector_store(mut self, store: Arc<dyn VectorStore>) -> Self {
+        self.vectors = Some(store);
+        self
+    }
+
+    /// Adds a knowledge base that ingests `config.sources` on build and
+    /// registers the `kb_search` and `kb_ingest` tools.
+    pub fn knowledge_base(mut self, config: KnowledgeConfig) -> Self {
+        self.knowledge = Some(config);
+        self
+    }
+
+    /// Adds long-term memory of facts about users and registers the
+    /// `remember`, `recall`, `forget` and `list_memories` tools.
+    pub fn user_memory(mut self, config: UserMemoryConfig) -> Self {
+        self.user_memory = Some(config);
+        self
+    }
+
+    /// Persist user memories through `store` so they survive restarts.
+    pub fn memory_store(mut self, store: Arc<dyn DataRepository>) -> Self {
+        self.memory_store = Some(store);
+        self
+    }
+
+    /// Summarizes messages that leave a session's context into memories of
+    /// the user and session, recalled in the session's later turns.
+    pub fn conversation_memory(mut self, config: MemoryConfig) -> Self {
+        self.conversation_memory = Some(config);
+        self
+    }
+
+    /// Persist the long-term tier of conversation memory through `store`.
+    pub fn conversation_memory_store(mut self, store: Arc<dyn DataRepository>) -> Self {
+        self.conversation_memory_store = Some(store);
+        self
+    }
+
+    /// Local tools the LLM may call. They are also registered as
+    /// capabilities of the agent.
+    pub fn tools(mut self, engine: Arc<ToolExecutionEngine>) -> Self {
+        self.tools = Some(engine);
+        self
+    }
+
+    /// Record LLM calls, tool calls and A2A delegations in `trail`.
+    pub fn audit(mut self, trail: Arc<AuditTrail>) -> Self {
+        self.audit = Some(trail);
         self
     }
 
     pub async fn build(self) -> Result<Agent, String> {
         let mut agent = Agent::new(self.config);
+        agent.sessions = Arc::new(match self.session_store {
+            Some(store) => SessionManager::with_store(self.session_config, store),
+            None => SessionManager::new(self.session_config),
+        });
+        agent.limiter = Arc::new(ConcurrencyLimiter::new(self.limiter_config));
+        agent.request_timeout = self.request_timeout;
+        agent.max_tool_iterations = self.max_tool_iterations;
+        agent.approval_policy = self.approval_policy;
+        agent.memory = Arc::new(MemoryService::new().with_config(self.compression));
+        if let Some(embeddings) = self.embeddings {
+            agent.embeddings = embeddings;
+        }
+        if let Some(vectors) = self.vectors {
+            agent.vectors = vectors;
+        }
+        if let Some(audit) = self.audit {
+            agent.audit = audit;
+        }
 
         // Add MCP clients
         for (name, url) in self.mcp_endpoints {
@@ -47,13 +222,65 @@ impl AgentBuilder {
         }
 
         // Add A2A clients
-        for (name, url) in self.a2a_endpoints {
-            let client = A2AClient::new(url);
+        for (name, client) in self.a2a_endpoints {
             agent.add_a2a_client(name, client).await?;
         }
 
-        // Fetch all manifests
+        let engine = self.tools.unwrap_or_default();
+        if let Some(config) = self.knowledge {
+            let knowledge = Arc::new(KnowledgeBase::new(
+                config,
+                agent.embeddings.clone(),
+                agent.vectors.clone(),
+            ));
+            let (ingested, failures) = knowledge.ingest_sources().await;
+            for (source, e) in failures {
+                tracing::warn!("Failed to ingest knowledge source {}: {}", source, e);
+            }
+            tracing::info!("Knowledge base ingested {} documents", ingested);
+            engine
+                .register_tool(Arc::new(KbSearchTool::new(knowledge.clone())))
+                .await
+                .map_err(|e| e.to_string())?;
+            engine
+                .register_tool(Arc::new(KbIngestTool::new(knowledge.clone())))
+                .await
+                .map_err(|e| e.to_string())?;
+            agent.knowledge = Some(knowledge);
+        }
+        if let Some(config) = self.user_memory {
+            let memory = Arc::new(UserMemory::new(config, agent.embeddings.clone()));
+            if let Some(store) = self.memory_store {
+                let loaded = memory.use_store(store).await.map_err(|e| e.to_string())?;
+                tracing::info!("Loaded {} user memories", loaded);
+            }
+            let agent_id = agent.config.name.clone();
+            let tools: [Arc<dyn Tool>; 4] = [
+                Arc::new(RememberTool::new(memory.clone(), &agent_id)),
+                Arc::new(RecallTool::new(memory.clone(), &agent_id)),
+                Arc::new(ForgetTool::new(memory.clone(), &agent_id)),
+                Arc::new(ListMemoriesTool::new(memory.clone(), &agent_id)),
+            ];
+            for tool in tools {
+                engine.register_tool(tool).await.map_err(|e| e.to_string())?;
+            }
+            agent.user_memory = Some(memory);
+        }
+        if let Some(config) = self.conversation_memory {
+            let memory = TieredMemory::new(config)
+                .with_embedder(agent.embeddings.clone())
+                .with_summarizer(agent.memory.clone());
+            if let Some(store) = self.conversation_memory_store {
+                let loaded = memory.use_store(store).await.map_err(|e| e.to_string())?;
+                tracing::info!("Loaded {} conversation memories", loaded);
+            }
+            agent.conversation_memory = Some(Arc::new(memory));
+        }
+        agent.tools = engine;
+
+        // Fetch all manifests and register their capabilities
         agent.fetch_manifests().await?;
+        agent.register_tools(&agent.tools).await;
 
         Ok(agent)
     }
@@ -0,0 +1,240 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Clone)]
+pub struct LimiterConfig {
+    /// Maximum number of LLM calls running at the same time.
+    pub max_in_flight: usize,
+    /// Maximum number of requests waiting for a slot before new ones are rejected.
+    pub max_queue: usize,
+}
+
+impl Default for LimiterConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 16,
+            max_queue: 64,
+        }
+    }
+}
+
+impl From<&crate::config::ConcurrencySettings> for LimiterConfig {
+    fn from(settings: &crate::config::ConcurrencySettings) -> Self {
+        Self {
+            max_in_flight: settings.max_in_flight,
+            max_queue: settings.max_queue,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LimiterError {
+    #[error("too many requests: {in_flight} in flight, {queued} queued")]
+    QueueFull { in_flight: usize, queued: usize },
+}
+
+/// Snapshot of the limiter's load, exposed through the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimiterStats {
+    pub in_flight: usize,
+    pub queued: usize,
+    pub max_in_flight: usize,
+    pub max_queue: usize,
+}
+
+/// Bounded worker pool for LLM calls: at most `max_in_flight` permits are
+/// handed out, up to `max_queue` callers wait for one, and everyone else is
+/// rejected immediately so the server can answer with backpressure.
+#[derive(Debug)]
+pub struct ConcurrencyLimiter {
+    config: LimiterConfig,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl Default for ConcurrencyLimiter {
+    fn default() -> Self {
+        Self::new(LimiterConfig::default())
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(config: LimiterConfig) -> Self {
+        let config = LimiterConfig {
+            max_in_flight: config.max_in_flight.max(1),
+            max_queue: config.max_queue,
+        };
+        Self {
+            semaphore: Arc::new(Semaphore::new(config.max_in_flight)),
+            queued: AtomicUsize::new(0),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &LimiterConfig {
+        &self.config
+    }
+
+    /// Waits for a free slot, or fails right away when the queue is full.
+    /// The slot is released when the returned permit is dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, LimiterError> {
+        Ok(self.admit()?.acquire().await)
+    }
+
+    /// Takes a place in the queue without waiting, or fails right away when
+    /// every slot is busy and the queue is full. [`Admission::acquire`] then
+    /// waits for a slot.
+    pub fn admit(&self) -> Result<Admission<'_>, LimiterError> {
+        let (queued, slot) = self.enqueue();
+        if queued >= self.config.max_queue + self.semaphore.available_permits() {
+            return Err(LimiterError::QueueFull {
+                in_flight: self.in_flight(),
+                queued,
+            });
+        }
+        Ok(Admission {
+            _slot: slot,
+            semaphore: self.semaphore.clone(),
+        })
+    }
+
+    /// Counts the caller as queued until the returned slot is dropped.
+    fn enqueue(&self) -> (usize, QueueSlot<'_>) {
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        (queued, QueueSlot(&self.queued))
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.config.max_in_flight - self.semaphore.available_permits()
+    }
+
+    pub fn stats(&self) -> LimiterStats {
+        LimiterStats {
+            in_flight: self.in_flight(),
+            queued: self.queued.load(Ordering::SeqCst),
+            max_in_flight: self.config.max_in_flight,
+            max_queue: self.config.max_queue,
+        }
+    }
+}
+
+/// A caller admitted to the queue. Dropping it, or the future of
+/// [`Admission::acquire`], gives up the place.
+pub struct Admission<'a> {
+    _slot: QueueSlot<'a>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl Admission<'_> {
+    /// Waits for a free slot.
+    pub async fn acquire(self) -> OwnedSemaphorePermit {
+        // The semaphore is never closed.
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("limiter semaphore closed")
+    }
+}
+
+/// The slot of a running turn. The turn gives it back while it waits on
+/// something other than the LLM, such as a human approval, and waits for a
+/// slot again afterwards without being rejected, since it was admitted.
+pub(crate) struct WorkerSlot<'a> {
+    limiter: &'a ConcurrencyLimiter,
+    permit: std::sync::Mutex<Option<OwnedSemaphorePermit>>,
+}
+
+impl<'a> WorkerSlot<'a> {
+    pub(crate) fn new(limiter: &'a ConcurrencyLimiter, permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            limiter,
+            permit: std::sync::Mutex::new(Some(permit)),
+        }
+    }
+
+    pub(crate) fn release(&self) {
+        self.permit.lock().unwrap().take();
+    }
+
+    pub(crate) async fn reacquire(&self) {
+        if self.permit.lock().unwrap().is_some() {
+            return;
+        }
+        let (_, _slot) = self.limiter.enqueue();
+        let permit = self
+            .limiter
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("limiter semaphore closed");
+        *self.permit.lock().unwrap() = Some(permit);
+    }
+}
+
+/// A place in the wait queue, released on drop.
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejects_when_queue_is_full() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(LimiterConfig {
+            max_in_flight: 1,
+            max_queue: 1,
+        }));
+
+        let running = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.in_flight(), 1);
+
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire().await.map(|_| ()) })
+        };
+        while limiter.stats().queued == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(LimiterError::QueueFull { in_flight: 1, queued: 1 })
+        ));
+
+        drop(running);
+        waiter.await.unwrap().unwrap();
+        assert_eq!(limiter.stats().queued, 0);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_wait_leaves_queue() {
+        let limiter = ConcurrencyLimiter::new(LimiterConfig {
+            max_in_flight: 1,
+            max_queue: 1,
+        });
+        let running = limiter.acquire().await.unwrap();
+
+        let waited = tokio::time::timeout(
+            std::time::Duration::from_millis(10),
+            limiter.acquire(),
+        )
+        .await;
+        assert!(waited.is_err());
+        assert_eq!(limiter.stats().queued, 0);
+
+        drop(running);
+        assert!(limiter.acquire().await.is_ok());
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+use uuid::Uuid;
+
+use crate::agent::state::{AgentState, StateMachine};
+use crate::integrations::database::{
+    ConversationRecord, DataRepository, MessageRecord, Pagination,
+};
+use crate::llm::LLMOverrides;
+use crate::protocol::message::{Message, MessageContent};
+
+/// Namespace used to derive stable conversation ids from non-UUID session keys.
+const SESSION_NAMESPACE: Uuid = Uuid::from_u128(0x6f6d_6e69_2d61_6765_6e74_2d73_6573_7369);
+
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    pub max_context_size: usize,
+    pub idle_timeout: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            max_context_size: 100,
+            idle_timeout: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl From<&crate::config::SessionSettings> for SessionConfig {
+    fn from(settings: &crate::config::SessionSettings) -> Self {
+        Self {
+            max_context_size: settings.max_context_size,
+            idle_timeout: Duration::from_secs(settings.idle_timeout_secs),
+        }
+    }
+}
+
+/// One conversation with its own context window, state and LLM settings.
+#[derive(Debug)]
+pub struct Session {
+    pub id: String,
+    pub conversation_id: Uuid,
+    pub state_machine: RwLock<StateMachine>,
+    pub created_at: DateTime<Utc>,
+    llm_overrides: RwLock<LLMOverrides>,
+    owner: RwLock<Option<String>>,
+    participants: Mutex<Vec<String>>,
+    last_active: Mutex<Instant>,
+    turn: Mutex<()>,
+}
+
+impl Session {
+    fn new(id: String, max_context_size: usize) -> Self {
+        let conversation_id = SessionManager::conversation_id(&id);
+        Self {
+            id,
+            conversation_id,
+            state_machine: RwLock::new(StateMachine::with_session_id(
+                max_context_size,
+                conversation_id,
+            )),
+            created_at: Utc::now(),
+            llm_overrides: RwLock::new(LLMOverrides::default()),
+            owner: RwLock::new(None),
+            participants: Mutex::new(Vec::new()),
+            last_active: Mutex::new(Instant::now()),
+            turn: Mutex::new(()),
+        }
+    }
+
+    /// Serializes turns within this session so each request sees the
+    /// previous exchange. Other sessions are not affected.
+    pub async fn lock_turn(&self) -> MutexGuard<'_, ()> {
+        self.turn.lock().await
+    }
+
+    pub async fn llm_overrides(&self) -> LLMOverrides {
+        self.llm_overrides.read().await.clone()
+    }
+
+    pub async fn set_llm_overrides(&self, overrides: LLMOverrides) {
+        *self.llm_overrides.write().await = overrides;
+    }
+
+    /// The user the session belongs to, if an authenticated caller has used it.
+    pub async fn owner(&self) -> Option<String> {
+        self.owner.read().await.clone()
+    }
+
+    /// Binds the session to `user_id` if it has no owner yet. Returns whether
+    /// the session belongs to `user_id` afterwards.
+    pub async fn claim(&self, user_id: &str) -> bool {
+        let mut owner = self.owner.write().await;
+        owner.get_or_insert_with(|| user_id.to_string()) == user_id
+    }
+
+    pub async fn touch(&self) {
+        *self.last_active.lock().await = Instant::now();
+    }
+
+    pub async fn idle_for(&self) -> Duration {
+        self.last_active.lock().await.elapsed()
+    }
+
+    pub async fn info(&self) -> SessionInfo {
+        let (state, message_count) = {
+            let state_machine = self.state_machine.read().await;
+            (state_machine.get_state().clone(), state_machine.context.len())
+        };
+
+        SessionInfo {
+            id: self.id.clone(),
+            conversation_id: self.conversation_id,
+            state: format!("{state:?}"),
+            message_count,
+            created_at: self.created_at,
+            idle_secs: self.idle_for().await.as_secs(),
+            llm_overrides: self.llm_overrides().await,
+            owner: self.owner().await,
+        }
+    }
+}
+
+/// Serializable summary of a session, used by the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub conversation_id: Uuid,
+    pub state: String,
+    pub message_count: usize,
+    pub created_at: DateTime<Utc>,
+    pub idle_secs: u64,
+    pub llm_overrides: LLMOverrides,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// Keeps one [`Session`] per conversation/context id, evicts idle ones and
+/// optionally persists their messages through a [`DataRepository`].
+pub struct SessionManager {
+    config: SessionConfig,
+    sessions: RwLock<HashMap<String, Arc<Session>>>,
+    store: Option<Arc<dyn DataRepository>>,
+}
+
+impl std::fmt::Debug for SessionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionManager")
+            .field("config", &self.config)
+            .field("persistent", &self.store.is_some())
+            .finish()
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new(SessionConfig::default())
+    }
+}
+
+impl SessionManager {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            sessions: RwLock::new(HashMap::new()),
+            store: None,
+        }
+    }
+
+    pub fn with_store(config: SessionConfig, store: Arc<dyn DataRepository>) -> Self {
+        Self {
+            store: Some(store),
+            ..Self::new(config)
+        }
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.store.is_some()
+    }
+
+    /// Maps a session key to the conversation id used for persistence.
+    /// UUID keys are used as-is, anything else gets a stable v5 UUID.
+    pub fn conversation_id(session_id: &str) -> Uuid {
+        Uuid::parse_str(session_id)
+            .unwrap_or_else(|_| Uuid::new_v5(&SESSION_NAMESPACE, session_id.as_bytes()))
+    }
+
+    pub async fn get(&self, session_id: &str) -> Option<Arc<Session>> {
+        self.sessions.read().await.get(session_id).cloned()
+    }
+
+    /// Returns the session for `session_id`, creating it (and restoring its
+    /// history and owner from the store, if any) on first use.
+    pub async fn get_or_create(&self, session_id: &str) -> Arc<Session> {
+        if let Some(session) = self.get(session_id).await {
+            session.touch().await;
+            return session;
+        }
+
+        // Build outside the map lock so a slow store does not block other sessions.
+        let session = Arc::new(Session::new(
+            session_id.to_string(),
+            self.config.max_context_size,
+        ));
+        self.restore_history(&session).await;
+
+        let mut sessions = self.sessions.write().await;
+        sessions
+            .entry(session_id.to_string())
+            .or_insert(session)
+            .clone()
+    }
+
+    pub async fn remove(&self, session_id: &str) -> bool {
+        self.sessions.write().await.remove(session_id).is_some()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.sessions.read().await.is_empty()
+    }
+
+    pub async fn list(&self) -> Vec<SessionInfo> {
+        let sessions: Vec<Arc<Session>> = self.sessions.read().await.values().cloned().collect();
+        let mut infos = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            infos.push(session.info().await);
+        }
+        infos.sort_by(|a, b| a.id.cmp(&b.id));
+        infos
+    }
+
+    pub async fn set_llm_overrides(&self, session_id: &str, overrides: LLMOverrides) -> Arc<Session> {
+        let session = self.get_or_create(session_id).await;
+        session.set_llm_overrides(overrides).await;
+        session
+    }
+
+    /// Drops sessions idle longer than the configured timeout. Sessions that
+    /// are mid-request, i.e. whose turn lock is held, are kept; a session left
+    /// in an error state by its last turn is evicted like any other. Returns
+    /// the evicted ids.
+    pub async fn evict_idle(&self) -> Vec<String> {
+        let candidates: Vec<Arc<Session>> = self.sessions.read().await.values().cloned().collect();
+
+        let mut expired = Vec::new();
+        for session in candidates {
+            if session.idle_for().await < self.config.idle_timeout {
+                continue;
+            }
+            if session.turn.try_lock().is_ok() {
+                expired.push(session.id.clone());
+            }
+        }
+
+        if !expired.is_empty() {
+            let mut sessions = self.sessions.write().await;
+            for id in &expired {
+                sessions.remove(id);
+            }
+            tracing::info!("Evicted {} idle session(s)", expired.len());
+        }
+
+        expired
+    }
+
+    pub fn spawn_eviction_task(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match manager.upgrade() {
+                    Some(manager) => {
+                        manager.evict_idle().await;
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
+
+    /// Persists a message of `session`. Storage failures are logged and do
+    /// not fail the conversation.
+    pub async fn record_message(&self, session: &Session, message: &Message) {
+        let Some(store) = &self.store else {
+            return;
+        };
+
+        let participants = {
+            let mut participants = session.participants.lock().await;
+            for name in [&message.sender, &message.recipient] {
+                if !participants.contains(name) {
+                    participants.push(name.clone());
+                }
+            }
+            participants.clone()
+        };
+
+        let conversation = ConversationRecord {
+            id: session.conversation_id,
+            title: session.id.clone(),
+            participants,
+            owner: session.owner().await,
+            created_at: session.created_at,
+            updated_at: Utc::now(),
+        };
+        if let Err(e) = store.save_conversation(&conversation).await {
+            tracing::warn!("Failed to persist session {}: {}", session.id, e);
+            return;
+        }
+
+        if let Err(e) = store
+            .save_message(&message_to_record(session.conversation_id, message))
+            .await
+        {
+            tracing::warn!("Failed to persist message for session {}: {}", session.id, e);
+        }
+    }
+
+    async fn restore_history(&self, session: &Session) {
+        let Some(store) = &self.store else {
+            return;
+        };
+
+        let conversation = match store.get_conversation_by_id(&session.conversation_id).await {
+            Ok(Some(conversation)) => conversation,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("Failed to load session {}: {}", session.id, e);
+                return;
+            }
+        };
+
+        let limit = self.config.max_context_size.min(Pagination::MAX_LIMIT as usize) as u32;
+        let total = match store
+            .list_messages(&session.conversation_id, Pagination::new(0, 1))
+            .await
+        {
+            Ok(page) => page.total,
+            Err(e) => {
+                tracing::warn!("Failed to load history of session {}: {}", session.id, e);
+                return;
+            }
+        };
+        let offset = total.saturating_sub(limit as u64) as u32;
+
+        match store
+            .list_messages(&session.conversation_id, Pagination::new(offset, limit))
+            .await
+        {
+            Ok(page) => {
+                let mut state_machine = session.state_machine.write().await;
+                for record in page.items {
+                    if let Some(message) = record_to_message(record) {
+                        state_machine.add_message(message);
+                    }
+                }
+                state_machine.transition(AgentState::Idle);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load history of session {}: {}", session.id, e);
+            }
+        }
+
+        *session.participants.lock().await = conversation.participants;
+        *session.owner.write().await = conversation.owner;
+    }
+}
+
+fn message_to_record(conversation_id: Uuid, message: &Message) -> MessageRecord {
+    let content = serde_json::to_value(&message.content).unwrap_or(serde_json::Value::Null);
+    let message_type = content
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("Unknown")
+        .to_string();
+
+    MessageRecord {
+        id: message.id,
+        conversation_id,
+        sender: message.sender.clone(),
+        recipient: message.recipient.clone(),
+        content,
+        message_type,
+        timestamp: message.timestamp,
+        metadata: message.metadata.clone(),
+    }
+}
+
+fn record_to_message(record: MessageRecord) -> Option<Message> {
+    let content: MessageContent = serde_json::from_value(record.content).ok()?;
+    Some(Message {
+        id: record.id,
+        sender: record.sender,
+        recipient: record.recipient,
+        content,
+        timestamp: record.timestamp,
+        metadata: record.metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrations::database::SqliteRepository;
+
+    fn text(sender: &str, text: &str) -> Message {
+        Message::new(
+            sender.to_string(),
+            "agent".to_string(),
+            MessageContent::Text {
+                text: text.to_string(),
+            },
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_isolated() {
+        let manager = SessionManager::default();
+        let a = manager.get_or_create("alice").await;
+        let b = manager.get_or_create("bob").await;
+
+        a.state_machine.write().await.add_message(text("alice", "hi"));
+
+        assert_eq!(a.state_machine.read().await.context.len(), 1);
+        assert!(b.state_machine.read().await.context.is_empty());
+        assert_ne!(a.conversation_id, b.conversation_id);
+        assert_eq!(
+            a.state_machine.read().await.get_session_id(),
+            SessionManager::conversation_id("alice")
+        );
+        assert_eq!(manager.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_are_evicted() {
+        let manager = SessionManager::new(SessionConfig {
+            max_context_size: 10,
+            idle_timeout: Duration::from_millis(20),
+        });
+        manager.get_or_create("old").await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        manager.get_or_create("fresh").await;
+
+        let evicted = manager.evict_idle().await;
+        assert_eq!(evicted, vec!["old".to_string()]);
+        assert!(manager.get("old").await.is_none());
+        assert!(manager.get("fresh").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_sessions_are_evicted_but_running_turns_are_kept() {
+        let manager = SessionManager::new(SessionConfig {
+            max_context_size: 10,
+            idle_timeout: Duration::from_millis(20),
+        });
+        let failed = manager.get_or_create("failed").await;
+        failed
+            .state_machine
+            .write()
+            .await
+            .transition(AgentState::Error("LLM unavailable".to_string()));
+        let running = manager.get_or_create("running").await;
+        let _turn = running.lock_turn().await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        let evicted = manager.evict_idle().await;
+        assert_eq!(evicted, vec!["failed".to_string()]);
+        assert!(manager.get("running").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_history_is_restored_from_store() {
+        let store = Arc::new(SqliteRepository::connect("sqlite::memory:").await.unwrap());
+        let config = SessionConfig::default();
+
+        let manager = SessionManager::with_store(config.clone(), store.clone());
+        let session = manager.get_or_create("persisted").await;
+        assert!(session.claim("carol").await);
+        for i in 0..3 {
+            let message = text("carol", &format!("message {i}"));
+            session.state_machine.write().await.add_message(message.clone());
+            manager.record_message(&session, &message).await;
+        }
+
+        // A fresh manager simulates a process restart.
+        let restarted = SessionManager::with_store(config, store);
+        let restored = restarted.get_or_create("persisted").await;
+        let context = restored.state_machine.read().await.get_context();
+        assert_eq!(context.len(), 3);
+        assert!(matches!(
+            &context[2].content,
+            MessageContent::Text { text } if text == "message 2"
+        ));
+
+        // The owner survives the restart, so another user cannot take over.
+        assert_eq!(restored.owner().await.as_deref(), Some("carol"));
+        assert!(!restored.claim("mallory").await);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use uuid::Uuid;
 
 use crate::protocol::message::Message;
@@ -9,6 +9,8 @@ pub enum AgentState {
     Processing,
     WaitingForTool,
     WaitingForAgent,
+    /// A sensitive tool call waits for human approval.
+    InputRequired,
     Error(String),
 }
 
@@ -17,14 +19,23 @@ pub struct StateMachine {
     pub state: AgentState,
     pub context: VecDeque<Message>,
     pub max_context_size: usize,
+    session_id: Uuid,
+    /// Messages that left the context since the last `take_evicted`.
+    evicted: Vec<Message>,
 }
 
 impl StateMachine {
     pub fn new(max_context_size: usize) -> Self {
+        Self::with_session_id(max_context_size, Uuid::new_v4())
+    }
+
+    pub fn with_session_id(max_context_size: usize, session_id: Uuid) -> Self {
         Self {
             state: AgentState::Idle,
             context: VecDeque::with_capacity(max_context_size),
             max_context_size,
+            session_id,
+            evicted: Vec::new(),
         }
     }
 
@@ -35,7 +46,7 @@ impl StateMachine {
 
     pub fn add_message(&mut self, message: Message) {
         if self.context.len() >= self.max_context_size {
-            self.context.pop_front();
+            self.evicted.extend(self.context.pop_front());
         }
         self.context.push_back(message);
     }
@@ -44,6 +55,23 @@ impl StateMachine {
         self.context.iter().cloned().collect()
     }
 
+    /// Replaces the context, e.g. with a compressed version of it. Only the
+    /// newest `max_context_size` messages are kept; messages of the old
+    /// context that are no longer part of it count as evicted.
+    pub fn replace_context(&mut self, messages: Vec<Message>) {
+        let skip = messages.len().saturating_sub(self.max_context_size);
+        let context: VecDeque<Message> = messages.into_iter().skip(skip).collect();
+        let old = std::mem::replace(&mut self.context, context);
+        let kept: HashSet<Uuid> = self.context.iter().map(|message| message.id).collect();
+        self.evicted
+            .extend(old.into_iter().filter(|message| !kept.contains(&message.id)));
+    }
+
+    /// Drains the messages that were pushed out of the context, oldest first.
+    pub fn take_evicted(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.evicted)
+    }
+
     pub fn get_last_message(&self) -> Option<&Message> {
         self.context.back()
     }
@@ -61,10 +89,6 @@ impl StateMachine {
     }
 
     pub fn get_session_id(&self) -> Uuid {
-        // Use the ID of the first message or generate a new one
-        self.context
-            .front()
-            .map(|msg| msg.id)
-            .unwrap_or_else(Uuid::new_v4)
+        self.session_id
     }
 }
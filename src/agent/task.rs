@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+/// Finished tasks kept around so clients can still look up their outcome.
+const MAX_FINISHED_TASKS: usize = 1024;
+
+/// Lifecycle of an agent task, using the A2A task state names.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskStatus {
+    Working,
+    /// Paused until a human approves or denies a sensitive tool call.
+    InputRequired,
+    Completed,
+    Canceled,
+    Failed,
+    /// Stopped because a tool call was denied.
+    Rejected,
+}
+
+impl TaskStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, TaskStatus::Working | TaskStatus::InputRequired)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub id: String,
+    pub session_id: String,
+    /// Authenticated user who started the task. Only they and admins may see
+    /// or cancel it; tasks without an owner are visible to admins only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub status: TaskStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TaskError {
+    #[error("task {0} not found")]
+    NotFound(String),
+    #[error("task {0} already exists")]
+    AlreadyExists(String),
+    #[error("task {id} already {status:?}")]
+    AlreadyFinished { id: String, status: TaskStatus },
+}
+
+#[derive(Debug)]
+struct TaskEntry {
+    info: TaskInfo,
+    token: CancellationToken,
+}
+
+/// Tracks running agent tasks and the cancellation token of each, so a task
+/// can be stopped from outside the request that started it.
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    tasks: RwLock<HashMap<String, TaskEntry>>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a working task of `owner`. A random id is used when `id` is
+    /// `None`.
+    pub async fn start(
+        &self,
+        id: Option<String>,
+        session_id: &str,
+        owner: Option<&str>,
+    ) -> Result<(String, CancellationToken), TaskError> {
+        let id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let mut tasks = self.tasks.write().await;
+        if tasks.get(&id).is_some_and(|entry| !entry.info.status.is_finished()) {
+            return Err(TaskError::AlreadyExists(id));
+        }
+
+        let now = Utc::now();
+        let token = CancellationToken::new();
+        tasks.insert(
+            id.clone(),
+            TaskEntry {
+                info: TaskInfo {
+                    id: id.clone(),
+                    session_id: session_id.to_string(),
+                    owner: owner.map(str::to_string),
+                    status: TaskStatus::Working,
+                    created_at: now,
+                    updated_at: now,
+                    error: None,
+                },
+                token: token.clone(),
+            },
+        );
+        Self::prune(&mut tasks);
+
+        Ok((id, token))
+    }
+
+    /// Records the outcome of a task. A task that was already canceled keeps
+    /// its canceled state.
+    pub async fn finish(&self, id: &str, status: TaskStatus, error: Option<String>) {
+        if let Some(entry) = self.tasks.write().await.get_mut(id) {
+            if entry.info.status.is_finished() {
+                return;
+            }
+            entry.info.status = status;
+            entry.info.error = error;
+            entry.info.updated_at = Utc::now();
+        }
+    }
+
+    /// Switches a running task between `Working` and `InputRequired`.
+    pub async fn set_status(&self, id: &str, status: TaskStatus) {
+        if let Some(entry) = self.tasks.write().await.get_mut(id) {
+            if !entry.info.status.is_finished() {
+                entry.info.status = status;
+                entry.info.updated_at = Utc::now();
+            }
+        }
+    }
+
+    /// Moves a working task to the canceled state and signals its token.
+    pub async fn cancel(&self, id: &str) -> Result<TaskInfo, TaskError> {
+        self.stop(id, TaskStatus::Canceled, None).await
+    }
+
+    /// Fails a working task because its deadline passed and signals its token.
+    pub async fn expire(&self, id: &str) -> Result<TaskInfo, TaskError> {
+        self.stop(id, TaskStatus::Failed, Some("deadline exceeded".to_string()))
+            .await
+    }
+
+    async fn stop(
+        &self,
+        id: &str,
+        status: TaskStatus,
+        error: Option<String>,
+    ) -> Result<TaskInfo, TaskError> {
+        let mut tasks = self.tasks.write().await;
+        let entry = tasks
+            .get_mut(id)
+            .ok_or_else(|| TaskError::NotFound(id.to_string()))?;
+        if entry.info.status.is_finished() {
+            return Err(TaskError::AlreadyFinished {
+                id: id.to_string(),
+                status: entry.info.status.clone(),
+            });
+        }
+
+        entry.info.status = status;
+        entry.info.error = error;
+        entry.info.updated_at = Utc::now();
+        entry.token.cancel();
+        tracing::info!("Task {} stopped: {:?}", id, entry.info.status);
+        Ok(entry.info.clone())
+    }
+
+    pub async fn get(&self, id: &str) -> Option<TaskInfo> {
+        self.tasks.read().await.get(id).map(|entry| entry.info.clone())
+    }
+
+    pub async fn list(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = self
+            .tasks
+            .read()
+            .await
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect();
+        tasks.sort_by_key(|task| std::cmp::Reverse(task.created_at));
+        tasks
+    }
+
+    fn prune(tasks: &mut HashMap<String, TaskEntry>) {
+        let mut finished: Vec<(DateTime<Utc>, String)> = tasks
+            .values()
+            .filter(|entry| entry.info.status.is_finished())
+            .map(|entry| (entry.info.updated_at, entry.info.id.clone()))
+            .collect();
+        if finished.len() <= MAX_FINISHED_TASKS {
+            return;
+        }
+
+        finished.sort();
+        let excess = finished.len() - MAX_FINISHED_TASKS;
+        for (_, id) in finished.into_iter().take(excess) {
+            tasks.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_signals_token_once() {
+        let registry = TaskRegistry::new();
+        let (id, token) = registry.start(Some("t1".to_string()), "s1", None).await.unwrap();
+        assert!(matches!(
+            registry.start(Some("t1".to_string()), "s1", None).await,
+            Err(TaskError::AlreadyExists(_))
+        ));
+
+        let info = registry.cancel(&id).await.unwrap();
+        assert_eq!(info.status, TaskStatus::Canceled);
+        assert!(token.is_cancelled());
+
+        // Finishing after a cancel keeps the canceled state
+        registry.finish(&id, TaskStatus::Completed, None).await;
+        assert_eq!(registry.get(&id).await.unwrap().status, TaskStatus::Canceled);
+        assert!(matches!(
+            registry.cancel(&id).await,
+            Err(TaskError::AlreadyFinished { .. })
+        ));
+        assert!(matches!(
+            registry.cancel("missing").await,
+            Err(TaskError::NotFound(_))
+        ));
+    }
+}
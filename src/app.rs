@@ -1,9 +1,11 @@
 use serde_json::json;
+use std::sync::Arc;
 use tracing::info;
 
 use crate::config::AppConfig;
 use crate::llm::LLMService;
 use crate::server::A2AServer;
+use crate::services::security::SecurityManager;
 
 pub struct OmniApp {
     config: AppConfig,
@@ -35,7 +37,8 @@ impl OmniApp {
         );
         info!("🤝 A2A Peers configured: {}", self.config.a2a.servers.len());
 
-        let server = A2AServer::new(self.config.server.port);
+        let security = SecurityManager::from_config(self.config.security.clone())?;
+        let server = A2AServer::new(self.config.server.port).with_security(Arc::new(security));
         server.run().await?;
 
         Ok(())
@@ -89,13 +89,12 @@ async fn test_capability_manager() {
     let capability_manager = CapabilityManager::new();
     
     // 注册测试能力
-    let capability = Capability {
-        id: "test_capability".to_string(),
-        name: "测试能力".to_string(),
-        description: "用于测试的能力".to_string(),
-        category: "test".to_string(),
-        enabled: true,
-    };
+    let capability = Capability::new(
+        "test_capability",
+        "测试能力",
+        "用于测试的能力",
+        "test",
+    );
     
     capability_manager.register_capability(capability).await.unwrap();
     
@@ -1,6 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::core::decision::{DecisionRule, Exploration};
+use crate::core::state::{MemoryConfig, UserMemoryConfig};
+use crate::core::router::{StrategyKind, DEFAULT_CONFIDENCE_THRESHOLD};
+use crate::services::audit::AuditConfig;
+use crate::services::embeddings::EmbeddingConfig;
+use crate::services::knowledge::KnowledgeConfig;
+use crate::services::memory::CompressionConfig;
+use crate::services::security::SecurityConfig;
+use crate::services::tools::ApprovalPolicy;
+use crate::services::vector_store::VectorStoreConfig;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
@@ -8,6 +19,45 @@ pub struct AppConfig {
     pub mcp: McpConfig,
     pub a2a: A2AConfig,
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub session: SessionSettings,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub concurrency: ConcurrencySettings,
+    #[serde(default)]
+    pub tasks: TaskSettings,
+    /// 接近模型上下文窗口时的上下文压缩
+    #[serde(default)]
+    pub context: CompressionConfig,
+    /// 检索、记忆和向量路由使用的向量化提供者
+    #[serde(default)]
+    pub embeddings: EmbeddingConfig,
+    /// 检索使用的向量存储
+    #[serde(default)]
+    pub vector_store: VectorStoreConfig,
+    /// 智能体回答时检索的本地文档
+    #[serde(default)]
+    pub knowledge: KnowledgeConfig,
+    /// 智能体为用户记住的事实
+    #[serde(default)]
+    pub user_memory: UserMemoryConfig,
+    /// 按用户和会话概括、召回被挤出上下文的对话
+    #[serde(default)]
+    pub conversation_memory: MemoryConfig,
+    /// 密码策略和账户锁定
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// 审计日志的持久化和轮转
+    #[serde(default)]
+    pub audit: AuditConfig,
+    /// 敏感工具调用的人工审批策略
+    #[serde(default)]
+    pub approvals: ApprovalPolicy,
+    #[serde(default)]
+    pub workflows: WorkflowSettings,
+    #[serde(default)]
+    pub router: RouterSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +118,130 @@ pub struct LoggingConfig {
     pub file: Option<String>,
 }
 
+/// 会话管理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    /// 每个会话保留的最大上下文消息数
+    pub max_context_size: usize,
+    /// 会话空闲多久后被回收（秒）
+    pub idle_timeout_secs: u64,
+    /// 是否将会话消息持久化到数据库
+    pub persist: bool,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            max_context_size: 100,
+            idle_timeout_secs: 1800,
+            persist: false,
+        }
+    }
+}
+
+/// 并发控制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcurrencySettings {
+    /// 同时进行的LLM调用上限
+    pub max_in_flight: usize,
+    /// 等待队列长度，队列满时返回 429
+    pub max_queue: usize,
+}
+
+impl Default for ConcurrencySettings {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 16,
+            max_queue: 64,
+        }
+    }
+}
+
+/// 任务执行配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskSettings {
+    /// 单次请求的默认超时时间（秒）
+    pub request_timeout_secs: u64,
+    /// 单次请求内允许的最大工具调用次数，超过后终止工具循环
+    pub max_tool_iterations: usize,
+}
+
+impl Default for TaskSettings {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 120,
+            max_tool_iterations: 8,
+        }
+    }
+}
+
+/// 工作流配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkflowSettings {
+    /// 启动时加载工作流定义文件（`.yaml`、`.yml`、`.json`）的目录
+    pub directory: String,
+    /// 是否将运行记录持久化到数据库，启用后重启时恢复未完成的运行
+    pub persist_runs: bool,
+}
+
+impl Default for WorkflowSettings {
+    fn default() -> Self {
+        Self {
+            directory: "workflows".to_string(),
+            persist_runs: false,
+        }
+    }
+}
+
+/// 智能路由配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouterSettings {
+    /// 依次尝试的路由策略：`rules`、`embedding`、`llm`
+    pub strategies: Vec<StrategyKind>,
+    /// 路由决策的最低置信度，低于该值时回退到本地LLM
+    pub confidence_threshold: f32,
+    /// 规则策略使用的决策规则，动作为目标名称（`llm`、`mcp:<工具>`、`a2a:<智能体>`），
+    /// 默认没有规则
+    pub rules: Vec<DecisionRule>,
+    /// 多条规则同时匹配时的探索方式，默认总是选择优先级最高的规则
+    pub exploration: Exploration,
+    /// 是否将对回复的反馈持久化到数据库，启用后重启时保留学习到的成功率
+    pub persist_feedback: bool,
+}
+
+impl Default for RouterSettings {
+    fn default() -> Self {
+        Self {
+            strategies: vec![StrategyKind::Rules, StrategyKind::Embedding, StrategyKind::Llm],
+            confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+            rules: Vec::new(),
+            exploration: Exploration::Off,
+            persist_feedback: false,
+        }
+    }
+}
+
+/// 数据库配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// 数据库连接URL，例如 `sqlite://data/omni-agent.db`
+    pub url: String,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: "sqlite://omni-agent.db".to_string(),
+        }
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -99,6 +273,21 @@ impl Default for AppConfig {
                 format: "json".to_string(),
                 file: None,
             },
+            session: SessionSettings::default(),
+            database: DatabaseConfig::default(),
+            concurrency: ConcurrencySettings::default(),
+            tasks: TaskSettings::default(),
+            context: CompressionConfig::default(),
+            embeddings: EmbeddingConfig::default(),
+            vector_store: VectorStoreConfig::default(),
+            knowledge: KnowledgeConfig::default(),
+            user_memory: UserMemoryConfig::default(),
+            conversation_memory: MemoryConfig::default(),
+            security: SecurityConfig::default(),
+            audit: AuditConfig::default(),
+            approvals: ApprovalPolicy::default(),
+            workflows: WorkflowSettings::default(),
+            router: RouterSettings::default(),
         }
     }
 }
@@ -159,5 +348,9 @@ impl AppConfig {
         if let Ok(mock) = std::env::var("USE_MOCK") {
             self.llm.use_mock = mock == "true" || mock == "1";
         }
+
+        if let Ok(secret) = std::env::var("OMNI_JWT_SECRET") {
+            self.security.jwt_secret = secret;
+        }
     }
 }
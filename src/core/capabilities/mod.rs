@@ -1,9 +1,83 @@
-//! 能力管理器模块 - 简化版
+//! 能力管理器模块
+//!
+//! 能力是智能体可以使用的工具和协作方：已连接 MCP 服务器的工具、A2A 对端智能体
+//! 及其技能、本地注册的工具，以及手动注册的能力。除手动注册外，能力按来源整体
+//! 同步，同步时保留已有能力的启用状态；来源暂时不可用时其能力标记为不健康，
+//! 恢复后重新同步。路由候选和智能体名片都从这里取得可用能力。
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::protocol::agent_card::AgentCard;
+use crate::protocol::manifest::{A2AManifest, MCPManifest, MCPTool};
+use crate::services::tools::Tool;
+
+/// 能力类别：MCP 工具
+pub const CATEGORY_MCP: &str = "mcp";
+/// 能力类别：A2A 智能体及其技能
+pub const CATEGORY_A2A: &str = "a2a";
+/// 能力类别：本地工具
+pub const CATEGORY_TOOL: &str = "tool";
+
+/// 能力的来源
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Provenance {
+    /// 通过 [`CapabilityManager::register_capability`] 手动注册
+    #[default]
+    Manual,
+    /// MCP 服务器提供的工具
+    Mcp { server: String },
+    /// A2A 对端智能体的名片或清单
+    A2a { agent: String },
+    /// 本地工具执行引擎中的工具
+    LocalTool,
+}
+
+/// 健康状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// 尚未检查
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+/// 能力的健康信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityHealth {
+    pub status: HealthStatus,
+    /// 最近一次检查失败的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl CapabilityHealth {
+    pub fn healthy() -> Self {
+        Self {
+            status: HealthStatus::Healthy,
+            error: None,
+            checked_at: Some(Utc::now()),
+        }
+    }
+
+    pub fn unhealthy(error: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Unhealthy,
+            error: Some(error.into()),
+            checked_at: Some(Utc::now()),
+        }
+    }
+}
 
 /// 能力信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,18 +87,216 @@ pub struct Capability {
     pub description: String,
     pub category: String,
     pub enabled: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 调用参数的 JSON Schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<Value>,
+    /// 接受的输入 MIME 类型
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_modes: Vec<String>,
+    /// 产生的输出 MIME 类型
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_modes: Vec<String>,
+    #[serde(default)]
+    pub provenance: Provenance,
+    #[serde(default)]
+    pub health: CapabilityHealth,
+}
+
+impl Capability {
+    /// 创建启用的手动能力
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        category: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: description.into(),
+            category: category.into(),
+            enabled: true,
+            tags: Vec::new(),
+            input_schema: None,
+            input_modes: Vec::new(),
+            output_modes: Vec::new(),
+            provenance: Provenance::Manual,
+            health: CapabilityHealth::default(),
+        }
+    }
+
+    pub fn with_tags<I, T>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_input_schema(mut self, schema: Value) -> Self {
+        self.input_schema = Some(schema);
+        self
+    }
+
+    pub fn with_modes(mut self, input_modes: Vec<String>, output_modes: Vec<String>) -> Self {
+        self.input_modes = input_modes;
+        self.output_modes = output_modes;
+        self
+    }
+
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// 启用且来源没有报告故障
+    pub fn is_available(&self) -> bool {
+        self.enabled && self.health.status != HealthStatus::Unhealthy
+    }
+
+    /// 提供该能力的 A2A 智能体名称
+    pub fn a2a_agent(&self) -> Option<&str> {
+        match &self.provenance {
+            Provenance::A2a { agent } => Some(agent),
+            _ => None,
+        }
+    }
+
+    /// MCP 服务器的工具，标签取服务器清单的能力列表
+    pub fn from_mcp_tool(server: &str, manifest: &MCPManifest, tool: &MCPTool) -> Self {
+        let name = tool
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.title.clone())
+            .unwrap_or_else(|| tool.name.clone());
+        Self::new(&tool.name, name, &tool.description, CATEGORY_MCP)
+            .with_tags(manifest.capabilities.iter().cloned())
+            .with_input_schema(tool.input_schema.clone())
+            .with_provenance(Provenance::Mcp {
+                server: server.to_string(),
+            })
+    }
+
+    /// MCP 服务器清单中的全部工具
+    pub fn from_mcp_manifest(server: &str, manifest: &MCPManifest) -> Vec<Self> {
+        manifest
+            .tools
+            .iter()
+            .map(|tool| Self::from_mcp_tool(server, manifest, tool))
+            .collect()
+    }
+
+    /// A2A 对端智能体：智能体本身是一项能力（ID 为配置的名称），名片中的每个技能
+    /// 是一项 ID 为 `<智能体>/<技能>` 的能力。没有名片时只使用清单
+    pub fn from_a2a_agent(agent: &str, manifest: &A2AManifest, card: Option<&AgentCard>) -> Vec<Self> {
+        let provenance = Provenance::A2a {
+            agent: agent.to_string(),
+        };
+        let mut tags = manifest.capabilities.clone();
+        let (input_modes, output_modes) = match card {
+            Some(card) => {
+                for tag in card.skills.iter().flat_map(|skill| skill.tags.iter()) {
+                    if !tags.contains(tag) {
+                        tags.push(tag.clone());
+                    }
+                }
+                (card.default_input_modes.clone(), card.default_output_modes.clone())
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        let description = match card {
+            Some(card) if manifest.description.is_empty() => card.description.clone(),
+            _ => manifest.description.clone(),
+        };
+        let mut capabilities = vec![Self::new(agent, &manifest.name, description, CATEGORY_A2A)
+            .with_tags(tags)
+            .with_modes(input_modes.clone(), output_modes.clone())
+            .with_provenance(provenance.clone())];
+
+        let skills = card.map(|card| card.skills.as_slice()).unwrap_or_default();
+        capabilities.extend(skills.iter().map(|skill| {
+            Self::new(
+                format!("{agent}/{}", skill.id),
+                &skill.name,
+                &skill.description,
+                CATEGORY_A2A,
+            )
+            .with_tags(skill.tags.iter().cloned())
+            .with_modes(
+                skill.input_modes.clone().unwrap_or_else(|| input_modes.clone()),
+                skill.output_modes.clone().unwrap_or_else(|| output_modes.clone()),
+            )
+            .with_provenance(provenance.clone())
+        }));
+        capabilities
+    }
+
+    /// 本地工具
+    pub fn from_tool(tool: &dyn Tool) -> Self {
+        let mut capability = Self::new(tool.name(), tool.name(), tool.description(), CATEGORY_TOOL)
+            .with_provenance(Provenance::LocalTool);
+        capability.input_schema = tool.input_schema();
+        capability
+    }
+}
+
+/// 能力查询条件，未设置的条件不参与过滤
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapabilityQuery {
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// 在ID、名称和说明中查找，不区分大小写
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+impl CapabilityQuery {
+    pub fn matches(&self, capability: &Capability) -> bool {
+        if self
+            .category
+            .as_ref()
+            .is_some_and(|category| !capability.category.eq_ignore_ascii_case(category))
+        {
+            return false;
+        }
+        if self
+            .tag
+            .as_ref()
+            .is_some_and(|tag| !capability.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        {
+            return false;
+        }
+        if self.enabled.is_some_and(|enabled| capability.enabled != enabled) {
+            return false;
+        }
+        match &self.text {
+            Some(text) => {
+                let text = text.to_lowercase();
+                [&capability.id, &capability.name, &capability.description]
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(&text))
+            }
+            None => true,
+        }
+    }
 }
 
 /// 能力管理器
+#[derive(Debug, Default)]
 pub struct CapabilityManager {
     capabilities: Arc<RwLock<HashMap<String, Capability>>>,
 }
 
 impl CapabilityManager {
     pub fn new() -> Self {
-        Self {
-            capabilities: Arc::new(RwLock::new(HashMap::new())),
-        }
+        Self::default()
     }
 
     /// 注册能力
@@ -34,9 +306,208 @@ impl CapabilityManager {
         Ok(())
     }
 
-    /// 获取所有能力
+    /// 移除能力
+    pub async fn remove_capability(&self, id: &str) -> Option<Capability> {
+        self.capabilities.write().await.remove(id)
+    }
+
+    /// 按ID获取能力
+    pub async fn get_capability(&self, id: &str) -> Option<Capability> {
+        let capabilities = self.capabilities.read().await;
+        capabilities.get(id).cloned()
+    }
+
+    /// 获取所有能力，按ID排序
     pub async fn get_all_capabilities(&self) -> Vec<Capability> {
+        self.search(&CapabilityQuery::default()).await
+    }
+
+    /// 按条件查找能力，按ID排序
+    pub async fn search(&self, query: &CapabilityQuery) -> Vec<Capability> {
         let capabilities = self.capabilities.read().await;
-        capabilities.values().cloned().collect()
+        let mut found: Vec<Capability> = capabilities
+            .values()
+            .filter(|capability| query.matches(capability))
+            .cloned()
+            .collect();
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        found
+    }
+
+    /// 可用的能力：启用且来源健康，按ID排序
+    pub async fn available(&self) -> Vec<Capability> {
+        let mut available = self.get_all_capabilities().await;
+        available.retain(Capability::is_available);
+        available
+    }
+
+    /// 用来源当前提供的能力替换该来源的全部能力并标记为健康。已存在的能力保留
+    /// 启用状态，来源不再提供的能力被移除。返回同步后的能力数量
+    pub async fn sync(&self, provenance: &Provenance, fresh: Vec<Capability>) -> usize {
+        let mut capabilities = self.capabilities.write().await;
+        let mut previous: HashMap<String, Capability> = HashMap::new();
+        capabilities.retain(|id, capability| {
+            if capability.provenance == *provenance {
+                previous.insert(id.clone(), capability.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        let count = fresh.len();
+        for mut capability in fresh {
+            capability.provenance = provenance.clone();
+            capability.health = CapabilityHealth::healthy();
+            if let Some(existing) = previous.get(&capability.id) {
+                capability.enabled = existing.enabled;
+            }
+            capabilities.insert(capability.id.clone(), capability);
+        }
+        count
     }
-}
\ No newline at end of file
+
+    /// 将来源的全部能力标记为不健康，返回受影响的数量
+    pub async fn mark_unhealthy(&self, provenance: &Provenance, error: &str) -> usize {
+        let mut capabilities = self.capabilities.write().await;
+        let mut count = 0;
+        for capability in capabilities
+            .values_mut()
+            .filter(|capability| capability.provenance == *provenance)
+        {
+            capability.health = CapabilityHealth::unhealthy(error);
+            count += 1;
+        }
+        count
+    }
+
+    /// 启用或禁用能力，返回更新后的能力
+    pub async fn set_enabled(&self, id: &str, enabled: bool) -> Option<Capability> {
+        let mut capabilities = self.capabilities.write().await;
+        let capability = capabilities.get_mut(id)?;
+        capability.enabled = enabled;
+        Some(capability.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::agent_card::AgentSkill;
+
+    fn mcp_manifest() -> MCPManifest {
+        serde_json::from_value(serde_json::json!({
+            "name": "weather-server",
+            "version": "1.0.0",
+            "description": "Weather tools",
+            "capabilities": ["weather"],
+            "tools": [{
+                "name": "get_weather",
+                "description": "Get the weather",
+                "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }],
+            "metadata": {}
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sync_preserves_enabled_state_and_tracks_health() {
+        let manager = CapabilityManager::new();
+        let server = Provenance::Mcp {
+            server: "weather".to_string(),
+        };
+        let manifest = mcp_manifest();
+        let tools = Capability::from_mcp_manifest("weather", &manifest);
+        assert_eq!(manager.sync(&server, tools.clone()).await, 1);
+
+        let tool = manager.get_capability("get_weather").await.unwrap();
+        assert_eq!(tool.provenance, server);
+        assert_eq!(tool.health.status, HealthStatus::Healthy);
+        assert_eq!(tool.tags, vec!["weather"]);
+        assert!(tool.input_schema.is_some());
+
+        manager.set_enabled("get_weather", false).await.unwrap();
+        manager.sync(&server, tools).await;
+        assert!(!manager.get_capability("get_weather").await.unwrap().enabled);
+        manager.set_enabled("get_weather", true).await.unwrap();
+
+        assert_eq!(manager.mark_unhealthy(&server, "connection refused").await, 1);
+        let tool = manager.get_capability("get_weather").await.unwrap();
+        assert_eq!(tool.health.error.as_deref(), Some("connection refused"));
+        assert!(manager.available().await.is_empty());
+
+        // 来源不再提供的能力被移除
+        manager.sync(&server, Vec::new()).await;
+        assert!(manager.get_all_capabilities().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_by_category_tag_and_text() {
+        let manager = CapabilityManager::new();
+        let manifest: A2AManifest = serde_json::from_value(serde_json::json!({
+            "name": "analyst",
+            "version": "1.0.0",
+            "description": "Data analysis agent",
+            "capabilities": ["analysis"],
+            "endpoints": [],
+            "metadata": {}
+        }))
+        .unwrap();
+        let card = AgentCard::new(
+            "analyst".to_string(),
+            "Data analysis agent".to_string(),
+            "1.0.0".to_string(),
+            "http://analyst/".to_string(),
+            vec![AgentSkill {
+                id: "report".to_string(),
+                name: "Reporting".to_string(),
+                description: "Write reports".to_string(),
+                tags: vec!["reporting".to_string()],
+                examples: None,
+                input_modes: None,
+                output_modes: Some(vec!["text/markdown".to_string()]),
+            }],
+        );
+        let agent = Provenance::A2a {
+            agent: "analyst".to_string(),
+        };
+        manager
+            .sync(&agent, Capability::from_a2a_agent("analyst", &manifest, Some(&card)))
+            .await;
+        manager
+            .register_capability(Capability::new("chat", "Chat", "Local LLM", "llm"))
+            .await
+            .unwrap();
+
+        let a2a = manager
+            .search(&CapabilityQuery {
+                category: Some(CATEGORY_A2A.to_string()),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(a2a.len(), 2);
+        assert_eq!(a2a[0].tags, vec!["analysis", "reporting"]);
+        assert_eq!(a2a[1].id, "analyst/report");
+        assert_eq!(a2a[1].a2a_agent(), Some("analyst"));
+        assert_eq!(a2a[1].output_modes, vec!["text/markdown"]);
+
+        let tagged = manager
+            .search(&CapabilityQuery {
+                tag: Some("Reporting".to_string()),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(tagged.len(), 2);
+
+        let text = manager
+            .search(&CapabilityQuery {
+                text: Some("llm".to_string()),
+                enabled: Some(true),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(text.len(), 1);
+        assert_eq!(text[0].provenance, Provenance::Manual);
+    }
+}
@@ -0,0 +1,602 @@
+//! 决策规则条件
+//!
+//! 条件以 JSON 描述，可以组合文本包含、正则、`all`/`any`/`not`、消息元数据字段、
+//! 发送者和角色、时间窗口以及工具和智能体的可用性，例如：
+//!
+//! ```json
+//! {"all": [
+//!   {"regex": "(?i)invoice|发票"},
+//!   {"field": {"path": "user.tier", "one_of": ["gold", "platinum"]}},
+//!   {"time_window": {"start": "09:00", "end": "18:00", "days": ["mon", "tue", "wed", "thu", "fri"]}},
+//!   {"tool_available": "create_invoice"}
+//! ]}
+//! ```
+//!
+//! 为兼容旧配置，规则的条件也可以是字符串：`"*"` 总是匹配，其他字符串表示
+//! 消息包含该文本。
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::protocol::message::{Message, MessageContent};
+
+/// 时间窗口的时刻格式
+const TIME_FORMAT: &str = "%H:%M";
+
+/// 评估条件时可用的上下文
+#[derive(Debug, Clone)]
+pub struct DecisionContext {
+    /// 消息文本
+    pub text: String,
+    pub sender: Option<String>,
+    pub role: Option<String>,
+    /// 消息元数据
+    pub metadata: Value,
+    /// 评估时刻
+    pub now: DateTime<Utc>,
+    /// 可用的工具名称
+    pub tools: HashSet<String>,
+    /// 可用的智能体名称
+    pub agents: HashSet<String>,
+}
+
+impl DecisionContext {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            sender: None,
+            role: None,
+            metadata: Value::Null,
+            now: Utc::now(),
+            tools: HashSet::new(),
+            agents: HashSet::new(),
+        }
+    }
+
+    /// 由消息创建上下文，角色取自元数据中的 `role`
+    pub fn from_message(message: &Message) -> Self {
+        let text = match &message.content {
+            MessageContent::Text { text } => text.clone(),
+            other => serde_json::to_string(other).unwrap_or_default(),
+        };
+        let metadata = message.metadata.clone().unwrap_or(Value::Null);
+        let role = metadata
+            .get("role")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        Self {
+            sender: Some(message.sender.clone()),
+            role,
+            metadata,
+            ..Self::new(text)
+        }
+    }
+
+    pub fn with_sender(mut self, sender: impl Into<String>) -> Self {
+        self.sender = Some(sender.into());
+        self
+    }
+
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_tools(mut self, tools: impl IntoIterator<Item = String>) -> Self {
+        self.tools.extend(tools);
+        self
+    }
+
+    pub fn with_agents(mut self, agents: impl IntoIterator<Item = String>) -> Self {
+        self.agents.extend(agents);
+        self
+    }
+
+    /// 指定评估时刻
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.now = now;
+        self
+    }
+}
+
+/// 规则条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// 总是匹配
+    #[serde(rename = "*")]
+    Always,
+    /// 消息包含文本
+    Contains(String),
+    /// 消息匹配正则表达式
+    Regex(Pattern),
+    /// 全部子条件满足
+    All(Vec<Condition>),
+    /// 任一子条件满足
+    Any(Vec<Condition>),
+    /// 子条件不满足
+    Not(Box<Condition>),
+    /// 元数据字段满足谓词
+    Field(FieldPredicate),
+    /// 发送者等于给定值
+    Sender(String),
+    /// 角色等于给定值
+    Role(String),
+    /// 评估时刻在时间窗口内
+    TimeWindow(TimeWindow),
+    /// 工具可用
+    ToolAvailable(String),
+    /// 智能体可用
+    AgentAvailable(String),
+}
+
+/// 条件评估结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub matched: bool,
+    /// 匹配或不匹配的原因
+    pub reason: String,
+}
+
+impl Evaluation {
+    fn new(matched: bool, reason: impl Into<String>) -> Self {
+        Self {
+            matched,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Condition {
+    /// 评估条件并说明原因
+    pub fn evaluate(&self, context: &DecisionContext) -> Evaluation {
+        match self {
+            Condition::Always => Evaluation::new(true, "总是匹配"),
+            Condition::Contains(text) => {
+                let matched = context.text.contains(text.as_str());
+                Evaluation::new(
+                    matched,
+                    format!("消息{}“{}”", if matched { "包含" } else { "不包含" }, text),
+                )
+            }
+            Condition::Regex(pattern) => match pattern.0.find(&context.text) {
+                Some(found) => Evaluation::new(
+                    true,
+                    format!("消息匹配正则 /{}/: “{}”", pattern.as_str(), found.as_str()),
+                ),
+                None => Evaluation::new(false, format!("消息不匹配正则 /{}/", pattern.as_str())),
+            },
+            Condition::All(conditions) => {
+                let mut reasons = Vec::new();
+                for condition in conditions {
+                    let evaluation = condition.evaluate(context);
+                    if !evaluation.matched {
+                        return evaluation;
+                    }
+                    reasons.push(evaluation.reason);
+                }
+                Evaluation::new(true, reasons.join("；"))
+            }
+            Condition::Any(conditions) => {
+                let mut reasons = Vec::new();
+                for condition in conditions {
+                    let evaluation = condition.evaluate(context);
+                    if evaluation.matched {
+                        return evaluation;
+                    }
+                    reasons.push(evaluation.reason);
+                }
+                Evaluation::new(false, format!("均不满足: {}", reasons.join("；")))
+            }
+            Condition::Not(condition) => {
+                let evaluation = condition.evaluate(context);
+                Evaluation::new(!evaluation.matched, format!("非（{}）", evaluation.reason))
+            }
+            Condition::Field(predicate) => predicate.evaluate(&context.metadata),
+            Condition::Sender(sender) => {
+                equals_evaluation("发送者", context.sender.as_deref(), sender)
+            }
+            Condition::Role(role) => equals_evaluation("角色", context.role.as_deref(), role),
+            Condition::TimeWindow(window) => window.evaluate(context.now),
+            Condition::ToolAvailable(tool) => {
+                let matched = context.tools.contains(tool);
+                Evaluation::new(
+                    matched,
+                    format!("工具 {} {}", tool, if matched { "可用" } else { "不可用" }),
+                )
+            }
+            Condition::AgentAvailable(agent) => {
+                let matched = context.agents.contains(agent);
+                Evaluation::new(
+                    matched,
+                    format!(
+                        "智能体 {} {}",
+                        agent,
+                        if matched { "可用" } else { "不可用" }
+                    ),
+                )
+            }
+        }
+    }
+
+    /// 条件是否满足
+    pub fn matches(&self, context: &DecisionContext) -> bool {
+        self.evaluate(context).matched
+    }
+
+    /// 检查条件结构，返回第一个问题
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Condition::Always | Condition::Regex(_) => Ok(()),
+            Condition::Contains(value) => non_empty("contains", value),
+            Condition::Sender(value) => non_empty("sender", value),
+            Condition::Role(value) => non_empty("role", value),
+            Condition::ToolAvailable(value) => non_empty("tool_available", value),
+            Condition::AgentAvailable(value) => non_empty("agent_available", value),
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                if conditions.is_empty() {
+                    return Err("all/any 至少需要一个子条件".to_string());
+                }
+                conditions.iter().try_for_each(Condition::validate)
+            }
+            Condition::Not(condition) => condition.validate(),
+            Condition::Field(predicate) => predicate.validate(),
+            Condition::TimeWindow(window) => window.validate(),
+        }
+    }
+}
+
+/// 字符串转换为旧格式条件：`"*"` 总是匹配，其他文本表示包含
+impl From<&str> for Condition {
+    fn from(condition: &str) -> Self {
+        if condition == "*" {
+            Condition::Always
+        } else {
+            Condition::Contains(condition.to_string())
+        }
+    }
+}
+
+/// 反序列化规则条件，同时接受结构化条件和旧格式字符串
+pub(super) fn deserialize_condition<'de, D>(deserializer: D) -> Result<Condition, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(text) => Ok(Condition::from(text.as_str())),
+        value => Condition::deserialize(value).map_err(serde::de::Error::custom),
+    }
+}
+
+fn non_empty(name: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        Err(format!("{name} 不能为空"))
+    } else {
+        Ok(())
+    }
+}
+
+fn equals_evaluation(name: &str, actual: Option<&str>, expected: &str) -> Evaluation {
+    match actual {
+        Some(actual) if actual == expected => Evaluation::new(true, format!("{name}为 {expected}")),
+        Some(actual) => Evaluation::new(false, format!("{name}为 {actual}，不是 {expected}")),
+        None => Evaluation::new(false, format!("没有{name}信息")),
+    }
+}
+
+/// 正则表达式，序列化为模式字符串，反序列化时编译
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 元数据字段谓词，给出的各项检查需全部满足
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldPredicate {
+    /// 以 `.` 分隔的字段路径，数组元素用下标表示，例如 `user.tier`、`tags.0`
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exists: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub one_of: Option<Vec<Value>>,
+    /// 字符串包含给定文本，或数组包含给定字符串
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gt: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lt: Option<f64>,
+}
+
+impl FieldPredicate {
+    fn lookup<'a>(&self, metadata: &'a Value) -> Option<&'a Value> {
+        self.path
+            .split('.')
+            .try_fold(metadata, |value, key| match value {
+                Value::Object(map) => map.get(key),
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+                _ => None,
+            })
+            .filter(|value| !value.is_null())
+    }
+
+    fn evaluate(&self, metadata: &Value) -> Evaluation {
+        let path = &self.path;
+        let value = self.lookup(metadata);
+        if let Some(exists) = self.exists {
+            if value.is_some() != exists {
+                let state = if exists { "不存在" } else { "存在" };
+                return Evaluation::new(false, format!("字段 {path} {state}"));
+            }
+        }
+        let Some(value) = value else {
+            return if self.exists == Some(false) {
+                Evaluation::new(true, format!("字段 {path} 不存在"))
+            } else {
+                Evaluation::new(false, format!("字段 {path} 不存在"))
+            };
+        };
+
+        let failed =
+            |check: String| Evaluation::new(false, format!("字段 {path} = {value}，{check}"));
+        if let Some(expected) = &self.equals {
+            if value != expected {
+                return failed(format!("不等于 {expected}"));
+            }
+        }
+        if let Some(options) = &self.one_of {
+            if !options.contains(value) {
+                return failed(format!("不在 {} 中", Value::from(options.clone())));
+            }
+        }
+        if let Some(text) = &self.contains {
+            let contained = match value {
+                Value::String(value) => value.contains(text.as_str()),
+                Value::Array(items) => items.iter().any(|item| item.as_str() == Some(text)),
+                _ => false,
+            };
+            if !contained {
+                return failed(format!("不包含“{text}”"));
+            }
+        }
+        if self.gt.is_some() || self.lt.is_some() {
+            let Some(number) = value.as_f64() else {
+                return failed("不是数字".to_string());
+            };
+            if let Some(bound) = self.gt.filter(|bound| number <= *bound) {
+                return failed(format!("不大于 {bound}"));
+            }
+            if let Some(bound) = self.lt.filter(|bound| number >= *bound) {
+                return failed(format!("不小于 {bound}"));
+            }
+        }
+        Evaluation::new(true, format!("字段 {path} = {value}"))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.path.is_empty() || self.path.split('.').any(str::is_empty) {
+            return Err(format!("字段路径无效: “{}”", self.path));
+        }
+        let checks = [
+            self.exists.is_some(),
+            self.equals.is_some(),
+            self.one_of.is_some(),
+            self.contains.is_some(),
+            self.gt.is_some(),
+            self.lt.is_some(),
+        ];
+        if !checks.contains(&true) {
+            return Err(format!("字段 {} 至少需要一项检查", self.path));
+        }
+        Ok(())
+    }
+}
+
+/// 每天的时间窗口，`start` 含、`end` 不含；`start` 晚于 `end` 时窗口跨越午夜
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindow {
+    /// 开始时刻，`HH:MM`
+    #[serde(with = "hour_minute")]
+    pub start: NaiveTime,
+    /// 结束时刻，`HH:MM`
+    #[serde(with = "hour_minute")]
+    pub end: NaiveTime,
+    /// 生效的星期，例如 `["mon", "fri"]`，为空时每天生效
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    /// 相对 UTC 的分钟偏移，例如北京时间为 480
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+impl TimeWindow {
+    fn evaluate(&self, now: DateTime<Utc>) -> Evaluation {
+        let local = now.naive_utc() + Duration::minutes(i64::from(self.utc_offset_minutes));
+        let time = local.time();
+        let day = local.weekday();
+        let window = format!(
+            "{}-{}",
+            self.start.format(TIME_FORMAT),
+            self.end.format(TIME_FORMAT)
+        );
+        if !self.days.is_empty() && !self.days.contains(&day) {
+            return Evaluation::new(false, format!("{day} 不在生效日期内"));
+        }
+        let inside = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        let state = if inside { "在" } else { "不在" };
+        Evaluation::new(
+            inside,
+            format!(
+                "当前时间 {} {} {} 内",
+                time.format(TIME_FORMAT),
+                state,
+                window
+            ),
+        )
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.start == self.end {
+            return Err("时间窗口的开始和结束不能相同".to_string());
+        }
+        if self.utc_offset_minutes.unsigned_abs() >= 24 * 60 {
+            return Err(format!("UTC 偏移无效: {}", self.utc_offset_minutes));
+        }
+        Ok(())
+    }
+}
+
+mod hour_minute {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::TIME_FORMAT;
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&time.format(TIME_FORMAT))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&text, TIME_FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn condition(value: Value) -> Condition {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_structured_conditions() {
+        // 2024-06-03 是星期一
+        let monday_10am = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+        let context = DecisionContext::new("Please create an INVOICE for ACME")
+            .with_sender("alice")
+            .with_role("user")
+            .with_metadata(json!({"user": {"tier": "gold", "seats": 12}, "tags": ["billing"]}))
+            .with_tools(["create_invoice".to_string()])
+            .at(monday_10am);
+
+        let rule = condition(json!({"all": [
+            {"regex": "(?i)invoice|发票"},
+            {"field": {"path": "user.tier", "one_of": ["gold", "platinum"]}},
+            {"field": {"path": "user.seats", "gt": 10, "lt": 100}},
+            {"field": {"path": "tags", "contains": "billing"}},
+            {"time_window": {"start": "09:00", "end": "18:00", "days": ["mon", "tue"]}},
+            {"tool_available": "create_invoice"},
+            {"any": [{"sender": "bob"}, {"role": "user"}]},
+            {"not": {"agent_available": "billing_agent"}}
+        ]}));
+        assert!(rule.validate().is_ok());
+        let evaluation = rule.evaluate(&context);
+        assert!(evaluation.matched, "{}", evaluation.reason);
+        assert!(evaluation.reason.contains("INVOICE"));
+
+        let evaluation = condition(json!({"field": {"path": "user.tier", "equals": "silver"}}))
+            .evaluate(&context);
+        assert!(!evaluation.matched);
+        assert!(evaluation.reason.contains("不等于 \"silver\""));
+
+        // 跨越午夜的窗口和时区偏移：UTC 10:00 是 UTC+8 的 18:00
+        let night = condition(json!({"time_window": {"start": "22:00", "end": "06:00"}}));
+        assert!(!night.matches(&context));
+        assert!(night.matches(&context.clone().at(monday_10am + Duration::hours(14))));
+        let evening = condition(
+            json!({"time_window": {"start": "17:00", "end": "19:00", "utc_offset_minutes": 480}}),
+        );
+        assert!(evening.matches(&context));
+
+        assert!(
+            !condition(json!({"field": {"path": "missing", "exists": true}})).matches(&context)
+        );
+        assert!(
+            condition(json!({"field": {"path": "missing", "exists": false}})).matches(&context)
+        );
+    }
+
+    #[test]
+    fn test_validation_and_serialization() {
+        assert!(condition(json!({"any": []})).validate().is_err());
+        assert!(condition(json!({"field": {"path": "user.tier"}}))
+            .validate()
+            .is_err());
+        assert!(condition(json!({"not": {"contains": ""}}))
+            .validate()
+            .is_err());
+        assert!(serde_json::from_value::<Condition>(json!({"regex": "("})).is_err());
+        assert!(serde_json::from_value::<Condition>(
+            json!({"time_window": {"start": "9am", "end": "18:00"}})
+        )
+        .is_err());
+        assert!(
+            serde_json::from_value::<Condition>(json!({"field": {"path": "a", "is": 1}})).is_err()
+        );
+        for offset in [1440, -1440, i32::MIN] {
+            assert!(condition(json!({"time_window": {
+                "start": "09:00", "end": "18:00", "utc_offset_minutes": offset
+            }}))
+            .validate()
+            .is_err());
+        }
+
+        let original = condition(json!({"all": [
+            "*",
+            {"regex": "^hi"},
+            {"time_window": {"start": "09:00", "end": "18:30", "days": ["sat"]}}
+        ]}));
+        let value = serde_json::to_value(&original).unwrap();
+        assert_eq!(value["all"][0], "*");
+        assert_eq!(value["all"][2]["time_window"]["end"], "18:30");
+        assert_eq!(condition(value), original);
+    }
+}
@@ -0,0 +1,207 @@
+//! 基于反馈的学习
+//!
+//! 反馈分数在 -1.0 到 1.0 之间，换算为 0 到 1 的奖励后按规则和动作累计。
+//! 置信度是以先验置信度为起点的奖励均值：没有反馈时等于先验，反馈越多越接近
+//! 实际的成功率。多条规则同时匹配时，可以按 [`Exploration`] 在它们之间探索。
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// 先验置信度相当于的反馈条数
+pub const PRIOR_WEIGHT: f32 = 5.0;
+
+/// 反馈统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackStats {
+    /// 反馈条数
+    pub count: u32,
+    /// 正面反馈条数
+    pub positive: u32,
+    /// 负面反馈条数
+    pub negative: u32,
+    /// 累计奖励，每条反馈贡献 0 到 1
+    pub reward: f32,
+}
+
+impl FeedbackStats {
+    /// 记录一条反馈
+    pub fn record(&mut self, feedback: f32) {
+        let feedback = feedback.clamp(-1.0, 1.0);
+        self.count += 1;
+        if feedback > 0.0 {
+            self.positive += 1;
+        } else if feedback < 0.0 {
+            self.negative += 1;
+        }
+        self.reward += (feedback + 1.0) / 2.0;
+    }
+
+    /// 成功率（平均奖励），没有反馈时为 `None`
+    pub fn success_rate(&self) -> Option<f32> {
+        (self.count > 0).then(|| self.reward / self.count as f32)
+    }
+
+    /// 以 `prior` 为先验的置信度
+    pub fn confidence(&self, prior: f32) -> f32 {
+        (prior * PRIOR_WEIGHT + self.reward) / (PRIOR_WEIGHT + self.count as f32)
+    }
+}
+
+/// 按规则ID和动作汇总的反馈统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LearningStats {
+    pub rules: HashMap<String, FeedbackStats>,
+    pub actions: HashMap<String, FeedbackStats>,
+}
+
+impl LearningStats {
+    /// 记录一条反馈，`rule_id` 为空时只计入动作
+    pub fn record(&mut self, rule_id: Option<&str>, action: &str, feedback: f32) {
+        if let Some(rule_id) = rule_id {
+            self.rules
+                .entry(rule_id.to_string())
+                .or_default()
+                .record(feedback);
+        }
+        self.actions
+            .entry(action.to_string())
+            .or_default()
+            .record(feedback);
+    }
+
+    pub fn rule(&self, rule_id: &str) -> FeedbackStats {
+        self.rules.get(rule_id).cloned().unwrap_or_default()
+    }
+
+    pub fn action(&self, action: &str) -> FeedbackStats {
+        self.actions.get(action).cloned().unwrap_or_default()
+    }
+}
+
+/// 多条规则同时匹配时的选择方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Exploration {
+    /// 总是选择优先级最高的规则
+    #[default]
+    Off,
+    /// 以 `epsilon` 的概率随机选择，否则选择置信度最高的规则
+    EpsilonGreedy { epsilon: f32 },
+    /// 置信度加上反馈越少越大的探索奖励（UCB1），选择得分最高的规则
+    Ucb {
+        #[serde(default = "default_ucb_weight")]
+        weight: f32,
+    },
+}
+
+fn default_ucb_weight() -> f32 {
+    1.0
+}
+
+impl Exploration {
+    /// 检查参数范围
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Exploration::Off => Ok(()),
+            Exploration::EpsilonGreedy { epsilon } if (0.0..=1.0).contains(&epsilon) => Ok(()),
+            Exploration::EpsilonGreedy { epsilon } => {
+                Err(format!("epsilon 必须在 0 到 1 之间: {epsilon}"))
+            }
+            Exploration::Ucb { weight } if weight >= 0.0 => Ok(()),
+            Exploration::Ucb { weight } => Err(format!("weight 不能为负数: {weight}")),
+        }
+    }
+
+    /// 在按优先级排列的候选中选择一个，返回其下标；候选为 `(先验置信度, 反馈统计)`，
+    /// 得分相同时选择优先级较高的候选
+    pub fn select(&self, candidates: &[(f32, FeedbackStats)]) -> Option<usize> {
+        if candidates.is_empty() {
+            return None;
+        }
+        match *self {
+            Exploration::Off => Some(0),
+            Exploration::EpsilonGreedy { epsilon } => {
+                let mut rng = rand::thread_rng();
+                if rng.gen::<f32>() < epsilon {
+                    Some(rng.gen_range(0..candidates.len()))
+                } else {
+                    best(candidates, |(prior, stats)| stats.confidence(*prior))
+                }
+            }
+            Exploration::Ucb { weight } => {
+                let total: u32 = candidates.iter().map(|(_, stats)| stats.count).sum();
+                let explore = ((total + 1) as f32).ln();
+                best(candidates, |(prior, stats)| {
+                    stats.confidence(*prior)
+                        + weight * (explore / (stats.count + 1) as f32).sqrt()
+                })
+            }
+        }
+    }
+}
+
+/// 得分最高的下标，得分相同时取靠前的
+fn best<T>(items: &[T], score: impl Fn(&T) -> f32) -> Option<usize> {
+    items
+        .iter()
+        .map(score)
+        .enumerate()
+        .fold(None, |best: Option<(usize, f32)>, (index, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((index, score)),
+        })
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(feedback: &[f32]) -> FeedbackStats {
+        let mut stats = FeedbackStats::default();
+        for score in feedback {
+            stats.record(*score);
+        }
+        stats
+    }
+
+    #[test]
+    fn test_confidence_moves_from_prior_towards_success_rate() {
+        let empty = FeedbackStats::default();
+        assert_eq!(empty.success_rate(), None);
+        assert_eq!(empty.confidence(0.8), 0.8);
+
+        let bad = stats(&[-1.0, -1.0, -1.0, 1.0]);
+        assert_eq!((bad.count, bad.positive, bad.negative), (4, 1, 3));
+        assert_eq!(bad.success_rate(), Some(0.25));
+        assert!((bad.confidence(0.8) - 0.5556).abs() < 0.001);
+
+        let good = stats(&[1.0; 8]);
+        assert!(good.confidence(0.5) > 0.8);
+    }
+
+    #[test]
+    fn test_exploration_selects_between_candidates() {
+        let candidates = vec![
+            (0.8, stats(&[-1.0, -1.0, -1.0])),
+            (0.8, stats(&[1.0, 1.0, 1.0])),
+            (0.8, FeedbackStats::default()),
+        ];
+        assert_eq!(Exploration::Off.select(&candidates), Some(0));
+        assert_eq!(
+            Exploration::EpsilonGreedy { epsilon: 0.0 }.select(&candidates),
+            Some(1)
+        );
+        // 没有反馈的候选获得最大的探索奖励
+        assert_eq!(Exploration::Ucb { weight: 1.0 }.select(&candidates), Some(2));
+        assert_eq!(Exploration::Ucb { weight: 0.0 }.select(&candidates), Some(1));
+        assert_eq!(Exploration::Ucb { weight: 1.0 }.select(&[]), None);
+
+        let always_random = Exploration::EpsilonGreedy { epsilon: 1.0 };
+        assert!(always_random.select(&candidates).unwrap() < candidates.len());
+        assert!(Exploration::EpsilonGreedy { epsilon: 1.5 }.validate().is_err());
+        assert!(Exploration::Ucb { weight: -1.0 }.validate().is_err());
+    }
+}
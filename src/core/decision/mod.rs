@@ -1,10 +1,49 @@
 //! 决策引擎模块
+//!
+//! 规则条件使用结构化的规则语言（见 [`condition`]），注册和导入时校验。规则可以
+//! 导出为 JSON 并整体导入，[`DecisionEngine::explain`] 说明每条规则是否匹配及原因。
+//!
+//! 决策的置信度由反馈历史计算（见 [`learning`]），配置存储后学习记录持久化。
 
-use std::collections::HashMap;
+pub mod condition;
+pub mod learning;
+
+pub use condition::{Condition, DecisionContext, Evaluation, FieldPredicate, Pattern, TimeWindow};
+pub use learning::{Exploration, FeedbackStats, LearningStats};
+
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tracing::info;
+use uuid::Uuid;
+
+use crate::integrations::database::{DataRepository, FeedbackRecord};
+
+/// 规则匹配时的先验置信度
+pub const MATCHED_PRIOR: f32 = 0.8;
+
+/// 没有规则匹配时的先验置信度
+pub const DEFAULT_PRIOR: f32 = 0.5;
+
+/// 没有规则匹配时决策使用的规则ID
+pub const DEFAULT_RULE_ID: &str = "default";
+
+/// 决策引擎错误
+#[derive(Debug, Error)]
+pub enum DecisionError {
+    #[error("规则 {id} 无效: {reason}")]
+    InvalidRule { id: String, reason: String },
+    #[error("规则ID重复: {0}")]
+    DuplicateRule(String),
+    #[error("规则JSON无效: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("探索参数无效: {0}")]
+    InvalidExploration(String),
+    #[error("学习记录存储失败: {0}")]
+    Storage(String),
+}
 
 /// 决策类型
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,7 +60,8 @@ pub struct DecisionRule {
     pub id: String,
     pub name: String,
     pub description: String,
-    pub condition: String,  // 规则条件（简化为字符串）
+    #[serde(deserialize_with = "condition::deserialize_condition")]
+    pub condition: Condition,  // 规则条件
     pub action: String,     // 执行动作
     pub priority: u32,      // 优先级
     pub enabled: bool,      // 是否启用
@@ -37,21 +77,109 @@ pub struct DecisionResult {
     pub reasoning: String,
 }
 
+impl DecisionRule {
+    /// 检查规则ID、动作和条件结构
+    pub fn validate(&self) -> Result<(), DecisionError> {
+        let invalid = |reason: String| DecisionError::InvalidRule {
+            id: self.id.clone(),
+            reason,
+        };
+        if self.id.trim().is_empty() {
+            return Err(invalid("规则ID不能为空".to_string()));
+        }
+        if self.action.trim().is_empty() {
+            return Err(invalid("动作不能为空".to_string()));
+        }
+        self.condition.validate().map_err(invalid)
+    }
+}
+
+/// 单条规则的评估结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleEvaluation {
+    pub rule_id: String,
+    pub name: String,
+    pub action: String,
+    pub priority: u32,
+    pub enabled: bool,
+    pub matched: bool,
+    /// 匹配或不匹配的原因
+    pub reason: String,
+}
+
 /// 学习记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LearningRecord {
-    pub id: String,
+    pub id: Uuid,
+    /// 被评价的决策，例如回复消息的ID
     pub decision_id: String,
+    /// 做出决策的规则，非规则决策为空
+    pub rule_id: Option<String>,
+    /// 决策的动作
+    pub action: String,
     pub context: String,
     pub outcome: String,
     pub feedback: f32,  // 反馈分数 (-1.0 到 1.0)
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl LearningRecord {
+    pub fn new(
+        decision_id: impl Into<String>,
+        rule_id: Option<String>,
+        action: impl Into<String>,
+        context: impl Into<String>,
+        feedback: f32,
+    ) -> Self {
+        let feedback = feedback.clamp(-1.0, 1.0);
+        Self {
+            id: Uuid::new_v4(),
+            decision_id: decision_id.into(),
+            rule_id,
+            action: action.into(),
+            context: context.into(),
+            outcome: if feedback >= 0.0 { "positive" } else { "negative" }.to_string(),
+            feedback,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn to_record(&self) -> FeedbackRecord {
+        FeedbackRecord {
+            id: self.id,
+            decision_id: self.decision_id.clone(),
+            rule_id: self.rule_id.clone(),
+            action: self.action.clone(),
+            context: self.context.clone(),
+            outcome: self.outcome.clone(),
+            feedback: self.feedback,
+            created_at: self.timestamp,
+        }
+    }
+
+    fn from_record(record: FeedbackRecord) -> Self {
+        Self {
+            id: record.id,
+            decision_id: record.decision_id,
+            rule_id: record.rule_id,
+            action: record.action,
+            context: record.context,
+            outcome: record.outcome,
+            feedback: record.feedback,
+            timestamp: record.created_at,
+        }
+    }
+}
+
 /// 决策引擎
+///
+/// 规则的置信度由反馈历史计算，多条规则同时匹配时按 [`Exploration`] 选择。
 pub struct DecisionEngine {
     rules: Arc<RwLock<HashMap<String, DecisionRule>>>,
     learning_records: Arc<RwLock<Vec<LearningRecord>>>,
+    stats: Arc<RwLock<LearningStats>>,
+    exploration: Exploration,
+    store: RwLock<Option<Arc<dyn DataRepository>>>,
 }
 
 impl DecisionEngine {
@@ -60,61 +188,166 @@ impl DecisionEngine {
         Self {
             rules: Arc::new(RwLock::new(HashMap::new())),
             learning_records: Arc::new(RwLock::new(Vec::new())),
+            stats: Arc::new(RwLock::new(LearningStats::default())),
+            exploration: Exploration::Off,
+            store: RwLock::new(None),
         }
     }
 
-    /// 注册决策规则
-    pub async fn register_rule(&self, rule: DecisionRule) -> Result<(), String> {
+    /// 创建包含给定规则的决策引擎，规则需通过校验且ID不重复
+    pub fn with_rules(rules: Vec<DecisionRule>) -> Result<Self, DecisionError> {
+        Ok(Self {
+            rules: Arc::new(RwLock::new(validated(rules)?)),
+            ..Self::new()
+        })
+    }
+
+    /// 设置多条规则同时匹配时的选择方式
+    pub fn with_exploration(mut self, exploration: Exploration) -> Result<Self, DecisionError> {
+        exploration
+            .validate()
+            .map_err(DecisionError::InvalidExploration)?;
+        self.exploration = exploration;
+        Ok(self)
+    }
+
+    /// 将学习记录持久化到存储，并载入存储中已有的记录，返回载入的记录数
+    pub async fn use_store(&self, store: Arc<dyn DataRepository>) -> Result<usize, DecisionError> {
+        let records = store
+            .list_feedback()
+            .await
+            .map_err(|e| DecisionError::Storage(e.to_string()))?;
+        let count = records.len();
+        {
+            let mut learning_records = self.learning_records.write().await;
+            let mut stats = self.stats.write().await;
+            *learning_records = records.into_iter().map(LearningRecord::from_record).collect();
+            *stats = LearningStats::default();
+            for record in learning_records.iter() {
+                stats.record(record.rule_id.as_deref(), &record.action, record.feedback);
+            }
+        }
+        *self.store.write().await = Some(store);
+        Ok(count)
+    }
+
+    /// 注册决策规则，同ID的规则被替换
+    pub async fn register_rule(&self, rule: DecisionRule) -> Result<(), DecisionError> {
+        rule.validate()?;
         let mut rules = self.rules.write().await;
         rules.insert(rule.id.clone(), rule);
         Ok(())
     }
 
+    /// 删除规则，返回规则是否存在
+    pub async fn remove_rule(&self, id: &str) -> bool {
+        self.rules.write().await.remove(id).is_some()
+    }
+
     /// 基于上下文做出决策
     pub async fn make_decision(&self, context: &str, decision_type: DecisionType) -> DecisionResult {
-        info!("🧠 基于上下文做出决策: {}", context);
-        
-        // 简化的决策逻辑 - 实际实现中应该更复杂
+        self.decide(&DecisionContext::new(context), decision_type).await
+    }
+
+    /// 基于完整的决策上下文做出决策：选择优先级最高的匹配规则，优先级相同时按规则ID
+    pub async fn decide(&self, context: &DecisionContext, decision_type: DecisionType) -> DecisionResult {
+        info!("🧠 基于上下文做出决策: {}", context.text);
+
         let rules = self.rules.read().await;
-        
-        // 查找匹配的规则（简化实现）
-        let mut matched_rules: Vec<&DecisionRule> = rules
-            .values()
-            .filter(|rule| rule.enabled && self.matches_context(rule, context))
+        let enabled = sorted(rules.values()).into_iter().filter(|rule| rule.enabled);
+        let mut matched = Vec::new();
+        for rule in enabled {
+            let evaluation = rule.condition.evaluate(context);
+            if evaluation.matched {
+                matched.push((rule, evaluation));
+                // 不探索时只需要优先级最高的匹配规则
+                if self.exploration == Exploration::Off {
+                    break;
+                }
+            }
+        }
+
+        let stats = self.stats.read().await;
+        let candidates: Vec<(f32, FeedbackStats)> = matched
+            .iter()
+            .map(|(rule, _)| (MATCHED_PRIOR, stats.rule(&rule.id)))
             .collect();
-        
-        // 按优先级排序
-        matched_rules.sort_by(|a, b| b.priority.cmp(&a.priority));
-        
-        // 选择最高优先级的规则
-        if let Some(rule) = matched_rules.first() {
+
+        if let Some(index) = self.exploration.select(&candidates) {
+            let (rule, evaluation) = &matched[index];
+            let history = &candidates[index].1;
             info!("✅ 匹配到规则: {}", rule.name);
-            
+
             DecisionResult {
                 decision_type,
                 rule_id: rule.id.clone(),
                 action: rule.action.clone(),
-                confidence: 0.8, // 简化置信度
-                reasoning: format!("匹配到规则 '{}': {}", rule.name, rule.description),
+                confidence: history.confidence(MATCHED_PRIOR),
+                reasoning: format!(
+                    "匹配到规则 '{}': {}（{}）{}",
+                    rule.name,
+                    rule.description,
+                    evaluation.reason,
+                    describe_history(history)
+                ),
             }
         } else {
             // 默认决策
             info!("🔄 使用默认决策");
-            
+            let history = stats.rule(DEFAULT_RULE_ID);
+
             DecisionResult {
                 decision_type: decision_type.clone(),
-                rule_id: "default".to_string(),
+                rule_id: DEFAULT_RULE_ID.to_string(),
                 action: self.get_default_action(&decision_type),
-                confidence: 0.5,
-                reasoning: "使用默认决策规则".to_string(),
+                confidence: history.confidence(DEFAULT_PRIOR),
+                reasoning: format!("使用默认决策规则{}", describe_history(&history)),
             }
         }
     }
 
-    /// 检查规则是否匹配上下文（简化实现）
-    fn matches_context(&self, rule: &DecisionRule, context: &str) -> bool {
-        // 简单的包含检查
-        context.contains(&rule.condition) || rule.condition == "*"
+    /// 按决策顺序评估每条规则，说明是否匹配及原因
+    pub async fn explain(&self, context: &DecisionContext) -> Vec<RuleEvaluation> {
+        let rules = self.rules.read().await;
+        sorted(rules.values())
+            .into_iter()
+            .map(|rule| {
+                let evaluation = rule.condition.evaluate(context);
+                RuleEvaluation {
+                    rule_id: rule.id.clone(),
+                    name: rule.name.clone(),
+                    action: rule.action.clone(),
+                    priority: rule.priority,
+                    enabled: rule.enabled,
+                    matched: evaluation.matched,
+                    reason: if rule.enabled {
+                        evaluation.reason
+                    } else {
+                        format!("规则已禁用（{}）", evaluation.reason)
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// 导出全部规则为 JSON 数组，按决策顺序排列
+    pub async fn export_rules(&self) -> Result<String, DecisionError> {
+        let rules = self.rules.read().await;
+        Ok(serde_json::to_string_pretty(&sorted(rules.values()))?)
+    }
+
+    /// 从 JSON 数组导入规则并替换现有规则；任一规则无效时不做任何修改
+    pub async fn import_rules(&self, json: &str) -> Result<usize, DecisionError> {
+        let imported: Vec<DecisionRule> = serde_json::from_str(json)?;
+        self.replace_rules(imported).await
+    }
+
+    /// 校验并替换全部规则，返回规则数量
+    pub async fn replace_rules(&self, rules: Vec<DecisionRule>) -> Result<usize, DecisionError> {
+        let rules = validated(rules)?;
+        let count = rules.len();
+        *self.rules.write().await = rules;
+        Ok(count)
     }
 
     /// 获取默认动作
@@ -127,26 +360,43 @@ impl DecisionEngine {
         }
     }
 
-    /// 记录学习反馈
-    pub async fn record_learning(&self, decision_id: &str, context: &str, outcome: &str, feedback: f32) {
-        let record = LearningRecord {
-            id: uuid::Uuid::new_v4().to_string(),
-            decision_id: decision_id.to_string(),
-            context: context.to_string(),
-            outcome: outcome.to_string(),
-            feedback,
-            timestamp: chrono::Utc::now(),
-        };
-        
-        let mut learning_records = self.learning_records.write().await;
-        learning_records.push(record);
-        info!("📝 记录学习反馈，ID: {}", decision_id);
+    /// 记录学习反馈，更新规则和动作的统计；配置存储时先写入存储
+    pub async fn record_learning(&self, record: LearningRecord) -> Result<(), DecisionError> {
+        if let Some(store) = self.store.read().await.as_ref() {
+            store
+                .save_feedback(&record.to_record())
+                .await
+                .map_err(|e| DecisionError::Storage(e.to_string()))?;
+        }
+
+        self.stats
+            .write()
+            .await
+            .record(record.rule_id.as_deref(), &record.action, record.feedback);
+        info!("📝 记录学习反馈，ID: {} ({:+.1})", record.decision_id, record.feedback);
+        self.learning_records.write().await.push(record);
+        Ok(())
+    }
+
+    /// 按规则ID和动作汇总的反馈统计
+    pub async fn learning_stats(&self) -> LearningStats {
+        self.stats.read().await.clone()
     }
 
-    /// 获取所有规则
+    /// 以 `prior` 为先验，按动作的反馈历史计算置信度
+    pub async fn action_confidence(&self, action: &str, prior: f32) -> f32 {
+        self.stats.read().await.action(action).confidence(prior)
+    }
+
+    /// 按ID获取规则
+    pub async fn get_rule(&self, id: &str) -> Option<DecisionRule> {
+        self.rules.read().await.get(id).cloned()
+    }
+
+    /// 获取所有规则，按决策顺序排列
     pub async fn get_all_rules(&self) -> Vec<DecisionRule> {
         let rules = self.rules.read().await;
-        rules.values().cloned().collect()
+        sorted(rules.values()).into_iter().cloned().collect()
     }
 
     /// 获取学习记录
@@ -156,6 +406,33 @@ impl DecisionEngine {
     }
 }
 
+/// 说明决策所依据的反馈历史
+fn describe_history(stats: &FeedbackStats) -> String {
+    match stats.success_rate() {
+        Some(rate) => format!("，历史成功率 {:.0}%（{} 条反馈）", rate * 100.0, stats.count),
+        None => String::new(),
+    }
+}
+
+/// 按决策顺序排列规则：优先级从高到低，相同时按ID
+fn sorted<'a>(rules: impl Iterator<Item = &'a DecisionRule>) -> Vec<&'a DecisionRule> {
+    let mut rules: Vec<&DecisionRule> = rules.collect();
+    rules.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+    rules
+}
+
+/// 校验规则并按ID建立索引
+fn validated(rules: Vec<DecisionRule>) -> Result<HashMap<String, DecisionRule>, DecisionError> {
+    let mut ids = HashSet::new();
+    for rule in &rules {
+        rule.validate()?;
+        if !ids.insert(rule.id.as_str()) {
+            return Err(DecisionError::DuplicateRule(rule.id.clone()));
+        }
+    }
+    Ok(rules.into_iter().map(|rule| (rule.id.clone(), rule)).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,7 +452,7 @@ mod tests {
             id: "test_rule".to_string(),
             name: "测试规则".to_string(),
             description: "用于测试的规则".to_string(),
-            condition: "*".to_string(),
+            condition: "*".into(),
             action: "test_action".to_string(),
             priority: 100,
             enabled: true,
@@ -193,7 +470,7 @@ mod tests {
             id: "weather_rule".to_string(),
             name: "天气查询规则".to_string(),
             description: "处理天气查询请求".to_string(),
-            condition: "天气".to_string(),
+            condition: "天气".into(),
             action: "route_to_weather_agent".to_string(),
             priority: 100,
             enabled: true,
@@ -205,4 +482,120 @@ mod tests {
         assert_eq!(decision.rule_id, "weather_rule");
         assert_eq!(decision.action, "route_to_weather_agent");
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_import_export_and_explain() {
+        let engine = DecisionEngine::new();
+        let imported = engine
+            .import_rules(
+                r#"[
+                    {"id": "legacy", "name": "旧规则", "description": "字符串条件", "condition": "天气",
+                     "action": "a2a:info_agent", "priority": 10, "enabled": true},
+                    {"id": "vip", "name": "VIP", "description": "VIP用户的发票请求",
+                     "condition": {"all": [{"regex": "(?i)invoice|发票"}, {"field": {"path": "tier", "equals": "vip"}}]},
+                     "action": "mcp:create_invoice", "priority": 100, "enabled": true},
+                    {"id": "off", "name": "停用", "description": "已停用", "condition": "*",
+                     "action": "llm", "priority": 200, "enabled": false}
+                ]"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(imported, 3);
+
+        let context = DecisionContext::new("明天天气如何，顺便开张发票")
+            .with_metadata(serde_json::json!({"tier": "vip"}));
+        let explanation = engine.explain(&context).await;
+        let ids: Vec<&str> = explanation.iter().map(|rule| rule.rule_id.as_str()).collect();
+        assert_eq!(ids, ["off", "vip", "legacy"]);
+        assert!(explanation.iter().all(|rule| rule.matched));
+        assert!(explanation[0].reason.starts_with("规则已禁用"));
+        assert!(explanation[1].reason.contains("tier"));
+        let decision = engine.decide(&context, DecisionType::Route).await;
+        assert_eq!(decision.rule_id, "vip");
+
+        let exported = engine.export_rules().await.unwrap();
+        let other = DecisionEngine::new();
+        other.import_rules(&exported).await.unwrap();
+        assert_eq!(other.export_rules().await.unwrap(), exported);
+
+        // 无效的导入不修改现有规则
+        for invalid in [
+            r#"[{"id": "a", "name": "", "description": "", "condition": {"any": []}, "action": "llm", "priority": 1, "enabled": true}]"#,
+            r#"[{"id": "a", "name": "", "description": "", "condition": {"regex": "("}, "action": "llm", "priority": 1, "enabled": true}]"#,
+            r#"[{"id": "a", "name": "", "description": "", "condition": "*", "action": "llm", "priority": 1, "enabled": true},
+                {"id": "a", "name": "", "description": "", "condition": "*", "action": "llm", "priority": 2, "enabled": true}]"#,
+        ] {
+            assert!(engine.import_rules(invalid).await.is_err());
+        }
+        assert_eq!(engine.get_all_rules().await.len(), 3);
+
+        let mut rule = engine.get_rule("legacy").await.unwrap();
+        rule.condition = Condition::All(Vec::new());
+        assert!(matches!(
+            engine.register_rule(rule).await,
+            Err(DecisionError::InvalidRule { .. })
+        ));
+        assert!(engine.remove_rule("legacy").await);
+        assert!(!engine.remove_rule("legacy").await);
+    }
+
+    #[tokio::test]
+    async fn test_feedback_adjusts_confidence_and_persists() {
+        let rule = |id: &str, priority| DecisionRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            condition: "天气".into(),
+            action: format!("a2a:{id}"),
+            priority,
+            enabled: true,
+        };
+        let store = Arc::new(
+            crate::integrations::database::SqliteRepository::connect("sqlite::memory:")
+                .await
+                .unwrap(),
+        );
+        let engine = DecisionEngine::with_rules(vec![rule("primary", 100), rule("backup", 50)])
+            .unwrap()
+            .with_exploration(Exploration::EpsilonGreedy { epsilon: 0.0 })
+            .unwrap();
+        assert_eq!(engine.use_store(store.clone()).await.unwrap(), 0);
+
+        let context = DecisionContext::new("查询天气");
+        let decision = engine.decide(&context, DecisionType::Route).await;
+        assert_eq!(decision.rule_id, "primary");
+        assert_eq!(decision.confidence, MATCHED_PRIOR);
+
+        // 差评降低置信度，另一条匹配的规则胜出
+        for i in 0..3 {
+            let record = LearningRecord::new(
+                format!("message-{i}"),
+                Some("primary".to_string()),
+                "a2a:primary",
+                "查询天气",
+                -1.0,
+            );
+            engine.record_learning(record).await.unwrap();
+        }
+        let decision = engine.decide(&context, DecisionType::Route).await;
+        assert_eq!(decision.rule_id, "backup");
+        assert_eq!(decision.confidence, MATCHED_PRIOR);
+        let stats = engine.learning_stats().await;
+        assert_eq!(stats.rule("primary").negative, 3);
+        assert_eq!(stats.action("a2a:primary").count, 3);
+        assert!(engine.action_confidence("a2a:primary", MATCHED_PRIOR).await < 0.6);
+
+        // 不探索时仍按优先级选择，但置信度来自反馈历史
+        let reloaded = DecisionEngine::with_rules(vec![rule("primary", 100), rule("backup", 50)]).unwrap();
+        assert_eq!(reloaded.use_store(store).await.unwrap(), 3);
+        assert_eq!(reloaded.get_learning_records().await.len(), 3);
+        let decision = reloaded.decide(&context, DecisionType::Route).await;
+        assert_eq!(decision.rule_id, "primary");
+        assert!(decision.confidence < 0.6);
+        assert!(decision.reasoning.contains("历史成功率 0%"));
+
+        assert!(DecisionEngine::new()
+            .with_exploration(Exploration::EpsilonGreedy { epsilon: 2.0 })
+            .is_err());
+    }
+}
@@ -1,16 +1,43 @@
 //! 智能体编排引擎模块
+//!
+//! 路由决策的目标交给真实后端执行：本地 LLM 使用 [`LLMService`]，MCP 工具通过
+//! [`McpManager`] 调用（参数由 LLM 从消息中提取），A2A 智能体通过 [`A2AClient`]
+//! 发送消息。对话缓冲区中的消息作为 LLM 上下文，已注册但被禁用的能力不会被调用。
+//!
+//! [`orchestrate`](OrchestrationEngine::orchestrate) 把一条消息作为单个任务执行；
+//! 规划模式（见 [`planner`]）把目标拆分为带依赖关系的多个子任务。
+//!
+//! LLM 调用、工具调用和 A2A 委托都写入审计日志，未设置审计日志时只保存在内存中。
+
+pub mod planner;
+
+pub use planner::{Plan, PlanError, PlanStatus, PlanTarget, PlannedTask};
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use serde_json::{json, Value};
+use tracing::{info, warn};
 
+use crate::a2a::client::{A2AClient, A2AContent, A2AMessage};
 use crate::core::{
     router::{IntelligentRouter, RouteTarget},
-    state::StateManager,
-    capabilities::CapabilityManager,
+    state::{BufferedMessage, MessageType, StateManager},
+    capabilities::{Capability, CapabilityManager},
 };
+use crate::llm::{LLMConfig, LLMService};
+use crate::llm::providers::ProviderConfig;
+use crate::mcp::manager::McpManager;
+use crate::protocol::manifest::MCPTool;
+use crate::protocol::message::{Message, MessageContent};
+use crate::services::audit::AuditTrail;
+
+/// 编排引擎发送 A2A 消息时使用的发送方名称
+const ORCHESTRATOR_NAME: &str = "orchestrator";
 
 /// 编排任务状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +46,7 @@ pub enum OrchestrationTaskStatus {
     Running,
     Completed,
     Failed,
+    Canceled,
 }
 
 /// 编排任务
@@ -31,6 +59,27 @@ pub struct OrchestrationTask {
     pub status: OrchestrationTaskStatus,
     pub dependencies: Vec<String>,
     pub result: Option<String>,
+    /// 失败、超时或取消的原因
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// 从开始执行到结束的耗时（毫秒）
+    pub duration_ms: Option<u64>,
+}
+
+impl OrchestrationTask {
+    /// 记录任务结束时的状态、结果和耗时
+    fn finish(&mut self, status: OrchestrationTaskStatus, result: Option<String>, error: Option<String>) {
+        let now = Utc::now();
+        self.status = status;
+        self.result = result;
+        self.error = error;
+        self.finished_at = Some(now);
+        self.duration_ms = self
+            .started_at
+            .map(|started| (now - started).num_milliseconds().max(0) as u64);
+    }
 }
 
 /// 智能体编排引擎
@@ -39,10 +88,27 @@ pub struct OrchestrationEngine {
     state_manager: Arc<StateManager>,
     capability_manager: Arc<CapabilityManager>,
     tasks: Arc<RwLock<HashMap<String, OrchestrationTask>>>,
+    /// 运行中任务的取消令牌
+    cancellations: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    /// 单个任务的执行期限
+    task_timeout: Option<Duration>,
+    /// 本地 LLM 目标使用的服务，也用于提取 MCP 工具参数
+    llm: LLMService,
+    mcp: Option<Arc<McpManager>>,
+    a2a_clients: HashMap<String, A2AClient>,
+    /// 规划模式生成的计划
+    plans: Arc<RwLock<HashMap<String, Plan>>>,
+    audit: Arc<AuditTrail>,
+}
+
+/// 审计记录中的调用方和关联的任务
+struct AuditScope<'a> {
+    user_id: &'a str,
+    scope: Value,
 }
 
 impl OrchestrationEngine {
-    /// 创建新的编排引擎
+    /// 创建新的编排引擎，默认使用模拟 LLM，未配置 MCP 和 A2A 后端
     pub fn new(
         router: Arc<IntelligentRouter>,
         state_manager: Arc<StateManager>,
@@ -53,11 +119,74 @@ impl OrchestrationEngine {
             state_manager,
             capability_manager,
             tasks: Arc::new(RwLock::new(HashMap::new())),
+            cancellations: Arc::new(RwLock::new(HashMap::new())),
+            task_timeout: None,
+            llm: LLMService::new(
+                LLMConfig::default(),
+                ProviderConfig {
+                    openai: None,
+                    claude: None,
+                    google: None,
+                },
+            ),
+            mcp: None,
+            a2a_clients: HashMap::new(),
+            plans: Arc::new(RwLock::new(HashMap::new())),
+            audit: Arc::new(AuditTrail::default()),
         }
     }
 
+    /// 设置审计日志，通常与智能体共用
+    pub fn with_audit(mut self, audit: Arc<AuditTrail>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// 设置单个任务的执行期限，超时的任务标记为失败
+    pub fn with_task_timeout(mut self, timeout: Duration) -> Self {
+        self.task_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置本地 LLM 服务
+    pub fn with_llm(mut self, llm: LLMService) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// 设置 MCP 管理器，服务器应已连接
+    pub fn with_mcp_manager(mut self, manager: Arc<McpManager>) -> Self {
+        self.mcp = Some(manager);
+        self
+    }
+
+    /// 添加 A2A 智能体客户端，`name` 与路由目标中的智能体名称对应
+    pub fn with_a2a_client(mut self, name: impl Into<String>, client: A2AClient) -> Self {
+        self.a2a_clients.insert(name.into(), client);
+        self
+    }
+
     /// 编排智能体执行任务
     pub async fn orchestrate(&self, message: &str) -> Result<String, String> {
+        self.orchestrate_with_cancel(message, CancellationToken::new()).await
+    }
+
+    /// 编排智能体执行任务，`cancel` 触发时任务被取消
+    pub async fn orchestrate_with_cancel(
+        &self,
+        message: &str,
+        cancel: CancellationToken,
+    ) -> Result<String, String> {
+        self.orchestrate_as("", message, cancel).await
+    }
+
+    /// 代表用户 `user_id` 编排任务，审计记录归属于该用户
+    pub async fn orchestrate_as(
+        &self,
+        user_id: &str,
+        message: &str,
+        cancel: CancellationToken,
+    ) -> Result<String, String> {
         info!("🤖 开始编排任务: {}", message);
         
         // 1. 使用路由器分析消息并决定处理方式
@@ -74,61 +203,365 @@ impl OrchestrationEngine {
             status: OrchestrationTaskStatus::Pending,
             dependencies: vec![],
             result: None,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
         };
         
-        // 3. 注册任务
+        // 3. 注册任务及其取消令牌
         {
             let mut tasks = self.tasks.write().await;
             tasks.insert(task_id.clone(), task);
         }
+        self.cancellations
+            .write()
+            .await
+            .insert(task_id.clone(), cancel.clone());
         
-        // 4. 执行任务
-        let result = self.execute_task(&task_id, message).await?;
+        // 4. 召回的记忆和之前的对话作为上下文，再把本条消息写入对话缓冲区
+        let history = self.history(message).await;
+        self.remember(MessageType::UserMessage, message).await;
+
+        // 5. 执行任务，直到完成、被取消或超时
+        let audit = AuditScope {
+            user_id,
+            scope: json!({ "orchestration_task_id": task_id }),
+        };
+        let result = self
+            .run_with_cancel(&task_id, message, &history, &audit, &cancel)
+            .await;
+        self.cancellations.write().await.remove(&task_id);
         
-        Ok(result)
+        result
     }
 
-    /// 执行特定任务
-    async fn execute_task(&self, task_id: &str, message: &str) -> Result<String, String> {
-        // 更新任务状态为运行中
+    /// 取消运行中的任务，返回任务是否处于可取消状态
+    pub async fn cancel_task(&self, task_id: &str) -> bool {
         {
             let mut tasks = self.tasks.write().await;
-            if let Some(task) = tasks.get_mut(task_id) {
-                task.status = OrchestrationTaskStatus::Running;
+            match tasks.get_mut(task_id) {
+                Some(task)
+                    if matches!(
+                        task.status,
+                        OrchestrationTaskStatus::Pending | OrchestrationTaskStatus::Running
+                    ) =>
+                {
+                    task.finish(OrchestrationTaskStatus::Canceled, None, Some("任务已取消".to_string()));
+                }
+                _ => return false,
             }
         }
-        
-        // 获取任务目标并执行
-        let result = {
-            let tasks = self.tasks.read().await;
-            if let Some(task) = tasks.get(task_id) {
-                match &task.target {
-                    RouteTarget::LocalLLM => {
-                        // 这里应该调用LLM服务，但暂时返回模拟响应
-                        format!("LLM处理结果: {}", message)
-                    },
-                    RouteTarget::A2AAgent(agent_name) => {
-                        format!("A2A智能体 {} 处理结果", agent_name)
-                    },
-                    RouteTarget::MCPTool(tool_name) => {
-                        format!("MCP工具 {} 执行结果", tool_name)
-                    },
-                }
-            } else {
-                return Err("任务未找到".to_string());
+
+        if let Some(token) = self.cancellations.read().await.get(task_id) {
+            token.cancel();
+        }
+        info!("🛑 任务已取消: {}", task_id);
+        true
+    }
+
+    /// 在取消令牌和执行期限的约束下执行任务
+    async fn run_with_cancel(
+        &self,
+        task_id: &str,
+        message: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        let token = cancel.child_token();
+        let execution = self.execute_task(task_id, message, history, audit, &token);
+        match run_until_deadline(self.task_timeout, &token, execution).await {
+            Some(result) => result,
+            None => {
+                warn!("⌛ 任务超时: {}", task_id);
+                let error = format!("任务超时: {task_id}");
+                self.finish_task(task_id, OrchestrationTaskStatus::Failed, None, Some(error.clone())).await;
+                Err(error)
             }
+        }
+    }
+
+    /// 把任务标记为已取消，保留期限到达等已经记录的结束状态
+    fn mark_canceled(task: &mut OrchestrationTask) {
+        if matches!(
+            task.status,
+            OrchestrationTaskStatus::Pending | OrchestrationTaskStatus::Running
+        ) {
+            task.finish(OrchestrationTaskStatus::Canceled, None, Some("任务已取消".to_string()));
+        }
+    }
+
+    async fn finish_task(
+        &self,
+        task_id: &str,
+        status: OrchestrationTaskStatus,
+        result: Option<String>,
+        error: Option<String>,
+    ) {
+        if let Some(task) = self.tasks.write().await.get_mut(task_id) {
+            task.finish(status, result, error);
+        }
+    }
+
+    /// 执行特定任务，`cancel` 触发时停止执行并把任务标记为已取消
+    async fn execute_task(
+        &self,
+        task_id: &str,
+        message: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        // 更新任务状态为运行中并取得目标
+        let target = {
+            let mut tasks = self.tasks.write().await;
+            let task = tasks.get_mut(task_id).ok_or_else(|| "任务未找到".to_string())?;
+            if cancel.is_cancelled() {
+                Self::mark_canceled(task);
+                return Err(format!("任务已取消: {task_id}"));
+            }
+            task.status = OrchestrationTaskStatus::Running;
+            task.started_at = Some(Utc::now());
+            task.target.clone()
         };
         
-        // 更新任务状态为完成并保存结果
+        let result = self.run_target(&target, message, history, audit, cancel).await;
+
+        // 记录结果或错误，已取消的任务保持取消状态
         {
             let mut tasks = self.tasks.write().await;
             if let Some(task) = tasks.get_mut(task_id) {
-                task.status = OrchestrationTaskStatus::Completed;
-                task.result = Some(result.clone());
+                if cancel.is_cancelled() || matches!(task.status, OrchestrationTaskStatus::Canceled) {
+                    Self::mark_canceled(task);
+                    return Err(format!("任务已取消: {task_id}"));
+                }
+                match &result {
+                    Ok(output) => task.finish(OrchestrationTaskStatus::Completed, Some(output.clone()), None),
+                    Err(error) => {
+                        warn!("❌ 任务 {} 失败: {}", task_id, error);
+                        task.finish(OrchestrationTaskStatus::Failed, None, Some(error.clone()));
+                    }
+                }
             }
         }
         
-        Ok(result)
+        if let Ok(output) = &result {
+            let message_type = match target {
+                RouteTarget::LocalLLM => MessageType::LLMResponse,
+                _ => MessageType::ToolResponse,
+            };
+            self.remember(message_type, output).await;
+        }
+        result
+    }
+
+    /// 把消息交给目标后端执行；`cancel` 触发时进行中的 MCP 工具和 A2A 调用
+    /// 会通知对方取消
+    async fn run_target(
+        &self,
+        target: &RouteTarget,
+        message: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        match target {
+            RouteTarget::LocalLLM => self.call_llm(message, history, audit, cancel).await,
+            RouteTarget::MCPTool(tool_name) => {
+                self.call_tool(tool_name, message, history, audit, cancel).await
+            }
+            RouteTarget::A2AAgent(agent_name) => self.call_agent(agent_name, message, audit, cancel).await,
+        }
+    }
+
+    async fn call_llm(
+        &self,
+        message: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        let reply = self.process_llm(message, history, audit, cancel).await?;
+        Ok(content_text(reply.content))
+    }
+
+    /// 调用 LLM 并写入审计日志，`cancel` 触发时放弃调用
+    async fn process_llm(
+        &self,
+        input: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<Message, String> {
+        let reply = tokio::select! {
+            reply = self.llm.process_message(input, history) => reply,
+            _ = cancel.cancelled() => return Err("LLM 调用已取消".to_string()),
+        };
+        self.audit
+            .llm_call(audit.user_id, &self.llm.config, input, history, &reply, audit.scope.clone())
+            .await;
+        reply
+    }
+
+    /// 由 LLM 从消息中提取参数后调用 MCP 工具
+    async fn call_tool(
+        &self,
+        tool_name: &str,
+        message: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        let capability = self.enabled_capability(tool_name).await?;
+        let mcp = self.mcp.as_ref().ok_or_else(|| "MCP 管理器未配置".to_string())?;
+        let (server_id, tool) = mcp
+            .find_tool(tool_name)
+            .ok_or_else(|| format!("没有 MCP 服务器提供工具: {tool_name}"))?;
+
+        let arguments = self
+            .extract_arguments(tool, capability.as_ref(), message, history, audit, cancel)
+            .await?;
+        info!("🔧 调用 MCP 工具 {}/{}: {}", server_id, tool_name, arguments);
+        let result = mcp
+            .call_tool_with_cancel(server_id, tool_name, arguments.clone(), cancel)
+            .await
+            .map_err(|e| format!("MCP 工具 {tool_name} 调用失败: {e}"));
+        self.audit
+            .tool_call(
+                audit.user_id,
+                tool_name,
+                "mcp",
+                &arguments,
+                &result,
+                audit.scope.clone(),
+            )
+            .await;
+        Ok(value_text(result?))
+    }
+
+    async fn extract_arguments(
+        &self,
+        tool: &MCPTool,
+        capability: Option<&Capability>,
+        message: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<Value, String> {
+        let description = match capability {
+            Some(capability) if tool.description.is_empty() => capability.description.as_str(),
+            _ => tool.description.as_str(),
+        };
+        let prompt = format!(
+            "Extract the arguments for calling the tool `{}` from the request below.\n\
+             Tool description: {}\n\
+             Input JSON schema: {}\n\
+             Request: {}\n\
+             Reply with a single JSON object and nothing else.",
+            tool.name, description, tool.input_schema, message
+        );
+        let reply = self.process_llm(&prompt, history, audit, cancel).await?;
+        match reply.content {
+            MessageContent::ToolCall { parameters, .. } if parameters.is_object() => Ok(parameters),
+            MessageContent::Text { text } => parse_json_object(&text)
+                .ok_or_else(|| format!("无法从 LLM 回复中解析工具 {} 的参数: {}", tool.name, text)),
+            other => Err(format!("无法从 LLM 回复中解析工具 {} 的参数: {:?}", tool.name, other)),
+        }
+    }
+
+    async fn call_agent(
+        &self,
+        agent_name: &str,
+        message: &str,
+        audit: &AuditScope<'_>,
+        cancel: &CancellationToken,
+    ) -> Result<String, String> {
+        self.enabled_capability(agent_name).await?;
+        let client = self
+            .a2a_clients
+            .get(agent_name)
+            .ok_or_else(|| format!("A2A 智能体未配置: {agent_name}"))?;
+        let request = A2AMessage {
+            id: uuid::Uuid::new_v4(),
+            sender: ORCHESTRATOR_NAME.to_string(),
+            recipient: agent_name.to_string(),
+            content: A2AContent::Text {
+                text: message.to_string(),
+            },
+            timestamp: Utc::now(),
+        };
+
+        let request_json = serde_json::to_value(&request).unwrap_or(Value::Null);
+        let reply = client
+            .send_message_with_cancel(request, cancel)
+            .await
+            .map_err(|e| format!("A2A 智能体 {agent_name} 调用失败: {e}"));
+        self.audit
+            .delegation(audit.user_id, agent_name, &request_json, &reply, audit.scope.clone())
+            .await;
+        let reply = reply?;
+        match reply.content {
+            A2AContent::Text { text } => Ok(text),
+            A2AContent::Response { data, .. } => Ok(value_text(data)),
+            A2AContent::Error { code, message } => Err(format!("A2A 智能体 {agent_name} 返回错误 {code}: {message}")),
+            task @ A2AContent::Task { .. } => serde_json::to_string(&task).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// 查找目标对应的能力，已注册但被禁用的能力不能调用
+    async fn enabled_capability(&self, id: &str) -> Result<Option<Capability>, String> {
+        match self.capability_manager.get_capability(id).await {
+            Some(capability) if !capability.enabled => Err(format!("能力已禁用: {id}")),
+            capability => Ok(capability),
+        }
+    }
+
+    /// 回答 `query` 的 LLM 上下文：召回的长期记忆和中期摘要合并为一条系统消息，
+    /// 之后是对话缓冲区中的消息
+    async fn history(&self, query: &str) -> Vec<Message> {
+        let memory = self.state_manager.turn_memory(query).await;
+        let mut history = Vec::with_capacity(memory.recent.len() + 1);
+
+        if let Some(text) = memory.context() {
+            history.push(Message::new(
+                "system".to_string(),
+                ORCHESTRATOR_NAME.to_string(),
+                MessageContent::Text { text },
+                Some(json!({ "role": "system" })),
+            ));
+        }
+
+        history.extend(memory.recent.into_iter().map(|buffered| {
+            let role = match buffered.message_type {
+                MessageType::UserMessage => "user",
+                MessageType::SystemMessage => "system",
+                MessageType::LLMResponse | MessageType::ToolResponse => "assistant",
+            };
+            Message::new(
+                role.to_string(),
+                ORCHESTRATOR_NAME.to_string(),
+                MessageContent::Text {
+                    text: buffered.content,
+                },
+                Some(json!({ "role": role })),
+            )
+        }));
+        history
+    }
+
+    async fn remember(&self, message_type: MessageType, content: &str) {
+        let message = BufferedMessage {
+            id: uuid::Uuid::new_v4(),
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            message_type,
+            context_relevance: 1.0,
+        };
+        if let Err(e) = self.state_manager.add_to_buffer(message).await {
+            warn!("⚠️  写入对话缓冲区失败: {}", e);
+        }
     }
 
     /// 获取所有任务
@@ -137,6 +570,12 @@ impl OrchestrationEngine {
         tasks.values().cloned().collect()
     }
 
+    /// 获取任务
+    pub async fn get_task(&self, task_id: &str) -> Option<OrchestrationTask> {
+        let tasks = self.tasks.read().await;
+        tasks.get(task_id).cloned()
+    }
+
     /// 获取任务状态
     pub async fn get_task_status(&self, task_id: &str) -> Option<OrchestrationTaskStatus> {
         let tasks = self.tasks.read().await;
@@ -144,9 +583,52 @@ impl OrchestrationEngine {
     }
 }
 
+/// 在期限内执行 `run`，期限到达时返回 `None`。到期后先触发 `token` 再等待
+/// `run` 结束，让进行中的 MCP 工具和 A2A 调用通知对方取消
+async fn run_until_deadline<T>(
+    timeout: Option<Duration>,
+    token: &CancellationToken,
+    run: impl std::future::Future<Output = T>,
+) -> Option<T> {
+    let Some(timeout) = timeout else {
+        return Some(run.await);
+    };
+    tokio::pin!(run);
+    if let Ok(result) = tokio::time::timeout(timeout, &mut run).await {
+        return Some(result);
+    }
+    token.cancel();
+    run.await;
+    None
+}
+
+fn content_text(content: MessageContent) -> String {
+    match content {
+        MessageContent::Text { text } => text,
+        other => serde_json::to_string(&other).unwrap_or_default(),
+    }
+}
+
+fn value_text(value: Value) -> String {
+    match value {
+        Value::String(text) => text,
+        other => other.to_string(),
+    }
+}
+
+/// 解析 LLM 回复中的 JSON 对象，允许外层有代码块或说明文字
+pub(crate) fn parse_json_object(text: &str) -> Option<Value> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str::<Value>(text.get(start..=end)?)
+        .ok()
+        .filter(Value::is_object)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::decision::DecisionRule;
 
     #[tokio::test]
     async fn test_orchestration_engine_creation() {
@@ -164,10 +646,107 @@ mod tests {
         let state_manager = Arc::new(StateManager::new());
         let capability_manager = Arc::new(CapabilityManager::new());
         
-        let engine = OrchestrationEngine::new(router, state_manager, capability_manager);
+        let engine = OrchestrationEngine::new(router, state_manager.clone(), capability_manager);
         let result = engine.orchestrate("测试消息").await;
         
-        assert!(result.is_ok());
-        assert_eq!(engine.get_all_tasks().await.len(), 1);
+        assert!(result.unwrap().contains("测试消息"));
+        let tasks = engine.get_all_tasks().await;
+        assert_eq!(tasks.len(), 1);
+        assert!(matches!(tasks[0].status, OrchestrationTaskStatus::Completed));
+        assert!(tasks[0].started_at.is_some() && tasks[0].duration_ms.is_some());
+        // 消息和回复都写入对话缓冲区
+        assert_eq!(state_manager.buffer_size(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_recalled_memories_are_injected_into_context() {
+        use crate::core::state::{MemoryConfig, TieredMemory};
+
+        let router = Arc::new(IntelligentRouter::new());
+        let state_manager = Arc::new(StateManager::with_memory(TieredMemory::new(MemoryConfig {
+            short_term_capacity: 2,
+            summary_batch: 2,
+            medium_term_capacity: 1,
+            ..MemoryConfig::default()
+        })));
+        let capability_manager = Arc::new(CapabilityManager::new());
+        let engine = OrchestrationEngine::new(router, state_manager, capability_manager);
+
+        for message in ["我的猫叫 Mochi", "你好", "讲个笑话", "谢谢"] {
+            engine.orchestrate(message).await.unwrap();
+        }
+
+        let history = engine.history("我的猫 Mochi 喜欢吃什么").await;
+        assert_eq!(history.len(), 3);
+        let MessageContent::Text { text } = &history[0].content else {
+            panic!("expected text");
+        };
+        assert!(text.starts_with("相关的长期记忆"));
+        assert!(text.contains("Mochi"));
+        assert!(text.contains("较早对话的摘要"));
+        assert_eq!(history[0].sender, "system");
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_targets_fail_with_error() {
+        let router = Arc::new(IntelligentRouter::new());
+        let rules = [("天气", "a2a:info_agent"), ("文件", "mcp:file_processor")]
+            .into_iter()
+            .map(|(keyword, action)| DecisionRule {
+                id: keyword.to_string(),
+                name: keyword.to_string(),
+                description: String::new(),
+                condition: keyword.into(),
+                action: action.to_string(),
+                priority: 100,
+                enabled: true,
+            })
+            .collect();
+        router.decision_engine().replace_rules(rules).await.unwrap();
+        let state_manager = Arc::new(StateManager::new());
+        let capability_manager = Arc::new(CapabilityManager::new());
+        let mut capability = Capability::new("info_agent", "信息查询", "天气和时间", "a2a");
+        capability.enabled = false;
+        capability_manager.register_capability(capability).await.unwrap();
+
+        let engine = OrchestrationEngine::new(router, state_manager, capability_manager);
+        let disabled = engine.orchestrate("今天天气怎么样").await;
+        assert_eq!(disabled.unwrap_err(), "能力已禁用: info_agent");
+        let missing = engine.orchestrate("计算文件大小").await;
+        assert!(missing.unwrap_err().contains("MCP 管理器未配置"));
+
+        for task in engine.get_all_tasks().await {
+            assert!(matches!(task.status, OrchestrationTaskStatus::Failed));
+            assert!(task.error.is_some() && task.finished_at.is_some());
+            assert!(task.result.is_none());
+        }
+    }
+
+    #[test]
+    fn test_parse_json_object() {
+        assert_eq!(
+            parse_json_object("```json\n{\"city\": \"Paris\"}\n```"),
+            Some(json!({"city": "Paris"}))
+        );
+        assert_eq!(parse_json_object("[1, 2]"), None);
+        assert_eq!(parse_json_object("no json"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_task_is_not_executed() {
+        let router = Arc::new(IntelligentRouter::new());
+        let state_manager = Arc::new(StateManager::new());
+        let capability_manager = Arc::new(CapabilityManager::new());
+
+        let engine = OrchestrationEngine::new(router, state_manager, capability_manager);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = engine.orchestrate_with_cancel("测试消息", cancel).await;
+        assert!(result.is_err());
+
+        let tasks = engine.get_all_tasks().await;
+        assert!(matches!(tasks[0].status, OrchestrationTaskStatus::Canceled));
+        assert!(!engine.cancel_task(&tasks[0].id).await);
     }
 }
\ No newline at end of file
@@ -0,0 +1,671 @@
+//! 任务规划器 - 由 LLM 把用户目标拆分为带依赖关系的子任务
+//!
+//! 规划结果先保存为草稿，执行前可以查看和修改。执行时依赖全部完成的子任务
+//! 并发运行，上游失败的子任务被取消；全部完成后由 LLM 汇总子任务结果给出
+//! 最终回答。
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use super::{
+    content_text, parse_json_object, run_until_deadline, AuditScope, OrchestrationEngine,
+    OrchestrationTask, OrchestrationTaskStatus,
+};
+use crate::core::router::RouteTarget;
+use crate::core::state::MessageType;
+use crate::protocol::message::{Message, MessageContent};
+
+/// 单个计划最多包含的子任务数
+pub const MAX_PLAN_TASKS: usize = 16;
+/// 依赖链最多的层数，即最长依赖链上的子任务数
+pub const MAX_PLAN_DEPTH: usize = 8;
+/// 子任务ID的最大字符数
+pub const MAX_TASK_ID_CHARS: usize = 64;
+/// 子任务说明的最大字符数
+pub const MAX_TASK_DESCRIPTION_CHARS: usize = 2000;
+/// 规划时 LLM 回复的最大字节数，超过时不解析
+pub const MAX_PLAN_REPLY_BYTES: usize = 32 * 1024;
+
+/// 规划错误
+#[derive(Debug, thiserror::Error)]
+pub enum PlanError {
+    #[error("计划未找到: {0}")]
+    NotFound(String),
+    #[error("计划 {0} 已开始执行，不能修改或再次执行")]
+    NotDraft(String),
+    #[error("无效的计划: {0}")]
+    Invalid(String),
+    #[error("规划失败: {0}")]
+    Planning(String),
+    #[error("子任务失败: {}", .0.join(", "))]
+    TasksFailed(Vec<String>),
+    #[error("汇总结果失败: {0}")]
+    Synthesis(String),
+}
+
+/// 计划状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    /// 等待执行，可以修改
+    Draft,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// 规划出的子任务，也是修改计划时提交的格式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlannedTask {
+    pub id: String,
+    pub description: String,
+    /// `llm`、`mcp:<工具>` 或 `a2a:<智能体>`
+    #[serde(default = "default_target")]
+    pub target: String,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+fn default_target() -> String {
+    "llm".to_string()
+}
+
+#[derive(Deserialize)]
+struct PlanSpec {
+    tasks: Vec<PlannedTask>,
+}
+
+/// 执行计划
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub id: String,
+    pub goal: String,
+    /// 创建计划的用户，规划和执行时的审计记录归属于该用户
+    #[serde(default)]
+    pub user_id: String,
+    pub status: PlanStatus,
+    /// 子任务，`dependencies` 为同一计划中的子任务ID
+    pub tasks: Vec<OrchestrationTask>,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 规划时可以分配给子任务的目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanTarget {
+    /// `llm`、`mcp:<工具>` 或 `a2a:<智能体>`
+    pub name: String,
+    pub description: String,
+    pub target: RouteTarget,
+}
+
+impl OrchestrationEngine {
+    /// 可分配给子任务的目标：本地 LLM、已连接的 MCP 工具和已配置的 A2A 智能体。
+    /// 能力管理器中注册的说明优先，被禁用的能力不可用。
+    pub async fn available_targets(&self) -> Vec<PlanTarget> {
+        let mut candidates = vec![(
+            RouteTarget::LocalLLM,
+            "General reasoning, writing and summarization".to_string(),
+        )];
+        if let Some(mcp) = &self.mcp {
+            for (_, tool) in mcp.available_tools() {
+                candidates.push((
+                    RouteTarget::MCPTool(tool.name.clone()),
+                    format!("{} Input schema: {}", tool.description, tool.input_schema),
+                ));
+            }
+        }
+        let mut agents: Vec<&String> = self.a2a_clients.keys().collect();
+        agents.sort();
+        for agent in agents {
+            candidates.push((
+                RouteTarget::A2AAgent(agent.clone()),
+                format!("A2A agent {agent}"),
+            ));
+        }
+
+        let mut targets = Vec::new();
+        for (target, description) in candidates {
+            let id = match &target {
+                RouteTarget::LocalLLM => None,
+                RouteTarget::MCPTool(name) | RouteTarget::A2AAgent(name) => Some(name.as_str()),
+            };
+            let capability = match id {
+                Some(id) => self.capability_manager.get_capability(id).await,
+                None => None,
+            };
+            let description = match capability {
+                Some(capability) if !capability.enabled => continue,
+                Some(capability) if !capability.description.is_empty() => capability.description,
+                _ => description,
+            };
+            targets.push(PlanTarget {
+                name: target.to_string(),
+                description,
+                target,
+            });
+        }
+        targets
+    }
+
+    /// 由 LLM 把目标拆分为子任务，返回待执行的草稿计划
+    pub async fn create_plan(&self, goal: &str) -> Result<Plan, PlanError> {
+        self.create_plan_as("", goal).await
+    }
+
+    /// 代表用户 `user_id` 规划，计划的审计记录归属于该用户
+    pub async fn create_plan_as(&self, user_id: &str, goal: &str) -> Result<Plan, PlanError> {
+        info!("🗺️  规划目标: {}", goal);
+        let targets = self.available_targets().await;
+        let target_list: Vec<String> = targets
+            .iter()
+            .map(|target| format!("- {}: {}", target.name, target.description))
+            .collect();
+        let prompt = format!(
+            "Break the goal below into a small number of subtasks (at most {MAX_PLAN_TASKS}, \
+             with dependency chains at most {MAX_PLAN_DEPTH} subtasks long). Assign each \
+             subtask to one of the available targets; a subtask may depend on the results of \
+             other subtasks.\n\
+             Available targets:\n{}\n\
+             Reply with a single JSON object and nothing else, in the form \
+             {{\"tasks\": [{{\"id\": \"t1\", \"description\": \"what to do\", \"target\": \"llm\", \"depends_on\": []}}]}}\n\
+             Goal: {goal}",
+            target_list.join("\n")
+        );
+
+        let plan_id = uuid::Uuid::new_v4().to_string();
+        let history = self.history(goal).await;
+        let audit = AuditScope {
+            user_id,
+            scope: json!({ "plan_id": plan_id }),
+        };
+        let reply = self
+            .process_llm(&prompt, &history, &audit, &CancellationToken::new())
+            .await
+            .map_err(PlanError::Planning)?;
+        let spec = match reply.content {
+            MessageContent::Text { text } if text.len() > MAX_PLAN_REPLY_BYTES => {
+                return Err(PlanError::Planning(format!(
+                    "LLM 回复超过 {MAX_PLAN_REPLY_BYTES} 字节"
+                )))
+            }
+            MessageContent::Text { text } => parse_json_object(&text)
+                .and_then(|value| serde_json::from_value::<PlanSpec>(value).ok())
+                .ok_or_else(|| PlanError::Planning(format!("无法从 LLM 回复中解析计划: {text}")))?,
+            other => {
+                return Err(PlanError::Planning(format!(
+                    "无法从 LLM 回复中解析计划: {other:?}"
+                )))
+            }
+        };
+
+        let now = Utc::now();
+        let plan = Plan {
+            id: plan_id,
+            goal: goal.to_string(),
+            user_id: user_id.to_string(),
+            status: PlanStatus::Draft,
+            tasks: build_tasks(spec.tasks, &targets)?,
+            answer: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        info!("🗺️  计划 {} 包含 {} 个子任务", plan.id, plan.tasks.len());
+        self.plans
+            .write()
+            .await
+            .insert(plan.id.clone(), plan.clone());
+        Ok(plan)
+    }
+
+    /// 替换草稿计划的子任务
+    pub async fn update_plan(
+        &self,
+        plan_id: &str,
+        tasks: Vec<PlannedTask>,
+    ) -> Result<Plan, PlanError> {
+        let targets = self.available_targets().await;
+        let tasks = build_tasks(tasks, &targets)?;
+        let mut plans = self.plans.write().await;
+        let plan = plans
+            .get_mut(plan_id)
+            .ok_or_else(|| PlanError::NotFound(plan_id.to_string()))?;
+        if plan.status != PlanStatus::Draft {
+            return Err(PlanError::NotDraft(plan_id.to_string()));
+        }
+        plan.tasks = tasks;
+        plan.updated_at = Utc::now();
+        Ok(plan.clone())
+    }
+
+    /// 获取计划
+    pub async fn get_plan(&self, plan_id: &str) -> Option<Plan> {
+        self.plans.read().await.get(plan_id).cloned()
+    }
+
+    /// 按创建时间倒序列出计划
+    pub async fn list_plans(&self) -> Vec<Plan> {
+        let mut plans: Vec<Plan> = self.plans.read().await.values().cloned().collect();
+        plans.sort_by_key(|plan| std::cmp::Reverse(plan.created_at));
+        plans
+    }
+
+    /// 规划并立即执行
+    pub async fn plan_and_execute(&self, goal: &str) -> Result<String, PlanError> {
+        self.plan_and_execute_as("", goal).await
+    }
+
+    /// 代表用户 `user_id` 规划并立即执行
+    pub async fn plan_and_execute_as(&self, user_id: &str, goal: &str) -> Result<String, PlanError> {
+        let plan = self.create_plan_as(user_id, goal).await?;
+        self.execute_plan(&plan.id).await
+    }
+
+    /// 按依赖顺序执行草稿计划，返回汇总后的最终回答；审计记录归属于创建
+    /// 计划的用户
+    pub async fn execute_plan(&self, plan_id: &str) -> Result<String, PlanError> {
+        let (goal, user_id, mut tasks) = {
+            let mut plans = self.plans.write().await;
+            let plan = plans
+                .get_mut(plan_id)
+                .ok_or_else(|| PlanError::NotFound(plan_id.to_string()))?;
+            if plan.status != PlanStatus::Draft {
+                return Err(PlanError::NotDraft(plan_id.to_string()));
+            }
+            plan.status = PlanStatus::Running;
+            plan.updated_at = Utc::now();
+            (plan.goal.clone(), plan.user_id.clone(), plan.tasks.clone())
+        };
+        info!("🚀 执行计划 {}: {}", plan_id, goal);
+        let history = self.history(&goal).await;
+        self.remember(MessageType::UserMessage, &goal).await;
+
+        // 每轮并发执行依赖全部完成的子任务
+        loop {
+            let completed: HashSet<&str> = tasks
+                .iter()
+                .filter(|task| matches!(task.status, OrchestrationTaskStatus::Completed))
+                .map(|task| task.id.as_str())
+                .collect();
+            let ready: Vec<usize> = tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| matches!(task.status, OrchestrationTaskStatus::Pending))
+                .filter(|(_, task)| {
+                    task.dependencies
+                        .iter()
+                        .all(|dep| completed.contains(dep.as_str()))
+                })
+                .map(|(index, _)| index)
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+
+            let inputs: Vec<(RouteTarget, String, AuditScope)> = ready
+                .iter()
+                .map(|&index| {
+                    (
+                        tasks[index].target.clone(),
+                        subtask_input(&tasks[index], &tasks),
+                        AuditScope {
+                            user_id: &user_id,
+                            scope: json!({ "plan_id": plan_id, "subtask_id": tasks[index].id }),
+                        },
+                    )
+                })
+                .collect();
+            for &index in &ready {
+                tasks[index].status = OrchestrationTaskStatus::Running;
+                tasks[index].started_at = Some(Utc::now());
+            }
+            self.store_plan_tasks(plan_id, &tasks).await;
+
+            let results = join_all(
+                inputs
+                    .iter()
+                    .map(|(target, input, audit)| self.run_subtask(target, input, &history, audit)),
+            )
+            .await;
+            for (&index, result) in ready.iter().zip(results) {
+                let task = &mut tasks[index];
+                match result {
+                    Ok(output) => {
+                        task.finish(OrchestrationTaskStatus::Completed, Some(output), None)
+                    }
+                    Err(error) => {
+                        warn!("❌ 子任务 {} 失败: {}", task.id, error);
+                        task.finish(OrchestrationTaskStatus::Failed, None, Some(error));
+                    }
+                }
+            }
+            self.store_plan_tasks(plan_id, &tasks).await;
+        }
+
+        // 剩下的子任务依赖了失败的子任务
+        let failed: Vec<String> = tasks
+            .iter()
+            .filter(|task| matches!(task.status, OrchestrationTaskStatus::Failed))
+            .map(|task| task.id.clone())
+            .collect();
+        if !failed.is_empty() {
+            for task in &mut tasks {
+                if matches!(task.status, OrchestrationTaskStatus::Pending) {
+                    task.finish(
+                        OrchestrationTaskStatus::Canceled,
+                        None,
+                        Some("依赖的子任务失败".to_string()),
+                    );
+                }
+            }
+            let error = PlanError::TasksFailed(failed);
+            self.finish_plan(plan_id, tasks, Err(&error)).await;
+            return Err(error);
+        }
+
+        let audit = AuditScope {
+            user_id: &user_id,
+            scope: json!({ "plan_id": plan_id }),
+        };
+        let answer = self.synthesize(&goal, &tasks, &history, &audit).await;
+        match answer {
+            Ok(answer) => {
+                self.remember(MessageType::LLMResponse, &answer).await;
+                self.finish_plan(plan_id, tasks, Ok(&answer)).await;
+                Ok(answer)
+            }
+            Err(error) => {
+                self.finish_plan(plan_id, tasks, Err(&error)).await;
+                Err(error)
+            }
+        }
+    }
+
+    async fn run_subtask(
+        &self,
+        target: &RouteTarget,
+        input: &str,
+        history: &[Message],
+        audit: &AuditScope<'_>,
+    ) -> Result<String, String> {
+        let token = CancellationToken::new();
+        let run = self.run_target(target, input, history, audit, &token);
+        run_until_deadline(self.task_timeout, &token, run)
+            .await
+            .unwrap_or_else(|| Err("子任务超时".to_string()))
+    }
+
+    async fn synthesize(
+        &self,
+        goal: &str,
+        tasks: &[OrchestrationTask],
+        history: &[Message],
+        audit: &AuditScope<'_>,
+    ) -> Result<String, PlanError> {
+        let results: Vec<String> = tasks
+            .iter()
+            .map(|task| {
+                format!(
+                    "[{}] {}\n{}",
+                    task.id,
+                    task.description,
+                    task.result.as_deref().unwrap_or_default()
+                )
+            })
+            .collect();
+        let prompt = format!(
+            "Goal: {goal}\n\nResults of the subtasks:\n{}\n\n\
+             Write the final answer to the goal based on these results.",
+            results.join("\n\n")
+        );
+        let reply = self
+            .process_llm(&prompt, history, audit, &CancellationToken::new())
+            .await
+            .map_err(PlanError::Synthesis)?;
+        Ok(content_text(reply.content))
+    }
+
+    async fn store_plan_tasks(&self, plan_id: &str, tasks: &[OrchestrationTask]) {
+        if let Some(plan) = self.plans.write().await.get_mut(plan_id) {
+            plan.tasks = tasks.to_vec();
+            plan.updated_at = Utc::now();
+        }
+    }
+
+    async fn finish_plan(
+        &self,
+        plan_id: &str,
+        tasks: Vec<OrchestrationTask>,
+        result: Result<&String, &PlanError>,
+    ) {
+        if let Some(plan) = self.plans.write().await.get_mut(plan_id) {
+            plan.tasks = tasks;
+            match result {
+                Ok(answer) => {
+                    plan.status = PlanStatus::Completed;
+                    plan.answer = Some(answer.clone());
+                }
+                Err(error) => {
+                    plan.status = PlanStatus::Failed;
+                    plan.error = Some(error.to_string());
+                }
+            }
+            plan.updated_at = Utc::now();
+        }
+    }
+}
+
+/// 子任务的输入：任务说明，加上所依赖子任务的结果
+fn subtask_input(task: &OrchestrationTask, tasks: &[OrchestrationTask]) -> String {
+    let upstream: Vec<String> = task
+        .dependencies
+        .iter()
+        .filter_map(|dep| tasks.iter().find(|other| &other.id == dep))
+        .map(|dep| {
+            format!(
+                "[{}] {}\n{}",
+                dep.id,
+                dep.description,
+                dep.result.as_deref().unwrap_or_default()
+            )
+        })
+        .collect();
+    if upstream.is_empty() {
+        task.description.clone()
+    } else {
+        format!(
+            "{}\n\nResults of prerequisite tasks:\n{}",
+            task.description,
+            upstream.join("\n\n")
+        )
+    }
+}
+
+/// 校验子任务并转换为编排任务：数量、长度和依赖层数不超过上限，ID 唯一、
+/// 依赖存在且无环、目标可用
+fn build_tasks(
+    specs: Vec<PlannedTask>,
+    targets: &[PlanTarget],
+) -> Result<Vec<OrchestrationTask>, PlanError> {
+    let invalid = |message: String| Err(PlanError::Invalid(message));
+    if specs.is_empty() {
+        return invalid("计划没有子任务".to_string());
+    }
+    if specs.len() > MAX_PLAN_TASKS {
+        return invalid(format!(
+            "子任务数 {} 超过上限 {MAX_PLAN_TASKS}",
+            specs.len()
+        ));
+    }
+
+    let mut ids = HashSet::new();
+    for spec in &specs {
+        if spec.id.trim().is_empty() {
+            return invalid("子任务ID不能为空".to_string());
+        }
+        if spec.id.chars().count() > MAX_TASK_ID_CHARS {
+            return invalid(format!("子任务ID超过 {MAX_TASK_ID_CHARS} 个字符"));
+        }
+        if !ids.insert(spec.id.as_str()) {
+            return invalid(format!("子任务ID重复: {}", spec.id));
+        }
+        if spec.description.trim().is_empty() {
+            return invalid(format!("子任务 {} 缺少说明", spec.id));
+        }
+        if spec.description.chars().count() > MAX_TASK_DESCRIPTION_CHARS {
+            return invalid(format!(
+                "子任务 {} 的说明超过 {MAX_TASK_DESCRIPTION_CHARS} 个字符",
+                spec.id
+            ));
+        }
+    }
+    for spec in &specs {
+        for dep in &spec.depends_on {
+            if dep == &spec.id || !ids.contains(dep.as_str()) {
+                return invalid(format!("子任务 {} 的依赖无效: {}", spec.id, dep));
+            }
+        }
+    }
+
+    // 按依赖逐层移除子任务，移除不完的部分构成环，层数即最长依赖链的长度
+    let mut remaining: HashMap<&str, HashSet<&str>> = specs
+        .iter()
+        .map(|spec| {
+            (
+                spec.id.as_str(),
+                spec.depends_on.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect();
+    let mut depth = 0;
+    while !remaining.is_empty() {
+        let free: Vec<&str> = remaining
+            .iter()
+            .filter(|(_, deps)| deps.iter().all(|dep| !remaining.contains_key(dep)))
+            .map(|(id, _)| *id)
+            .collect();
+        if free.is_empty() {
+            let mut cycle: Vec<&str> = remaining.keys().copied().collect();
+            cycle.sort();
+            return invalid(format!("子任务存在循环依赖: {}", cycle.join(", ")));
+        }
+        for id in free {
+            remaining.remove(id);
+        }
+        depth += 1;
+        if depth > MAX_PLAN_DEPTH {
+            return invalid(format!("依赖链超过 {MAX_PLAN_DEPTH} 层"));
+        }
+    }
+
+    let now = Utc::now();
+    specs
+        .into_iter()
+        .map(|spec| {
+            let target = targets
+                .iter()
+                .find(|target| target.name == spec.target)
+                .map(|target| target.target.clone())
+                .ok_or_else(|| {
+                    PlanError::Invalid(format!("子任务 {} 的目标不可用: {}", spec.id, spec.target))
+                })?;
+            Ok(OrchestrationTask {
+                name: format!("子任务 {}", spec.id),
+                id: spec.id,
+                description: spec.description,
+                target,
+                status: OrchestrationTaskStatus::Pending,
+                dependencies: spec.depends_on,
+                result: None,
+                error: None,
+                created_at: now,
+                started_at: None,
+                finished_at: None,
+                duration_ms: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, target: &str, depends_on: &[&str]) -> PlannedTask {
+        PlannedTask {
+            id: id.to_string(),
+            description: format!("do {id}"),
+            target: target.to_string(),
+            depends_on: depends_on.iter().map(|dep| dep.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_tasks_validates_graph_and_targets() {
+        let targets = vec![
+            PlanTarget {
+                name: "llm".to_string(),
+                description: String::new(),
+                target: RouteTarget::LocalLLM,
+            },
+            PlanTarget {
+                name: "mcp:search".to_string(),
+                description: String::new(),
+                target: RouteTarget::MCPTool("search".to_string()),
+            },
+        ];
+
+        let tasks = build_tasks(
+            vec![task("a", "mcp:search", &[]), task("b", "llm", &["a"])],
+            &targets,
+        )
+        .unwrap();
+        assert!(matches!(&tasks[0].target, RouteTarget::MCPTool(tool) if tool == "search"));
+        assert_eq!(tasks[1].dependencies, ["a"]);
+
+        let invalid = [
+            vec![],
+            vec![task("a", "llm", &[]), task("a", "llm", &[])],
+            vec![task("a", "llm", &["missing"])],
+            vec![task("a", "llm", &["a"])],
+            vec![task("a", "llm", &["b"]), task("b", "llm", &["a"])],
+            vec![task("a", "a2a:mailer", &[])],
+        ];
+        for specs in invalid {
+            assert!(matches!(
+                build_tasks(specs, &targets),
+                Err(PlanError::Invalid(_))
+            ));
+        }
+
+        // 子任务数、依赖层数和长度有上限
+        let chain = |length: usize| -> Vec<PlannedTask> {
+            let ids: Vec<String> = (0..length).map(|i| format!("t{i}")).collect();
+            (0..length)
+                .map(|i| {
+                    let depends_on: Vec<&str> = ids[..i].last().map(String::as_str).into_iter().collect();
+                    task(&ids[i], "llm", &depends_on)
+                })
+                .collect()
+        };
+        assert_eq!(build_tasks(chain(MAX_PLAN_DEPTH), &targets).unwrap().len(), MAX_PLAN_DEPTH);
+        assert!(matches!(build_tasks(chain(MAX_PLAN_DEPTH + 1), &targets), Err(PlanError::Invalid(_))));
+        let wide: Vec<PlannedTask> = (0..=MAX_PLAN_TASKS).map(|i| task(&format!("t{i}"), "llm", &[])).collect();
+        assert!(matches!(build_tasks(wide, &targets), Err(PlanError::Invalid(_))));
+        let mut long = task("a", "llm", &[]);
+        long.description = "x".repeat(MAX_TASK_DESCRIPTION_CHARS + 1);
+        assert!(matches!(build_tasks(vec![long], &targets), Err(PlanError::Invalid(_))));
+        let long_id = task(&"x".repeat(MAX_TASK_ID_CHARS + 1), "llm", &[]);
+        assert!(matches!(build_tasks(vec![long_id], &targets), Err(PlanError::Invalid(_))));
+    }
+}
@@ -0,0 +1,98 @@
+//! LLM 分类路由策略 - 由模型在候选目标中选择，并给出置信度和理由
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use super::{RouteCandidate, RouteDecision, RouteTarget, RouterError, RoutingStrategy};
+use crate::core::decision::DecisionContext;
+use crate::core::orchestration::parse_json_object;
+use crate::llm::LLMService;
+use crate::protocol::message::MessageContent;
+
+/// 模型的分类结果
+#[derive(Debug, Deserialize)]
+struct Classification {
+    target: String,
+    confidence: f32,
+    #[serde(default)]
+    reasoning: String,
+}
+
+/// LLM 分类路由策略
+pub struct LlmClassifierStrategy {
+    llm: LLMService,
+    candidates: RwLock<Vec<RouteCandidate>>,
+}
+
+impl LlmClassifierStrategy {
+    pub fn new(llm: LLMService) -> Self {
+        Self {
+            llm,
+            candidates: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 替换候选目标，本地 LLM 始终可选
+    pub async fn set_candidates(&self, candidates: Vec<RouteCandidate>) {
+        *self.candidates.write().await = candidates;
+    }
+
+    fn prompt(candidates: &[RouteCandidate], message: &str) -> String {
+        let mut targets = vec!["- llm: Answer directly with the local language model".to_string()];
+        targets.extend(
+            candidates
+                .iter()
+                .filter(|candidate| candidate.target != RouteTarget::LocalLLM)
+                .map(|candidate| format!("- {}: {}", candidate.target, candidate.description)),
+        );
+        format!(
+            "Choose the target that should handle the user request.\n\n\
+             Routing targets:\n{}\n\n\
+             Request: {}\n\n\
+             Reply with only a JSON object of the form \
+             {{\"target\": \"<target name>\", \"confidence\": <number from 0 to 1>, \"reasoning\": \"<one sentence>\"}}.",
+            targets.join("\n"),
+            message
+        )
+    }
+}
+
+#[async_trait]
+impl RoutingStrategy for LlmClassifierStrategy {
+    fn name(&self) -> &str {
+        "llm"
+    }
+
+    async fn route(&self, context: &DecisionContext) -> Result<Option<RouteDecision>, RouterError> {
+        let candidates = self.candidates.read().await.clone();
+        let reply = self
+            .llm
+            .process_message(&Self::prompt(&candidates, &context.text), &[])
+            .await
+            .map_err(RouterError::Classifier)?;
+        let text = match reply.content {
+            MessageContent::Text { text } => text,
+            other => serde_json::to_string(&other).unwrap_or_default(),
+        };
+        let classification: Classification = parse_json_object(&text)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .ok_or_else(|| RouterError::Classifier(format!("无法解析分类结果: {text}")))?;
+
+        let target: RouteTarget = classification.target.parse()?;
+        if target != RouteTarget::LocalLLM
+            && !candidates
+                .iter()
+                .any(|candidate| candidate.target == target)
+        {
+            return Err(RouterError::InvalidTarget(classification.target));
+        }
+        Ok(Some(RouteDecision {
+            target,
+            confidence: classification.confidence.clamp(0.0, 1.0),
+            reasoning: classification.reasoning,
+            strategy: self.name().to_string(),
+            rule_id: None,
+        }))
+    }
+}
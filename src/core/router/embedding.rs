@@ -0,0 +1,145 @@
+//! 向量路由策略 - 按消息与候选说明的余弦相似度选择目标
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use super::{RouteCandidate, RouteDecision, RouterError, RoutingStrategy};
+use crate::core::decision::DecisionContext;
+use crate::services::embeddings::{cosine_similarity, EmbeddingProvider};
+
+/// 向量路由策略
+pub struct EmbeddingStrategy {
+    embedder: Arc<dyn EmbeddingProvider>,
+    candidates: RwLock<Vec<(RouteCandidate, Vec<f32>)>>,
+}
+
+impl EmbeddingStrategy {
+    pub fn new(embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        Self {
+            embedder,
+            candidates: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 替换候选目标并预先计算说明的向量
+    pub async fn set_candidates(&self, candidates: Vec<RouteCandidate>) -> Result<(), RouterError> {
+        let descriptions: Vec<String> = candidates
+            .iter()
+            .map(|candidate| candidate.description.clone())
+            .collect();
+        let vectors = self
+            .embedder
+            .embed(&descriptions)
+            .await
+            .map_err(|e| RouterError::Embedding(e.to_string()))?;
+        if vectors.len() != candidates.len() {
+            return Err(RouterError::Embedding(format!(
+                "期望 {} 个向量，实际返回 {} 个",
+                candidates.len(),
+                vectors.len()
+            )));
+        }
+        *self.candidates.write().await = candidates.into_iter().zip(vectors).collect();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RoutingStrategy for EmbeddingStrategy {
+    fn name(&self) -> &str {
+        "embedding"
+    }
+
+    async fn route(&self, context: &DecisionContext) -> Result<Option<RouteDecision>, RouterError> {
+        let candidates = self.candidates.read().await;
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let query = self
+            .embedder
+            .embed_one(&context.text)
+            .await
+            .map_err(|e| RouterError::Embedding(e.to_string()))?;
+
+        let best = candidates
+            .iter()
+            .map(|(candidate, vector)| (candidate, cosine_similarity(&query, vector)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        Ok(best.map(|(candidate, similarity)| RouteDecision {
+            target: candidate.target.clone(),
+            confidence: similarity.clamp(0.0, 1.0),
+            reasoning: format!(
+                "与 {} 的说明最相似（相似度 {:.2}）: {}",
+                candidate.target, similarity, candidate.description
+            ),
+            strategy: self.name().to_string(),
+            rule_id: None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::router::RouteTarget;
+    use crate::services::embeddings::HashingEmbedder;
+
+    #[tokio::test]
+    async fn test_routes_to_most_similar_description() {
+        let strategy = EmbeddingStrategy::new(Arc::new(HashingEmbedder::default()));
+        assert!(strategy
+            .route(&DecisionContext::new("anything"))
+            .await
+            .unwrap()
+            .is_none());
+
+        strategy
+            .set_candidates(vec![
+                RouteCandidate::new(
+                    RouteTarget::MCPTool("read_file".to_string()),
+                    "Read the contents of a local file",
+                ),
+                RouteCandidate::new(
+                    RouteTarget::A2AAgent("weather".to_string()),
+                    "Weather forecast for a city 查询城市天气预报",
+                ),
+            ])
+            .await
+            .unwrap();
+
+        let decision = strategy
+            .route(&DecisionContext::new(
+                "What is the weather forecast for Paris?",
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decision.target,
+            RouteTarget::A2AAgent("weather".to_string())
+        );
+        assert!(decision.confidence > 0.0);
+
+        let decision = strategy
+            .route(&DecisionContext::new("明天北京天气怎么样"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decision.target,
+            RouteTarget::A2AAgent("weather".to_string())
+        );
+
+        let decision = strategy
+            .route(&DecisionContext::new("read the file notes.txt"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decision.target,
+            RouteTarget::MCPTool("read_file".to_string())
+        );
+    }
+}
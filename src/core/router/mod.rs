@@ -1,54 +1,613 @@
-//! 智能路由器模块 - 简化版
+//! 智能路由器模块
+//!
+//! 路由器依次询问配置的路由策略：规则策略使用决策引擎中的规则，向量策略按描述
+//! 相似度匹配能力和技能，LLM 分类策略由模型在候选目标中选择。第一个置信度达到
+//! 阈值的决策生效，都达不到时回退到本地 LLM。
+//!
+//! 对回复的反馈通过 [`IntelligentRouter::record_feedback`] 计入决策引擎：规则决策
+//! 按规则的反馈历史计算置信度，其他策略的置信度按目标的反馈历史调整。
 
-use tracing::info;
+pub mod classifier;
+pub mod embedding;
+pub mod rules;
 
-/// 智能路由器
-#[derive(Clone)]
-pub struct IntelligentRouter;
+pub use classifier::LlmClassifierStrategy;
+pub use embedding::EmbeddingStrategy;
+pub use rules::{validate_rule_actions, RuleStrategy};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::config::RouterSettings;
+use crate::core::capabilities::Capability;
+use crate::core::decision::{
+    DecisionContext, DecisionEngine, DecisionError, LearningRecord, DEFAULT_PRIOR,
+};
+use crate::llm::LLMService;
+use crate::protocol::manifest::Manifest;
+use crate::services::embeddings::{EmbeddingProvider, HashingEmbedder};
+
+/// 默认置信度阈值
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.6;
+
+/// 回退决策使用的策略名称
+const FALLBACK_STRATEGY: &str = "fallback";
+
+/// 等待反馈的决策最多保留的条数
+const MAX_TRACKED_DECISIONS: usize = 1000;
+
+/// 路由错误
+#[derive(Debug, Error)]
+pub enum RouterError {
+    #[error("无效的路由目标: {0}")]
+    InvalidTarget(String),
+    #[error("向量计算失败: {0}")]
+    Embedding(String),
+    #[error("LLM 分类失败: {0}")]
+    Classifier(String),
+    #[error("路由规则无效: {0}")]
+    Rules(#[from] DecisionError),
+    #[error("没有找到消息的路由决策: {0}")]
+    UnknownDecision(String),
+    #[error("消息已经评价过: {0}")]
+    AlreadyRated(String),
+    #[error("只能评价自己收到的回复: {0}")]
+    NotOwner(String),
+    #[error("记录反馈失败: {0}")]
+    Feedback(DecisionError),
+}
 
 /// 路由决策结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteDecision {
     pub target: RouteTarget,
     pub confidence: f32,
     pub reasoning: String,
+    /// 做出决策的策略
+    pub strategy: String,
+    /// 做出决策的规则，非规则决策为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
 }
 
 /// 路由目标
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum RouteTarget {
     LocalLLM,
     A2AAgent(String),
     MCPTool(String),
 }
 
+/// 目标名称：`llm`、`mcp:<工具>` 或 `a2a:<智能体>`
+impl fmt::Display for RouteTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteTarget::LocalLLM => write!(f, "llm"),
+            RouteTarget::MCPTool(tool) => write!(f, "mcp:{tool}"),
+            RouteTarget::A2AAgent(agent) => write!(f, "a2a:{agent}"),
+        }
+    }
+}
+
+impl FromStr for RouteTarget {
+    type Err = RouterError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim();
+        if name == "llm" {
+            return Ok(RouteTarget::LocalLLM);
+        }
+        match name.split_once(':') {
+            Some(("mcp", tool)) if !tool.is_empty() => Ok(RouteTarget::MCPTool(tool.to_string())),
+            Some(("a2a", agent)) if !agent.is_empty() => {
+                Ok(RouteTarget::A2AAgent(agent.to_string()))
+            }
+            _ => Err(RouterError::InvalidTarget(name.to_string())),
+        }
+    }
+}
+
+/// 可路由的目标及其说明，供向量策略和 LLM 分类策略匹配
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteCandidate {
+    pub target: RouteTarget,
+    pub description: String,
+}
+
+impl RouteCandidate {
+    pub fn new(target: RouteTarget, description: impl Into<String>) -> Self {
+        Self {
+            target,
+            description: description.into(),
+        }
+    }
+
+    /// 由能力生成候选，类别 `mcp`、`a2a`、`llm` 决定目标类型，A2A 技能路由到
+    /// 提供它的智能体；其他类别和不可用的能力不参与路由
+    pub fn from_capability(capability: &Capability) -> Option<Self> {
+        if !capability.is_available() {
+            return None;
+        }
+        let target = match capability.category.as_str() {
+            "mcp" => RouteTarget::MCPTool(capability.id.clone()),
+            "a2a" => RouteTarget::A2AAgent(
+                capability.a2a_agent().unwrap_or(&capability.id).to_string(),
+            ),
+            "llm" => RouteTarget::LocalLLM,
+            _ => return None,
+        };
+        let description = if capability.description.is_empty() {
+            capability.name.clone()
+        } else {
+            format!("{}: {}", capability.name, capability.description)
+        };
+        Some(Self::new(target, description))
+    }
+
+    /// 由能力生成候选，按目标名称排序
+    pub fn from_capabilities(capabilities: &[Capability]) -> Vec<Self> {
+        let mut candidates: Vec<Self> = capabilities
+            .iter()
+            .filter_map(Self::from_capability)
+            .collect();
+        candidates.sort_by_key(|candidate| candidate.target.to_string());
+        candidates
+    }
+
+    /// 由已获取的清单生成候选：每个 MCP 工具一个候选，每个 A2A 智能体按说明和
+    /// 能力列表生成一个候选，按目标名称排序
+    pub fn from_manifests(manifests: &HashMap<String, Manifest>) -> Vec<Self> {
+        let mut candidates: Vec<Self> = manifests
+            .iter()
+            .flat_map(|(name, manifest)| match manifest {
+                Manifest::MCP(manifest) => manifest
+                    .tools
+                    .iter()
+                    .map(|tool| {
+                        Self::new(
+                            RouteTarget::MCPTool(tool.name.clone()),
+                            tool.description.clone(),
+                        )
+                    })
+                    .collect(),
+                Manifest::A2A(manifest) => vec![Self::new(
+                    RouteTarget::A2AAgent(name.clone()),
+                    format!(
+                        "{} {}",
+                        manifest.description,
+                        manifest.capabilities.join(" ")
+                    )
+                    .trim()
+                    .to_string(),
+                )],
+            })
+            .collect();
+        candidates.sort_by_key(|candidate| candidate.target.to_string());
+        candidates
+    }
+}
+
+/// 路由策略
+#[async_trait]
+pub trait RoutingStrategy: Send + Sync {
+    /// 策略名称，记录在决策的 `strategy` 字段
+    fn name(&self) -> &str;
+
+    /// 为消息给出路由决策，无法判断时返回 `None`
+    async fn route(&self, context: &DecisionContext) -> Result<Option<RouteDecision>, RouterError>;
+}
+
+/// 路由策略种类，用于配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyKind {
+    /// 决策引擎规则
+    Rules,
+    /// 能力和技能说明的向量相似度
+    Embedding,
+    /// LLM 分类
+    Llm,
+}
+
+/// 等待反馈的决策
+struct TrackedDecision {
+    decision: RouteDecision,
+    message: String,
+    /// 收到回复的认证用户，未认证的请求为空
+    owner: Option<String>,
+    rated: bool,
+}
+
+/// 最近的路由决策，按消息ID索引，超过上限时丢弃最早的决策
+#[derive(Default)]
+struct TrackedDecisions {
+    decisions: HashMap<String, TrackedDecision>,
+    order: VecDeque<String>,
+}
+
+/// 智能路由器
+#[derive(Clone)]
+pub struct IntelligentRouter {
+    strategies: Vec<Arc<dyn RoutingStrategy>>,
+    confidence_threshold: f32,
+    rules: Arc<DecisionEngine>,
+    tracked: Arc<RwLock<TrackedDecisions>>,
+}
+
 impl IntelligentRouter {
+    /// 创建只有规则策略的路由器，初始没有规则，可通过
+    /// [`IntelligentRouter::decision_engine`] 添加
     pub fn new() -> Self {
-        Self
+        let rules = Arc::new(DecisionEngine::new());
+        Self {
+            strategies: vec![Arc::new(RuleStrategy::new(rules.clone()))],
+            confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+            rules,
+            tracked: Arc::default(),
+        }
+    }
+
+    /// 按配置创建路由器，向量策略和 LLM 分类策略在 `candidates` 中选择目标，
+    /// 向量策略使用本地哈希向量
+    pub async fn from_settings(
+        settings: &RouterSettings,
+        llm: LLMService,
+        candidates: Vec<RouteCandidate>,
+    ) -> Result<Self, RouterError> {
+        Self::from_settings_with_embedder(
+            settings,
+            llm,
+            Arc::new(HashingEmbedder::default()),
+            candidates,
+        )
+        .await
+    }
+
+    /// 同 [`IntelligentRouter::from_settings`]，向量策略使用 `embedder`
+    pub async fn from_settings_with_embedder(
+        settings: &RouterSettings,
+        llm: LLMService,
+        embedder: Arc<dyn EmbeddingProvider>,
+        candidates: Vec<RouteCandidate>,
+    ) -> Result<Self, RouterError> {
+        validate_rule_actions(&settings.rules)?;
+        let rules = Arc::new(
+            DecisionEngine::with_rules(settings.rules.clone())?
+                .with_exploration(settings.exploration)?,
+        );
+
+        let mut strategies: Vec<Arc<dyn RoutingStrategy>> = Vec::new();
+        for kind in &settings.strategies {
+            let strategy: Arc<dyn RoutingStrategy> = match kind {
+                StrategyKind::Rules => Arc::new(RuleStrategy::new(rules.clone())),
+                StrategyKind::Embedding => {
+                    let strategy = EmbeddingStrategy::new(embedder.clone());
+                    strategy.set_candidates(candidates.clone()).await?;
+                    Arc::new(strategy)
+                }
+                StrategyKind::Llm => {
+                    let strategy = LlmClassifierStrategy::new(llm.clone());
+                    strategy.set_candidates(candidates.clone()).await;
+                    Arc::new(strategy)
+                }
+            };
+            strategies.push(strategy);
+        }
+
+        Ok(Self {
+            strategies,
+            confidence_threshold: settings.confidence_threshold,
+            rules,
+            tracked: Arc::default(),
+        })
+    }
+
+    /// 替换全部路由策略；规则策略应使用 [`IntelligentRouter::decision_engine`]，
+    /// 规则才能在运行时查看和修改
+    pub fn with_strategies(mut self, strategies: Vec<Arc<dyn RoutingStrategy>>) -> Self {
+        self.strategies = strategies;
+        self
+    }
+
+    /// 追加一个路由策略，排在已有策略之后
+    pub fn with_strategy(mut self, strategy: Arc<dyn RoutingStrategy>) -> Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    /// 设置置信度阈值
+    pub fn with_confidence_threshold(mut self, threshold: f32) -> Self {
+        self.confidence_threshold = threshold;
+        self
+    }
+
+    pub fn confidence_threshold(&self) -> f32 {
+        self.confidence_threshold
+    }
+
+    /// 路由规则所在的决策引擎
+    pub fn decision_engine(&self) -> &Arc<DecisionEngine> {
+        &self.rules
     }
 
     /// 分析用户消息并决定最佳行动方案
     pub async fn route_message(&self, message: &str) -> RouteDecision {
-        info!("🔍 分析用户消息: {}", message);
-
-        // 简单的路由逻辑
-        if message.contains("天气") || message.contains("时间") {
-            RouteDecision {
-                target: RouteTarget::A2AAgent("info_agent".to_string()),
-                confidence: 0.7,
-                reasoning: "匹配到信息查询关键词".to_string(),
+        self.route(&DecisionContext::new(message)).await
+    }
+
+    /// 按完整的决策上下文路由，规则可以使用发送者、元数据和可用的工具与智能体
+    pub async fn route(&self, context: &DecisionContext) -> RouteDecision {
+        info!("🔍 分析用户消息: {}", context.text);
+
+        let mut best: Option<RouteDecision> = None;
+        for strategy in &self.strategies {
+            let decision = match strategy.route(context).await {
+                Ok(Some(decision)) => Ok(Some(self.adjust_confidence(decision).await)),
+                other => other,
+            };
+            match decision {
+                Ok(Some(decision)) if decision.confidence >= self.confidence_threshold => {
+                    info!(
+                        "🧭 {} 策略路由到 {} (置信度 {:.2})",
+                        decision.strategy, decision.target, decision.confidence
+                    );
+                    return decision;
+                }
+                Ok(Some(decision)) => {
+                    if best
+                        .as_ref()
+                        .is_none_or(|best| decision.confidence > best.confidence)
+                    {
+                        best = Some(decision);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("⚠️  {} 路由策略失败: {}", strategy.name(), e),
             }
-        } else if message.contains("文件") || message.contains("计算") {
-            RouteDecision {
-                target: RouteTarget::MCPTool("file_processor".to_string()),
-                confidence: 0.8,
-                reasoning: "匹配到工具处理关键词".to_string(),
+        }
+
+        let reasoning = match best {
+            Some(best) => format!(
+                "{} 策略的最佳候选 {} 置信度 {:.2} 低于阈值 {:.2}，回退到本地LLM",
+                best.strategy, best.target, best.confidence, self.confidence_threshold
+            ),
+            None => "没有策略给出路由决策，回退到本地LLM".to_string(),
+        };
+        let target = RouteTarget::LocalLLM;
+        RouteDecision {
+            confidence: self
+                .rules
+                .action_confidence(&target.to_string(), DEFAULT_PRIOR)
+                .await,
+            target,
+            reasoning,
+            strategy: FALLBACK_STRATEGY.to_string(),
+            rule_id: None,
+        }
+    }
+
+    /// 非规则决策的置信度以策略给出的置信度为先验，按目标的反馈历史调整；
+    /// 规则决策的置信度已经由规则的反馈历史决定
+    async fn adjust_confidence(&self, mut decision: RouteDecision) -> RouteDecision {
+        if decision.rule_id.is_none() {
+            decision.confidence = self
+                .rules
+                .action_confidence(&decision.target.to_string(), decision.confidence)
+                .await;
+        }
+        decision
+    }
+
+    /// 记住回复消息的路由决策，以便之后对回复的反馈计入决策。`owner` 为收到
+    /// 回复的认证用户，之后只有该用户可以评价
+    pub async fn track(
+        &self,
+        message_id: impl Into<String>,
+        decision: &RouteDecision,
+        message: &str,
+        owner: Option<&str>,
+    ) {
+        let message_id = message_id.into();
+        let mut tracked = self.tracked.write().await;
+        if tracked.order.len() >= MAX_TRACKED_DECISIONS {
+            if let Some(oldest) = tracked.order.pop_front() {
+                tracked.decisions.remove(&oldest);
             }
-        } else {
-            RouteDecision {
-                target: RouteTarget::LocalLLM,
-                confidence: 0.9,
-                reasoning: "默认路由到本地LLM".to_string(),
+        }
+        tracked.order.push_back(message_id.clone());
+        tracked.decisions.insert(
+            message_id,
+            TrackedDecision {
+                decision: decision.clone(),
+                message: message.to_string(),
+                owner: owner.map(str::to_string),
+                rated: false,
+            },
+        );
+    }
+
+    /// 记录对回复的反馈（-1.0 到 1.0），每条回复只能评价一次。`caller` 为评价的
+    /// 用户，回复属于其他用户时拒绝；为 `None` 时不检查（未启用认证或管理员）
+    pub async fn record_feedback(
+        &self,
+        message_id: &str,
+        feedback: f32,
+        caller: Option<&str>,
+    ) -> Result<LearningRecord, RouterError> {
+        let record = {
+            let mut tracked = self.tracked.write().await;
+            let entry = tracked
+                .decisions
+                .get_mut(message_id)
+                .ok_or_else(|| RouterError::UnknownDecision(message_id.to_string()))?;
+            if let (Some(caller), Some(owner)) = (caller, entry.owner.as_deref()) {
+                if caller != owner {
+                    return Err(RouterError::NotOwner(message_id.to_string()));
+                }
+            }
+            if entry.rated {
+                return Err(RouterError::AlreadyRated(message_id.to_string()));
             }
+            entry.rated = true;
+            LearningRecord::new(
+                message_id,
+                entry.decision.rule_id.clone(),
+                entry.decision.target.to_string(),
+                entry.message.clone(),
+                feedback,
+            )
+        };
+
+        if let Err(e) = self.rules.record_learning(record.clone()).await {
+            if let Some(entry) = self.tracked.write().await.decisions.get_mut(message_id) {
+                entry.rated = false;
+            }
+            return Err(RouterError::Feedback(e));
         }
+        Ok(record)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::decision::DecisionRule;
+
+    /// 总是给出固定决策的策略
+    struct FixedStrategy(RouteTarget, f32);
+
+    #[async_trait]
+    impl RoutingStrategy for FixedStrategy {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        async fn route(
+            &self,
+            _context: &DecisionContext,
+        ) -> Result<Option<RouteDecision>, RouterError> {
+            Ok(Some(RouteDecision {
+                target: self.0.clone(),
+                confidence: self.1,
+                reasoning: "固定决策".to_string(),
+                strategy: "fixed".to_string(),
+                rule_id: None,
+            }))
+        }
+    }
+
+    /// 包含关键词的消息路由到 `action`
+    fn keyword_rule(keyword: &str, action: &str) -> DecisionRule {
+        DecisionRule {
+            id: format!("route_{keyword}"),
+            name: format!("{keyword}路由"),
+            description: String::new(),
+            condition: keyword.into(),
+            action: action.to_string(),
+            priority: 100,
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rules_route_messages() {
+        let router = IntelligentRouter::new();
+        assert_eq!(
+            router.route_message("查询天气").await.target,
+            RouteTarget::LocalLLM
+        );
+        router
+            .decision_engine()
+            .replace_rules(vec![
+                keyword_rule("天气", "a2a:info_agent"),
+                keyword_rule("文件", "mcp:file_processor"),
+            ])
+            .await
+            .unwrap();
+
+        let decision = router.route_message("查询天气").await;
+        assert_eq!(
+            decision.target,
+            RouteTarget::A2AAgent("info_agent".to_string())
+        );
+        assert_eq!(decision.strategy, "rules");
+
+        let decision = router.route_message("处理文件").await;
+        assert_eq!(
+            decision.target,
+            RouteTarget::MCPTool("file_processor".to_string())
+        );
+
+        let decision = router.route_message("普通问题").await;
+        assert_eq!(decision.target, RouteTarget::LocalLLM);
+        assert_eq!(decision.strategy, FALLBACK_STRATEGY);
+    }
+
+    #[tokio::test]
+    async fn test_low_confidence_falls_back_to_local_llm() {
+        let weak = Arc::new(FixedStrategy(
+            RouteTarget::MCPTool("search".to_string()),
+            0.4,
+        ));
+        let strong = Arc::new(FixedStrategy(
+            RouteTarget::A2AAgent("peer".to_string()),
+            0.7,
+        ));
+
+        let router = IntelligentRouter::new().with_strategies(vec![weak.clone()]);
+        let decision = router.route_message("任意消息").await;
+        assert_eq!(decision.target, RouteTarget::LocalLLM);
+        assert!(decision.reasoning.contains("mcp:search"));
+
+        let router = router.with_strategy(strong).with_confidence_threshold(0.5);
+        let decision = router.route_message("任意消息").await;
+        assert_eq!(decision.target, RouteTarget::A2AAgent("peer".to_string()));
+
+        let router = IntelligentRouter::new()
+            .with_strategies(vec![weak])
+            .with_confidence_threshold(0.3);
+        let decision = router.route_message("任意消息").await;
+        assert_eq!(decision.target, RouteTarget::MCPTool("search".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_feedback_only_from_reply_owner() {
+        let router = IntelligentRouter::new();
+        let decision = router.route_message("普通问题").await;
+        router.track("m1", &decision, "普通问题", Some("alice")).await;
+
+        assert!(matches!(
+            router.record_feedback("m1", 1.0, Some("bob")).await,
+            Err(RouterError::NotOwner(_))
+        ));
+        let record = router.record_feedback("m1", 1.0, Some("alice")).await.unwrap();
+        assert_eq!(record.feedback, 1.0);
+
+        // 未认证的回复和不检查调用方（管理员）时都可以评价
+        router.track("m2", &decision, "普通问题", None).await;
+        router.record_feedback("m2", -1.0, Some("bob")).await.unwrap();
+        router.track("m3", &decision, "普通问题", Some("alice")).await;
+        router.record_feedback("m3", -1.0, None).await.unwrap();
+    }
+
+    #[test]
+    fn test_route_target_names() {
+        for target in [
+            RouteTarget::LocalLLM,
+            RouteTarget::MCPTool("search".to_string()),
+            RouteTarget::A2AAgent("peer".to_string()),
+        ] {
+            assert_eq!(target.to_string().parse::<RouteTarget>().unwrap(), target);
+        }
+        assert!("mcp:".parse::<RouteTarget>().is_err());
+        assert!("agent".parse::<RouteTarget>().is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! 规则路由策略 - 由决策引擎匹配规则，规则动作是路由目标名称
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{RouteDecision, RouteTarget, RouterError, RoutingStrategy};
+use crate::core::decision::{
+    DecisionContext, DecisionEngine, DecisionError, DecisionRule, DecisionType, DEFAULT_RULE_ID,
+};
+
+/// 检查路由规则的动作都是目标名称（`llm`、`mcp:<工具>`、`a2a:<智能体>`）
+pub fn validate_rule_actions(rules: &[DecisionRule]) -> Result<(), RouterError> {
+    for rule in rules {
+        if let Err(e) = rule.action.parse::<RouteTarget>() {
+            return Err(DecisionError::InvalidRule {
+                id: rule.id.clone(),
+                reason: e.to_string(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// 规则路由策略
+pub struct RuleStrategy {
+    engine: Arc<DecisionEngine>,
+}
+
+impl RuleStrategy {
+    pub fn new(engine: Arc<DecisionEngine>) -> Self {
+        Self { engine }
+    }
+
+    /// 策略使用的决策引擎，可在运行时增删规则
+    pub fn engine(&self) -> &Arc<DecisionEngine> {
+        &self.engine
+    }
+}
+
+#[async_trait]
+impl RoutingStrategy for RuleStrategy {
+    fn name(&self) -> &str {
+        "rules"
+    }
+
+    async fn route(&self, context: &DecisionContext) -> Result<Option<RouteDecision>, RouterError> {
+        let decision = self.engine.decide(context, DecisionType::Route).await;
+        if decision.rule_id == DEFAULT_RULE_ID {
+            return Ok(None);
+        }
+        Ok(Some(RouteDecision {
+            target: decision.action.parse()?,
+            confidence: decision.confidence,
+            reasoning: decision.reasoning,
+            strategy: self.name().to_string(),
+            rule_id: Some(decision.rule_id),
+        }))
+    }
+}
@@ -0,0 +1,466 @@
+//! 分层记忆
+//!
+//! 短期记忆是对话缓冲区中的最近消息。被挤出缓冲区的消息攒够一批后由
+//! [`Summarizer`] 概括为一条中期记忆；中期记忆超出容量时最旧的一条转入长期
+//! 记忆并计算向量。每轮对话前按相关度从长期记忆中召回，相关度是与当前消息的
+//! 向量相似度按重要性（消息的 `context_relevance`）加权的结果。
+//!
+//! 记忆按 [`MemoryScope`]（用户和会话）分开保存和召回，一个会话只能召回自己的
+//! 记忆。配置了存储时长期记忆写入 [`DataRepository`]，重启后仍然保留。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::{BufferedMessage, MemoryTier, MessageType};
+use crate::integrations::database::{ConversationMemoryRecord, DataRepository, RepositoryError};
+use crate::services::embeddings::{cosine_similarity, EmbeddingProvider, HashingEmbedder};
+
+/// 摘要中每条消息保留的最大字符数
+const EXTRACT_CHARS: usize = 80;
+
+/// 分层记忆配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// 短期记忆（对话缓冲区）保留的消息数
+    pub short_term_capacity: usize,
+    /// 几条被挤出的消息概括为一条中期记忆
+    pub summary_batch: usize,
+    /// 中期记忆条数，超出后最旧的转入长期记忆
+    pub medium_term_capacity: usize,
+    /// 长期记忆条数，超出后丢弃最不重要的
+    pub long_term_capacity: usize,
+    /// 每轮召回的长期记忆条数
+    pub recall_limit: usize,
+    /// 召回的最低相关度
+    pub min_relevance: f32,
+    /// 是否为智能体的会话启用分层记忆
+    pub enabled: bool,
+    /// 是否把长期记忆写入数据库
+    pub persist: bool,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            short_term_capacity: 10,
+            summary_batch: 4,
+            medium_term_capacity: 20,
+            long_term_capacity: 1000,
+            recall_limit: 3,
+            min_relevance: 0.1,
+            enabled: false,
+            persist: true,
+        }
+    }
+}
+
+/// 记忆的归属：用户和会话
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryScope {
+    pub user_id: String,
+    pub session_id: String,
+}
+
+impl MemoryScope {
+    pub fn new(user_id: impl Into<String>, session_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            session_id: session_id.into(),
+        }
+    }
+}
+
+/// 中期或长期记忆
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub scope: MemoryScope,
+    pub tier: MemoryTier,
+    pub content: String,
+    /// 概括的消息ID
+    pub sources: Vec<Uuid>,
+    /// 0 到 1，取概括的消息中最高的 `context_relevance`
+    pub importance: f32,
+    /// 最早的源消息时间
+    pub created_at: DateTime<Utc>,
+    pub access_count: u32,
+    pub last_accessed: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    embedding: Vec<f32>,
+}
+
+impl MemoryEntry {
+    /// 直接创建一条记忆
+    pub fn new(tier: MemoryTier, content: impl Into<String>, importance: f32) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            scope: MemoryScope::default(),
+            tier,
+            content: content.into(),
+            sources: Vec::new(),
+            importance: importance.clamp(0.0, 1.0),
+            created_at: Utc::now(),
+            access_count: 0,
+            last_accessed: None,
+            embedding: Vec::new(),
+        }
+    }
+
+    fn to_record(&self) -> ConversationMemoryRecord {
+        ConversationMemoryRecord {
+            id: self.id,
+            user_id: self.scope.user_id.clone(),
+            session_id: self.scope.session_id.clone(),
+            content: self.content.clone(),
+            sources: self.sources.clone(),
+            importance: self.importance,
+            created_at: self.created_at,
+        }
+    }
+
+    fn from_record(record: ConversationMemoryRecord) -> Self {
+        Self {
+            id: record.id,
+            scope: MemoryScope::new(record.user_id, record.session_id),
+            sources: record.sources,
+            created_at: record.created_at,
+            ..Self::new(MemoryTier::LongTerm, record.content, record.importance)
+        }
+    }
+}
+
+/// 召回的记忆及其相关度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecalledMemory {
+    pub entry: MemoryEntry,
+    pub relevance: f32,
+}
+
+/// 一轮对话可用的记忆：召回的长期记忆、中期摘要和短期消息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnMemory {
+    pub recalled: Vec<RecalledMemory>,
+    pub summaries: Vec<MemoryEntry>,
+    pub recent: Vec<BufferedMessage>,
+}
+
+impl TurnMemory {
+    /// 召回的长期记忆和中期摘要合并成的上下文文本，两者都为空时返回 `None`
+    pub fn context(&self) -> Option<String> {
+        let mut sections = Vec::new();
+        if !self.recalled.is_empty() {
+            let lines: Vec<String> = self
+                .recalled
+                .iter()
+                .map(|recalled| format!("- {}", recalled.entry.content))
+                .collect();
+            sections.push(format!("相关的长期记忆:\n{}", lines.join("\n")));
+        }
+        if !self.summaries.is_empty() {
+            let lines: Vec<String> = self
+                .summaries
+                .iter()
+                .map(|summary| format!("- {}", summary.content))
+                .collect();
+            sections.push(format!("较早对话的摘要:\n{}", lines.join("\n")));
+        }
+        (!sections.is_empty()).then(|| sections.join("\n\n"))
+    }
+}
+
+/// 把一批消息概括为一段文本
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    async fn summarize(&self, messages: &[BufferedMessage]) -> Result<String, String>;
+}
+
+/// 抽取式摘要：每条消息保留角色和开头一段，不依赖 LLM
+#[derive(Debug, Clone, Default)]
+pub struct ExtractiveSummarizer;
+
+#[async_trait]
+impl Summarizer for ExtractiveSummarizer {
+    async fn summarize(&self, messages: &[BufferedMessage]) -> Result<String, String> {
+        Ok(messages
+            .iter()
+            .map(|message| {
+                let role = match message.message_type {
+                    MessageType::UserMessage => "用户",
+                    MessageType::SystemMessage => "系统",
+                    MessageType::ToolResponse => "工具",
+                    MessageType::LLMResponse => "助手",
+                };
+                format!("{role}: {}", truncate_chars(message.content.trim(), EXTRACT_CHARS))
+            })
+            .collect::<Vec<_>>()
+            .join("；"))
+    }
+}
+
+/// 按字符截断，超出时以省略号结尾
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+/// 中期和长期记忆，按 [`MemoryScope`] 分开
+pub struct TieredMemory {
+    config: MemoryConfig,
+    embedder: Arc<dyn EmbeddingProvider>,
+    summarizer: Arc<dyn Summarizer>,
+    /// 被挤出缓冲区、尚未概括的消息
+    pending: RwLock<HashMap<MemoryScope, Vec<BufferedMessage>>>,
+    medium: RwLock<HashMap<MemoryScope, VecDeque<MemoryEntry>>>,
+    long: RwLock<Vec<MemoryEntry>>,
+    store: RwLock<Option<Arc<dyn DataRepository>>>,
+}
+
+impl std::fmt::Debug for TieredMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TieredMemory")
+            .field("config", &self.config)
+            .field("embedder", &self.embedder.name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl TieredMemory {
+    pub fn new(config: MemoryConfig) -> Self {
+        Self {
+            config,
+            embedder: Arc::new(HashingEmbedder::default()),
+            summarizer: Arc::new(ExtractiveSummarizer),
+            pending: RwLock::new(HashMap::new()),
+            medium: RwLock::new(HashMap::new()),
+            long: RwLock::new(Vec::new()),
+            store: RwLock::new(None),
+        }
+    }
+
+    pub fn with_embedder(mut self, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embedder = embedder;
+        self
+    }
+
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.summarizer = summarizer;
+        self
+    }
+
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// 从存储加载长期记忆并重新计算向量，之后写入的长期记忆都保存到存储中
+    pub async fn use_store(&self, store: Arc<dyn DataRepository>) -> Result<usize, RepositoryError> {
+        let mut loaded: Vec<MemoryEntry> = store
+            .list_conversation_memories()
+            .await?
+            .into_iter()
+            .map(MemoryEntry::from_record)
+            .collect();
+        for batch in loaded.chunks_mut(64) {
+            let contents: Vec<String> = batch.iter().map(|entry| entry.content.clone()).collect();
+            match self.embedder.embed(&contents).await {
+                Ok(embeddings) => {
+                    for (entry, embedding) in batch.iter_mut().zip(embeddings) {
+                        entry.embedding = embedding;
+                    }
+                }
+                Err(e) => warn!("⚠️  记忆向量化失败，{} 条记忆无法被召回: {}", batch.len(), e),
+            }
+        }
+        let count = loaded.len();
+        *self.long.write().await = loaded;
+        *self.store.write().await = Some(store);
+        Ok(count)
+    }
+
+    /// 接收 `scope` 中被挤出短期记忆的消息，攒够一批时概括为中期记忆
+    pub async fn age(&self, scope: &MemoryScope, evicted: BufferedMessage) {
+        let batch = {
+            let mut pending = self.pending.write().await;
+            let queue = pending.entry(scope.clone()).or_default();
+            queue.push(evicted);
+            if queue.len() < self.config.summary_batch.max(1) {
+                return;
+            }
+            pending.remove(scope).unwrap_or_default()
+        };
+
+        let content = match self.summarizer.summarize(&batch).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("⚠️  概括对话失败，使用抽取式摘要: {}", e);
+                ExtractiveSummarizer.summarize(&batch).await.unwrap_or_default()
+            }
+        };
+        let entry = MemoryEntry {
+            scope: scope.clone(),
+            sources: batch.iter().map(|message| message.id).collect(),
+            importance: batch
+                .iter()
+                .map(|message| message.context_relevance)
+                .fold(0.0, f32::max)
+                .clamp(0.0, 1.0),
+            created_at: batch.first().map(|message| message.timestamp).unwrap_or_else(Utc::now),
+            ..MemoryEntry::new(MemoryTier::MediumTerm, content, 0.0)
+        };
+
+        let promoted = {
+            let mut medium = self.medium.write().await;
+            let entries = medium.entry(scope.clone()).or_default();
+            entries.push_back(entry);
+            let overflow = entries.len().saturating_sub(self.config.medium_term_capacity);
+            entries.drain(..overflow).collect::<Vec<_>>()
+        };
+        for entry in promoted {
+            self.store_long_term(entry).await;
+        }
+    }
+
+    /// 写入长期记忆，同一用户的记忆超出容量时丢弃最不重要、最旧的一条
+    pub async fn store_long_term(&self, mut entry: MemoryEntry) {
+        entry.tier = MemoryTier::LongTerm;
+        match self.embedder.embed(std::slice::from_ref(&entry.content)).await {
+            Ok(mut vectors) => entry.embedding = vectors.pop().unwrap_or_default(),
+            Err(e) => warn!("⚠️  记忆向量化失败，该记忆无法被召回: {}", e),
+        }
+        let record = entry.to_record();
+
+        let evicted = {
+            let mut long = self.long.write().await;
+            let user_id = entry.scope.user_id.clone();
+            long.push(entry);
+            let owned = long.iter().filter(|entry| entry.scope.user_id == user_id).count();
+            if owned > self.config.long_term_capacity {
+                long.iter()
+                    .enumerate()
+                    .filter(|(_, entry)| entry.scope.user_id == user_id)
+                    .min_by(|(_, a), (_, b)| {
+                        a.importance
+                            .total_cmp(&b.importance)
+                            .then(a.created_at.cmp(&b.created_at))
+                    })
+                    .map(|(index, _)| index)
+                    .map(|index| long.remove(index))
+            } else {
+                None
+            }
+        };
+
+        let Some(store) = self.store.read().await.clone() else {
+            return;
+        };
+        match evicted {
+            Some(evicted) if evicted.id == record.id => return,
+            Some(evicted) => {
+                if let Err(e) = store.delete_conversation_memory(&evicted.id).await {
+                    warn!("⚠️  删除长期记忆 {} 失败: {}", evicted.id, e);
+                }
+            }
+            None => {}
+        }
+        if let Err(e) = store.save_conversation_memory(&record).await {
+            warn!("⚠️  保存长期记忆 {} 失败，重启后将丢失: {}", record.id, e);
+        }
+    }
+
+    /// 按与 `query` 的相关度召回 `scope` 的长期记忆，最相关的在前
+    pub async fn recall(&self, scope: &MemoryScope, query: &str, limit: usize) -> Vec<RecalledMemory> {
+        if limit == 0 || !self.long.read().await.iter().any(|entry| entry.scope == *scope) {
+            return Vec::new();
+        }
+        let query = match self.embedder.embed(&[query.to_string()]).await {
+            Ok(mut vectors) => vectors.pop().unwrap_or_default(),
+            Err(e) => {
+                warn!("⚠️  查询向量化失败，跳过记忆召回: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut long = self.long.write().await;
+        let mut scored: Vec<(usize, f32)> = long
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.scope == *scope)
+            .map(|(index, entry)| {
+                let similarity = cosine_similarity(&query, &entry.embedding);
+                (index, similarity * (0.5 + 0.5 * entry.importance))
+            })
+            .filter(|(_, relevance)| *relevance >= self.config.min_relevance)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+
+        let now = Utc::now();
+        scored
+            .into_iter()
+            .map(|(index, relevance)| {
+                let entry = &mut long[index];
+                entry.access_count += 1;
+                entry.last_accessed = Some(now);
+                RecalledMemory {
+                    entry: entry.clone(),
+                    relevance,
+                }
+            })
+            .collect()
+    }
+
+    /// `scope` 的中期记忆，最旧的在前
+    pub async fn medium_term(&self, scope: &MemoryScope) -> Vec<MemoryEntry> {
+        self.medium
+            .read()
+            .await
+            .get(scope)
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 所有长期记忆，按写入顺序
+    pub async fn long_term(&self) -> Vec<MemoryEntry> {
+        self.long.read().await.clone()
+    }
+
+    /// 删除用户在所有会话中的记忆，包括存储中的长期记忆，返回删除的长期记忆条数
+    pub async fn forget_user(&self, user_id: &str) -> Result<u64, RepositoryError> {
+        self.pending.write().await.retain(|scope, _| scope.user_id != user_id);
+        self.medium.write().await.retain(|scope, _| scope.user_id != user_id);
+        let removed = {
+            let mut long = self.long.write().await;
+            let before = long.len();
+            long.retain(|entry| entry.scope.user_id != user_id);
+            (before - long.len()) as u64
+        };
+        match self.store.read().await.clone() {
+            Some(store) => store.delete_user_conversation_memories(user_id).await,
+            None => Ok(removed),
+        }
+    }
+
+    /// 清空内存中所有中期和长期记忆，不影响存储
+    pub async fn clear(&self) {
+        self.pending.write().await.clear();
+        self.medium.write().await.clear();
+        self.long.write().await.clear();
+    }
+}
+
+impl Default for TieredMemory {
+    fn default() -> Self {
+        Self::new(MemoryConfig::default())
+    }
+}
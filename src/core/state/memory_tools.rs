@@ -0,0 +1,275 @@
+//! 用户记忆工具 - `remember`、`recall`、`forget`、`list_memories`
+//!
+//! 记忆归属于调用工具的用户（见 [`ToolContext`]）和创建工具的智能体，LLM
+//! 生成的参数不能指定其他用户。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Duration;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::user_memory::{MemoryFilter, UserMemory, UserMemoryError};
+use crate::protocol::manifest::ToolAnnotations;
+use crate::services::tools::{Tool, ToolContext, ToolError};
+
+/// 当前用户在该智能体中的记忆范围
+fn scope(agent_id: &str) -> Result<MemoryFilter, ToolError> {
+    let user_id = ToolContext::current()
+        .and_then(|context| context.user_id)
+        .ok_or_else(|| ToolError::PermissionDenied("工具调用没有所属用户".to_string()))?;
+    Ok(MemoryFilter::scope(user_id, agent_id))
+}
+
+fn tags(parameters: &Value) -> Vec<String> {
+    parameters["tags"]
+        .as_array()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn storage_failed(error: UserMemoryError) -> ToolError {
+    match error {
+        UserMemoryError::EmptyContent => ToolError::ValidationFailed(error.to_string()),
+        _ => ToolError::ExecutionFailed(error.to_string()),
+    }
+}
+
+fn read_only() -> Option<ToolAnnotations> {
+    Some(ToolAnnotations {
+        read_only_hint: Some(true),
+        ..ToolAnnotations::default()
+    })
+}
+
+/// 记住关于用户的事实
+pub struct RememberTool {
+    memory: Arc<UserMemory>,
+    agent_id: String,
+}
+
+impl RememberTool {
+    pub fn new(memory: Arc<UserMemory>, agent_id: impl Into<String>) -> Self {
+        Self {
+            memory,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for RememberTool {
+    fn name(&self) -> &str {
+        "remember"
+    }
+
+    fn description(&self) -> &str {
+        "Save a fact about the current user for later conversations"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        let scope = scope(&self.agent_id)?;
+        let content = parameters["content"]
+            .as_str()
+            .ok_or_else(|| ToolError::ValidationFailed("缺少参数 content".to_string()))?;
+        let ttl = parameters["ttl_secs"]
+            .as_u64()
+            .map(|secs| Duration::seconds(secs as i64));
+        let memory = self
+            .memory
+            .remember(
+                scope.user_id.as_deref().unwrap_or_default(),
+                &self.agent_id,
+                content,
+                tags(&parameters),
+                ttl,
+            )
+            .await
+            .map_err(storage_failed)?;
+        Ok(json!({ "memory": memory }))
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        Some(ToolAnnotations {
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            ..ToolAnnotations::default()
+        })
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "content": {"type": "string", "description": "The fact to remember"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "ttl_secs": {"type": "integer", "minimum": 1, "description": "Forget the fact after this many seconds"}
+            },
+            "required": ["content"]
+        }))
+    }
+}
+
+/// 召回与查询相关的记忆
+pub struct RecallTool {
+    memory: Arc<UserMemory>,
+    agent_id: String,
+}
+
+impl RecallTool {
+    pub fn new(memory: Arc<UserMemory>, agent_id: impl Into<String>) -> Self {
+        Self {
+            memory,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for RecallTool {
+    fn name(&self) -> &str {
+        "recall"
+    }
+
+    fn description(&self) -> &str {
+        "Find saved facts about the current user that relate to a query"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        let filter = scope(&self.agent_id)?.with_tags(tags(&parameters));
+        let query = parameters["query"].as_str().unwrap_or_default();
+        let limit = parameters["limit"]
+            .as_u64()
+            .map_or(self.memory.config().recall_limit, |limit| limit as usize);
+        let hits = self
+            .memory
+            .recall(&filter, query, limit)
+            .await
+            .map_err(storage_failed)?;
+        Ok(json!({ "memories": hits }))
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        read_only()
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "description": "What to look for; empty returns the newest facts"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "limit": {"type": "integer", "minimum": 1}
+            }
+        }))
+    }
+}
+
+/// 删除一条记忆
+pub struct ForgetTool {
+    memory: Arc<UserMemory>,
+    agent_id: String,
+}
+
+impl ForgetTool {
+    pub fn new(memory: Arc<UserMemory>, agent_id: impl Into<String>) -> Self {
+        Self {
+            memory,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ForgetTool {
+    fn name(&self) -> &str {
+        "forget"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a saved fact about the current user by its id"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        let scope = scope(&self.agent_id)?;
+        let id = parameters["id"]
+            .as_str()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| ToolError::ValidationFailed("缺少有效的参数 id".to_string()))?;
+        let forgotten = self
+            .memory
+            .forget(&id, &scope)
+            .await
+            .map_err(storage_failed)?;
+        Ok(json!({ "forgotten": forgotten }))
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        Some(ToolAnnotations {
+            read_only_hint: Some(false),
+            destructive_hint: Some(true),
+            idempotent_hint: Some(true),
+            ..ToolAnnotations::default()
+        })
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "string", "description": "Id of the fact, from recall or list_memories"}
+            },
+            "required": ["id"]
+        }))
+    }
+}
+
+/// 列出当前用户的记忆
+pub struct ListMemoriesTool {
+    memory: Arc<UserMemory>,
+    agent_id: String,
+}
+
+impl ListMemoriesTool {
+    pub fn new(memory: Arc<UserMemory>, agent_id: impl Into<String>) -> Self {
+        Self {
+            memory,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ListMemoriesTool {
+    fn name(&self) -> &str {
+        "list_memories"
+    }
+
+    fn description(&self) -> &str {
+        "List the saved facts about the current user, oldest first"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        let filter = scope(&self.agent_id)?.with_tags(tags(&parameters));
+        Ok(json!({ "memories": self.memory.list(&filter).await }))
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        read_only()
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        }))
+    }
+}
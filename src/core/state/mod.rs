@@ -1,4 +1,19 @@
-//! 状态管理器模块 - 简化版
+//! 状态管理器模块
+//!
+//! 对话缓冲区是短期记忆，被挤出的消息经 [`TieredMemory`] 概括为中期记忆，
+//! 再转入可按相关度召回的长期记忆。智能体为用户明确记住的事实保存在
+//! [`UserMemory`] 中，可以持久化。
+
+pub mod memory;
+pub mod memory_tools;
+pub mod user_memory;
+
+pub use memory::{
+    ExtractiveSummarizer, MemoryConfig, MemoryEntry, MemoryScope, RecalledMemory, Summarizer,
+    TieredMemory, TurnMemory,
+};
+pub use memory_tools::{ForgetTool, ListMemoriesTool, RecallTool, RememberTool};
+pub use user_memory::{MemoryFilter, UserMemory, UserMemoryConfig, UserMemoryError, UserMemoryHit};
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -7,8 +22,11 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::collections::VecDeque;
 
+use crate::protocol::message::{Message, MessageContent};
+
 /// 内存层级
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MemoryTier {
     ShortTerm,
     MediumTerm,
@@ -31,9 +49,42 @@ pub struct BufferedMessage {
     pub content: String,
     pub timestamp: DateTime<Utc>,
     pub message_type: MessageType,
+    /// 0 到 1 的重要性，决定概括后的记忆被召回的权重
     pub context_relevance: f32,
 }
 
+impl From<&Message> for BufferedMessage {
+    /// 按元数据中的 `role` 区分消息类型，没有时按内容判断
+    fn from(message: &Message) -> Self {
+        let role = message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("role"))
+            .and_then(|role| role.as_str());
+        let message_type = match (role, &message.content) {
+            (Some("system"), _) => MessageType::SystemMessage,
+            (Some("assistant"), _) | (None, MessageContent::ToolCall { .. }) => {
+                MessageType::LLMResponse
+            }
+            (Some("tool"), _) | (None, MessageContent::ToolResult { .. }) => {
+                MessageType::ToolResponse
+            }
+            _ => MessageType::UserMessage,
+        };
+        let content = match &message.content {
+            MessageContent::Text { text } => text.clone(),
+            other => serde_json::to_string(other).unwrap_or_default(),
+        };
+        Self {
+            id: message.id,
+            content,
+            timestamp: message.timestamp,
+            message_type,
+            context_relevance: 1.0,
+        }
+    }
+}
+
 /// 对话缓冲区
 pub struct ConversationBuffer {
     messages: Arc<RwLock<VecDeque<BufferedMessage>>>,
@@ -62,17 +113,22 @@ impl ConversationBuffer {
 
     /// 添加消息到缓冲区
     pub async fn add_message(&self, message: BufferedMessage) -> Result<(), String> {
+        self.push(message).await;
+        Ok(())
+    }
+
+    /// 添加消息到缓冲区，缓冲区已满时移除并返回最旧的消息
+    pub async fn push(&self, message: BufferedMessage) -> Option<BufferedMessage> {
         let mut messages = self.messages.write().await;
-        
-        // 如果缓冲区已满，移除最旧的消息
-        if messages.len() >= self.max_size {
-            messages.pop_front();
-        }
-        
+        let evicted = if messages.len() >= self.max_size {
+            messages.pop_front()
+        } else {
+            None
+        };
+
         messages.push_back(message);
         self.current_size.store(messages.len(), Ordering::Relaxed);
-        
-        Ok(())
+        evicted
     }
 
     /// 获取缓冲区中的所有消息
@@ -94,18 +150,38 @@ impl ConversationBuffer {
 #[derive(Clone)]
 pub struct StateManager {
     conversation_buffer: Arc<ConversationBuffer>,
+    memory: Arc<TieredMemory>,
+    user_memory: Option<Arc<UserMemory>>,
 }
 
 impl StateManager {
     pub fn new() -> Self {
+        Self::with_memory(TieredMemory::default())
+    }
+
+    /// 使用指定的分层记忆，缓冲区大小取其短期记忆容量
+    pub fn with_memory(memory: TieredMemory) -> Self {
         Self {
-            conversation_buffer: Arc::new(ConversationBuffer::new(10)), // 默认缓冲区大小为10
+            conversation_buffer: Arc::new(ConversationBuffer::new(
+                memory.config().short_term_capacity.max(1),
+            )),
+            memory: Arc::new(memory),
+            user_memory: None,
         }
     }
 
-    /// 添加消息到对话缓冲区
+    /// 使用用户长期记忆；配置了存储的 [`UserMemory`] 在重启后仍然保留
+    pub fn with_user_memory(mut self, user_memory: Arc<UserMemory>) -> Self {
+        self.user_memory = Some(user_memory);
+        self
+    }
+
+    /// 添加消息到对话缓冲区，挤出的消息转入中期记忆
     pub async fn add_to_buffer(&self, message: BufferedMessage) -> Result<(), String> {
-        self.conversation_buffer.add_message(message).await
+        if let Some(evicted) = self.conversation_buffer.push(message).await {
+            self.memory.age(&MemoryScope::default(), evicted).await;
+        }
+        Ok(())
     }
 
     /// 从对话缓冲区获取消息
@@ -122,4 +198,97 @@ impl StateManager {
     pub fn buffer_size(&self) -> usize {
         self.conversation_buffer.current_size.load(Ordering::Relaxed)
     }
-}
\ No newline at end of file
+
+    /// 中期和长期记忆
+    pub fn memory(&self) -> &TieredMemory {
+        &self.memory
+    }
+
+    /// 用户长期记忆
+    pub fn user_memory(&self) -> Option<&Arc<UserMemory>> {
+        self.user_memory.as_ref()
+    }
+
+    /// 回答 `query` 前可用的记忆：召回的长期记忆、中期摘要和缓冲区中的消息。
+    /// 状态管理器只有一个对话缓冲区，记忆都归属于默认的 [`MemoryScope`]
+    pub async fn turn_memory(&self, query: &str) -> TurnMemory {
+        let scope = MemoryScope::default();
+        TurnMemory {
+            recalled: self
+                .memory
+                .recall(&scope, query, self.memory.config().recall_limit)
+                .await,
+            summaries: self.memory.medium_term(&scope).await,
+            recent: self.get_buffer_messages().await,
+        }
+    }
+}
+
+impl Default for StateManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(content: &str, relevance: f32) -> BufferedMessage {
+        BufferedMessage {
+            id: uuid::Uuid::new_v4(),
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            message_type: MessageType::UserMessage,
+            context_relevance: relevance,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_age_into_summaries_and_long_term_memory() {
+        let state = StateManager::with_memory(TieredMemory::new(MemoryConfig {
+            short_term_capacity: 2,
+            summary_batch: 2,
+            medium_term_capacity: 1,
+            ..MemoryConfig::default()
+        }));
+        let contents = [
+            "我的项目叫 Falcon，用 Rust 编写",
+            "部署在法兰克福的服务器上",
+            "今天的天气不错",
+            "午饭吃了面条",
+            "明天开会",
+            "记得买牛奶",
+        ];
+        for (i, content) in contents.iter().enumerate() {
+            let relevance = if i < 2 { 1.0 } else { 0.2 };
+            state.add_to_buffer(message(content, relevance)).await.unwrap();
+        }
+
+        // 2 条在缓冲区，2 条概括为中期记忆，最早的 2 条转入长期记忆
+        assert_eq!(state.buffer_size(), 2);
+        let medium = state.memory().medium_term(&MemoryScope::default()).await;
+        assert_eq!(medium.len(), 1);
+        assert_eq!(medium[0].sources.len(), 2);
+        assert!(medium[0].content.contains("午饭"));
+        let long = state.memory().long_term().await;
+        assert_eq!(long.len(), 1);
+        assert_eq!(long[0].tier, MemoryTier::LongTerm);
+        assert_eq!(long[0].importance, 1.0);
+
+        let memory = state.turn_memory("Falcon 项目部署在哪里").await;
+        assert_eq!(memory.recalled.len(), 1);
+        assert!(memory.recalled[0].entry.content.contains("Falcon"));
+        assert_eq!(memory.recalled[0].entry.access_count, 1);
+        assert_eq!(memory.summaries.len(), 1);
+        assert_eq!(memory.recent.len(), 2);
+
+        assert!(state.turn_memory("completely unrelated words").await.recalled.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_chars_respects_utf8_boundaries() {
+        assert_eq!(memory::truncate_chars("你好世界", 2), "你好…");
+        assert_eq!(memory::truncate_chars("hi", 5), "hi");
+    }
+}
@@ -0,0 +1,476 @@
+//! 用户长期记忆
+//!
+//! 智能体为某个用户明确记住的事实，按用户ID和智能体ID归属，可以带标签和过期
+//! 时间。与 [`TieredMemory`](super::TieredMemory) 自动概括的对话记忆不同，这里的
+//! 记忆由 `remember` 工具或 REST 接口写入，配置存储后通过 [`DataRepository`]
+//! 持久化，重启后仍然存在。召回按与查询的向量相似度排序。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::integrations::database::{DataRepository, MemoryRecord, RepositoryError};
+use crate::services::embeddings::{cosine_similarity, EmbeddingError, EmbeddingProvider};
+
+/// 用户记忆错误
+#[derive(Debug, thiserror::Error)]
+pub enum UserMemoryError {
+    #[error("记忆内容不能为空")]
+    EmptyContent,
+    #[error("向量化失败: {0}")]
+    Embedding(#[from] EmbeddingError),
+    #[error("存储失败: {0}")]
+    Storage(#[from] RepositoryError),
+}
+
+/// 用户记忆配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserMemoryConfig {
+    pub enabled: bool,
+    /// 未指定过期时间的记忆保留的秒数，为空表示永不过期
+    pub default_ttl_secs: Option<u64>,
+    /// 每个用户在每个智能体中最多保留的记忆数，超出后删除最旧的
+    pub max_per_user: usize,
+    /// 每次召回的条数
+    pub recall_limit: usize,
+    /// 召回的最低相似度
+    pub min_relevance: f32,
+    /// 是否在每轮对话前召回当前用户的记忆并加入上下文
+    pub auto_recall: bool,
+    /// 配置了数据库时是否持久化
+    pub persist: bool,
+}
+
+impl Default for UserMemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_ttl_secs: None,
+            max_per_user: 500,
+            recall_limit: 5,
+            min_relevance: 0.1,
+            auto_recall: true,
+            persist: true,
+        }
+    }
+}
+
+/// 记忆的筛选条件，标签须全部匹配
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryFilter {
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl MemoryFilter {
+    /// 某个用户在某个智能体中的记忆
+    pub fn scope(user_id: impl Into<String>, agent_id: impl Into<String>) -> Self {
+        Self {
+            user_id: Some(user_id.into()),
+            agent_id: Some(agent_id.into()),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn matches(&self, memory: &MemoryRecord) -> bool {
+        self.user_id
+            .as_ref()
+            .is_none_or(|user| *user == memory.user_id)
+            && self
+                .agent_id
+                .as_ref()
+                .is_none_or(|agent| *agent == memory.agent_id)
+            && self.tags.iter().all(|tag| memory.tags.contains(tag))
+    }
+}
+
+/// 召回的记忆及其相似度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserMemoryHit {
+    pub memory: MemoryRecord,
+    pub relevance: f32,
+}
+
+struct Entry {
+    record: MemoryRecord,
+    embedding: Vec<f32>,
+}
+
+/// 用户长期记忆
+pub struct UserMemory {
+    config: UserMemoryConfig,
+    embedder: Arc<dyn EmbeddingProvider>,
+    entries: RwLock<HashMap<Uuid, Entry>>,
+    store: RwLock<Option<Arc<dyn DataRepository>>>,
+}
+
+impl std::fmt::Debug for UserMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserMemory")
+            .field("config", &self.config)
+            .field("embedder", &self.embedder.name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl UserMemory {
+    pub fn new(config: UserMemoryConfig, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        Self {
+            config,
+            embedder,
+            entries: RwLock::new(HashMap::new()),
+            store: RwLock::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &UserMemoryConfig {
+        &self.config
+    }
+
+    /// 使用存储持久化记忆，并载入存储中未过期的记忆，返回载入的条数
+    pub async fn use_store(
+        &self,
+        store: Arc<dyn DataRepository>,
+    ) -> Result<usize, UserMemoryError> {
+        let now = Utc::now();
+        let mut records = Vec::new();
+        for record in store.list_memories(None, None).await? {
+            if is_expired(&record, now) {
+                store.delete_memory(&record.id).await?;
+            } else {
+                records.push(record);
+            }
+        }
+
+        let mut loaded = HashMap::with_capacity(records.len());
+        for batch in records.chunks(64) {
+            let contents: Vec<String> = batch.iter().map(|record| record.content.clone()).collect();
+            let embeddings = self.embedder.embed(&contents).await?;
+            for (record, embedding) in batch.iter().zip(embeddings) {
+                loaded.insert(
+                    record.id,
+                    Entry {
+                        record: record.clone(),
+                        embedding,
+                    },
+                );
+            }
+        }
+        let count = loaded.len();
+        *self.entries.write().await = loaded;
+        *self.store.write().await = Some(store);
+        Ok(count)
+    }
+
+    /// 记住一条内容；`ttl` 为空时使用配置的默认保留时间
+    pub async fn remember(
+        &self,
+        user_id: &str,
+        agent_id: &str,
+        content: &str,
+        tags: Vec<String>,
+        ttl: Option<Duration>,
+    ) -> Result<MemoryRecord, UserMemoryError> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(UserMemoryError::EmptyContent);
+        }
+        self.purge_expired().await?;
+
+        let now = Utc::now();
+        let ttl = ttl.or_else(|| {
+            self.config
+                .default_ttl_secs
+                .map(|secs| Duration::seconds(secs as i64))
+        });
+        let mut tags = tags;
+        tags.sort();
+        tags.dedup();
+        let record = MemoryRecord {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            agent_id: agent_id.to_string(),
+            content: content.to_string(),
+            tags,
+            created_at: now,
+            updated_at: now,
+            expires_at: ttl.map(|ttl| now + ttl),
+        };
+        let embedding = self.embedder.embed_one(content).await?;
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.save_memory(&record).await?;
+        }
+
+        let oldest = {
+            let mut entries = self.entries.write().await;
+            entries.insert(
+                record.id,
+                Entry {
+                    record: record.clone(),
+                    embedding,
+                },
+            );
+            let scope = MemoryFilter::scope(user_id, agent_id);
+            let mut owned: Vec<(DateTime<Utc>, Uuid)> = entries
+                .values()
+                .filter(|entry| scope.matches(&entry.record))
+                .map(|entry| (entry.record.created_at, entry.record.id))
+                .collect();
+            owned.sort();
+            let overflow = owned.len().saturating_sub(self.config.max_per_user.max(1));
+            owned.truncate(overflow);
+            owned
+        };
+        for (_, id) in oldest {
+            self.remove(&id).await?;
+        }
+        Ok(record)
+    }
+
+    /// 按与 `query` 的相似度召回匹配 `filter` 的记忆，最相关的在前；`query`
+    /// 为空时返回最新的记忆
+    pub async fn recall(
+        &self,
+        filter: &MemoryFilter,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<UserMemoryHit>, UserMemoryError> {
+        if query.trim().is_empty() {
+            let mut memories = self.list(filter).await;
+            memories.reverse();
+            memories.truncate(limit);
+            return Ok(memories
+                .into_iter()
+                .map(|memory| UserMemoryHit {
+                    memory,
+                    relevance: 1.0,
+                })
+                .collect());
+        }
+
+        let query = self.embedder.embed_one(query).await?;
+        let now = Utc::now();
+        let entries = self.entries.read().await;
+        let mut hits: Vec<UserMemoryHit> = entries
+            .values()
+            .filter(|entry| filter.matches(&entry.record) && !is_expired(&entry.record, now))
+            .map(|entry| UserMemoryHit {
+                memory: entry.record.clone(),
+                relevance: cosine_similarity(&query, &entry.embedding),
+            })
+            .filter(|hit| hit.relevance >= self.config.min_relevance)
+            .collect();
+        hits.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// 匹配 `filter` 的未过期记忆，按创建时间顺序
+    pub async fn list(&self, filter: &MemoryFilter) -> Vec<MemoryRecord> {
+        let now = Utc::now();
+        let mut memories: Vec<MemoryRecord> = self
+            .entries
+            .read()
+            .await
+            .values()
+            .filter(|entry| filter.matches(&entry.record) && !is_expired(&entry.record, now))
+            .map(|entry| entry.record.clone())
+            .collect();
+        memories.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        memories
+    }
+
+    /// 按ID获取未过期的记忆
+    pub async fn get(&self, id: &Uuid) -> Option<MemoryRecord> {
+        let entries = self.entries.read().await;
+        let record = &entries.get(id)?.record;
+        (!is_expired(record, Utc::now())).then(|| record.clone())
+    }
+
+    /// 删除匹配 `filter` 的记忆中ID为 `id` 的一条，返回是否删除
+    pub async fn forget(&self, id: &Uuid, filter: &MemoryFilter) -> Result<bool, UserMemoryError> {
+        let owned = self
+            .entries
+            .read()
+            .await
+            .get(id)
+            .is_some_and(|entry| filter.matches(&entry.record));
+        if !owned {
+            return Ok(false);
+        }
+        self.remove(id).await
+    }
+
+    /// 删除用户在所有智能体中的记忆，返回删除的条数
+    pub async fn forget_user(&self, user_id: &str) -> Result<usize, UserMemoryError> {
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.delete_user_memories(user_id).await?;
+        }
+        let mut entries = self.entries.write().await;
+        let before = entries.len();
+        entries.retain(|_, entry| entry.record.user_id != user_id);
+        Ok(before - entries.len())
+    }
+
+    /// 删除所有过期的记忆，返回删除的条数
+    pub async fn purge_expired(&self) -> Result<usize, UserMemoryError> {
+        let now = Utc::now();
+        let expired: Vec<Uuid> = self
+            .entries
+            .read()
+            .await
+            .values()
+            .filter(|entry| is_expired(&entry.record, now))
+            .map(|entry| entry.record.id)
+            .collect();
+        for id in &expired {
+            self.remove(id).await?;
+        }
+        Ok(expired.len())
+    }
+
+    async fn remove(&self, id: &Uuid) -> Result<bool, UserMemoryError> {
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.delete_memory(id).await?;
+        }
+        Ok(self.entries.write().await.remove(id).is_some())
+    }
+}
+
+fn is_expired(record: &MemoryRecord, now: DateTime<Utc>) -> bool {
+    record
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+}
+
+/// 把召回的记忆整理为加入上下文的说明
+pub fn format_memories(hits: &[UserMemoryHit]) -> String {
+    let mut text = String::from("关于当前用户的长期记忆:");
+    for hit in hits {
+        text.push_str(&format!("\n- {}", hit.memory.content));
+        if !hit.memory.tags.is_empty() {
+            text.push_str(&format!(" [{}]", hit.memory.tags.join(", ")));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::embeddings::HashingEmbedder;
+
+    fn memory(max_per_user: usize) -> UserMemory {
+        UserMemory::new(
+            UserMemoryConfig {
+                enabled: true,
+                max_per_user,
+                ..UserMemoryConfig::default()
+            },
+            Arc::new(HashingEmbedder::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_recall_is_scoped_and_ranked() {
+        let memory = memory(10);
+        memory
+            .remember(
+                "alice",
+                "omni",
+                "Alice prefers tea over coffee",
+                vec!["preference".into()],
+                None,
+            )
+            .await
+            .unwrap();
+        memory
+            .remember(
+                "alice",
+                "omni",
+                "Alice works on the Falcon project",
+                vec![],
+                None,
+            )
+            .await
+            .unwrap();
+        memory
+            .remember(
+                "bob",
+                "omni",
+                "Bob prefers coffee",
+                vec!["preference".into()],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let alice = MemoryFilter::scope("alice", "omni");
+        let hits = memory
+            .recall(&alice, "what does alice drink, tea?", 5)
+            .await
+            .unwrap();
+        assert_eq!(hits[0].memory.content, "Alice prefers tea over coffee");
+        assert!(hits.iter().all(|hit| hit.memory.user_id == "alice"));
+
+        let tagged = memory
+            .recall(&alice.clone().with_tags(vec!["preference".into()]), "", 5)
+            .await
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+
+        // 不能删除其他用户的记忆
+        let bob_memory = memory.list(&MemoryFilter::scope("bob", "omni")).await[0].id;
+        assert!(!memory.forget(&bob_memory, &alice).await.unwrap());
+        assert_eq!(memory.forget_user("alice").await.unwrap(), 2);
+        assert_eq!(memory.list(&MemoryFilter::default()).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_and_capacity() {
+        let memory = memory(2);
+        memory
+            .remember(
+                "alice",
+                "omni",
+                "short lived",
+                vec![],
+                Some(Duration::seconds(-1)),
+            )
+            .await
+            .unwrap();
+        assert!(memory.list(&MemoryFilter::default()).await.is_empty());
+
+        for content in ["first", "second", "third"] {
+            memory
+                .remember("alice", "omni", content, vec![], None)
+                .await
+                .unwrap();
+        }
+        let contents: Vec<String> = memory
+            .list(&MemoryFilter::scope("alice", "omni"))
+            .await
+            .into_iter()
+            .map(|memory| memory.content)
+            .collect();
+        assert_eq!(contents, ["second", "third"]);
+        assert!(matches!(
+            memory.remember("alice", "omni", "  ", vec![], None).await,
+            Err(UserMemoryError::EmptyContent)
+        ));
+    }
+}
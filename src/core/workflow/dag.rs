@@ -0,0 +1,79 @@
+//! 工作流依赖图 - 校验步骤依赖并计算拓扑顺序
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{Workflow, WorkflowError};
+
+/// 校验工作流的依赖图，返回一个拓扑顺序
+///
+/// 检查步骤ID唯一、依赖的步骤存在且不存在环。
+pub fn topological_order(workflow: &Workflow) -> Result<Vec<String>, WorkflowError> {
+    let mut ids = HashSet::new();
+    for step in &workflow.steps {
+        if !ids.insert(step.id.as_str()) {
+            return Err(WorkflowError::DuplicateStep(step.id.clone()));
+        }
+    }
+
+    let mut in_degree: HashMap<&str, usize> = HashMap::new();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for step in &workflow.steps {
+        in_degree.insert(step.id.as_str(), step.dependencies.len());
+        for dependency in &step.dependencies {
+            if !ids.contains(dependency.as_str()) {
+                return Err(WorkflowError::UnknownDependency {
+                    step: step.id.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+            dependents
+                .entry(dependency.as_str())
+                .or_default()
+                .push(step.id.as_str());
+        }
+    }
+
+    // Kahn 算法：按声明顺序处理入度为零的步骤
+    let mut ready: VecDeque<&str> = workflow
+        .steps
+        .iter()
+        .map(|step| step.id.as_str())
+        .filter(|id| in_degree[id] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(workflow.steps.len());
+    while let Some(id) = ready.pop_front() {
+        order.push(id.to_string());
+        for dependent in dependents.get(id).into_iter().flatten() {
+            let degree = in_degree.get_mut(dependent).expect("依赖图中的步骤");
+            *degree -= 1;
+            if *degree == 0 {
+                ready.push_back(dependent);
+            }
+        }
+    }
+
+    if order.len() < workflow.steps.len() {
+        let cycle = workflow
+            .steps
+            .iter()
+            .filter(|step| in_degree[step.id.as_str()] > 0)
+            .map(|step| step.id.clone())
+            .collect();
+        return Err(WorkflowError::Cycle(cycle));
+    }
+    Ok(order)
+}
+
+/// 每个步骤的直接下游步骤
+pub fn dependents(workflow: &Workflow) -> HashMap<String, Vec<String>> {
+    let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+    for step in &workflow.steps {
+        for dependency in &step.dependencies {
+            dependents
+                .entry(dependency.clone())
+                .or_default()
+                .push(step.id.clone());
+        }
+    }
+    dependents
+}
@@ -0,0 +1,416 @@
+//! 工作流定义文件 - 从 YAML/JSON 加载并校验工作流
+//!
+//! ```yaml
+//! id: city-brief
+//! name: 城市简报
+//! inputs:
+//!   city: { required: true }
+//! steps:
+//!   - id: weather
+//!     target: { type: mcp_tool, tool: get_weather, arguments: { city: "{{inputs.city}}" } }
+//!   - id: brief
+//!     depends_on: [weather]
+//!     target: { type: llm, prompt: "根据天气写一段简报: {{steps.weather.output}}" }
+//! output: "{{steps.brief.output}}"
+//! ```
+//!
+//! 步骤还可以设置 `condition`（别名 `if`）、`for_each`、`concurrency`、
+//! `retry` 和 `on_error`：
+//!
+//! ```yaml
+//! - id: notify
+//!   depends_on: [weather]
+//!   if: "steps.weather.output.alerts contains 'storm'"
+//!   for_each: "{{inputs.recipients}}"
+//!   concurrency: 2
+//!   retry: { max_attempts: 3, backoff_ms: 200 }
+//!   on_error: { output: "通知失败: {{error}}" }
+//!   target: { type: a2a, agent: mailer, input: { to: "{{item}}" } }
+//! ```
+//!
+//! 文件结构由 [`Workflow`] 的 serde 定义约束，未知字段会被拒绝；随后检查
+//! 步骤ID、依赖图、条件表达式，以及模板只引用已声明的输入和上游步骤。
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use super::expression::Expression;
+use super::template::{self, Reference, TemplateContext};
+use super::{dag, StepTarget, Workflow, WorkflowError};
+
+impl Workflow {
+    /// 从 YAML 文本解析并校验工作流
+    pub fn from_yaml(text: &str) -> Result<Self, WorkflowError> {
+        let workflow: Workflow = serde_yaml::from_str(text)
+            .map_err(|e| WorkflowError::InvalidDefinition(e.to_string()))?;
+        workflow.validate()?;
+        Ok(workflow)
+    }
+
+    /// 从 JSON 文本解析并校验工作流
+    pub fn from_json(text: &str) -> Result<Self, WorkflowError> {
+        let workflow: Workflow = serde_json::from_str(text)
+            .map_err(|e| WorkflowError::InvalidDefinition(e.to_string()))?;
+        workflow.validate()?;
+        Ok(workflow)
+    }
+
+    /// 按扩展名读取工作流文件
+    pub fn from_file(path: &Path) -> Result<Self, WorkflowError> {
+        let invalid = |reason: String| {
+            WorkflowError::InvalidDefinition(format!("{}: {reason}", path.display()))
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&text),
+            _ => Self::from_yaml(&text),
+        };
+        parsed.map_err(|e| match e {
+            WorkflowError::InvalidDefinition(reason) => invalid(reason),
+            other => invalid(other.to_string()),
+        })
+    }
+
+    /// 校验步骤ID、依赖图和模板引用
+    pub fn validate(&self) -> Result<(), WorkflowError> {
+        let invalid = |reason: String| WorkflowError::InvalidDefinition(reason);
+        if !is_identifier(&self.id) {
+            return Err(invalid(format!("工作流ID无效: `{}`", self.id)));
+        }
+        if let Some(step) = self.steps.iter().find(|step| !is_identifier(&step.id)) {
+            return Err(invalid(format!("步骤ID无效: `{}`", step.id)));
+        }
+        let order = dag::topological_order(self)?;
+
+        // 每个步骤的上游步骤（传递依赖）
+        let steps: HashMap<&str, _> = self.steps.iter().map(|s| (s.id.as_str(), s)).collect();
+        let mut ancestors: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for id in &order {
+            let mut upstream = HashSet::new();
+            for dependency in &steps[id.as_str()].dependencies {
+                upstream.insert(dependency.as_str());
+                upstream.extend(ancestors[dependency.as_str()].iter().copied());
+            }
+            ancestors.insert(id.as_str(), upstream);
+        }
+
+        for step in &self.steps {
+            let step_invalid = |reason: String| invalid(format!("步骤 {}: {reason}", step.id));
+            if step.concurrency.is_some() && step.for_each.is_none() {
+                return Err(step_invalid(
+                    "concurrency 只能用于 for_each 步骤".to_string(),
+                ));
+            }
+            if step.concurrency == Some(0) {
+                return Err(step_invalid("concurrency 必须大于 0".to_string()));
+            }
+            if step
+                .retry
+                .as_ref()
+                .is_some_and(|retry| retry.max_attempts == 0)
+            {
+                return Err(step_invalid("retry.max_attempts 必须大于 0".to_string()));
+            }
+
+            // (位置, 引用, 可用 item/index, 可用 error)
+            let looped = step.for_each.is_some();
+            let mut scopes: Vec<(&str, Vec<Reference>, bool, bool)> = Vec::new();
+            if let Some(condition) = &step.condition {
+                let references = Expression::parse(condition)
+                    .map_err(|e| step_invalid(e.to_string()))?
+                    .references()
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                scopes.push(("条件", references, false, false));
+            }
+            if let Some(items) = &step.for_each {
+                scopes.push((
+                    "for_each",
+                    template::references(&Value::String(items.clone()))?,
+                    false,
+                    false,
+                ));
+            }
+            if let Some(target) = &step.target {
+                scopes.push(("目标", target.references()?, looped, false));
+            }
+            if let Some(handler) = &step.on_error {
+                match (&handler.target, &handler.output) {
+                    (Some(_), Some(_)) => {
+                        return Err(step_invalid(
+                            "on_error 不能同时指定 target 和 output".to_string(),
+                        ));
+                    }
+                    (Some(target), None) => {
+                        scopes.push(("on_error", target.references()?, false, true))
+                    }
+                    (None, Some(output)) => {
+                        scopes.push(("on_error", template::references(output)?, false, true))
+                    }
+                    (None, None) => {}
+                }
+            }
+
+            for (place, references, item, error) in scopes {
+                for reference in references {
+                    self.check_reference(
+                        &reference,
+                        |id| ancestors[step.id.as_str()].contains(id),
+                        item,
+                        error,
+                    )
+                    .map_err(|reason| step_invalid(format!("{place}: {reason}")))?;
+                }
+            }
+        }
+        if let Some(output) = &self.output {
+            for reference in template::references(output)? {
+                self.check_reference(&reference, |id| steps.contains_key(id), false, false)
+                    .map_err(|reason| invalid(format!("输出: {reason}")))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_reference(
+        &self,
+        reference: &Reference,
+        visible: impl Fn(&str) -> bool,
+        item: bool,
+        error: bool,
+    ) -> Result<(), String> {
+        match reference {
+            Reference::Item { .. } | Reference::Index if !item => {
+                Err("`item` 和 `index` 只能用于 for_each 步骤的目标".to_string())
+            }
+            Reference::Error if !error => Err("`error` 只能用于 on_error".to_string()),
+            Reference::Input { name, .. }
+                if !self.inputs.is_empty() && !self.inputs.contains_key(name) =>
+            {
+                Err(format!("引用了未声明的输入 `{name}`"))
+            }
+            Reference::Step { id, .. } if !visible(id) => {
+                Err(format!("引用的步骤 `{id}` 不存在或不是上游步骤"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 校验输入并填充默认值
+    pub fn resolve_inputs(
+        &self,
+        mut inputs: Map<String, Value>,
+    ) -> Result<Map<String, Value>, WorkflowError> {
+        if self.inputs.is_empty() {
+            return Ok(inputs);
+        }
+        if let Some(name) = inputs.keys().find(|name| !self.inputs.contains_key(*name)) {
+            return Err(WorkflowError::UnknownInput(name.clone()));
+        }
+        for (name, declaration) in &self.inputs {
+            if inputs.contains_key(name) {
+                continue;
+            }
+            match &declaration.default {
+                Some(default) => {
+                    inputs.insert(name.clone(), default.clone());
+                }
+                None if declaration.required => {
+                    return Err(WorkflowError::MissingInput(name.clone()))
+                }
+                None => {}
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+impl StepTarget {
+    /// 目标中出现的所有模板引用
+    pub fn references(&self) -> Result<Vec<Reference>, WorkflowError> {
+        template::references(&self.templates())
+    }
+
+    /// 展开目标中的模板
+    pub fn render(self, context: &TemplateContext) -> Result<Self, WorkflowError> {
+        Ok(match self {
+            StepTarget::Llm {
+                prompt,
+                provider,
+                model,
+                temperature,
+            } => StepTarget::Llm {
+                prompt: context.render_text(&prompt)?,
+                provider,
+                model,
+                temperature,
+            },
+            StepTarget::McpTool {
+                server,
+                tool,
+                arguments,
+            } => StepTarget::McpTool {
+                server,
+                tool,
+                arguments: context.render(&arguments)?,
+            },
+            StepTarget::A2a {
+                agent,
+                skill,
+                input,
+            } => StepTarget::A2a {
+                agent,
+                skill,
+                input: context.render(&input)?,
+            },
+            StepTarget::Workflow { workflow, inputs } => StepTarget::Workflow {
+                workflow,
+                inputs: context.render_object(&inputs)?,
+            },
+        })
+    }
+
+    /// 可以包含模板的字段
+    fn templates(&self) -> Value {
+        match self {
+            StepTarget::Llm { prompt, .. } => Value::String(prompt.clone()),
+            StepTarget::McpTool { arguments, .. } => arguments.clone(),
+            StepTarget::A2a { input, .. } => input.clone(),
+            StepTarget::Workflow { inputs, .. } => Value::Object(inputs.clone()),
+        }
+    }
+}
+
+/// 读取目录中的所有工作流文件，按文件名排序；目录不存在时返回空列表
+pub fn read_directory(dir: &Path) -> Result<Vec<Workflow>, WorkflowError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| WorkflowError::InvalidDefinition(format!("{}: {e}", dir.display())))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("yaml" | "yml" | "json")
+            )
+        })
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| Workflow::from_file(path)).collect()
+}
+
+/// ID 只能包含字母、数字、`_` 和 `-`，以便在模板中引用
+fn is_identifier(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BRIEF: &str = r#"
+id: city-brief
+name: 城市简报
+inputs:
+  city: { required: true }
+  style: { default: "简洁" }
+steps:
+  - id: weather
+    target: { type: mcp_tool, tool: get_weather, arguments: { city: "{{inputs.city}}" } }
+  - id: brief
+    depends_on: [weather]
+    target:
+      type: llm
+      prompt: "{{inputs.style}}地写: {{steps.weather.output.summary}}"
+output: "{{steps.brief.output}}"
+"#;
+
+    #[test]
+    fn test_parse_yaml_definition() {
+        let workflow = Workflow::from_yaml(BRIEF).unwrap();
+        assert_eq!(workflow.steps.len(), 2);
+        assert_eq!(workflow.steps[1].dependencies, ["weather"]);
+        assert!(matches!(
+            &workflow.steps[0].target,
+            Some(StepTarget::McpTool { tool, .. }) if tool == "get_weather"
+        ));
+
+        let inputs = workflow
+            .resolve_inputs(json!({"city": "Paris"}).as_object().unwrap().clone())
+            .unwrap();
+        assert_eq!(
+            Value::Object(inputs),
+            json!({"city": "Paris", "style": "简洁"})
+        );
+        assert_eq!(
+            workflow.resolve_inputs(Map::new()),
+            Err(WorkflowError::MissingInput("city".to_string()))
+        );
+        assert_eq!(
+            workflow.resolve_inputs(json!({"city": "a", "x": 1}).as_object().unwrap().clone()),
+            Err(WorkflowError::UnknownInput("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_definitions() {
+        let invalid = |text: &str| {
+            matches!(
+                Workflow::from_yaml(text),
+                Err(WorkflowError::InvalidDefinition(_))
+            )
+        };
+
+        // 未知字段和未知目标类型
+        assert!(invalid("id: a\nsteps: []\nunknown: 1"));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s\n    target: { type: shell, command: ls }"
+        ));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s\n    target: { type: llm, prompt: x, extra: 1 }"
+        ));
+        // 引用非上游步骤和未声明的输入
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s1\n    target: { type: llm, prompt: x }\n  - id: s2\n    target: { type: llm, prompt: '{{steps.s1.output}}' }"
+        ));
+        assert!(invalid(
+            "id: a\ninputs: { city: {} }\nsteps:\n  - id: s\n    target: { type: llm, prompt: '{{inputs.town}}' }"
+        ));
+        assert!(invalid("id: 'a b'\nsteps: []"));
+        // 条件、循环和错误处理
+        assert!(invalid("id: a\nsteps:\n  - id: s\n    if: 'inputs.x =='"));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s\n    target: { type: llm, prompt: '{{item}}' }"
+        ));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s\n    target: { type: llm, prompt: '{{error}}' }"
+        ));
+        assert!(invalid("id: a\nsteps:\n  - id: s\n    concurrency: 2"));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s\n    retry: { max_attempts: 0 }"
+        ));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s\n    on_error: { output: x, target: { type: llm, prompt: y } }"
+        ));
+        assert!(invalid(
+            "id: a\nsteps:\n  - id: s1\n  - id: s2\n    if: 'steps.s1.output.ok'"
+        ));
+        assert!(Workflow::from_yaml(
+            "id: a\nsteps:\n  - id: s1\n  - id: s2\n    depends_on: [s1]\n    if: 'steps.s1.output.ok'\n    for_each: '{{steps.s1.output.items}}'\n    target: { type: llm, prompt: '{{item}}' }\n    on_error: { output: '{{error}}' }"
+        )
+        .is_ok());
+        assert!(matches!(
+            Workflow::from_yaml("id: a\nsteps:\n  - id: s\n    depends_on: [s]"),
+            Err(WorkflowError::Cycle(_))
+        ));
+    }
+}
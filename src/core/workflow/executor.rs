@@ -0,0 +1,243 @@
+//! 基于智能体的步骤执行器 - 把步骤目标交给智能体的 LLM、MCP 和 A2A 客户端
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+
+use super::template::to_text;
+use super::{RunInitiator, StepExecutor, StepInput, StepTarget, WorkflowStep};
+use crate::a2a::client::{A2AContent, A2AMessage};
+use crate::agent::Agent;
+use crate::llm::LLMOverrides;
+use crate::protocol::manifest::Manifest;
+use crate::protocol::message::MessageContent;
+use crate::services::security::Permission;
+
+/// 使用智能体已配置的后端执行步骤
+///
+/// 需要人工审批的 MCP 工具不能在工作流中调用，这类步骤会直接失败。有发起者
+/// 的运行只能调用发起者持有 `tool:<名称>` 权限的工具和持有 `agent:<名称>`
+/// 权限的 A2A 智能体，审计日志记录发起者。
+pub struct AgentStepExecutor {
+    agent: Arc<Agent>,
+}
+
+impl AgentStepExecutor {
+    pub fn new(agent: Arc<Agent>) -> Self {
+        Self { agent }
+    }
+
+    async fn call_llm(
+        &self,
+        prompt: &str,
+        overrides: LLMOverrides,
+        user_id: &str,
+        scope: &Value,
+        cancel: &CancellationToken,
+    ) -> Result<Value, String> {
+        let llm = self.agent.llm.read().await.clone();
+        let reply = tokio::select! {
+            reply = llm.process_message_with_overrides(prompt, &[], &overrides) => reply,
+            _ = cancel.cancelled() => return Err("LLM 调用已取消".to_string()),
+        };
+        self.agent
+            .audit_llm_call(
+                user_id,
+                &overrides.apply(&llm.config),
+                prompt,
+                &[],
+                &reply,
+                scope.clone(),
+            )
+            .await;
+        let reply = reply?;
+        Ok(match reply.content {
+            MessageContent::Text { text } => Value::String(text),
+            other => serde_json::to_value(other).map_err(|e| e.to_string())?,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        server: Option<&str>,
+        tool: &str,
+        arguments: Value,
+        initiator: Option<&RunInitiator>,
+        scope: &Value,
+        cancel: &CancellationToken,
+    ) -> Result<Value, String> {
+        authorize(initiator, Permission::ToolAccess(tool.to_string()))?;
+        let (client, annotations) = match server {
+            Some(server) => {
+                let client = self
+                    .agent
+                    .mcp_clients
+                    .get(server)
+                    .cloned()
+                    .ok_or_else(|| format!("MCP 服务器未配置: {server}"))?;
+                let annotations = match self.agent.manifests.read().await.get(server) {
+                    Some(Manifest::MCP(manifest)) => manifest
+                        .tools
+                        .iter()
+                        .find(|t| t.name == tool)
+                        .and_then(|t| t.annotations.clone()),
+                    _ => None,
+                };
+                (client, annotations)
+            }
+            None => self
+                .agent
+                .mcp_client_for_tool(tool)
+                .await
+                .ok_or_else(|| format!("没有 MCP 服务器提供工具: {tool}"))?,
+        };
+
+        if let Some(reason) =
+            self.agent
+                .approval_policy
+                .requires_approval(tool, annotations.as_ref(), &[])
+        {
+            return Err(format!(
+                "工具 {tool} 需要人工批准，不能在工作流中调用: {reason}"
+            ));
+        }
+        let result = client
+            .call_tool_with_cancel(tool, arguments.clone(), cancel)
+            .await
+            .map_err(|e| e.to_string());
+        self.agent
+            .audit_tool_call(
+                audit_user(initiator),
+                tool,
+                "mcp",
+                &arguments,
+                &result,
+                scope.clone(),
+            )
+            .await;
+        result
+    }
+
+    async fn call_agent(
+        &self,
+        agent: &str,
+        skill: Option<String>,
+        input: Value,
+        initiator: Option<&RunInitiator>,
+        scope: &Value,
+        cancel: &CancellationToken,
+    ) -> Result<Value, String> {
+        authorize(initiator, Permission::AgentAccess(agent.to_string()))?;
+        let client = self
+            .agent
+            .a2a_clients
+            .get(agent)
+            .ok_or_else(|| format!("A2A 对端未配置: {agent}"))?;
+        let content = match skill {
+            Some(task_type) => A2AContent::Task {
+                task_type,
+                parameters: match input {
+                    Value::Object(map) => map.into_iter().collect(),
+                    Value::Null => HashMap::new(),
+                    other => HashMap::from([("input".to_string(), other)]),
+                },
+            },
+            None => A2AContent::Text {
+                text: to_text(&input),
+            },
+        };
+        let message = A2AMessage {
+            id: uuid::Uuid::new_v4(),
+            sender: self.agent.config.name.clone(),
+            recipient: agent.to_string(),
+            content,
+            timestamp: chrono::Utc::now(),
+        };
+
+        let request = json!(message.content);
+        let reply = client.send_message_with_cancel(message, cancel).await;
+        self.agent
+            .audit_delegation(audit_user(initiator), agent, &request, &reply, scope.clone())
+            .await;
+        let reply = reply.map_err(|e| e.to_string())?;
+        match reply.content {
+            A2AContent::Text { text } => Ok(Value::String(text)),
+            A2AContent::Response { data, .. } => Ok(data),
+            A2AContent::Error { code, message } => Err(format!("{code}: {message}")),
+            A2AContent::Task {
+                task_type,
+                parameters,
+            } => Ok(json!({ "task_type": task_type, "parameters": parameters })),
+        }
+    }
+}
+
+/// 发起者缺少 `required` 权限时拒绝调用
+fn authorize(initiator: Option<&RunInitiator>, required: Permission) -> Result<(), String> {
+    match initiator {
+        Some(initiator) if !required.granted_by(&initiator.permissions) => Err(format!(
+            "用户 {} 缺少权限 {required}",
+            initiator.user_id
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 审计日志中的用户，没有发起者的运行为空
+fn audit_user(initiator: Option<&RunInitiator>) -> &str {
+    initiator.map_or("", |initiator| initiator.user_id.as_str())
+}
+
+#[async_trait]
+impl StepExecutor for AgentStepExecutor {
+    async fn execute(&self, step: &WorkflowStep, input: StepInput) -> Result<Value, String> {
+        let scope = json!({ "workflow_step": step.id });
+        let initiator = input.initiator.as_ref();
+        let user_id = audit_user(initiator);
+        let cancel = input.cancel.clone();
+        match step.target.clone() {
+            Some(StepTarget::Llm {
+                prompt,
+                provider,
+                model,
+                temperature,
+            }) => {
+                let overrides = LLMOverrides {
+                    provider,
+                    model,
+                    temperature,
+                    max_tokens: None,
+                };
+                self.call_llm(&prompt, overrides, user_id, &scope, &cancel)
+                    .await
+            }
+            Some(StepTarget::McpTool {
+                server,
+                tool,
+                arguments,
+            }) => {
+                self.call_tool(server.as_deref(), &tool, arguments, initiator, &scope, &cancel)
+                    .await
+            }
+            Some(StepTarget::A2a {
+                agent,
+                skill,
+                input,
+            }) => {
+                self.call_agent(&agent, skill, input, initiator, &scope, &cancel)
+                    .await
+            }
+            Some(StepTarget::Workflow { workflow, .. }) => {
+                Err(format!("子工作流 {workflow} 应由工作流引擎执行"))
+            }
+            // 没有目标的步骤把输入交给 LLM
+            None => {
+                self.call_llm(&input.to_text(), LLMOverrides::default(), user_id, &scope, &cancel)
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! 步骤条件表达式 - 对工作流输入和步骤输出求布尔值
+//!
+//! ```text
+//! steps.check.output.status == 'ok' && !(inputs.retries > 3)
+//! $.steps.fetch.output.items[0].tags contains "urgent" || inputs.force
+//! ```
+//!
+//! - 路径与模板引用相同（`inputs.*`、`steps.ID.output.*`、`env.*`、`item.*`、
+//!   `index`），可写成 JSONPath 形式：`$.` 前缀、`[0]` 下标、`['key']` 字段
+//! - 字面量：数字、`'…'`/`"…"` 字符串、`true`、`false`、`null`
+//! - 比较：`==`、`!=`、`<`、`<=`、`>`、`>=`、`contains`
+//! - 布尔运算：`!`、`&&`、`||`，支持括号
+//!
+//! 路径不存在时取 `null`；单独的值按真值判断，`null`、`false`、`0`、空字符串、
+//! 空数组和空对象为假。整个表达式可以包在 `{{ }}` 中。括号和 `!` 最多嵌套
+//! [`MAX_DEPTH`] 层，表达式最多 [`MAX_TOKENS`] 个记号，超出时解析失败。
+
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use super::template::{Reference, TemplateContext};
+use super::WorkflowError;
+
+/// 括号和 `!` 的最大嵌套层数
+pub const MAX_DEPTH: usize = 32;
+/// 表达式的最大记号数，限制 `&&`/`||` 链形成的语法树深度
+pub const MAX_TOKENS: usize = 512;
+
+/// 比较运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+/// 解析后的条件表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Value),
+    Path(Reference),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Compare(Box<Expression>, Comparison, Box<Expression>),
+}
+
+impl Expression {
+    /// 解析表达式
+    pub fn parse(source: &str) -> Result<Self, WorkflowError> {
+        let trimmed = source.trim();
+        let body = trimmed
+            .strip_prefix("{{")
+            .and_then(|rest| rest.strip_suffix("}}"))
+            .unwrap_or(trimmed);
+        let invalid =
+            |reason: String| WorkflowError::Template(format!("条件 `{source}`: {reason}"));
+
+        let tokens = tokenize(body).map_err(invalid)?;
+        if tokens.len() > MAX_TOKENS {
+            return Err(invalid(format!("超过 {MAX_TOKENS} 个记号")));
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let expression = parser.or().map_err(invalid)?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(token) => Err(invalid(format!("多余的 `{token:?}`"))),
+        }
+    }
+
+    /// 表达式中的所有路径引用
+    pub fn references(&self) -> Vec<&Reference> {
+        match self {
+            Expression::Literal(_) => Vec::new(),
+            Expression::Path(reference) => vec![reference],
+            Expression::Not(inner) => inner.references(),
+            Expression::And(left, right)
+            | Expression::Or(left, right)
+            | Expression::Compare(left, _, right) => {
+                let mut found = left.references();
+                found.extend(right.references());
+                found
+            }
+        }
+    }
+
+    /// 求布尔值
+    pub fn evaluate(&self, context: &TemplateContext) -> Result<bool, WorkflowError> {
+        match self {
+            Expression::Not(inner) => Ok(!inner.evaluate(context)?),
+            Expression::And(left, right) => Ok(left.evaluate(context)? && right.evaluate(context)?),
+            Expression::Or(left, right) => Ok(left.evaluate(context)? || right.evaluate(context)?),
+            Expression::Compare(left, comparison, right) => {
+                compare(&left.value(context)?, *comparison, &right.value(context)?)
+            }
+            other => Ok(truthy(&other.value(context)?)),
+        }
+    }
+
+    fn value(&self, context: &TemplateContext) -> Result<Value, WorkflowError> {
+        match self {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Path(reference) => Ok(context.resolve(reference).unwrap_or(Value::Null)),
+            other => other.evaluate(context).map(Value::Bool),
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(flag) => *flag,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn compare(left: &Value, comparison: Comparison, right: &Value) -> Result<bool, WorkflowError> {
+    let ordering = || match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    let ordered = |accept: fn(Ordering) -> bool| {
+        ordering()
+            .map(accept)
+            .ok_or_else(|| WorkflowError::Template(format!("无法比较 {left} 和 {right}")))
+    };
+
+    match comparison {
+        Comparison::Eq => Ok(equal(left, right)),
+        Comparison::Ne => Ok(!equal(left, right)),
+        Comparison::Lt => ordered(Ordering::is_lt),
+        Comparison::Le => ordered(Ordering::is_le),
+        Comparison::Gt => ordered(Ordering::is_gt),
+        Comparison::Ge => ordered(Ordering::is_ge),
+        Comparison::Contains => Ok(match (left, right) {
+            (Value::String(text), Value::String(part)) => text.contains(part.as_str()),
+            (Value::Array(items), value) => items.iter().any(|item| equal(item, value)),
+            (Value::Object(map), Value::String(key)) => map.contains_key(key),
+            _ => false,
+        }),
+    }
+}
+
+/// 数字按数值比较，`1 == 1.0`
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => left == right,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(Value),
+    Path(Vec<String>),
+    Compare(Comparison),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, width) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Compare(Comparison::Eq), 2),
+            ('!', Some('=')) => (Token::Compare(Comparison::Ne), 2),
+            ('<', Some('=')) => (Token::Compare(Comparison::Le), 2),
+            ('>', Some('=')) => (Token::Compare(Comparison::Ge), 2),
+            ('!', _) => (Token::Not, 1),
+            ('<', _) => (Token::Compare(Comparison::Lt), 1),
+            ('>', _) => (Token::Compare(Comparison::Gt), 1),
+            ('\'' | '"', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .ok_or("未闭合的字符串")?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Literal(Value::String(text)), end + 2)
+            }
+            (c, _)
+                if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|ch| !(ch.is_ascii_digit() || *ch == '.'))
+                    .map_or(chars.len(), |offset| i + 1 + offset);
+                let text: String = chars[i..end].iter().collect();
+                let number: serde_json::Number =
+                    text.parse().map_err(|_| format!("无效的数字 `{text}`"))?;
+                (Token::Literal(Value::Number(number)), end - i)
+            }
+            (c, _) if c == '$' || c.is_alphabetic() || c == '_' => path(&chars[i..])?,
+            (c, _) => return Err(format!("无法识别的字符 `{c}`")),
+        };
+        tokens.push(token);
+        i += width;
+    }
+    Ok(tokens)
+}
+
+/// 读取路径或关键字，返回词法单元和消耗的字符数
+fn path(chars: &[char]) -> Result<(Token, usize), String> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let mut segments = Vec::new();
+    let mut i = 0;
+    if chars.first() == Some(&'$') {
+        i = 1;
+        if chars.get(1) == Some(&'.') {
+            i = 2;
+        }
+    }
+
+    loop {
+        let start = i;
+        while i < chars.len() && is_name(chars[i]) {
+            i += 1;
+        }
+        if i == start {
+            return Err("路径中缺少字段名".to_string());
+        }
+        segments.push(chars[start..i].iter().collect::<String>());
+
+        // 下标和带引号的字段：`[0]`、`['key']`
+        while chars.get(i) == Some(&'[') {
+            let end = chars[i..]
+                .iter()
+                .position(|&c| c == ']')
+                .ok_or("未闭合的 `[`")?;
+            let inner: String = chars[i + 1..i + end].iter().collect();
+            let inner = inner.trim();
+            let segment = inner
+                .strip_prefix('\'')
+                .and_then(|rest| rest.strip_suffix('\''))
+                .or_else(|| {
+                    inner
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.strip_suffix('"'))
+                })
+                .unwrap_or(inner);
+            segments.push(segment.to_string());
+            i += end + 1;
+        }
+
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    let token = match segments.as_slice() {
+        [word] if word == "true" => Token::Literal(Value::Bool(true)),
+        [word] if word == "false" => Token::Literal(Value::Bool(false)),
+        [word] if word == "null" => Token::Literal(Value::Null),
+        [word] if word == "contains" => Token::Compare(Comparison::Contains),
+        _ => Token::Path(segments),
+    };
+    Ok((token, i))
+}
+
+/// 递归下降：`or := and ('||' and)*`，`and := unary ('&&' unary)*`，
+/// `unary := '!' unary | operand (cmp operand)?`
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// 当前的括号和 `!` 嵌套层数
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expression, String> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            left = Expression::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            left = Expression::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    /// 在下一层嵌套中解析，超过 [`MAX_DEPTH`] 时失败
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("嵌套超过 {MAX_DEPTH} 层"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            let inner = self.nested(Self::unary)?;
+            return Ok(Expression::Not(Box::new(inner)));
+        }
+        let left = self.operand()?;
+        if let Some(Token::Compare(comparison)) = self.peek().cloned() {
+            self.advance();
+            let right = self.operand()?;
+            return Ok(Expression::Compare(
+                Box::new(left),
+                comparison,
+                Box::new(right),
+            ));
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expression, String> {
+        match self.advance() {
+            Some(Token::Literal(value)) => Ok(Expression::Literal(value)),
+            Some(Token::Path(segments)) => {
+                let text = segments.join(".");
+                Reference::from_segments(segments)
+                    .map(Expression::Path)
+                    .ok_or_else(|| format!("无效的路径 `{text}`"))
+            }
+            Some(Token::Open) => {
+                let inner = self.nested(Self::or)?;
+                match self.advance() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("缺少 `)`".to_string()),
+                }
+            }
+            Some(token) => Err(format!("意外的 `{token:?}`")),
+            None => Err("表达式不完整".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            inputs: json!({"limit": 3, "force": false, "name": ""})
+                .as_object()
+                .unwrap()
+                .clone(),
+            steps: json!({
+                "check": {"status": "ok", "count": 5, "tags": ["a", "urgent"]},
+                "skipped": null
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+            ..Default::default()
+        }
+    }
+
+    fn eval(source: &str) -> bool {
+        Expression::parse(source)
+            .unwrap()
+            .evaluate(&context())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_comparisons_and_boolean_operators() {
+        assert!(eval("steps.check.output.status == 'ok'"));
+        assert!(eval("{{ steps.check.output.count > inputs.limit }}"));
+        assert!(eval(
+            "$.steps.check.output.count >= 5.0 && inputs.limit != 4"
+        ));
+        assert!(eval("!(inputs.limit < 2) || inputs.force"));
+        assert!(eval("$.steps.check.output.tags[1] == \"urgent\""));
+        assert!(eval("$.steps.check.output['tags'] contains 'urgent'"));
+        assert!(eval("steps.check.output.status contains 'k'"));
+        assert!(!eval("inputs.force || inputs.name"));
+        assert!(eval(
+            "steps.skipped.output == null && steps.missing.output.x == null"
+        ));
+        assert!(eval("true && !false"));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for source in [
+            "a.b == 1",
+            "inputs.x ==",
+            "(inputs.x",
+            "inputs.x = 1",
+            "'open",
+            "inputs.x 1",
+        ] {
+            assert!(Expression::parse(source).is_err(), "{source}");
+        }
+        // 过深的嵌套和过长的表达式返回解析错误而不是耗尽栈
+        let deep = format!("{}inputs.force{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert!(Expression::parse(&deep).is_err());
+        assert!(Expression::parse(&format!("{}true", "!".repeat(10_000))).is_err());
+        assert!(Expression::parse(&vec!["true"; 10_000].join(" && ")).is_err());
+        let nested = format!("{}true{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert!(Expression::parse(&nested).unwrap().evaluate(&context()).unwrap());
+
+        let ordering = Expression::parse("steps.check.output.tags > 1").unwrap();
+        assert!(ordering.evaluate(&context()).is_err());
+    }
+
+    #[test]
+    fn test_references() {
+        let expression = Expression::parse("inputs.a > 1 && steps.s.output.x").unwrap();
+        assert_eq!(
+            expression.references(),
+            [
+                &Reference::Input {
+                    name: "a".to_string(),
+                    path: vec![]
+                },
+                &Reference::Step {
+                    id: "s".to_string(),
+                    path: vec!["x".to_string()]
+                }
+            ]
+        );
+    }
+}
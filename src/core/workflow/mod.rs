@@ -1,139 +1,1096 @@
 //! 工作流引擎模块
+//!
+//! 工作流是由步骤组成的有向无环图。注册时校验依赖关系，执行时按拓扑顺序
+//! 调度，互不依赖的步骤并发执行；每个步骤能看到其声明依赖的输出，失败会
+//! 使所有下游步骤被跳过。
+//!
+//! 工作流可以写成 YAML/JSON 文件（见 [`definition`]），每个步骤指定一个
+//! 执行目标：LLM 提示词、MCP 工具、A2A 对端或子工作流，步骤输入通过
+//! [`template`] 引用工作流输入、上游步骤输出和环境变量。
+//!
+//! 步骤还可以设置执行条件（[`expression`]）、对数组逐项执行（`for_each`）、
+//! 失败重试（`retry`）和错误处理（`on_error`）；工作流可以设置整体超时。
+//! 条件不满足的步骤不执行，其下游步骤也不执行，除非下游步骤自己设置了条件
+//! （未执行步骤的输出视为 `null`），以此组成 if/else 分支。
+//!
+//! 每次执行对应一条独立的运行记录（见 [`run`]），可以持久化、在重启后恢复，
+//! 也可以从指定步骤重跑。
 
-use std::collections::HashMap;
+pub mod dag;
+pub mod definition;
+pub mod executor;
+pub mod expression;
+pub mod run;
+pub mod template;
+
+pub use executor::AgentStepExecutor;
+pub use run::{RunInitiator, RunSummary, StepRun, StepStatus, WorkflowRun};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
+use futures::future::BoxFuture;
+use futures::stream::{self, FuturesUnordered, StreamExt, TryStreamExt};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+use uuid::Uuid;
 
 use crate::core::orchestration::OrchestrationEngine;
+use crate::integrations::database::{DataRepository, Page, Pagination};
+use expression::Expression;
+use template::TemplateContext;
 
-/// 工作流状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// `for_each` 步骤默认的并发数
+const DEFAULT_FOR_EACH_CONCURRENCY: usize = 4;
+
+/// 工作流超时后等待运行中的步骤响应取消的最长时间
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// 工作流运行状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WorkflowStatus {
+    #[default]
     Pending,
     Running,
     Completed,
     Failed,
 }
 
+impl WorkflowStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkflowStatus::Pending => "pending",
+            WorkflowStatus::Running => "running",
+            WorkflowStatus::Completed => "completed",
+            WorkflowStatus::Failed => "failed",
+        }
+    }
+}
+
+/// 步骤的执行目标，字符串字段支持模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StepTarget {
+    /// 以 `prompt` 调用 LLM，可覆盖提供商和模型
+    Llm {
+        prompt: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        temperature: Option<f32>,
+    },
+    /// 调用 MCP 工具，省略 `server` 时使用清单中提供该工具的服务器
+    McpTool {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        server: Option<String>,
+        tool: String,
+        #[serde(default)]
+        arguments: Value,
+    },
+    /// 向 A2A 对端发送消息；指定 `skill` 时以任务形式调用该技能
+    A2a {
+        agent: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        skill: Option<String>,
+        #[serde(default)]
+        input: Value,
+    },
+    /// 运行已注册的子工作流
+    Workflow {
+        workflow: String,
+        #[serde(default)]
+        inputs: Map<String, Value>,
+    },
+}
+
 /// 工作流步骤
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WorkflowStep {
     pub id: String,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub task_id: Option<String>,
+    #[serde(default, alias = "depends_on")]
     pub dependencies: Vec<String>,
-    pub condition: Option<String>, // 执行条件
+    /// 执行条件表达式，不满足时跳过该步骤
+    #[serde(default, alias = "if")]
+    pub condition: Option<String>,
+    /// 执行目标，省略时交给步骤执行器的默认处理
+    #[serde(default)]
+    pub target: Option<StepTarget>,
+    /// 求值为数组的模板，对每个元素执行一次目标，输出为各次输出组成的数组
+    #[serde(default)]
+    pub for_each: Option<String>,
+    /// `for_each` 的最大并发数，默认 4
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default)]
+    pub on_error: Option<ErrorHandler>,
+}
+
+/// 步骤失败后的重试策略，等待时间按指数增长
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// 最多执行次数（含第一次）
+    #[serde(default = "RetryPolicy::default_max_attempts")]
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间
+    #[serde(default = "RetryPolicy::default_backoff_ms")]
+    pub backoff_ms: u64,
+    #[serde(default = "RetryPolicy::default_multiplier")]
+    pub multiplier: f64,
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::default_max_attempts(),
+            backoff_ms: Self::default_backoff_ms(),
+            multiplier: Self::default_multiplier(),
+            max_backoff_ms: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+
+    fn default_backoff_ms() -> u64 {
+        500
+    }
+
+    fn default_multiplier() -> f64 {
+        2.0
+    }
+
+    /// 第 `retry` 次重试（从 1 开始）前的等待时间
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let mut millis = self.backoff_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+        if let Some(max) = self.max_backoff_ms {
+            millis = millis.min(max as f64);
+        }
+        Duration::from_millis(millis.min(u64::MAX as f64) as u64)
+    }
+}
+
+/// 步骤失败（含重试）后的处理：执行备用目标或返回固定输出，都省略时输出
+/// `null`；其中的模板可以用 `{{error}}` 引用错误信息。处理成功后步骤视为成功。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ErrorHandler {
+    #[serde(default)]
+    pub target: Option<StepTarget>,
+    #[serde(default)]
+    pub output: Option<Value>,
+}
+
+/// 工作流输入声明
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowInput {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<Value>,
 }
 
 /// 工作流定义
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Workflow {
     pub id: String,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    /// 声明的输入；为空时接受任意输入
+    #[serde(default)]
+    pub inputs: BTreeMap<String, WorkflowInput>,
     pub steps: Vec<WorkflowStep>,
-    pub status: WorkflowStatus,
-    pub results: HashMap<String, String>, // 步骤结果存储
+    /// 工作流输出模板，省略时为末端步骤的输出
+    #[serde(default)]
+    pub output: Option<Value>,
+    /// 整个工作流的超时时间，超时后取消所有运行中的步骤
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// 工作流错误
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum WorkflowError {
+    #[error("工作流未找到: {0}")]
+    NotFound(String),
+    #[error("步骤ID重复: {0}")]
+    DuplicateStep(String),
+    #[error("步骤 {step} 依赖不存在的步骤 {dependency}")]
+    UnknownDependency { step: String, dependency: String },
+    #[error("步骤依赖存在环: {0:?}")]
+    Cycle(Vec<String>),
+    #[error("工作流定义无效: {0}")]
+    InvalidDefinition(String),
+    #[error("模板错误: {0}")]
+    Template(String),
+    #[error("缺少必填输入: {0}")]
+    MissingInput(String),
+    #[error("未声明的输入: {0}")]
+    UnknownInput(String),
+    #[error("子工作流递归调用: {0}")]
+    Recursion(String),
+    #[error("工作流执行超时（{0} 秒）")]
+    Timeout(u64),
+    #[error("工作流运行未找到: {0}")]
+    RunNotFound(Uuid),
+    #[error("工作流运行已结束: {0}")]
+    RunFinished(Uuid),
+    #[error("工作流运行正在执行: {0}")]
+    RunActive(Uuid),
+    #[error("步骤不存在: {0}")]
+    UnknownStep(String),
+    #[error("运行被中断")]
+    Interrupted,
+    #[error("运行记录存储失败: {0}")]
+    Storage(String),
+    #[error("工作流执行失败，失败步骤: {failed:?}，跳过步骤: {skipped:?}")]
+    StepsFailed {
+        failed: BTreeMap<String, String>,
+        skipped: Vec<String>,
+    },
+}
+
+/// 步骤输入：工作流输入和各依赖步骤的输出
+#[derive(Debug, Clone)]
+pub struct StepInput {
+    pub inputs: Map<String, Value>,
+    /// 依赖步骤的输出，按声明顺序排列；未执行的依赖为 `null`
+    pub dependencies: Vec<(String, Value)>,
+    /// `for_each` 的当前元素
+    pub item: Option<Value>,
+    /// 发起运行的用户
+    pub initiator: Option<RunInitiator>,
+    /// 工作流超时时触发，执行器应停止步骤并通知 MCP 服务器和 A2A 对端取消
+    pub cancel: CancellationToken,
+}
+
+impl StepInput {
+    /// 合并为文本：`for_each` 中为当前元素；没有依赖时为 `input` 输入（或全部
+    /// 输入的 JSON），只有一个依赖时为其输出，多个依赖时按步骤分段
+    pub fn to_text(&self) -> String {
+        if let Some(item) = &self.item {
+            return template::to_text(item);
+        }
+        match self.dependencies.as_slice() {
+            [] => match self.inputs.get("input") {
+                Some(input) => template::to_text(input),
+                None => Value::Object(self.inputs.clone()).to_string(),
+            },
+            [(_, output)] => template::to_text(output),
+            outputs => outputs
+                .iter()
+                .map(|(step, output)| format!("[{step}]\n{}", template::to_text(output)))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        }
+    }
+}
+
+/// 步骤执行器，收到的步骤目标中的模板已经展开
+#[async_trait::async_trait]
+pub trait StepExecutor: Send + Sync {
+    async fn execute(&self, step: &WorkflowStep, input: StepInput) -> Result<Value, String>;
+}
+
+/// 通过编排引擎执行步骤
+pub struct OrchestrationStepExecutor {
+    orchestration_engine: Arc<OrchestrationEngine>,
+}
+
+impl OrchestrationStepExecutor {
+    pub fn new(orchestration_engine: Arc<OrchestrationEngine>) -> Self {
+        Self { orchestration_engine }
+    }
+}
+
+#[async_trait::async_trait]
+impl StepExecutor for OrchestrationStepExecutor {
+    async fn execute(&self, _step: &WorkflowStep, input: StepInput) -> Result<Value, String> {
+        let user_id = input
+            .initiator
+            .as_ref()
+            .map_or("", |initiator| initiator.user_id.as_str());
+        self.orchestration_engine
+            .orchestrate_as(user_id, &input.to_text(), input.cancel.clone())
+            .await
+            .map(Value::String)
+    }
+}
+
+/// 一次执行中各步骤的结果
+#[derive(Debug, Default)]
+struct StepOutcomes {
+    outputs: HashMap<String, Value>,
+    failed: BTreeMap<String, String>,
+    /// 因上游失败而跳过的步骤
+    skipped: Vec<String>,
+    /// 因条件不满足而未执行的步骤
+    bypassed: HashSet<String>,
+}
+
+impl StepOutcomes {
+    /// 模板和条件可见的步骤输出，未执行的步骤为 `null`
+    fn step_values(&self) -> Map<String, Value> {
+        self.outputs
+            .iter()
+            .map(|(id, output)| (id.clone(), output.clone()))
+            .chain(self.bypassed.iter().map(|id| (id.clone(), Value::Null)))
+            .collect()
+    }
+}
+
+/// 依赖图调度状态：记录每个步骤还在等待的依赖数，依赖全部结束后决定步骤去向
+struct Schedule<'a> {
+    steps: HashMap<&'a str, &'a WorkflowStep>,
+    dependents: HashMap<String, Vec<String>>,
+    waiting: HashMap<&'a str, usize>,
+    ready: Vec<&'a WorkflowStep>,
+    outcomes: StepOutcomes,
+}
+
+impl<'a> Schedule<'a> {
+    /// 从运行记录恢复调度状态：可复用的步骤保留结果，其余步骤重置为待执行
+    fn resume(workflow: &'a Workflow, run: &mut WorkflowRun) -> Self {
+        let mut outcomes = StepOutcomes::default();
+        for step in &mut run.steps {
+            match step.status {
+                StepStatus::Succeeded => {
+                    outcomes
+                        .outputs
+                        .insert(step.step_id.clone(), step.output.clone().unwrap_or(Value::Null));
+                }
+                StepStatus::Bypassed => {
+                    outcomes.bypassed.insert(step.step_id.clone());
+                }
+                _ => *step = StepRun::pending(&step.step_id),
+            }
+        }
+
+        let settled = |id: &str| outcomes.outputs.contains_key(id) || outcomes.bypassed.contains(id);
+        let pending: Vec<&'a WorkflowStep> = workflow
+            .steps
+            .iter()
+            .filter(|step| !settled(&step.id))
+            .collect();
+        let waiting = pending
+            .iter()
+            .map(|step| {
+                let count = step.dependencies.iter().filter(|d| !settled(d)).count();
+                (step.id.as_str(), count)
+            })
+            .collect();
+
+        let mut schedule = Self {
+            steps: workflow.steps.iter().map(|step| (step.id.as_str(), step)).collect(),
+            dependents: dag::dependents(workflow),
+            waiting,
+            ready: Vec::new(),
+            outcomes,
+        };
+        for step in pending {
+            if schedule.waiting[step.id.as_str()] == 0 {
+                schedule.settle(step, run);
+            }
+        }
+        schedule
+    }
+
+    /// 依赖全部结束的步骤：上游失败的被跳过；上游未执行且自身没有条件的同样
+    /// 不执行；两种情况都继续向下游传播，否则步骤就绪
+    fn settle(&mut self, step: &'a WorkflowStep, run: &mut WorkflowRun) {
+        let outcomes = &self.outcomes;
+        let dependencies = &step.dependencies;
+        if dependencies
+            .iter()
+            .any(|dependency| outcomes.failed.contains_key(dependency) || outcomes.skipped.contains(dependency))
+        {
+            info!("⏩ 跳过步骤: {}", step.name);
+            self.outcomes.skipped.push(step.id.clone());
+            run.step_mut(&step.id).finish(StepStatus::Skipped, None, None);
+            self.release(&step.id, run);
+        } else if step.condition.is_none()
+            && dependencies.iter().any(|dependency| outcomes.bypassed.contains(dependency))
+        {
+            info!("⏩ 上游未执行，不执行步骤: {}", step.name);
+            self.outcomes.bypassed.insert(step.id.clone());
+            run.step_mut(&step.id).finish(StepStatus::Bypassed, None, None);
+            self.release(&step.id, run);
+        } else {
+            self.ready.push(step);
+        }
+    }
+
+    /// 步骤结束后释放其下游步骤
+    fn release(&mut self, id: &str, run: &mut WorkflowRun) {
+        let dependents = self.dependents.get(id).cloned().unwrap_or_default();
+        for dependent in dependents {
+            let count = self.waiting.get_mut(dependent.as_str()).expect("依赖图中的步骤");
+            *count -= 1;
+            if *count == 0 {
+                let step = self.steps[dependent.as_str()];
+                self.settle(step, run);
+            }
+        }
+    }
+}
+
+/// 运行的执行权，释放后运行才能再次执行
+struct RunClaim {
+    run_id: Uuid,
+    active: Arc<std::sync::Mutex<HashSet<Uuid>>>,
+}
+
+impl Drop for RunClaim {
+    fn drop(&mut self) {
+        self.active.lock().unwrap().remove(&self.run_id);
+    }
+}
+
+/// 正在执行的运行及其上层工作流，用于创建子运行和发现子工作流递归
+struct RunScope {
+    run_id: Uuid,
+    stack: Vec<String>,
+    initiator: Option<RunInitiator>,
+    /// 运行超时或上层运行超时时触发
+    cancel: CancellationToken,
 }
 
 /// 工作流引擎
+///
+/// 每次执行创建一条 [`WorkflowRun`]，步骤结束后写入检查点。配置存储后运行
+/// 记录持久化，结束的运行只保留在存储中；否则所有运行保存在内存中。
 pub struct WorkflowEngine {
-    orchestration_engine: Arc<OrchestrationEngine>,
+    executor: Arc<dyn StepExecutor>,
     workflows: Arc<RwLock<HashMap<String, Workflow>>>,
+    runs: Arc<RwLock<HashMap<Uuid, WorkflowRun>>>,
+    /// 正在执行的运行，同一运行同时只能执行一次
+    active: Arc<std::sync::Mutex<HashSet<Uuid>>>,
+    store: Option<Arc<dyn DataRepository>>,
 }
 
 impl WorkflowEngine {
-    /// 创建新的工作流引擎
+    /// 创建新的工作流引擎，步骤通过编排引擎执行
     pub fn new(orchestration_engine: Arc<OrchestrationEngine>) -> Self {
+        Self::with_executor(Arc::new(OrchestrationStepExecutor::new(orchestration_engine)))
+    }
+
+    /// 使用自定义步骤执行器创建工作流引擎
+    pub fn with_executor(executor: Arc<dyn StepExecutor>) -> Self {
         Self {
-            orchestration_engine,
+            executor,
             workflows: Arc::new(RwLock::new(HashMap::new())),
+            runs: Arc::new(RwLock::new(HashMap::new())),
+            active: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            store: None,
         }
     }
 
-    /// 注册工作流
-    pub async fn register_workflow(&self, workflow: Workflow) -> Result<(), String> {
+    /// 将运行记录持久化到存储
+    pub fn with_store(mut self, store: Arc<dyn DataRepository>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// 注册工作流，定义无效、依赖关系无效或存在环时拒绝
+    pub async fn register_workflow(&self, workflow: Workflow) -> Result<(), WorkflowError> {
+        workflow.validate()?;
         let mut workflows = self.workflows.write().await;
         workflows.insert(workflow.id.clone(), workflow);
         Ok(())
     }
 
-    /// 执行工作流
-    pub async fn execute_workflow(&self, workflow_id: &str, initial_input: &str) -> Result<String, String> {
-        info!("🔄 开始执行工作流: {}", workflow_id);
-        
-        // 获取工作流定义
-        let _workflow = {
-            let workflows = self.workflows.read().await;
-            workflows.get(workflow_id).cloned().ok_or("工作流未找到")?
+    /// 加载目录中的所有工作流文件（`.yaml`、`.yml`、`.json`），返回加载的工作流ID
+    ///
+    /// 任一文件无效时整体失败，已加载的工作流保持注册。
+    pub async fn load_directory(&self, dir: impl AsRef<std::path::Path>) -> Result<Vec<String>, WorkflowError> {
+        let mut loaded = Vec::new();
+        for workflow in definition::read_directory(dir.as_ref())? {
+            info!("📋 加载工作流: {}", workflow.id);
+            loaded.push(workflow.id.clone());
+            self.register_workflow(workflow).await?;
+        }
+        Ok(loaded)
+    }
+
+    /// 以文本输入执行工作流，输入作为 `input` 传入
+    pub async fn execute_workflow(&self, workflow_id: &str, initial_input: &str) -> Result<String, WorkflowError> {
+        let inputs = Map::from_iter([("input".to_string(), Value::String(initial_input.to_string()))]);
+        self.run_workflow(workflow_id, inputs)
+            .await
+            .map(|output| template::to_text(&output))
+    }
+
+    /// 执行工作流，返回输出模板的结果或末端步骤（没有下游的步骤）的输出
+    pub async fn run_workflow(&self, workflow_id: &str, inputs: Map<String, Value>) -> Result<Value, WorkflowError> {
+        let run = self.create_run(workflow_id, inputs, None).await?;
+        self.execute_run(run.id).await
+    }
+
+    /// 校验输入并创建待执行的运行，步骤按 `initiator` 的权限调用工具和智能体
+    pub async fn create_run(
+        &self,
+        workflow_id: &str,
+        inputs: Map<String, Value>,
+        initiator: Option<RunInitiator>,
+    ) -> Result<WorkflowRun, WorkflowError> {
+        let mut run = self.prepare_run(workflow_id, inputs).await?;
+        run.initiator = initiator;
+        self.save_run(&run).await;
+        Ok(run)
+    }
+
+    async fn prepare_run(&self, workflow_id: &str, inputs: Map<String, Value>) -> Result<WorkflowRun, WorkflowError> {
+        let workflow = self
+            .get_workflow(workflow_id)
+            .await
+            .ok_or_else(|| WorkflowError::NotFound(workflow_id.to_string()))?;
+        let inputs = workflow.resolve_inputs(inputs)?;
+        Ok(WorkflowRun::new(workflow, inputs))
+    }
+
+    /// 基于已有运行创建重跑运行，见 [`WorkflowRun::rerun_from`]；重跑按
+    /// `initiator` 而不是原运行发起者的权限执行
+    pub async fn create_rerun(
+        &self,
+        run_id: Uuid,
+        from_step: Option<&str>,
+        initiator: Option<RunInitiator>,
+    ) -> Result<WorkflowRun, WorkflowError> {
+        let previous = self
+            .get_run(run_id)
+            .await?
+            .ok_or(WorkflowError::RunNotFound(run_id))?;
+        let mut run = previous.rerun_from(from_step)?;
+        run.initiator = initiator;
+        self.save_run(&run).await;
+        Ok(run)
+    }
+
+    /// 执行待执行或被中断的运行，已结束或正在执行的运行不能再次执行
+    pub async fn execute_run(&self, run_id: Uuid) -> Result<Value, WorkflowError> {
+        let (_claim, run) = self.claim_run(run_id).await?;
+        self.execute(run, Vec::new(), CancellationToken::new()).await
+    }
+
+    /// 在后台执行运行，与 [`Self::execute_run`] 的检查相同；运行不随调用方
+    /// 取消，通过 [`Self::get_run`] 查看进度和结果
+    pub async fn start_run(self: &Arc<Self>, run_id: Uuid) -> Result<(), WorkflowError> {
+        let (claim, run) = self.claim_run(run_id).await?;
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let _claim = claim;
+            if let Err(e) = engine.execute(run, Vec::new(), CancellationToken::new()).await {
+                warn!("❌ 工作流运行 {} 失败: {}", run_id, e);
+            }
+        });
+        Ok(())
+    }
+
+    /// 取得运行的执行权，运行不存在、已结束或正在执行时失败
+    async fn claim_run(&self, run_id: Uuid) -> Result<(RunClaim, WorkflowRun), WorkflowError> {
+        if !self.active.lock().unwrap().insert(run_id) {
+            return Err(WorkflowError::RunActive(run_id));
+        }
+        let claim = RunClaim {
+            run_id,
+            active: self.active.clone(),
         };
-        
-        // 更新工作流状态为运行中
-        {
-            let mut workflows = self.workflows.write().await;
-            if let Some(wf) = workflows.get_mut(workflow_id) {
-                wf.status = WorkflowStatus::Running;
+        let run = self
+            .get_run(run_id)
+            .await?
+            .ok_or(WorkflowError::RunNotFound(run_id))?;
+        if run.is_finished() {
+            return Err(WorkflowError::RunFinished(run_id));
+        }
+        Ok((claim, run))
+    }
+
+    /// 在后台恢复存储中未结束的运行，返回恢复的运行ID
+    ///
+    /// 子工作流的运行由上层运行重新执行，因此直接记为失败。
+    pub async fn resume_interrupted(self: &Arc<Self>) -> Result<Vec<Uuid>, WorkflowError> {
+        let Some(store) = &self.store else {
+            return Ok(Vec::new());
+        };
+        let statuses = [WorkflowStatus::Pending.as_str(), WorkflowStatus::Running.as_str()];
+        let records = store
+            .list_workflow_runs_by_status(&statuses)
+            .await
+            .map_err(|e| WorkflowError::Storage(e.to_string()))?;
+
+        let mut resumed = Vec::new();
+        for record in records {
+            let mut run = WorkflowRun::from_record(record)?;
+            if run.parent_run.is_some() {
+                run.finish(&Err(WorkflowError::Interrupted));
+                self.save_run(&run).await;
+                continue;
+            }
+
+            info!("♻️  恢复工作流运行: {} ({})", run.workflow_id, run.id);
+            match self.start_run(run.id).await {
+                Ok(()) => resumed.push(run.id),
+                Err(e) => warn!("⚠️  恢复运行 {} 失败: {}", run.id, e),
             }
         }
-        
-        // 执行工作流步骤
-        let final_result = self.execute_workflow_steps(workflow_id, initial_input).await?;
-        
-        // 更新工作流状态为完成
+        Ok(resumed)
+    }
+
+    /// 获取运行记录，内存中没有时查询存储
+    pub async fn get_run(&self, run_id: Uuid) -> Result<Option<WorkflowRun>, WorkflowError> {
+        if let Some(run) = self.runs.read().await.get(&run_id) {
+            return Ok(Some(run.clone()));
+        }
+        let Some(store) = &self.store else {
+            return Ok(None);
+        };
+        let record = store
+            .get_workflow_run(&run_id)
+            .await
+            .map_err(|e| WorkflowError::Storage(e.to_string()))?;
+        record.map(WorkflowRun::from_record).transpose()
+    }
+
+    /// 按创建时间倒序列出运行，可限定工作流
+    pub async fn list_runs(&self, workflow_id: Option<&str>, page: Pagination) -> Result<Page<RunSummary>, WorkflowError> {
+        if let Some(store) = &self.store {
+            let records = store
+                .list_workflow_runs(workflow_id, page)
+                .await
+                .map_err(|e| WorkflowError::Storage(e.to_string()))?;
+            let items = records
+                .items
+                .into_iter()
+                .map(|record| WorkflowRun::from_record(record).map(|run| run.summary()))
+                .collect::<Result<_, _>>()?;
+            return Ok(Page {
+                items,
+                total: records.total,
+                offset: records.offset,
+                limit: records.limit,
+            });
+        }
+
+        let runs = self.runs.read().await;
+        let mut matching: Vec<&WorkflowRun> = runs
+            .values()
+            .filter(|run| workflow_id.is_none_or(|id| run.workflow_id == id))
+            .collect();
+        matching.sort_by_key(|run| std::cmp::Reverse((run.created_at, run.id)));
+        Ok(Page {
+            total: matching.len() as u64,
+            items: matching
+                .into_iter()
+                .skip(page.offset as usize)
+                .take(page.limit as usize)
+                .map(WorkflowRun::summary)
+                .collect(),
+            offset: page.offset,
+            limit: page.limit,
+        })
+    }
+
+    /// 保存检查点：更新内存中的运行并写入存储，存储失败只记录日志
+    async fn save_run(&self, run: &WorkflowRun) {
         {
-            let mut workflows = self.workflows.write().await;
-            if let Some(wf) = workflows.get_mut(workflow_id) {
-                wf.status = WorkflowStatus::Completed;
+            let mut runs = self.runs.write().await;
+            if self.store.is_some() && run.is_finished() {
+                runs.remove(&run.id);
+            } else {
+                runs.insert(run.id, run.clone());
             }
         }
-        
-        Ok(final_result)
+        let Some(store) = &self.store else {
+            return;
+        };
+        let saved = match run.to_record() {
+            Ok(record) => store
+                .save_workflow_run(&record)
+                .await
+                .map_err(|e| WorkflowError::Storage(e.to_string())),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            warn!("⚠️  工作流运行 {} 保存失败: {}", run.id, e);
+        }
     }
 
-    /// 执行工作流步骤
-    async fn execute_workflow_steps(&self, workflow_id: &str, initial_input: &str) -> Result<String, String> {
-        let workflows = self.workflows.read().await;
-        let workflow = workflows.get(workflow_id).ok_or("工作流未找到")?;
-        
-        let mut current_input = initial_input.to_string();
-        let mut step_results = HashMap::new();
-        
-        // 按顺序执行步骤（简化实现，实际应该考虑依赖关系）
-        for step in &workflow.steps {
-            info!("⏭️  执行步骤: {}", step.name);
-            
-            // 执行编排任务
-            let result = self.orchestration_engine.orchestrate(&current_input).await?;
-            
-            // 保存步骤结果
-            step_results.insert(step.id.clone(), result.clone());
-            current_input = result;
+    /// 执行运行直到结束；`stack` 为正在运行的上层工作流，`cancel` 触发时
+    /// 运行中的步骤被取消
+    fn execute(
+        &self,
+        mut run: WorkflowRun,
+        stack: Vec<String>,
+        cancel: CancellationToken,
+    ) -> BoxFuture<'_, Result<Value, WorkflowError>> {
+        Box::pin(async move {
+            info!("🔄 开始执行工作流: {} (运行 {})", run.workflow_id, run.id);
+            let workflow = run.workflow.clone();
+            run.status = WorkflowStatus::Running;
+            let mut stack = stack;
+            stack.push(workflow.id.clone());
+            let scope = RunScope {
+                run_id: run.id,
+                stack,
+                initiator: run.initiator.clone(),
+                cancel,
+            };
+
+            // 执行工作流步骤；超时后取消运行中的步骤，让它们通知 MCP 服务器和
+            // A2A 对端，最多等待 CANCEL_GRACE 后丢弃仍未结束的步骤。被丢弃的
+            // 步骤由 `run.finish` 记为失败，其中的子运行由 `abandon_children`
+            // 记为失败
+            let steps = self.execute_workflow_steps(&workflow, &mut run, &scope);
+            let outcomes = match workflow.timeout_secs {
+                Some(secs) => {
+                    tokio::pin!(steps);
+                    match tokio::time::timeout(Duration::from_secs(secs), &mut steps).await {
+                        Ok(outcomes) => Ok(outcomes),
+                        Err(_) => {
+                            scope.cancel.cancel();
+                            let _ = tokio::time::timeout(CANCEL_GRACE, steps).await;
+                            Err(WorkflowError::Timeout(secs))
+                        }
+                    }
+                }
+                None => Ok(steps.await),
+            };
+            let result = match outcomes {
+                Ok(outcomes) if outcomes.failed.is_empty() => Self::final_output(&workflow, &run.inputs, &outcomes),
+                Ok(outcomes) => {
+                    warn!("❌ 工作流 {} 失败: {:?}", workflow.id, outcomes.failed);
+                    Err(WorkflowError::StepsFailed {
+                        failed: outcomes.failed,
+                        skipped: outcomes.skipped,
+                    })
+                }
+                Err(e) => {
+                    warn!("⏰ 工作流 {} 超时", workflow.id);
+                    self.abandon_children(run.id, &e).await;
+                    Err(e)
+                }
+            };
+
+            run.finish(&result);
+            self.save_run(&run).await;
+            result
+        })
+    }
+
+    /// 把 `run_id` 仍未结束的子运行（逐层向下）记为失败；执行它们的步骤已被
+    /// 丢弃，子运行不会再写入结束状态
+    async fn abandon_children(&self, run_id: Uuid, error: &WorkflowError) {
+        let mut parents = vec![run_id];
+        while let Some(parent) = parents.pop() {
+            let children: Vec<WorkflowRun> = self
+                .runs
+                .read()
+                .await
+                .values()
+                .filter(|run| run.parent_run == Some(parent) && !run.is_finished())
+                .cloned()
+                .collect();
+            for mut child in children {
+                parents.push(child.id);
+                child.finish(&Err(error.clone()));
+                self.save_run(&child).await;
+            }
         }
-        
-        // 保存工作流结果
-        {
-            let mut workflows = self.workflows.write().await;
-            if let Some(wf) = workflows.get_mut(workflow_id) {
-                wf.results = step_results;
+    }
+
+    /// 按依赖关系调度步骤：依赖全部完成的步骤立即开始，互不依赖的步骤并发执行；
+    /// 步骤能看到所有已完成步骤的输出，校验保证模板只引用上游步骤。每轮调度
+    /// 前写入检查点。
+    async fn execute_workflow_steps(
+        &self,
+        workflow: &Workflow,
+        run: &mut WorkflowRun,
+        scope: &RunScope,
+    ) -> StepOutcomes {
+        let mut schedule = Schedule::resume(workflow, run);
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            for step in std::mem::take(&mut schedule.ready) {
+                info!("⏭️  执行步骤: {}", step.name);
+                let context = TemplateContext {
+                    inputs: run.inputs.clone(),
+                    steps: schedule.outcomes.step_values(),
+                    ..Default::default()
+                };
+                let input = StepInput {
+                    inputs: run.inputs.clone(),
+                    dependencies: step
+                        .dependencies
+                        .iter()
+                        .map(|dependency| (dependency.clone(), context.steps[dependency].clone()))
+                        .collect(),
+                    item: None,
+                    initiator: scope.initiator.clone(),
+                    cancel: scope.cancel.clone(),
+                };
+                run.step_mut(&step.id).start();
+                running.push(async move { (step, self.execute_step(step, input, context, scope).await) });
+            }
+            run.updated_at = chrono::Utc::now();
+            self.save_run(run).await;
+
+            let Some((step, result)) = running.next().await else {
+                break;
+            };
+            let record = run.step_mut(&step.id);
+            match result {
+                Ok(Some(output)) => {
+                    record.finish(StepStatus::Succeeded, Some(output.clone()), None);
+                    schedule.outcomes.outputs.insert(step.id.clone(), output);
+                }
+                Ok(None) => {
+                    info!("⏩ 条件不满足，不执行步骤: {}", step.name);
+                    record.finish(StepStatus::Bypassed, None, None);
+                    schedule.outcomes.bypassed.insert(step.id.clone());
+                }
+                Err(e) => {
+                    warn!("❌ 步骤 {} 失败: {}", step.id, e);
+                    record.finish(StepStatus::Failed, None, Some(e.clone()));
+                    schedule.outcomes.failed.insert(step.id.clone(), e);
+                }
             }
+            schedule.release(&step.id, run);
         }
-        
-        Ok(current_input)
+
+        schedule.outcomes
+    }
+
+    /// 执行一个步骤：检查条件，按 `for_each` 展开并重试，最终失败时交给
+    /// `on_error` 处理；条件不满足时返回 `None`
+    async fn execute_step(
+        &self,
+        step: &WorkflowStep,
+        input: StepInput,
+        context: TemplateContext,
+        scope: &RunScope,
+    ) -> Result<Option<Value>, String> {
+        if let Some(condition) = &step.condition {
+            let condition = Expression::parse(condition).map_err(|e| e.to_string())?;
+            if !condition.evaluate(&context).map_err(|e| e.to_string())? {
+                return Ok(None);
+            }
+        }
+
+        let result = match &step.for_each {
+            Some(items) => self.execute_for_each(step, items, &input, &context, scope).await,
+            None => self.execute_with_retry(step, &input, &context, scope).await,
+        };
+        let (error, handler) = match (result, &step.on_error) {
+            (Err(error), Some(handler)) => (error, handler),
+            (result, _) => return result.map(Some),
+        };
+
+        warn!("⚠️  步骤 {} 失败，执行错误处理: {}", step.id, error);
+        let context = TemplateContext {
+            error: Some(error),
+            ..context
+        };
+        let output = match (&handler.target, &handler.output) {
+            (Some(target), _) => {
+                let fallback = WorkflowStep {
+                    target: Some(target.clone()),
+                    ..step.clone()
+                };
+                let fallback = Self::render_target(&fallback, &context)?;
+                self.dispatch(&fallback, input, scope).await?
+            }
+            (None, Some(output)) => context.render(output).map_err(|e| e.to_string())?,
+            (None, None) => Value::Null,
+        };
+        Ok(Some(output))
+    }
+
+    /// 对 `for_each` 数组中的每个元素执行目标，输出按元素顺序排列，任一元素失败则步骤失败
+    async fn execute_for_each(
+        &self,
+        step: &WorkflowStep,
+        items: &str,
+        input: &StepInput,
+        context: &TemplateContext,
+        scope: &RunScope,
+    ) -> Result<Value, String> {
+        let items = match context.render_str(items).map_err(|e| e.to_string())? {
+            Value::Array(items) => items,
+            other => return Err(format!("for_each 的值不是数组: {other}")),
+        };
+        let concurrency = step.concurrency.unwrap_or(DEFAULT_FOR_EACH_CONCURRENCY).max(1);
+
+        let outputs: Vec<Value> = stream::iter(items.into_iter().enumerate())
+            .map(|(index, item)| {
+                let context = TemplateContext {
+                    item: Some((index, item.clone())),
+                    ..context.clone()
+                };
+                let input = StepInput {
+                    item: Some(item),
+                    ..input.clone()
+                };
+                async move {
+                    self.execute_with_retry(step, &input, &context, scope)
+                        .await
+                        .map_err(|e| format!("第 {index} 项失败: {e}"))
+                }
+            })
+            .buffered(concurrency)
+            .try_collect()
+            .await?;
+        Ok(Value::Array(outputs))
+    }
+
+    /// 展开模板后执行，失败时按重试策略重试；模板错误不重试
+    async fn execute_with_retry(
+        &self,
+        step: &WorkflowStep,
+        input: &StepInput,
+        context: &TemplateContext,
+        scope: &RunScope,
+    ) -> Result<Value, String> {
+        let step = Self::render_target(step, context)?;
+        let mut attempt = 1;
+        loop {
+            if scope.cancel.is_cancelled() {
+                return Err("工作流已取消".to_string());
+            }
+            let error = match self.dispatch(&step, input.clone(), scope).await {
+                Ok(output) => return Ok(output),
+                Err(error) => error,
+            };
+            let Some(retry) = step.retry.as_ref().filter(|retry| attempt < retry.max_attempts) else {
+                return Err(error);
+            };
+            let delay = retry.delay(attempt);
+            warn!(
+                "🔁 步骤 {} 第 {} 次执行失败，{:?} 后重试: {}",
+                step.id, attempt, delay, error
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = scope.cancel.cancelled() => return Err(error),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// 展开步骤目标中的模板
+    fn render_target(step: &WorkflowStep, context: &TemplateContext) -> Result<WorkflowStep, String> {
+        let mut step = step.clone();
+        step.target = step
+            .target
+            .map(|target| target.render(context))
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        Ok(step)
+    }
+
+    /// 执行已展开模板的步骤目标；子工作流由引擎自身运行
+    async fn dispatch(&self, step: &WorkflowStep, input: StepInput, scope: &RunScope) -> Result<Value, String> {
+        match &step.target {
+            Some(StepTarget::Workflow { workflow, inputs }) => self
+                .run_child(workflow, inputs.clone(), scope)
+                .await
+                .map_err(|e| e.to_string()),
+            _ => self.executor.execute(step, input).await,
+        }
+    }
+
+    /// 以子运行的形式执行子工作流
+    async fn run_child(&self, workflow_id: &str, inputs: Map<String, Value>, scope: &RunScope) -> Result<Value, WorkflowError> {
+        if scope.stack.iter().any(|id| id == workflow_id) {
+            return Err(WorkflowError::Recursion(workflow_id.to_string()));
+        }
+        let mut run = self.prepare_run(workflow_id, inputs).await?;
+        run.parent_run = Some(scope.run_id);
+        run.initiator = scope.initiator.clone();
+        self.execute(run, scope.stack.clone(), scope.cancel.child_token())
+            .await
+    }
+
+    /// 工作流输出：有输出模板时展开模板，否则取末端步骤的输出，
+    /// 多个末端步骤时按步骤ID组成对象
+    fn final_output(
+        workflow: &Workflow,
+        inputs: &Map<String, Value>,
+        outcomes: &StepOutcomes,
+    ) -> Result<Value, WorkflowError> {
+        if let Some(output) = &workflow.output {
+            let context = TemplateContext {
+                inputs: inputs.clone(),
+                steps: outcomes.step_values(),
+                ..Default::default()
+            };
+            return context.render(output);
+        }
+        let outputs = &outcomes.outputs;
+
+        let dependents = dag::dependents(workflow);
+        let sinks: Vec<(&String, &Value)> = workflow
+            .steps
+            .iter()
+            .filter(|step| !dependents.contains_key(&step.id))
+            .filter_map(|step| outputs.get(&step.id).map(|output| (&step.id, output)))
+            .collect();
+        Ok(match sinks.as_slice() {
+            [] => Value::Object(inputs.clone()),
+            [(_, output)] => (*output).clone(),
+            sinks => Value::Object(
+                sinks
+                    .iter()
+                    .map(|(id, output)| ((*id).clone(), (*output).clone()))
+                    .collect(),
+            ),
+        })
     }
 
-    /// 获取工作流状态
+    /// 获取工作流定义
+    pub async fn get_workflow(&self, workflow_id: &str) -> Option<Workflow> {
+        self.workflows.read().await.get(workflow_id).cloned()
+    }
+
+    /// 获取工作流最近一次运行的状态
     pub async fn get_workflow_status(&self, workflow_id: &str) -> Option<WorkflowStatus> {
-        let workflows = self.workflows.read().await;
-        workflows.get(workflow_id).map(|wf| wf.status.clone())
+        let latest = self.list_runs(Some(workflow_id), Pagination::new(0, 1)).await.ok()?;
+        latest.items.into_iter().next().map(|run| run.status)
     }
 
     /// 获取所有工作流
     pub async fn get_all_workflows(&self) -> Vec<Workflow> {
         let workflows = self.workflows.read().await;
-        workflows.values().cloned().collect()
+        let mut all: Vec<Workflow> = workflows.values().cloned().collect();
+        all.sort_by(|a, b| a.id.cmp(&b.id));
+        all
     }
 }
 
@@ -170,12 +1127,292 @@ mod tests {
             id: "test_workflow".to_string(),
             name: "测试工作流".to_string(),
             description: "用于测试的工作流".to_string(),
+            inputs: BTreeMap::new(),
             steps: vec![],
-            status: WorkflowStatus::Pending,
-            results: HashMap::new(),
+            output: None,
+            timeout_secs: None,
         };
         
         assert!(engine.register_workflow(workflow).await.is_ok());
         assert_eq!(engine.get_all_workflows().await.len(), 1);
     }
-}
\ No newline at end of file
+
+    fn step(id: &str, dependencies: &[&str]) -> WorkflowStep {
+        WorkflowStep {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            task_id: None,
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            condition: None,
+            target: None,
+            for_each: None,
+            concurrency: None,
+            retry: None,
+            on_error: None,
+        }
+    }
+
+    fn workflow(steps: Vec<WorkflowStep>) -> Workflow {
+        Workflow {
+            id: "dag".to_string(),
+            name: "DAG".to_string(),
+            description: String::new(),
+            inputs: BTreeMap::new(),
+            steps,
+            output: None,
+            timeout_secs: None,
+        }
+    }
+
+    /// 输出 `步骤ID(依赖输出)`，名为 `fail` 的步骤失败，并记录最大并发数
+    #[derive(Default)]
+    struct RecordingExecutor {
+        active: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl StepExecutor for RecordingExecutor {
+        async fn execute(&self, step: &WorkflowStep, input: StepInput) -> Result<Value, String> {
+            use std::sync::atomic::Ordering;
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            if step.id == "fail" {
+                return Err("boom".to_string());
+            }
+            let inputs: Vec<String> = input
+                .dependencies
+                .iter()
+                .map(|(_, output)| template::to_text(output))
+                .collect();
+            Ok(Value::String(format!("{}({})", step.id, inputs.join(","))))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registration_rejects_invalid_graphs() {
+        let engine = WorkflowEngine::with_executor(Arc::new(RecordingExecutor::default()));
+
+        let cyclic = workflow(vec![step("a", &["c"]), step("b", &["a"]), step("c", &["b"]), step("d", &[])]);
+        assert_eq!(
+            engine.register_workflow(cyclic).await,
+            Err(WorkflowError::Cycle(vec!["a".to_string(), "b".to_string(), "c".to_string()]))
+        );
+        let unknown = workflow(vec![step("a", &["missing"])]);
+        assert!(matches!(
+            engine.register_workflow(unknown).await,
+            Err(WorkflowError::UnknownDependency { .. })
+        ));
+        let duplicate = workflow(vec![step("a", &[]), step("a", &[])]);
+        assert_eq!(
+            engine.register_workflow(duplicate).await,
+            Err(WorkflowError::DuplicateStep("a".to_string()))
+        );
+        assert!(engine.get_all_workflows().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dag_runs_independent_steps_concurrently() {
+        let executor = Arc::new(RecordingExecutor::default());
+        let engine = WorkflowEngine::with_executor(executor.clone());
+        // a 和 b 并行，c 等待两者，d 等待 c
+        let dag = workflow(vec![step("d", &["c"]), step("c", &["a", "b"]), step("a", &[]), step("b", &[])]);
+        engine.register_workflow(dag).await.unwrap();
+
+        let output = engine.execute_workflow("dag", "input").await.unwrap();
+        assert_eq!(output, "d(c(a(),b()))");
+        assert_eq!(executor.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(matches!(
+            engine.get_workflow_status("dag").await,
+            Some(WorkflowStatus::Completed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failure_skips_dependent_steps() {
+        let engine = WorkflowEngine::with_executor(Arc::new(RecordingExecutor::default()));
+        let dag = workflow(vec![
+            step("fail", &[]),
+            step("after_fail", &["fail"]),
+            step("transitive", &["after_fail", "ok"]),
+            step("ok", &[]),
+            step("after_ok", &["ok"]),
+        ]);
+        engine.register_workflow(dag).await.unwrap();
+
+        let error = engine.execute_workflow("dag", "input").await.unwrap_err();
+        let WorkflowError::StepsFailed { failed, mut skipped } = error else {
+            panic!("unexpected error: {error:?}");
+        };
+        assert_eq!(failed.keys().collect::<Vec<_>>(), ["fail"]);
+        skipped.sort();
+        assert_eq!(skipped, ["after_fail", "transitive"]);
+
+        // 独立分支仍然执行完成，结果记录在运行中
+        let runs = engine.list_runs(Some("dag"), Pagination::default()).await.unwrap();
+        let run = engine.get_run(runs.items[0].id).await.unwrap().unwrap();
+        assert_eq!(run.status, WorkflowStatus::Failed);
+        assert_eq!(run.step("after_ok").unwrap().output, Some(Value::from("after_ok(ok())")));
+        assert_eq!(run.step("after_fail").unwrap().status, StepStatus::Skipped);
+    }
+
+    /// 以展开后的 LLM 提示词为输出：`fail` 总是失败，`flaky` 前两次失败，
+    /// `slow` 等待较长时间，取消时提前结束；记录调用次数和最大并发数
+    #[derive(Default)]
+    struct EchoExecutor {
+        calls: std::sync::atomic::AtomicUsize,
+        active: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl StepExecutor for EchoExecutor {
+        async fn execute(&self, step: &WorkflowStep, input: StepInput) -> Result<Value, String> {
+            use std::sync::atomic::Ordering;
+            let Some(StepTarget::Llm { prompt, .. }) = &step.target else {
+                return Err("缺少目标".to_string());
+            };
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            if prompt == "stuck" {
+                // 不响应取消的步骤
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            }
+            let delay = if prompt == "slow" { 5_000 } else { 10 };
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_millis(delay)) => {}
+                _ = input.cancel.cancelled() => {
+                    self.active.fetch_sub(1, Ordering::SeqCst);
+                    return Err("已取消".to_string());
+                }
+            }
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            match prompt.as_str() {
+                "fail" => Err("boom".to_string()),
+                "flaky" if calls < 3 => Err(format!("attempt {calls}")),
+                _ => Ok(Value::String(prompt.clone())),
+            }
+        }
+    }
+
+    async fn run_yaml(executor: Arc<EchoExecutor>, yaml: &str, inputs: Value) -> Result<Value, WorkflowError> {
+        let engine = WorkflowEngine::with_executor(executor);
+        let workflow = Workflow::from_yaml(yaml).unwrap();
+        let id = workflow.id.clone();
+        engine.register_workflow(workflow).await.unwrap();
+        engine.run_workflow(&id, inputs.as_object().unwrap().clone()).await
+    }
+
+    #[tokio::test]
+    async fn test_conditions_select_branches() {
+        let yaml = r#"
+id: branch
+inputs: { score: { required: true } }
+steps:
+  - id: pass
+    if: "inputs.score >= 50"
+    target: { type: llm, prompt: passed }
+  - id: after_pass
+    depends_on: [pass]
+    target: { type: llm, prompt: "after {{steps.pass.output}}" }
+  - id: retake
+    if: "!(inputs.score >= 50)"
+    target: { type: llm, prompt: retake }
+  - id: join
+    depends_on: [pass, retake]
+    if: "steps.pass.output || steps.retake.output"
+    target: { type: llm, prompt: "{{steps.pass.output}}|{{steps.retake.output}}" }
+output: { join: "{{steps.join.output}}", after: "{{steps.after_pass.output}}" }
+"#;
+        let high = run_yaml(Arc::default(), yaml, serde_json::json!({"score": 80})).await.unwrap();
+        assert_eq!(high, serde_json::json!({"join": "passed|null", "after": "after passed"}));
+        let low = run_yaml(Arc::default(), yaml, serde_json::json!({"score": 20})).await.unwrap();
+        assert_eq!(low, serde_json::json!({"join": "null|retake", "after": null}));
+    }
+
+    #[tokio::test]
+    async fn test_for_each_preserves_order_with_bounded_concurrency() {
+        let yaml = r#"
+id: loop
+steps:
+  - id: each
+    for_each: "{{inputs.items}}"
+    concurrency: 2
+    target: { type: llm, prompt: "{{index}}:{{item.name}}" }
+"#;
+        let executor = Arc::new(EchoExecutor::default());
+        let items = serde_json::json!({"items": [{"name": "a"}, {"name": "b"}, {"name": "c"}, {"name": "d"}]});
+        let output = run_yaml(executor.clone(), yaml, items).await.unwrap();
+        assert_eq!(output, serde_json::json!(["0:a", "1:b", "2:c", "3:d"]));
+        assert_eq!(executor.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let error = run_yaml(Arc::default(), yaml, serde_json::json!({"items": "abc"})).await.unwrap_err();
+        assert!(matches!(error, WorkflowError::StepsFailed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_error_handlers() {
+        let yaml = r#"
+id: recover
+steps:
+  - id: flaky
+    retry: { max_attempts: 3, backoff_ms: 1 }
+    target: { type: llm, prompt: flaky }
+  - id: constant
+    on_error: { output: "fallback: {{error}}" }
+    target: { type: llm, prompt: fail }
+  - id: alternative
+    on_error: { target: { type: llm, prompt: "recovered from {{error}}" } }
+    target: { type: llm, prompt: fail }
+"#;
+        let executor = Arc::new(EchoExecutor::default());
+        let output = run_yaml(executor.clone(), yaml, serde_json::json!({})).await.unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!({"flaky": "flaky", "constant": "fallback: boom", "alternative": "recovered from boom"})
+        );
+
+        // 重试次数用尽后失败
+        let exhausted = "id: exhausted\nsteps:\n  - id: s\n    retry: { max_attempts: 2, backoff_ms: 1 }\n    target: { type: llm, prompt: fail }";
+        let executor = Arc::new(EchoExecutor::default());
+        let error = run_yaml(executor.clone(), exhausted, serde_json::json!({})).await.unwrap_err();
+        assert!(matches!(error, WorkflowError::StepsFailed { .. }));
+        assert_eq!(executor.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_workflow_timeout() {
+        let yaml = "id: slow\ntimeout_secs: 1\nsteps:\n  - id: s\n    target: { type: llm, prompt: slow }";
+        let error = run_yaml(Arc::default(), yaml, serde_json::json!({})).await.unwrap_err();
+        assert_eq!(error, WorkflowError::Timeout(1));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_finishes_running_steps_and_child_runs() {
+        let engine = WorkflowEngine::with_executor(Arc::new(EchoExecutor::default()));
+        let child = "id: child\nsteps:\n  - id: stuck\n    target: { type: llm, prompt: stuck }";
+        let parent = "id: parent\ntimeout_secs: 1\nsteps:\n  - id: slow\n    target: { type: llm, prompt: slow }\n  - id: nested\n    target: { type: workflow, workflow: child }";
+        for yaml in [child, parent] {
+            engine.register_workflow(Workflow::from_yaml(yaml).unwrap()).await.unwrap();
+        }
+        let error = engine.run_workflow("parent", Map::new()).await.unwrap_err();
+        assert_eq!(error, WorkflowError::Timeout(1));
+
+        // 响应取消的步骤在宽限期内结束，不响应的被丢弃并记为超时
+        let runs = engine.list_runs(None, Pagination::new(0, 10)).await.unwrap().items;
+        assert_eq!(runs.len(), 2);
+        for summary in runs {
+            let run = engine.get_run(summary.id).await.unwrap().unwrap();
+            assert_eq!(run.status, WorkflowStatus::Failed, "{}", run.workflow_id);
+            assert!(run.steps.iter().all(|step| step.status == StepStatus::Failed));
+            let stuck = run.steps.iter().find(|step| step.step_id != "slow").unwrap();
+            assert!(stuck.error.as_deref().unwrap().contains("超时"));
+        }
+    }
+}
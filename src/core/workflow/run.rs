@@ -0,0 +1,318 @@
+//! 工作流运行记录 - 每次执行独立的输入、步骤状态和输出
+//!
+//! 运行开始时保存工作流定义的快照，每个步骤结束后写入检查点。进程重启后，
+//! 未结束的运行从快照恢复：已成功或未满足条件的步骤保留结果，其余步骤重新执行。
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use super::{dag, Workflow, WorkflowError, WorkflowStatus};
+use crate::integrations::database::WorkflowRunRecord;
+use crate::services::security::Permission;
+
+/// 步骤在一次运行中的状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    #[default]
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    /// 上游失败而跳过
+    Skipped,
+    /// 条件不满足而未执行
+    Bypassed,
+}
+
+impl StepStatus {
+    /// 恢复或重跑时可以直接复用的结果
+    pub fn is_reusable(self) -> bool {
+        matches!(self, StepStatus::Succeeded | StepStatus::Bypassed)
+    }
+}
+
+/// 步骤运行记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepRun {
+    pub step_id: String,
+    pub status: StepStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl StepRun {
+    pub(super) fn pending(step_id: &str) -> Self {
+        Self {
+            step_id: step_id.to_string(),
+            status: StepStatus::Pending,
+            output: None,
+            error: None,
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    pub(super) fn start(&mut self) {
+        self.status = StepStatus::Running;
+        self.started_at = Some(Utc::now());
+    }
+
+    pub(super) fn finish(
+        &mut self,
+        status: StepStatus,
+        output: Option<Value>,
+        error: Option<String>,
+    ) {
+        self.status = status;
+        self.output = output;
+        self.error = error;
+        self.finished_at = Some(Utc::now());
+    }
+}
+
+/// 发起运行的用户及其权限，步骤调用工具和 A2A 智能体时按这些权限检查
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunInitiator {
+    pub user_id: String,
+    pub permissions: Vec<Permission>,
+}
+
+/// 一次工作流运行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: Uuid,
+    pub workflow_id: String,
+    pub status: WorkflowStatus,
+    /// 补全默认值后的输入
+    pub inputs: Map<String, Value>,
+    /// 各步骤的状态，按定义顺序排列
+    pub steps: Vec<StepRun>,
+    #[serde(default)]
+    pub output: Option<Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// 重跑来源运行
+    #[serde(default)]
+    pub rerun_of: Option<Uuid>,
+    /// 子工作流运行所属的上层运行
+    #[serde(default)]
+    pub parent_run: Option<Uuid>,
+    /// 发起运行的用户，省略时不检查步骤权限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initiator: Option<RunInitiator>,
+    /// 运行开始时的工作流定义，恢复和重跑都使用该快照
+    pub workflow: Workflow,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 运行列表中的摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub id: Uuid,
+    pub workflow_id: String,
+    pub status: WorkflowStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub rerun_of: Option<Uuid>,
+    #[serde(default)]
+    pub parent_run: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WorkflowRun {
+    /// 创建待执行的运行，`inputs` 应已通过 [`Workflow::resolve_inputs`] 校验
+    pub fn new(workflow: Workflow, inputs: Map<String, Value>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            workflow_id: workflow.id.clone(),
+            status: WorkflowStatus::Pending,
+            inputs,
+            steps: workflow
+                .steps
+                .iter()
+                .map(|step| StepRun::pending(&step.id))
+                .collect(),
+            output: None,
+            error: None,
+            rerun_of: None,
+            parent_run: None,
+            initiator: None,
+            workflow,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// 基于本次运行创建新运行：`from_step` 及其所有下游步骤重新执行，其余已成功
+    /// 或未满足条件的步骤沿用本次结果；省略 `from_step` 时全部重新执行
+    pub fn rerun_from(&self, from_step: Option<&str>) -> Result<Self, WorkflowError> {
+        let mut rerun = Self::new(self.workflow.clone(), self.inputs.clone());
+        rerun.rerun_of = Some(self.id);
+        let Some(from_step) = from_step else {
+            return Ok(rerun);
+        };
+        if self.step(from_step).is_none() {
+            return Err(WorkflowError::UnknownStep(from_step.to_string()));
+        }
+
+        let dependents = dag::dependents(&self.workflow);
+        let mut invalidated = HashSet::from([from_step.to_string()]);
+        let mut pending = vec![from_step.to_string()];
+        while let Some(id) = pending.pop() {
+            for dependent in dependents.get(&id).into_iter().flatten() {
+                if invalidated.insert(dependent.clone()) {
+                    pending.push(dependent.clone());
+                }
+            }
+        }
+
+        for (step, previous) in rerun.steps.iter_mut().zip(&self.steps) {
+            if !invalidated.contains(&step.step_id) && previous.status.is_reusable() {
+                *step = previous.clone();
+            }
+        }
+        Ok(rerun)
+    }
+
+    pub fn step(&self, step_id: &str) -> Option<&StepRun> {
+        self.steps.iter().find(|step| step.step_id == step_id)
+    }
+
+    pub(super) fn step_mut(&mut self, step_id: &str) -> &mut StepRun {
+        self.steps
+            .iter_mut()
+            .find(|step| step.step_id == step_id)
+            .expect("运行中的步骤")
+    }
+
+    /// 步骤时间线：已开始的步骤按开始时间排列，未开始的步骤按定义顺序排在最后
+    pub fn timeline(&self) -> Vec<&StepRun> {
+        let mut timeline: Vec<&StepRun> = self.steps.iter().collect();
+        timeline.sort_by_key(|step| (step.started_at.is_none(), step.started_at));
+        timeline
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            WorkflowStatus::Completed | WorkflowStatus::Failed
+        )
+    }
+
+    /// 记录运行结果；仍在运行的步骤（如超时被取消）记为失败
+    pub(super) fn finish(&mut self, result: &Result<Value, WorkflowError>) {
+        match result {
+            Ok(output) => {
+                self.status = WorkflowStatus::Completed;
+                self.output = Some(output.clone());
+                self.error = None;
+            }
+            Err(error) => {
+                self.status = WorkflowStatus::Failed;
+                self.output = None;
+                self.error = Some(error.to_string());
+                for step in &mut self.steps {
+                    if step.status == StepStatus::Running {
+                        step.finish(StepStatus::Failed, None, Some(error.to_string()));
+                    }
+                }
+            }
+        }
+        self.updated_at = Utc::now();
+    }
+
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            id: self.id,
+            workflow_id: self.workflow_id.clone(),
+            status: self.status.clone(),
+            error: self.error.clone(),
+            rerun_of: self.rerun_of,
+            parent_run: self.parent_run,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    pub fn to_record(&self) -> Result<WorkflowRunRecord, WorkflowError> {
+        Ok(WorkflowRunRecord {
+            id: self.id,
+            workflow_id: self.workflow_id.clone(),
+            status: self.status.as_str().to_string(),
+            data: serde_json::to_value(self).map_err(|e| WorkflowError::Storage(e.to_string()))?,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+
+    pub fn from_record(record: WorkflowRunRecord) -> Result<Self, WorkflowError> {
+        serde_json::from_value(record.data).map_err(|e| WorkflowError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rerun_keeps_results_outside_invalidated_steps() {
+        let workflow = Workflow::from_yaml(
+            "id: w\nsteps:\n  - id: a\n  - id: b\n    depends_on: [a]\n  - id: c\n    depends_on: [b]\n  - id: d\n  - id: e",
+        )
+        .unwrap();
+        let mut run = WorkflowRun::new(workflow, Map::new());
+        for (id, status) in [
+            ("a", StepStatus::Succeeded),
+            ("b", StepStatus::Succeeded),
+            ("c", StepStatus::Succeeded),
+            ("d", StepStatus::Bypassed),
+            ("e", StepStatus::Failed),
+        ] {
+            let step = run.step_mut(id);
+            step.start();
+            step.finish(status, Some(json!(id)), None);
+        }
+        run.finish(&Err(WorkflowError::NotFound("x".to_string())));
+
+        let rerun = run.rerun_from(Some("b")).unwrap();
+        assert_eq!(rerun.rerun_of, Some(run.id));
+        assert_eq!(rerun.status, WorkflowStatus::Pending);
+        let statuses: Vec<StepStatus> = rerun.steps.iter().map(|step| step.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                StepStatus::Succeeded,
+                StepStatus::Pending,
+                StepStatus::Pending,
+                StepStatus::Bypassed,
+                StepStatus::Pending
+            ]
+        );
+        assert_eq!(rerun.step("a").unwrap().output, Some(json!("a")));
+        assert!(matches!(
+            run.rerun_from(Some("missing")),
+            Err(WorkflowError::UnknownStep(_))
+        ));
+
+        let record = rerun.to_record().unwrap();
+        assert_eq!(record.status, "pending");
+        let restored = WorkflowRun::from_record(record).unwrap();
+        assert_eq!(restored.steps, rerun.steps);
+    }
+}
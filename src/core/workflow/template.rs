@@ -0,0 +1,329 @@
+//! 步骤输入模板 - 解析并替换 `{{ ... }}` 引用
+//!
+//! 支持的引用：
+//! - `{{inputs.name}}`：工作流输入
+//! - `{{steps.fetch.output.field}}`：上游步骤的输出，可继续按字段名或数组下标取值
+//! - `{{env.NAME}}`：环境变量
+//! - `{{item.field}}`、`{{index}}`：`for_each` 步骤中的当前元素及其下标
+//! - `{{error}}`：`on_error` 处理中步骤的错误信息
+//!
+//! 字符串恰好是一个引用时替换为被引用的 JSON 值本身（保留类型），否则按
+//! 文本插值。替换结果不会再次展开，输入值中的 `{{ }}` 原样保留。
+
+use serde_json::{Map, Value};
+
+use super::WorkflowError;
+
+/// 模板中的一处引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    Input { name: String, path: Vec<String> },
+    Step { id: String, path: Vec<String> },
+    Env(String),
+    Item { path: Vec<String> },
+    Index,
+    Error,
+}
+
+impl Reference {
+    /// 解析 `{{ }}` 内的表达式
+    pub fn parse(expression: &str) -> Result<Self, WorkflowError> {
+        let segments = expression
+            .trim()
+            .split('.')
+            .map(|s| s.trim().to_string())
+            .collect();
+        Self::from_segments(segments)
+            .ok_or_else(|| WorkflowError::Template(format!("无效的引用 `{{{{{expression}}}}}`")))
+    }
+
+    /// 由路径段构造引用，如 `["steps", "fetch", "output", "items", "0"]`
+    pub fn from_segments(segments: Vec<String>) -> Option<Self> {
+        if segments.iter().any(String::is_empty) {
+            return None;
+        }
+        let mut segments = segments.into_iter();
+        let root = segments.next()?;
+        if root == "item" {
+            return Some(Self::Item {
+                path: segments.collect(),
+            });
+        }
+        let name = segments.next();
+        let rest: Vec<String> = segments.collect();
+
+        match (root.as_str(), name) {
+            ("index", None) => Some(Self::Index),
+            ("error", None) => Some(Self::Error),
+            ("inputs", Some(name)) => Some(Self::Input { name, path: rest }),
+            ("steps", Some(id)) => match rest.split_first() {
+                Some((output, path)) if output == "output" => Some(Self::Step {
+                    id,
+                    path: path.to_vec(),
+                }),
+                _ => None,
+            },
+            ("env", Some(name)) if rest.is_empty() => Some(Self::Env(name)),
+            _ => None,
+        }
+    }
+}
+
+/// 模板求值所需的数据
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub inputs: Map<String, Value>,
+    pub steps: Map<String, Value>,
+    /// `for_each` 的当前下标和元素
+    pub item: Option<(usize, Value)>,
+    /// `on_error` 处理的错误信息
+    pub error: Option<String>,
+}
+
+impl TemplateContext {
+    /// 解析引用的值
+    pub fn resolve(&self, reference: &Reference) -> Result<Value, WorkflowError> {
+        let (root, path) = match reference {
+            Reference::Input { name, path } => (self.inputs.get(name), path),
+            Reference::Step { id, path } => (self.steps.get(id), path),
+            Reference::Item { path } => (self.item.as_ref().map(|(_, item)| item), path),
+            Reference::Index => {
+                return self
+                    .item
+                    .as_ref()
+                    .map(|(index, _)| Value::from(*index))
+                    .ok_or_else(|| unresolved(reference));
+            }
+            Reference::Error => {
+                return self
+                    .error
+                    .clone()
+                    .map(Value::String)
+                    .ok_or_else(|| unresolved(reference));
+            }
+            Reference::Env(name) => {
+                return std::env::var(name)
+                    .map(Value::String)
+                    .map_err(|_| WorkflowError::Template(format!("环境变量未设置: {name}")));
+            }
+        };
+
+        let mut value = root.ok_or_else(|| unresolved(reference))?;
+        for segment in path {
+            value = match value {
+                Value::Object(map) => map.get(segment),
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            }
+            .ok_or_else(|| unresolved(reference))?;
+        }
+        Ok(value.clone())
+    }
+
+    /// 展开 JSON 值中所有字符串里的模板
+    pub fn render(&self, template: &Value) -> Result<Value, WorkflowError> {
+        match template {
+            Value::String(text) => self.render_str(text),
+            Value::Array(items) => items.iter().map(|item| self.render(item)).collect(),
+            Value::Object(map) => self.render_object(map).map(Value::Object),
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// 展开对象中各字段的模板
+    pub fn render_object(
+        &self,
+        template: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, WorkflowError> {
+        template
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), self.render(value)?)))
+            .collect()
+    }
+
+    /// 展开字符串模板
+    pub fn render_str(&self, text: &str) -> Result<Value, WorkflowError> {
+        let parts = split(text)?;
+        if let [Part::Reference(reference)] = parts.as_slice() {
+            return self.resolve(reference);
+        }
+
+        let mut rendered = String::new();
+        for part in parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Reference(reference) => {
+                    rendered.push_str(&to_text(&self.resolve(&reference)?))
+                }
+            }
+        }
+        Ok(Value::String(rendered))
+    }
+
+    /// 展开字符串模板并转为文本
+    pub fn render_text(&self, text: &str) -> Result<String, WorkflowError> {
+        self.render_str(text).map(|value| to_text(&value))
+    }
+}
+
+/// JSON 值的文本形式：字符串取原文，其它类型序列化为 JSON
+pub fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 收集 JSON 值中出现的所有引用，用于注册时校验
+pub fn references(template: &Value) -> Result<Vec<Reference>, WorkflowError> {
+    let mut found = Vec::new();
+    collect(template, &mut found)?;
+    Ok(found)
+}
+
+fn collect(template: &Value, found: &mut Vec<Reference>) -> Result<(), WorkflowError> {
+    match template {
+        Value::String(text) => {
+            for part in split(text)? {
+                if let Part::Reference(reference) = part {
+                    found.push(reference);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect(item, found)?;
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values() {
+                collect(value, found)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Reference(Reference),
+}
+
+fn split(text: &str) -> Result<Vec<Part<'_>>, WorkflowError> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| WorkflowError::Template(format!("未闭合的模板: {text}")))?;
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        parts.push(Part::Reference(Reference::parse(
+            &rest[start + 2..start + end],
+        )?));
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Ok(parts)
+}
+
+fn unresolved(reference: &Reference) -> WorkflowError {
+    WorkflowError::Template(format!("无法解析引用: {reference:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            inputs: json!({"city": "Paris", "days": 3})
+                .as_object()
+                .unwrap()
+                .clone(),
+            steps: json!({"fetch": {"items": [{"name": "a"}, {"name": "b"}], "ok": true}})
+                .as_object()
+                .unwrap()
+                .clone(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_keeps_types_of_whole_references() {
+        let context = context();
+        assert_eq!(
+            context
+                .render(&json!({
+                    "days": "{{ inputs.days }}",
+                    "items": "{{steps.fetch.output.items}}",
+                    "text": "{{inputs.city}} x{{inputs.days}}: {{steps.fetch.output.items.1.name}}"
+                }))
+                .unwrap(),
+            json!({
+                "days": 3,
+                "items": [{"name": "a"}, {"name": "b"}],
+                "text": "Paris x3: b"
+            })
+        );
+        assert_eq!(
+            context.render_text("{{steps.fetch.output.ok}}").unwrap(),
+            "true"
+        );
+    }
+
+    #[test]
+    fn test_invalid_and_missing_references() {
+        let context = context();
+        assert!(matches!(
+            context.render_str("{{steps.fetch}}"),
+            Err(WorkflowError::Template(_))
+        ));
+        assert!(matches!(
+            context.render_str("{{other.x}}"),
+            Err(WorkflowError::Template(_))
+        ));
+        assert!(matches!(
+            context.render_str("{{inputs.city"),
+            Err(WorkflowError::Template(_))
+        ));
+        assert!(matches!(
+            context.render_str("{{steps.fetch.output.items.9}}"),
+            Err(WorkflowError::Template(_))
+        ));
+
+        // 输入值中的模板不会再次展开
+        let mut context = context;
+        context
+            .inputs
+            .insert("raw".to_string(), json!("{{env.HOME}}"));
+        assert_eq!(
+            context.render_text("{{inputs.raw}}").unwrap(),
+            "{{env.HOME}}"
+        );
+    }
+
+    #[test]
+    fn test_item_and_error_references() {
+        let mut context = context();
+        assert!(context.render_str("{{item}}").is_err());
+        assert!(matches!(
+            context.render_str("{{index.x}}"),
+            Err(WorkflowError::Template(_))
+        ));
+
+        context.item = Some((1, json!({"name": "b"})));
+        context.error = Some("boom".to_string());
+        assert_eq!(
+            context
+                .render_text("{{index}}:{{item.name}} {{error}}")
+                .unwrap(),
+            "1:b boom"
+        );
+    }
+}
@@ -1,30 +1,109 @@
 //! 数据库连接管理
 //!
-//! 该模块提供了数据库连接管理和池化功能。
+//! 该模块提供了数据库连接管理和池化功能。默认使用内嵌的 SQLite，
+//! 启用 `postgres` 特性后可使用 PostgreSQL。
 
-use sqlx::{PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::str::FromStr;
 use std::sync::Arc;
 use thiserror::Error;
 
+#[cfg(feature = "postgres")]
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
 /// 数据库错误类型
 #[derive(Debug, Error)]
 pub enum DatabaseError {
     /// SQLx错误
     #[error("SQLx error: {0}")]
     Sqlx(#[from] sqlx::Error),
-    
+
+    /// 迁移错误
+    #[error("Migration error: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
+
     /// 配置错误
     #[error("Configuration error: {0}")]
     Config(String),
 }
 
-/// 数据库管理器
+/// SQLite数据库管理器
+#[derive(Debug, Clone)]
+pub struct SqliteManager {
+    /// SQLite连接池
+    pool: Arc<SqlitePool>,
+}
+
+impl SqliteManager {
+    /// 创建新的SQLite数据库管理器
+    ///
+    /// # 参数
+    /// * `database_url` - 数据库连接URL，例如 `sqlite://data/omni.db`
+    ///
+    /// # 返回值
+    /// 返回新的数据库管理器实例或错误，数据库文件不存在时会自动创建
+    pub async fn new(database_url: &str) -> Result<Self, DatabaseError> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+
+        Ok(Self {
+            pool: Arc::new(pool),
+        })
+    }
+
+    /// 创建内存数据库管理器
+    ///
+    /// 内存数据库只存在于单个连接中，因此连接池大小固定为1。
+    pub async fn in_memory() -> Result<Self, DatabaseError> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?.foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+
+        Ok(Self {
+            pool: Arc::new(pool),
+        })
+    }
+
+    /// 获取数据库连接池
+    pub fn get_pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// 执行版本化迁移
+    ///
+    /// 已执行过的迁移会被跳过，可以安全地重复调用。
+    pub async fn run_migrations(&self) -> Result<(), DatabaseError> {
+        sqlx::migrate!("src/integrations/database/migrations/sqlite")
+            .run(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 检查数据库连接
+    pub async fn check_connection(&self) -> Result<bool, DatabaseError> {
+        sqlx::query("SELECT 1").execute(&*self.pool).await?;
+        Ok(true)
+    }
+}
+
+/// PostgreSQL数据库管理器
+#[cfg(feature = "postgres")]
 #[derive(Debug, Clone)]
 pub struct DatabaseManager {
     /// PostgreSQL连接池
     pool: Arc<PgPool>,
 }
 
+#[cfg(feature = "postgres")]
 impl DatabaseManager {
     /// 创建新的数据库管理器
     ///
@@ -51,7 +130,15 @@ impl DatabaseManager {
     pub fn get_pool(&self) -> &PgPool {
         &self.pool
     }
-    
+
+    /// 执行版本化迁移
+    pub async fn run_migrations(&self) -> Result<(), DatabaseError> {
+        sqlx::migrate!("src/integrations/database/migrations/postgres")
+            .run(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
     /// 检查数据库连接
     ///
     /// # 返回值
@@ -62,4 +149,4 @@ impl DatabaseManager {
             Err(e) => Err(DatabaseError::Sqlx(e)),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,3 @@
+-- 智能体名称索引，用于列表与搜索
+CREATE INDEX IF NOT EXISTS idx_agents_name ON agents(name);
+CREATE INDEX IF NOT EXISTS idx_agents_created_at ON agents(created_at);
@@ -0,0 +1,12 @@
+-- 工作流运行记录（运行详情以JSON存储）
+CREATE TABLE IF NOT EXISTS workflow_runs (
+    id UUID PRIMARY KEY,
+    workflow_id VARCHAR(255) NOT NULL,
+    status VARCHAR(32) NOT NULL,
+    data JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_workflow_id ON workflow_runs(workflow_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_status ON workflow_runs(status);
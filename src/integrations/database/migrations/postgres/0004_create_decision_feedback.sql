@@ -0,0 +1,13 @@
+-- 决策反馈记录，用于按规则和目标计算成功率
+CREATE TABLE IF NOT EXISTS decision_feedback (
+    id UUID PRIMARY KEY,
+    decision_id VARCHAR(255) NOT NULL,
+    rule_id VARCHAR(255),
+    action VARCHAR(255) NOT NULL,
+    context TEXT NOT NULL,
+    outcome VARCHAR(32) NOT NULL,
+    feedback REAL NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_decision_feedback_created_at ON decision_feedback(created_at);
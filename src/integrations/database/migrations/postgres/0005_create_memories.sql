@@ -0,0 +1,13 @@
+-- 用户和智能体的长期记忆
+CREATE TABLE IF NOT EXISTS memories (
+    id UUID PRIMARY KEY,
+    user_id VARCHAR(255) NOT NULL,
+    agent_id VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_memories_user_agent ON memories(user_id, agent_id);
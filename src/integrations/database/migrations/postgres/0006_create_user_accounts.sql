@@ -0,0 +1,12 @@
+-- 安全管理器的用户账户
+CREATE TABLE IF NOT EXISTS user_accounts (
+    id VARCHAR(255) PRIMARY KEY,
+    username VARCHAR(255) NOT NULL UNIQUE,
+    role VARCHAR(32) NOT NULL,
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    password_hash TEXT NOT NULL,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_login TIMESTAMP WITH TIME ZONE
+);
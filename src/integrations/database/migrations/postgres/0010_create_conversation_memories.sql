@@ -0,0 +1,12 @@
+-- 对话自动概括出的长期记忆，按用户和会话归属
+CREATE TABLE IF NOT EXISTS conversation_memories (
+    id UUID PRIMARY KEY,
+    user_id VARCHAR(255) NOT NULL,
+    session_id VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    sources UUID[] NOT NULL DEFAULT '{}',
+    importance REAL NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_memories_user_session ON conversation_memories(user_id, session_id);
@@ -0,0 +1,12 @@
+-- 工作流运行记录（运行详情以JSON存储）
+CREATE TABLE IF NOT EXISTS workflow_runs (
+    id TEXT PRIMARY KEY NOT NULL,
+    workflow_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_workflow_id ON workflow_runs(workflow_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_status ON workflow_runs(status);
@@ -0,0 +1,13 @@
+-- 决策反馈记录，用于按规则和目标计算成功率
+CREATE TABLE IF NOT EXISTS decision_feedback (
+    id TEXT PRIMARY KEY NOT NULL,
+    decision_id TEXT NOT NULL,
+    rule_id TEXT,
+    action TEXT NOT NULL,
+    context TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    feedback REAL NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_decision_feedback_created_at ON decision_feedback(created_at);
@@ -0,0 +1,12 @@
+-- 安全管理器的用户账户
+CREATE TABLE IF NOT EXISTS user_accounts (
+    id TEXT PRIMARY KEY NOT NULL,
+    username TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL,
+    permissions TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TEXT,
+    created_at TEXT NOT NULL,
+    last_login TEXT
+);
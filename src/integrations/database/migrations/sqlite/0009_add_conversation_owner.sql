@@ -0,0 +1,2 @@
+-- 对话所有者（认证用户ID），用于重启后恢复会话归属
+ALTER TABLE conversations ADD COLUMN owner TEXT;
@@ -0,0 +1,12 @@
+-- 对话自动概括出的长期记忆，按用户和会话归属
+CREATE TABLE IF NOT EXISTS conversation_memories (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    sources TEXT NOT NULL DEFAULT '[]',
+    importance REAL NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_memories_user_session ON conversation_memories(user_id, session_id);
@@ -1,11 +1,25 @@
 //! 数据库集成模块
 //!
-//! 该模块提供了数据库集成功能，包括连接管理、数据访问等。
+//! 该模块提供了数据库集成功能，包括连接管理、版本化迁移和数据访问等。
+//! 默认使用内嵌的 SQLite 后端，PostgreSQL 后端需要启用 `postgres` 特性。
 
 pub mod connection;
 pub mod models;
 pub mod repository;
+pub mod sqlite;
 
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+pub use connection::{DatabaseError, SqliteManager};
+pub use models::{
+    AgentRecord, ApiKeyRecord, ConversationMemoryRecord, ConversationRecord, FeedbackRecord, MemoryRecord, MessageRecord,
+    Page, Pagination, RevokedTokenRecord, UserAccountRecord, WorkflowRunRecord,
+};
+pub use repository::{DataRepository, RepositoryError};
+pub use sqlite::SqliteRepository;
+
+#[cfg(feature = "postgres")]
 pub use connection::DatabaseManager;
-pub use models::{AgentRecord, ConversationRecord, MessageRecord};
-pub use repository::DataRepository;
\ No newline at end of file
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresRepository;
@@ -3,11 +3,11 @@
 //! 该模块定义了数据库中使用的数据模型。
 
 use serde::{Deserialize, Serialize};
-use sqlx::types::Uuid;
+use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 /// 智能体记录模型
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentRecord {
     /// 记录ID
     pub id: Uuid,
@@ -29,7 +29,7 @@ pub struct AgentRecord {
 }
 
 /// 对话记录模型
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConversationRecord {
     /// 记录ID
     pub id: Uuid,
@@ -40,6 +40,10 @@ pub struct ConversationRecord {
     /// 参与者列表
     pub participants: Vec<String>,
     
+    /// 所有者（认证用户ID），保存后不再变更
+    #[serde(default)]
+    pub owner: Option<String>,
+    
     /// 创建时间
     pub created_at: DateTime<Utc>,
     
@@ -48,7 +52,7 @@ pub struct ConversationRecord {
 }
 
 /// 消息记录模型
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MessageRecord {
     /// 记录ID
     pub id: Uuid,
@@ -73,4 +77,237 @@ pub struct MessageRecord {
     
     /// 元数据
     pub metadata: Option<serde_json::Value>,
-}
\ No newline at end of file
+}
+
+/// 工作流运行记录模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowRunRecord {
+    /// 运行ID
+    pub id: Uuid,
+
+    /// 工作流ID
+    pub workflow_id: String,
+
+    /// 运行状态
+    pub status: String,
+
+    /// 运行详情（定义快照、输入、各步骤状态和输出）
+    pub data: serde_json::Value,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 决策反馈记录模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    /// 记录ID
+    pub id: Uuid,
+
+    /// 被评价的决策，例如回复消息的ID
+    pub decision_id: String,
+
+    /// 做出决策的规则
+    pub rule_id: Option<String>,
+
+    /// 决策的动作
+    pub action: String,
+
+    /// 决策时的上下文
+    pub context: String,
+
+    /// 结果
+    pub outcome: String,
+
+    /// 反馈分数（-1.0 到 1.0）
+    pub feedback: f32,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 长期记忆记录模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryRecord {
+    /// 记录ID
+    pub id: Uuid,
+
+    /// 记忆所属的用户
+    pub user_id: String,
+
+    /// 记住该内容的智能体
+    pub agent_id: String,
+
+    /// 记忆内容
+    pub content: String,
+
+    /// 标签
+    pub tags: Vec<String>,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+
+    /// 过期时间，为空表示永不过期
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 对话记忆记录模型：分层记忆中自动概括出的长期记忆
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationMemoryRecord {
+    /// 记录ID
+    pub id: Uuid,
+
+    /// 记忆所属的用户
+    pub user_id: String,
+
+    /// 记忆来自的会话
+    pub session_id: String,
+
+    /// 摘要内容
+    pub content: String,
+
+    /// 被概括的消息ID
+    pub sources: Vec<Uuid>,
+
+    /// 重要性（0 到 1）
+    pub importance: f32,
+
+    /// 最早的源消息时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 用户账户记录模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserAccountRecord {
+    /// 用户ID
+    pub id: String,
+
+    /// 用户名，唯一
+    pub username: String,
+
+    /// 角色，例如 `admin`
+    pub role: String,
+
+    /// 权限字符串，格式见 `Permission::parse`
+    pub permissions: Vec<String>,
+
+    /// argon2 密码哈希（PHC 字符串）
+    pub password_hash: String,
+
+    /// 连续认证失败次数
+    pub failed_attempts: i32,
+
+    /// 锁定截止时间，为空表示未锁定
+    pub locked_until: Option<DateTime<Utc>>,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 最后登录时间
+    pub last_login: Option<DateTime<Utc>>,
+
+    /// 令牌版本，递增后之前签发的令牌失效
+    pub token_version: i32,
+}
+
+/// API 密钥记录模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    /// 记录ID
+    pub id: Uuid,
+
+    /// 便于识别的名称
+    pub name: String,
+
+    /// 密钥的公开前缀，用于查找记录
+    pub prefix: String,
+
+    /// 密钥的 SHA-256 哈希（十六进制）
+    pub key_hash: String,
+
+    /// 密钥所属的用户，通常是服务账户
+    pub user_id: String,
+
+    /// 密钥的权限范围，格式见 `Permission::parse`
+    pub permissions: Vec<String>,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 过期时间，为空表示永不过期
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// 最后使用时间
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// 被吊销的令牌记录模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevokedTokenRecord {
+    /// 令牌ID（JWT 的 `jti`）
+    pub jti: String,
+
+    /// 令牌所属的用户
+    pub user_id: String,
+
+    /// 令牌原本的过期时间，之后记录可以清理
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 分页参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pagination {
+    /// 跳过的记录数
+    pub offset: u32,
+
+    /// 每页最大记录数
+    pub limit: u32,
+}
+
+impl Pagination {
+    /// 单页允许的最大记录数
+    pub const MAX_LIMIT: u32 = 500;
+
+    /// 创建分页参数，`limit` 会被限制在 1 到 [`Pagination::MAX_LIMIT`] 之间
+    pub fn new(offset: u32, limit: u32) -> Self {
+        Self {
+            offset,
+            limit: limit.clamp(1, Self::MAX_LIMIT),
+        }
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self::new(0, 50)
+    }
+}
+
+/// 分页查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// 当前页记录
+    pub items: Vec<T>,
+
+    /// 满足条件的记录总数
+    pub total: u64,
+
+    /// 当前页偏移量
+    pub offset: u32,
+
+    /// 每页最大记录数
+    pub limit: u32,
+}
+
+impl<T> Page<T> {
+    /// 是否还有下一页
+    pub fn has_more(&self) -> bool {
+        (self.offset as u64 + self.items.len() as u64) < self.total
+    }
+}
@@ -1,12 +1,15 @@
 //! 数据访问仓库
 //!
-//! 该模块提供了数据访问仓库实现，用于操作数据库中的记录。
+//! 该模块定义了与存储后端无关的数据访问仓库 trait，具体实现见
+//! [`SqliteRepository`](super::SqliteRepository) 和 PostgreSQL 后端。
 
-use crate::integrations::database::models::{AgentRecord, ConversationRecord, MessageRecord};
-use crate::integrations::database::connection::DatabaseManager;
-use sqlx::types::Uuid;
-use chrono::Utc;
+use crate::integrations::database::models::{
+    AgentRecord, ApiKeyRecord, ConversationMemoryRecord, ConversationRecord, FeedbackRecord, MemoryRecord, MessageRecord,
+    Page, Pagination, RevokedTokenRecord, UserAccountRecord, WorkflowRunRecord,
+};
+use chrono::{DateTime, Utc};
 use thiserror::Error;
+use uuid::Uuid;
 
 /// 数据仓库错误类型
 #[derive(Debug, Error)]
@@ -14,7 +17,7 @@ pub enum RepositoryError {
     /// 数据库错误
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
-    
+
     /// 记录未找到错误
     #[error("Record not found")]
     NotFound,
@@ -23,136 +26,157 @@ pub enum RepositoryError {
 /// 数据仓库 trait
 #[async_trait::async_trait]
 pub trait DataRepository: Send + Sync {
-    /// 保存智能体记录
+    /// 保存智能体记录（存在时更新）
     async fn save_agent(&self, agent: &AgentRecord) -> Result<(), RepositoryError>;
-    
+
     /// 根据ID获取智能体记录
     async fn get_agent_by_id(&self, id: &Uuid) -> Result<Option<AgentRecord>, RepositoryError>;
-    
-    /// 保存对话记录
+
+    /// 按创建时间分页列出智能体记录
+    async fn list_agents(&self, page: Pagination) -> Result<Page<AgentRecord>, RepositoryError>;
+
+    /// 保存对话记录（存在时更新）
     async fn save_conversation(&self, conversation: &ConversationRecord) -> Result<(), RepositoryError>;
-    
+
     /// 根据ID获取对话记录
     async fn get_conversation_by_id(&self, id: &Uuid) -> Result<Option<ConversationRecord>, RepositoryError>;
-    
+
+    /// 按更新时间倒序分页列出对话记录
+    async fn list_conversations(&self, page: Pagination) -> Result<Page<ConversationRecord>, RepositoryError>;
+
+    /// 按标题或参与者搜索对话记录（不区分大小写的子串匹配）
+    async fn search_conversations(
+        &self,
+        query: &str,
+        page: Pagination,
+    ) -> Result<Page<ConversationRecord>, RepositoryError>;
+
     /// 保存消息记录
     async fn save_message(&self, message: &MessageRecord) -> Result<(), RepositoryError>;
-    
+
     /// 根据对话ID获取消息记录列表
     async fn get_messages_by_conversation_id(&self, conversation_id: &Uuid) -> Result<Vec<MessageRecord>, RepositoryError>;
-}
 
-/// PostgreSQL数据仓库实现
-pub struct PostgresRepository {
-    /// 数据库管理器
-    db_manager: DatabaseManager,
-}
+    /// 按时间顺序分页列出对话中的消息
+    async fn list_messages(
+        &self,
+        conversation_id: &Uuid,
+        page: Pagination,
+    ) -> Result<Page<MessageRecord>, RepositoryError>;
 
-impl PostgresRepository {
-    /// 创建新的PostgreSQL数据仓库
-    ///
-    /// # 参数
-    /// * `db_manager` - 数据库管理器实例
+    /// 按内容搜索消息，可限定在单个对话内
     ///
-    /// # 返回值
-    /// 返回新的PostgreSQL数据仓库实例
-    pub fn new(db_manager: DatabaseManager) -> Self {
-        Self { db_manager }
-    }
+    /// 只匹配文本消息的 `text` 和错误消息的 `message`，不匹配序列化后的 JSON 键名和结构
+    async fn search_messages(
+        &self,
+        query: &str,
+        conversation_id: Option<&Uuid>,
+        page: Pagination,
+    ) -> Result<Page<MessageRecord>, RepositoryError>;
+
+    /// 保存工作流运行记录（存在时更新）
+    async fn save_workflow_run(&self, run: &WorkflowRunRecord) -> Result<(), RepositoryError>;
+
+    /// 根据ID获取工作流运行记录
+    async fn get_workflow_run(&self, id: &Uuid) -> Result<Option<WorkflowRunRecord>, RepositoryError>;
+
+    /// 按创建时间倒序分页列出工作流运行记录，可限定在单个工作流内
+    async fn list_workflow_runs(
+        &self,
+        workflow_id: Option<&str>,
+        page: Pagination,
+    ) -> Result<Page<WorkflowRunRecord>, RepositoryError>;
+
+    /// 按创建时间顺序列出处于给定状态的工作流运行记录
+    async fn list_workflow_runs_by_status(
+        &self,
+        statuses: &[&str],
+    ) -> Result<Vec<WorkflowRunRecord>, RepositoryError>;
+
+    /// 保存决策反馈记录
+    async fn save_feedback(&self, feedback: &FeedbackRecord) -> Result<(), RepositoryError>;
+
+    /// 按创建时间顺序列出全部决策反馈记录
+    async fn list_feedback(&self) -> Result<Vec<FeedbackRecord>, RepositoryError>;
+
+    /// 保存长期记忆记录（存在时更新）
+    async fn save_memory(&self, memory: &MemoryRecord) -> Result<(), RepositoryError>;
+
+    /// 按创建时间顺序列出长期记忆记录，可限定用户和智能体
+    async fn list_memories(
+        &self,
+        user_id: Option<&str>,
+        agent_id: Option<&str>,
+    ) -> Result<Vec<MemoryRecord>, RepositoryError>;
+
+    /// 删除长期记忆记录，返回记录是否存在
+    async fn delete_memory(&self, id: &Uuid) -> Result<bool, RepositoryError>;
+
+    /// 删除用户在所有智能体中的长期记忆，返回删除的条数
+    async fn delete_user_memories(&self, user_id: &str) -> Result<u64, RepositoryError>;
+
+    /// 保存对话记忆记录（存在时更新）
+    async fn save_conversation_memory(
+        &self,
+        memory: &ConversationMemoryRecord,
+    ) -> Result<(), RepositoryError>;
+
+    /// 按创建时间顺序列出全部对话记忆记录
+    async fn list_conversation_memories(&self) -> Result<Vec<ConversationMemoryRecord>, RepositoryError>;
+
+    /// 删除对话记忆记录，返回记录是否存在
+    async fn delete_conversation_memory(&self, id: &Uuid) -> Result<bool, RepositoryError>;
+
+    /// 删除用户在所有会话中的对话记忆，返回删除的条数
+    async fn delete_user_conversation_memories(&self, user_id: &str) -> Result<u64, RepositoryError>;
+
+    /// 保存用户账户记录（存在时更新）
+    async fn save_user_account(&self, account: &UserAccountRecord) -> Result<(), RepositoryError>;
+
+    /// 按创建时间顺序列出全部用户账户记录
+    async fn list_user_accounts(&self) -> Result<Vec<UserAccountRecord>, RepositoryError>;
+
+    /// 删除用户账户记录，返回记录是否存在
+    async fn delete_user_account(&self, id: &str) -> Result<bool, RepositoryError>;
+
+    /// 保存被吊销的令牌记录，返回是否新增（令牌此前未被吊销）
+    async fn save_revoked_token(&self, token: &RevokedTokenRecord) -> Result<bool, RepositoryError>;
+
+    /// 列出全部被吊销的令牌记录
+    async fn list_revoked_tokens(&self) -> Result<Vec<RevokedTokenRecord>, RepositoryError>;
+
+    /// 删除原本在给定时间之前过期的吊销记录，返回删除的条数
+    async fn delete_expired_revoked_tokens(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError>;
+
+    /// 保存 API 密钥记录（存在时更新）
+    async fn save_api_key(&self, key: &ApiKeyRecord) -> Result<(), RepositoryError>;
+
+    /// 按创建时间顺序列出全部 API 密钥记录
+    async fn list_api_keys(&self) -> Result<Vec<ApiKeyRecord>, RepositoryError>;
+
+    /// 删除 API 密钥记录，返回记录是否存在
+    async fn delete_api_key(&self, id: &Uuid) -> Result<bool, RepositoryError>;
 }
 
-#[async_trait::async_trait]
-impl DataRepository for PostgresRepository {
-    /// 保存智能体记录
-    async fn save_agent(&self, agent: &AgentRecord) -> Result<(), RepositoryError> {
-        sqlx::query!(
-            "INSERT INTO agents (id, name, agent_type, config, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6)
-             ON CONFLICT (id) DO UPDATE SET name = $2, agent_type = $3, config = $4, updated_at = $6",
-            agent.id,
-            agent.name,
-            agent.agent_type,
-            agent.config,
-            agent.created_at,
-            agent.updated_at
-        )
-        .execute(self.db_manager.get_pool())
-        .await?;
-
-        Ok(())
-    }
-    
-    /// 根据ID获取智能体记录
-    async fn get_agent_by_id(&self, id: &Uuid) -> Result<Option<AgentRecord>, RepositoryError> {
-        let row = sqlx::query_as!(
-            AgentRecord,
-            "SELECT id, name, agent_type, config, created_at, updated_at FROM agents WHERE id = $1",
-            id
-        )
-        .fetch_optional(self.db_manager.get_pool())
-        .await?;
-
-        Ok(row)
-    }
-    
-    /// 保存对话记录
-    async fn save_conversation(&self, conversation: &ConversationRecord) -> Result<(), RepositoryError> {
-        sqlx::query!(
-            "INSERT INTO conversations (id, title, participants, created_at, updated_at) VALUES ($1, $2, $3, $4, $5)
-             ON CONFLICT (id) DO UPDATE SET title = $2, participants = $3, updated_at = $5",
-            conversation.id,
-            conversation.title,
-            &conversation.participants,
-            conversation.created_at,
-            conversation.updated_at
-        )
-        .execute(self.db_manager.get_pool())
-        .await?;
-
-        Ok(())
-    }
-    
-    /// 根据ID获取对话记录
-    async fn get_conversation_by_id(&self, id: &Uuid) -> Result<Option<ConversationRecord>, RepositoryError> {
-        let row = sqlx::query_as!(
-            ConversationRecord,
-            "SELECT id, title, participants, created_at, updated_at FROM conversations WHERE id = $1",
-            id
-        )
-        .fetch_optional(self.db_manager.get_pool())
-        .await?;
-
-        Ok(row)
-    }
-    
-    /// 保存消息记录
-    async fn save_message(&self, message: &MessageRecord) -> Result<(), RepositoryError> {
-        sqlx::query!(
-            "INSERT INTO messages (id, conversation_id, sender, recipient, content, message_type, timestamp, metadata) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-            message.id,
-            message.conversation_id,
-            message.sender,
-            message.recipient,
-            message.content,
-            message.message_type,
-            message.timestamp,
-            message.metadata
-        )
-        .execute(self.db_manager.get_pool())
-        .await?;
-
-        Ok(())
-    }
-    
-    /// 根据对话ID获取消息记录列表
-    async fn get_messages_by_conversation_id(&self, conversation_id: &Uuid) -> Result<Vec<MessageRecord>, RepositoryError> {
-        let rows = sqlx::query_as!(
-            MessageRecord,
-            "SELECT id, conversation_id, sender, recipient, content, message_type, timestamp, metadata FROM messages WHERE conversation_id = $1 ORDER BY timestamp ASC",
-            conversation_id
-        )
-        .fetch_all(self.db_manager.get_pool())
-        .await?;
-
-        Ok(rows)
+/// 将搜索词转换为 `LIKE` 模式，转义其中的通配符
+pub(crate) fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("天气"), "%天气%");
+        assert_eq!(like_pattern("100%_done"), "%100\\%\\_done%");
     }
-}
\ No newline at end of file
+}
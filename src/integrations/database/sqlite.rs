@@ -0,0 +1,732 @@
+//! SQLite数据仓库实现
+//!
+//! 内嵌数据库后端，无需外部服务。UUID 以文本形式存储，JSON 字段（包括
+//! 对话参与者列表）以 JSON 文本存储。
+
+use crate::integrations::database::connection::SqliteManager;
+use crate::integrations::database::models::{
+    AgentRecord, ApiKeyRecord, ConversationMemoryRecord, ConversationRecord, FeedbackRecord, MemoryRecord, MessageRecord,
+    Page, Pagination, RevokedTokenRecord, UserAccountRecord, WorkflowRunRecord,
+};
+use crate::integrations::database::repository::{like_pattern, DataRepository, RepositoryError};
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::types::Json;
+use sqlx::Row;
+use uuid::fmt::Hyphenated;
+use uuid::Uuid;
+
+/// SQLite数据仓库实现
+#[derive(Debug, Clone)]
+pub struct SqliteRepository {
+    /// 数据库管理器
+    db_manager: SqliteManager,
+}
+
+impl SqliteRepository {
+    /// 创建新的SQLite数据仓库
+    ///
+    /// # 参数
+    /// * `db_manager` - 数据库管理器实例，调用方负责事先执行迁移
+    pub fn new(db_manager: SqliteManager) -> Self {
+        Self { db_manager }
+    }
+
+    /// 连接数据库并执行迁移
+    ///
+    /// # 参数
+    /// * `database_url` - 数据库连接URL，`sqlite::memory:` 表示内存数据库
+    pub async fn connect(database_url: &str) -> Result<Self, crate::integrations::database::DatabaseError> {
+        let db_manager = if database_url == "sqlite::memory:" {
+            SqliteManager::in_memory().await?
+        } else {
+            SqliteManager::new(database_url).await?
+        };
+        db_manager.run_migrations().await?;
+        Ok(Self::new(db_manager))
+    }
+
+    fn agent_from_row(row: &SqliteRow) -> Result<AgentRecord, RepositoryError> {
+        Ok(AgentRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            name: row.try_get("name")?,
+            agent_type: row.try_get("agent_type")?,
+            config: row.try_get::<Json<serde_json::Value>, _>("config")?.0,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    fn conversation_from_row(row: &SqliteRow) -> Result<ConversationRecord, RepositoryError> {
+        Ok(ConversationRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            title: row.try_get("title")?,
+            participants: row.try_get::<Json<Vec<String>>, _>("participants")?.0,
+            owner: row.try_get("owner")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    fn message_from_row(row: &SqliteRow) -> Result<MessageRecord, RepositoryError> {
+        Ok(MessageRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            conversation_id: row.try_get::<Hyphenated, _>("conversation_id")?.into_uuid(),
+            sender: row.try_get("sender")?,
+            recipient: row.try_get("recipient")?,
+            content: row.try_get::<Json<serde_json::Value>, _>("content")?.0,
+            message_type: row.try_get("message_type")?,
+            timestamp: row.try_get("timestamp")?,
+            metadata: row
+                .try_get::<Option<Json<serde_json::Value>>, _>("metadata")?
+                .map(|json| json.0),
+        })
+    }
+
+    fn workflow_run_from_row(row: &SqliteRow) -> Result<WorkflowRunRecord, RepositoryError> {
+        Ok(WorkflowRunRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            workflow_id: row.try_get("workflow_id")?,
+            status: row.try_get("status")?,
+            data: row.try_get::<Json<serde_json::Value>, _>("data")?.0,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    fn feedback_from_row(row: &SqliteRow) -> Result<FeedbackRecord, RepositoryError> {
+        Ok(FeedbackRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            decision_id: row.try_get("decision_id")?,
+            rule_id: row.try_get("rule_id")?,
+            action: row.try_get("action")?,
+            context: row.try_get("context")?,
+            outcome: row.try_get("outcome")?,
+            feedback: row.try_get("feedback")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+
+    fn conversation_memory_from_row(row: &SqliteRow) -> Result<ConversationMemoryRecord, RepositoryError> {
+        Ok(ConversationMemoryRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            user_id: row.try_get("user_id")?,
+            session_id: row.try_get("session_id")?,
+            content: row.try_get("content")?,
+            sources: row.try_get::<Json<Vec<Uuid>>, _>("sources")?.0,
+            importance: row.try_get("importance")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+
+    fn memory_from_row(row: &SqliteRow) -> Result<MemoryRecord, RepositoryError> {
+        Ok(MemoryRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            user_id: row.try_get("user_id")?,
+            agent_id: row.try_get("agent_id")?,
+            content: row.try_get("content")?,
+            tags: row.try_get::<Json<Vec<String>>, _>("tags")?.0,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+
+    fn user_account_from_row(row: &SqliteRow) -> Result<UserAccountRecord, RepositoryError> {
+        Ok(UserAccountRecord {
+            id: row.try_get("id")?,
+            username: row.try_get("username")?,
+            role: row.try_get("role")?,
+            permissions: row.try_get::<Json<Vec<String>>, _>("permissions")?.0,
+            password_hash: row.try_get("password_hash")?,
+            failed_attempts: row.try_get("failed_attempts")?,
+            locked_until: row.try_get("locked_until")?,
+            created_at: row.try_get("created_at")?,
+            last_login: row.try_get("last_login")?,
+            token_version: row.try_get("token_version")?,
+        })
+    }
+
+    fn revoked_token_from_row(row: &SqliteRow) -> Result<RevokedTokenRecord, RepositoryError> {
+        Ok(RevokedTokenRecord {
+            jti: row.try_get("jti")?,
+            user_id: row.try_get("user_id")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+
+    fn api_key_from_row(row: &SqliteRow) -> Result<ApiKeyRecord, RepositoryError> {
+        Ok(ApiKeyRecord {
+            id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+            name: row.try_get("name")?,
+            prefix: row.try_get("prefix")?,
+            key_hash: row.try_get("key_hash")?,
+            user_id: row.try_get("user_id")?,
+            permissions: row.try_get::<Json<Vec<String>>, _>("permissions")?.0,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+            last_used_at: row.try_get("last_used_at")?,
+        })
+    }
+
+    fn page<T>(items: Vec<T>, total: i64, page: Pagination) -> Page<T> {
+        Page {
+            items,
+            total: total.max(0) as u64,
+            offset: page.offset,
+            limit: page.limit,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DataRepository for SqliteRepository {
+    async fn save_agent(&self, agent: &AgentRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO agents (id, name, agent_type, config, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (id) DO UPDATE SET name = ?2, agent_type = ?3, config = ?4, updated_at = ?6",
+        )
+        .bind(agent.id.hyphenated())
+        .bind(&agent.name)
+        .bind(&agent.agent_type)
+        .bind(Json(&agent.config))
+        .bind(agent.created_at)
+        .bind(agent.updated_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_agent_by_id(&self, id: &Uuid) -> Result<Option<AgentRecord>, RepositoryError> {
+        let row = sqlx::query(
+            "SELECT id, name, agent_type, config, created_at, updated_at FROM agents WHERE id = ?1",
+        )
+        .bind(id.hyphenated())
+        .fetch_optional(self.db_manager.get_pool())
+        .await?;
+
+        row.as_ref().map(Self::agent_from_row).transpose()
+    }
+
+    async fn list_agents(&self, page: Pagination) -> Result<Page<AgentRecord>, RepositoryError> {
+        let pool = self.db_manager.get_pool();
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM agents")
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, name, agent_type, config, created_at, updated_at FROM agents
+             ORDER BY created_at ASC, id ASC LIMIT ?1 OFFSET ?2",
+        )
+        .bind(page.limit)
+        .bind(page.offset)
+        .fetch_all(pool)
+        .await?;
+
+        let items = rows.iter().map(Self::agent_from_row).collect::<Result<_, _>>()?;
+        Ok(Self::page(items, total, page))
+    }
+
+    async fn save_conversation(&self, conversation: &ConversationRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO conversations (id, title, participants, created_at, updated_at, owner)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (id) DO UPDATE SET title = ?2, participants = ?3, updated_at = ?5,
+                 owner = COALESCE(conversations.owner, ?6)",
+        )
+        .bind(conversation.id.hyphenated())
+        .bind(&conversation.title)
+        .bind(Json(&conversation.participants))
+        .bind(conversation.created_at)
+        .bind(conversation.updated_at)
+        .bind(&conversation.owner)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_conversation_by_id(&self, id: &Uuid) -> Result<Option<ConversationRecord>, RepositoryError> {
+        let row = sqlx::query(
+            "SELECT id, title, participants, owner, created_at, updated_at FROM conversations WHERE id = ?1",
+        )
+        .bind(id.hyphenated())
+        .fetch_optional(self.db_manager.get_pool())
+        .await?;
+
+        row.as_ref().map(Self::conversation_from_row).transpose()
+    }
+
+    async fn list_conversations(&self, page: Pagination) -> Result<Page<ConversationRecord>, RepositoryError> {
+        let pool = self.db_manager.get_pool();
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM conversations")
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, title, participants, owner, created_at, updated_at FROM conversations
+             ORDER BY updated_at DESC, id ASC LIMIT ?1 OFFSET ?2",
+        )
+        .bind(page.limit)
+        .bind(page.offset)
+        .fetch_all(pool)
+        .await?;
+
+        let items = rows
+            .iter()
+            .map(Self::conversation_from_row)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::page(items, total, page))
+    }
+
+    async fn search_conversations(
+        &self,
+        query: &str,
+        page: Pagination,
+    ) -> Result<Page<ConversationRecord>, RepositoryError> {
+        let pool = self.db_manager.get_pool();
+        let pattern = like_pattern(query);
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM conversations
+             WHERE title LIKE ?1 ESCAPE '\\' OR participants LIKE ?1 ESCAPE '\\'",
+        )
+        .bind(&pattern)
+        .fetch_one(pool)
+        .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, title, participants, owner, created_at, updated_at FROM conversations
+             WHERE title LIKE ?1 ESCAPE '\\' OR participants LIKE ?1 ESCAPE '\\'
+             ORDER BY updated_at DESC, id ASC LIMIT ?2 OFFSET ?3",
+        )
+        .bind(&pattern)
+        .bind(page.limit)
+        .bind(page.offset)
+        .fetch_all(pool)
+        .await?;
+
+        let items = rows
+            .iter()
+            .map(Self::conversation_from_row)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::page(items, total, page))
+    }
+
+    async fn save_message(&self, message: &MessageRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO messages (id, conversation_id, sender, recipient, content, message_type, timestamp, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .bind(message.id.hyphenated())
+        .bind(message.conversation_id.hyphenated())
+        .bind(&message.sender)
+        .bind(&message.recipient)
+        .bind(Json(&message.content))
+        .bind(&message.message_type)
+        .bind(message.timestamp)
+        .bind(message.metadata.as_ref().map(Json))
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_messages_by_conversation_id(&self, conversation_id: &Uuid) -> Result<Vec<MessageRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, conversation_id, sender, recipient, content, message_type, timestamp, metadata
+             FROM messages WHERE conversation_id = ?1 ORDER BY timestamp ASC, rowid ASC",
+        )
+        .bind(conversation_id.hyphenated())
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::message_from_row).collect()
+    }
+
+    async fn list_messages(
+        &self,
+        conversation_id: &Uuid,
+        page: Pagination,
+    ) -> Result<Page<MessageRecord>, RepositoryError> {
+        let pool = self.db_manager.get_pool();
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE conversation_id = ?1")
+            .bind(conversation_id.hyphenated())
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, conversation_id, sender, recipient, content, message_type, timestamp, metadata
+             FROM messages WHERE conversation_id = ?1 ORDER BY timestamp ASC, rowid ASC LIMIT ?2 OFFSET ?3",
+        )
+        .bind(conversation_id.hyphenated())
+        .bind(page.limit)
+        .bind(page.offset)
+        .fetch_all(pool)
+        .await?;
+
+        let items = rows.iter().map(Self::message_from_row).collect::<Result<_, _>>()?;
+        Ok(Self::page(items, total, page))
+    }
+
+    async fn search_messages(
+        &self,
+        query: &str,
+        conversation_id: Option<&Uuid>,
+        page: Pagination,
+    ) -> Result<Page<MessageRecord>, RepositoryError> {
+        let pool = self.db_manager.get_pool();
+        let pattern = like_pattern(query);
+        let conversation_id = conversation_id.map(|id| id.hyphenated());
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM messages
+             WHERE COALESCE(json_extract(content, '$.text'), json_extract(content, '$.message')) LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR conversation_id = ?2)",
+        )
+        .bind(&pattern)
+        .bind(conversation_id)
+        .fetch_one(pool)
+        .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, conversation_id, sender, recipient, content, message_type, timestamp, metadata
+             FROM messages WHERE COALESCE(json_extract(content, '$.text'), json_extract(content, '$.message')) LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR conversation_id = ?2)
+             ORDER BY timestamp DESC, rowid DESC LIMIT ?3 OFFSET ?4",
+        )
+        .bind(&pattern)
+        .bind(conversation_id)
+        .bind(page.limit)
+        .bind(page.offset)
+        .fetch_all(pool)
+        .await?;
+
+        let items = rows.iter().map(Self::message_from_row).collect::<Result<_, _>>()?;
+        Ok(Self::page(items, total, page))
+    }
+
+    async fn save_workflow_run(&self, run: &WorkflowRunRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO workflow_runs (id, workflow_id, status, data, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (id) DO UPDATE SET status = ?3, data = ?4, updated_at = ?6",
+        )
+        .bind(run.id.hyphenated())
+        .bind(&run.workflow_id)
+        .bind(&run.status)
+        .bind(Json(&run.data))
+        .bind(run.created_at)
+        .bind(run.updated_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_workflow_run(&self, id: &Uuid) -> Result<Option<WorkflowRunRecord>, RepositoryError> {
+        let row = sqlx::query(
+            "SELECT id, workflow_id, status, data, created_at, updated_at FROM workflow_runs WHERE id = ?1",
+        )
+        .bind(id.hyphenated())
+        .fetch_optional(self.db_manager.get_pool())
+        .await?;
+
+        row.as_ref().map(Self::workflow_run_from_row).transpose()
+    }
+
+    async fn list_workflow_runs(
+        &self,
+        workflow_id: Option<&str>,
+        page: Pagination,
+    ) -> Result<Page<WorkflowRunRecord>, RepositoryError> {
+        let pool = self.db_manager.get_pool();
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM workflow_runs WHERE ?1 IS NULL OR workflow_id = ?1")
+            .bind(workflow_id)
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query(
+            "SELECT id, workflow_id, status, data, created_at, updated_at FROM workflow_runs
+             WHERE ?1 IS NULL OR workflow_id = ?1
+             ORDER BY created_at DESC, rowid DESC LIMIT ?2 OFFSET ?3",
+        )
+        .bind(workflow_id)
+        .bind(page.limit)
+        .bind(page.offset)
+        .fetch_all(pool)
+        .await?;
+
+        let items = rows.iter().map(Self::workflow_run_from_row).collect::<Result<_, _>>()?;
+        Ok(Self::page(items, total, page))
+    }
+
+    async fn list_workflow_runs_by_status(
+        &self,
+        statuses: &[&str],
+    ) -> Result<Vec<WorkflowRunRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, workflow_id, status, data, created_at, updated_at FROM workflow_runs
+             WHERE status IN (SELECT value FROM json_each(?1))
+             ORDER BY created_at ASC, rowid ASC",
+        )
+        .bind(Json(statuses))
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::workflow_run_from_row).collect()
+    }
+
+    async fn save_feedback(&self, feedback: &FeedbackRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO decision_feedback (id, decision_id, rule_id, action, context, outcome, feedback, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .bind(feedback.id.hyphenated())
+        .bind(&feedback.decision_id)
+        .bind(&feedback.rule_id)
+        .bind(&feedback.action)
+        .bind(&feedback.context)
+        .bind(&feedback.outcome)
+        .bind(feedback.feedback)
+        .bind(feedback.created_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_feedback(&self) -> Result<Vec<FeedbackRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, decision_id, rule_id, action, context, outcome, feedback, created_at
+             FROM decision_feedback ORDER BY created_at ASC, rowid ASC",
+        )
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::feedback_from_row).collect()
+    }
+
+    async fn save_memory(&self, memory: &MemoryRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO memories (id, user_id, agent_id, content, tags, created_at, updated_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (id) DO UPDATE SET content = ?4, tags = ?5, updated_at = ?7, expires_at = ?8",
+        )
+        .bind(memory.id.hyphenated())
+        .bind(&memory.user_id)
+        .bind(&memory.agent_id)
+        .bind(&memory.content)
+        .bind(Json(&memory.tags))
+        .bind(memory.created_at)
+        .bind(memory.updated_at)
+        .bind(memory.expires_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_memories(
+        &self,
+        user_id: Option<&str>,
+        agent_id: Option<&str>,
+    ) -> Result<Vec<MemoryRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, user_id, agent_id, content, tags, created_at, updated_at, expires_at FROM memories
+             WHERE (?1 IS NULL OR user_id = ?1) AND (?2 IS NULL OR agent_id = ?2)
+             ORDER BY created_at ASC, rowid ASC",
+        )
+        .bind(user_id)
+        .bind(agent_id)
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::memory_from_row).collect()
+    }
+
+    async fn delete_memory(&self, id: &Uuid) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM memories WHERE id = ?1")
+            .bind(id.hyphenated())
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user_memories(&self, user_id: &str) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM memories WHERE user_id = ?1")
+            .bind(user_id)
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn save_conversation_memory(
+        &self,
+        memory: &ConversationMemoryRecord,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO conversation_memories (id, user_id, session_id, content, sources, importance, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (id) DO UPDATE SET content = ?4, sources = ?5, importance = ?6",
+        )
+        .bind(memory.id.hyphenated())
+        .bind(&memory.user_id)
+        .bind(&memory.session_id)
+        .bind(&memory.content)
+        .bind(Json(&memory.sources))
+        .bind(memory.importance)
+        .bind(memory.created_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_conversation_memories(&self) -> Result<Vec<ConversationMemoryRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, user_id, session_id, content, sources, importance, created_at FROM conversation_memories
+             ORDER BY created_at ASC, rowid ASC",
+        )
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::conversation_memory_from_row).collect()
+    }
+
+    async fn delete_conversation_memory(&self, id: &Uuid) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM conversation_memories WHERE id = ?1")
+            .bind(id.hyphenated())
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user_conversation_memories(&self, user_id: &str) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM conversation_memories WHERE user_id = ?1")
+            .bind(user_id)
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn save_user_account(&self, account: &UserAccountRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO user_accounts (id, username, role, permissions, password_hash, failed_attempts, locked_until, created_at, last_login, token_version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT (id) DO UPDATE SET username = ?2, role = ?3, permissions = ?4, password_hash = ?5,
+                 failed_attempts = ?6, locked_until = ?7, last_login = ?9, token_version = ?10",
+        )
+        .bind(&account.id)
+        .bind(&account.username)
+        .bind(&account.role)
+        .bind(Json(&account.permissions))
+        .bind(&account.password_hash)
+        .bind(account.failed_attempts)
+        .bind(account.locked_until)
+        .bind(account.created_at)
+        .bind(account.last_login)
+        .bind(account.token_version)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_user_accounts(&self) -> Result<Vec<UserAccountRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, username, role, permissions, password_hash, failed_attempts, locked_until, created_at, last_login, token_version
+             FROM user_accounts ORDER BY created_at ASC, rowid ASC",
+        )
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::user_account_from_row).collect()
+    }
+
+    async fn delete_user_account(&self, id: &str) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM user_accounts WHERE id = ?1")
+            .bind(id)
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn save_revoked_token(&self, token: &RevokedTokenRecord) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
+            "INSERT INTO revoked_tokens (jti, user_id, expires_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (jti) DO NOTHING",
+        )
+        .bind(&token.jti)
+        .bind(&token.user_id)
+        .bind(token.expires_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_revoked_tokens(&self) -> Result<Vec<RevokedTokenRecord>, RepositoryError> {
+        let rows = sqlx::query("SELECT jti, user_id, expires_at FROM revoked_tokens ORDER BY expires_at ASC")
+            .fetch_all(self.db_manager.get_pool())
+            .await?;
+
+        rows.iter().map(Self::revoked_token_from_row).collect()
+    }
+
+    async fn delete_expired_revoked_tokens(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < ?1")
+            .bind(before)
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn save_api_key(&self, key: &ApiKeyRecord) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO api_keys (id, name, prefix, key_hash, user_id, permissions, created_at, expires_at, last_used_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT (id) DO UPDATE SET name = ?2, permissions = ?6, expires_at = ?8, last_used_at = ?9",
+        )
+        .bind(key.id.hyphenated())
+        .bind(&key.name)
+        .bind(&key.prefix)
+        .bind(&key.key_hash)
+        .bind(&key.user_id)
+        .bind(Json(&key.permissions))
+        .bind(key.created_at)
+        .bind(key.expires_at)
+        .bind(key.last_used_at)
+        .execute(self.db_manager.get_pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKeyRecord>, RepositoryError> {
+        let rows = sqlx::query(
+            "SELECT id, name, prefix, key_hash, user_id, permissions, created_at, expires_at, last_used_at
+             FROM api_keys ORDER BY created_at ASC, rowid ASC",
+        )
+        .fetch_all(self.db_manager.get_pool())
+        .await?;
+
+        rows.iter().map(Self::api_key_from_row).collect()
+    }
+
+    async fn delete_api_key(&self, id: &Uuid) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM api_keys WHERE id = ?1")
+            .bind(id.hyphenated())
+            .execute(self.db_manager.get_pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
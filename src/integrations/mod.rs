@@ -1,3 +1,4 @@
 //! 外部集成层 - 外部系统集成层
 
-pub mod adapters;
\ No newline at end of file
+pub mod adapters;
+pub mod database;
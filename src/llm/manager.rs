@@ -8,7 +8,7 @@ use crate::llm::providers::{LLMProvider, OpenAIProvider, ProviderConfig};
 
 #[derive(Clone)]
 pub struct LLMManager {
-    providers: Arc<RwLock<HashMap<String, Box<dyn LLMProvider + Send + Sync>>>>,
+    providers: Arc<RwLock<HashMap<String, Arc<dyn LLMProvider + Send + Sync>>>>,
     default_provider: String,
 }
 
@@ -16,7 +16,7 @@ impl std::fmt::Debug for LLMManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LLMManager")
             .field("default_provider", &self.default_provider)
-            .field("providers", &"HashMap<String, Box<dyn LLMProvider>>")
+            .field("providers", &"HashMap<String, Arc<dyn LLMProvider>>")
             .finish()
     }
 }
@@ -26,7 +26,7 @@ impl LLMManager {
         let mut providers = HashMap::new();
 
         if let Some(openai_config) = config.openai {
-            let provider: Box<dyn LLMProvider + Send + Sync> = Box::new(OpenAIProvider::new(
+            let provider: Arc<dyn LLMProvider + Send + Sync> = Arc::new(OpenAIProvider::new(
                 openai_config.api_key,
                 Some(openai_config.model),
                 openai_config.base_url,
@@ -35,7 +35,7 @@ impl LLMManager {
         }
 
         if let Some(claude_config) = config.claude {
-            let provider: Box<dyn LLMProvider + Send + Sync> = Box::new(ClaudeProvider::new(
+            let provider: Arc<dyn LLMProvider + Send + Sync> = Arc::new(ClaudeProvider::new(
                 claude_config.api_key,
                 Some(claude_config.model),
                 claude_config.base_url,
@@ -44,7 +44,7 @@ impl LLMManager {
         }
 
         if let Some(google_config) = config.google {
-            let provider: Box<dyn LLMProvider + Send + Sync> = Box::new(GoogleProvider::new(
+            let provider: Arc<dyn LLMProvider + Send + Sync> = Arc::new(GoogleProvider::new(
                 google_config.api_key,
                 Some(google_config.model),
                 google_config.base_url,
@@ -60,29 +60,12 @@ impl LLMManager {
 
     pub async fn add_provider(&self, name: String, provider: Box<dyn LLMProvider + Send + Sync>) {
         let mut providers = self.providers.write().await;
-        providers.insert(name, provider);
+        providers.insert(name, Arc::from(provider));
     }
 
-    pub async fn get_provider(&self, name: &str) -> Option<Box<dyn LLMProvider + Send + Sync>> {
+    pub async fn get_provider(&self, name: &str) -> Option<Arc<dyn LLMProvider + Send + Sync>> {
         let providers = self.providers.read().await;
-        providers.get(name).map(|p| match p.provider_name() {
-            "openai" => Box::new(OpenAIProvider::new(
-                "mock-key".to_string(),
-                Some("gpt-3.5-turbo".to_string()),
-                None,
-            )) as Box<dyn LLMProvider + Send + Sync>,
-            "claude" => Box::new(ClaudeProvider::new(
-                "mock-key".to_string(),
-                Some("claude-3-haiku-20240307".to_string()),
-                None,
-            )) as Box<dyn LLMProvider + Send + Sync>,
-            "google" => Box::new(GoogleProvider::new(
-                "mock-key".to_string(),
-                Some("gemini-pro".to_string()),
-                None,
-            )) as Box<dyn LLMProvider + Send + Sync>,
-            _ => panic!("Unknown provider"),
-        })
+        providers.get(name).cloned()
     }
 
     pub fn get_default_provider(&self) -> &str {
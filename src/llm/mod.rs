@@ -29,7 +29,36 @@ impl Default for LLMConfig {
     }
 }
 
-#[derive(Debug)]
+/// Per-session overrides applied on top of the service-wide [`LLMConfig`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LLMOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl LLMOverrides {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn apply(&self, base: &LLMConfig) -> LLMConfig {
+        LLMConfig {
+            provider: self.provider.clone().unwrap_or_else(|| base.provider.clone()),
+            model: self.model.clone().unwrap_or_else(|| base.model.clone()),
+            temperature: self.temperature.unwrap_or(base.temperature),
+            max_tokens: self.max_tokens.unwrap_or(base.max_tokens),
+            use_mock: base.use_mock,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct LLMService {
     pub manager: LLMManager,
     pub config: LLMConfig,
@@ -95,30 +124,79 @@ impl LLMService {
         input: &str,
         context: &[Message],
     ) -> Result<Message, String> {
-        if self.config.use_mock {
-            self.mock_process(input, context).await
+        self.process_message_with_overrides(input, context, &LLMOverrides::default())
+            .await
+    }
+
+    /// Processes `input` with `context` as prior conversation history, using
+    /// the service config with `overrides` applied.
+    pub async fn process_message_with_overrides(
+        &self,
+        input: &str,
+        context: &[Message],
+        overrides: &LLMOverrides,
+    ) -> Result<Message, String> {
+        let config = overrides.apply(&self.config);
+        if config.use_mock {
+            self.mock_process(input, context, &config).await
         } else {
-            self.real_process(input, context).await
+            self.real_process(input, context, &config).await
         }
     }
 
-    async fn real_process(&self, input: &str, _context: &[Message]) -> Result<Message, String> {
+    /// Converts text messages of a conversation into provider messages.
+    /// Messages tagged with `{"role": "assistant"}` (or `"system"`) metadata
+    /// keep that role; everything else is treated as user input.
+    fn history_messages(context: &[Message]) -> Vec<crate::llm::providers::Message> {
+        use crate::llm::providers::MessageRole;
+
+        context
+            .iter()
+            .filter_map(|message| {
+                let MessageContent::Text { text } = &message.content else {
+                    return None;
+                };
+                let role = match message
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get("role"))
+                    .and_then(|r| r.as_str())
+                {
+                    Some("assistant") => MessageRole::Assistant,
+                    Some("system") => MessageRole::System,
+                    _ => MessageRole::User,
+                };
+                Some(crate::llm::providers::Message {
+                    role,
+                    content: text.clone(),
+                })
+            })
+            .collect()
+    }
+
+    async fn real_process(
+        &self,
+        input: &str,
+        context: &[Message],
+        config: &LLMConfig,
+    ) -> Result<Message, String> {
         let provider = self
             .manager
-            .get_provider(&self.config.provider)
+            .get_provider(&config.provider)
             .await
-            .ok_or_else(|| format!("Provider {} not found", self.config.provider))?;
+            .ok_or_else(|| format!("Provider {} not found", config.provider))?;
 
-        let messages = vec![crate::llm::providers::Message {
+        let mut messages = Self::history_messages(context);
+        messages.push(crate::llm::providers::Message {
             role: crate::llm::providers::MessageRole::User,
             content: input.to_string(),
-        }];
+        });
 
         let request = LLMRequest {
             messages,
-            model: self.config.model.clone(),
-            temperature: Some(self.config.temperature),
-            max_tokens: Some(self.config.max_tokens),
+            model: config.model.clone(),
+            temperature: Some(config.temperature),
+            max_tokens: Some(config.max_tokens),
             stream: Some(false),
         };
 
@@ -130,7 +208,7 @@ impl LLMService {
                     text: response.content,
                 },
                 Some(json!({
-                    "provider": self.config.provider,
+                    "provider": config.provider,
                     "model": response.model,
                     "usage": response.usage
                 })),
@@ -139,12 +217,17 @@ impl LLMService {
         }
     }
 
-    async fn mock_process(&self, input: &str, _context: &[Message]) -> Result<Message, String> {
+    async fn mock_process(
+        &self,
+        input: &str,
+        context: &[Message],
+        config: &LLMConfig,
+    ) -> Result<Message, String> {
         // Enhanced mock processing with provider awareness
-        let response_text = match self.config.provider.as_str() {
-            "openai" => format!("[OpenAI {}] Processing: {}", self.config.model, input),
-            "claude" => format!("[Claude {}] Analyzing: {}", self.config.model, input),
-            "google" => format!("[Google {}] Responding: {}", self.config.model, input),
+        let response_text = match config.provider.as_str() {
+            "openai" => format!("[OpenAI {}] Processing: {}", config.model, input),
+            "claude" => format!("[Claude {}] Analyzing: {}", config.model, input),
+            "google" => format!("[Google {}] Responding: {}", config.model, input),
             _ => format!("[Mock] Received: {input}"),
         };
 
@@ -169,9 +252,10 @@ impl LLMService {
             "user".to_string(),
             content,
             Some(json!({
-                "provider": &self.config.provider,
-                "model": &self.config.model,
-                "mock": true
+                "provider": &config.provider,
+                "model": &config.model,
+                "mock": true,
+                "context_messages": context.len()
             })),
         ))
     }
@@ -1,18 +1,15 @@
-use axum::{
-    extract::State,
-    response::Json as JsonResponse,
-    routing::{get, post},
-    Json, Router,
-};
-use clap::Parser;
-use omni_agent::{Agent, AgentBuilder, AppConfig};
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use std::collections::HashMap;
+use clap::{Parser, Subcommand};
+use omni_agent::core::router::{IntelligentRouter, RouteCandidate};
+use omni_agent::integrations::database::SqliteRepository;
+use omni_agent::services::audit::AuditTrail;
+use omni_agent::services::security::{SecurityManager, UserRole};
+use omni_agent::ui::api::{create_app, AppState};
+use omni_agent::{AgentBuilder, AppConfig};
+use serde_json::json;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{error, info, warn, Level};
+use std::time::Duration;
+use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
 /// 智能体应用配置
@@ -34,140 +31,48 @@ struct Cli {
     /// 日志级别
     #[arg(long, default_value = "info")]
     log_level: String,
-}
-
-/// 请求消息结构
-#[derive(Debug, Serialize, Deserialize)]
-struct UserRequest {
-    message: String,
-    context: Option<HashMap<String, Value>>,
-}
 
-/// 响应消息结构
-#[derive(Debug, Serialize, Deserialize)]
-struct AgentResponse {
-    message: String,
-    source: String,
-    details: HashMap<String, Value>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-/// 智能体应用状态
-#[derive(Clone)]
-struct AppState {
-    agent: Arc<RwLock<omni_agent::Agent>>,
-    config: AppConfig,
+/// 管理命令，省略时启动服务器
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 在配置的数据库中创建管理员账户，用于初始化部署
+    CreateAdmin {
+        /// 管理员用户名；密码从环境变量 OMNI_ADMIN_PASSWORD 读取，未设置时在
+        /// 终端输入（不回显），不通过命令行参数传递以免出现在进程列表中
+        #[arg(long)]
+        username: String,
+    },
 }
 
-/// 智能路由器 - 决定使用哪个工具/智能体
-struct IntelligentRouter;
-
-impl IntelligentRouter {
-    /// 分析用户消息并决定最佳行动方案
-    async fn route_message(
-        &self,
-        message: &str,
-        agent: &omni_agent::Agent,
-    ) -> Result<(String, String, HashMap<String, Value>), Box<dyn std::error::Error>> {
-        info!("🔍 分析用户消息: {}", message);
-
-        // 1. 检查 MCP 工具是否适用
-        if let Some((tool_name, tool_result)) = self.try_mcp_tools(message, agent).await? {
-            info!("🛠️  使用 MCP 工具: {}", tool_name);
-            return Ok((
-                tool_result,
-                "mcp_tool".to_string(),
-                HashMap::from([("tool".to_string(), json!(tool_name))]),
-            ));
-        }
-
-        // 2. 检查 A2A 智能体是否适用
-        if let Some((agent_name, agent_result)) = self.try_a2a_agents(message, agent).await? {
-            info!("🤝 使用 A2A 智能体: {}", agent_name);
-            return Ok((
-                agent_result,
-                "a2a_agent".to_string(),
-                HashMap::from([("agent".to_string(), json!(agent_name))]),
-            ));
-        }
-
-        // 3. 使用本地 LLM
-        info!("🧠 使用本地 LLM 回答");
-        let llm_response = self.use_local_llm(message, agent).await?;
-        Ok((llm_response, "local_llm".to_string(), HashMap::new()))
-    }
-
-    /// 尝试使用 MCP 工具解决请求
-    async fn try_mcp_tools(
-        &self,
-        message: &str,
-        agent: &omni_agent::Agent,
-    ) -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
-        // 检查是否有启用的 MCP 客户端
-        if agent.mcp_clients.is_empty() {
-            return Ok(None);
-        }
-
-        // 简单的关键词匹配策略
-        let keywords = ["文件", "读取", "写入", "计算", "搜索", "查询"];
-
-        for keyword in keywords.iter() {
-            if message.contains(keyword) {
-                // 模拟 MCP 工具调用
-                return Ok(Some((
-                    "模拟MCP工具".to_string(),
-                    format!("[模拟] 使用 MCP 工具处理了包含 '{keyword}' 的请求: {message}"),
-                )));
-            }
-        }
-
-        Ok(None)
-    }
-
-    /// 尝试使用 A2A 智能体解决请求
-    async fn try_a2a_agents(
-        &self,
-        message: &str,
-        agent: &omni_agent::Agent,
-    ) -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
-        // 检查是否有启用的 A2A 客户端
-        if agent.a2a_clients.is_empty() {
-            return Ok(None);
-        }
-
-        // 简单的关键词匹配策略
-        let keywords = ["天气", "时间", "新闻", "翻译", "定义", "查询"];
-
-        for keyword in keywords.iter() {
-            if message.contains(keyword) {
-                // 模拟 A2A 智能体调用
-                return Ok(Some((
-                    "模拟A2A智能体".to_string(),
-                    format!("[模拟] 使用 A2A 智能体处理了包含 '{keyword}' 的请求: {message}"),
-                )));
-            }
-        }
+/// 创建管理员账户
+async fn create_admin(config: &AppConfig, username: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let password = match std::env::var("OMNI_ADMIN_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => rpassword::prompt_password("管理员密码: ")?,
+    };
 
-        Ok(None)
+    let repository = Arc::new(SqliteRepository::connect(&config.database.url).await?);
+    let security = SecurityManager::new(String::new()).with_config(config.security.clone());
+    security.use_store(repository).await?;
+    let admins = security
+        .list_users()
+        .await
+        .into_iter()
+        .filter(|user| user.role == UserRole::Admin)
+        .count();
+    if admins > 0 {
+        warn!("⚠️  数据库中已有 {} 个管理员账户", admins);
     }
 
-    /// 使用本地 LLM 回答
-    async fn use_local_llm(
-        &self,
-        message: &str,
-        agent: &omni_agent::Agent,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let response = agent
-            .llm
-            .write()
-            .await
-            .process_message(message, &[])
-            .await?;
-
-        match response.content {
-            omni_agent::protocol::message::MessageContent::Text { text } => Ok(text),
-            _ => Ok("无法处理消息格式".to_string()),
-        }
-    }
+    let user_id = security
+        .register_user(username, &password, UserRole::Admin)
+        .await?;
+    info!("✅ 已创建管理员 {} ({})", username, user_id);
+    Ok(())
 }
 
 /// 创建默认配置文件
@@ -242,53 +147,6 @@ async fn create_default_config(path: &PathBuf) -> Result<(), Box<dyn std::error:
     Ok(())
 }
 
-/// 处理聊天请求
-async fn chat_handler(
-    State(state): State<AppState>,
-    Json(request): Json<UserRequest>,
-) -> JsonResponse<AgentResponse> {
-    let router = IntelligentRouter;
-
-    let agent = state.agent.read().await;
-    match router.route_message(&request.message, &agent).await {
-        Ok((response, source, details)) => JsonResponse(AgentResponse {
-            message: response,
-            source,
-            details,
-        }),
-        Err(e) => {
-            error!("❌ 处理消息失败: {}", e);
-            JsonResponse(AgentResponse {
-                message: format!("处理失败: {e}"),
-                source: "error".to_string(),
-                details: HashMap::new(),
-            })
-        }
-    }
-}
-
-/// 健康检查端点
-async fn health_handler() -> JsonResponse<Value> {
-    JsonResponse(json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
-}
-
-/// 获取智能体信息
-async fn info_handler(State(state): State<AppState>) -> JsonResponse<Value> {
-    let agent = state.agent.read().await;
-    JsonResponse(json!({
-        "name": agent.config.name,
-        "description": agent.config.description,
-        "version": agent.config.version,
-        "mcp_clients": agent.mcp_clients.len(),
-        "a2a_clients": agent.a2a_clients.len(),
-        "llm_provider": state.config.llm.provider,
-        "llm_model": state.config.llm.model
-    }))
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 解析命令行参数
@@ -324,6 +182,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("🎭 启用模拟模式");
     }
 
+    if let Some(Command::CreateAdmin { username }) = cli.command {
+        return create_admin(&config, &username).await;
+    }
+
     if let Some(port) = cli.port {
         config.server.port = port;
         info!("🌐 使用端口: {}", port);
@@ -335,43 +197,156 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("   MCP 服务器: {}", config.mcp.servers.len());
     info!("   A2A 智能体: {}", config.a2a.servers.len());
     info!("   模拟模式: {}", config.llm.use_mock);
+    info!(
+        "   并发上限: {} (队列 {})",
+        config.concurrency.max_in_flight, config.concurrency.max_queue
+    );
 
     // 创建智能体（简化版本，跳过外部连接）
-    let agent_builder = AgentBuilder::new("omni-agent", "全能智能体助手").version("1.0.0");
+    let mut agent_builder = AgentBuilder::new("omni-agent", "全能智能体助手")
+        .version("1.0.0")
+        .session_config((&config.session).into())
+        .concurrency((&config.concurrency).into())
+        .request_timeout(Duration::from_secs(config.tasks.request_timeout_secs))
+        .max_tool_iterations(config.tasks.max_tool_iterations)
+        .context_compression(config.context.clone())
+        .approval_policy(config.approvals.clone());
+
+    // 启用持久化时打不开审计日志就不启动，避免审计记录悄悄只留在内存中
+    let audit = AuditTrail::open(config.audit.clone())
+        .await
+        .map_err(|e| format!("打开审计日志失败: {e}"))?;
+    if config.audit.persist {
+        info!("📜 审计日志目录: {}", config.audit.directory);
+    }
+    agent_builder = agent_builder.audit(Arc::new(audit));
+
+    let persist_memories = config.user_memory.enabled && config.user_memory.persist;
+    let persist_conversation_memory =
+        config.conversation_memory.enabled && config.conversation_memory.persist;
+    let mut repository = None;
+    if config.session.persist
+        || config.workflows.persist_runs
+        || config.router.persist_feedback
+        || persist_memories
+        || persist_conversation_memory
+        || config.security.persist
+    {
+        match SqliteRepository::connect(&config.database.url).await {
+            Ok(repo) => repository = Some(Arc::new(repo)),
+            Err(e) => warn!("⚠️  数据库连接失败: {}，会话、工作流运行、反馈、用户记忆和账户仅保存在内存中", e),
+        }
+    }
+    if let Some(repo) = repository.clone().filter(|_| config.session.persist) {
+        info!("💾 会话持久化已启用: {}", config.database.url);
+        agent_builder = agent_builder.session_store(repo);
+    }
+
+    match config.embeddings.build() {
+        Ok(embeddings) => {
+            info!("🧮 向量化提供者: {}", embeddings.name());
+            agent_builder = agent_builder.embeddings(embeddings);
+        }
+        Err(e) => warn!("⚠️  向量化配置无效: {}，使用本地哈希向量", e),
+    }
+    match config.vector_store.open().await {
+        Ok(store) => agent_builder = agent_builder.vector_store(store),
+        Err(e) => warn!("⚠️  打开向量存储失败: {}，向量仅保存在内存中", e),
+    }
+
+    if config.knowledge.enabled {
+        info!("📚 知识库来源: {:?}", config.knowledge.sources);
+        agent_builder = agent_builder.knowledge_base(config.knowledge.clone());
+    }
+    if config.user_memory.enabled {
+        agent_builder = agent_builder.user_memory(config.user_memory.clone());
+        if let Some(repo) = repository.clone().filter(|_| persist_memories) {
+            info!("💾 用户记忆持久化已启用: {}", config.database.url);
+            agent_builder = agent_builder.memory_store(repo);
+        }
+    }
+    if config.conversation_memory.enabled {
+        agent_builder = agent_builder.conversation_memory(config.conversation_memory.clone());
+        if let Some(repo) = repository.clone().filter(|_| persist_conversation_memory) {
+            info!("💾 对话记忆持久化已启用: {}", config.database.url);
+            agent_builder = agent_builder.conversation_memory_store(repo);
+        }
+    }
 
     // 我们不添加任何 MCP/A2A 客户端，因为它们是模拟的
     // 这将允许应用在没有外部服务的情况下启动
 
-    let agent = match agent_builder.build().await {
-        Ok(agent) => agent,
-        Err(e) => {
-            warn!("⚠️  智能体构建失败: {}，使用简化模式启动", e);
-            // 创建最小化的智能体
-            let config = omni_agent::agent::AgentConfig {
-                name: "omni-agent".to_string(),
-                description: "全能智能体助手".to_string(),
-                version: "1.0.0".to_string(),
-            };
-            Agent::new(config)
-        }
-    };
+    // 构建失败时退出，而不是退回到没有审计日志和持久化的简化智能体
+    let agent = agent_builder
+        .build()
+        .await
+        .map_err(|e| format!("智能体构建失败: {e}"))?;
 
     info!("✅ 智能体创建完成");
 
     let port = config.server.port;
 
-    // 创建应用状态
-    let state = AppState {
-        agent: Arc::new(RwLock::new(agent)),
-        config,
-    };
+    // 定期清理空闲会话
+    let eviction_interval = (agent.sessions.config().idle_timeout / 2).clamp(
+        Duration::from_secs(1),
+        Duration::from_secs(60),
+    );
+    agent.sessions.spawn_eviction_task(eviction_interval);
+
+    // 按配置创建路由器，候选目标来自可用的能力
+    let llm = agent.llm.read().await.clone();
+    let candidates = RouteCandidate::from_capabilities(&agent.capabilities.available().await);
+    let router = IntelligentRouter::from_settings_with_embedder(
+        &config.router,
+        llm,
+        agent.embeddings.clone(),
+        candidates,
+    )
+    .await;
+
+    // 加载工作流定义
+    let mut state = AppState::new(agent, config);
+    match router {
+        Ok(router) => state = state.with_router(router),
+        Err(e) => warn!("⚠️  路由配置无效: {}，使用内置规则", e),
+    }
+    if let Some(repo) = repository.clone().filter(|_| state.config.router.persist_feedback) {
+        match state.router.decision_engine().use_store(repo).await {
+            Ok(loaded) => info!("💾 反馈持久化已启用，载入 {} 条反馈", loaded),
+            Err(e) => warn!("⚠️  载入反馈失败: {}", e),
+        }
+    }
+    // 安全配置无效时拒绝启动，避免在未认证的情况下开放端点
+    let security = SecurityManager::from_config(state.config.security.clone())?
+        .with_audit(state.agent.audit.clone());
+    if let Some(repo) = repository.clone().filter(|_| state.config.security.persist) {
+        if let Err(e) = security.use_store(repo).await {
+            warn!("⚠️  载入账户和 API 密钥失败: {}", e);
+        }
+    }
+    if state.config.security.require_auth {
+        info!("🔒 所有端点都需要认证");
+    } else {
+        warn!("⚠️  未启用 require_auth，匿名请求可以访问只读端点");
+    }
+    state = state.with_security(security);
+    if let Some(repo) = repository.filter(|_| state.config.workflows.persist_runs) {
+        info!("💾 工作流运行持久化已启用");
+        state = state.with_workflow_store(repo);
+    }
+    match state.workflows.load_directory(&state.config.workflows.directory).await {
+        Ok(loaded) if !loaded.is_empty() => info!("📋 已加载 {} 个工作流", loaded.len()),
+        Ok(_) => {}
+        Err(e) => warn!("⚠️  工作流加载失败: {}", e),
+    }
+    match state.workflows.resume_interrupted().await {
+        Ok(resumed) if !resumed.is_empty() => info!("🔁 恢复 {} 个未完成的工作流运行", resumed.len()),
+        Ok(_) => {}
+        Err(e) => warn!("⚠️  恢复工作流运行失败: {}", e),
+    }
 
     // 创建路由
-    let app = Router::new()
-        .route("/health", get(health_handler))
-        .route("/info", get(info_handler))
-        .route("/chat", post(chat_handler))
-        .with_state(state);
+    let app = create_app(state);
 
     let addr = format!("127.0.0.1:{port}");
     info!("🌐 服务器启动于 http://{}", addr);
@@ -1,6 +1,7 @@
 use crate::protocol::manifest::MCPManifest;
 use serde::Serialize;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
@@ -11,6 +12,8 @@ pub enum MCPError {
     Json(#[from] serde_json::Error),
     #[error("Protocol error: {0}")]
     Protocol(String),
+    #[error("Tool call {0} was cancelled")]
+    Cancelled(Uuid),
 }
 
 #[derive(Debug, Clone)]
@@ -46,6 +49,19 @@ impl MCPClient {
         &self,
         tool_name: &str,
         parameters: serde_json::Value,
+    ) -> Result<serde_json::Value, MCPError> {
+        self.call_tool_with_cancel(tool_name, parameters, &CancellationToken::new())
+            .await
+    }
+
+    /// Calls a tool, aborting the request when `cancel` fires. On cancellation
+    /// the server is sent a `notifications/cancelled` notification for the
+    /// request id so it can stop the work as well.
+    pub async fn call_tool_with_cancel(
+        &self,
+        tool_name: &str,
+        parameters: serde_json::Value,
+        cancel: &CancellationToken,
     ) -> Result<serde_json::Value, MCPError> {
         let url = format!("{}/tools/{}/call", self.base_url, tool_name);
 
@@ -60,16 +76,50 @@ impl MCPClient {
             id: Uuid::new_v4(),
         };
 
-        let response = self.client.post(&url).json(&request).send().await?;
+        let call = async {
+            let response = self.client.post(&url).json(&request).send().await?;
+
+            if !response.status().is_success() {
+                return Err(MCPError::Protocol(format!(
+                    "Tool call failed: {}",
+                    response.status()
+                )));
+            }
+
+            let result: serde_json::Value = response.json().await?;
+            Ok(result)
+        };
+
+        tokio::select! {
+            result = call => result,
+            _ = cancel.cancelled() => {
+                if let Err(e) = self.notify_cancelled(request.id, "cancelled by client").await {
+                    tracing::warn!("Failed to notify MCP server of cancelled call {}: {}", request.id, e);
+                }
+                Err(MCPError::Cancelled(request.id))
+            }
+        }
+    }
+
+    /// Sends a `notifications/cancelled` JSON-RPC notification for `request_id`.
+    pub async fn notify_cancelled(&self, request_id: Uuid, reason: &str) -> Result<(), MCPError> {
+        let url = format!("{}/notifications", self.base_url);
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": {
+                "requestId": request_id,
+                "reason": reason,
+            }
+        });
 
+        let response = self.client.post(&url).json(&notification).send().await?;
         if !response.status().is_success() {
             return Err(MCPError::Protocol(format!(
-                "Tool call failed: {}",
+                "Cancel notification failed: {}",
                 response.status()
             )));
         }
-
-        let result: serde_json::Value = response.json().await?;
-        Ok(result)
+        Ok(())
     }
 }
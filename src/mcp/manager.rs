@@ -1,30 +1,30 @@
 use std::collections::HashMap;
-use std::time::Duration;
 use serde_json::Value;
-use tracing::{info, error, debug};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, error};
 
 use crate::config::{McpConfig, McpServerConfig};
-use crate::mcp::client::McpClient;
+use crate::mcp::client::MCPClient;
+use crate::protocol::manifest::MCPTool;
 
 #[derive(Debug, Clone)]
 pub struct ConnectedMcpServer {
     pub config: McpServerConfig,
-    pub client: McpClient,
+    pub client: MCPClient,
     pub connected: bool,
     pub capabilities: Vec<String>,
+    /// Tools advertised in the server's manifest
+    pub tools: Vec<MCPTool>,
 }
 
+#[derive(Default)]
 pub struct McpManager {
-    config: McpConfig,
     servers: HashMap<String, ConnectedMcpServer>,
 }
 
 impl McpManager {
     pub async fn from_config(config: &McpConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut manager = Self {
-            config: config.clone(),
-            servers: HashMap::new(),
-        };
+        let mut manager = Self::default();
 
         for (id, server_config) in &config.servers {
             if server_config.enabled {
@@ -40,13 +40,14 @@ impl McpManager {
         id: String,
         config: McpServerConfig,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let client = McpClient::new(config.url.clone());
+        let client = MCPClient::new(config.url.clone());
 
         let server = ConnectedMcpServer {
             config,
             client,
             connected: false,
             capabilities: Vec::new(),
+            tools: Vec::new(),
         };
 
         self.servers.insert(id, server);
@@ -73,6 +74,7 @@ impl McpManager {
                 Ok(manifest) => {
                     server.connected = true;
                     server.capabilities = manifest.capabilities;
+                    server.tools = manifest.tools;
                     info!("Connected to MCP server {} with capabilities: {:?}", 
                           server.config.name, server.capabilities);
                 }
@@ -88,7 +90,7 @@ impl McpManager {
     }
 
     pub async fn disconnect_all(&mut self) {
-        for (_, server) in &mut self.servers {
+        for server in self.servers.values_mut() {
             if server.connected {
                 server.connected = false;
                 info!("Disconnected from MCP server: {}", server.config.name);
@@ -108,15 +110,53 @@ impl McpManager {
         self.servers.get(id).map(|server| &server.capabilities)
     }
 
+    /// Finds a connected server offering `tool_name`, preferring the lowest
+    /// server id when several do.
+    pub fn find_tool(&self, tool_name: &str) -> Option<(&str, &MCPTool)> {
+        self.available_tools()
+            .into_iter()
+            .find(|(_, tool)| tool.name == tool_name)
+    }
+
+    /// Tools of all connected servers with their server id, ordered by server id.
+    pub fn available_tools(&self) -> Vec<(&str, &MCPTool)> {
+        let mut servers: Vec<(&String, &ConnectedMcpServer)> = self
+            .servers
+            .iter()
+            .filter(|(_, server)| server.connected)
+            .collect();
+        servers.sort_by_key(|(id, _)| *id);
+        servers
+            .into_iter()
+            .flat_map(|(id, server)| server.tools.iter().map(move |tool| (id.as_str(), tool)))
+            .collect()
+    }
+
     pub async fn call_tool(
         &self,
         server_id: &str,
         tool_name: &str,
         parameters: Value,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        self.call_tool_with_cancel(server_id, tool_name, parameters, &CancellationToken::new())
+            .await
+    }
+
+    /// Calls a tool on `server_id`; when `cancel` fires the request is
+    /// aborted and the server is notified.
+    pub async fn call_tool_with_cancel(
+        &self,
+        server_id: &str,
+        tool_name: &str,
+        parameters: Value,
+        cancel: &CancellationToken,
     ) -> Result<Value, Box<dyn std::error::Error>> {
         if let Some(server) = self.servers.get(server_id) {
             if server.connected {
-                server.client.call_tool(tool_name, parameters).await
+                Ok(server
+                    .client
+                    .call_tool_with_cancel(tool_name, parameters, cancel)
+                    .await?)
             } else {
                 Err(format!("MCP server {} is not connected", server_id).into())
             }
@@ -1,31 +1,46 @@
 use axum::{
-    extract::{Path, State},
-    response::Response,
-    routing::get,
-    Json, Router,
+    extract::{FromRef, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
 };
 use serde_json::json;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::agent::{Agent, AgentConfig};
+use crate::agent::{Agent, AgentConfig, AgentError, TurnOptions};
 use crate::protocol::message::{Message, MessageContent};
 use crate::protocol::agent_card::AgentCard;
+use crate::services::security::{AuthToken, Permission, SecurityManager};
+use crate::ui::api::auth;
+
+pub mod tasks;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub agent: Arc<RwLock<Agent>>,
+    pub agent: Arc<Agent>,
+    /// Authenticates and authorizes the message and task routes. The default
+    /// manager has no accounts and requires credentials, so those routes are
+    /// closed until one is configured with [`A2AServer::with_security`].
+    pub security: Arc<SecurityManager>,
 }
 
 impl AppState {
     pub fn new(agent: Agent) -> Self {
         Self {
-            agent: Arc::new(RwLock::new(agent)),
+            agent: Arc::new(agent),
+            security: Arc::new(SecurityManager::new(String::new())),
         }
     }
 }
 
+impl FromRef<AppState> for Arc<Agent> {
+    fn from_ref(state: &AppState) -> Self {
+        state.agent.clone()
+    }
+}
+
 pub struct A2AServer {
     pub port: u16,
     state: AppState,
@@ -45,22 +60,41 @@ impl A2AServer {
         Self { port, state }
     }
 
+    /// Uses `security` to check callers of the message, task and approval routes.
+    pub fn with_security(mut self, security: Arc<SecurityManager>) -> Self {
+        self.state.security = security;
+        self
+    }
+
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
-        let app = Router::new()
+        let port = self.port;
+        let app = self.app();
+        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+
+        println!("🔥 A2A Server running on http://localhost:{}", port);
+
+        axum::serve(listener, app).await?;
+        Ok(())
+    }
+
+    fn app(self) -> Router {
+        // Sending messages and approving, denying and canceling tasks go
+        // through the same route policy as the HTTP API
+        let protected = Router::new()
+            .route("/messages", post(handle_message))
+            .merge(tasks::routes())
+            .route_layer(axum::middleware::from_fn_with_state(
+                self.state.security.clone(),
+                auth::authenticate,
+            ));
+        Router::new()
             .route("/", get(root))
             .route("/health", get(health))
             .route("/manifest", get(get_manifest))
             .route("/agent.json", get(get_agent_card))
-            .route("/messages", axum::routing::post(handle_message))
             .route("/messages/:id", get(get_message))
-            .with_state(self.state);
-
-        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", self.port)).await?;
-
-        println!("🔥 A2A Server running on http://localhost:{}", self.port);
-
-        axum::serve(listener, app).await?;
-        Ok(())
+            .merge(protected)
+            .with_state(self.state)
     }
 }
 
@@ -74,7 +108,9 @@ async fn root() -> Json<serde_json::Value> {
             "/manifest": "Agent capabilities",
             "/agent.json": "Agent card (A2A specification)",
             "/messages": "Send messages",
-            "/messages/:id": "Get message by ID"
+            "/messages/:id": "Get message by ID",
+            "/tasks/:id": "Get or cancel (DELETE) a task",
+            "/tasks/cancel": "Cancel a task (A2A tasks/cancel)"
         }
     }))
 }
@@ -87,8 +123,14 @@ async fn health() -> Json<serde_json::Value> {
 }
 
 async fn get_manifest(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let agent = state.agent.read().await;
-    let capabilities = agent.get_capabilities().await;
+    let agent = &state.agent;
+    let capabilities: Vec<String> = agent
+        .capabilities
+        .available()
+        .await
+        .into_iter()
+        .map(|capability| capability.id)
+        .collect();
 
     Json(json!({
         "name": agent.config.name,
@@ -100,16 +142,86 @@ async fn get_manifest(State(state): State<AppState>) -> Json<serde_json::Value>
     }))
 }
 
+/// Runs a message from a peer. The caller must hold `agent:<sender>` for the
+/// peer it claims to be; the turn runs as the authenticated user, with tools
+/// limited to the caller's permissions, and only in sessions the caller owns.
 async fn handle_message(
     State(state): State<AppState>,
-    Json(message): Json<Message>,
+    principal: Option<Extension<AuthToken>>,
+    Json(mut message): Json<Message>,
 ) -> Result<Json<Message>, Response> {
-    let agent = state.agent.read().await;
+    let agent = &state.agent;
+    let Some(Extension(principal)) = principal else {
+        return Err(auth::error_body(StatusCode::UNAUTHORIZED, "需要认证").into_response());
+    };
+    let required = Permission::AgentAccess(message.sender.clone());
+    let resource = required.to_string();
+    if let Err(e) = state
+        .security
+        .authorize(&principal.user_id, &principal.permissions, &required, &resource)
+        .await
+    {
+        return Err(auth::error_body(StatusCode::FORBIDDEN, &e.to_string()).into_response());
+    }
+
+    // Text messages run as agent tasks keyed by the message id, so the
+    // sender can cancel them through `tasks/cancel`
+    if let MessageContent::Text { .. } = &message.content {
+        let sender = std::mem::replace(&mut message.sender, principal.user_id.clone());
+        let session_id = Agent::session_key(&message);
+        let session = agent.sessions.get_or_create(&session_id).await;
+        if !session.claim(&principal.user_id).await
+            && !Permission::Admin.granted_by(&principal.permissions)
+        {
+            let reason = format!("会话属于其他用户: {session_id}");
+            state
+                .security
+                .record_denial(&principal.user_id, &format!("session:{session_id}"), reason.clone())
+                .await;
+            return Err(auth::error_body(StatusCode::FORBIDDEN, &reason).into_response());
+        }
+        let options = TurnOptions {
+            task_id: Some(message.id.to_string()),
+            timeout: None,
+            permissions: principal.permissions.clone(),
+            restrict_tools: true,
+            owner: Some(principal.user_id.clone()),
+        };
+        let response = match agent.run_turn(&session_id, message, options).await {
+            Ok(mut response) => {
+                response.recipient = sender.clone();
+                response
+            }
+            Err(e) => {
+                if let AgentError::Forbidden(permission) = &e {
+                    state
+                        .security
+                        .record_denial(&principal.user_id, &permission.to_string(), e.to_string())
+                        .await;
+                }
+                let code = match e {
+                    AgentError::Canceled(_) => "CANCELED",
+                    AgentError::Timeout { .. } => "TIMEOUT",
+                    AgentError::Overloaded(_) => "OVERLOADED",
+                    AgentError::ApprovalDenied { .. } => "APPROVAL_DENIED",
+                    AgentError::Forbidden(_) => "FORBIDDEN",
+                    _ => "PROCESSING_FAILED",
+                };
+                Message::new(
+                    agent.config.name.clone(),
+                    sender,
+                    MessageContent::Error {
+                        code: code.to_string(),
+                        message: e.to_string(),
+                    },
+                    None,
+                )
+            }
+        };
+        return Ok(Json(response));
+    }
 
     let response_content = match &message.content {
-        MessageContent::Text { text } => MessageContent::Text {
-            text: format!("Received: {text}"),
-        },
         MessageContent::ToolCall { tool, parameters } => MessageContent::ToolResult {
             tool: tool.clone(),
             result: json!({"mock": true, "tool": tool, "parameters": parameters}),
@@ -137,7 +249,7 @@ async fn handle_message(
 }
 
 async fn get_agent_card(State(state): State<AppState>) -> Json<AgentCard> {
-    let agent = state.agent.read().await;
+    let agent = &state.agent;
     // Construct base URL from the server configuration
     // In a real deployment, this would come from configuration
     let base_url = "http://localhost:8080".to_string();
@@ -157,3 +269,91 @@ async fn get_message(Path(id): Path<Uuid>) -> Result<Json<Message>, Response> {
 
     Ok(Json(message))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_task_routes_require_credentials() {
+        let app = A2AServer::new(0).app();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let client = reqwest::Client::new();
+
+        assert_eq!(client.get(format!("{base}/health")).send().await.unwrap().status(), 200);
+        for request in [
+            client.get(format!("{base}/approvals")),
+            client.post(format!("{base}/tasks/t1/approve")),
+            client.post(format!("{base}/tasks/t1/deny")),
+            client.delete(format!("{base}/tasks/t1")),
+        ] {
+            assert_eq!(request.send().await.unwrap().status(), 401);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_run_as_the_authenticated_peer() {
+        let security = Arc::new(SecurityManager::new("test_secret".to_string()));
+        let mut keys = Vec::new();
+        for name in ["peer-a", "peer-b"] {
+            let permissions = vec![Permission::Execute, Permission::AgentAccess(name.to_string())];
+            let user_id = security
+                .create_service_account(name, permissions.clone())
+                .await
+                .unwrap();
+            let key = security
+                .create_api_key(&user_id, name, permissions, None)
+                .await
+                .unwrap()
+                .1;
+            keys.push((user_id, key));
+        }
+        let server = A2AServer::new(0).with_security(security);
+        let agent = server.state.agent.clone();
+        let app = server.app();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let client = reqwest::Client::new();
+        let send = |key: Option<&str>, sender: &str, session: &str| {
+            let message = Message::new(
+                sender.to_string(),
+                "OmniAgent".to_string(),
+                MessageContent::Text {
+                    text: "hello".to_string(),
+                },
+                Some(json!({ "session_id": session })),
+            );
+            let mut request = client.post(format!("{base}/messages")).json(&message);
+            if let Some(key) = key {
+                request = request.header("X-API-Key", key);
+            }
+            request.send()
+        };
+
+        let response = send(None, "peer-a", "s1").await.unwrap();
+        assert_eq!(response.status(), 401);
+        // A caller may only speak for the peers it holds `agent:<name>` for
+        let response = send(Some(&keys[0].1), "peer-b", "s1").await.unwrap();
+        assert_eq!(response.status(), 403);
+
+        let response = send(Some(&keys[0].1), "peer-a", "s1").await.unwrap();
+        assert_eq!(response.status(), 200);
+        let reply: Message = response.json().await.unwrap();
+        assert_eq!(reply.recipient, "peer-a");
+        let session = agent.sessions.get("s1").await.unwrap();
+        assert_eq!(session.owner().await.as_deref(), Some(keys[0].0.as_str()));
+        let context = session.state_machine.read().await.get_context();
+        assert_eq!(context[0].sender, keys[0].0);
+
+        // Other peers cannot continue the session
+        let response = send(Some(&keys[1].1), "peer-b", "s1").await.unwrap();
+        assert_eq!(response.status(), 403);
+    }
+}
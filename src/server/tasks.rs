@@ -0,0 +1,212 @@
+use axum::{
+    extract::{FromRef, Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::agent::{
+    Agent, ApprovalDecision, ApprovalError, ApprovalRequest, PendingApproval, TaskError, TaskInfo,
+};
+use crate::services::security::{AuthToken, Permission};
+
+/// A2A error code for an unknown task id.
+const TASK_NOT_FOUND: i64 = -32001;
+/// A2A error code for a task that already reached a final state.
+const TASK_NOT_CANCELABLE: i64 = -32002;
+
+/// Task endpoints, shared by the A2A server and the main API:
+/// `GET /tasks`, `GET /tasks/:id`, `DELETE /tasks/:id` and the JSON-RPC
+/// style `POST /tasks/cancel` used by A2A peers.
+///
+/// Tasks in the `input-required` state expose their pending tool call at
+/// `GET /tasks/:id/approval` and resume through `POST /tasks/:id/approve`
+/// (optionally with edited `arguments`) or `POST /tasks/:id/deny`.
+/// `GET /approvals` lists all pending calls.
+///
+/// Authenticated callers only see and cancel their own tasks and approvals;
+/// other tasks look like they don't exist. Admins see all of them.
+pub fn routes<S>() -> Router<S>
+where
+    Arc<Agent>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/tasks", get(list_tasks))
+        .route("/tasks/cancel", post(cancel_task_rpc))
+        .route("/tasks/:id", get(get_task).delete(cancel_task))
+        .route("/tasks/:id/approval", get(get_approval))
+        .route("/tasks/:id/approve", post(approve_task))
+        .route("/tasks/:id/deny", post(deny_task))
+        .route("/approvals", get(list_approvals))
+}
+
+/// Whether the caller may see a task of `owner`. Requests that bypassed the
+/// authentication layer see every task.
+fn owns(principal: &Option<Extension<AuthToken>>, owner: Option<&str>) -> bool {
+    match principal {
+        None => true,
+        Some(Extension(principal)) => {
+            Permission::Admin.granted_by(&principal.permissions)
+                || owner == Some(principal.user_id.as_str())
+        }
+    }
+}
+
+/// The task `id` if the caller may see it.
+async fn owned_task(
+    agent: &Agent,
+    principal: &Option<Extension<AuthToken>>,
+    id: &str,
+) -> Result<TaskInfo, TaskError> {
+    agent
+        .tasks
+        .get(id)
+        .await
+        .filter(|task| owns(principal, task.owner.as_deref()))
+        .ok_or_else(|| TaskError::NotFound(id.to_string()))
+}
+
+async fn list_tasks(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+) -> Json<Vec<TaskInfo>> {
+    let mut tasks = agent.tasks.list().await;
+    tasks.retain(|task| owns(&principal, task.owner.as_deref()));
+    Json(tasks)
+}
+
+async fn get_task(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+) -> Result<Json<TaskInfo>, (StatusCode, Json<Value>)> {
+    owned_task(&agent, &principal, &id)
+        .await
+        .map(Json)
+        .map_err(task_error)
+}
+
+async fn cancel_task(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+) -> Result<Json<TaskInfo>, (StatusCode, Json<Value>)> {
+    owned_task(&agent, &principal, &id).await.map_err(task_error)?;
+    agent.tasks.cancel(&id).await.map(Json).map_err(task_error)
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelRequest {
+    #[serde(default)]
+    id: Value,
+    params: CancelParams,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelParams {
+    id: String,
+}
+
+async fn cancel_task_rpc(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Json(request): Json<CancelRequest>,
+) -> Json<Value> {
+    let id = &request.params.id;
+    let canceled = match owned_task(&agent, &principal, id).await {
+        Ok(_) => agent.tasks.cancel(id).await,
+        Err(e) => Err(e),
+    };
+    let response = match canceled {
+        Ok(task) => json!({ "jsonrpc": "2.0", "id": request.id, "result": task }),
+        Err(e) => {
+            let code = match e {
+                TaskError::NotFound(_) => TASK_NOT_FOUND,
+                _ => TASK_NOT_CANCELABLE,
+            };
+            json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "error": { "code": code, "message": e.to_string() }
+            })
+        }
+    };
+    Json(response)
+}
+
+async fn list_approvals(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+) -> Json<Vec<PendingApproval>> {
+    let mut approvals = agent.approvals.list().await;
+    approvals.retain(|approval| owns(&principal, approval.owner.as_deref()));
+    Json(approvals)
+}
+
+async fn get_approval(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+) -> Result<Json<PendingApproval>, (StatusCode, Json<Value>)> {
+    agent
+        .approvals
+        .get(&id)
+        .await
+        .filter(|approval| owns(&principal, approval.owner.as_deref()))
+        .map(Json)
+        .ok_or_else(|| approval_error(ApprovalError::NotFound(id)))
+}
+
+async fn approve_task(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<String>,
+    body: Option<Json<ApprovalRequest>>,
+) -> Result<Json<PendingApproval>, (StatusCode, Json<Value>)> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    let decision = ApprovalDecision::Approve {
+        arguments: request.arguments,
+    };
+    agent
+        .approvals
+        .resolve(&id, decision)
+        .await
+        .map(Json)
+        .map_err(approval_error)
+}
+
+async fn deny_task(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<String>,
+    body: Option<Json<ApprovalRequest>>,
+) -> Result<Json<PendingApproval>, (StatusCode, Json<Value>)> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    let decision = ApprovalDecision::Deny {
+        reason: request.reason,
+    };
+    agent
+        .approvals
+        .resolve(&id, decision)
+        .await
+        .map(Json)
+        .map_err(approval_error)
+}
+
+fn approval_error(error: ApprovalError) -> (StatusCode, Json<Value>) {
+    let status = match error {
+        ApprovalError::NotFound(_) => StatusCode::NOT_FOUND,
+        ApprovalError::AlreadyPending(_) => StatusCode::CONFLICT,
+    };
+    (status, Json(json!({ "error": error.to_string() })))
+}
+
+fn task_error(error: TaskError) -> (StatusCode, Json<Value>) {
+    let status = match error {
+        TaskError::NotFound(_) => StatusCode::NOT_FOUND,
+        TaskError::AlreadyExists(_) | TaskError::AlreadyFinished { .. } => StatusCode::CONFLICT,
+    };
+    (status, Json(json!({ "error": error.to_string() })))
+}
@@ -0,0 +1,960 @@
+//! 审计日志 - 防篡改的操作记录
+//!
+//! 每条记录保存前一条记录的哈希，自身的哈希覆盖前一条哈希和全部字段，修改、
+//! 插入或删除中间的记录都会使哈希链断开。启用持久化时记录以 JSON Lines 追加
+//! 写入目录下的 `audit.jsonl`，文件超过大小上限后重命名为
+//! `audit-<首条序号>.jsonl` 并开始新文件；重启时从最后一条记录继续哈希链，
+//! 进程崩溃时写了一半的最后一行会被截掉。
+//! 写入失败的记录不进入哈希链。内存中只保留最近的记录，查询、导出和校验
+//! 读取全部文件；读取时只在取文件快照时持有锁，不阻塞写入，导出逐个文件
+//! 输出。
+
+use futures::stream::{self, BoxStream, StreamExt};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use tracing::{error, warn};
+
+use crate::llm::LLMConfig;
+use crate::protocol::message::{Message, MessageContent};
+use crate::services::memory::MemoryService;
+
+/// 当前写入的日志文件
+const ACTIVE_FILE: &str = "audit.jsonl";
+/// 轮转文件名的前缀
+const ROTATED_PREFIX: &str = "audit-";
+/// 第一条记录的前一条哈希
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// 审计动作名称
+pub mod action {
+    /// 调用 LLM
+    pub const LLM_CALL: &str = "llm_call";
+    /// 调用 MCP 或本地工具
+    pub const TOOL_CALL: &str = "tool_call";
+    /// 委托给 A2A 智能体
+    pub const A2A_DELEGATION: &str = "a2a_delegation";
+    /// 访问被拒绝
+    pub const ACCESS_DENIED: &str = "access_denied";
+    /// 调用需要管理员权限的端点
+    pub const ADMIN_ACTION: &str = "admin_action";
+}
+
+/// 审计日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// 是否把记录追加写入 `directory`
+    pub persist: bool,
+    /// 日志文件目录
+    pub directory: String,
+    /// 单个文件的大小上限（字节），超过后轮转
+    pub max_file_bytes: u64,
+    /// 保留的文件数（含当前文件），超出时删除最早的文件，0 表示全部保留
+    pub max_files: usize,
+    /// 内存中保留的最近记录数
+    pub memory_entries: usize,
+    /// 是否记录提示词、回复和工具参数，默认只记录模型和令牌数
+    pub record_content: bool,
+    /// 每条记录写入后是否同步到磁盘；关闭后写入更快，但系统崩溃时可能丢失
+    /// 最后几条记录
+    pub fsync: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            persist: true,
+            directory: "audit".to_string(),
+            max_file_bytes: 10 * 1024 * 1024,
+            max_files: 0,
+            memory_entries: 1000,
+            record_content: false,
+            fsync: true,
+        }
+    }
+}
+
+/// 审计日志条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditLog {
+    /// 序号，从 1 开始连续递增
+    pub seq: u64,
+    pub id: String,
+    pub user_id: String,
+    pub action: String,
+    pub resource: String,
+    /// Unix 时间戳（秒）
+    pub timestamp: u64,
+    pub success: bool,
+    pub details: Option<String>,
+    /// 动作相关的附加信息，例如模型和令牌数
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub metadata: Value,
+    /// 前一条记录的哈希
+    pub prev_hash: String,
+    /// 本条记录的 SHA-256 哈希（十六进制）
+    pub hash: String,
+}
+
+impl AuditLog {
+    /// 在 `prev_hash` 之后创建序号为 `seq` 的记录
+    fn chained(seq: u64, prev_hash: &str, event: AuditEvent) -> Self {
+        let mut log = Self {
+            seq,
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: event.user_id,
+            action: event.action,
+            resource: event.resource,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            success: event.success,
+            details: event.details,
+            metadata: event.metadata,
+            prev_hash: prev_hash.to_string(),
+            hash: String::new(),
+        };
+        log.hash = log.compute_hash();
+        log
+    }
+
+    /// 按除 `hash` 外的全部字段计算哈希，字段按名称排序后序列化
+    pub fn compute_hash(&self) -> String {
+        let body = json!({
+            "seq": self.seq,
+            "id": self.id,
+            "user_id": self.user_id,
+            "action": self.action,
+            "resource": self.resource,
+            "timestamp": self.timestamp,
+            "success": self.success,
+            "details": self.details,
+            "metadata": self.metadata,
+            "prev_hash": self.prev_hash,
+        });
+        hex(digest(&SHA256, body.to_string().as_bytes()).as_ref())
+    }
+}
+
+/// 待记录的事件
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub user_id: String,
+    pub action: String,
+    pub resource: String,
+    pub success: bool,
+    pub details: Option<String>,
+    pub metadata: Value,
+}
+
+impl AuditEvent {
+    /// 成功的事件，用户为空表示系统动作
+    pub fn new(action: impl Into<String>, resource: impl Into<String>) -> Self {
+        Self {
+            user_id: String::new(),
+            action: action.into(),
+            resource: resource.into(),
+            success: true,
+            details: None,
+            metadata: Value::Null,
+        }
+    }
+
+    pub fn user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = user_id.into();
+        self
+    }
+
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    /// 标记为失败，`details` 记录原因
+    pub fn failed(mut self, details: impl Into<String>) -> Self {
+        self.success = false;
+        self.details(details)
+    }
+
+    /// 按 `result` 标记成功或失败
+    pub fn outcome<T, E: fmt::Display>(self, result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => self,
+            Err(e) => self.failed(e.to_string()),
+        }
+    }
+
+    /// 添加一项附加信息
+    pub fn with(mut self, key: &str, value: Value) -> Self {
+        if !self.metadata.is_object() {
+            self.metadata = json!({});
+        }
+        if let Some(metadata) = self.metadata.as_object_mut() {
+            metadata.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    /// 合并 `metadata` 对象中的全部附加信息
+    pub fn extend(self, metadata: Value) -> Self {
+        match metadata {
+            Value::Object(map) => map
+                .into_iter()
+                .fold(self, |event, (key, value)| event.with(&key, value)),
+            _ => self,
+        }
+    }
+}
+
+/// 查询条件，全部条件同时满足的记录才会返回
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditFilter {
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub action: Option<String>,
+    /// 起始时间（Unix 秒，含）
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// 结束时间（Unix 秒，含）
+    #[serde(default)]
+    pub until: Option<u64>,
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// 最多返回的记录数，取最新的记录
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, log: &AuditLog) -> bool {
+        self.user_id.as_ref().is_none_or(|user_id| &log.user_id == user_id)
+            && self.action.as_ref().is_none_or(|action| &log.action == action)
+            && self.since.is_none_or(|since| log.timestamp >= since)
+            && self.until.is_none_or(|until| log.timestamp <= until)
+            && self.success.is_none_or(|success| log.success == success)
+    }
+}
+
+/// 哈希链校验结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainReport {
+    /// 检查的记录数
+    pub entries: usize,
+    pub first_seq: Option<u64>,
+    pub last_seq: Option<u64>,
+    /// 哈希链是否完整
+    pub valid: bool,
+    /// 第一处断开的记录序号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broken_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 校验按顺序排列的记录。第一条记录的序号为 1 时其前一条哈希必须是
+/// [`GENESIS_HASH`]，否则以它为起点（更早的文件已被轮转删除）。
+pub fn verify_chain(entries: &[AuditLog]) -> ChainReport {
+    let mut report = ChainReport {
+        entries: entries.len(),
+        first_seq: entries.first().map(|log| log.seq),
+        last_seq: entries.last().map(|log| log.seq),
+        valid: true,
+        broken_at: None,
+        error: None,
+    };
+    let mut previous: Option<&AuditLog> = None;
+    for log in entries {
+        let problem = match previous {
+            _ if log.hash != log.compute_hash() => Some("哈希与内容不符".to_string()),
+            None if log.seq == 1 && log.prev_hash != GENESIS_HASH => {
+                Some("第一条记录的前一条哈希无效".to_string())
+            }
+            Some(previous) if log.seq != previous.seq + 1 => {
+                Some(format!("序号不连续: {} 之后是 {}", previous.seq, log.seq))
+            }
+            Some(previous) if log.prev_hash != previous.hash => {
+                Some("前一条哈希与上一条记录不符".to_string())
+            }
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            report.valid = false;
+            report.broken_at = Some(log.seq);
+            report.error = Some(problem);
+            break;
+        }
+        previous = Some(log);
+    }
+    report
+}
+
+/// 审计日志错误
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("审计日志读写失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("审计日志序列化失败: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("审计日志损坏: {0}")]
+    Corrupt(String),
+}
+
+#[derive(Debug)]
+struct TrailState {
+    /// 最后一条记录的序号和哈希
+    seq: u64,
+    last_hash: String,
+    recent: VecDeque<AuditLog>,
+    /// 当前文件的大小和首条记录的序号
+    file_bytes: u64,
+    file_first_seq: u64,
+    /// 当前文件的写入句柄，首次写入时打开，轮转或写入失败后关闭
+    writer: Option<BufWriter<tokio::fs::File>>,
+}
+
+/// 读取时的数据来源
+enum Source {
+    /// 内存中的记录
+    Memory(Vec<AuditLog>),
+    /// 轮转文件，内容不再变化，可能在读取前被保留策略删除
+    Rotated(PathBuf),
+    /// 取快照时打开的当前文件，只读取快照时已写入的长度
+    Active(tokio::fs::File, u64),
+}
+
+impl Source {
+    async fn read(self) -> Result<Vec<AuditLog>, AuditError> {
+        match self {
+            Source::Memory(entries) => Ok(entries),
+            Source::Rotated(path) => match tokio::fs::read_to_string(&path).await {
+                Ok(text) => parse_entries(&path, &text),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+                Err(e) => Err(e.into()),
+            },
+            Source::Active(file, len) => {
+                let mut bytes = Vec::new();
+                file.take(len).read_to_end(&mut bytes).await?;
+                let text = String::from_utf8(bytes).map_err(|e| {
+                    AuditError::Corrupt(format!("{ACTIVE_FILE}: {e}"))
+                })?;
+                parse_entries(Path::new(ACTIVE_FILE), &text)
+            }
+        }
+    }
+}
+
+/// 哈希链审计日志
+#[derive(Debug)]
+pub struct AuditTrail {
+    config: AuditConfig,
+    /// 持久化目录，为空时只保存在内存中
+    directory: Option<PathBuf>,
+    /// 写入时持有，记录按序号顺序写入；读取只在取快照时持有
+    state: Mutex<TrailState>,
+}
+
+impl Default for AuditTrail {
+    fn default() -> Self {
+        Self::new(AuditConfig::default())
+    }
+}
+
+impl AuditTrail {
+    /// 只保存在内存中的审计日志，忽略 `persist`
+    pub fn new(config: AuditConfig) -> Self {
+        Self {
+            config,
+            directory: None,
+            state: Mutex::new(TrailState {
+                seq: 0,
+                last_hash: GENESIS_HASH.to_string(),
+                recent: VecDeque::new(),
+                file_bytes: 0,
+                file_first_seq: 0,
+                writer: None,
+            }),
+        }
+    }
+
+    /// 按配置打开审计日志；持久化时载入最近的记录并从最后一条继续哈希链。
+    /// 当前文件末尾没有换行的半行被截掉，其余无法解析的记录视为损坏
+    pub async fn open(config: AuditConfig) -> Result<Self, AuditError> {
+        if !config.persist {
+            return Ok(Self::new(config));
+        }
+        let directory = PathBuf::from(&config.directory);
+        tokio::fs::create_dir_all(&directory).await?;
+        repair_torn_line(&directory.join(ACTIVE_FILE)).await?;
+
+        let mut trail = Self::new(config);
+        let state = trail.state.get_mut();
+        let files = log_files(&directory).await?;
+        let mut last: Option<AuditLog> = None;
+        for path in files.iter().rev() {
+            let entries = read_entries(path).await?;
+            if path.ends_with(ACTIVE_FILE) {
+                state.file_bytes = tokio::fs::metadata(path).await?.len();
+                state.file_first_seq = entries.first().map_or(0, |log| log.seq);
+            }
+            if last.is_none() {
+                last = entries.last().cloned();
+            }
+            let room = trail.config.memory_entries.saturating_sub(state.recent.len());
+            for log in entries.into_iter().rev().take(room) {
+                state.recent.push_front(log);
+            }
+            if last.is_some() && state.recent.len() >= trail.config.memory_entries {
+                break;
+            }
+        }
+        if let Some(last) = last {
+            state.seq = last.seq;
+            state.last_hash = last.hash;
+        }
+        trail.directory = Some(directory);
+        Ok(trail)
+    }
+
+    pub fn config(&self) -> &AuditConfig {
+        &self.config
+    }
+
+    /// 追加一条记录；写入文件失败时记录错误，返回的条目不进入哈希链，下一条
+    /// 记录沿用它的序号
+    pub async fn record(&self, event: AuditEvent) -> AuditLog {
+        let mut state = self.state.lock().await;
+        let log = AuditLog::chained(state.seq + 1, &state.last_hash, event);
+        if let Some(directory) = &self.directory {
+            if let Err(e) = self.append(directory, &mut state, &log).await {
+                error!(
+                    "❌ 写入审计日志失败，记录 {} {} 未保存: {}",
+                    log.action, log.resource, e
+                );
+                Self::discard_partial(directory, &mut state).await;
+                return log;
+            }
+        }
+        state.seq = log.seq;
+        state.last_hash = log.hash.clone();
+        state.recent.push_back(log.clone());
+        while state.recent.len() > self.config.memory_entries {
+            state.recent.pop_front();
+        }
+        log
+    }
+
+    /// 记录一次 LLM 调用：提供商、模型和令牌用量，记录内容时还包括提示词和
+    /// 回复。提供商报告用量时使用报告值，否则按文本估算
+    pub async fn llm_call(
+        &self,
+        user_id: &str,
+        config: &LLMConfig,
+        input: &str,
+        context: &[Message],
+        reply: &Result<Message, String>,
+        scope: Value,
+    ) {
+        let reported = reply
+            .as_ref()
+            .ok()
+            .and_then(|reply| reply.metadata.as_ref()?.get("usage"))
+            .filter(|usage| usage.is_object())
+            .cloned();
+        let usage = reported.unwrap_or_else(|| {
+            let prompt_tokens =
+                MemoryService::count_tokens(context) + MemoryService::estimate_tokens(input);
+            let completion_tokens = reply.as_ref().map_or(0, |reply| {
+                MemoryService::estimate_tokens(&content_text(&reply.content))
+            });
+            json!({
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens,
+                "estimated": true
+            })
+        });
+        let mut event = AuditEvent::new(
+            action::LLM_CALL,
+            format!("{}/{}", config.provider, config.model),
+        )
+        .user(user_id)
+        .outcome(reply)
+        .with("provider", json!(config.provider))
+        .with("model", json!(config.model))
+        .with("usage", usage)
+        .extend(scope);
+        if self.config.record_content {
+            event = event.with("prompt", json!(input));
+            if let Ok(reply) = reply {
+                event = event.with("reply", json!(content_text(&reply.content)));
+            }
+        }
+        self.record(event).await;
+    }
+
+    /// 记录一次工具调用，记录内容时包括参数和结果
+    pub async fn tool_call(
+        &self,
+        user_id: &str,
+        tool: &str,
+        source: &str,
+        arguments: &Value,
+        result: &Result<Value, String>,
+        scope: Value,
+    ) {
+        let mut event = AuditEvent::new(action::TOOL_CALL, tool)
+            .user(user_id)
+            .outcome(result)
+            .with("source", json!(source))
+            .extend(scope);
+        if self.config.record_content {
+            event = event.with("arguments", arguments.clone());
+            if let Ok(result) = result {
+                event = event.with("result", result.clone());
+            }
+        }
+        self.record(event).await;
+    }
+
+    /// 记录一次委托给 A2A 智能体 `peer` 的调用，记录内容时包括请求和回复
+    pub async fn delegation<T: Serialize, E: fmt::Display>(
+        &self,
+        user_id: &str,
+        peer: &str,
+        request: &Value,
+        reply: &Result<T, E>,
+        scope: Value,
+    ) {
+        let mut event = AuditEvent::new(action::A2A_DELEGATION, peer)
+            .user(user_id)
+            .outcome(reply)
+            .extend(scope);
+        if self.config.record_content {
+            event = event.with("request", request.clone());
+            if let Ok(reply) = reply {
+                event = event.with("reply", json!(reply));
+            }
+        }
+        self.record(event).await;
+    }
+
+    /// 内存中最近的记录，最新的在前
+    pub async fn recent(&self, limit: Option<usize>) -> Vec<AuditLog> {
+        let state = self.state.lock().await;
+        state
+            .recent
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// 查询满足条件的记录，最新的在前
+    pub async fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditLog>, AuditError> {
+        let mut entries = self.entries(filter).await?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// 以 JSON Lines 导出满足条件的记录，按序号排列；不限数量时逐个文件输出，
+    /// 不把全部记录读入内存
+    pub async fn export(
+        &self,
+        filter: &AuditFilter,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, AuditError>>, AuditError> {
+        if filter.limit.is_some() {
+            let chunk = to_json_lines(&self.entries(filter).await?);
+            return Ok(stream::once(async move { chunk }).boxed());
+        }
+        let filter = filter.clone();
+        let sources = self.snapshot().await?;
+        Ok(stream::iter(sources)
+            .then(move |source| {
+                let filter = filter.clone();
+                async move {
+                    let entries: Vec<AuditLog> = source
+                        .read()
+                        .await?
+                        .into_iter()
+                        .filter(|log| filter.matches(log))
+                        .collect();
+                    to_json_lines(&entries)
+                }
+            })
+            .boxed())
+    }
+
+    /// 校验全部记录的哈希链
+    pub async fn verify(&self) -> Result<ChainReport, AuditError> {
+        let entries = self.entries(&AuditFilter::default()).await?;
+        Ok(verify_chain(&entries))
+    }
+
+    /// 满足条件的记录，按序号排列；有数量限制时只保留最新的记录
+    async fn entries(&self, filter: &AuditFilter) -> Result<Vec<AuditLog>, AuditError> {
+        let limit = filter.limit.unwrap_or(usize::MAX);
+        let mut entries = VecDeque::new();
+        for source in self.snapshot().await? {
+            for log in source.read().await? {
+                if !filter.matches(&log) {
+                    continue;
+                }
+                if entries.len() == limit {
+                    entries.pop_front();
+                }
+                if limit > 0 {
+                    entries.push_back(log);
+                }
+            }
+        }
+        Ok(entries.into())
+    }
+
+    /// 在锁内确定要读取的数据：当前文件在锁内打开，轮转后仍读到同一文件
+    async fn snapshot(&self) -> Result<Vec<Source>, AuditError> {
+        let state = self.state.lock().await;
+        let Some(directory) = &self.directory else {
+            return Ok(vec![Source::Memory(state.recent.iter().cloned().collect())]);
+        };
+        let mut sources = Vec::new();
+        for path in log_files(directory).await? {
+            if path.ends_with(ACTIVE_FILE) {
+                let file = tokio::fs::File::open(&path).await?;
+                sources.push(Source::Active(file, state.file_bytes));
+            } else {
+                sources.push(Source::Rotated(path));
+            }
+        }
+        Ok(sources)
+    }
+
+    async fn append(
+        &self,
+        directory: &Path,
+        state: &mut TrailState,
+        log: &AuditLog,
+    ) -> Result<(), AuditError> {
+        let mut line = serde_json::to_vec(log)?;
+        line.push(b'\n');
+        if state.file_bytes > 0 && state.file_bytes + line.len() as u64 > self.config.max_file_bytes {
+            self.rotate(directory, state).await?;
+        }
+
+        let writer = match &mut state.writer {
+            Some(writer) => writer,
+            None => {
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(directory.join(ACTIVE_FILE))
+                    .await?;
+                state.writer.insert(BufWriter::new(file))
+            }
+        };
+        writer.write_all(&line).await?;
+        writer.flush().await?;
+        if self.config.fsync {
+            writer.get_ref().sync_data().await?;
+        }
+        if state.file_bytes == 0 {
+            state.file_first_seq = log.seq;
+        }
+        state.file_bytes += line.len() as u64;
+        Ok(())
+    }
+
+    /// 关闭写入句柄并截掉写入失败时可能留下的半行，当前文件只保留已记入
+    /// 哈希链的记录
+    async fn discard_partial(directory: &Path, state: &mut TrailState) {
+        state.writer = None;
+        let truncated = async {
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(directory.join(ACTIVE_FILE))
+                .await?;
+            file.set_len(state.file_bytes).await
+        };
+        match truncated.await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => error!("❌ 截断审计日志失败: {}", e),
+        }
+    }
+
+    /// 把当前文件重命名为轮转文件，并删除超出数量的最早文件；删除失败只记录警告
+    async fn rotate(&self, directory: &Path, state: &mut TrailState) -> Result<(), AuditError> {
+        let rotated = directory.join(format!(
+            "{ROTATED_PREFIX}{:020}.jsonl",
+            state.file_first_seq
+        ));
+        state.writer = None;
+        tokio::fs::rename(directory.join(ACTIVE_FILE), rotated).await?;
+        state.file_bytes = 0;
+
+        if self.config.max_files > 0 {
+            let rotated = log_files(directory).await?;
+            let excess = (rotated.len() + 1).saturating_sub(self.config.max_files);
+            for path in rotated.iter().take(excess) {
+                if let Err(e) = tokio::fs::remove_file(path).await {
+                    warn!("⚠️  删除过期审计日志 {} 失败: {}", path.display(), e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 目录中的日志文件，按记录顺序排列：轮转文件在前，当前文件在最后
+async fn log_files(directory: &Path) -> Result<Vec<PathBuf>, AuditError> {
+    let mut rotated = Vec::new();
+    let mut active = None;
+    let mut entries = tokio::fs::read_dir(directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == ACTIVE_FILE {
+            active = Some(entry.path());
+        } else if name.starts_with(ROTATED_PREFIX) && name.ends_with(".jsonl") {
+            rotated.push(entry.path());
+        }
+    }
+    // 文件名中的序号补零到固定宽度，按名称排序即按序号排序
+    rotated.sort();
+    rotated.extend(active);
+    Ok(rotated)
+}
+
+/// 处理写入中断留下的最后一行：完整的记录补上换行，不完整的截掉
+async fn repair_torn_line(path: &Path) -> Result<(), AuditError> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if bytes.last().is_none_or(|b| *b == b'\n') {
+        return Ok(());
+    }
+    let complete = bytes.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    let mut file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+    if serde_json::from_slice::<AuditLog>(&bytes[complete..]).is_ok() {
+        file.seek(std::io::SeekFrom::End(0)).await?;
+        file.write_all(b"\n").await?;
+    } else {
+        warn!(
+            "⚠️  审计日志 {} 末尾有写了一半的记录（{} 字节），已截掉",
+            path.display(),
+            bytes.len() - complete
+        );
+        file.set_len(complete as u64).await?;
+    }
+    file.sync_all().await?;
+    Ok(())
+}
+
+async fn read_entries(path: &Path) -> Result<Vec<AuditLog>, AuditError> {
+    let text = tokio::fs::read_to_string(path).await?;
+    parse_entries(path, &text)
+}
+
+fn parse_entries(path: &Path, text: &str) -> Result<Vec<AuditLog>, AuditError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                AuditError::Corrupt(format!("{}:{}: {}", path.display(), index + 1, e))
+            })
+        })
+        .collect()
+}
+
+fn to_json_lines(entries: &[AuditLog]) -> Result<Vec<u8>, AuditError> {
+    let mut output = Vec::new();
+    for log in entries {
+        serde_json::to_writer(&mut output, log)?;
+        output.push(b'\n');
+    }
+    Ok(output)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// 消息的文本，用于估算令牌数和记录内容
+fn content_text(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text { text } => text.clone(),
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config() -> AuditConfig {
+        AuditConfig {
+            directory: std::env::temp_dir()
+                .join(format!("omni-agent-audit-{}", uuid::Uuid::new_v4()))
+                .display()
+                .to_string(),
+            ..AuditConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_and_tamper_detection() {
+        let trail = AuditTrail::new(AuditConfig::default());
+        let first = trail.record(AuditEvent::new("login", "alice").user("u1")).await;
+        let second = trail
+            .record(AuditEvent::new(action::TOOL_CALL, "search").user("u1").failed("超时"))
+            .await;
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+        assert_eq!(second.prev_hash, first.hash);
+        assert_eq!(second.seq, 2);
+        assert!(trail.verify().await.unwrap().valid);
+
+        let mut entries = vec![first.clone(), second.clone()];
+        entries[0].user_id = "u2".to_string();
+        let report = verify_chain(&entries);
+        assert!(!report.valid);
+        assert_eq!(report.broken_at, Some(1));
+
+        // 删除中间的记录
+        let third = trail.record(AuditEvent::new("logout", "alice")).await;
+        let report = verify_chain(&[first, third]);
+        assert_eq!(report.broken_at, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_persistence_and_rotation() {
+        let config = AuditConfig {
+            max_file_bytes: 1024,
+            max_files: 3,
+            ..temp_config()
+        };
+        let trail = AuditTrail::open(config.clone()).await.unwrap();
+        for i in 0..20 {
+            trail
+                .record(AuditEvent::new(action::LLM_CALL, "mock/gpt").user(format!("u{}", i % 2)))
+                .await;
+        }
+        let directory = PathBuf::from(&config.directory);
+        let files = log_files(&directory).await.unwrap();
+        assert_eq!(files.len(), 3);
+
+        // 重启后从最后一条记录继续哈希链
+        let reopened = AuditTrail::open(config.clone()).await.unwrap();
+        let next = reopened.record(AuditEvent::new("logout", "u0")).await;
+        assert_eq!(next.seq, 21);
+        let report = reopened.verify().await.unwrap();
+        assert!(report.valid, "{report:?}");
+        assert!(report.first_seq.unwrap() > 1);
+        assert_eq!(report.last_seq, Some(21));
+
+        let filter = AuditFilter {
+            user_id: Some("u1".to_string()),
+            limit: Some(2),
+            ..AuditFilter::default()
+        };
+        let found = reopened.query(&filter).await.unwrap();
+        assert_eq!(found.iter().map(|log| log.seq).collect::<Vec<_>>(), vec![20, 18]);
+
+        // 导出逐个文件输出，内容与查询一致
+        let chunks: Vec<Vec<u8>> = reopened
+            .export(&AuditFilter::default())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+        let exported: Vec<AuditLog> = chunks
+            .concat()
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        let mut all = reopened.query(&AuditFilter::default()).await.unwrap();
+        all.reverse();
+        assert_eq!(exported, all);
+
+        // 修改文件中的记录后校验失败
+        let active = directory.join(ACTIVE_FILE);
+        let text = tokio::fs::read_to_string(&active).await.unwrap();
+        tokio::fs::write(&active, text.replace("\"u0\"", "\"u9\"")).await.unwrap();
+        assert!(!reopened.verify().await.unwrap().valid);
+
+        let _ = tokio::fs::remove_dir_all(&directory).await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_does_not_advance_chain() {
+        let config = AuditConfig {
+            max_file_bytes: 1,
+            fsync: false,
+            ..temp_config()
+        };
+        let trail = AuditTrail::open(config.clone()).await.unwrap();
+        let first = trail.record(AuditEvent::new("login", "alice")).await;
+
+        // 同名目录使轮转失败，第二条记录写不进文件
+        let directory = PathBuf::from(&config.directory);
+        let blocker = directory.join(format!("{ROTATED_PREFIX}{:020}.jsonl", first.seq));
+        tokio::fs::create_dir_all(blocker.join("x")).await.unwrap();
+        let lost = trail.record(AuditEvent::new("logout", "alice")).await;
+        assert_eq!(lost.seq, 2);
+        assert_eq!(trail.recent(None).await.len(), 1);
+
+        tokio::fs::remove_dir_all(&blocker).await.unwrap();
+        let next = trail.record(AuditEvent::new("logout", "alice")).await;
+        assert_eq!(next.seq, 2);
+        assert_eq!(next.prev_hash, first.hash);
+        let report = trail.verify().await.unwrap();
+        assert!(report.valid, "{report:?}");
+        assert_eq!(report.last_seq, Some(2));
+
+        let _ = tokio::fs::remove_dir_all(&directory).await;
+    }
+
+    #[tokio::test]
+    async fn test_torn_last_line_is_truncated() {
+        let config = temp_config();
+        let trail = AuditTrail::open(config.clone()).await.unwrap();
+        let first = trail.record(AuditEvent::new("login", "alice")).await;
+        let second = trail.record(AuditEvent::new("logout", "alice")).await;
+        drop(trail);
+
+        let active = PathBuf::from(&config.directory).join(ACTIVE_FILE);
+        let text = tokio::fs::read_to_string(&active).await.unwrap();
+        // 最后一条记录没有换行，仍然保留
+        tokio::fs::write(&active, text.trim_end()).await.unwrap();
+        let reopened = AuditTrail::open(config.clone()).await.unwrap();
+        assert_eq!(reopened.recent(None).await[0], second);
+        drop(reopened);
+
+        // 写了一半的记录被截掉，哈希链从上一条完整记录继续
+        let mut torn = text.clone();
+        torn.push_str(r#"{"seq":3,"id":"#);
+        tokio::fs::write(&active, torn).await.unwrap();
+        let reopened = AuditTrail::open(config.clone()).await.unwrap();
+        assert_eq!(tokio::fs::read_to_string(&active).await.unwrap(), text);
+        let next = reopened.record(AuditEvent::new("login", "bob")).await;
+        assert_eq!(next.seq, 3);
+        assert_eq!(next.prev_hash, second.hash);
+        assert!(reopened.verify().await.unwrap().valid);
+        assert_eq!(reopened.query(&AuditFilter::default()).await.unwrap()[2], first);
+
+        let _ = tokio::fs::remove_dir_all(&config.directory).await;
+    }
+}
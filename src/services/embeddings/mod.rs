@@ -0,0 +1,238 @@
+//! 向量化服务 - 把文本转换为向量
+//!
+//! 记忆召回、向量路由和文档检索都通过 [`EmbeddingProvider`] 计算向量。内置
+//! OpenAI、Gemini 和 Ollama 的向量接口，以及不依赖外部服务的本地哈希向量
+//! [`HashingEmbedder`]，后者结果确定，适合测试和离线运行。
+
+mod remote;
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub use remote::{GeminiEmbeddings, OllamaEmbeddings, OpenAIEmbeddings};
+
+/// 向量化错误
+#[derive(Debug, thiserror::Error)]
+pub enum EmbeddingError {
+    #[error("HTTP错误: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("向量接口返回 {status}: {body}")]
+    Api { status: u16, body: String },
+    #[error("无效的向量响应: {0}")]
+    InvalidResponse(String),
+    #[error("向量配置无效: {0}")]
+    Config(String),
+}
+
+/// 文本向量化提供者
+#[async_trait]
+pub trait EmbeddingProvider: fmt::Debug + Send + Sync {
+    /// 提供者名称，例如 `openai`
+    fn name(&self) -> &str;
+
+    /// 向量维度，取决于远程模型时为 `None`
+    fn dimensions(&self) -> Option<usize> {
+        None
+    }
+
+    /// 为每段文本生成一个向量，顺序与输入一致
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>;
+
+    /// 为一段文本生成向量
+    async fn embed_one(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| EmbeddingError::InvalidResponse("没有返回向量".to_string()))
+    }
+}
+
+/// 本地哈希向量：把英文单词和中文字符、相邻字符对哈希到固定维度，不依赖外部服务
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let text = text.to_lowercase();
+        for segment in text.split(|c: char| !c.is_alphanumeric()) {
+            if segment.is_empty() {
+                continue;
+            }
+            if segment.is_ascii() {
+                self.add_token(&mut vector, segment);
+                continue;
+            }
+            let chars: Vec<char> = segment.chars().collect();
+            for c in &chars {
+                self.add_token(&mut vector, c.encode_utf8(&mut [0; 4]));
+            }
+            for pair in chars.windows(2) {
+                self.add_token(&mut vector, &pair.iter().collect::<String>());
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+
+    fn add_token(&self, vector: &mut [f32], token: &str) {
+        let hash = fnv1a(token.as_bytes());
+        let index = (hash % self.dimensions as u64) as usize;
+        vector[index] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashingEmbedder {
+    fn name(&self) -> &str {
+        "hashing"
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(self.dimensions)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+/// 向量化提供者类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProviderKind {
+    #[default]
+    Hashing,
+    OpenAI,
+    Gemini,
+    Ollama,
+}
+
+/// 向量化配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    pub provider: EmbeddingProviderKind,
+    /// 模型名称，为空时使用提供者的默认模型
+    pub model: Option<String>,
+    /// OpenAI 和 Gemini 需要的 API 密钥
+    pub api_key: Option<String>,
+    /// 接口地址，为空时使用提供者的默认地址
+    pub base_url: Option<String>,
+    /// 本地哈希向量的维度
+    pub dimensions: usize,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            provider: EmbeddingProviderKind::Hashing,
+            model: None,
+            api_key: None,
+            base_url: None,
+            dimensions: 256,
+        }
+    }
+}
+
+impl EmbeddingConfig {
+    /// 按配置创建向量化提供者
+    pub fn build(&self) -> Result<Arc<dyn EmbeddingProvider>, EmbeddingError> {
+        let api_key = || {
+            self.api_key
+                .clone()
+                .filter(|key| !key.is_empty())
+                .ok_or_else(|| EmbeddingError::Config("缺少 api_key".to_string()))
+        };
+        Ok(match self.provider {
+            EmbeddingProviderKind::Hashing => Arc::new(HashingEmbedder::new(self.dimensions)),
+            EmbeddingProviderKind::OpenAI => Arc::new(OpenAIEmbeddings::new(
+                api_key()?,
+                self.model.clone(),
+                self.base_url.clone(),
+            )),
+            EmbeddingProviderKind::Gemini => Arc::new(GeminiEmbeddings::new(
+                api_key()?,
+                self.model.clone(),
+                self.base_url.clone(),
+            )),
+            EmbeddingProviderKind::Ollama => {
+                Arc::new(OllamaEmbeddings::new(self.model.clone(), self.base_url.clone()))
+            }
+        })
+    }
+}
+
+/// 两个向量的余弦相似度，任一向量为零时为 0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hashing_embedder_is_deterministic() {
+        let embedder = HashingEmbedder::new(64);
+        let texts = vec!["查询城市天气".to_string(), "read a file".to_string()];
+        let first = embedder.embed(&texts).await.unwrap();
+        assert_eq!(first, embedder.embed(&texts).await.unwrap());
+        assert_eq!(first[0].len(), 64);
+
+        let similar = embedder.embed_one("城市天气预报").await.unwrap();
+        assert!(cosine_similarity(&first[0], &similar) > cosine_similarity(&first[1], &similar));
+    }
+
+    #[test]
+    fn test_remote_providers_require_api_key() {
+        let config = EmbeddingConfig {
+            provider: EmbeddingProviderKind::OpenAI,
+            ..EmbeddingConfig::default()
+        };
+        assert!(matches!(config.build(), Err(EmbeddingError::Config(_))));
+
+        let config = EmbeddingConfig {
+            provider: EmbeddingProviderKind::Ollama,
+            ..EmbeddingConfig::default()
+        };
+        assert_eq!(config.build().unwrap().name(), "ollama");
+    }
+}
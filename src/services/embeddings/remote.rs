@@ -0,0 +1,204 @@
+//! 远程向量接口：OpenAI、Gemini 和 Ollama
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{EmbeddingError, EmbeddingProvider};
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const OPENAI_MODEL: &str = "text-embedding-3-small";
+const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const GEMINI_MODEL: &str = "text-embedding-004";
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+const OLLAMA_MODEL: &str = "nomic-embed-text";
+
+/// OpenAI 向量接口 `POST /embeddings`，也适用于兼容该接口的服务
+#[derive(Debug, Clone)]
+pub struct OpenAIEmbeddings {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+impl OpenAIEmbeddings {
+    pub fn new(api_key: String, model: Option<String>, base_url: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model: model.unwrap_or_else(|| OPENAI_MODEL.to_string()),
+            base_url: trim_base_url(base_url, OPENAI_BASE_URL),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIResponse {
+    data: Vec<OpenAIEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIEmbeddings {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "model": self.model, "input": texts }));
+        let mut response: OpenAIResponse = send(request).await?;
+        response.data.sort_by_key(|embedding| embedding.index);
+        check_count(
+            texts,
+            response.data.into_iter().map(|embedding| embedding.embedding).collect(),
+        )
+    }
+}
+
+/// Gemini 向量接口 `models/{model}:batchEmbedContents`
+#[derive(Debug, Clone)]
+pub struct GeminiEmbeddings {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+impl GeminiEmbeddings {
+    pub fn new(api_key: String, model: Option<String>, base_url: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model: model.unwrap_or_else(|| GEMINI_MODEL.to_string()),
+            base_url: trim_base_url(base_url, GEMINI_BASE_URL),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GeminiResponse {
+    embeddings: Vec<GeminiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct GeminiEmbedding {
+    values: Vec<f32>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for GeminiEmbeddings {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let model = format!("models/{}", self.model);
+        let requests: Vec<_> = texts
+            .iter()
+            .map(|text| json!({ "model": model, "content": { "parts": [{ "text": text }] } }))
+            .collect();
+        let request = self
+            .client
+            .post(format!("{}/{model}:batchEmbedContents", self.base_url))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&json!({ "requests": requests }));
+        let response: GeminiResponse = send(request).await?;
+        check_count(
+            texts,
+            response.embeddings.into_iter().map(|embedding| embedding.values).collect(),
+        )
+    }
+}
+
+/// Ollama 向量接口 `POST /api/embed`
+#[derive(Debug, Clone)]
+pub struct OllamaEmbeddings {
+    client: Client,
+    model: String,
+    base_url: String,
+}
+
+impl OllamaEmbeddings {
+    pub fn new(model: Option<String>, base_url: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            model: model.unwrap_or_else(|| OLLAMA_MODEL.to_string()),
+            base_url: trim_base_url(base_url, OLLAMA_BASE_URL),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddings {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&json!({ "model": self.model, "input": texts }));
+        let response: OllamaResponse = send(request).await?;
+        check_count(texts, response.embeddings)
+    }
+}
+
+fn trim_base_url(base_url: Option<String>, default: &str) -> String {
+    base_url
+        .as_deref()
+        .unwrap_or(default)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+async fn send<T: for<'de> Deserialize<'de>>(
+    request: reqwest::RequestBuilder,
+) -> Result<T, EmbeddingError> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(EmbeddingError::Api {
+            status: status.as_u16(),
+            body: response.text().await.unwrap_or_default(),
+        });
+    }
+    let body = response.text().await?;
+    serde_json::from_str(&body).map_err(|e| EmbeddingError::InvalidResponse(e.to_string()))
+}
+
+fn check_count(texts: &[String], vectors: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    if vectors.len() != texts.len() {
+        return Err(EmbeddingError::InvalidResponse(format!(
+            "期望 {} 个向量，实际返回 {} 个",
+            texts.len(),
+            vectors.len()
+        )));
+    }
+    Ok(vectors)
+}
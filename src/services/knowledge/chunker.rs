@@ -0,0 +1,228 @@
+//! 文档分段
+//!
+//! - `fixed`：按固定字符数切分，相邻片段重叠 `chunk_overlap` 个字符
+//! - `paragraph`：以空行分隔的段落为单位合并到 `chunk_size` 以内，过长的段落按
+//!   `fixed` 切分
+//! - `heading`：先按 Markdown 标题切分为小节，小节内按 `paragraph` 合并，片段
+//!   记录所在的标题路径；没有标题的文档与 `paragraph` 相同
+
+use serde::{Deserialize, Serialize};
+
+/// 分段方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStrategy {
+    Fixed,
+    Paragraph,
+    #[default]
+    Heading,
+}
+
+/// 分段配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingConfig {
+    pub strategy: ChunkStrategy,
+    /// 片段的最大字符数
+    pub chunk_size: usize,
+    /// `fixed` 分段时相邻片段重叠的字符数
+    pub chunk_overlap: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkStrategy::Heading,
+            chunk_size: 800,
+            chunk_overlap: 100,
+        }
+    }
+}
+
+/// 文档片段
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub index: usize,
+    pub text: String,
+    /// 所在的标题路径，例如 `退款 > 时限`
+    pub heading: Option<String>,
+}
+
+/// 按配置把文本切分为片段
+pub fn split(text: &str, config: &ChunkingConfig) -> Vec<Chunk> {
+    let size = config.chunk_size.max(1);
+    let pieces: Vec<(Option<String>, String)> = match config.strategy {
+        ChunkStrategy::Fixed => fixed(text, size, config.chunk_overlap)
+            .into_iter()
+            .map(|text| (None, text))
+            .collect(),
+        ChunkStrategy::Paragraph => paragraphs(text, size)
+            .into_iter()
+            .map(|text| (None, text))
+            .collect(),
+        ChunkStrategy::Heading => sections(text)
+            .into_iter()
+            .flat_map(|(heading, body)| {
+                paragraphs(&body, size)
+                    .into_iter()
+                    .map(move |text| (heading.clone(), text))
+            })
+            .collect(),
+    };
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(index, (heading, text))| Chunk {
+            index,
+            text,
+            heading,
+        })
+        .collect()
+}
+
+fn fixed(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let step = size.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let end = (start + size).min(chars.len());
+        let chunk: String = chars[start..end].iter().collect();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+        start += step;
+    }
+    chunks
+}
+
+fn paragraphs(text: &str, size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let length = paragraph.chars().count();
+        if length > size {
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            chunks.extend(fixed(paragraph, size, 0));
+            continue;
+        }
+        if !current.is_empty() && current.chars().count() + 2 + length > size {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(paragraph);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 按 Markdown 标题切分，返回每个小节的标题路径和正文；代码块中的 `#` 不是标题
+fn sections(text: &str) -> Vec<(Option<String>, String)> {
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut body = String::new();
+    let mut in_code = false;
+    let path = |headings: &[(usize, String)]| {
+        (!headings.is_empty()).then(|| {
+            headings
+                .iter()
+                .map(|(_, title)| title.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ")
+        })
+    };
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let heading = (!in_code).then(|| heading(line)).flatten();
+        let Some((level, title)) = heading else {
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        };
+        if !body.trim().is_empty() {
+            sections.push((path(&headings), std::mem::take(&mut body)));
+        }
+        body.clear();
+        headings.retain(|(existing, _)| *existing < level);
+        headings.push((level, title));
+    }
+    if !body.trim().is_empty() {
+        sections.push((path(&headings), body));
+    }
+    sections
+}
+
+/// 解析 `# 标题` 形式的行，返回级别和标题
+fn heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let title = line[level..]
+        .strip_prefix(' ')?
+        .trim()
+        .trim_end_matches('#')
+        .trim();
+    (!title.is_empty()).then(|| (level, title.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(strategy: ChunkStrategy, chunk_size: usize, chunk_overlap: usize) -> ChunkingConfig {
+        ChunkingConfig {
+            strategy,
+            chunk_size,
+            chunk_overlap,
+        }
+    }
+
+    #[test]
+    fn test_fixed_chunks_overlap() {
+        let chunks = split("abcdefghij", &config(ChunkStrategy::Fixed, 4, 1));
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["abcd", "defg", "ghij"]);
+    }
+
+    #[test]
+    fn test_paragraphs_are_packed() {
+        let text = "one one\n\ntwo two\n\nthree three three three three";
+        let chunks = split(text, &config(ChunkStrategy::Paragraph, 20, 0));
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["one one\n\ntwo two", "three three three th", "ree three"]
+        );
+    }
+
+    #[test]
+    fn test_heading_paths() {
+        let text = "intro\n# Refunds\nwithin 30 days\n## Exceptions\nsale items\n```\n# not a heading\n```\n# Shipping\nfree";
+        let chunks = split(text, &ChunkingConfig::default());
+        let headings: Vec<Option<&str>> = chunks.iter().map(|c| c.heading.as_deref()).collect();
+        assert_eq!(
+            headings,
+            [
+                None,
+                Some("Refunds"),
+                Some("Refunds > Exceptions"),
+                Some("Shipping")
+            ]
+        );
+        assert!(chunks[2].text.contains("# not a heading"));
+        assert_eq!(chunks[3].index, 3);
+    }
+}
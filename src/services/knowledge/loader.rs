@@ -0,0 +1,273 @@
+//! 文档载入 - 按扩展名识别文档类型并提取纯文本
+//!
+//! HTML 去掉标签、脚本和样式，标题标签转换为 Markdown 标题，使分段时可以按标题
+//! 切分；PDF 见 [`super::pdf`]。
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{pdf, KnowledgeError};
+
+/// 文档类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentKind {
+    Markdown,
+    Text,
+    Html,
+    Pdf,
+}
+
+impl DocumentKind {
+    /// 按扩展名识别，不支持的扩展名返回 `None`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "txt" | "text" | "rst" | "log" => Some(Self::Text),
+            "html" | "htm" => Some(Self::Html),
+            "pdf" => Some(Self::Pdf),
+            _ => None,
+        }
+    }
+}
+
+/// 提取出的文档
+#[derive(Debug, Clone)]
+pub struct LoadedDocument {
+    pub title: String,
+    pub kind: DocumentKind,
+    pub text: String,
+}
+
+/// 读取文件并提取文本
+pub async fn load_file(path: &Path) -> Result<LoadedDocument, KnowledgeError> {
+    let kind = DocumentKind::from_path(path)
+        .ok_or_else(|| KnowledgeError::UnsupportedFormat(path.display().to_string()))?;
+    let bytes = tokio::fs::read(path).await?;
+    let fallback_title = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let mut document = parse(kind, &bytes)?;
+    if document.title.is_empty() {
+        document.title = fallback_title;
+    }
+    Ok(document)
+}
+
+/// 从文件内容提取文本，没有标题时 `title` 为空
+pub fn parse(kind: DocumentKind, bytes: &[u8]) -> Result<LoadedDocument, KnowledgeError> {
+    let (title, text) = match kind {
+        DocumentKind::Markdown => {
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            let title = text
+                .lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            (title, text)
+        }
+        DocumentKind::Text => (String::new(), String::from_utf8_lossy(bytes).into_owned()),
+        DocumentKind::Html => html_to_text(&String::from_utf8_lossy(bytes)),
+        DocumentKind::Pdf => (String::new(), pdf::extract_text(bytes)?),
+    };
+    Ok(LoadedDocument { title, kind, text })
+}
+
+/// 块级标签，前后换行
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "ul",
+    "ol",
+    "tr",
+    "table",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "blockquote",
+    "pre",
+    "hr",
+    "dd",
+    "dt",
+];
+/// 内容不是正文的标签
+const SKIPPED_TAGS: &[&str] = &["script", "style", "head", "noscript", "template", "svg"];
+
+/// 返回 `<title>` 和正文
+fn html_to_text(html: &str) -> (String, String) {
+    let title = between_tags(html, "title")
+        .map(|title| decode_entities(title).trim().to_string())
+        .unwrap_or_default();
+
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        push_text(&mut text, &rest[..open]);
+        rest = &rest[open..];
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        let Some(close) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !closing && SKIPPED_TAGS.contains(&name.as_str()) {
+            let end = format!("</{name}");
+            rest = find_ignore_case(rest, &end)
+                .and_then(|start| rest[start..].find('>').map(|end| &rest[start + end + 1..]))
+                .unwrap_or("");
+            continue;
+        }
+        match name.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                break_lines(&mut text, 2);
+                if !closing {
+                    let level = usize::from(name.as_bytes()[1] - b'0');
+                    text.push_str(&"#".repeat(level));
+                    text.push(' ');
+                }
+            }
+            "p" | "div" | "section" | "article" | "table" | "ul" | "ol" | "pre" | "blockquote" => {
+                break_lines(&mut text, 2)
+            }
+            name if BLOCK_TAGS.contains(&name) => break_lines(&mut text, 1),
+            _ => {}
+        }
+    }
+    push_text(&mut text, rest);
+
+    // 合并空白：行内连续空白压缩为一个空格，最多保留一个空行
+    let mut normalized = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        normalized.push_str(&line);
+        blank_lines = 0;
+    }
+    (title, normalized)
+}
+
+/// 源码中的换行不是正文的换行，换行由块级标签决定
+fn push_text(text: &mut String, raw: &str) {
+    text.push_str(&decode_entities(raw).replace(['\n', '\r', '\t'], " "));
+}
+
+/// 保证文本以至少 `count` 个换行结尾（忽略行尾空白）
+fn break_lines(text: &mut String, count: usize) {
+    let trimmed = text.trim_end_matches([' ', '\t']).len();
+    text.truncate(trimmed);
+    let existing = text.len() - text.trim_end_matches('\n').len();
+    for _ in existing..count {
+        text.push('\n');
+    }
+}
+
+fn between_tags<'a>(html: &'a str, tag: &str) -> Option<&'a str> {
+    let start = find_ignore_case(html, &format!("<{tag}"))?;
+    let content = start + html[start..].find('>')? + 1;
+    let end = content + find_ignore_case(&html[content..], &format!("</{tag}"))?;
+    Some(&html[content..end])
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (character, entity) {
+            (Some(character), Some(entity)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>Refund &amp; Returns</title><style>p { color: red }</style></head>
+            <body><h1>Refunds</h1><p>Refunds take <b>5&nbsp;days</b>.</p>
+            <script>alert("x")</script><!-- hidden --><ul><li>One</li><li>Two &#x4e00;</li></ul></body></html>"#;
+        let document = parse(DocumentKind::Html, html.as_bytes()).unwrap();
+        assert_eq!(document.title, "Refund & Returns");
+        assert_eq!(
+            document.text,
+            "# Refunds\n\nRefunds take 5 days.\n\nOne\nTwo 一"
+        );
+    }
+
+    #[test]
+    fn test_kind_and_markdown_title() {
+        assert_eq!(
+            DocumentKind::from_path(Path::new("docs/README.MD")),
+            Some(DocumentKind::Markdown)
+        );
+        assert_eq!(DocumentKind::from_path(Path::new("image.png")), None);
+
+        let document = parse(DocumentKind::Markdown, b"intro\n# Guide\ntext").unwrap();
+        assert_eq!(document.title, "Guide");
+    }
+}
@@ -0,0 +1,361 @@
+//! 知识库 - 载入本地文档，分段、向量化后检索
+//!
+//! 支持 Markdown、纯文本、HTML 和 PDF 文本。片段保存在智能体的向量存储中，载荷
+//! 带有文档ID、标题和标题路径，检索结果据此生成引用。智能体可以通过
+//! `kb_search`、`kb_ingest` 工具（见 [`tools`]）使用知识库，也可以在每轮对话前
+//! 自动检索，回复的 `metadata.citations` 列出引用的片段。
+
+pub mod chunker;
+pub mod loader;
+mod pdf;
+pub mod tools;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::services::embeddings::{EmbeddingError, EmbeddingProvider};
+use crate::services::vector_store::{VectorFilter, VectorRecord, VectorStore, VectorStoreError};
+
+pub use chunker::{Chunk, ChunkStrategy, ChunkingConfig};
+pub use loader::DocumentKind;
+pub use tools::{KbIngestTool, KbSearchTool};
+
+/// 回复元数据中引用列表的键
+pub const CITATIONS_METADATA_KEY: &str = "citations";
+/// 知识库片段在向量存储中的命名空间
+const NAMESPACE: &str = "knowledge";
+/// 每次向量化的片段数
+const EMBED_BATCH: usize = 64;
+
+/// 知识库错误
+#[derive(Debug, thiserror::Error)]
+pub enum KnowledgeError {
+    #[error("IO错误: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("不支持的文档格式: {0}")]
+    UnsupportedFormat(String),
+    #[error("文档解析失败: {0}")]
+    Parse(String),
+    #[error("路径不在知识库来源目录中: {0}")]
+    PathNotAllowed(String),
+    #[error("向量化失败: {0}")]
+    Embedding(#[from] EmbeddingError),
+    #[error("向量存储失败: {0}")]
+    Store(#[from] VectorStoreError),
+}
+
+/// 知识库配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnowledgeConfig {
+    pub enabled: bool,
+    /// 启动时载入的文件或目录；`kb_ingest` 只能读取这些路径下的文件
+    pub sources: Vec<String>,
+    pub chunking: ChunkingConfig,
+    /// 每次检索返回的片段数
+    pub top_k: usize,
+    /// 检索结果的最低相似度
+    pub min_score: f32,
+    /// 是否在每轮对话前自动检索并把结果加入上下文
+    pub auto_retrieve: bool,
+}
+
+impl Default for KnowledgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sources: Vec::new(),
+            chunking: ChunkingConfig::default(),
+            top_k: 4,
+            min_score: 0.2,
+            auto_retrieve: true,
+        }
+    }
+}
+
+/// 已载入的文档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    /// 文件的绝对路径，或直接载入的文本的来源
+    pub id: String,
+    pub title: String,
+    pub kind: DocumentKind,
+    pub chunks: usize,
+    pub characters: usize,
+    pub ingested_at: DateTime<Utc>,
+}
+
+/// 引用的片段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub document_id: String,
+    pub title: String,
+    pub chunk: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    pub score: f32,
+}
+
+/// 检索到的片段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeHit {
+    pub text: String,
+    pub citation: Citation,
+}
+
+/// 知识库
+pub struct KnowledgeBase {
+    config: KnowledgeConfig,
+    embeddings: Arc<dyn EmbeddingProvider>,
+    store: Arc<dyn VectorStore>,
+    documents: RwLock<HashMap<String, DocumentInfo>>,
+}
+
+impl fmt::Debug for KnowledgeBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KnowledgeBase")
+            .field("config", &self.config)
+            .field("embeddings", &self.embeddings.name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl KnowledgeBase {
+    pub fn new(
+        config: KnowledgeConfig,
+        embeddings: Arc<dyn EmbeddingProvider>,
+        store: Arc<dyn VectorStore>,
+    ) -> Self {
+        Self {
+            config,
+            embeddings,
+            store,
+            documents: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &KnowledgeConfig {
+        &self.config
+    }
+
+    /// 载入配置的所有来源，返回载入的文档数和失败的来源
+    pub async fn ingest_sources(&self) -> (usize, Vec<(String, KnowledgeError)>) {
+        let mut ingested = 0;
+        let mut failures = Vec::new();
+        for source in &self.config.sources {
+            match self.ingest_path(Path::new(source)).await {
+                Ok(documents) => ingested += documents.len(),
+                Err(e) => failures.push((source.clone(), e)),
+            }
+        }
+        (ingested, failures)
+    }
+
+    /// 载入文件，或递归载入目录中所有支持的文件；目录中无法解析的文件被跳过
+    pub async fn ingest_path(&self, path: &Path) -> Result<Vec<DocumentInfo>, KnowledgeError> {
+        if !tokio::fs::metadata(path).await?.is_dir() {
+            return Ok(vec![self.ingest_file(path).await?]);
+        }
+        let mut documents = Vec::new();
+        for file in supported_files(path).await? {
+            match self.ingest_file(&file).await {
+                Ok(document) => documents.push(document),
+                Err(e) => warn!("⚠️  跳过文档 {}: {}", file.display(), e),
+            }
+        }
+        Ok(documents)
+    }
+
+    /// 载入一个文件，已载入的同一文件被替换
+    pub async fn ingest_file(&self, path: &Path) -> Result<DocumentInfo, KnowledgeError> {
+        let document = loader::load_file(path).await?;
+        let id = tokio::fs::canonicalize(path).await?.display().to_string();
+        self.ingest_text(&id, &document.title, document.kind, &document.text)
+            .await
+    }
+
+    /// 载入文本，`id` 相同的文档被替换
+    pub async fn ingest_text(
+        &self,
+        id: &str,
+        title: &str,
+        kind: DocumentKind,
+        text: &str,
+    ) -> Result<DocumentInfo, KnowledgeError> {
+        let chunks = chunker::split(text, &self.config.chunking);
+        let mut records = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(EMBED_BATCH) {
+            let inputs: Vec<String> = batch
+                .iter()
+                .map(|chunk| match &chunk.heading {
+                    Some(heading) => format!("{title}\n{heading}\n{}", chunk.text),
+                    None => format!("{title}\n{}", chunk.text),
+                })
+                .collect();
+            let vectors = self.embeddings.embed(&inputs).await?;
+            for (chunk, vector) in batch.iter().zip(vectors) {
+                let mut record =
+                    VectorRecord::new(format!("{NAMESPACE}:{id}#{}", chunk.index), vector)
+                        .with_payload("namespace", NAMESPACE)
+                        .with_payload("document_id", id)
+                        .with_payload("title", title)
+                        .with_payload("chunk", chunk.index)
+                        .with_payload("text", chunk.text.clone());
+                if let Some(heading) = &chunk.heading {
+                    record = record.with_payload("heading", heading.clone());
+                }
+                records.push(record);
+            }
+        }
+
+        self.store.delete_matching(&document_filter(id)).await?;
+        self.store.upsert(records).await?;
+
+        let info = DocumentInfo {
+            id: id.to_string(),
+            title: title.to_string(),
+            kind,
+            chunks: chunks.len(),
+            characters: text.chars().count(),
+            ingested_at: Utc::now(),
+        };
+        self.documents
+            .write()
+            .await
+            .insert(id.to_string(), info.clone());
+        Ok(info)
+    }
+
+    /// 删除文档的所有片段，返回文档是否存在
+    pub async fn remove(&self, id: &str) -> Result<bool, KnowledgeError> {
+        self.store.delete_matching(&document_filter(id)).await?;
+        Ok(self.documents.write().await.remove(id).is_some())
+    }
+
+    /// 本次运行中载入的文档，按ID排序
+    pub async fn documents(&self) -> Vec<DocumentInfo> {
+        let mut documents: Vec<DocumentInfo> =
+            self.documents.read().await.values().cloned().collect();
+        documents.sort_by(|a, b| a.id.cmp(&b.id));
+        documents
+    }
+
+    /// 检索与 `query` 最相似的片段，只返回相似度不低于 `min_score` 的结果
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<KnowledgeHit>, KnowledgeError> {
+        let vector = self.embeddings.embed_one(query).await?;
+        let filter = VectorFilter::new().eq("namespace", NAMESPACE);
+        let matches = self.store.search(&vector, limit, Some(&filter)).await?;
+        Ok(matches
+            .into_iter()
+            .filter(|m| m.score >= self.config.min_score)
+            .map(|m| {
+                let text = |key: &str| {
+                    m.payload
+                        .get(key)
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                };
+                KnowledgeHit {
+                    text: text("text").unwrap_or_default(),
+                    citation: Citation {
+                        document_id: text("document_id").unwrap_or_default(),
+                        title: text("title").unwrap_or_default(),
+                        chunk: m.payload.get("chunk").and_then(Value::as_u64).unwrap_or(0) as usize,
+                        heading: text("heading"),
+                        score: m.score,
+                    },
+                }
+            })
+            .collect())
+    }
+
+    /// `path` 是否位于某个来源之下
+    pub async fn is_allowed(&self, path: &Path) -> bool {
+        let Ok(path) = tokio::fs::canonicalize(path).await else {
+            return false;
+        };
+        for source in &self.config.sources {
+            if let Ok(root) = tokio::fs::canonicalize(source).await {
+                if path.starts_with(&root) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// 把检索结果整理为加入上下文的说明，每个片段带编号以便引用
+pub fn format_hits(hits: &[KnowledgeHit]) -> String {
+    let mut text = String::from("知识库中的相关内容（回答时注明引用编号）:");
+    for (index, hit) in hits.iter().enumerate() {
+        let location = match &hit.citation.heading {
+            Some(heading) => format!("{} > {}", hit.citation.title, heading),
+            None => hit.citation.title.clone(),
+        };
+        text.push_str(&format!("\n[{}] {}: {}", index + 1, location, hit.text));
+    }
+    text
+}
+
+/// 检索结果的引用，JSON 形式
+pub fn citation_values(hits: &[KnowledgeHit]) -> Vec<Value> {
+    hits.iter()
+        .filter_map(|hit| serde_json::to_value(&hit.citation).ok())
+        .collect()
+}
+
+fn document_filter(id: &str) -> VectorFilter {
+    VectorFilter::new()
+        .eq("namespace", NAMESPACE)
+        .eq("document_id", id)
+}
+
+/// 目录中所有支持的文件，按路径排序
+async fn supported_files(root: &Path) -> Result<Vec<PathBuf>, KnowledgeError> {
+    let mut files = Vec::new();
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let mut entries = tokio::fs::read_dir(&directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                directories.push(path);
+            } else if file_type.is_file() && DocumentKind::from_path(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 把引用合并到消息元数据的引用列表，重复的引用只保留一个
+pub(crate) fn merge_citations(metadata: &mut Map<String, Value>, citations: Vec<Value>) {
+    if citations.is_empty() {
+        return;
+    }
+    let entry = metadata
+        .entry(CITATIONS_METADATA_KEY)
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Value::Array(existing) = entry {
+        for citation in citations {
+            if !existing.contains(&citation) {
+                existing.push(citation);
+            }
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! PDF 文本提取
+//!
+//! 解码页面内容流（未压缩或 FlateDecode），提取 `Tj`、`TJ`、`'`、`"` 操作符中的
+//! 字符串。字符串按 PDFDocEncoding（近似 Latin-1）或带 BOM 的 UTF-16BE 解码；
+//! 使用自定义编码（如 CID 字体）的文本无法还原，会被丢弃。
+
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+use tracing::debug;
+
+use super::KnowledgeError;
+
+/// 单个内容流解压后的最大字节数
+const MAX_STREAM_BYTES: usize = 16 * 1024 * 1024;
+/// 一个文档所有内容流解压后的最大总字节数
+const MAX_DOCUMENT_BYTES: usize = 64 * 1024 * 1024;
+
+/// 提取 PDF 中的文本，每个文本块一行
+pub fn extract_text(bytes: &[u8]) -> Result<String, KnowledgeError> {
+    if !bytes.starts_with(b"%PDF") {
+        return Err(KnowledgeError::Parse("不是 PDF 文件".to_string()));
+    }
+
+    let mut text = String::new();
+    let mut inflated = 0;
+    let mut position = 0;
+    while let Some(start) = find(bytes, b"stream", position) {
+        position = start + b"stream".len();
+        // 跳过 `endstream`
+        if start >= 3 && &bytes[start - 3..start] == b"end" {
+            continue;
+        }
+        let dictionary = stream_dictionary(bytes, start);
+        let mut data_start = position;
+        if bytes.get(data_start) == Some(&b'\r') {
+            data_start += 1;
+        }
+        if bytes.get(data_start) == Some(&b'\n') {
+            data_start += 1;
+        }
+        let Some(data_end) = find(bytes, b"endstream", data_start) else {
+            break;
+        };
+        position = data_end + b"endstream".len();
+        if !is_content_stream(dictionary) {
+            continue;
+        }
+
+        let data = &bytes[data_start..data_end];
+        let decoded = if contains(dictionary, b"/FlateDecode") {
+            let remaining = MAX_DOCUMENT_BYTES - inflated;
+            match inflate_zlib(data, MAX_STREAM_BYTES.min(remaining)) {
+                Ok(decoded) => {
+                    inflated += decoded.len();
+                    decoded
+                }
+                Err(InflateError::TooLarge) if remaining < MAX_STREAM_BYTES => {
+                    return Err(KnowledgeError::Parse(format!(
+                        "PDF 内容流解压后超过 {} 字节",
+                        MAX_DOCUMENT_BYTES
+                    )));
+                }
+                Err(InflateError::TooLarge) => {
+                    return Err(KnowledgeError::Parse(format!(
+                        "PDF 内容流解压后超过单个流上限 {} 字节",
+                        MAX_STREAM_BYTES
+                    )));
+                }
+                Err(InflateError::Invalid(reason)) => {
+                    debug!("跳过无法解压的内容流: {}", reason);
+                    continue;
+                }
+            }
+        } else if contains(dictionary, b"/Filter") {
+            // 其他压缩方式不支持
+            continue;
+        } else {
+            data.to_vec()
+        };
+        let block = content_text(&decoded);
+        if !block.trim().is_empty() {
+            text.push_str(block.trim());
+            text.push_str("\n\n");
+        }
+    }
+
+    if text.trim().is_empty() {
+        return Err(KnowledgeError::Parse("PDF 中没有可提取的文本".to_string()));
+    }
+    Ok(text.trim_end().to_string())
+}
+
+/// `stream` 关键字前的字典
+fn stream_dictionary(bytes: &[u8], stream_start: usize) -> &[u8] {
+    let before = &bytes[..stream_start];
+    let object_start = rfind(before, b" obj").unwrap_or(0);
+    &before[object_start..]
+}
+
+/// 图片、字体、交叉引用等流不含页面文本
+fn is_content_stream(dictionary: &[u8]) -> bool {
+    ![
+        b"/Image".as_slice(),
+        b"/Length1",
+        b"/Length2",
+        b"/FontFile",
+        b"/XRef",
+        b"/ObjStm",
+        b"/Metadata",
+        b"/ICCBased",
+        b"/DCTDecode",
+    ]
+    .iter()
+    .any(|marker| contains(dictionary, marker))
+}
+
+/// 从内容流中提取文本操作符的字符串
+fn content_text(content: &[u8]) -> String {
+    let mut text = String::new();
+    let mut operands: Vec<Operand> = Vec::new();
+    let mut i = 0;
+    while i < content.len() {
+        let byte = content[i];
+        match byte {
+            b'(' => {
+                let (string, next) = literal_string(content, i + 1);
+                operands.push(Operand::String(string));
+                i = next;
+            }
+            b'<' if content.get(i + 1) == Some(&b'<') => {
+                // 内联字典，跳过
+                i = find(content, b">>", i + 2).map_or(content.len(), |end| end + 2);
+            }
+            b'<' => {
+                let end = find(content, b">", i + 1).unwrap_or(content.len());
+                operands.push(Operand::String(hex_string(&content[i + 1..end])));
+                i = end + 1;
+            }
+            b'[' => {
+                operands.push(Operand::ArrayStart);
+                i += 1;
+            }
+            b']' => {
+                let start = operands
+                    .iter()
+                    .rposition(|operand| matches!(operand, Operand::ArrayStart))
+                    .unwrap_or(0);
+                let items = operands.split_off(start);
+                let mut array = String::new();
+                for item in items {
+                    match item {
+                        Operand::String(string) => array.push_str(&decode_string(&string)),
+                        // 较大的字距调整表示词间空格
+                        Operand::Number(n) if n < -200.0 => array.push(' '),
+                        _ => {}
+                    }
+                }
+                operands.push(Operand::Array(array));
+                i += 1;
+            }
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'/' => {
+                i += 1;
+                while i < content.len() && !is_delimiter(content[i]) {
+                    i += 1;
+                }
+                operands.push(Operand::Other);
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => {
+                let start = i;
+                i += 1;
+                while i < content.len() && matches!(content[i], b'.' | b'0'..=b'9') {
+                    i += 1;
+                }
+                let number = std::str::from_utf8(&content[start..i])
+                    .ok()
+                    .and_then(|n| n.parse::<f32>().ok())
+                    .unwrap_or(0.0);
+                operands.push(Operand::Number(number));
+            }
+            _ if is_delimiter(byte) => i += 1,
+            _ => {
+                let start = i;
+                while i < content.len() && !is_delimiter(content[i]) {
+                    i += 1;
+                }
+                apply_operator(&content[start..i], &operands, &mut text);
+                operands.clear();
+            }
+        }
+    }
+    text
+}
+
+enum Operand {
+    String(Vec<u8>),
+    Number(f32),
+    ArrayStart,
+    Array(String),
+    Other,
+}
+
+fn apply_operator(operator: &[u8], operands: &[Operand], text: &mut String) {
+    let newline = |text: &mut String| {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+    };
+    match operator {
+        b"Tj" | b"'" | b"\"" => {
+            if operator != b"Tj" {
+                newline(text);
+            }
+            if let Some(Operand::String(string)) = operands.last() {
+                text.push_str(&decode_string(string));
+            }
+        }
+        b"TJ" => {
+            if let Some(Operand::Array(array)) = operands.last() {
+                text.push_str(array);
+            }
+        }
+        b"T*" | b"ET" => newline(text),
+        b"Td" | b"TD" => {
+            let vertical = match operands {
+                [.., Operand::Number(_), Operand::Number(ty)] => *ty,
+                _ => 0.0,
+            };
+            if vertical.abs() > f32::EPSILON {
+                newline(text);
+            } else if !text.is_empty() && !text.ends_with([' ', '\n']) {
+                text.push(' ');
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 解析字面字符串，返回内容和结束位置之后的下标
+fn literal_string(content: &[u8], mut i: usize) -> (Vec<u8>, usize) {
+    let mut string = Vec::new();
+    let mut depth = 0;
+    while i < content.len() {
+        let byte = content[i];
+        i += 1;
+        match byte {
+            b'\\' => {
+                let Some(&escaped) = content.get(i) else {
+                    break;
+                };
+                i += 1;
+                match escaped {
+                    b'n' => string.push(b'\n'),
+                    b'r' => string.push(b'\r'),
+                    b't' => string.push(b'\t'),
+                    b'b' => string.push(0x08),
+                    b'f' => string.push(0x0c),
+                    b'0'..=b'7' => {
+                        let mut value = u32::from(escaped - b'0');
+                        for _ in 0..2 {
+                            match content.get(i) {
+                                Some(&digit @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(digit - b'0');
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        string.push(value as u8);
+                    }
+                    // 续行
+                    b'\r' | b'\n' => {
+                        if escaped == b'\r' && content.get(i) == Some(&b'\n') {
+                            i += 1;
+                        }
+                    }
+                    other => string.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                string.push(byte);
+            }
+            b')' if depth == 0 => break,
+            b')' => {
+                depth -= 1;
+                string.push(byte);
+            }
+            _ => string.push(byte),
+        }
+    }
+    (string, i)
+}
+
+fn hex_string(hex: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = hex
+        .iter()
+        .filter_map(|c| (*c as char).to_digit(16).map(|d| d as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+/// 按 UTF-16BE（有 BOM 时）或 Latin-1 解码，丢弃控制字符
+fn decode_string(bytes: &[u8]) -> String {
+    let decoded = if let Some(utf16) = bytes.strip_prefix(&[0xfe, 0xff]) {
+        let units: Vec<u16> = utf16
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|&byte| byte as char).collect()
+    };
+    decoded
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n')
+        .collect()
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || b"()<>[]{}/%".contains(&byte)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|index| index + from)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    find(haystack, needle, 0).is_some()
+}
+
+/// 解压失败的原因
+#[derive(Debug)]
+enum InflateError {
+    /// 数据损坏
+    Invalid(String),
+    /// 解压后超过上限
+    TooLarge,
+}
+
+/// 解压 zlib 格式（FlateDecode）的数据，输出超过 `limit` 字节时停止。不少 PDF
+/// 生成器写出的流缺少或写错结尾的 Adler-32 校验和，因此只检查头部，之后按原始
+/// DEFLATE 解压
+fn inflate_zlib(data: &[u8], limit: usize) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 2
+        || data[0] & 0x0f != 8
+        || (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 != 0
+    {
+        return Err(InflateError::Invalid("无效的 zlib 头".to_string()));
+    }
+    let mut output = Vec::new();
+    // 多读一个字节以区分恰好达到上限和超过上限
+    DeflateDecoder::new(&data[2..])
+        .take(limit as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(|e| InflateError::Invalid(e.to_string()))?;
+    if output.len() > limit {
+        return Err(InflateError::TooLarge);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate_fixed_and_stored_blocks() {
+        // zlib 压缩的 `hello hello hello`，固定 Huffman 编码
+        let compressed = [
+            0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+            0x06, 0x7d,
+        ];
+        assert_eq!(
+            inflate_zlib(&compressed, MAX_STREAM_BYTES).unwrap(),
+            b"hello hello hello"
+        );
+
+        // 未压缩块
+        let stored = [0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert_eq!(inflate_zlib(&stored, MAX_STREAM_BYTES).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_inflate_dynamic_block() {
+        let compressed = hex_string(
+            b"78da754d490e803008fc0a5f43416b42a521f5d0df6b3a78f0e065c8ac788806ed5c2b135b2b4ca1db\
+              754a4a49f234b7631d246a9d5fe27320038b3e069ac04f03d1ff11fc87319772152e74208c1bbb8245a1",
+        );
+        let text =
+            String::from_utf8(inflate_zlib(&compressed, MAX_STREAM_BYTES).unwrap()).unwrap();
+        assert!(text.starts_with("order gamma alpha refund"));
+        assert!(text.ends_with("delta delta refund"));
+        assert_eq!(text.split(' ').count(), 30);
+    }
+
+    /// 固定 Huffman 编码的 zlib 流：一个 `a` 后重复 `matches` 次长度 258 的回溯
+    fn repeated_stream(matches: usize) -> Vec<u8> {
+        let mut bytes = vec![0x78, 0x01];
+        let (mut current, mut filled) = (0u8, 0);
+        let mut push = |value: u32, count: u32, reversed: bool| {
+            for i in 0..count {
+                let bit = if reversed { value >> (count - 1 - i) } else { value >> i } & 1;
+                current |= (bit as u8) << filled;
+                filled += 1;
+                if filled == 8 {
+                    bytes.push(current);
+                    (current, filled) = (0, 0);
+                }
+            }
+        };
+        push(1, 1, false);
+        push(1, 2, false);
+        push(0x30 + u32::from(b'a'), 8, true);
+        for _ in 0..matches {
+            push(0xc5, 8, true);
+            push(0, 5, true);
+        }
+        push(0, 7, true);
+        push(0, 7, false);
+        bytes
+    }
+
+    #[test]
+    fn test_inflate_output_is_capped() {
+        let stream = repeated_stream(4);
+        assert_eq!(inflate_zlib(&stream, 1 + 4 * 258).unwrap(), vec![b'a'; 1 + 4 * 258]);
+        assert!(matches!(inflate_zlib(&stream, 4 * 258), Err(InflateError::TooLarge)));
+
+        let stored = [0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert!(matches!(inflate_zlib(&stored, 2), Err(InflateError::TooLarge)));
+
+        // 解压炸弹：约 90KB 的流解压后超过单个流的上限
+        let bomb = repeated_stream(MAX_STREAM_BYTES / 258 + 1);
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Filter /FlateDecode >>\nstream\n".to_vec();
+        pdf.extend_from_slice(&bomb);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n%%EOF");
+        assert!(matches!(
+            extract_text(&pdf),
+            Err(KnowledgeError::Parse(reason)) if reason.contains("上限")
+        ));
+    }
+
+    #[test]
+    fn test_extracts_text_operators() {
+        let pdf = b"%PDF-1.4\n1 0 obj\n<< /Length 60 >>\nstream\nBT /F1 12 Tf 72 712 Td (Hello \\(PDF\\)) Tj 0 -14 Td [(Wor) -20 (ld) -300 (again)] TJ ET\nendstream\nendobj\n%%EOF";
+        assert_eq!(extract_text(pdf).unwrap(), "Hello (PDF)\nWorld again");
+    }
+}
@@ -0,0 +1,156 @@
+//! 知识库工具 - `kb_search` 检索知识库，`kb_ingest` 载入文档
+//!
+//! `kb_search` 的结果带有 `citations` 字段，智能体把它合并到回复的元数据中。
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{citation_values, DocumentKind, KnowledgeBase, KnowledgeError};
+use crate::protocol::manifest::ToolAnnotations;
+use crate::services::tools::{Tool, ToolError};
+
+/// `kb_ingest` 直接提供的文本的文档ID前缀
+pub const INLINE_PREFIX: &str = "inline:";
+
+/// 检索知识库
+pub struct KbSearchTool {
+    knowledge: Arc<KnowledgeBase>,
+}
+
+impl KbSearchTool {
+    pub fn new(knowledge: Arc<KnowledgeBase>) -> Self {
+        Self { knowledge }
+    }
+}
+
+#[async_trait]
+impl Tool for KbSearchTool {
+    fn name(&self) -> &str {
+        "kb_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the internal knowledge base and return matching passages with citations"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        let query = parameters["query"]
+            .as_str()
+            .filter(|query| !query.trim().is_empty())
+            .ok_or_else(|| ToolError::ValidationFailed("缺少参数 query".to_string()))?;
+        let limit = parameters["limit"]
+            .as_u64()
+            .map_or(self.knowledge.config().top_k, |limit| limit as usize);
+        let hits = self
+            .knowledge
+            .search(query, limit)
+            .await
+            .map_err(execution_failed)?;
+        Ok(json!({
+            "results": hits,
+            "citations": citation_values(&hits),
+        }))
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        Some(ToolAnnotations {
+            read_only_hint: Some(true),
+            ..ToolAnnotations::default()
+        })
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "description": "What to look for"},
+                "limit": {"type": "integer", "minimum": 1}
+            },
+            "required": ["query"]
+        }))
+    }
+}
+
+/// 载入文档：`path` 为知识库来源下的文件或目录，或者直接提供 `text` 和 `title`
+pub struct KbIngestTool {
+    knowledge: Arc<KnowledgeBase>,
+}
+
+impl KbIngestTool {
+    pub fn new(knowledge: Arc<KnowledgeBase>) -> Self {
+        Self { knowledge }
+    }
+}
+
+#[async_trait]
+impl Tool for KbIngestTool {
+    fn name(&self) -> &str {
+        "kb_ingest"
+    }
+
+    fn description(&self) -> &str {
+        "Add a document to the internal knowledge base, from a file below a configured source or from text"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        let documents = if let Some(path) = parameters["path"].as_str() {
+            let path = Path::new(path);
+            if !self.knowledge.is_allowed(path).await {
+                return Err(ToolError::PermissionDenied(
+                    KnowledgeError::PathNotAllowed(path.display().to_string()).to_string(),
+                ));
+            }
+            self.knowledge
+                .ingest_path(path)
+                .await
+                .map_err(execution_failed)?
+        } else if let Some(text) = parameters["text"].as_str() {
+            let title = parameters["title"]
+                .as_str()
+                .ok_or_else(|| ToolError::ValidationFailed("缺少参数 title".to_string()))?;
+            // 加上前缀，文本不会替换从文件载入的文档
+            let source = parameters["source"].as_str().unwrap_or(title);
+            let id = format!("{INLINE_PREFIX}{source}");
+            let document = self
+                .knowledge
+                .ingest_text(&id, title, DocumentKind::Markdown, text)
+                .await
+                .map_err(execution_failed)?;
+            vec![document]
+        } else {
+            return Err(ToolError::ValidationFailed(
+                "需要参数 path 或 text".to_string(),
+            ));
+        };
+        Ok(json!({ "documents": documents }))
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        Some(ToolAnnotations {
+            read_only_hint: Some(false),
+            // 再次载入同一来源会替换已有的片段
+            destructive_hint: Some(true),
+            idempotent_hint: Some(true),
+            ..ToolAnnotations::default()
+        })
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "File or directory below a knowledge source"},
+                "text": {"type": "string", "description": "Markdown or plain text to add"},
+                "title": {"type": "string"},
+                "source": {"type": "string", "description": "Identifier of the text, stored as inline:<source>; re-ingesting the same source replaces it"}
+            }
+        }))
+    }
+}
+
+fn execution_failed(error: KnowledgeError) -> ToolError {
+    ToolError::ExecutionFailed(error.to_string())
+}
@@ -1,4 +1,34 @@
-//! 内存服务模块 - 简化版
+//! 内存服务模块 - 上下文压缩
+//!
+//! 对话接近模型的上下文窗口时，把较早的轮次概括为一条滚动摘要：上一次的摘要
+//! 和新移出的轮次一起重新概括，最近的几条消息不压缩。较早的工具结果从新到旧
+//! 尽量原样保留，放不下时截断，连截断后也放不下的并入摘要。
+//! 配置了 LLM 时由 LLM 概括，模拟模式下或 LLM 失败时使用抽取式摘要，优先保留
+//! 含数字、链接等关键事实的句子。令牌数按中文每字一个、其他文字每四个字符一个
+//! 估算，每条消息另加固定开销。
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::core::state::memory::truncate_chars;
+use crate::core::state::{BufferedMessage, MessageType, Summarizer};
+use crate::llm::LLMService;
+use crate::protocol::message::{Message, MessageContent};
+
+/// 摘要消息元数据中的标记键
+pub const SUMMARY_METADATA_KEY: &str = "context_summary";
+/// 每条消息的角色和格式开销
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// 摘要至少可用的令牌数
+const MIN_SUMMARY_TOKENS: usize = 32;
+/// 抽取式摘要中每条消息保留的字符数
+const GIST_CHARS: usize = 60;
+/// 抽取式摘要中关键事实句保留的字符数
+const FACT_CHARS: usize = 160;
+/// 截断后的工具结果至少保留的令牌数，也是并入摘要的工具结果在对话记录中的长度
+const MIN_TOOL_RESULT_TOKENS: usize = 64;
 
 /// 上下文数据
 #[derive(Debug, Clone)]
@@ -14,32 +44,647 @@ pub struct CompressedContext {
     pub compressed_token_count: usize,
 }
 
+/// 上下文压缩配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// 模型的上下文窗口（令牌）
+    pub context_window_tokens: usize,
+    /// 上下文达到窗口的该比例时压缩
+    pub trigger_ratio: f32,
+    /// 压缩后的目标大小，占窗口的比例
+    pub target_ratio: f32,
+    /// 不压缩的最近消息数
+    pub keep_recent: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            context_window_tokens: 8192,
+            trigger_ratio: 0.8,
+            target_ratio: 0.5,
+            keep_recent: 6,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// 触发压缩的令牌数
+    pub fn trigger_tokens(&self) -> usize {
+        (self.context_window_tokens as f32 * self.trigger_ratio) as usize
+    }
+
+    /// 压缩后的目标令牌数
+    pub fn target_tokens(&self) -> usize {
+        (self.context_window_tokens as f32 * self.target_ratio) as usize
+    }
+}
+
+/// 压缩后的对话
+#[derive(Debug, Clone)]
+pub struct CompressedHistory {
+    /// 摘要消息、保留的工具结果和最近的消息
+    pub messages: Vec<Message>,
+    pub summary: Option<String>,
+    pub original_token_count: usize,
+    pub compressed_token_count: usize,
+    /// 被概括的消息数（含上一次的摘要）
+    pub summarized_messages: usize,
+    /// 保留的工具结果数（含截断的）
+    pub preserved_tool_results: usize,
+    pub truncated_tool_results: usize,
+    /// 摘要是否由 LLM 生成
+    pub used_llm: bool,
+    /// 概括时发出的 LLM 调用，供调用方记录审计；未调用 LLM 时为空
+    pub llm_call: Option<SummaryCall>,
+}
+
+/// 概括上下文的一次 LLM 调用
+#[derive(Debug, Clone)]
+pub struct SummaryCall {
+    pub prompt: String,
+    pub reply: Result<Message, String>,
+}
+
 /// 内存服务
-pub struct MemoryService;
+#[derive(Debug, Clone, Default)]
+pub struct MemoryService {
+    config: CompressionConfig,
+    llm: Option<LLMService>,
+}
 
 impl MemoryService {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: CompressionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 使用 LLM 生成摘要
+    pub fn with_llm(mut self, llm: LLMService) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+
+    /// 估算文本的令牌数
+    pub fn estimate_tokens(text: &str) -> usize {
+        let mut tokens = 0;
+        let mut ascii_run: usize = 0;
+        for c in text.chars() {
+            if c.is_ascii() {
+                ascii_run += 1;
+            } else {
+                tokens += ascii_run.div_ceil(4) + 1;
+                ascii_run = 0;
+            }
+        }
+        tokens + ascii_run.div_ceil(4)
+    }
+
+    /// 估算消息列表的令牌数
+    pub fn count_tokens(messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| Self::estimate_tokens(&content_text(&message.content)) + MESSAGE_OVERHEAD_TOKENS)
+            .sum()
+    }
+
+    /// 上下文达到 `tokens` 时是否需要压缩
+    pub fn should_compress(&self, tokens: usize) -> bool {
+        self.config.enabled && tokens >= self.config.trigger_tokens()
     }
 
     /// 压缩上下文
     pub async fn compress_context(&self, context: ContextData) -> CompressedContext {
-        let original_text: String = context.messages.join("\n");
-        let original_tokens = original_text.chars().count() / 4; // 简单估算
-        
-        // 简单的摘要生成
-        let summary = if original_text.len() > 100 {
-            format!("{}...", &original_text[..100])
+        let messages: Vec<Message> = context
+            .messages
+            .into_iter()
+            .map(|text| {
+                Message::new(
+                    "user".to_string(),
+                    "assistant".to_string(),
+                    MessageContent::Text { text },
+                    None,
+                )
+            })
+            .collect();
+        let compressed = self.compress_messages(&messages).await;
+        CompressedContext {
+            summary: compressed
+                .messages
+                .iter()
+                .map(|message| content_text(&message.content))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            original_token_count: compressed.original_token_count,
+            compressed_token_count: compressed.compressed_token_count,
+        }
+    }
+
+    /// 用服务配置的 LLM 压缩对话
+    pub async fn compress_messages(&self, messages: &[Message]) -> CompressedHistory {
+        self.compress_messages_with(messages, self.llm.as_ref()).await
+    }
+
+    /// 压缩对话：最近 `keep_recent` 条之前的消息概括为一条摘要，工具结果在目标
+    /// 令牌数内保留，摘要的长度使结果不超过目标令牌数。上一次压缩后只剩摘要可以
+    /// 概括、工具结果也都放得下时不再压缩，原样返回
+    pub async fn compress_messages_with(
+        &self,
+        messages: &[Message],
+        llm: Option<&LLMService>,
+    ) -> CompressedHistory {
+        let original_token_count = Self::count_tokens(messages);
+        let split = messages.len().saturating_sub(self.config.keep_recent);
+        let (older, recent) = messages.split_at(split);
+
+        // 从新到旧为工具结果分配摘要之外的令牌
+        let mut room = self
+            .config
+            .target_tokens()
+            .saturating_sub(Self::count_tokens(recent) + MESSAGE_OVERHEAD_TOKENS + MIN_SUMMARY_TOKENS);
+        let mut kept: Vec<Option<Message>> = vec![None; older.len()];
+        let mut truncated_tool_results = 0;
+        for (index, message) in older.iter().enumerate().rev() {
+            if !matches!(message.content, MessageContent::ToolResult { .. }) {
+                continue;
+            }
+            let tokens = Self::count_tokens(std::slice::from_ref(message));
+            if tokens <= room {
+                room -= tokens;
+                kept[index] = Some(message.clone());
+            } else if room >= MIN_TOOL_RESULT_TOKENS {
+                let capped = cap_tool_result(message, room);
+                room -= Self::count_tokens(std::slice::from_ref(&capped));
+                kept[index] = Some(capped);
+                truncated_tool_results += 1;
+            }
+        }
+
+        let mut preserved = Vec::new();
+        let mut to_summarize = Vec::new();
+        for (message, kept) in older.iter().zip(kept) {
+            match kept {
+                Some(kept) => preserved.push(kept),
+                None => to_summarize.push(message),
+            }
+        }
+
+        let previous_summary = match to_summarize.as_slice() {
+            [message] if is_summary(message) => Some(content_text(&message.content)),
+            _ => None,
+        };
+        if to_summarize.is_empty() || (previous_summary.is_some() && truncated_tool_results == 0) {
+            return CompressedHistory {
+                messages: messages.to_vec(),
+                summary: None,
+                original_token_count,
+                compressed_token_count: original_token_count,
+                summarized_messages: 0,
+                preserved_tool_results: 0,
+                truncated_tool_results: 0,
+                used_llm: false,
+                llm_call: None,
+            };
+        }
+
+        let fixed = Self::count_tokens(recent) + Self::count_tokens(&preserved) + MESSAGE_OVERHEAD_TOKENS;
+        let budget = self
+            .config
+            .target_tokens()
+            .saturating_sub(fixed)
+            .max(MIN_SUMMARY_TOKENS);
+        let transcript: Vec<String> = to_summarize
+            .iter()
+            .map(|message| match message.content {
+                MessageContent::ToolResult { .. } => {
+                    truncate_tokens(&transcript_line(message), MIN_TOOL_RESULT_TOKENS)
+                }
+                _ => transcript_line(message),
+            })
+            .collect();
+
+        let mut llm_call = None;
+        let mut llm_summary = None;
+        // 只剩上一次的摘要时沿用它，不再调用 LLM
+        if let Some(previous) = previous_summary {
+            llm_summary = Some(previous);
+        } else if let Some(llm) = llm.filter(|llm| !llm.config.use_mock) {
+            let prompt = summary_prompt(&transcript, budget);
+            let reply = llm.process_message(&prompt, &[]).await;
+            match summary_text(&reply) {
+                Ok(summary) => llm_summary = Some(summary),
+                Err(e) => warn!("⚠️  LLM 概括上下文失败，使用抽取式摘要: {}", e),
+            }
+            llm_call = Some(SummaryCall { prompt, reply });
+        }
+        let used_llm = llm_call.is_some() && llm_summary.is_some();
+        let summary = llm_summary.unwrap_or_else(|| extract_summary(&transcript));
+        let summary = truncate_tokens(&summary, budget);
+
+        let mut compressed = Vec::with_capacity(preserved.len() + recent.len() + 1);
+        compressed.push(Message::new(
+            "system".to_string(),
+            "assistant".to_string(),
+            MessageContent::Text {
+                text: summary.clone(),
+            },
+            Some(json!({ "role": "system", SUMMARY_METADATA_KEY: true })),
+        ));
+        let preserved_tool_results = preserved.len();
+        compressed.extend(preserved);
+        compressed.extend_from_slice(recent);
+
+        CompressedHistory {
+            compressed_token_count: Self::count_tokens(&compressed),
+            messages: compressed,
+            summary: Some(summary),
+            original_token_count,
+            summarized_messages: to_summarize.len(),
+            preserved_tool_results,
+            truncated_tool_results,
+            used_llm,
+            llm_call,
+        }
+    }
+}
+
+/// 概括对话缓冲区中被挤出的消息，作为分层记忆的摘要器
+#[async_trait]
+impl Summarizer for MemoryService {
+    async fn summarize(&self, messages: &[BufferedMessage]) -> Result<String, String> {
+        let transcript: Vec<String> = messages
+            .iter()
+            .map(|message| {
+                let role = match message.message_type {
+                    MessageType::UserMessage => "user",
+                    MessageType::SystemMessage => "system",
+                    MessageType::ToolResponse => "tool",
+                    MessageType::LLMResponse => "assistant",
+                };
+                format!("{role}: {}", message.content.trim())
+            })
+            .collect();
+        let budget = self.config.target_tokens().max(MIN_SUMMARY_TOKENS);
+        match self.llm.as_ref().filter(|llm| !llm.config.use_mock) {
+            Some(llm) => summarize_with_llm(llm, &transcript, budget).await,
+            None => Ok(truncate_tokens(&extract_summary(&transcript), budget)),
+        }
+    }
+}
+
+async fn summarize_with_llm(
+    llm: &LLMService,
+    transcript: &[String],
+    budget: usize,
+) -> Result<String, String> {
+    let reply = llm.process_message(&summary_prompt(transcript, budget), &[]).await;
+    summary_text(&reply)
+}
+
+fn summary_prompt(transcript: &[String], budget: usize) -> String {
+    format!(
+        "Summarize the earlier part of a conversation so the summary can replace it in the \
+         context window. If it starts with a previous summary, merge the two. Keep every key \
+         fact: names, numbers, dates, identifiers, decisions, user preferences and open \
+         questions. Use at most {budget} tokens and reply with the summary only.\n\n{}",
+        transcript.join("\n")
+    )
+}
+
+fn summary_text(reply: &Result<Message, String>) -> Result<String, String> {
+    match &reply.as_ref()?.content {
+        MessageContent::Text { text } if !text.trim().is_empty() => Ok(text.trim().to_string()),
+        _ => Err("LLM 没有返回文本摘要".to_string()),
+    }
+}
+
+/// 抽取式摘要：先列出含数字或链接的关键事实句，再列出每条消息的开头
+fn extract_summary(transcript: &[String]) -> String {
+    let mut facts: Vec<String> = Vec::new();
+    let mut gists = Vec::new();
+    for line in transcript {
+        let (role, text) = line.split_once(": ").unwrap_or(("", line));
+        for sentence in text
+            .split_inclusive(['。', '！', '？', '\n', '.', '!', '?'])
+            .map(str::trim)
+            .filter(|sentence| is_key_fact(sentence))
+        {
+            let fact = format!("{role}: {}", truncate_chars(sentence, FACT_CHARS));
+            if !facts.contains(&fact) {
+                facts.push(fact);
+            }
+        }
+        gists.push(format!("{role}: {}", truncate_chars(text.trim(), GIST_CHARS)));
+    }
+
+    let mut summary = String::new();
+    if !facts.is_empty() {
+        summary.push_str("关键事实:\n");
+        summary.push_str(&facts.join("\n"));
+        summary.push_str("\n\n");
+    }
+    summary.push_str("较早的对话:\n");
+    summary.push_str(&gists.join("\n"));
+    summary
+}
+
+fn is_key_fact(sentence: &str) -> bool {
+    sentence.chars().any(|c| c.is_ascii_digit()) || sentence.contains("http") || sentence.contains('@')
+}
+
+/// 按估算的令牌数截断，在字符边界处截断并以省略号结尾
+pub fn truncate_tokens(text: &str, max_tokens: usize) -> String {
+    if MemoryService::estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+    // 为省略号留出一个令牌
+    let limit = max_tokens.saturating_sub(1);
+    let (mut tokens, mut ascii_run, mut end) = (0, 0usize, 0);
+    for (index, c) in text.char_indices() {
+        if c.is_ascii() {
+            ascii_run += 1;
         } else {
-            original_text.clone()
+            tokens += ascii_run.div_ceil(4) + 1;
+            ascii_run = 0;
+        }
+        if tokens + ascii_run.div_ceil(4) > limit {
+            break;
+        }
+        end = index + c.len_utf8();
+    }
+    format!("{}…", &text[..end])
+}
+
+/// 把工具结果截断到约 `max_tokens` 个令牌，结果改为截断后的 JSON 文本
+fn cap_tool_result(message: &Message, max_tokens: usize) -> Message {
+    let MessageContent::ToolResult { tool, result } = &message.content else {
+        return message.clone();
+    };
+    let text = result.to_string();
+    let mut limit = max_tokens;
+    loop {
+        let mut capped = message.clone();
+        capped.content = MessageContent::ToolResult {
+            tool: tool.clone(),
+            result: serde_json::Value::String(truncate_tokens(&text, limit)),
         };
-        
-        let compressed_tokens = summary.chars().count() / 4;
-        
-        CompressedContext {
-            summary,
-            original_token_count: original_tokens,
-            compressed_token_count: compressed_tokens,
+        // 字符串转义会让估算变大，超出时按超出的部分收紧
+        let tokens = MemoryService::count_tokens(std::slice::from_ref(&capped));
+        if tokens <= max_tokens || limit == 0 {
+            return capped;
+        }
+        limit = limit.saturating_sub(tokens - max_tokens);
+    }
+}
+
+fn is_summary(message: &Message) -> bool {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get(SUMMARY_METADATA_KEY))
+        .and_then(|flag| flag.as_bool())
+        .unwrap_or(false)
+}
+
+fn transcript_line(message: &Message) -> String {
+    let role = message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("role"))
+        .and_then(|role| role.as_str())
+        .unwrap_or(&message.sender);
+    format!("{role}: {}", content_text(&message.content))
+}
+
+fn content_text(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text { text } => text.clone(),
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::providers::{LLMError, LLMProvider, LLMRequest, LLMResponse, ProviderConfig};
+    use crate::llm::LLMConfig;
+    use std::sync::{Arc, Mutex};
+
+    struct SummaryProvider {
+        prompts: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for SummaryProvider {
+        async fn chat(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+            self.prompts
+                .lock()
+                .unwrap()
+                .push(request.messages.last().unwrap().content.clone());
+            Ok(LLMResponse {
+                content: "用户住在柏林，订单号 4711。".to_string(),
+                usage: None,
+                model: "summary".to_string(),
+            })
+        }
+
+        async fn chat_stream(&self, request: LLMRequest) -> Result<String, LLMError> {
+            self.chat(request).await.map(|response| response.content)
+        }
+
+        fn provider_name(&self) -> &'static str {
+            "summary"
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
         }
     }
-}
\ No newline at end of file
+
+    fn text(role: &str, text: &str) -> Message {
+        Message::new(
+            role.to_string(),
+            "agent".to_string(),
+            MessageContent::Text {
+                text: text.to_string(),
+            },
+            Some(json!({ "role": role })),
+        )
+    }
+
+    fn conversation() -> Vec<Message> {
+        let mut messages = vec![
+            text("user", "我住在柏林。我的订单号是 4711，请帮我查一下物流状态。"),
+            Message::new(
+                "lookup_order".to_string(),
+                "agent".to_string(),
+                MessageContent::ToolResult {
+                    tool: "lookup_order".to_string(),
+                    result: json!({"order": 4711, "status": "shipped"}),
+                },
+                None,
+            ),
+        ];
+        for i in 0..10 {
+            messages.push(text("user", &"这是一段很长的闲聊内容，没有什么重要的信息。".repeat(4)));
+            messages.push(text("assistant", &format!("好的，第 {i} 条回复。")));
+        }
+        messages
+    }
+
+    fn config() -> CompressionConfig {
+        CompressionConfig {
+            context_window_tokens: 400,
+            keep_recent: 2,
+            ..CompressionConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compression_preserves_tool_results_and_fits_budget() {
+        let service = MemoryService::new().with_config(config());
+        let messages = conversation();
+        let original = MemoryService::count_tokens(&messages);
+        assert!(service.should_compress(original));
+
+        let compressed = service.compress_messages(&messages).await;
+        assert_eq!(compressed.original_token_count, original);
+        assert!(compressed.compressed_token_count <= service.config().target_tokens());
+        assert_eq!(
+            compressed.compressed_token_count,
+            MemoryService::count_tokens(&compressed.messages)
+        );
+        assert!(!compressed.used_llm);
+        assert_eq!(compressed.preserved_tool_results, 1);
+        assert_eq!(compressed.summarized_messages, 19);
+        // 摘要、工具结果、最近两条消息
+        assert_eq!(compressed.messages.len(), 4);
+        assert!(matches!(compressed.messages[1].content, MessageContent::ToolResult { .. }));
+        let summary = compressed.summary.unwrap();
+        assert!(summary.contains("4711"));
+
+        // 再次压缩时上一次的摘要一起被概括
+        let mut rolled = compressed.messages.clone();
+        rolled.extend(conversation().into_iter().skip(2));
+        let again = service.compress_messages(&rolled).await;
+        assert_eq!(again.preserved_tool_results, 1);
+        assert!(again.summary.unwrap().contains("4711"));
+    }
+
+    async fn summary_llm(prompts: Arc<Mutex<Vec<String>>>) -> LLMService {
+        let llm = LLMService::new(
+            LLMConfig {
+                provider: "summary".to_string(),
+                use_mock: false,
+                ..LLMConfig::default()
+            },
+            ProviderConfig {
+                openai: None,
+                claude: None,
+                google: None,
+            },
+        );
+        llm.manager
+            .add_provider("summary".to_string(), Box::new(SummaryProvider { prompts }))
+            .await;
+        llm
+    }
+
+    #[tokio::test]
+    async fn test_large_tool_results_are_capped_and_compressed_history_is_kept() {
+        let tool_result = |i: usize| {
+            Message::new(
+                "search".to_string(),
+                "agent".to_string(),
+                MessageContent::ToolResult {
+                    tool: "search".to_string(),
+                    result: json!({ "id": i, "text": "搜索结果的正文。".repeat(60) }),
+                },
+                None,
+            )
+        };
+        let mut messages = conversation();
+        for i in 0..3 {
+            messages.insert(4 + i * 2, tool_result(i));
+        }
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let service = MemoryService::new()
+            .with_config(CompressionConfig {
+                context_window_tokens: 800,
+                ..config()
+            })
+            .with_llm(summary_llm(prompts.clone()).await);
+
+        let compressed = service.compress_messages(&messages).await;
+        assert!(compressed.compressed_token_count <= service.config().target_tokens());
+        // 最新的工具结果截断后保留，放不下的并入摘要
+        assert_eq!(compressed.truncated_tool_results, 1);
+        assert_eq!(compressed.preserved_tool_results, 1);
+        let preserved: Vec<_> = compressed
+            .messages
+            .iter()
+            .filter_map(|message| match &message.content {
+                MessageContent::ToolResult { result, .. } => Some(result.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert!(preserved.iter().any(|result| result.contains("\\\"id\\\":2") && result.contains('…')));
+        assert_eq!(prompts.lock().unwrap().len(), 1);
+
+        // 已经压缩到最小的上下文再次压缩时原样返回，不调用 LLM
+        let again = service.compress_messages(&compressed.messages).await;
+        assert!(again.summary.is_none());
+        assert_eq!(again.messages.len(), compressed.messages.len());
+        assert_eq!(prompts.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_llm_summary_and_utf8_safe_compress_context() {
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let llm = summary_llm(prompts.clone()).await;
+
+        let service = MemoryService::new().with_config(config()).with_llm(llm);
+        let compressed = service.compress_messages(&conversation()).await;
+        assert!(compressed.used_llm);
+        assert_eq!(compressed.summary.as_deref(), Some("用户住在柏林，订单号 4711。"));
+        assert!(prompts.lock().unwrap()[0].contains("订单号是 4711"));
+        // 调用方据此记录审计
+        let call = compressed.llm_call.unwrap();
+        assert_eq!(call.prompt, prompts.lock().unwrap()[0]);
+        assert!(call.reply.is_ok());
+
+        // 多字节文本不会在字符中间被截断
+        let context = ContextData {
+            messages: (0..30).map(|i| format!("第{i}条：你好世界，这是中文消息。")).collect(),
+        };
+        let compressed = MemoryService::new()
+            .with_config(CompressionConfig {
+                context_window_tokens: 100,
+                keep_recent: 2,
+                ..CompressionConfig::default()
+            })
+            .compress_context(context)
+            .await;
+        assert!(compressed.compressed_token_count < compressed.original_token_count);
+        assert!(!compressed.summary.is_empty());
+    }
+
+    #[test]
+    fn test_token_estimates() {
+        assert_eq!(MemoryService::estimate_tokens(""), 0);
+        assert_eq!(MemoryService::estimate_tokens("hello world"), 3);
+        assert_eq!(MemoryService::estimate_tokens("你好"), 2);
+        assert_eq!(truncate_tokens("你好世界", 3), "你好…");
+    }
+}
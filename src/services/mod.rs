@@ -3,4 +3,8 @@
 pub mod llm;
 pub mod tools;
 pub mod memory;
-pub mod security;
\ No newline at end of file
+pub mod security;
+pub mod audit;
+pub mod embeddings;
+pub mod vector_store;
+pub mod knowledge;
@@ -0,0 +1,84 @@
+//! API 密钥 - 机器客户端使用的长期凭据
+//!
+//! 密钥形如 `oa_<8 位十六进制>_<随机部分>`，其中 `oa_<8 位十六进制>` 是公开
+//! 前缀，用于查找记录和在界面中识别密钥。密钥本身只在创建时返回一次，
+//! 存储中只保存其 SHA-256 哈希；密钥是高熵随机串，不需要慢哈希。
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+
+use super::Permission;
+
+/// API 密钥的固定开头
+pub const API_KEY_PREFIX: &str = "oa_";
+
+/// API 密钥信息，不含密钥本身
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// 公开前缀，例如 `oa_1a2b3c4d`
+    pub prefix: String,
+    /// 密钥所属的用户
+    pub user_id: String,
+    /// 密钥的权限范围
+    pub permissions: Vec<Permission>,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub last_used_at: Option<u64>,
+}
+
+impl ApiKey {
+    /// 密钥在给定时间是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// 生成新的密钥，返回公开前缀和完整密钥
+pub fn generate_api_key() -> (String, String) {
+    let id: [u8; 4] = rand::random();
+    let secret: [u8; 32] = rand::random();
+    let prefix = format!("{API_KEY_PREFIX}{}", hex(&id));
+    let key = format!("{prefix}_{}", URL_SAFE_NO_PAD.encode(secret));
+    (prefix, key)
+}
+
+/// 从完整密钥中取出公开前缀，格式不符时返回 `None`
+pub fn api_key_prefix(key: &str) -> Option<&str> {
+    let rest = key.strip_prefix(API_KEY_PREFIX)?;
+    let (id, secret) = rest.split_once('_')?;
+    if id.len() != 8 || secret.is_empty() {
+        return None;
+    }
+    Some(&key[..API_KEY_PREFIX.len() + id.len()])
+}
+
+/// 密钥的 SHA-256 哈希（十六进制）
+pub fn hash_api_key(key: &str) -> String {
+    hex(digest(&SHA256, key.as_bytes()).as_ref())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys() {
+        let (prefix, key) = generate_api_key();
+        assert!(prefix.starts_with("oa_") && prefix.len() == 11);
+        assert_eq!(api_key_prefix(&key), Some(prefix.as_str()));
+        assert_eq!(hash_api_key(&key).len(), 64);
+        assert_ne!(generate_api_key().1, key);
+
+        assert_eq!(api_key_prefix("oa_1234"), None);
+        assert_eq!(api_key_prefix("oa_12345678_"), None);
+        assert_eq!(api_key_prefix("eyJhbGciOiJIUzI1NiJ9.e30.sig"), None);
+    }
+}
@@ -0,0 +1,295 @@
+//! JWT 签发与校验 - 支持 HS256 和 EdDSA（Ed25519）
+//!
+//! [`KeyRing`] 按添加顺序保存签名密钥，最后添加的密钥签发新令牌，较早的
+//! 密钥只用于校验，直到被移除。令牌头部的 `kid` 指明签名所用的密钥。
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::hmac;
+use ring::signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+
+/// 签名算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JwtAlgorithm {
+    HS256,
+    EdDSA,
+}
+
+/// 签名密钥配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningKeyConfig {
+    /// 密钥标识，写入令牌头部的 `kid`
+    pub kid: String,
+    pub algorithm: JwtAlgorithm,
+    /// HS256 为共享密钥原文；EdDSA 为 32 字节种子的 base64 编码
+    pub key: String,
+}
+
+impl SigningKeyConfig {
+    /// 使用随机种子生成 EdDSA 密钥
+    pub fn generate_ed25519(kid: impl Into<String>) -> Self {
+        let seed: [u8; 32] = rand::random();
+        Self {
+            kid: kid.into(),
+            algorithm: JwtAlgorithm::EdDSA,
+            key: STANDARD.encode(seed),
+        }
+    }
+}
+
+/// 令牌用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenUse {
+    Access,
+    Refresh,
+}
+
+/// 令牌声明
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub iss: String,
+    /// 用户ID
+    pub sub: String,
+    /// 令牌ID，用于吊销
+    pub jti: String,
+    pub iat: u64,
+    pub exp: u64,
+    pub role: String,
+    /// 权限字符串，格式见 `Permission::parse`
+    pub permissions: Vec<String>,
+    pub token_use: TokenUse,
+    /// 签发时用户的令牌版本，修改密码后递增使旧令牌失效
+    pub ver: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    alg: JwtAlgorithm,
+    typ: String,
+    kid: String,
+}
+
+/// JWT 错误类型
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("令牌格式错误")]
+    Malformed,
+    #[error("未知的签名密钥: {0}")]
+    UnknownKey(String),
+    #[error("签名算法与密钥不匹配")]
+    AlgorithmMismatch,
+    #[error("签名无效")]
+    InvalidSignature,
+    #[error("密钥无效: {0}")]
+    InvalidKey(String),
+    #[error("没有可用的签名密钥")]
+    NoActiveKey,
+}
+
+enum SigningKey {
+    Hmac(hmac::Key),
+    Ed25519(Box<Ed25519KeyPair>),
+}
+
+impl SigningKey {
+    fn algorithm(&self) -> JwtAlgorithm {
+        match self {
+            Self::Hmac(_) => JwtAlgorithm::HS256,
+            Self::Ed25519(_) => JwtAlgorithm::EdDSA,
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Hmac(key) => hmac::sign(key, message).as_ref().to_vec(),
+            Self::Ed25519(pair) => pair.sign(message).as_ref().to_vec(),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            Self::Hmac(key) => hmac::verify(key, message, signature).is_ok(),
+            Self::Ed25519(pair) => UnparsedPublicKey::new(&signature::ED25519, pair.public_key().as_ref())
+                .verify(message, signature)
+                .is_ok(),
+        }
+    }
+}
+
+/// 签名密钥环
+#[derive(Default)]
+pub struct KeyRing {
+    keys: Vec<(String, SigningKey)>,
+}
+
+impl KeyRing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加密钥并用它签发之后的令牌；同名 `kid` 的旧密钥被替换
+    pub fn add(&mut self, config: &SigningKeyConfig) -> Result<(), JwtError> {
+        if config.kid.is_empty() {
+            return Err(JwtError::InvalidKey("kid 不能为空".to_string()));
+        }
+        let key = match config.algorithm {
+            JwtAlgorithm::HS256 => {
+                if config.key.is_empty() {
+                    return Err(JwtError::InvalidKey(format!("{}: 共享密钥为空", config.kid)));
+                }
+                SigningKey::Hmac(hmac::Key::new(hmac::HMAC_SHA256, config.key.as_bytes()))
+            }
+            JwtAlgorithm::EdDSA => {
+                let seed = STANDARD
+                    .decode(config.key.trim())
+                    .map_err(|e| JwtError::InvalidKey(format!("{}: {e}", config.kid)))?;
+                let pair = Ed25519KeyPair::from_seed_unchecked(&seed)
+                    .map_err(|_| JwtError::InvalidKey(format!("{}: 种子必须为 32 字节", config.kid)))?;
+                SigningKey::Ed25519(Box::new(pair))
+            }
+        };
+        self.keys.retain(|(kid, _)| kid != &config.kid);
+        self.keys.push((config.kid.clone(), key));
+        Ok(())
+    }
+
+    /// 移除密钥，之后由它签发的令牌无法通过校验；不能移除正在签发的密钥
+    pub fn retire(&mut self, kid: &str) -> Result<bool, JwtError> {
+        if self.active_kid() == Some(kid) {
+            return Err(JwtError::InvalidKey(format!("{kid}: 正在用于签发令牌")));
+        }
+        let count = self.keys.len();
+        self.keys.retain(|(existing, _)| existing != kid);
+        Ok(self.keys.len() < count)
+    }
+
+    /// 签发新令牌使用的密钥
+    pub fn active_kid(&self) -> Option<&str> {
+        self.keys.last().map(|(kid, _)| kid.as_str())
+    }
+
+    /// 全部密钥标识，按添加顺序
+    pub fn kids(&self) -> Vec<String> {
+        self.keys.iter().map(|(kid, _)| kid.clone()).collect()
+    }
+
+    /// 用当前密钥签名
+    pub fn sign(&self, claims: &Claims) -> Result<String, JwtError> {
+        let (kid, key) = self.keys.last().ok_or(JwtError::NoActiveKey)?;
+        let header = Header {
+            alg: key.algorithm(),
+            typ: "JWT".to_string(),
+            kid: kid.clone(),
+        };
+        let signing_input = format!("{}.{}", encode_json(&header)?, encode_json(claims)?);
+        let signature = URL_SAFE_NO_PAD.encode(key.sign(signing_input.as_bytes()));
+        Ok(format!("{signing_input}.{signature}"))
+    }
+
+    /// 校验签名并解析声明，不检查过期时间和吊销状态
+    pub fn verify(&self, token: &str) -> Result<Claims, JwtError> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(JwtError::Malformed);
+        };
+        let header: Header = decode_json(header)?;
+        let (_, key) = self
+            .keys
+            .iter()
+            .find(|(kid, _)| kid == &header.kid)
+            .ok_or_else(|| JwtError::UnknownKey(header.kid.clone()))?;
+        // 按密钥而不是头部决定算法，防止算法混淆
+        if key.algorithm() != header.alg {
+            return Err(JwtError::AlgorithmMismatch);
+        }
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| JwtError::Malformed)?;
+        let (signing_input, _) = token.rsplit_once('.').ok_or(JwtError::Malformed)?;
+        if !key.verify(signing_input.as_bytes(), &signature) {
+            return Err(JwtError::InvalidSignature);
+        }
+        decode_json(payload)
+    }
+}
+
+fn encode_json<T: Serialize>(value: &T) -> Result<String, JwtError> {
+    let json = serde_json::to_vec(value).map_err(|_| JwtError::Malformed)?;
+    Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+fn decode_json<T: for<'de> Deserialize<'de>>(part: &str) -> Result<T, JwtError> {
+    let json = URL_SAFE_NO_PAD.decode(part).map_err(|_| JwtError::Malformed)?;
+    serde_json::from_slice(&json).map_err(|_| JwtError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> Claims {
+        Claims {
+            iss: "omni-agent".to_string(),
+            sub: "user-1".to_string(),
+            jti: "token-1".to_string(),
+            iat: 1,
+            exp: 2,
+            role: "user".to_string(),
+            permissions: vec!["read".to_string(), "tool:search".to_string()],
+            token_use: TokenUse::Access,
+            ver: 0,
+        }
+    }
+
+    fn hs256(kid: &str, secret: &str) -> SigningKeyConfig {
+        SigningKeyConfig {
+            kid: kid.to_string(),
+            algorithm: JwtAlgorithm::HS256,
+            key: secret.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_both_algorithms() {
+        for config in [hs256("hs", "secret"), SigningKeyConfig::generate_ed25519("ed")] {
+            let mut keys = KeyRing::new();
+            keys.add(&config).unwrap();
+            let token = keys.sign(&claims()).unwrap();
+            assert_eq!(keys.verify(&token).unwrap(), claims());
+
+            // 篡改声明后签名失效
+            let mut parts: Vec<&str> = token.split('.').collect();
+            let mut forged = claims();
+            forged.role = "admin".to_string();
+            let payload = encode_json(&forged).unwrap();
+            parts[1] = &payload;
+            assert!(matches!(keys.verify(&parts.join(".")), Err(JwtError::InvalidSignature)));
+        }
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_keys_until_retired() {
+        let mut keys = KeyRing::new();
+        keys.add(&hs256("2024", "old-secret")).unwrap();
+        let old_token = keys.sign(&claims()).unwrap();
+
+        keys.add(&SigningKeyConfig::generate_ed25519("2025")).unwrap();
+        assert_eq!(keys.active_kid(), Some("2025"));
+        assert!(keys.verify(&old_token).is_ok());
+        assert!(keys.retire("2025").is_err());
+
+        assert!(keys.retire("2024").unwrap());
+        assert!(matches!(keys.verify(&old_token), Err(JwtError::UnknownKey(_))));
+
+        // 另一密钥环签发的同名密钥令牌无法通过校验
+        let mut other = KeyRing::new();
+        other.add(&hs256("2025", "guess")).unwrap();
+        let forged = other.sign(&claims()).unwrap();
+        assert!(matches!(keys.verify(&forged), Err(JwtError::AlgorithmMismatch)));
+        assert!(matches!(keys.verify("not-a-token"), Err(JwtError::Malformed)));
+    }
+}
@@ -1,11 +1,86 @@
 //! 安全管理模块 - 实现认证、授权和审计功能
+//!
+//! 密码以 argon2id 哈希保存，连续认证失败达到上限后账户被临时锁定。认证
+//! 签发带角色和权限声明的 JWT 访问令牌和刷新令牌，校验只需签名密钥、吊销
+//! 列表和内存中的用户令牌版本。机器客户端使用绑定到服务账户、限定权限
+//! 范围的 API 密钥。用户账户、吊销列表和 API 密钥可以通过
+//! [`SecurityManager::use_store`] 持久化到任意 [`DataRepository`] 后端。
+//! 授权由 [`SecurityManager::authorize`] 统一判断，拒绝记入审计日志；HTTP
+//! 端点所需的权限见 [`RoutePolicy`]。
 
+pub mod api_keys;
+pub mod jwt;
+pub mod password;
+pub mod policy;
+
+pub use api_keys::{api_key_prefix, ApiKey, API_KEY_PREFIX};
+pub use jwt::{Claims, JwtAlgorithm, JwtError, KeyRing, SigningKeyConfig, TokenUse};
+pub use password::{hash_password, verify_password};
+pub use policy::{RouteAccess, RoutePolicy, RouteRule};
+pub use crate::services::audit::AuditLog;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
+
+use crate::services::audit::{action, AuditEvent, AuditTrail};
+use crate::integrations::database::{
+    ApiKeyRecord, DataRepository, RevokedTokenRecord, UserAccountRecord,
+};
+use api_keys::{generate_api_key, hash_api_key};
+use password::{hash_password_blocking, verify_password_blocking};
+
+/// 安全配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// 密码最短长度（字符数）
+    pub min_password_length: usize,
+    /// 连续认证失败多少次后锁定账户，0 表示不锁定
+    pub max_failed_attempts: u32,
+    /// 账户锁定时长（秒）
+    pub lockout_secs: u64,
+    /// HS256 共享密钥，未配置 `signing_keys` 时使用；为空时每次启动随机生成
+    pub jwt_secret: String,
+    /// 令牌签发者（`iss`）
+    pub issuer: String,
+    /// 访问令牌有效期（秒）
+    pub access_token_ttl_secs: u64,
+    /// 刷新令牌有效期（秒）
+    pub refresh_token_ttl_secs: u64,
+    /// 签名密钥，按从旧到新排列，最后一个签发新令牌，其余只用于校验
+    pub signing_keys: Vec<SigningKeyConfig>,
+    /// 是否要求 HTTP 请求携带令牌或 API 密钥，默认开启；关闭时匿名请求只能
+    /// 访问只需 `read` 的端点，仅用于本地开发
+    pub require_auth: bool,
+    /// 路由访问策略，为空时使用 [`RoutePolicy::default`]
+    pub routes: Vec<RouteRule>,
+    /// 是否将用户账户、吊销列表和 API 密钥保存到 `database.url`
+    pub persist: bool,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            min_password_length: 8,
+            max_failed_attempts: 5,
+            lockout_secs: 900,
+            jwt_secret: String::new(),
+            issuer: "omni-agent".to_string(),
+            access_token_ttl_secs: 900,
+            refresh_token_ttl_secs: 7 * 24 * 3600,
+            signing_keys: Vec::new(),
+            require_auth: true,
+            routes: Vec::new(),
+            persist: true,
+        }
+    }
+}
 
 /// 用户角色
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -16,17 +91,101 @@ pub enum UserRole {
     Service,
 }
 
-/// 权限类型
+impl UserRole {
+    /// 角色名称：`admin`、`user`、`guest`、`service`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::User => "user",
+            Self::Guest => "guest",
+            Self::Service => "service",
+        }
+    }
+
+    /// 解析角色名称
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "admin" => Some(Self::Admin),
+            "user" => Some(Self::User),
+            "guest" => Some(Self::Guest),
+            "service" => Some(Self::Service),
+            _ => None,
+        }
+    }
+}
+
+/// 权限类型，序列化为 [`Permission::parse`] 接受的字符串
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
 pub enum Permission {
     Read,
     Write,
     Execute,
     Admin,
     ToolAccess(String),
+    /// 免审批调用匹配模式的敏感工具，模式支持 `*` 通配符
+    ToolApprove(String),
     AgentAccess(String),
 }
 
+impl Permission {
+    /// 解析权限字符串：`read`、`write`、`execute`、`admin`、`tool:<名称>`、
+    /// `tool-approve:<模式>`、`agent:<名称>`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            "execute" => Some(Self::Execute),
+            "admin" => Some(Self::Admin),
+            _ => {
+                if let Some(tool) = value.strip_prefix("tool:") {
+                    Some(Self::ToolAccess(tool.to_string()))
+                } else if let Some(pattern) = value.strip_prefix("tool-approve:") {
+                    Some(Self::ToolApprove(pattern.to_string()))
+                } else {
+                    value
+                        .strip_prefix("agent:")
+                        .map(|agent| Self::AgentAccess(agent.to_string()))
+                }
+            }
+        }
+    }
+
+    /// `granted` 是否包含该权限；`admin` 包含全部权限
+    pub fn granted_by(&self, granted: &[Permission]) -> bool {
+        granted.iter().any(|p| p == self || *p == Permission::Admin)
+    }
+}
+
+impl fmt::Display for Permission {
+    /// 与 [`Permission::parse`] 互逆的字符串形式
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+            Self::Execute => write!(f, "execute"),
+            Self::Admin => write!(f, "admin"),
+            Self::ToolAccess(tool) => write!(f, "tool:{tool}"),
+            Self::ToolApprove(pattern) => write!(f, "tool-approve:{pattern}"),
+            Self::AgentAccess(agent) => write!(f, "agent:{agent}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Permission {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value).ok_or_else(|| format!("未知权限: {value}"))
+    }
+}
+
+impl From<Permission> for String {
+    fn from(permission: Permission) -> Self {
+        permission.to_string()
+    }
+}
+
 /// 用户信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -42,7 +201,7 @@ pub struct User {
 #[derive(Debug, Clone)]
 pub struct Credentials {
     pub username: String,
-    pub password: String, // 在实际实现中应该使用哈希
+    pub password: String,
 }
 
 /// 认证令牌
@@ -52,18 +211,10 @@ pub struct AuthToken {
     pub user_id: String,
     pub expires_at: u64,
     pub permissions: Vec<Permission>,
-}
-
-/// 审计日志条目
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AuditLog {
-    pub id: String,
-    pub user_id: String,
-    pub action: String,
-    pub resource: String,
-    pub timestamp: u64,
-    pub success: bool,
-    pub details: Option<String>,
+    pub role: UserRole,
+    /// 刷新令牌，仅在认证和刷新时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
 }
 
 /// 安全错误类型
@@ -79,154 +230,1018 @@ pub enum SecurityError {
     UserNotFound(String),
     #[error("权限不足: {0}")]
     InsufficientPermissions(String),
+    #[error("API 密钥不存在: {0}")]
+    ApiKeyNotFound(String),
+    #[error("账户已锁定，解锁时间: {0}")]
+    AccountLocked(DateTime<Utc>),
+    #[error("用户名已存在: {0}")]
+    UserExists(String),
+    #[error("密码不符合要求: {0}")]
+    WeakPassword(String),
+    #[error("存储错误: {0}")]
+    Storage(String),
+}
+
+/// 用户账户：用户信息及其凭据状态
+#[derive(Debug, Clone)]
+struct Account {
+    user: User,
+    password_hash: String,
+    failed_attempts: u32,
+    locked_until: Option<u64>,
+    token_version: u32,
+}
+
+impl Account {
+    fn to_record(&self) -> UserAccountRecord {
+        UserAccountRecord {
+            id: self.user.id.clone(),
+            username: self.user.username.clone(),
+            role: self.user.role.as_str().to_string(),
+            permissions: self.user.permissions.iter().map(ToString::to_string).collect(),
+            password_hash: self.password_hash.clone(),
+            failed_attempts: self.failed_attempts as i32,
+            locked_until: self.locked_until.map(to_datetime),
+            created_at: to_datetime(self.user.created_at),
+            last_login: self.user.last_login.map(to_datetime),
+            token_version: self.token_version as i32,
+        }
+    }
+
+    fn from_record(record: UserAccountRecord) -> Result<Self, SecurityError> {
+        let role = UserRole::parse(&record.role)
+            .ok_or_else(|| SecurityError::Storage(format!("未知角色: {}", record.role)))?;
+        let permissions = record
+            .permissions
+            .iter()
+            .map(|p| Permission::parse(p).ok_or_else(|| SecurityError::Storage(format!("未知权限: {p}"))))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            user: User {
+                id: record.id,
+                username: record.username,
+                role,
+                permissions,
+                created_at: record.created_at.timestamp().max(0) as u64,
+                last_login: record.last_login.map(|t| t.timestamp().max(0) as u64),
+            },
+            password_hash: record.password_hash,
+            failed_attempts: record.failed_attempts.max(0) as u32,
+            locked_until: record.locked_until.map(|t| t.timestamp().max(0) as u64),
+            token_version: record.token_version.max(0) as u32,
+        })
+    }
+}
+
+/// 密码尝试被拒绝的原因
+#[derive(Debug)]
+enum AttemptDenied {
+    /// 服务账户没有密码
+    NoPassword,
+    /// 账户锁定到该时间
+    Locked(u64),
+    /// 并发的尝试已用尽失败次数
+    Exhausted,
+    /// 密码错误，`locked` 表示这次失败使账户锁定
+    WrongPassword { locked: bool },
+}
+
+impl AttemptDenied {
+    fn reason(&self) -> &'static str {
+        match self {
+            AttemptDenied::NoPassword => "账户不支持密码登录",
+            AttemptDenied::Locked(_) => "账户已锁定",
+            AttemptDenied::Exhausted => "失败次数已用尽",
+            AttemptDenied::WrongPassword { locked: true } => "密码错误，账户已锁定",
+            AttemptDenied::WrongPassword { locked: false } => "密码错误",
+        }
+    }
+
+    /// 锁定返回 [`SecurityError::AccountLocked`]，其余返回 `rejected` 给出的错误
+    fn into_error(self, rejected: impl FnOnce() -> SecurityError) -> SecurityError {
+        match self {
+            AttemptDenied::Locked(until) => SecurityError::AccountLocked(to_datetime(until)),
+            _ => rejected(),
+        }
+    }
+}
+
+/// 更新 API 密钥的请求，省略的字段保持不变
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiKeyUpdate {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub permissions: Option<Vec<Permission>>,
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+/// API 密钥及其哈希
+#[derive(Debug, Clone)]
+struct StoredApiKey {
+    key: ApiKey,
+    key_hash: String,
+    /// 已写入存储的最后使用时间
+    persisted_last_used: Option<u64>,
+}
+
+impl StoredApiKey {
+    fn to_record(&self) -> Result<ApiKeyRecord, SecurityError> {
+        Ok(ApiKeyRecord {
+            id: uuid::Uuid::parse_str(&self.key.id).map_err(|e| SecurityError::Storage(e.to_string()))?,
+            name: self.key.name.clone(),
+            prefix: self.key.prefix.clone(),
+            key_hash: self.key_hash.clone(),
+            user_id: self.key.user_id.clone(),
+            permissions: self.key.permissions.iter().map(ToString::to_string).collect(),
+            created_at: to_datetime(self.key.created_at),
+            expires_at: self.key.expires_at.map(to_datetime),
+            last_used_at: self.key.last_used_at.map(to_datetime),
+        })
+    }
+
+    fn from_record(record: ApiKeyRecord) -> Result<Self, SecurityError> {
+        let permissions = record
+            .permissions
+            .iter()
+            .map(|p| Permission::parse(p).ok_or_else(|| SecurityError::Storage(format!("未知权限: {p}"))))
+            .collect::<Result<_, _>>()?;
+        let last_used_at = record.last_used_at.map(|t| t.timestamp().max(0) as u64);
+        Ok(Self {
+            key: ApiKey {
+                id: record.id.to_string(),
+                name: record.name,
+                prefix: record.prefix,
+                user_id: record.user_id,
+                permissions,
+                created_at: record.created_at.timestamp().max(0) as u64,
+                expires_at: record.expires_at.map(|t| t.timestamp().max(0) as u64),
+                last_used_at,
+            },
+            key_hash: record.key_hash,
+            persisted_last_used: last_used_at,
+        })
+    }
+}
+
+/// 最后使用时间至少间隔这么多秒才写入存储，避免每个请求都写库
+const LAST_USED_PERSIST_INTERVAL_SECS: u64 = 60;
+
+fn to_datetime(secs: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp(secs as i64, 0).unwrap_or_default()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 /// 安全管理器
 pub struct SecurityManager {
-    users: Arc<RwLock<HashMap<String, User>>>,
-    tokens: Arc<RwLock<HashMap<String, AuthToken>>>,
-    audit_logs: Arc<RwLock<Vec<AuditLog>>>,
-    secret_key: String,
+    users: Arc<RwLock<HashMap<String, Account>>>,
+    /// 按令牌ID索引的吊销记录
+    revoked: Arc<RwLock<HashMap<String, RevokedTokenRecord>>>,
+    /// 按ID索引的 API 密钥
+    api_keys: Arc<RwLock<HashMap<String, StoredApiKey>>>,
+    /// 审计日志，默认只保存在内存中
+    audit: Arc<AuditTrail>,
+    keys: std::sync::RwLock<KeyRing>,
+    config: SecurityConfig,
+    route_policy: RoutePolicy,
+    store: RwLock<Option<Arc<dyn DataRepository>>>,
 }
 
 impl SecurityManager {
-    /// 创建新的安全管理器
+    /// 创建新的安全管理器，`secret_key` 作为 `kid` 为 `default` 的 HS256
+    /// 签名密钥；为空时随机生成，签发的令牌在重启后失效
     pub fn new(secret_key: String) -> Self {
+        let secret_key = if secret_key.is_empty() {
+            let random: [u8; 32] = rand::random();
+            random.iter().map(|b| format!("{b:02x}")).collect()
+        } else {
+            secret_key
+        };
+        let mut keys = KeyRing::new();
+        keys.add(&SigningKeyConfig {
+            kid: "default".to_string(),
+            algorithm: JwtAlgorithm::HS256,
+            key: secret_key,
+        })
+        .expect("非空的 HS256 密钥总是有效");
+
         Self {
             users: Arc::new(RwLock::new(HashMap::new())),
-            tokens: Arc::new(RwLock::new(HashMap::new())),
-            audit_logs: Arc::new(RwLock::new(Vec::new())),
-            secret_key,
+            revoked: Arc::new(RwLock::new(HashMap::new())),
+            api_keys: Arc::new(RwLock::new(HashMap::new())),
+            audit: Arc::new(AuditTrail::default()),
+            keys: std::sync::RwLock::new(keys),
+            config: SecurityConfig::default(),
+            route_policy: RoutePolicy::default(),
+            store: RwLock::new(None),
+        }
+    }
+
+    /// 按配置创建安全管理器，包括签名密钥
+    pub fn from_config(config: SecurityConfig) -> Result<Self, SecurityError> {
+        if config.jwt_secret.is_empty() && config.signing_keys.is_empty() {
+            warn!("⚠️  未配置令牌签名密钥，使用随机密钥，令牌在重启后失效");
+        }
+        let manager = Self::new(config.jwt_secret.clone()).with_config(config);
+        for key in &manager.config.signing_keys {
+            manager.keys_mut().add(key).map_err(invalid_token)?;
         }
+        Ok(manager)
+    }
+
+    /// 设置密码策略、锁定策略和令牌有效期；签名密钥见 [`Self::from_config`]
+    pub fn with_config(mut self, config: SecurityConfig) -> Self {
+        self.route_policy = RoutePolicy::from_rules(&config.routes);
+        self.config = config;
+        self
+    }
+
+    /// 把审计记录写入 `audit`，通常与智能体共用
+    pub fn with_audit(mut self, audit: Arc<AuditTrail>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// 审计日志
+    pub fn audit(&self) -> &Arc<AuditTrail> {
+        &self.audit
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &SecurityConfig {
+        &self.config
+    }
+
+    /// HTTP 端点的访问策略
+    pub fn route_policy(&self) -> &RoutePolicy {
+        &self.route_policy
+    }
+
+    /// 添加签名密钥并用它签发之后的令牌，已签发的令牌仍可用旧密钥校验
+    pub async fn rotate_signing_key(&self, key: SigningKeyConfig) -> Result<(), SecurityError> {
+        self.keys_mut().add(&key).map_err(invalid_token)?;
+        info!("🔑 令牌签名密钥已轮换: {}", key.kid);
+        self.log_audit("", "rotate_signing_key", &key.kid, true, None).await;
+        Ok(())
+    }
+
+    /// 移除旧的签名密钥，由它签发的令牌随即失效
+    pub async fn retire_signing_key(&self, kid: &str) -> Result<bool, SecurityError> {
+        let removed = self.keys_mut().retire(kid).map_err(invalid_token)?;
+        if removed {
+            self.log_audit("", "retire_signing_key", kid, true, None).await;
+        }
+        Ok(removed)
+    }
+
+    /// 签名密钥标识，按从旧到新排列
+    pub fn signing_key_ids(&self) -> Vec<String> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).kids()
+    }
+
+    /// 使用数据仓库持久化用户账户，载入已保存的账户并写入仅在内存中的账户，
+    /// 返回载入的账户数
+    pub async fn use_store(&self, store: Arc<dyn DataRepository>) -> Result<usize, SecurityError> {
+        let records = store.list_user_accounts().await.map_err(storage_error)?;
+        let mut users = self.users.write().await;
+        for account in users.values() {
+            if !records.iter().any(|r| r.id == account.user.id) {
+                store.save_user_account(&account.to_record()).await.map_err(storage_error)?;
+            }
+        }
+        let loaded = records.len();
+        for record in records {
+            let account = Account::from_record(record)?;
+            users.insert(account.user.id.clone(), account);
+        }
+        drop(users);
+
+        let revoked = store.list_revoked_tokens().await.map_err(storage_error)?;
+        let mut revoked_map = self.revoked.write().await;
+        for record in revoked_map.values() {
+            store.save_revoked_token(record).await.map_err(storage_error)?;
+        }
+        for record in revoked {
+            revoked_map.insert(record.jti.clone(), record);
+        }
+        drop(revoked_map);
+
+        let records = store.list_api_keys().await.map_err(storage_error)?;
+        let mut api_keys = self.api_keys.write().await;
+        for stored in api_keys.values() {
+            if !records.iter().any(|r| r.id.to_string() == stored.key.id) {
+                store.save_api_key(&stored.to_record()?).await.map_err(storage_error)?;
+            }
+        }
+        for record in records {
+            let stored = StoredApiKey::from_record(record)?;
+            api_keys.insert(stored.key.id.clone(), stored);
+        }
+        drop(api_keys);
+
+        *self.store.write().await = Some(store);
+        info!("💾 已载入 {} 个用户账户", loaded);
+        Ok(loaded)
     }
 
     /// 注册用户
-    pub async fn register_user(&self, username: &str, _password: &str, role: UserRole) -> Result<String, SecurityError> {
-        let user_id = uuid::Uuid::new_v4().to_string();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| SecurityError::AuthenticationFailed(e.to_string()))?
-            .as_secs();
+    pub async fn register_user(&self, username: &str, password: &str, role: UserRole) -> Result<String, SecurityError> {
+        self.check_password_policy(password)?;
+        let password_hash = hash_password_blocking(password).await?;
 
-        let user = User {
-            id: user_id.clone(),
-            username: username.to_string(),
-            role: role.clone(),
-            permissions: self.get_role_permissions(&role),
-            created_at: now,
-            last_login: None,
+        let mut users = self.users.write().await;
+        if users.values().any(|a| a.user.username == username) {
+            return Err(SecurityError::UserExists(username.to_string()));
+        }
+
+        let user_id = uuid::Uuid::new_v4().to_string();
+        let account = Account {
+            user: User {
+                id: user_id.clone(),
+                username: username.to_string(),
+                role: role.clone(),
+                permissions: self.get_role_permissions(&role),
+                created_at: now_secs(),
+                last_login: None,
+            },
+            password_hash,
+            failed_attempts: 0,
+            locked_until: None,
+            token_version: 0,
         };
+        self.persist(&account).await?;
+        users.insert(user_id.clone(), account);
+        drop(users);
 
-        let mut users = self.users.write().await;
-        users.insert(user_id.clone(), user);
-        
         info!("✅ 用户注册成功: {}", username);
         self.log_audit(&user_id, "register_user", username, true, None).await;
-        
+
         Ok(user_id)
     }
 
     /// 用户认证
+    ///
+    /// 用户不存在和密码错误返回相同的错误；连续失败达到
+    /// `max_failed_attempts` 次后账户锁定 `lockout_secs` 秒。
     pub async fn authenticate(&self, credentials: &Credentials) -> Result<AuthToken, SecurityError> {
-        let users = self.users.read().await;
-        let user = users.values().find(|u| u.username == credentials.username)
-            .ok_or_else(|| SecurityError::AuthenticationFailed("用户不存在".to_string()))?;
+        let rejected = || SecurityError::AuthenticationFailed("用户名或密码错误".to_string());
 
-        // 简化的密码验证（实际实现中应使用哈希）
-        if credentials.password != "password123" { // 仅用于演示
-            self.log_audit(&user.id, "authenticate", &credentials.username, false, Some("密码错误".to_string())).await;
-            return Err(SecurityError::AuthenticationFailed("密码错误".to_string()));
-        }
+        let Some((user_id, reserved)) = self
+            .reserve_attempt(|account| account.user.username == credentials.username)
+            .await
+        else {
+            self.log_audit("", "authenticate", &credentials.username, false, Some("用户不存在".to_string())).await;
+            return Err(rejected());
+        };
+        let password_hash = match reserved {
+            Ok(password_hash) => password_hash,
+            Err(denied) => {
+                self.log_audit(&user_id, "authenticate", &credentials.username, false, Some(denied.reason().to_string())).await;
+                return Err(denied.into_error(rejected));
+            }
+        };
 
-        // 更新最后登录时间
+        let verified = verify_password_blocking(&credentials.password, &password_hash).await;
+        let account = match self
+            .settle_attempt(&user_id, verified, |account| account.user.last_login = Some(now_secs()))
+            .await
+            .map_err(|e| match e {
+                // 验证期间账户被删除
+                SecurityError::UserNotFound(_) => rejected(),
+                e => e,
+            })?
         {
-            let mut users_write = self.users.write().await;
-            if let Some(u) = users_write.get_mut(&user.id) {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .map_err(|e| SecurityError::AuthenticationFailed(e.to_string()))?
-                    .as_secs();
-                u.last_login = Some(now);
+            Ok(account) => account,
+            Err(denied) => {
+                self.log_audit(&user_id, "authenticate", &credentials.username, false, Some(denied.reason().to_string())).await;
+                return Err(denied.into_error(rejected));
             }
-        }
+        };
 
         // 生成认证令牌
-        let token = self.generate_token(&user.id, &user.permissions)?;
+        let token = self.generate_token(&account)?;
         
-        self.log_audit(&user.id, "authenticate", &credentials.username, true, None).await;
+        self.log_audit(&user_id, "authenticate", &credentials.username, true, None).await;
         
         Ok(token)
     }
 
-    /// 验证令牌
+    /// 创建服务账户，权限为空时使用服务角色的默认权限；服务账户没有密码，
+    /// 只能通过 API 密钥认证
+    pub async fn create_service_account(&self, username: &str, permissions: Vec<Permission>) -> Result<String, SecurityError> {
+        let mut users = self.users.write().await;
+        if users.values().any(|a| a.user.username == username) {
+            return Err(SecurityError::UserExists(username.to_string()));
+        }
+
+        let user_id = uuid::Uuid::new_v4().to_string();
+        let permissions = if permissions.is_empty() {
+            self.get_role_permissions(&UserRole::Service)
+        } else {
+            permissions
+        };
+        let account = Account {
+            user: User {
+                id: user_id.clone(),
+                username: username.to_string(),
+                role: UserRole::Service,
+                permissions,
+                created_at: now_secs(),
+                last_login: None,
+            },
+            password_hash: String::new(),
+            failed_attempts: 0,
+            locked_until: None,
+            token_version: 0,
+        };
+        self.persist(&account).await?;
+        users.insert(user_id.clone(), account);
+        drop(users);
+
+        info!("✅ 服务账户创建成功: {}", username);
+        self.log_audit(&user_id, "create_service_account", username, true, None).await;
+        Ok(user_id)
+    }
+
+    /// 为用户创建 API 密钥，返回密钥信息和只在此时可见的完整密钥
+    ///
+    /// 密钥的权限范围不能超出所属用户的权限，管理员除外。
+    pub async fn create_api_key(
+        &self,
+        user_id: &str,
+        name: &str,
+        permissions: Vec<Permission>,
+        expires_at: Option<u64>,
+    ) -> Result<(ApiKey, String), SecurityError> {
+        let owner = self.account(user_id).await?.user;
+        self.check_key_scope(&owner, &permissions)?;
+
+        let (prefix, secret) = generate_api_key();
+        let stored = StoredApiKey {
+            key: ApiKey {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                prefix,
+                user_id: user_id.to_string(),
+                permissions,
+                created_at: now_secs(),
+                expires_at,
+                last_used_at: None,
+            },
+            key_hash: hash_api_key(&secret),
+            persisted_last_used: None,
+        };
+        self.persist_api_key(&stored).await?;
+        let key = stored.key.clone();
+        self.api_keys.write().await.insert(key.id.clone(), stored);
+
+        info!("🔑 已创建 API 密钥 {} ({})", key.prefix, owner.username);
+        self.log_audit(user_id, "create_api_key", &key.prefix, true, None).await;
+        Ok((key, secret))
+    }
+
+    /// 按创建时间顺序列出 API 密钥，可限定所属用户
+    pub async fn list_api_keys(&self, user_id: Option<&str>) -> Vec<ApiKey> {
+        let api_keys = self.api_keys.read().await;
+        let mut result: Vec<ApiKey> = api_keys
+            .values()
+            .map(|stored| stored.key.clone())
+            .filter(|key| user_id.is_none_or(|user_id| key.user_id == user_id))
+            .collect();
+        result.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.prefix.cmp(&b.prefix)));
+        result
+    }
+
+    /// 获取 API 密钥信息
+    pub async fn get_api_key(&self, id: &str) -> Option<ApiKey> {
+        self.api_keys.read().await.get(id).map(|stored| stored.key.clone())
+    }
+
+    /// 修改 API 密钥的名称、权限范围或过期时间
+    pub async fn update_api_key(&self, id: &str, update: ApiKeyUpdate) -> Result<ApiKey, SecurityError> {
+        let current = self
+            .get_api_key(id)
+            .await
+            .ok_or_else(|| SecurityError::ApiKeyNotFound(id.to_string()))?;
+        if let Some(permissions) = &update.permissions {
+            let owner = self.account(&current.user_id).await?.user;
+            self.check_key_scope(&owner, permissions)?;
+        }
+
+        let stored = {
+            let mut api_keys = self.api_keys.write().await;
+            let stored = api_keys
+                .get_mut(id)
+                .ok_or_else(|| SecurityError::ApiKeyNotFound(id.to_string()))?;
+            if let Some(name) = update.name {
+                stored.key.name = name;
+            }
+            if let Some(permissions) = update.permissions {
+                stored.key.permissions = permissions;
+            }
+            if let Some(expires_at) = update.expires_at {
+                stored.key.expires_at = Some(expires_at);
+            }
+            stored.clone()
+        };
+        self.persist_api_key(&stored).await?;
+        self.log_audit(&stored.key.user_id, "update_api_key", &stored.key.prefix, true, None).await;
+        Ok(stored.key)
+    }
+
+    /// 删除 API 密钥，之后使用该密钥的请求立即被拒绝；返回密钥是否存在
+    pub async fn delete_api_key(&self, id: &str) -> Result<bool, SecurityError> {
+        let Some(stored) = self.api_keys.write().await.remove(id) else {
+            return Ok(false);
+        };
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.delete_api_key(&stored.to_record()?.id).await.map_err(storage_error)?;
+        }
+        info!("🗑️  已删除 API 密钥 {}", stored.key.prefix);
+        self.log_audit(&stored.key.user_id, "delete_api_key", &stored.key.prefix, true, None).await;
+        Ok(true)
+    }
+
+    /// 使用 API 密钥认证，返回带密钥权限范围的令牌，令牌的 `token` 字段为
+    /// 密钥的公开前缀
+    ///
+    /// 令牌的权限是密钥权限中所有者当前仍持有的部分；所有者被删除或锁定时
+    /// 密钥不可用。
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<AuthToken, SecurityError> {
+        let rejected = || SecurityError::InvalidToken("API 密钥无效".to_string());
+        let prefix = api_key_prefix(key).ok_or_else(rejected)?;
+        let key_hash = hash_api_key(key);
+        let now = now_secs();
+
+        let found = {
+            let api_keys = self.api_keys.read().await;
+            api_keys
+                .values()
+                .find(|stored| stored.key.prefix == prefix && stored.key_hash == key_hash)
+                .map(|stored| stored.key.clone())
+        };
+        let Some(api_key) = found else {
+            self.log_audit("", "authenticate_api_key", prefix, false, Some("API 密钥无效".to_string())).await;
+            return Err(rejected());
+        };
+        let reason = if api_key.is_expired(now) {
+            "API 密钥已过期"
+        } else {
+            match self.account(&api_key.user_id).await {
+                Err(_) => "API 密钥的所有者不存在",
+                Ok(owner) if owner.locked_until.is_some_and(|until| until > now) => {
+                    "API 密钥的所有者已锁定"
+                }
+                Ok(owner) => match self.touch_api_key(&api_key.id, now).await {
+                    // 验证期间密钥被删除
+                    None => "API 密钥无效",
+                    Some((stored, persist)) => {
+                        if persist {
+                            self.persist_api_key(&stored).await?;
+                        }
+                        let permissions = api_key
+                            .permissions
+                            .into_iter()
+                            .filter(|p| p.granted_by(&owner.user.permissions))
+                            .collect();
+                        return Ok(AuthToken {
+                            token: api_key.prefix,
+                            user_id: owner.user.id,
+                            expires_at: api_key.expires_at.unwrap_or(u64::MAX),
+                            permissions,
+                            role: owner.user.role,
+                            refresh_token: None,
+                        });
+                    }
+                },
+            }
+        };
+        self.log_audit(&api_key.user_id, "authenticate_api_key", prefix, false, Some(reason.to_string())).await;
+        Err(SecurityError::InvalidToken(reason.to_string()))
+    }
+
+    /// 记录密钥的最近使用时间，返回密钥以及是否需要写入存储；密钥已删除时
+    /// 返回 `None`
+    async fn touch_api_key(&self, id: &str, now: u64) -> Option<(StoredApiKey, bool)> {
+        let mut api_keys = self.api_keys.write().await;
+        let stored = api_keys.get_mut(id)?;
+        stored.key.last_used_at = Some(now);
+        let persist = stored
+            .persisted_last_used
+            .is_none_or(|last| now.saturating_sub(last) >= LAST_USED_PERSIST_INTERVAL_SECS);
+        if persist {
+            stored.persisted_last_used = Some(now);
+        }
+        Some((stored.clone(), persist))
+    }
+
+    /// 修改密码，需要提供当前密码；成功后吊销该用户的全部令牌。当前密码错误
+    /// 和登录失败一样计入失败次数，达到上限后账户锁定
+    pub async fn change_password(&self, user_id: &str, current_password: &str, new_password: &str) -> Result<(), SecurityError> {
+        let rejected = || SecurityError::AuthenticationFailed("当前密码错误".to_string());
+        let account = self.account(user_id).await?;
+        let username = account.user.username;
+
+        let Some((_, reserved)) = self.reserve_attempt(|account| account.user.id == user_id).await else {
+            return Err(SecurityError::UserNotFound(user_id.to_string()));
+        };
+        let password_hash = match reserved {
+            Ok(password_hash) => password_hash,
+            Err(denied) => {
+                self.log_audit(user_id, "change_password", &username, false, Some(denied.reason().to_string())).await;
+                return Err(denied.into_error(rejected));
+            }
+        };
+        let verified = verify_password_blocking(current_password, &password_hash).await;
+        if let Err(denied) = self.settle_attempt(user_id, verified, |_| {}).await? {
+            self.log_audit(user_id, "change_password", &username, false, Some(denied.reason().to_string())).await;
+            return Err(denied.into_error(rejected));
+        }
+        self.set_password(user_id, new_password).await?;
+        self.log_audit(user_id, "change_password", &username, true, None).await;
+        Ok(())
+    }
+
+    /// 先把一次密码尝试计为失败，返回账户ID和用于验证的密码哈希，验证通过后
+    /// 由 [`Self::settle_attempt`] 清零；并发的错误尝试用尽次数后不再验证密码，
+    /// 不能绕过锁定。没有匹配的账户时返回 `None`
+    async fn reserve_attempt(
+        &self,
+        matches: impl Fn(&Account) -> bool,
+    ) -> Option<(String, Result<String, AttemptDenied>)> {
+        let mut users = self.users.write().await;
+        let account = users.values_mut().find(|account| matches(account))?;
+        let locked_until = account.locked_until.filter(|until| *until > now_secs());
+        let outcome = if account.password_hash.is_empty() {
+            // 服务账户没有密码，只能使用 API 密钥
+            Err(AttemptDenied::NoPassword)
+        } else if let Some(until) = locked_until {
+            Err(AttemptDenied::Locked(until))
+        } else if self.config.max_failed_attempts > 0
+            && account.failed_attempts >= self.config.max_failed_attempts
+        {
+            Err(AttemptDenied::Exhausted)
+        } else {
+            account.failed_attempts += 1;
+            Ok(account.password_hash.clone())
+        };
+        Some((account.user.id.clone(), outcome))
+    }
+
+    /// 记录密码验证的结果：失败时连续失败达到上限则锁定账户；成功时清零失败
+    /// 次数、执行 `on_success` 并返回更新后的账户
+    async fn settle_attempt(
+        &self,
+        user_id: &str,
+        verified: bool,
+        on_success: impl FnOnce(&mut Account),
+    ) -> Result<Result<Account, AttemptDenied>, SecurityError> {
+        let mut users = self.users.write().await;
+        let account = users
+            .get_mut(user_id)
+            .ok_or_else(|| SecurityError::UserNotFound(user_id.to_string()))?;
+        // 验证期间其他尝试可能已锁定账户
+        let locked_until = account.locked_until.filter(|until| *until > now_secs());
+        if !verified {
+            let locked = locked_until.is_none()
+                && self.config.max_failed_attempts > 0
+                && account.failed_attempts >= self.config.max_failed_attempts;
+            if locked {
+                account.failed_attempts = 0;
+                account.locked_until = Some(now_secs() + self.config.lockout_secs);
+                warn!("🔒 连续认证失败，账户已锁定: {}", account.user.username);
+            }
+            let account = account.clone();
+            drop(users);
+            self.persist(&account).await?;
+            return Ok(Err(AttemptDenied::WrongPassword { locked }));
+        }
+
+        if let Some(until) = locked_until {
+            return Ok(Err(AttemptDenied::Locked(until)));
+        }
+        account.failed_attempts = 0;
+        account.locked_until = None;
+        on_success(account);
+        let account = account.clone();
+        drop(users);
+        self.persist(&account).await?;
+        Ok(Ok(account))
+    }
+
+    /// 管理员重置密码，同时解除锁定并吊销该用户的全部令牌
+    pub async fn reset_password(&self, user_id: &str, new_password: &str) -> Result<(), SecurityError> {
+        let account = self.account(user_id).await?;
+        self.set_password(user_id, new_password).await?;
+        info!("🔑 已重置用户密码: {}", account.user.username);
+        self.log_audit(user_id, "reset_password", &account.user.username, true, None).await;
+        Ok(())
+    }
+
+    /// 解除账户锁定
+    pub async fn unlock_user(&self, user_id: &str) -> Result<(), SecurityError> {
+        let account = {
+            let mut users = self.users.write().await;
+            let account = users.get_mut(user_id)
+                .ok_or_else(|| SecurityError::UserNotFound(user_id.to_string()))?;
+            account.failed_attempts = 0;
+            account.locked_until = None;
+            account.clone()
+        };
+        self.persist(&account).await?;
+        self.log_audit(user_id, "unlock_user", &account.user.username, true, None).await;
+        Ok(())
+    }
+
+    /// 修改用户权限，用户的 API 密钥随即按新权限收窄；已签发的访问令牌在
+    /// 过期或吊销前保留原有权限
+    pub async fn set_user_permissions(&self, user_id: &str, permissions: Vec<Permission>) -> Result<(), SecurityError> {
+        let account = {
+            let mut users = self.users.write().await;
+            let account = users.get_mut(user_id)
+                .ok_or_else(|| SecurityError::UserNotFound(user_id.to_string()))?;
+            account.user.permissions = permissions;
+            account.clone()
+        };
+        self.persist(&account).await?;
+        self.log_audit(user_id, "set_user_permissions", &account.user.username, true, None).await;
+        Ok(())
+    }
+
+    /// 删除用户及其 API 密钥，返回用户是否存在
+    pub async fn delete_user(&self, user_id: &str) -> Result<bool, SecurityError> {
+        let Some(account) = self.users.write().await.remove(user_id) else {
+            return Ok(false);
+        };
+        let keys: Vec<String> = {
+            let api_keys = self.api_keys.read().await;
+            api_keys.values()
+                .filter(|stored| stored.key.user_id == user_id)
+                .map(|stored| stored.key.id.clone())
+                .collect()
+        };
+        for id in keys {
+            self.delete_api_key(&id).await?;
+        }
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.delete_user_account(user_id).await.map_err(storage_error)?;
+        }
+        info!("🗑️  已删除用户 {}", account.user.username);
+        self.log_audit(user_id, "delete_user", &account.user.username, true, None).await;
+        Ok(true)
+    }
+
+    /// 账户是否处于锁定中
+    pub async fn is_locked(&self, user_id: &str) -> Result<bool, SecurityError> {
+        let account = self.account(user_id).await?;
+        Ok(account.locked_until.is_some_and(|until| until > now_secs()))
+    }
+
+    /// 验证访问令牌：签名、签发者、过期时间、吊销列表和用户令牌版本
     pub async fn validate_token(&self, token_str: &str) -> Result<AuthToken, SecurityError> {
-        let tokens = self.tokens.read().await;
-        let token = tokens.get(token_str)
-            .ok_or_else(|| SecurityError::InvalidToken("令牌不存在".to_string()))?;
+        let claims = self.verify_claims(token_str, TokenUse::Access).await?;
+        Ok(AuthToken {
+            token: token_str.to_string(),
+            user_id: claims.sub,
+            expires_at: claims.exp,
+            permissions: claims.permissions.iter().filter_map(|p| Permission::parse(p)).collect(),
+            role: UserRole::parse(&claims.role)
+                .ok_or_else(|| SecurityError::InvalidToken(format!("未知角色: {}", claims.role)))?,
+            refresh_token: None,
+        })
+    }
 
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| SecurityError::InvalidToken(e.to_string()))?
-            .as_secs();
+    /// 用刷新令牌换取新的访问令牌和刷新令牌，旧的刷新令牌随即吊销；新令牌
+    /// 带有用户当前的角色和权限
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<AuthToken, SecurityError> {
+        let claims = self.verify_claims(refresh_token, TokenUse::Refresh).await?;
+        let account = self.account(&claims.sub).await?;
+        if let Some(until) = account.locked_until.filter(|until| *until > now_secs()) {
+            return Err(SecurityError::AccountLocked(to_datetime(until)));
+        }
+        // 并发使用同一个刷新令牌时只有先吊销它的请求换到新令牌
+        if !self.revoke_claims(&claims).await? {
+            return Err(SecurityError::InvalidToken("令牌已吊销".to_string()));
+        }
+        let token = self.generate_token(&account)?;
+        self.log_audit(&claims.sub, "refresh_token", &account.user.username, true, None).await;
+        Ok(token)
+    }
 
-        if token.expires_at < now {
-            return Err(SecurityError::InvalidToken("令牌已过期".to_string()));
+    /// 吊销访问令牌或刷新令牌，已过期的令牌无需吊销
+    pub async fn revoke_token(&self, token_str: &str) -> Result<(), SecurityError> {
+        let claims = self.keys().verify(token_str).map_err(invalid_token)?;
+        if claims.exp > now_secs() {
+            self.revoke_claims(&claims).await?;
         }
+        self.log_audit(&claims.sub, "revoke_token", &claims.jti, true, None).await;
+        Ok(())
+    }
 
-        Ok(token.clone())
+    /// 吊销用户已签发的全部令牌
+    pub async fn revoke_user_tokens(&self, user_id: &str) -> Result<(), SecurityError> {
+        let account = {
+            let mut users = self.users.write().await;
+            let account = users.get_mut(user_id)
+                .ok_or_else(|| SecurityError::UserNotFound(user_id.to_string()))?;
+            account.token_version += 1;
+            account.clone()
+        };
+        self.persist(&account).await?;
+        self.log_audit(user_id, "revoke_user_tokens", &account.user.username, true, None).await;
+        Ok(())
     }
 
     /// 权限检查
     pub async fn check_permission(&self, token: &AuthToken, required_permission: &Permission) -> Result<(), SecurityError> {
-        if token.permissions.contains(required_permission) {
-            Ok(())
-        } else {
-            Err(SecurityError::InsufficientPermissions("权限不足".to_string()))
+        self.authorize(&token.user_id, &token.permissions, required_permission, &required_permission.to_string())
+            .await
+    }
+
+    /// 检查 `granted` 是否包含 `required`，拒绝时以 `resource` 记录审计日志
+    pub async fn authorize(
+        &self,
+        user_id: &str,
+        granted: &[Permission],
+        required: &Permission,
+        resource: &str,
+    ) -> Result<(), SecurityError> {
+        if required.granted_by(granted) {
+            return Ok(());
         }
+        warn!("🚫 权限不足: {} 需要 {} 访问 {}", user_id, required, resource);
+        self.record_denial(user_id, resource, format!("需要权限 {required}")).await;
+        Err(SecurityError::InsufficientPermissions(format!("需要权限 {required}")))
+    }
+
+    /// 记录被拒绝的访问
+    pub async fn record_denial(&self, user_id: &str, resource: &str, reason: String) {
+        self.log_audit(user_id, action::ACCESS_DENIED, resource, false, Some(reason)).await;
     }
 
     /// 获取用户信息
     pub async fn get_user(&self, user_id: &str) -> Result<User, SecurityError> {
+        self.account(user_id).await.map(|account| account.user)
+    }
+
+    /// 按用户名查找用户
+    pub async fn find_user_by_username(&self, username: &str) -> Option<User> {
+        let users = self.users.read().await;
+        users.values()
+            .find(|a| a.user.username == username)
+            .map(|a| a.user.clone())
+    }
+
+    /// 按创建时间顺序列出全部用户
+    pub async fn list_users(&self) -> Vec<User> {
+        let users = self.users.read().await;
+        let mut result: Vec<User> = users.values().map(|a| a.user.clone()).collect();
+        result.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.username.cmp(&b.username)));
+        result
+    }
+
+    /// 添加权限到用户
+    pub async fn add_permission(&self, user_id: &str, permission: Permission) -> Result<(), SecurityError> {
+        let account = {
+            let mut users = self.users.write().await;
+            let account = users.get_mut(user_id)
+                .ok_or_else(|| SecurityError::UserNotFound("用户不存在".to_string()))?;
+
+            if !account.user.permissions.contains(&permission) {
+                account.user.permissions.push(permission.clone());
+            }
+            account.clone()
+        };
+        self.persist(&account).await?;
+
+        // 已签发的令牌保留原有权限声明，刷新后获得新权限
+        info!("✅ 为用户添加权限: {} {:?}", user_id, permission);
+        Ok(())
+    }
+
+    async fn account(&self, user_id: &str) -> Result<Account, SecurityError> {
         let users = self.users.read().await;
         users.get(user_id)
             .cloned()
             .ok_or_else(|| SecurityError::UserNotFound("用户不存在".to_string()))
     }
 
-    /// 添加权限到用户
-    pub async fn add_permission(&self, user_id: &str, permission: Permission) -> Result<(), SecurityError> {
-        let mut users = self.users.write().await;
-        let user = users.get_mut(user_id)
-            .ok_or_else(|| SecurityError::UserNotFound("用户不存在".to_string()))?;
+    fn check_password_policy(&self, password: &str) -> Result<(), SecurityError> {
+        if password.chars().count() < self.config.min_password_length {
+            return Err(SecurityError::WeakPassword(format!(
+                "至少需要 {} 个字符",
+                self.config.min_password_length
+            )));
+        }
+        Ok(())
+    }
+
+    /// 替换密码哈希，解除锁定并吊销令牌
+    async fn set_password(&self, user_id: &str, new_password: &str) -> Result<(), SecurityError> {
+        self.check_password_policy(new_password)?;
+        let password_hash = hash_password_blocking(new_password).await?;
+        let account = {
+            let mut users = self.users.write().await;
+            let account = users.get_mut(user_id)
+                .ok_or_else(|| SecurityError::UserNotFound(user_id.to_string()))?;
+            account.password_hash = password_hash;
+            account.failed_attempts = 0;
+            account.locked_until = None;
+            account.token_version += 1;
+            account.clone()
+        };
+        self.persist(&account).await
+    }
+
+    fn keys(&self) -> std::sync::RwLockReadGuard<'_, KeyRing> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn keys_mut(&self) -> std::sync::RwLockWriteGuard<'_, KeyRing> {
+        self.keys.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 校验令牌签名、用途、签发者、过期时间、吊销状态和用户令牌版本
+    async fn verify_claims(&self, token_str: &str, token_use: TokenUse) -> Result<Claims, SecurityError> {
+        let claims = self.keys().verify(token_str).map_err(invalid_token)?;
+        if claims.token_use != token_use {
+            return Err(SecurityError::InvalidToken("令牌用途不匹配".to_string()));
+        }
+        if claims.iss != self.config.issuer {
+            return Err(SecurityError::InvalidToken("签发者不匹配".to_string()));
+        }
+        if claims.exp <= now_secs() {
+            return Err(SecurityError::InvalidToken("令牌已过期".to_string()));
+        }
+        if self.revoked.read().await.contains_key(&claims.jti) {
+            return Err(SecurityError::InvalidToken("令牌已吊销".to_string()));
+        }
+        let current = self.users.read().await.get(&claims.sub).map(|a| a.token_version);
+        if current != Some(claims.ver) {
+            return Err(SecurityError::InvalidToken("令牌已吊销".to_string()));
+        }
+        Ok(claims)
+    }
 
-        if !user.permissions.contains(&permission) {
-            user.permissions.push(permission.clone());
+    /// 吊销令牌，返回是否由这次调用吊销；检查和吊销在同一把锁内完成，配置了
+    /// 存储时还要求存储中此前没有该令牌，多个实例共用存储时同样只有一次成功
+    async fn revoke_claims(&self, claims: &Claims) -> Result<bool, SecurityError> {
+        let record = RevokedTokenRecord {
+            jti: claims.jti.clone(),
+            user_id: claims.sub.clone(),
+            expires_at: to_datetime(claims.exp),
+        };
+        {
+            let mut revoked = self.revoked.write().await;
+            if revoked.contains_key(&record.jti) {
+                return Ok(false);
+            }
+            revoked.insert(record.jti.clone(), record.clone());
+        }
+        // 存储失败时令牌仍在内存中保持吊销
+        match self.store.read().await.as_ref() {
+            Some(store) => store.save_revoked_token(&record).await.map_err(storage_error),
+            None => Ok(true),
         }
+    }
 
-        // 如果用户有活跃令牌，更新令牌权限
-        self.update_user_tokens(user_id, &user.permissions).await;
+    fn check_key_scope(&self, owner: &User, permissions: &[Permission]) -> Result<(), SecurityError> {
+        match permissions.iter().find(|p| !p.granted_by(&owner.permissions)) {
+            Some(missing) => Err(SecurityError::InsufficientPermissions(format!(
+                "用户 {} 没有权限 {}",
+                owner.username, missing
+            ))),
+            None => Ok(()),
+        }
+    }
 
-        info!("✅ 为用户添加权限: {} {:?}", user_id, permission);
+    async fn persist_api_key(&self, stored: &StoredApiKey) -> Result<(), SecurityError> {
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.save_api_key(&stored.to_record()?).await.map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    async fn persist(&self, account: &Account) -> Result<(), SecurityError> {
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.save_user_account(&account.to_record()).await.map_err(storage_error)?;
+        }
         Ok(())
     }
 
     /// 记录审计日志
     async fn log_audit(&self, user_id: &str, action: &str, resource: &str, success: bool, details: Option<String>) {
-        let log = AuditLog {
-            id: uuid::Uuid::new_v4().to_string(),
-            user_id: user_id.to_string(),
-            action: action.to_string(),
-            resource: resource.to_string(),
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0),
-            success,
-            details,
-        };
-
-        let mut logs = self.audit_logs.write().await;
-        logs.push(log);
+        let mut event = AuditEvent::new(action, resource).user(user_id);
+        event.success = success;
+        event.details = details;
+        self.audit.record(event).await;
     }
 
     /// 获取角色默认权限
@@ -254,74 +1269,64 @@ impl SecurityManager {
         }
     }
 
-    /// 生成认证令牌
-    fn generate_token(&self, user_id: &str, permissions: &[Permission]) -> Result<AuthToken, SecurityError> {
-        let token = format!("{}-{}", user_id, uuid::Uuid::new_v4());
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| SecurityError::AuthenticationFailed(e.to_string()))?
-            .as_secs();
-
-        let expires_at = now + 3600; // 1小时过期
-
-        let auth_token = AuthToken {
-            token: token.clone(),
-            user_id: user_id.to_string(),
-            expires_at,
-            permissions: permissions.to_vec(),
+    /// 签发访问令牌和刷新令牌
+    fn generate_token(&self, account: &Account) -> Result<AuthToken, SecurityError> {
+        let now = now_secs();
+        let claims = |token_use, ttl| Claims {
+            iss: self.config.issuer.clone(),
+            sub: account.user.id.clone(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            iat: now,
+            exp: now + ttl,
+            role: account.user.role.as_str().to_string(),
+            permissions: account.user.permissions.iter().map(ToString::to_string).collect(),
+            token_use,
+            ver: account.token_version,
         };
+        let access = claims(TokenUse::Access, self.config.access_token_ttl_secs);
+        let refresh = claims(TokenUse::Refresh, self.config.refresh_token_ttl_secs);
 
-        // 存储令牌
-        tokio::spawn({
-            let tokens = Arc::clone(&self.tokens);
-            let auth_token = auth_token.clone();
-            async move {
-                let mut tokens = tokens.write().await;
-                tokens.insert(token, auth_token);
-            }
-        });
-
-        Ok(auth_token)
+        let keys = self.keys();
+        Ok(AuthToken {
+            token: keys.sign(&access).map_err(invalid_token)?,
+            user_id: account.user.id.clone(),
+            expires_at: access.exp,
+            permissions: account.user.permissions.clone(),
+            role: account.user.role.clone(),
+            refresh_token: Some(keys.sign(&refresh).map_err(invalid_token)?),
+        })
     }
 
-    /// 更新用户令牌权限
-    async fn update_user_tokens(&self, user_id: &str, permissions: &[Permission]) {
-        let mut tokens = self.tokens.write().await;
-        for token in tokens.values_mut() {
-            if token.user_id == user_id {
-                token.permissions = permissions.to_vec();
-            }
-        }
-    }
-
-    /// 获取审计日志
+    /// 获取内存中最近的审计日志，最新的在前；完整记录见 [`Self::audit`]
     pub async fn get_audit_logs(&self, limit: Option<usize>) -> Vec<AuditLog> {
-        let logs = self.audit_logs.read().await;
-        let mut result = logs.clone();
-        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
-        match limit {
-            Some(n) => result.into_iter().take(n).collect(),
-            None => result,
-        }
+        self.audit.recent(limit).await
     }
 
-    /// 清理过期令牌
+    /// 清理已过期令牌的吊销记录，返回清理的条数
     pub async fn cleanup_expired_tokens(&self) -> Result<usize, SecurityError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| SecurityError::InvalidToken(e.to_string()))?
-            .as_secs();
-
-        let mut tokens = self.tokens.write().await;
-        let count = tokens.len();
-        tokens.retain(|_, token| token.expires_at > now);
-        let removed = count - tokens.len();
+        let now = to_datetime(now_secs());
+        let mut revoked = self.revoked.write().await;
+        let count = revoked.len();
+        revoked.retain(|_, record| record.expires_at > now);
+        let removed = count - revoked.len();
+        drop(revoked);
+
+        if let Some(store) = self.store.read().await.as_ref() {
+            store.delete_expired_revoked_tokens(now).await.map_err(storage_error)?;
+        }
         
         Ok(removed)
     }
 }
 
+fn storage_error(error: crate::integrations::database::RepositoryError) -> SecurityError {
+    SecurityError::Storage(error.to_string())
+}
+
+fn invalid_token(error: JwtError) -> SecurityError {
+    SecurityError::InvalidToken(error.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -342,29 +1347,302 @@ mod tests {
     #[tokio::test]
     async fn test_authentication() {
         let manager = SecurityManager::new("test_secret".to_string());
-        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
-        
-        let credentials = Credentials {
-            username: "testuser".to_string(),
-            password: "password123".to_string(),
-        };
+        manager.register_user("testuser", "s3cret-pass", UserRole::User).await.unwrap();
         
-        let token = manager.authenticate(&credentials).await;
+        let token = manager.authenticate(&credentials("testuser", "s3cret-pass")).await;
         assert!(token.is_ok());
+
+        // 旧的演示密码不再被接受
+        let result = manager.authenticate(&credentials("testuser", "password123")).await;
+        assert!(matches!(result, Err(SecurityError::AuthenticationFailed(_))));
+        let result = manager.authenticate(&credentials("nobody", "s3cret-pass")).await;
+        assert!(matches!(result, Err(SecurityError::AuthenticationFailed(_))));
     }
 
     #[tokio::test]
     async fn test_permission_check() {
         let manager = SecurityManager::new("test_secret".to_string());
-        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
-        
-        let credentials = Credentials {
-            username: "testuser".to_string(),
-            password: "password123".to_string(),
-        };
+        manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
         
-        let token = manager.authenticate(&credentials).await.unwrap();
+        let token = manager.authenticate(&credentials("testuser", "password123")).await.unwrap();
         let result = manager.check_permission(&token, &Permission::Read).await;
         assert!(result.is_ok());
+
+        // 拒绝记入审计日志，管理员拥有全部权限
+        let search = Permission::ToolAccess("search".to_string());
+        let result = manager.check_permission(&token, &search).await;
+        assert!(matches!(result, Err(SecurityError::InsufficientPermissions(_))));
+        let denials = manager.get_audit_logs(Some(10)).await;
+        assert!(denials.iter().any(|log| log.action == "access_denied" && log.resource == "tool:search" && !log.success));
+        assert!(search.granted_by(&[Permission::Admin]));
+        assert!(!Permission::Admin.granted_by(std::slice::from_ref(&search)));
+    }
+
+    #[tokio::test]
+    async fn test_registration_rules() {
+        let manager = SecurityManager::new("test_secret".to_string());
+        manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+
+        let result = manager.register_user("testuser", "password456", UserRole::User).await;
+        assert!(matches!(result, Err(SecurityError::UserExists(_))));
+        let result = manager.register_user("other", "short", UserRole::User).await;
+        assert!(matches!(result, Err(SecurityError::WeakPassword(_))));
+    }
+
+    #[tokio::test]
+    async fn test_lockout_after_failed_attempts() {
+        let manager = SecurityManager::new("test_secret".to_string()).with_config(SecurityConfig {
+            max_failed_attempts: 2,
+            ..SecurityConfig::default()
+        });
+        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+
+        for _ in 0..2 {
+            let result = manager.authenticate(&credentials("testuser", "wrong-password")).await;
+            assert!(matches!(result, Err(SecurityError::AuthenticationFailed(_))));
+        }
+        assert!(manager.is_locked(&user_id).await.unwrap());
+
+        // 锁定期间正确的密码也被拒绝
+        let result = manager.authenticate(&credentials("testuser", "password123")).await;
+        assert!(matches!(result, Err(SecurityError::AccountLocked(_))));
+
+        manager.unlock_user(&user_id).await.unwrap();
+        assert!(manager.authenticate(&credentials("testuser", "password123")).await.is_ok());
+        assert!(manager.get_user(&user_id).await.unwrap().last_login.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_attempts_respect_lockout() {
+        let manager = Arc::new(SecurityManager::new("test_secret".to_string()).with_config(SecurityConfig {
+            max_failed_attempts: 3,
+            ..SecurityConfig::default()
+        }));
+        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+
+        // 并发的错误尝试中只有 3 次验证了密码，其余直接被拒绝
+        let attempts: Vec<_> = (0..10)
+            .map(|_| {
+                let manager = manager.clone();
+                tokio::spawn(async move { manager.authenticate(&credentials("testuser", "wrong-password")).await })
+            })
+            .collect();
+        for attempt in attempts {
+            assert!(attempt.await.unwrap().is_err());
+        }
+        assert!(manager.is_locked(&user_id).await.unwrap());
+        let verified = manager
+            .get_audit_logs(None)
+            .await
+            .into_iter()
+            .filter(|log| log.details.as_deref().is_some_and(|details| details.starts_with("密码错误")))
+            .count();
+        assert_eq!(verified, 3);
+
+        let result = manager.authenticate(&credentials("testuser", "password123")).await;
+        assert!(matches!(result, Err(SecurityError::AccountLocked(_))));
+    }
+
+    #[tokio::test]
+    async fn test_change_and_reset_password() {
+        let manager = SecurityManager::new("test_secret".to_string()).with_config(SecurityConfig {
+            max_failed_attempts: 3,
+            ..SecurityConfig::default()
+        });
+        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+
+        let result = manager.change_password(&user_id, "wrong-password", "new-password").await;
+        assert!(matches!(result, Err(SecurityError::AuthenticationFailed(_))));
+        manager.change_password(&user_id, "password123", "new-password").await.unwrap();
+        assert!(manager.authenticate(&credentials("testuser", "password123")).await.is_err());
+        assert!(manager.authenticate(&credentials("testuser", "new-password")).await.is_ok());
+
+        // 当前密码错误和登录失败一起计数，不能借修改密码绕过锁定
+        manager.authenticate(&credentials("testuser", "wrong-password")).await.unwrap_err();
+        for _ in 0..2 {
+            let result = manager.change_password(&user_id, "wrong-password", "other-password").await;
+            assert!(matches!(result, Err(SecurityError::AuthenticationFailed(_))));
+        }
+        assert!(manager.is_locked(&user_id).await.unwrap());
+        let result = manager.change_password(&user_id, "new-password", "other-password").await;
+        assert!(matches!(result, Err(SecurityError::AccountLocked(_))));
+
+        manager.reset_password(&user_id, "reset-password").await.unwrap();
+        assert!(manager.authenticate(&credentials("testuser", "reset-password")).await.is_ok());
+        let actions: Vec<String> = manager.get_audit_logs(None).await.into_iter().map(|log| log.action).collect();
+        assert!(actions.contains(&"reset_password".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_signed_jwts() {
+        let manager = SecurityManager::new("test_secret".to_string());
+        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+
+        // 签发后立即可以校验
+        let issued = manager.authenticate(&credentials("testuser", "password123")).await.unwrap();
+        assert_eq!(issued.token.split('.').count(), 3);
+        let validated = manager.validate_token(&issued.token).await.unwrap();
+        assert_eq!(validated.user_id, user_id);
+        assert_eq!(validated.role, UserRole::User);
+        assert_eq!(validated.permissions, issued.permissions);
+
+        // 其他密钥签发的令牌和刷新令牌不能当作访问令牌
+        let other = SecurityManager::new("other_secret".to_string());
+        assert!(other.validate_token(&issued.token).await.is_err());
+        let refresh = issued.refresh_token.unwrap();
+        assert!(manager.validate_token(&refresh).await.is_err());
+
+        let expiring = SecurityManager::new("test_secret".to_string()).with_config(SecurityConfig {
+            access_token_ttl_secs: 0,
+            ..SecurityConfig::default()
+        });
+        expiring.register_user("testuser", "password123", UserRole::User).await.unwrap();
+        let token = expiring.authenticate(&credentials("testuser", "password123")).await.unwrap();
+        assert!(matches!(expiring.validate_token(&token.token).await, Err(SecurityError::InvalidToken(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_and_revocation() {
+        let manager = Arc::new(SecurityManager::new("test_secret".to_string()));
+        let user_id = manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+        let issued = manager.authenticate(&credentials("testuser", "password123")).await.unwrap();
+        let refresh = issued.refresh_token.clone().unwrap();
+
+        // 刷新后获得新权限，旧刷新令牌只能使用一次
+        manager.add_permission(&user_id, Permission::ToolAccess("search".to_string())).await.unwrap();
+        let refreshed = manager.refresh_token(&refresh).await.unwrap();
+        assert!(refreshed.permissions.contains(&Permission::ToolAccess("search".to_string())));
+        assert!(manager.refresh_token(&refresh).await.is_err());
+
+        manager.revoke_token(&refreshed.token).await.unwrap();
+        assert!(manager.validate_token(&refreshed.token).await.is_err());
+        assert!(manager.validate_token(&issued.token).await.is_ok());
+
+        // 修改密码使之前签发的全部令牌失效
+        manager.change_password(&user_id, "password123", "new-password").await.unwrap();
+        assert!(manager.validate_token(&issued.token).await.is_err());
+        assert!(manager.refresh_token(refreshed.refresh_token.as_deref().unwrap()).await.is_err());
+        let issued = manager.authenticate(&credentials("testuser", "new-password")).await.unwrap();
+        assert!(manager.validate_token(&issued.token).await.is_ok());
+
+        // 并发使用同一个刷新令牌，只有一个请求成功
+        let refresh = issued.refresh_token.clone().unwrap();
+        let attempts: Vec<_> = (0..8)
+            .map(|_| {
+                let (manager, refresh) = (manager.clone(), refresh.clone());
+                tokio::spawn(async move { manager.refresh_token(&refresh).await })
+            })
+            .collect();
+        let mut succeeded = 0;
+        for attempt in attempts {
+            succeeded += attempt.await.unwrap().is_ok() as usize;
+        }
+        assert_eq!(succeeded, 1);
+
+        manager.revoke_user_tokens(&user_id).await.unwrap();
+        assert!(manager.validate_token(&issued.token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signing_key_rotation() {
+        let manager = SecurityManager::new("test_secret".to_string());
+        manager.register_user("testuser", "password123", UserRole::User).await.unwrap();
+        let old = manager.authenticate(&credentials("testuser", "password123")).await.unwrap();
+
+        manager.rotate_signing_key(SigningKeyConfig::generate_ed25519("2025-01")).await.unwrap();
+        assert_eq!(manager.signing_key_ids(), ["default", "2025-01"]);
+        let new = manager.authenticate(&credentials("testuser", "password123")).await.unwrap();
+        assert!(manager.validate_token(&old.token).await.is_ok());
+        assert!(manager.validate_token(&new.token).await.is_ok());
+
+        assert!(manager.retire_signing_key("2025-01").await.is_err());
+        assert!(manager.retire_signing_key("default").await.unwrap());
+        assert!(manager.validate_token(&old.token).await.is_err());
+        assert!(manager.validate_token(&new.token).await.is_ok());
+    }
+
+    #[test]
+    fn test_permission_serializes_as_string() {
+        let permissions = vec![
+            Permission::Read,
+            Permission::ToolAccess("search".to_string()),
+            Permission::ToolApprove("delete_*".to_string()),
+        ];
+        let json = serde_json::to_value(&permissions).unwrap();
+        assert_eq!(json, serde_json::json!(["read", "tool:search", "tool-approve:delete_*"]));
+        assert_eq!(serde_json::from_value::<Vec<Permission>>(json).unwrap(), permissions);
+        assert!(serde_json::from_value::<Permission>(serde_json::json!("root")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let manager = SecurityManager::new("test_secret".to_string());
+        let search = Permission::ToolAccess("search".to_string());
+        let service_id = manager.create_service_account("ci", vec![Permission::Read, search.clone()]).await.unwrap();
+
+        // 服务账户不能使用密码登录，密钥范围不能超出账户权限
+        assert!(manager.authenticate(&credentials("ci", "")).await.is_err());
+        let result = manager.create_api_key(&service_id, "deploy", vec![Permission::Admin], None).await;
+        assert!(matches!(result, Err(SecurityError::InsufficientPermissions(_))));
+
+        let (key, secret) = manager.create_api_key(&service_id, "deploy", vec![search.clone()], None).await.unwrap();
+        assert!(secret.starts_with(&key.prefix));
+        let token = manager.authenticate_api_key(&secret).await.unwrap();
+        assert_eq!(token.user_id, service_id);
+        assert_eq!(token.role, UserRole::Service);
+        assert_eq!(token.permissions, std::slice::from_ref(&search));
+        assert!(manager.get_api_key(&key.id).await.unwrap().last_used_at.is_some());
+
+        // 前缀正确但密钥错误、过期和删除后的密钥都被拒绝
+        let forged = format!("{}_{}", key.prefix, "x".repeat(43));
+        assert!(manager.authenticate_api_key(&forged).await.is_err());
+        let expired = ApiKeyUpdate {
+            expires_at: Some(now_secs()),
+            ..ApiKeyUpdate::default()
+        };
+        manager.update_api_key(&key.id, expired).await.unwrap();
+        assert!(manager.authenticate_api_key(&secret).await.is_err());
+        assert!(manager.delete_api_key(&key.id).await.unwrap());
+        assert!(!manager.delete_api_key(&key.id).await.unwrap());
+        assert!(manager.list_api_keys(Some(&service_id)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_follow_owner() {
+        let manager = SecurityManager::new("test_secret".to_string()).with_config(SecurityConfig {
+            max_failed_attempts: 1,
+            ..SecurityConfig::default()
+        });
+        let search = Permission::ToolAccess("search".to_string());
+        let user_id = manager.register_user("alice", "password123", UserRole::User).await.unwrap();
+        let mut permissions = manager.get_user(&user_id).await.unwrap().permissions;
+        permissions.push(search.clone());
+        manager.set_user_permissions(&user_id, permissions.clone()).await.unwrap();
+        let (_, secret) = manager
+            .create_api_key(&user_id, "cli", vec![Permission::Read, search.clone()], None)
+            .await
+            .unwrap();
+
+        // 所有者失去的权限不再经由密钥生效
+        permissions.retain(|p| *p != search);
+        manager.set_user_permissions(&user_id, permissions).await.unwrap();
+        let token = manager.authenticate_api_key(&secret).await.unwrap();
+        assert_eq!(token.permissions, [Permission::Read]);
+
+        // 所有者锁定或删除后密钥不可用
+        assert!(manager.authenticate(&credentials("alice", "wrong-password")).await.is_err());
+        assert!(manager.authenticate_api_key(&secret).await.is_err());
+        manager.unlock_user(&user_id).await.unwrap();
+        assert!(manager.authenticate_api_key(&secret).await.is_ok());
+        assert!(manager.delete_user(&user_id).await.unwrap());
+        assert!(manager.authenticate_api_key(&secret).await.is_err());
+        assert!(manager.list_api_keys(Some(&user_id)).await.is_empty());
+    }
+
+    fn credentials(username: &str, password: &str) -> Credentials {
+        Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,59 @@
+//! 密码哈希 - 使用 argon2id 生成和校验 PHC 格式的密码哈希
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::rngs::OsRng;
+
+use super::SecurityError;
+
+/// 使用随机盐生成密码哈希
+pub fn hash_password(password: &str) -> Result<String, SecurityError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| SecurityError::Storage(format!("密码哈希失败: {e}")))
+}
+
+/// 校验密码，哈希格式无效时视为不匹配
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// 在阻塞线程池中校验密码，避免 argon2 计算阻塞异步运行时
+pub async fn verify_password_blocking(password: &str, hash: &str) -> bool {
+    let (password, hash) = (password.to_string(), hash.to_string());
+    tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+        .await
+        .unwrap_or(false)
+}
+
+/// 在阻塞线程池中生成密码哈希
+pub async fn hash_password_blocking(password: &str) -> Result<String, SecurityError> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| SecurityError::Storage(format!("密码哈希失败: {e}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "password123"));
+        // 每次哈希使用不同的盐
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+    }
+}
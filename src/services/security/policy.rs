@@ -0,0 +1,182 @@
+//! 路由访问策略 - 把 HTTP 端点映射到所需权限
+//!
+//! 规则按顺序匹配，第一条匹配的规则生效。路径按 `/` 分段比较：`*` 匹配任意
+//! 一段，末尾的 `**` 匹配其余零段或多段。没有规则匹配的请求需要 `admin`
+//! 权限。
+
+use serde::{Deserialize, Serialize};
+
+use super::Permission;
+
+/// 一条路由规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteRule {
+    /// HTTP 方法，省略时匹配全部方法
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// 路径模式，例如 `/sessions/*` 或 `/admin/**`
+    pub path: String,
+    /// 所需权限，为 `null` 时端点公开，不需要凭据
+    pub permission: Option<Permission>,
+}
+
+impl RouteRule {
+    /// 需要 `permission` 的规则
+    pub fn new(method: Option<&str>, path: &str, permission: Permission) -> Self {
+        Self {
+            method: method.map(str::to_string),
+            path: path.to_string(),
+            permission: Some(permission),
+        }
+    }
+
+    /// 公开端点的规则
+    pub fn public(method: Option<&str>, path: &str) -> Self {
+        Self {
+            method: method.map(str::to_string),
+            path: path.to_string(),
+            permission: None,
+        }
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        self.method
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(method))
+            && path_matches(&self.path, path)
+    }
+}
+
+/// 端点的访问要求
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteAccess {
+    /// 不需要凭据
+    Public,
+    /// 需要持有该权限
+    Requires(Permission),
+}
+
+/// 路由访问策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutePolicy {
+    rules: Vec<RouteRule>,
+}
+
+impl Default for RoutePolicy {
+    /// 内置策略：查询需要 `read`，对话、A2A 消息和运行工作流需要 `execute`，
+    /// 查看审计日志、审批、查看和修改路由规则、解释路由以及启停能力需要
+    /// `admin`，其余修改需要 `write`
+    fn default() -> Self {
+        Self::new(vec![
+            RouteRule::public(Some("GET"), "/health"),
+            RouteRule::public(Some("POST"), "/auth/login"),
+            RouteRule::public(Some("POST"), "/auth/refresh"),
+            RouteRule::new(Some("POST"), "/auth/logout", Permission::Read),
+            RouteRule::new(None, "/admin/**", Permission::Admin),
+            RouteRule::new(Some("GET"), "/audit/**", Permission::Admin),
+            RouteRule::new(Some("POST"), "/tasks/*/approve", Permission::Admin),
+            RouteRule::new(Some("POST"), "/tasks/*/deny", Permission::Admin),
+            RouteRule::new(None, "/routing/rules", Permission::Admin),
+            RouteRule::new(None, "/routing/rules/*", Permission::Admin),
+            RouteRule::new(Some("POST"), "/routing/explain", Permission::Admin),
+            RouteRule::new(Some("PUT"), "/capabilities/*/enabled", Permission::Admin),
+            RouteRule::new(Some("POST"), "/chat", Permission::Execute),
+            RouteRule::new(Some("POST"), "/messages", Permission::Execute),
+            RouteRule::new(Some("POST"), "/workflows/*/run", Permission::Execute),
+            RouteRule::new(Some("POST"), "/workflow-runs/*/rerun", Permission::Execute),
+            RouteRule::new(Some("GET"), "/**", Permission::Read),
+            RouteRule::new(None, "/**", Permission::Write),
+        ])
+    }
+}
+
+impl RoutePolicy {
+    pub fn new(rules: Vec<RouteRule>) -> Self {
+        Self { rules }
+    }
+
+    /// 使用配置的规则，未配置时使用内置策略
+    pub fn from_rules(rules: &[RouteRule]) -> Self {
+        if rules.is_empty() {
+            Self::default()
+        } else {
+            Self::new(rules.to_vec())
+        }
+    }
+
+    pub fn rules(&self) -> &[RouteRule] {
+        &self.rules
+    }
+
+    /// 请求的访问要求
+    pub fn access(&self, method: &str, path: &str) -> RouteAccess {
+        match self.rules.iter().find(|rule| rule.matches(method, path)) {
+            Some(RouteRule {
+                permission: Some(permission),
+                ..
+            }) => RouteAccess::Requires(permission.clone()),
+            Some(_) => RouteAccess::Public,
+            None => RouteAccess::Requires(Permission::Admin),
+        }
+    }
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut segments = path.trim_matches('/').split('/').filter(|s| !s.is_empty());
+    let mut parts = pattern.trim_matches('/').split('/').filter(|s| !s.is_empty());
+    loop {
+        match (parts.next(), segments.next()) {
+            (Some("**"), _) => return true,
+            (Some("*"), Some(_)) => {}
+            (Some(part), Some(segment)) if part == segment => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = RoutePolicy::default();
+        let requires = |permission| RouteAccess::Requires(permission);
+
+        assert_eq!(policy.access("GET", "/health"), RouteAccess::Public);
+        assert_eq!(policy.access("POST", "/health"), requires(Permission::Write));
+        assert_eq!(policy.access("post", "/auth/login"), RouteAccess::Public);
+        assert_eq!(policy.access("GET", "/sessions/abc"), requires(Permission::Read));
+        assert_eq!(policy.access("POST", "/chat"), requires(Permission::Execute));
+        assert_eq!(policy.access("POST", "/messages"), requires(Permission::Execute));
+        assert_eq!(policy.access("DELETE", "/sessions/abc"), requires(Permission::Write));
+        assert_eq!(policy.access("POST", "/tasks/t1/approve"), requires(Permission::Admin));
+        assert_eq!(policy.access("GET", "/tasks/t1/approval"), requires(Permission::Read));
+        assert_eq!(policy.access("GET", "/admin/api-keys/1"), requires(Permission::Admin));
+        assert_eq!(policy.access("GET", "/admin"), requires(Permission::Admin));
+        assert_eq!(policy.access("GET", "/audit"), requires(Permission::Admin));
+        assert_eq!(policy.access("GET", "/audit/export"), requires(Permission::Admin));
+        for method in ["GET", "PUT", "POST"] {
+            assert_eq!(policy.access(method, "/routing/rules"), requires(Permission::Admin));
+        }
+        assert_eq!(policy.access("GET", "/routing/rules/r1"), requires(Permission::Admin));
+        assert_eq!(policy.access("DELETE", "/routing/rules/r1"), requires(Permission::Admin));
+        assert_eq!(policy.access("POST", "/routing/explain"), requires(Permission::Admin));
+        assert_eq!(policy.access("POST", "/messages/m1/feedback"), requires(Permission::Write));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        assert!(path_matches("/sessions/*", "/sessions/abc"));
+        assert!(!path_matches("/sessions/*", "/sessions"));
+        assert!(!path_matches("/sessions/*", "/sessions/abc/llm"));
+        assert!(path_matches("/sessions/**", "/sessions/abc/llm"));
+        assert!(path_matches("/", "/"));
+        assert!(!path_matches("/chat", "/chatter"));
+
+        // 未匹配任何规则的请求需要管理员权限
+        let policy = RoutePolicy::new(vec![RouteRule::new(Some("GET"), "/info", Permission::Read)]);
+        assert_eq!(policy.access("GET", "/sessions"), RouteAccess::Requires(Permission::Admin));
+    }
+}
@@ -0,0 +1,158 @@
+//! 工具审批策略 - 判断哪些工具调用在执行前需要人工批准
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::manifest::ToolAnnotations;
+use crate::services::security::Permission;
+
+/// 敏感工具的审批策略
+///
+/// 工具名匹配任一模式，或 MCP 服务器将其标注为破坏性操作时视为敏感。
+/// 持有匹配工具名的 `Permission::ToolApprove(模式)` 或 `Permission::Admin`
+/// 的调用方视为已预先批准；`ToolAccess` 只允许调用，不免除审批。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// 工具名模式，支持 `*` 通配符，如 `delete_*`、`*_admin`
+    #[serde(default)]
+    pub tool_patterns: Vec<String>,
+    /// 是否根据 MCP 工具注解中的 `destructiveHint` 判断
+    #[serde(default = "default_honor_destructive_hint")]
+    pub honor_destructive_hint: bool,
+}
+
+fn default_honor_destructive_hint() -> bool {
+    true
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            tool_patterns: Vec::new(),
+            honor_destructive_hint: true,
+        }
+    }
+}
+
+impl ApprovalPolicy {
+    /// 创建不拦截任何工具的策略
+    pub fn disabled() -> Self {
+        Self {
+            tool_patterns: Vec::new(),
+            honor_destructive_hint: false,
+        }
+    }
+
+    /// 添加工具名模式
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.tool_patterns.push(pattern.into());
+        self
+    }
+
+    /// 判断工具是否敏感，返回原因
+    pub fn sensitivity(&self, tool: &str, annotations: Option<&ToolAnnotations>) -> Option<String> {
+        if let Some(pattern) = self
+            .tool_patterns
+            .iter()
+            .find(|pattern| matches_pattern(pattern, tool))
+        {
+            return Some(format!("工具名匹配敏感模式 `{pattern}`"));
+        }
+        if self.honor_destructive_hint && annotations.is_some_and(ToolAnnotations::is_destructive) {
+            return Some("MCP 服务器将该工具标注为破坏性操作".to_string());
+        }
+        None
+    }
+
+    /// 判断调用是否需要人工批准，返回原因；已授权的调用方返回 `None`
+    pub fn requires_approval(
+        &self,
+        tool: &str,
+        annotations: Option<&ToolAnnotations>,
+        granted: &[Permission],
+    ) -> Option<String> {
+        let reason = self.sensitivity(tool, annotations)?;
+        let pre_approved = granted.iter().any(|permission| match permission {
+            Permission::Admin => true,
+            Permission::ToolApprove(pattern) => matches_pattern(pattern, tool),
+            _ => false,
+        });
+        (!pre_approved).then_some(reason)
+    }
+}
+
+/// 简单通配符匹配，`*` 匹配任意长度的字符
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if name.len() < first.len() + last.len() || !name.starts_with(first) || !name.ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches_pattern("delete_*", "delete_file"));
+        assert!(matches_pattern("*_admin", "user_admin"));
+        assert!(matches_pattern("*drop*", "dropdb"));
+        assert!(matches_pattern("run", "run"));
+        assert!(!matches_pattern("run", "run_shell"));
+        assert!(!matches_pattern("a*a", "a"));
+    }
+
+    #[test]
+    fn test_policy_sources() {
+        let policy = ApprovalPolicy::default().with_pattern("delete_*");
+        assert!(policy.requires_approval("delete_file", None, &[]).is_some());
+        assert!(policy.requires_approval("read_file", None, &[]).is_none());
+
+        let destructive = ToolAnnotations {
+            destructive_hint: Some(true),
+            ..Default::default()
+        };
+        let read_only = ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        };
+        assert!(policy
+            .requires_approval("wipe", Some(&destructive), &[])
+            .is_some());
+        assert!(policy
+            .requires_approval("list", Some(&read_only), &[])
+            .is_none());
+        assert!(ApprovalPolicy::disabled()
+            .requires_approval("wipe", Some(&destructive), &[])
+            .is_none());
+
+        // 调用权限不免除审批，免审批权限才视为预先批准
+        let granted = [Permission::ToolAccess("delete_file".to_string())];
+        assert!(policy
+            .requires_approval("delete_file", None, &granted)
+            .is_some());
+        let granted = [Permission::ToolApprove("delete_f*".to_string())];
+        assert!(policy
+            .requires_approval("delete_file", None, &granted)
+            .is_none());
+        assert!(policy
+            .requires_approval("delete_dir", None, &granted)
+            .is_some());
+        assert!(policy
+            .requires_approval("delete_dir", None, &[Permission::Admin])
+            .is_none());
+    }
+}
@@ -5,10 +5,12 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use serde_json::Value;
 use tokio::sync::{RwLock, Semaphore};
-use tracing::info;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 use uuid::Uuid;
 
-use super::{Tool, ToolError};
+use super::{ApprovalPolicy, Tool, ToolError};
+use crate::services::security::{Permission, SecurityManager};
 
 /// 工具执行阶段
 #[derive(Debug, Clone)]
@@ -29,6 +31,7 @@ pub enum ExecutionStatus {
     Running(ExecutionPhase),
     Completed,
     Failed(String),
+    Cancelled,
 }
 
 /// 执行上下文
@@ -58,6 +61,8 @@ pub struct EnhancedToolExecutionEngine {
     execution_cache: Arc<RwLock<HashMap<String, (Value, Instant)>>>,
     semaphore: Arc<Semaphore>,
     cache_ttl: Duration,
+    approval_policy: ApprovalPolicy,
+    security: Option<Arc<SecurityManager>>,
 }
 
 impl EnhancedToolExecutionEngine {
@@ -68,9 +73,24 @@ impl EnhancedToolExecutionEngine {
             execution_cache: Arc::new(RwLock::new(HashMap::new())),
             semaphore: Arc::new(Semaphore::new(max_concurrent)),
             cache_ttl,
+            approval_policy: ApprovalPolicy::default().with_pattern("*admin*"),
+            security: None,
         }
     }
 
+    /// 要求调用方持有 `tool:<名称>` 或 `admin` 权限才能执行工具，拒绝记入
+    /// `security` 的审计日志
+    pub fn with_security(mut self, security: Arc<SecurityManager>) -> Self {
+        self.security = Some(security);
+        self
+    }
+
+    /// 设置敏感工具的审批策略
+    pub fn with_approval_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval_policy = policy;
+        self
+    }
+
     /// 注册工具
     pub async fn register_tool(&self, tool: Arc<dyn Tool>) -> Result<(), ToolError> {
         let mut tools = self.tools.write().await;
@@ -86,6 +106,47 @@ impl EnhancedToolExecutionEngine {
         tool_name: &str,
         parameters: Value,
         context: ExecutionContext,
+    ) -> Result<ExecutionResult, ToolError> {
+        self.execute_tool_with_cancel(tool_name, parameters, context, &CancellationToken::new(), None)
+            .await
+    }
+
+    /// 执行工具，`cancel` 触发或超过 `timeout` 时中止等待和执行
+    pub async fn execute_tool_with_cancel(
+        &self,
+        tool_name: &str,
+        parameters: Value,
+        context: ExecutionContext,
+        cancel: &CancellationToken,
+        timeout: Option<Duration>,
+    ) -> Result<ExecutionResult, ToolError> {
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                warn!("🛑 工具执行已取消: {}", tool_name);
+                Err(ToolError::Cancelled(tool_name.to_string()))
+            }
+            _ = deadline => {
+                warn!("⌛ 工具执行超时: {}", tool_name);
+                Err(ToolError::Timeout(tool_name.to_string()))
+            }
+            result = self.run_lifecycle(tool_name, parameters, context) => result,
+        }
+    }
+
+    /// 依次执行8个生命周期阶段
+    async fn run_lifecycle(
+        &self,
+        tool_name: &str,
+        parameters: Value,
+        context: ExecutionContext,
     ) -> Result<ExecutionResult, ToolError> {
         let execution_id = Uuid::new_v4().to_string();
         info!("🚀 开始执行工具: {} (ID: {})", tool_name, execution_id);
@@ -96,7 +157,7 @@ impl EnhancedToolExecutionEngine {
         self.validate_input(tool_name, &parameters)?;
 
         // 阶段2：权限检查
-        self.check_permissions(tool_name, &context)?;
+        self.check_permissions(tool_name, &context).await?;
 
         // 阶段3：并发控制
         let _permit = self.semaphore.acquire().await
@@ -149,10 +210,39 @@ impl EnhancedToolExecutionEngine {
         Ok(())
     }
 
-    /// 权限检查
-    fn check_permissions(&self, tool_name: &str, context: &ExecutionContext) -> Result<(), ToolError> {
-        if tool_name.contains("admin") && !context.permissions.contains(&"admin".to_string()) {
-            return Err(ToolError::PermissionDenied("需要管理员权限".to_string()));
+    /// 权限检查：设置了安全管理器时，调用方必须持有 `tool:<名称>` 或 `admin`
+    /// 权限，否则返回 `PermissionDenied`；敏感工具在缺少这些权限时返回
+    /// `ApprovalRequired`，由调用方取得批准后带上授权重试
+    async fn check_permissions(&self, tool_name: &str, context: &ExecutionContext) -> Result<(), ToolError> {
+        let annotations = self
+            .tools
+            .read()
+            .await
+            .get(tool_name)
+            .and_then(|tool| tool.annotations());
+        let granted: Vec<Permission> = context
+            .permissions
+            .iter()
+            .filter_map(|permission| Permission::parse(permission))
+            .collect();
+
+        if let Some(security) = &self.security {
+            let required = Permission::ToolAccess(tool_name.to_string());
+            security
+                .authorize(&context.user_id, &granted, &required, &required.to_string())
+                .await
+                .map_err(|e| ToolError::PermissionDenied(e.to_string()))?;
+        }
+
+        if let Some(reason) = self
+            .approval_policy
+            .requires_approval(tool_name, annotations.as_ref(), &granted)
+        {
+            warn!("✋ 工具需要人工批准: {} ({})", tool_name, reason);
+            return Err(ToolError::ApprovalRequired {
+                tool: tool_name.to_string(),
+                reason,
+            });
         }
         info!("✅ 权限检查通过");
         Ok(())
@@ -266,4 +356,127 @@ mod tests {
         assert!(matches!(execution.status, ExecutionStatus::Completed));
         assert!(execution.result.is_some());
     }
+
+    struct AdminTool;
+
+    #[async_trait::async_trait]
+    impl Tool for AdminTool {
+        fn name(&self) -> &str {
+            "reset_admin"
+        }
+
+        fn description(&self) -> &str {
+            "Sensitive admin tool"
+        }
+
+        async fn execute(&self, _parameters: Value) -> Result<Value, ToolError> {
+            Ok(json!({"reset": true}))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_tool_requires_approval() {
+        let engine = EnhancedToolExecutionEngine::new(5, Duration::from_secs(300));
+        engine.register_tool(Arc::new(AdminTool)).await.unwrap();
+        let context = |permissions: &[&str]| ExecutionContext {
+            user_id: "test_user".to_string(),
+            session_id: "test_session".to_string(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            max_concurrent: 5,
+            cache_ttl: Duration::from_secs(300),
+        };
+
+        for granted in ["read", "tool:reset_admin"] {
+            let result = engine.execute_tool("reset_admin", json!({}), context(&[granted])).await;
+            assert!(matches!(result, Err(ToolError::ApprovalRequired { .. })));
+        }
+
+        // 获批后以免审批授权重试
+        for granted in ["tool-approve:reset_*", "admin"] {
+            let result = engine.execute_tool("reset_admin", json!({}), context(&[granted])).await;
+            assert!(matches!(result.unwrap().status, ExecutionStatus::Completed));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_access_enforced_with_security() {
+        let security = Arc::new(SecurityManager::new("test_secret".to_string()));
+        let engine = EnhancedToolExecutionEngine::new(5, Duration::from_secs(300))
+            .with_security(security.clone());
+        engine.register_tool(Arc::new(MockTool)).await.unwrap();
+        let context = |permissions: &[&str]| ExecutionContext {
+            user_id: "test_user".to_string(),
+            session_id: "test_session".to_string(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            max_concurrent: 5,
+            cache_ttl: Duration::from_secs(300),
+        };
+
+        let result = engine.execute_tool("mock_tool", json!({}), context(&["execute", "tool:other"])).await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+        let logs = security.get_audit_logs(None).await;
+        assert!(logs.iter().any(|log| log.user_id == "test_user" && log.resource == "tool:mock_tool" && !log.success));
+
+        for granted in ["tool:mock_tool", "admin"] {
+            let result = engine.execute_tool("mock_tool", json!({"n": granted}), context(&[granted])).await;
+            assert!(matches!(result.unwrap().status, ExecutionStatus::Completed));
+        }
+    }
+
+    struct HangingTool;
+
+    #[async_trait::async_trait]
+    impl Tool for HangingTool {
+        fn name(&self) -> &str {
+            "hanging_tool"
+        }
+
+        fn description(&self) -> &str {
+            "Tool that never finishes"
+        }
+
+        async fn execute(&self, _parameters: Value) -> Result<Value, ToolError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_execution_cancel_and_timeout() {
+        let engine = Arc::new(EnhancedToolExecutionEngine::new(1, Duration::from_secs(300)));
+        engine.register_tool(Arc::new(HangingTool)).await.unwrap();
+        let context = ExecutionContext {
+            user_id: "test_user".to_string(),
+            session_id: "test_session".to_string(),
+            permissions: vec![],
+            max_concurrent: 1,
+            cache_ttl: Duration::from_secs(300),
+        };
+
+        let result = engine
+            .execute_tool_with_cancel(
+                "hanging_tool",
+                json!({}),
+                context.clone(),
+                &CancellationToken::new(),
+                Some(Duration::from_millis(20)),
+            )
+            .await;
+        assert!(matches!(result, Err(ToolError::Timeout(_))));
+
+        let cancel = CancellationToken::new();
+        let task = {
+            let engine = engine.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                engine
+                    .execute_tool_with_cancel("hanging_tool", json!({}), context, &cancel, None)
+                    .await
+            })
+        };
+        cancel.cancel();
+        assert!(matches!(task.await.unwrap(), Err(ToolError::Cancelled(_))));
+
+        // 被中止的执行释放了并发许可
+        assert_eq!(engine.semaphore.available_permits(), 1);
+    }
 }
\ No newline at end of file
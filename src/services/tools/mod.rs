@@ -1,14 +1,19 @@
 //! 工具执行引擎模块 - 实现8阶段生命周期
 
+pub mod approval;
 pub mod enhanced_engine;
 
 use std::collections::HashMap;
+use std::future::Future;
 use serde_json::Value;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+pub use approval::ApprovalPolicy;
 pub use enhanced_engine::*;
 
+use crate::protocol::manifest::ToolAnnotations;
+
 /// 工具错误
 #[derive(Debug, thiserror::Error)]
 pub enum ToolError {
@@ -24,6 +29,12 @@ pub enum ToolError {
     ConcurrencyLimitExceeded(String),
     #[error("缓存错误: {0}")]
     CacheError(String),
+    #[error("工具执行已取消: {0}")]
+    Cancelled(String),
+    #[error("工具执行超时: {0}")]
+    Timeout(String),
+    #[error("工具 {tool} 需要人工批准: {reason}")]
+    ApprovalRequired { tool: String, reason: String },
 }
 
 /// 工具 trait
@@ -32,6 +43,42 @@ pub trait Tool: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     async fn execute(&self, parameters: Value) -> Result<Value, ToolError>;
+
+    /// 工具行为注解，用于判断是否需要人工批准
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        None
+    }
+
+    /// 参数的 JSON Schema，注册为能力时使用
+    fn input_schema(&self) -> Option<Value> {
+        None
+    }
+}
+
+/// 工具调用所属的用户和会话
+///
+/// 智能体调用本地工具时通过 [`ToolContext::scope`] 设置，工具在执行期间用
+/// [`ToolContext::current`] 读取，参数由 LLM 生成，不能用来指定用户。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolContext {
+    pub user_id: Option<String>,
+    pub session_id: Option<String>,
+}
+
+tokio::task_local! {
+    static TOOL_CONTEXT: ToolContext;
+}
+
+impl ToolContext {
+    /// 在该上下文中执行 `future`
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        TOOL_CONTEXT.scope(self, future).await
+    }
+
+    /// 当前的工具调用上下文，不在 [`ToolContext::scope`] 中时返回 `None`
+    pub fn current() -> Option<ToolContext> {
+        TOOL_CONTEXT.try_with(Clone::clone).ok()
+    }
 }
 
 /// 简化版工具执行引擎（向后兼容）
@@ -39,6 +86,18 @@ pub struct ToolExecutionEngine {
     tools: Arc<RwLock<HashMap<String, Arc<dyn Tool>>>>,
 }
 
+impl std::fmt::Debug for ToolExecutionEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolExecutionEngine").finish_non_exhaustive()
+    }
+}
+
+impl Default for ToolExecutionEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ToolExecutionEngine {
     pub fn new() -> Self {
         Self {
@@ -53,6 +112,19 @@ impl ToolExecutionEngine {
         Ok(())
     }
 
+    /// 已注册的工具，按名称排序
+    pub async fn list_tools(&self) -> Vec<Arc<dyn Tool>> {
+        let tools = self.tools.read().await;
+        let mut list: Vec<Arc<dyn Tool>> = tools.values().cloned().collect();
+        list.sort_by(|a, b| a.name().cmp(b.name()));
+        list
+    }
+
+    /// 按名称查找工具
+    pub async fn get_tool(&self, tool_name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.read().await.get(tool_name).cloned()
+    }
+
     /// 执行工具
     pub async fn execute_tool(
         &self,
@@ -0,0 +1,291 @@
+//! HNSW 近似最近邻索引
+//!
+//! 分层小世界图：每个节点随机分配层数，高层稀疏、低层稠密。查询从最高层的入口
+//! 贪心下降到第 0 层，再在第 0 层做宽度为 `ef` 的最佳优先搜索。删除只做标记，
+//! 被标记的节点仍参与遍历但不出现在结果中。
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::services::embeddings::cosine_similarity;
+
+/// 每个节点在第 1 层及以上保留的邻居数
+const M: usize = 16;
+/// 第 0 层保留的邻居数
+const M0: usize = 2 * M;
+const EF_CONSTRUCTION: usize = 100;
+const EF_SEARCH: usize = 64;
+
+struct Node {
+    vector: Vec<f32>,
+    /// 每层的邻居
+    neighbors: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// 按距离排序的节点
+#[derive(Clone, Copy, PartialEq)]
+struct Scored {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+pub(crate) struct HnswIndex {
+    nodes: Vec<Node>,
+    entry: Option<usize>,
+    max_level: usize,
+    deleted: usize,
+    level_mult: f64,
+    rng: StdRng,
+}
+
+impl HnswIndex {
+    pub(crate) fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            entry: None,
+            max_level: 0,
+            deleted: 0,
+            level_mult: 1.0 / (M as f64).ln(),
+            // 固定种子，同样的插入顺序得到同样的图
+            rng: StdRng::seed_from_u64(0x5eed),
+        }
+    }
+
+    /// 未删除的节点数
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len() - self.deleted
+    }
+
+    /// 被标记删除的节点数
+    pub(crate) fn deleted(&self) -> usize {
+        self.deleted
+    }
+
+    /// 插入向量，返回节点编号
+    pub(crate) fn insert(&mut self, vector: Vec<f32>) -> usize {
+        let level = (-self.rng.gen_range(f64::EPSILON..1.0).ln() * self.level_mult) as usize;
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            vector,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(node);
+            self.max_level = level;
+            return node;
+        };
+
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.greedy(node, entry, layer);
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let found = self.search_layer(&self.nodes[node].vector, &[entry], EF_CONSTRUCTION, layer);
+            entry = found[0].node;
+            let max = if layer == 0 { M0 } else { M };
+            let selected: Vec<usize> = found.iter().take(M).map(|scored| scored.node).collect();
+            for &neighbor in &selected {
+                self.nodes[neighbor].neighbors[layer].push(node);
+                if self.nodes[neighbor].neighbors[layer].len() > max {
+                    self.prune(neighbor, layer, max);
+                }
+            }
+            self.nodes[node].neighbors[layer] = selected;
+        }
+
+        if level > self.max_level {
+            self.entry = Some(node);
+            self.max_level = level;
+        }
+        node
+    }
+
+    /// 标记删除节点
+    pub(crate) fn remove(&mut self, node: usize) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            if !node.deleted {
+                node.deleted = true;
+                self.deleted += 1;
+            }
+        }
+    }
+
+    /// 最相似的 `limit` 个未删除节点及其余弦相似度，最相似的在前
+    pub(crate) fn search(&self, query: &[f32], limit: usize) -> Vec<(usize, f32)> {
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+        if limit == 0 {
+            return Vec::new();
+        }
+        for layer in (1..=self.max_level).rev() {
+            entry = self.greedy_to(query, entry, layer);
+        }
+        // 被删除的节点占据搜索宽度，按删除比例放宽
+        let ef = EF_SEARCH.max(limit) + self.deleted.min(self.nodes.len());
+        self.search_layer(query, &[entry], ef, 0)
+            .into_iter()
+            .filter(|scored| !self.nodes[scored.node].deleted)
+            .take(limit)
+            .map(|scored| (scored.node, 1.0 - scored.distance))
+            .collect()
+    }
+
+    fn distance(&self, query: &[f32], node: usize) -> f32 {
+        1.0 - cosine_similarity(query, &self.nodes[node].vector)
+    }
+
+    fn greedy(&self, node: usize, entry: usize, layer: usize) -> usize {
+        self.greedy_to(&self.nodes[node].vector, entry, layer)
+    }
+
+    /// 在一层中沿着更近的邻居移动，直到没有更近的邻居
+    fn greedy_to(&self, query: &[f32], mut current: usize, layer: usize) -> usize {
+        let mut best = self.distance(query, current);
+        loop {
+            let mut moved = false;
+            for &neighbor in &self.nodes[current].neighbors[layer] {
+                let distance = self.distance(query, neighbor);
+                if distance < best {
+                    best = distance;
+                    current = neighbor;
+                    moved = true;
+                }
+            }
+            if !moved {
+                return current;
+            }
+        }
+    }
+
+    /// 一层内的最佳优先搜索，返回最近的 `ef` 个节点，最近的在前
+    fn search_layer(&self, query: &[f32], entries: &[usize], ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        // 待扩展的节点，最近的先出
+        let mut candidates: BinaryHeap<std::cmp::Reverse<Scored>> = BinaryHeap::new();
+        // 已找到的节点，最远的先出
+        let mut found: BinaryHeap<Scored> = BinaryHeap::new();
+        for &node in entries {
+            let scored = Scored {
+                distance: self.distance(query, node),
+                node,
+            };
+            candidates.push(std::cmp::Reverse(scored));
+            found.push(scored);
+        }
+
+        while let Some(std::cmp::Reverse(current)) = candidates.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|far| current.distance > far.distance) {
+                break;
+            }
+            let Some(neighbors) = self.nodes[current.node].neighbors.get(layer) else {
+                continue;
+            };
+            for &neighbor in neighbors {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let scored = Scored {
+                    distance: self.distance(query, neighbor),
+                    node: neighbor,
+                };
+                if found.len() < ef || found.peek().is_some_and(|far| scored < *far) {
+                    candidates.push(std::cmp::Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    /// 只保留节点在一层中最近的 `max` 个邻居
+    fn prune(&mut self, node: usize, layer: usize, max: usize) {
+        let vector = &self.nodes[node].vector;
+        let mut scored: Vec<Scored> = self.nodes[node].neighbors[layer]
+            .iter()
+            .map(|&neighbor| Scored {
+                distance: 1.0 - cosine_similarity(vector, &self.nodes[neighbor].vector),
+                node: neighbor,
+            })
+            .collect();
+        scored.sort();
+        self.nodes[node].neighbors[layer] = scored.into_iter().take(max).map(|s| s.node).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..count)
+            .map(|_| (0..dimensions).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_matches_exact_neighbors() {
+        let vectors = random_vectors(500, 16);
+        let mut index = HnswIndex::new();
+        for vector in &vectors {
+            index.insert(vector.clone());
+        }
+
+        let mut hits = 0;
+        for query in vectors.iter().take(50) {
+            let mut exact: Vec<(usize, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(node, vector)| (node, cosine_similarity(query, vector)))
+                .collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let expected: HashSet<usize> = exact.iter().take(5).map(|(node, _)| *node).collect();
+            hits += index
+                .search(query, 5)
+                .iter()
+                .filter(|(node, _)| expected.contains(node))
+                .count();
+        }
+        // 召回率至少 90%
+        assert!(hits >= 225, "recall too low: {hits}/250");
+    }
+
+    #[test]
+    fn test_deleted_nodes_are_not_returned() {
+        let vectors = random_vectors(50, 8);
+        let mut index = HnswIndex::new();
+        for vector in &vectors {
+            index.insert(vector.clone());
+        }
+        index.remove(3);
+        assert_eq!(index.len(), 49);
+        let results = index.search(&vectors[3], 10);
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(|(node, _)| *node != 3));
+    }
+}
@@ -0,0 +1,595 @@
+//! 向量存储 - 保存向量及其载荷，按相似度检索
+//!
+//! [`LocalVectorStore`] 在进程内检索，可选精确扫描或 HNSW 近似索引，配置了文件
+//! 路径时每次写入追加到日志文件、日志较长时合并为快照，启动时载入快照、重放
+//! 日志并重建索引。[`QdrantStore`] 使用兼容 Qdrant 的 HTTP 接口。相似度均为
+//! 余弦相似度，越大越相似。
+
+mod hnsw;
+mod qdrant;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::services::embeddings::cosine_similarity;
+use hnsw::HnswIndex;
+
+pub use qdrant::QdrantStore;
+
+/// 向量存储错误
+#[derive(Debug, thiserror::Error)]
+pub enum VectorStoreError {
+    #[error("IO错误: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON错误: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("HTTP错误: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("向量存储返回 {status}: {body}")]
+    Api { status: u16, body: String },
+    #[error("向量维度不一致: 期望 {expected}，实际 {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+}
+
+/// 一条向量记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorRecord {
+    pub id: String,
+    pub vector: Vec<f32>,
+    /// 随向量保存的数据，例如原文和来源
+    #[serde(default)]
+    pub payload: Map<String, Value>,
+}
+
+impl VectorRecord {
+    pub fn new(id: impl Into<String>, vector: Vec<f32>) -> Self {
+        Self {
+            id: id.into(),
+            vector,
+            payload: Map::new(),
+        }
+    }
+
+    pub fn with_payload(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.payload.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// 检索结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorMatch {
+    pub id: String,
+    /// 与查询向量的余弦相似度
+    pub score: f32,
+    pub payload: Map<String, Value>,
+}
+
+/// 载荷过滤条件，所有字段都相等时匹配
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VectorFilter {
+    pub equals: Map<String, Value>,
+}
+
+impl VectorFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eq(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.equals.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn matches(&self, payload: &Map<String, Value>) -> bool {
+        self.equals
+            .iter()
+            .all(|(key, value)| payload.get(key) == Some(value))
+    }
+}
+
+/// 向量存储
+#[async_trait]
+pub trait VectorStore: fmt::Debug + Send + Sync {
+    /// 写入记录，同一ID的记录被替换
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<(), VectorStoreError>;
+
+    /// 与 `vector` 最相似的 `limit` 条记录，最相似的在前
+    async fn search(
+        &self,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<&VectorFilter>,
+    ) -> Result<Vec<VectorMatch>, VectorStoreError>;
+
+    async fn get(&self, id: &str) -> Result<Option<VectorRecord>, VectorStoreError>;
+
+    /// 删除指定ID的记录，不存在的ID被忽略
+    async fn delete(&self, ids: &[String]) -> Result<(), VectorStoreError>;
+
+    /// 删除载荷匹配 `filter` 的所有记录
+    async fn delete_matching(&self, filter: &VectorFilter) -> Result<(), VectorStoreError>;
+
+    /// 记录条数
+    async fn count(&self) -> Result<usize, VectorStoreError>;
+}
+
+/// 本地向量存储的检索方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexKind {
+    /// 精确扫描全部记录
+    #[default]
+    Flat,
+    /// HNSW 近似最近邻索引，适合大量记录；带过滤条件的检索多取候选后过滤，
+    /// 候选覆盖整个索引仍不够时精确扫描
+    Hnsw,
+}
+
+#[derive(Default)]
+struct LocalState {
+    records: HashMap<String, VectorRecord>,
+    hnsw: Option<HnswIndex>,
+    /// HNSW 节点编号到记录ID，以及记录ID到节点编号
+    slots: HashMap<usize, String>,
+    nodes: HashMap<String, usize>,
+    /// 上次快照后日志中的条数
+    logged: usize,
+}
+
+impl LocalState {
+    fn dimensions(&self) -> Option<usize> {
+        self.records.values().next().map(|record| record.vector.len())
+    }
+
+    fn index(&mut self, record: &VectorRecord) {
+        if let Some(index) = self.hnsw.as_mut() {
+            if let Some(old) = self.nodes.remove(&record.id) {
+                index.remove(old);
+                self.slots.remove(&old);
+            }
+            let node = index.insert(record.vector.clone());
+            self.slots.insert(node, record.id.clone());
+            self.nodes.insert(record.id.clone(), node);
+        }
+    }
+
+    /// 删除记录，返回记录是否存在
+    fn remove(&mut self, id: &str) -> bool {
+        if self.records.remove(id).is_none() {
+            return false;
+        }
+        if let (Some(index), Some(node)) = (self.hnsw.as_mut(), self.nodes.remove(id)) {
+            index.remove(node);
+            self.slots.remove(&node);
+        }
+        true
+    }
+
+    fn apply(&mut self, entry: LogEntry) {
+        match entry {
+            LogEntry::Upsert(record) => {
+                self.index(&record);
+                self.records.insert(record.id.clone(), record);
+            }
+            LogEntry::Delete(id) => {
+                self.remove(&id);
+            }
+        }
+    }
+
+    /// 删除的节点多于有效节点时重建索引
+    fn compact(&mut self) {
+        let needs_rebuild = self
+            .hnsw
+            .as_ref()
+            .is_some_and(|index| index.deleted() > index.len());
+        if needs_rebuild {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        if self.hnsw.is_none() {
+            return;
+        }
+        self.hnsw = Some(HnswIndex::new());
+        self.slots.clear();
+        self.nodes.clear();
+        let mut records: Vec<VectorRecord> = self.records.values().cloned().collect();
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        for record in &records {
+            self.index(record);
+        }
+    }
+}
+
+/// 持久化文件内容
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    records: Vec<VectorRecord>,
+}
+
+/// 日志中的一次写入，每行一条
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LogEntry {
+    Upsert(VectorRecord),
+    Delete(String),
+}
+
+/// 日志条数超过记录数和该值中的较大者时合并为快照
+const MIN_COMPACT_ENTRIES: usize = 1024;
+
+/// 带过滤条件的 HNSW 检索先取 `limit` 的多少倍候选
+const FILTER_OVERSAMPLING: usize = 4;
+
+/// 进程内向量存储
+pub struct LocalVectorStore {
+    kind: IndexKind,
+    path: Option<PathBuf>,
+    state: RwLock<LocalState>,
+}
+
+impl fmt::Debug for LocalVectorStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalVectorStore")
+            .field("kind", &self.kind)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LocalVectorStore {
+    /// 只保存在内存中的存储
+    pub fn new(kind: IndexKind) -> Self {
+        Self {
+            kind,
+            path: None,
+            state: RwLock::new(LocalState {
+                hnsw: (kind == IndexKind::Hnsw).then(HnswIndex::new),
+                ..LocalState::default()
+            }),
+        }
+    }
+
+    /// 保存到 `path` 的存储，文件已存在时载入快照并重放 `path` 同名的 `.log`
+    /// 日志中的写入
+    pub async fn open(path: impl AsRef<Path>, kind: IndexKind) -> Result<Self, VectorStoreError> {
+        let path = path.as_ref().to_path_buf();
+        let mut store = Self::new(kind);
+        let state = store.state.get_mut();
+        if let Some(bytes) = read_if_exists(&path).await? {
+            let snapshot: Snapshot = serde_json::from_slice(&bytes)?;
+            for record in snapshot.records {
+                state.records.insert(record.id.clone(), record);
+            }
+        }
+        if let Some(bytes) = read_if_exists(&log_path(&path)).await? {
+            let mut valid = 0;
+            // 只有写入中断的最后一行可能不完整，没有换行的也算不完整
+            for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+                let Some(entry) = line
+                    .strip_suffix(b"\n")
+                    .and_then(|entry| serde_json::from_slice::<LogEntry>(entry).ok())
+                else {
+                    warn!("⚠️  向量日志 {} 末尾有不完整的记录，已忽略", path.display());
+                    break;
+                };
+                state.apply(entry);
+                state.logged += 1;
+                valid += line.len();
+            }
+            // 截掉不完整的部分，之后追加的记录从新的一行开始
+            if valid < bytes.len() {
+                let log = tokio::fs::OpenOptions::new().write(true).open(log_path(&path)).await?;
+                log.set_len(valid as u64).await?;
+            }
+        }
+        state.rebuild();
+        store.path = Some(path);
+        Ok(store)
+    }
+
+    /// 把写入追加到日志，日志过长时合并为快照
+    async fn persist(&self, state: &mut LocalState, entries: &[LogEntry]) -> Result<(), VectorStoreError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut bytes = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut bytes, entry)?;
+            bytes.push(b'\n');
+        }
+        let mut log = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path(path))
+            .await?;
+        log.write_all(&bytes).await?;
+        log.sync_data().await?;
+        state.logged += entries.len();
+
+        if state.logged > state.records.len().max(MIN_COMPACT_ENTRIES) {
+            self.snapshot(path, state).await?;
+        }
+        Ok(())
+    }
+
+    /// 先写临时文件再替换快照，然后清空日志；清空前中断时重放日志得到同样的
+    /// 结果
+    async fn snapshot(&self, path: &Path, state: &mut LocalState) -> Result<(), VectorStoreError> {
+        let mut records: Vec<&VectorRecord> = state.records.values().collect();
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        let bytes = serde_json::to_vec(&serde_json::json!({ "records": records }))?;
+        let temp = path.with_extension("tmp");
+        tokio::fs::write(&temp, bytes).await?;
+        tokio::fs::rename(&temp, path).await?;
+        tokio::fs::write(log_path(path), b"").await?;
+        state.logged = 0;
+        Ok(())
+    }
+}
+
+fn log_path(path: &Path) -> PathBuf {
+    path.with_extension("log")
+}
+
+async fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, VectorStoreError> {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[async_trait]
+impl VectorStore for LocalVectorStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<(), VectorStoreError> {
+        let mut state = self.state.write().await;
+        let mut expected = state.dimensions();
+        for record in &records {
+            let actual = record.vector.len();
+            match expected {
+                Some(expected) if expected != actual => {
+                    return Err(VectorStoreError::DimensionMismatch { expected, actual })
+                }
+                _ => expected = Some(actual),
+            }
+        }
+        let entries: Vec<LogEntry> = records.into_iter().map(LogEntry::Upsert).collect();
+        for entry in &entries {
+            if let LogEntry::Upsert(record) = entry {
+                state.index(record);
+                state.records.insert(record.id.clone(), record.clone());
+            }
+        }
+        state.compact();
+        self.persist(&mut state, &entries).await
+    }
+
+    async fn search(
+        &self,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<&VectorFilter>,
+    ) -> Result<Vec<VectorMatch>, VectorStoreError> {
+        let state = self.state.read().await;
+        let to_match = |record: &VectorRecord, score: f32| VectorMatch {
+            id: record.id.clone(),
+            score,
+            payload: record.payload.clone(),
+        };
+
+        if let Some(index) = &state.hnsw {
+            // 带过滤条件时多取候选再过滤，不够时加倍，直到候选覆盖整个索引
+            let mut candidates = match filter {
+                Some(_) => limit.saturating_mul(FILTER_OVERSAMPLING),
+                None => limit,
+            };
+            loop {
+                let found = index.search(vector, candidates);
+                let exhausted = found.len() < candidates;
+                let matches: Vec<VectorMatch> = found
+                    .into_iter()
+                    .filter_map(|(node, score)| {
+                        let record = state.slots.get(&node).and_then(|id| state.records.get(id))?;
+                        filter
+                            .is_none_or(|filter| filter.matches(&record.payload))
+                            .then(|| to_match(record, score))
+                    })
+                    .take(limit)
+                    .collect();
+                if matches.len() >= limit || filter.is_none() {
+                    return Ok(matches);
+                }
+                if exhausted || candidates >= index.len() {
+                    break;
+                }
+                candidates = candidates.saturating_mul(FILTER_OVERSAMPLING);
+            }
+        }
+
+        let mut matches: Vec<VectorMatch> = state
+            .records
+            .values()
+            .filter(|record| filter.is_none_or(|filter| filter.matches(&record.payload)))
+            .map(|record| to_match(record, cosine_similarity(vector, &record.vector)))
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<VectorRecord>, VectorStoreError> {
+        Ok(self.state.read().await.records.get(id).cloned())
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<(), VectorStoreError> {
+        let mut state = self.state.write().await;
+        let entries: Vec<LogEntry> = ids
+            .iter()
+            .filter(|id| state.remove(id))
+            .map(|id| LogEntry::Delete(id.clone()))
+            .collect();
+        state.compact();
+        self.persist(&mut state, &entries).await
+    }
+
+    async fn delete_matching(&self, filter: &VectorFilter) -> Result<(), VectorStoreError> {
+        let mut state = self.state.write().await;
+        let ids: Vec<String> = state
+            .records
+            .values()
+            .filter(|record| filter.matches(&record.payload))
+            .map(|record| record.id.clone())
+            .collect();
+        for id in &ids {
+            state.remove(id);
+        }
+        state.compact();
+        let entries: Vec<LogEntry> = ids.into_iter().map(LogEntry::Delete).collect();
+        self.persist(&mut state, &entries).await
+    }
+
+    async fn count(&self) -> Result<usize, VectorStoreError> {
+        Ok(self.state.read().await.records.len())
+    }
+}
+
+/// 向量存储后端
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorBackend {
+    #[default]
+    Local,
+    Qdrant,
+}
+
+/// 向量存储配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VectorStoreConfig {
+    pub backend: VectorBackend,
+    /// 本地存储的检索方式
+    pub index: IndexKind,
+    /// 本地存储的文件路径，为空时只保存在内存中
+    pub path: Option<String>,
+    /// Qdrant 接口地址
+    pub url: String,
+    /// Qdrant 集合名称
+    pub collection: String,
+    pub api_key: Option<String>,
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: VectorBackend::Local,
+            index: IndexKind::Flat,
+            path: None,
+            url: "http://localhost:6333".to_string(),
+            collection: "omni-agent".to_string(),
+            api_key: None,
+        }
+    }
+}
+
+impl VectorStoreConfig {
+    /// 按配置打开向量存储
+    pub async fn open(&self) -> Result<Arc<dyn VectorStore>, VectorStoreError> {
+        Ok(match self.backend {
+            VectorBackend::Local => match &self.path {
+                Some(path) => Arc::new(LocalVectorStore::open(path, self.index).await?),
+                None => Arc::new(LocalVectorStore::new(self.index)),
+            },
+            VectorBackend::Qdrant => Arc::new(QdrantStore::new(
+                &self.url,
+                &self.collection,
+                self.api_key.clone(),
+            )),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upsert_replaces_and_rejects_other_dimensions() {
+        for kind in [IndexKind::Flat, IndexKind::Hnsw] {
+            let store = LocalVectorStore::new(kind);
+            store
+                .upsert(vec![
+                    VectorRecord::new("a", vec![1.0, 0.0]),
+                    VectorRecord::new("b", vec![0.0, 1.0]),
+                ])
+                .await
+                .unwrap();
+            store
+                .upsert(vec![VectorRecord::new("a", vec![0.0, 1.0]).with_payload("v", 2)])
+                .await
+                .unwrap();
+            assert_eq!(store.count().await.unwrap(), 2);
+
+            let matches = store.search(&[0.0, 1.0], 1, None).await.unwrap();
+            assert!((matches[0].score - 1.0).abs() < 1e-6);
+            let found = store
+                .search(&[1.0, 0.0], 5, Some(&VectorFilter::new().eq("v", 2)))
+                .await
+                .unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].id, "a");
+
+            let error = store.upsert(vec![VectorRecord::new("c", vec![1.0])]).await;
+            assert!(matches!(
+                error,
+                Err(VectorStoreError::DimensionMismatch { expected: 2, actual: 1 })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filtered_hnsw_search_matches_flat_scan() {
+        let flat = LocalVectorStore::new(IndexKind::Flat);
+        let hnsw = LocalVectorStore::new(IndexKind::Hnsw);
+        // 目标命名空间的记录离查询向量较远，只取前几个近邻时全被其它命名空间挤掉
+        let records: Vec<VectorRecord> = (0..400)
+            .map(|i| {
+                let angle = i as f32 / 400.0;
+                let namespace = if i % 50 == 0 { "rare" } else { "common" };
+                VectorRecord::new(format!("r{}", i), vec![1.0 - angle, angle, 0.1])
+                    .with_payload("namespace", namespace)
+            })
+            .collect();
+        flat.upsert(records.clone()).await.unwrap();
+        hnsw.upsert(records).await.unwrap();
+
+        let filter = VectorFilter::new().eq("namespace", "rare");
+        let expected = flat.search(&[1.0, 0.0, 0.0], 5, Some(&filter)).await.unwrap();
+        let found = hnsw.search(&[1.0, 0.0, 0.0], 5, Some(&filter)).await.unwrap();
+        assert_eq!(found.len(), 5);
+        assert!(found.iter().all(|m| m.payload["namespace"] == "rare"));
+        let ids = |matches: &[VectorMatch]| matches.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&found), ids(&expected));
+
+        let missing = VectorFilter::new().eq("namespace", "none");
+        assert!(hnsw.search(&[1.0, 0.0, 0.0], 5, Some(&missing)).await.unwrap().is_empty());
+    }
+}
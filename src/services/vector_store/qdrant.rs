@@ -0,0 +1,228 @@
+//! 兼容 Qdrant 的 HTTP 向量存储
+//!
+//! Qdrant 的点ID只能是整数或 UUID，记录ID按 UUID v5 映射为点ID，原始ID保存在
+//! 载荷的 `record_id` 字段中。集合在第一次写入时按向量维度创建，距离为余弦。
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use super::{VectorFilter, VectorMatch, VectorRecord, VectorStore, VectorStoreError};
+
+/// 载荷中保存原始记录ID的字段
+const RECORD_ID_KEY: &str = "record_id";
+
+/// Qdrant 向量存储
+#[derive(Debug)]
+pub struct QdrantStore {
+    client: Client,
+    base_url: String,
+    collection: String,
+    api_key: Option<String>,
+    created: OnceCell<()>,
+}
+
+#[derive(Deserialize)]
+struct QdrantResponse<T> {
+    result: T,
+}
+
+#[derive(Deserialize)]
+struct ScoredPoint {
+    score: f32,
+    #[serde(default)]
+    payload: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct Point {
+    #[serde(default)]
+    vector: Vec<f32>,
+    #[serde(default)]
+    payload: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct CountResult {
+    count: usize,
+}
+
+impl QdrantStore {
+    pub fn new(base_url: &str, collection: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            collection: collection.to_string(),
+            api_key,
+            created: OnceCell::new(),
+        }
+    }
+
+    fn point_id(id: &str) -> Uuid {
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, id.as_bytes())
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/collections/{}{path}", self.base_url, self.collection);
+        let request = self.client.request(method, url);
+        match &self.api_key {
+            Some(key) => request.header("api-key", key),
+            None => request,
+        }
+    }
+
+    /// 发送请求，集合不存在时返回 `None`
+    async fn send<T: for<'de> Deserialize<'de>>(
+        request: RequestBuilder,
+    ) -> Result<Option<T>, VectorStoreError> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(VectorStoreError::Api {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        let response: QdrantResponse<T> = response.json().await?;
+        Ok(Some(response.result))
+    }
+
+    /// 集合不存在时按维度创建
+    async fn ensure_collection(&self, dimensions: usize) -> Result<(), VectorStoreError> {
+        self.created
+            .get_or_try_init(|| async {
+                let exists = Self::send::<Value>(self.request(reqwest::Method::GET, ""))
+                    .await?
+                    .is_some();
+                if !exists {
+                    let request = self.request(reqwest::Method::PUT, "").json(&json!({
+                        "vectors": { "size": dimensions, "distance": "Cosine" }
+                    }));
+                    Self::send::<Value>(request).await?;
+                }
+                Ok(())
+            })
+            .await
+            .copied()
+    }
+
+    fn filter(filter: &VectorFilter) -> Value {
+        let must: Vec<Value> = filter
+            .equals
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "match": { "value": value } }))
+            .collect();
+        json!({ "must": must })
+    }
+
+    /// 从载荷中取出原始记录ID
+    fn split_payload(mut payload: Map<String, Value>) -> (String, Map<String, Value>) {
+        let id = match payload.remove(RECORD_ID_KEY) {
+            Some(Value::String(id)) => id,
+            _ => String::new(),
+        };
+        (id, payload)
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<(), VectorStoreError> {
+        let Some(first) = records.first() else {
+            return Ok(());
+        };
+        self.ensure_collection(first.vector.len()).await?;
+        let points: Vec<Value> = records
+            .into_iter()
+            .map(|record| {
+                let mut payload = record.payload;
+                payload.insert(RECORD_ID_KEY.to_string(), Value::String(record.id.clone()));
+                json!({
+                    "id": Self::point_id(&record.id),
+                    "vector": record.vector,
+                    "payload": payload,
+                })
+            })
+            .collect();
+        let request = self
+            .request(reqwest::Method::PUT, "/points?wait=true")
+            .json(&json!({ "points": points }));
+        Self::send::<Value>(request).await?;
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<&VectorFilter>,
+    ) -> Result<Vec<VectorMatch>, VectorStoreError> {
+        let mut body = json!({ "vector": vector, "limit": limit, "with_payload": true });
+        if let Some(filter) = filter {
+            body["filter"] = Self::filter(filter);
+        }
+        let request = self
+            .request(reqwest::Method::POST, "/points/search")
+            .json(&body);
+        let points: Vec<ScoredPoint> = Self::send(request).await?.unwrap_or_default();
+        Ok(points
+            .into_iter()
+            .map(|point| {
+                let (id, payload) = Self::split_payload(point.payload);
+                VectorMatch {
+                    id,
+                    score: point.score,
+                    payload,
+                }
+            })
+            .collect())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<VectorRecord>, VectorStoreError> {
+        let request = self.request(reqwest::Method::POST, "/points").json(&json!({
+            "ids": [Self::point_id(id)],
+            "with_payload": true,
+            "with_vector": true,
+        }));
+        let points: Vec<Point> = Self::send(request).await?.unwrap_or_default();
+        Ok(points.into_iter().next().map(|point| {
+            let (_, payload) = Self::split_payload(point.payload);
+            VectorRecord {
+                id: id.to_string(),
+                vector: point.vector,
+                payload,
+            }
+        }))
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<(), VectorStoreError> {
+        let points: Vec<Uuid> = ids.iter().map(|id| Self::point_id(id)).collect();
+        let request = self
+            .request(reqwest::Method::POST, "/points/delete?wait=true")
+            .json(&json!({ "points": points }));
+        Self::send::<Value>(request).await?;
+        Ok(())
+    }
+
+    async fn delete_matching(&self, filter: &VectorFilter) -> Result<(), VectorStoreError> {
+        let request = self
+            .request(reqwest::Method::POST, "/points/delete?wait=true")
+            .json(&json!({ "filter": Self::filter(filter) }));
+        Self::send::<Value>(request).await?;
+        Ok(())
+    }
+
+    async fn count(&self) -> Result<usize, VectorStoreError> {
+        let request = self
+            .request(reqwest::Method::POST, "/points/count")
+            .json(&json!({ "exact": true }));
+        let result: Option<CountResult> = Self::send(request).await?;
+        Ok(result.map(|result| result.count).unwrap_or_default())
+    }
+}
@@ -0,0 +1,189 @@
+//! 管理API - 服务账户和 API 密钥管理，仅限管理员
+//!
+//! 创建 API 密钥的响应中包含完整密钥，之后只能看到其公开前缀。
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+    routing::{get, post},
+    Extension, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::auth::require_admin;
+use crate::services::security::{
+    ApiKey, ApiKeyUpdate, AuthToken, Permission, SecurityError, SecurityManager,
+};
+
+/// 创建服务账户的请求，省略权限时使用服务角色的默认权限
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateServiceAccountRequest {
+    pub username: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// 创建 API 密钥的请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// 密钥所属的用户，通常是服务账户
+    pub user_id: String,
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    /// 有效期（秒），省略时永不过期
+    #[serde(default)]
+    pub expires_in_secs: Option<u64>,
+}
+
+/// 创建 API 密钥的响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    /// 完整密钥，只在创建时返回
+    pub secret: String,
+}
+
+/// 修改 API 密钥的请求
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateApiKeyRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub permissions: Option<Vec<Permission>>,
+    /// 从现在起的有效期（秒）
+    #[serde(default)]
+    pub expires_in_secs: Option<u64>,
+}
+
+/// 列出 API 密钥的查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ListApiKeysQuery {
+    pub user_id: Option<String>,
+}
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 管理端点：`POST /admin/service-accounts`、`GET/POST /admin/api-keys`、
+/// `GET/PATCH/DELETE /admin/api-keys/:id`
+pub fn routes<S>() -> Router<S>
+where
+    Arc<SecurityManager>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/admin/service-accounts", post(create_service_account))
+        .route("/admin/api-keys", get(list_api_keys).post(create_api_key))
+        .route(
+            "/admin/api-keys/:id",
+            get(get_api_key).patch(update_api_key).delete(delete_api_key),
+        )
+}
+
+fn expires_at(expires_in_secs: Option<u64>) -> Option<u64> {
+    expires_in_secs.map(|secs| chrono::Utc::now().timestamp().max(0) as u64 + secs)
+}
+
+async fn create_service_account(
+    State(security): State<Arc<SecurityManager>>,
+    principal: Option<Extension<AuthToken>>,
+    JsonResponse(request): JsonResponse<CreateServiceAccountRequest>,
+) -> Result<(StatusCode, JsonResponse<Value>), ApiError> {
+    require_admin(principal)?;
+    let user_id = security
+        .create_service_account(&request.username, request.permissions)
+        .await
+        .map_err(security_error)?;
+    let user = security.get_user(&user_id).await.map_err(security_error)?;
+    Ok((StatusCode::CREATED, JsonResponse(json!(user))))
+}
+
+async fn list_api_keys(
+    State(security): State<Arc<SecurityManager>>,
+    principal: Option<Extension<AuthToken>>,
+    Query(query): Query<ListApiKeysQuery>,
+) -> Result<JsonResponse<Vec<ApiKey>>, ApiError> {
+    require_admin(principal)?;
+    Ok(JsonResponse(
+        security.list_api_keys(query.user_id.as_deref()).await,
+    ))
+}
+
+async fn create_api_key(
+    State(security): State<Arc<SecurityManager>>,
+    principal: Option<Extension<AuthToken>>,
+    JsonResponse(request): JsonResponse<CreateApiKeyRequest>,
+) -> Result<(StatusCode, JsonResponse<CreateApiKeyResponse>), ApiError> {
+    require_admin(principal)?;
+    let (key, secret) = security
+        .create_api_key(
+            &request.user_id,
+            &request.name,
+            request.permissions,
+            expires_at(request.expires_in_secs),
+        )
+        .await
+        .map_err(security_error)?;
+    Ok((
+        StatusCode::CREATED,
+        JsonResponse(CreateApiKeyResponse { key, secret }),
+    ))
+}
+
+async fn get_api_key(
+    State(security): State<Arc<SecurityManager>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+) -> Result<JsonResponse<ApiKey>, ApiError> {
+    require_admin(principal)?;
+    security
+        .get_api_key(&id)
+        .await
+        .map(JsonResponse)
+        .ok_or_else(|| security_error(SecurityError::ApiKeyNotFound(id)))
+}
+
+async fn update_api_key(
+    State(security): State<Arc<SecurityManager>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+    JsonResponse(request): JsonResponse<UpdateApiKeyRequest>,
+) -> Result<JsonResponse<ApiKey>, ApiError> {
+    require_admin(principal)?;
+    let update = ApiKeyUpdate {
+        name: request.name,
+        permissions: request.permissions,
+        expires_at: expires_at(request.expires_in_secs),
+    };
+    security
+        .update_api_key(&id, update)
+        .await
+        .map(JsonResponse)
+        .map_err(security_error)
+}
+
+async fn delete_api_key(
+    State(security): State<Arc<SecurityManager>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(principal)?;
+    if security.delete_api_key(&id).await.map_err(security_error)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(security_error(SecurityError::ApiKeyNotFound(id)))
+    }
+}
+
+fn security_error(error: SecurityError) -> ApiError {
+    let status = match error {
+        SecurityError::UserNotFound(_) | SecurityError::ApiKeyNotFound(_) => StatusCode::NOT_FOUND,
+        SecurityError::UserExists(_) => StatusCode::CONFLICT,
+        SecurityError::InsufficientPermissions(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, JsonResponse(json!({ "error": error.to_string() })))
+}
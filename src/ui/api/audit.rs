@@ -0,0 +1,78 @@
+//! 审计API - 查询、导出和校验审计日志，仅限管理员
+//!
+//! 查询参数 `user_id`、`action`、`success` 精确匹配，`since`、`until` 为
+//! Unix 秒（含），`limit` 取最新的若干条。
+
+use axum::{
+    body::Body,
+    extract::{FromRef, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::auth::{error_body, require_admin};
+use crate::services::audit::{AuditError, AuditFilter, AuditLog, AuditTrail, ChainReport};
+use crate::services::security::AuthToken;
+
+/// 查询未指定 `limit` 时返回的记录数
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 审计端点：`GET /audit`、`GET /audit/export`、`GET /audit/verify`
+pub fn routes<S>() -> Router<S>
+where
+    Arc<AuditTrail>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/audit", get(query_audit))
+        .route("/audit/export", get(export_audit))
+        .route("/audit/verify", get(verify_audit))
+}
+
+fn audit_error(e: AuditError) -> ApiError {
+    error_body(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+}
+
+/// 满足条件的记录，最新的在前
+async fn query_audit(
+    State(audit): State<Arc<AuditTrail>>,
+    principal: Option<Extension<AuthToken>>,
+    Query(mut filter): Query<AuditFilter>,
+) -> Result<JsonResponse<Vec<AuditLog>>, ApiError> {
+    require_admin(principal)?;
+    filter.limit.get_or_insert(DEFAULT_QUERY_LIMIT);
+    Ok(JsonResponse(audit.query(&filter).await.map_err(audit_error)?))
+}
+
+/// 以 JSON Lines 流式导出满足条件的记录，按序号排列，默认导出全部记录
+async fn export_audit(
+    State(audit): State<Arc<AuditTrail>>,
+    principal: Option<Extension<AuthToken>>,
+    Query(filter): Query<AuditFilter>,
+) -> Result<Response, ApiError> {
+    require_admin(principal)?;
+    let body = audit.export(&filter).await.map_err(audit_error)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"audit.jsonl\""),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// 校验哈希链
+async fn verify_audit(
+    State(audit): State<Arc<AuditTrail>>,
+    principal: Option<Extension<AuthToken>>,
+) -> Result<JsonResponse<ChainReport>, ApiError> {
+    require_admin(principal)?;
+    Ok(JsonResponse(audit.verify().await.map_err(audit_error)?))
+}
@@ -0,0 +1,232 @@
+//! 认证API - 登录、刷新和注销，以及认证和授权请求的中间件
+//!
+//! 中间件接受 `Authorization: Bearer <JWT 或 API 密钥>` 和
+//! `X-API-Key: <API 密钥>`。凭据有效时把认证结果（[`AuthToken`]）放入请求
+//! 扩展，处理器通过 `Option<Extension<AuthToken>>` 读取；凭据无效时返回
+//! 401。携带凭据的请求按 [`RoutePolicy`](crate::services::security::RoutePolicy)
+//! 检查权限，不足时返回 403。未携带凭据的请求访问非公开端点返回 401；只有
+//! 关闭 `security.require_auth` 时匿名请求才能访问只需 `read` 的端点。拒绝都
+//! 记入审计日志，调用需要 `admin` 权限的端点记为 `admin_action`，包括响应
+//! 状态码。
+
+use axum::{
+    extract::{FromRef, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::post,
+    Extension, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::services::audit::{action, AuditEvent};
+use crate::services::security::{
+    api_key_prefix, AuthToken, Credentials, Permission, RouteAccess, SecurityError,
+    SecurityManager,
+};
+
+/// API 密钥请求头
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// 登录请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// 刷新或注销时提交的刷新令牌
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RefreshRequest {
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 认证端点：`POST /auth/login`、`POST /auth/refresh`、`POST /auth/logout`
+pub fn routes<S>() -> Router<S>
+where
+    Arc<SecurityManager>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/auth/login", post(login))
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/logout", post(logout))
+}
+
+/// 认证和授权请求的中间件，通过 `axum::middleware::from_fn_with_state` 挂载
+pub async fn authenticate(
+    State(security): State<Arc<SecurityManager>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let resource = format!("{} {}", request.method(), request.uri().path());
+    let principal = match credential(request.headers()) {
+        Some(credential) => {
+            let result = match credential {
+                Credential::ApiKey(key) => security.authenticate_api_key(key).await,
+                Credential::Bearer(token) => security.validate_token(token).await,
+            };
+            match result {
+                Ok(principal) => Some(principal),
+                Err(e) => {
+                    security.record_denial("", &resource, e.to_string()).await;
+                    return unauthorized(&e.to_string());
+                }
+            }
+        }
+        None => None,
+    };
+
+    let access = security
+        .route_policy()
+        .access(request.method().as_str(), request.uri().path());
+    let admin_action = access == RouteAccess::Requires(Permission::Admin);
+    match (access, &principal) {
+        (RouteAccess::Public, _) => {}
+        (RouteAccess::Requires(permission), Some(principal)) => {
+            if let Err(e) = security
+                .authorize(&principal.user_id, &principal.permissions, &permission, &resource)
+                .await
+            {
+                return forbidden(&e.to_string());
+            }
+        }
+        (RouteAccess::Requires(Permission::Read), None) if !security.config().require_auth => {}
+        (RouteAccess::Requires(_), None) => {
+            security
+                .record_denial("", &resource, "缺少凭据".to_string())
+                .await;
+            return unauthorized("需要认证");
+        }
+    }
+
+    let user_id = principal.as_ref().map(|principal| principal.user_id.clone());
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal);
+    }
+    let response = next.run(request).await;
+    if let (true, Some(user_id)) = (admin_action, user_id) {
+        let status = response.status();
+        let mut event = AuditEvent::new(action::ADMIN_ACTION, resource)
+            .user(user_id)
+            .with("status", json!(status.as_u16()));
+        if !status.is_success() {
+            event = event.failed(status.to_string());
+        }
+        security.audit().record(event).await;
+    }
+    response
+}
+
+/// 要求调用方持有管理员权限
+pub fn require_admin(principal: Option<Extension<AuthToken>>) -> Result<AuthToken, ApiError> {
+    let Some(Extension(principal)) = principal else {
+        return Err(error_body(StatusCode::UNAUTHORIZED, "需要认证"));
+    };
+    if Permission::Admin.granted_by(&principal.permissions) {
+        Ok(principal)
+    } else {
+        Err(error_body(StatusCode::FORBIDDEN, "需要权限 admin"))
+    }
+}
+
+/// 401 和 403 使用的错误响应体
+pub fn error_body(status: StatusCode, message: &str) -> ApiError {
+    (status, JsonResponse(json!({ "error": message })))
+}
+
+enum Credential<'a> {
+    ApiKey(&'a str),
+    Bearer(&'a str),
+}
+
+fn credential(headers: &HeaderMap) -> Option<Credential<'_>> {
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(Credential::ApiKey(key.trim()));
+    }
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))?
+        .trim();
+    if api_key_prefix(bearer).is_some() {
+        Some(Credential::ApiKey(bearer))
+    } else {
+        Some(Credential::Bearer(bearer))
+    }
+}
+
+fn unauthorized(message: &str) -> Response {
+    let mut response = error_body(StatusCode::UNAUTHORIZED, message).into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+fn forbidden(message: &str) -> Response {
+    error_body(StatusCode::FORBIDDEN, message).into_response()
+}
+
+fn auth_error(error: SecurityError) -> ApiError {
+    let status = match error {
+        SecurityError::AccountLocked(_) => StatusCode::LOCKED,
+        _ => StatusCode::UNAUTHORIZED,
+    };
+    (status, JsonResponse(json!({ "error": error.to_string() })))
+}
+
+async fn login(
+    State(security): State<Arc<SecurityManager>>,
+    JsonResponse(request): JsonResponse<LoginRequest>,
+) -> Result<JsonResponse<AuthToken>, ApiError> {
+    let credentials = Credentials {
+        username: request.username,
+        password: request.password,
+    };
+    security
+        .authenticate(&credentials)
+        .await
+        .map(JsonResponse)
+        .map_err(auth_error)
+}
+
+async fn refresh(
+    State(security): State<Arc<SecurityManager>>,
+    JsonResponse(request): JsonResponse<RefreshRequest>,
+) -> Result<JsonResponse<AuthToken>, ApiError> {
+    let refresh_token = request.refresh_token.ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({ "error": "缺少 refresh_token" })),
+        )
+    })?;
+    security
+        .refresh_token(&refresh_token)
+        .await
+        .map(JsonResponse)
+        .map_err(auth_error)
+}
+
+/// 吊销请求中的访问令牌，以及请求体中的刷新令牌（如果有）
+async fn logout(
+    State(security): State<Arc<SecurityManager>>,
+    headers: HeaderMap,
+    request: Option<JsonResponse<RefreshRequest>>,
+) -> Result<StatusCode, ApiError> {
+    let Some(Credential::Bearer(token)) = credential(&headers) else {
+        return Err(auth_error(SecurityError::InvalidToken(
+            "需要访问令牌".to_string(),
+        )));
+    };
+    security.revoke_token(token).await.map_err(auth_error)?;
+    if let Some(refresh_token) = request.and_then(|JsonResponse(r)| r.refresh_token) {
+        security.revoke_token(&refresh_token).await.map_err(auth_error)?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,113 @@
+//! 能力API - 查找智能体的能力，启用或禁用能力，重新获取对端的能力
+//!
+//! 能力来自已连接的 MCP 服务器、A2A 对端智能体和本地工具。禁用的能力不参与
+//! 路由，也不出现在智能体名片中，重新获取时保持禁用。
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+    routing::{get, post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::agent::Agent;
+use crate::core::capabilities::{Capability, CapabilityQuery};
+
+/// 启用或禁用能力的请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetEnabledRequest {
+    pub enabled: bool,
+}
+
+/// 重新获取能力的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshResponse {
+    pub capabilities: usize,
+    /// 无法访问的对端及原因
+    pub failures: Vec<RefreshFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshFailure {
+    pub peer: String,
+    pub error: String,
+}
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 能力端点：`GET /capabilities?category=&tag=&text=&enabled=` 查找能力、
+/// `GET /capabilities/:id`、`PUT /capabilities/:id/enabled`，
+/// 以及 `POST /capabilities/refresh` 重新获取对端的能力
+pub fn routes<S>() -> Router<S>
+where
+    Arc<Agent>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/capabilities", get(search_capabilities))
+        .route("/capabilities/refresh", post(refresh_capabilities))
+        .route("/capabilities/:id", get(get_capability))
+        .route("/capabilities/:id/enabled", put(set_enabled))
+}
+
+async fn search_capabilities(
+    State(agent): State<Arc<Agent>>,
+    Query(query): Query<CapabilityQuery>,
+) -> JsonResponse<Vec<Capability>> {
+    JsonResponse(agent.capabilities.search(&query).await)
+}
+
+async fn get_capability(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<String>,
+) -> Result<JsonResponse<Capability>, ApiError> {
+    agent
+        .capabilities
+        .get_capability(&id)
+        .await
+        .map(JsonResponse)
+        .ok_or_else(|| capability_not_found(&id))
+}
+
+async fn set_enabled(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<String>,
+    JsonResponse(request): JsonResponse<SetEnabledRequest>,
+) -> Result<JsonResponse<Capability>, ApiError> {
+    let capability = agent
+        .capabilities
+        .set_enabled(&id, request.enabled)
+        .await
+        .ok_or_else(|| capability_not_found(&id))?;
+    info!("⚙️  能力 {} 已{}", id, if request.enabled { "启用" } else { "禁用" });
+    Ok(JsonResponse(capability))
+}
+
+/// 重新获取所有 MCP 服务器和 A2A 对端的能力，无法访问的对端的能力标记为不健康
+async fn refresh_capabilities(State(agent): State<Arc<Agent>>) -> JsonResponse<RefreshResponse> {
+    let failures: Vec<RefreshFailure> = agent
+        .refresh_capabilities()
+        .await
+        .into_iter()
+        .map(|(peer, error)| {
+            warn!("⚠️  获取能力失败: {}", error);
+            RefreshFailure { peer, error }
+        })
+        .collect();
+    JsonResponse(RefreshResponse {
+        capabilities: agent.capabilities.get_all_capabilities().await.len(),
+        failures,
+    })
+}
+
+fn capability_not_found(id: &str) -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({ "error": format!("能力不存在: {id}") })),
+    )
+}
@@ -0,0 +1,247 @@
+//! 用户记忆API - 查看、写入、召回和删除智能体为用户记住的事实
+//!
+//! `DELETE /users/:user_id/memories` 删除一个用户在所有智能体中的记忆，用于
+//! 响应用户的数据删除请求。未启用用户记忆时所有端点返回 404。
+//!
+//! 已认证的调用方只能访问自己的记忆，指定其他用户时返回 403，访问其他用户
+//! 的单条记忆时返回 404；管理员可以访问所有用户的记忆。
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+    routing::{delete, get, post},
+    Extension, Router,
+};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::agent::Agent;
+use crate::core::state::{MemoryFilter, UserMemory, UserMemoryError, UserMemoryHit};
+use crate::integrations::database::MemoryRecord;
+use crate::services::security::{AuthToken, Permission};
+
+/// 列出记忆的查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ListMemoriesQuery {
+    pub user_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// 写入记忆的请求，省略 `agent_id` 时归属于本智能体
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RememberRequest {
+    pub user_id: String,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+
+/// 召回记忆的请求
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecallRequest {
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 用户记忆端点：`GET /memories?user_id=&agent_id=&tag=`、`POST /memories`、
+/// `POST /memories/recall`、`GET /memories/:id`、`DELETE /memories/:id`，
+/// 以及 `DELETE /users/:user_id/memories`
+pub fn routes<S>() -> Router<S>
+where
+    Arc<Agent>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/memories", get(list_memories).post(remember))
+        .route("/memories/recall", post(recall))
+        .route("/memories/:id", get(get_memory).delete(forget))
+        .route("/users/:user_id/memories", delete(forget_user))
+}
+
+fn user_memory(agent: &Agent) -> Result<&Arc<UserMemory>, ApiError> {
+    agent.user_memory.as_ref().ok_or_else(memory_disabled)
+}
+
+fn memory_disabled() -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({ "error": "用户记忆未启用" })),
+    )
+}
+
+/// 调用方可以访问的用户：非管理员只能访问自己，省略 `user_id` 时也只看到自己的记忆
+fn scope_user(
+    principal: &Option<Extension<AuthToken>>,
+    user_id: Option<String>,
+) -> Result<Option<String>, ApiError> {
+    let Some(Extension(principal)) = principal else {
+        return Ok(user_id);
+    };
+    if Permission::Admin.granted_by(&principal.permissions) {
+        return Ok(user_id);
+    }
+    match user_id {
+        Some(user_id) if user_id != principal.user_id => Err((
+            StatusCode::FORBIDDEN,
+            JsonResponse(json!({ "error": format!("无权访问用户 {user_id} 的记忆") })),
+        )),
+        _ => Ok(Some(principal.user_id.clone())),
+    }
+}
+
+async fn list_memories(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Query(query): Query<ListMemoriesQuery>,
+) -> Result<JsonResponse<Vec<MemoryRecord>>, ApiError> {
+    let filter = MemoryFilter {
+        user_id: scope_user(&principal, query.user_id)?,
+        agent_id: query.agent_id,
+        tags: query.tag.into_iter().collect(),
+    };
+    Ok(JsonResponse(user_memory(&agent)?.list(&filter).await))
+}
+
+async fn remember(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    JsonResponse(request): JsonResponse<RememberRequest>,
+) -> Result<(StatusCode, JsonResponse<MemoryRecord>), ApiError> {
+    scope_user(&principal, Some(request.user_id.clone()))?;
+    let agent_id = request
+        .agent_id
+        .unwrap_or_else(|| agent.config.name.clone());
+    let memory = user_memory(&agent)?
+        .remember(
+            &request.user_id,
+            &agent_id,
+            &request.content,
+            request.tags,
+            request.ttl_secs.map(|secs| Duration::seconds(secs as i64)),
+        )
+        .await
+        .map_err(memory_error)?;
+    Ok((StatusCode::CREATED, JsonResponse(memory)))
+}
+
+async fn recall(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    JsonResponse(request): JsonResponse<RecallRequest>,
+) -> Result<JsonResponse<Vec<UserMemoryHit>>, ApiError> {
+    let memory = user_memory(&agent)?;
+    let filter = MemoryFilter {
+        user_id: scope_user(&principal, request.user_id)?,
+        agent_id: request.agent_id,
+        tags: request.tags,
+    };
+    let limit = request.limit.unwrap_or(memory.config().recall_limit);
+    memory
+        .recall(&filter, &request.query, limit)
+        .await
+        .map(JsonResponse)
+        .map_err(memory_error)
+}
+
+async fn get_memory(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<Uuid>,
+) -> Result<JsonResponse<MemoryRecord>, ApiError> {
+    let user_id = scope_user(&principal, None)?;
+    user_memory(&agent)?
+        .get(&id)
+        .await
+        .filter(|memory| user_id.as_ref().is_none_or(|user_id| &memory.user_id == user_id))
+        .map(JsonResponse)
+        .ok_or_else(|| memory_not_found(&id))
+}
+
+async fn forget(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let filter = MemoryFilter {
+        user_id: scope_user(&principal, None)?,
+        ..MemoryFilter::default()
+    };
+    let forgotten = user_memory(&agent)?
+        .forget(&id, &filter)
+        .await
+        .map_err(memory_error)?;
+    if forgotten {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(memory_not_found(&id))
+    }
+}
+
+/// 删除用户在所有智能体中的记忆，以及在所有会话中的对话记忆
+async fn forget_user(
+    State(agent): State<Arc<Agent>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(user_id): Path<String>,
+) -> Result<JsonResponse<Value>, ApiError> {
+    scope_user(&principal, Some(user_id.clone()))?;
+    let deleted = match &agent.user_memory {
+        Some(memory) => memory.forget_user(&user_id).await.map_err(memory_error)?,
+        None if agent.conversation_memory.is_some() => 0,
+        None => return Err(memory_disabled()),
+    };
+    let conversation_deleted = match &agent.conversation_memory {
+        Some(memory) => memory.forget_user(&user_id).await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({ "error": e.to_string() })),
+            )
+        })?,
+        None => 0,
+    };
+    info!(
+        "🗑️  已删除用户 {} 的 {} 条记忆和 {} 条对话记忆",
+        user_id, deleted, conversation_deleted
+    );
+    Ok(JsonResponse(json!({
+        "user_id": user_id,
+        "deleted": deleted,
+        "conversation_memories_deleted": conversation_deleted
+    })))
+}
+
+fn memory_not_found(id: &Uuid) -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({ "error": format!("记忆不存在: {id}") })),
+    )
+}
+
+fn memory_error(error: UserMemoryError) -> ApiError {
+    let status = match error {
+        UserMemoryError::EmptyContent => StatusCode::BAD_REQUEST,
+        UserMemoryError::Embedding(_) | UserMemoryError::Storage(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (status, JsonResponse(json!({ "error": error.to_string() })))
+}
@@ -1,19 +1,89 @@
-//! REST API模块 - 简化版
+//! REST API模块
+//!
+//! 提供聊天、会话管理和健康检查等HTTP端点。每个会话拥有独立的上下文，
+//! 客户端通过 `session_id` 继续同一会话。
+
+pub mod admin;
+pub mod audit;
+pub mod auth;
+pub mod capabilities;
+pub mod memories;
+pub mod routing;
+pub mod workflows;
 
 use axum::{
-    response::Json as JsonResponse,
-    routing::{get, post},
-    Json, Router,
+    extract::{FromRef, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post, put},
+    Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::a2a::client::{A2AClient, A2AContent, A2AMessage};
+use crate::agent::{Agent, AgentError, Session, SessionInfo, TaskStatus, TurnOptions};
+use crate::config::AppConfig;
+use crate::core::router::{IntelligentRouter, RouteDecision, RouteTarget};
+use crate::core::workflow::{AgentStepExecutor, WorkflowEngine};
+use crate::integrations::database::DataRepository;
+use crate::llm::LLMOverrides;
+use crate::protocol::message::{Message, MessageContent};
+use crate::services::audit::AuditTrail;
+use crate::services::security::{AuthToken, Permission, SecurityManager};
+
+/// 返回 429 时建议客户端等待的秒数
+const RETRY_AFTER_SECS: &str = "1";
 
 /// 请求消息结构
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserRequest {
     pub message: String,
     pub context: Option<HashMap<String, serde_json::Value>>,
+    /// 会话ID，省略时创建新会话
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// 任务ID，可用于通过 `DELETE /tasks/:id` 取消请求，省略时自动生成
+    #[serde(default)]
+    pub task_id: Option<String>,
+    /// 请求超时时间（毫秒），省略时使用配置的默认值
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// 发送消息的用户，智能体为该用户记住和召回事实，省略时为 `user`。
+    /// 已认证的请求忽略该字段，使用认证用户的ID
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+impl UserRequest {
+    /// 解析请求所属的会话ID，依次查找 `session_id` 字段和 context 中的
+    /// `session_id`/`context_id`，都不存在时生成新ID
+    pub fn resolve_session_id(&self) -> String {
+        self.session_id
+            .clone()
+            .or_else(|| {
+                let context = self.context.as_ref()?;
+                ["session_id", "context_id"]
+                    .iter()
+                    .find_map(|key| context.get(*key).and_then(|v| v.as_str()))
+                    .map(str::to_string)
+            })
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    }
+
+    /// 构造本次请求的任务选项
+    pub fn turn_options(&self) -> TurnOptions {
+        TurnOptions {
+            task_id: self.task_id.clone().filter(|id| !id.is_empty()),
+            timeout: self.timeout_ms.map(std::time::Duration::from_millis),
+            ..Default::default()
+        }
+    }
 }
 
 /// 响应消息结构
@@ -29,6 +99,8 @@ pub struct AgentResponse {
 pub struct BufferRequest {
     pub action: String,
     pub message: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
 }
 
 /// 对话缓冲区响应结构
@@ -38,22 +110,354 @@ pub struct BufferResponse {
     pub message: Option<String>,
 }
 
-/// 简化的应用状态
+/// 应用状态
 #[derive(Clone)]
 pub struct AppState {
-    pub name: String,
+    pub agent: Arc<Agent>,
+    pub config: AppConfig,
+    /// 工作流引擎，步骤通过智能体的后端执行
+    pub workflows: Arc<WorkflowEngine>,
+    /// 聊天请求的路由器
+    pub router: Arc<IntelligentRouter>,
+    /// 用户、令牌和 API 密钥管理
+    pub security: Arc<SecurityManager>,
+}
+
+impl AppState {
+    pub fn new(agent: impl Into<Arc<Agent>>, config: AppConfig) -> Self {
+        let agent = agent.into();
+        let executor = Arc::new(AgentStepExecutor::new(agent.clone()));
+        // 默认与智能体共用审计日志
+        let security = SecurityManager::new(String::new()).with_audit(agent.audit.clone());
+        Self {
+            agent,
+            config,
+            workflows: Arc::new(WorkflowEngine::with_executor(executor)),
+            router: Arc::new(IntelligentRouter::new()),
+            security: Arc::new(security),
+        }
+    }
+
+    /// 使用指定的安全管理器认证请求
+    pub fn with_security(mut self, security: impl Into<Arc<SecurityManager>>) -> Self {
+        self.security = security.into();
+        self
+    }
+
+    /// 使用指定的路由器处理聊天请求
+    pub fn with_router(mut self, router: IntelligentRouter) -> Self {
+        self.router = Arc::new(router);
+        self
+    }
+
+    /// 将工作流运行记录持久化到存储
+    pub fn with_workflow_store(mut self, store: Arc<dyn DataRepository>) -> Self {
+        let executor = Arc::new(AgentStepExecutor::new(self.agent.clone()));
+        self.workflows = Arc::new(WorkflowEngine::with_executor(executor).with_store(store));
+        self
+    }
+}
+
+impl FromRef<AppState> for Arc<WorkflowEngine> {
+    fn from_ref(state: &AppState) -> Self {
+        state.workflows.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<IntelligentRouter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.router.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<SecurityManager> {
+    fn from_ref(state: &AppState) -> Self {
+        state.security.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AuditTrail> {
+    fn from_ref(state: &AppState) -> Self {
+        state.security.audit().clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Agent> {
+    fn from_ref(state: &AppState) -> Self {
+        state.agent.clone()
+    }
+}
+
+/// 按路由决策处理请求
+///
+/// 路由到已配置的 A2A 智能体时转发消息；其余请求在会话中交给本地LLM，
+/// MCP 工具由LLM在工具循环中调用。返回回复、来源和详情。
+async fn dispatch(
+    decision: &RouteDecision,
+    message: &str,
+    user_id: &str,
+    session_id: &str,
+    options: TurnOptions,
+    agent: &Agent,
+) -> Result<(String, String, HashMap<String, Value>), Box<dyn std::error::Error + Send + Sync>> {
+    let mut details = HashMap::from([("route".to_string(), json!(decision))]);
+
+    if let RouteTarget::A2AAgent(agent_name) = &decision.target {
+        if let Some(client) = agent.a2a_clients.get(agent_name) {
+            info!("🤝 使用 A2A 智能体: {}", agent_name);
+            // 转发注册为任务，取消任务或超过期限时通知对端取消
+            let (task_id, cancel) = agent
+                .tasks
+                .start(options.task_id.clone(), session_id, options.owner.as_deref())
+                .await?;
+            let watchdog = {
+                let tasks = agent.tasks.clone();
+                let task_id = task_id.clone();
+                let timeout = options.timeout.unwrap_or(agent.request_timeout);
+                tokio::spawn(async move {
+                    tokio::time::sleep(timeout).await;
+                    let _ = tasks.expire(&task_id).await;
+                })
+            };
+            let reply = forward_to_a2a(agent_name, client, message, agent, &cancel).await;
+            watchdog.abort();
+            let (status, error) = match &reply {
+                Ok(_) => (TaskStatus::Completed, None),
+                Err(e) => (TaskStatus::Failed, Some(e.to_string())),
+            };
+            agent.tasks.finish(&task_id, status, error).await;
+            agent
+                .audit_delegation(
+                    user_id,
+                    agent_name,
+                    &json!(message),
+                    &reply,
+                    json!({ "session_id": session_id, "task_id": task_id }),
+                )
+                .await;
+            let reply = reply?;
+            details.insert("agent".to_string(), json!(agent_name));
+            details.insert("task_id".to_string(), json!(task_id));
+            return Ok((reply, "a2a_agent".to_string(), details));
+        }
+        warn!("⚠️  A2A 智能体未配置: {}，使用本地 LLM", agent_name);
+    }
+
+    info!("🧠 使用本地 LLM 回答");
+    let (reply, task_id) = use_local_llm(message, user_id, session_id, options, agent).await?;
+    details.insert("task_id".to_string(), task_id);
+    Ok((reply, "local_llm".to_string(), details))
+}
+
+/// 将消息转发给 A2A 智能体，`cancel` 触发时请求对端取消任务
+async fn forward_to_a2a(
+    agent_name: &str,
+    client: &A2AClient,
+    message: &str,
+    agent: &Agent,
+    cancel: &CancellationToken,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let request = A2AMessage {
+        id: uuid::Uuid::new_v4(),
+        sender: agent.config.name.clone(),
+        recipient: agent_name.to_string(),
+        content: A2AContent::Text {
+            text: message.to_string(),
+        },
+        timestamp: chrono::Utc::now(),
+    };
+    match client.send_message_with_cancel(request, cancel).await?.content {
+        A2AContent::Text { text } => Ok(text),
+        A2AContent::Response { data: Value::String(text), .. } => Ok(text),
+        A2AContent::Response { data, .. } => Ok(data.to_string()),
+        A2AContent::Error { code, message } => {
+            Err(format!("A2A 智能体 {agent_name} 返回错误 {code}: {message}").into())
+        }
+        task @ A2AContent::Task { .. } => Ok(serde_json::to_string(&task)?),
+    }
+}
+
+/// 使用本地 LLM 在会话上下文中回答
+async fn use_local_llm(
+    message: &str,
+    user_id: &str,
+    session_id: &str,
+    options: TurnOptions,
+    agent: &Agent,
+) -> Result<(String, Value), Box<dyn std::error::Error + Send + Sync>> {
+    let request = Message::new(
+        user_id.to_string(),
+        agent.config.name.clone(),
+        MessageContent::Text {
+            text: message.to_string(),
+        },
+        None,
+    );
+    let response = agent.run_turn(session_id, request, options).await?;
+    let task_id = response
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("task_id").cloned())
+        .unwrap_or(Value::Null);
+
+    match response.content {
+        MessageContent::Text { text } => Ok((text, task_id)),
+        _ => Ok(("无法处理消息格式".to_string(), task_id)),
+    }
 }
 
 /// 处理聊天请求
+///
+/// 请求先由 [`AppState::router`] 决定目标，路由决策记录在 `details.route` 中。
+/// 成功的回复带有 `details.message_id`，可用于 `POST /messages/:id/feedback` 评价回复。
+/// 并发LLM调用已满且等待队列也已满时返回 `429 Too Many Requests`，
+/// 超过请求期限时返回 `504 Gateway Timeout`，被取消的请求返回 `source: "canceled"`，
+/// 敏感工具调用被拒绝时返回 `source: "denied"`。处理过程中需要人工审批时，
+/// 请求会一直等待到审批完成。
+///
+/// 已认证的请求只能委托给持有 `agent:<名称>` 权限的 A2A 智能体，只能调用
+/// 持有 `tool:<名称>` 权限的工具，否则返回 `403 Forbidden`。会话归首个
+/// 使用它的认证用户所有，其他用户（管理员除外）继续该会话时返回 `403 Forbidden`。
 pub async fn chat_handler(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    principal: Option<Extension<AuthToken>>,
     Json(request): Json<UserRequest>,
-) -> JsonResponse<AgentResponse> {
-    JsonResponse(AgentResponse {
-        message: format!("处理消息: {}", request.message),
-        source: "local_llm".to_string(),
-        details: HashMap::new(),
-    })
+) -> Response {
+    let session_id = request.resolve_session_id();
+    let context = routing::decision_context(&state.agent, &request.message, json!(request.context))
+        .await
+        .with_role("user");
+    let decision = state.router.route(&context).await;
+    let mut options = request.turn_options();
+    if let Some(Extension(principal)) = &principal {
+        let session = state.agent.sessions.get_or_create(&session_id).await;
+        if !session.claim(&principal.user_id).await
+            && !Permission::Admin.granted_by(&principal.permissions)
+        {
+            let message = format!("会话属于其他用户: {session_id}");
+            state
+                .security
+                .record_denial(&principal.user_id, &format!("session:{session_id}"), message.clone())
+                .await;
+            return auth::error_body(StatusCode::FORBIDDEN, &message).into_response();
+        }
+        // 只检查实际会委托的智能体，未配置的智能体由本地LLM回答
+        if let RouteTarget::A2AAgent(agent_name) = &decision.target {
+            if state.agent.a2a_clients.contains_key(agent_name) {
+                let required = Permission::AgentAccess(agent_name.clone());
+                let resource = required.to_string();
+                if let Err(e) = state
+                    .security
+                    .authorize(&principal.user_id, &principal.permissions, &required, &resource)
+                    .await
+                {
+                    return auth::error_body(StatusCode::FORBIDDEN, &e.to_string()).into_response();
+                }
+            }
+        }
+        options.permissions = principal.permissions.clone();
+        options.restrict_tools = true;
+        options.owner = Some(principal.user_id.clone());
+    }
+    let message_id = uuid::Uuid::new_v4().to_string();
+    let owner = principal.as_ref().map(|Extension(principal)| principal.user_id.as_str());
+    state
+        .router
+        .track(&message_id, &decision, &request.message, owner)
+        .await;
+
+    let user_id = match &principal {
+        Some(Extension(principal)) => principal.user_id.as_str(),
+        None => request.user_id.as_deref().unwrap_or("user"),
+    };
+    match dispatch(
+        &decision,
+        &request.message,
+        user_id,
+        &session_id,
+        options,
+        &state.agent,
+    )
+    .await
+    {
+        Ok((response, source, mut details)) => {
+            details.insert("session_id".to_string(), json!(session_id));
+            details.insert("message_id".to_string(), json!(message_id));
+            JsonResponse(AgentResponse {
+                message: response,
+                source,
+                details,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            let mut details = HashMap::from([("session_id".to_string(), json!(session_id))]);
+            let agent_error = e.downcast_ref::<AgentError>();
+            if let Some(AgentError::Canceled(task_id) | AgentError::Timeout { task_id, .. }) =
+                agent_error
+            {
+                details.insert("task_id".to_string(), json!(task_id));
+            }
+            if let Some(AgentError::Canceled(task_id)) = agent_error {
+                info!("🛑 请求已取消: {}", task_id);
+                return JsonResponse(AgentResponse {
+                    message: format!("任务已取消: {task_id}"),
+                    source: "canceled".to_string(),
+                    details,
+                })
+                .into_response();
+            }
+            if let Some(AgentError::Forbidden(permission)) = agent_error {
+                let user_id = principal.as_ref().map_or("", |Extension(p)| p.user_id.as_str());
+                state
+                    .security
+                    .record_denial(user_id, &permission.to_string(), e.to_string())
+                    .await;
+                return auth::error_body(StatusCode::FORBIDDEN, &e.to_string()).into_response();
+            }
+            if let Some(AgentError::ApprovalDenied { tool, reason }) = agent_error {
+                info!("✋ 工具调用被拒绝: {} ({})", tool, reason);
+                details.insert("tool".to_string(), json!(tool));
+                return JsonResponse(AgentResponse {
+                    message: format!("工具调用被拒绝: {reason}"),
+                    source: "denied".to_string(),
+                    details,
+                })
+                .into_response();
+            }
+            if let Some(AgentError::Timeout { timeout, .. }) = agent_error {
+                warn!("⌛ 请求超时: {:?}", timeout);
+                let body = JsonResponse(AgentResponse {
+                    message: format!("处理超时: {e}"),
+                    source: "timeout".to_string(),
+                    details,
+                });
+                return (StatusCode::GATEWAY_TIMEOUT, body).into_response();
+            }
+            if let Some(AgentError::Overloaded(limit)) = agent_error {
+                warn!("⏳ 请求被拒绝: {}", limit);
+                let body = JsonResponse(AgentResponse {
+                    message: format!("服务繁忙，请稍后重试: {limit}"),
+                    source: "overloaded".to_string(),
+                    details,
+                });
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, RETRY_AFTER_SECS)],
+                    body,
+                )
+                    .into_response();
+            }
+
+            error!("❌ 处理消息失败: {}", e);
+            JsonResponse(AgentResponse {
+                message: format!("处理失败: {e}"),
+                source: "error".to_string(),
+                details,
+            })
+            .into_response()
+        }
+    }
 }
 
 /// 健康检查端点
@@ -64,23 +468,193 @@ pub async fn health_handler() -> JsonResponse<serde_json::Value> {
     }))
 }
 
+/// 获取智能体信息
+pub async fn info_handler(State(state): State<AppState>) -> JsonResponse<Value> {
+    let agent = &state.agent;
+    JsonResponse(json!({
+        "name": agent.config.name,
+        "description": agent.config.description,
+        "version": agent.config.version,
+        "mcp_clients": agent.mcp_clients.len(),
+        "a2a_clients": agent.a2a_clients.len(),
+        "sessions": agent.sessions.len().await,
+        "concurrency": agent.limiter.stats(),
+        "llm_provider": state.config.llm.provider,
+        "llm_model": state.config.llm.model
+    }))
+}
+
 /// 对话缓冲区管理端点
+///
+/// 支持的动作：`clear` 清空会话上下文，`status` 返回会话消息数。
 pub async fn buffer_handler(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    principal: Option<Extension<AuthToken>>,
     Json(request): Json<BufferRequest>,
 ) -> JsonResponse<BufferResponse> {
-    JsonResponse(BufferResponse {
-        status: "success".to_string(),
-        message: Some(format!("处理了缓冲区请求: {}", request.action)),
-    })
+    let Some(session_id) = request.session_id.as_deref() else {
+        return JsonResponse(BufferResponse {
+            status: "error".to_string(),
+            message: Some("缺少 session_id".to_string()),
+        });
+    };
+
+    let agent = &state.agent;
+    let session = match agent.sessions.get(session_id).await {
+        Some(session) if can_access(&principal, &session).await => Some(session),
+        _ => None,
+    };
+    let Some(session) = session else {
+        return JsonResponse(BufferResponse {
+            status: "error".to_string(),
+            message: Some(format!("会话不存在: {session_id}")),
+        });
+    };
+
+    match request.action.as_str() {
+        "clear" => {
+            session.state_machine.write().await.clear_context();
+            JsonResponse(BufferResponse {
+                status: "success".to_string(),
+                message: Some(format!("已清空会话缓冲区: {session_id}")),
+            })
+        }
+        "status" => {
+            let count = session.state_machine.read().await.context.len();
+            JsonResponse(BufferResponse {
+                status: "success".to_string(),
+                message: Some(format!("会话 {session_id} 包含 {count} 条消息")),
+            })
+        }
+        action => JsonResponse(BufferResponse {
+            status: "error".to_string(),
+            message: Some(format!("不支持的缓冲区动作: {action}")),
+        }),
+    }
+}
+
+/// 列出活跃会话，非管理员只能看到自己的会话
+pub async fn list_sessions_handler(
+    State(state): State<AppState>,
+    principal: Option<Extension<AuthToken>>,
+) -> JsonResponse<Vec<SessionInfo>> {
+    let agent = &state.agent;
+    let mut sessions = agent.sessions.list().await;
+    if let Some(Extension(principal)) = &principal {
+        if !Permission::Admin.granted_by(&principal.permissions) {
+            sessions.retain(|info| info.owner.as_ref() == Some(&principal.user_id));
+        }
+    }
+    JsonResponse(sessions)
+}
+
+/// 获取会话详情及其上下文消息
+pub async fn get_session_handler(
+    State(state): State<AppState>,
+    principal: Option<Extension<AuthToken>>,
+    Path(session_id): Path<String>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let agent = &state.agent;
+    let session = agent
+        .sessions
+        .get(&session_id)
+        .await
+        .ok_or_else(|| session_not_found(&session_id))?;
+    if !can_access(&principal, &session).await {
+        return Err(session_not_found(&session_id));
+    }
+
+    let messages = session.state_machine.read().await.get_context();
+    Ok(JsonResponse(json!({
+        "session": session.info().await,
+        "messages": messages,
+    })))
+}
+
+/// 删除会话
+pub async fn delete_session_handler(
+    State(state): State<AppState>,
+    principal: Option<Extension<AuthToken>>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, JsonResponse<Value>)> {
+    let agent = &state.agent;
+    let Some(session) = agent.sessions.get(&session_id).await else {
+        return Err(session_not_found(&session_id));
+    };
+    if can_access(&principal, &session).await && agent.sessions.remove(&session_id).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(session_not_found(&session_id))
+    }
+}
+
+/// 设置会话级LLM配置覆盖，会话不存在时为调用方创建
+pub async fn set_session_llm_handler(
+    State(state): State<AppState>,
+    principal: Option<Extension<AuthToken>>,
+    Path(session_id): Path<String>,
+    Json(overrides): Json<LLMOverrides>,
+) -> Result<JsonResponse<SessionInfo>, (StatusCode, JsonResponse<Value>)> {
+    let agent = &state.agent;
+    let session = agent.sessions.get_or_create(&session_id).await;
+    if let Some(Extension(principal)) = &principal {
+        if !session.claim(&principal.user_id).await
+            && !Permission::Admin.granted_by(&principal.permissions)
+        {
+            return Err(session_not_found(&session_id));
+        }
+    }
+    session.set_llm_overrides(overrides).await;
+    Ok(JsonResponse(session.info().await))
+}
+
+/// 未经认证层的请求和管理员可以访问所有会话，其他调用方只能访问自己的会话
+async fn can_access(principal: &Option<Extension<AuthToken>>, session: &Session) -> bool {
+    match principal {
+        None => true,
+        Some(Extension(principal)) => {
+            Permission::Admin.granted_by(&principal.permissions)
+                || session.owner().await.as_ref() == Some(&principal.user_id)
+        }
+    }
+}
+
+fn session_not_found(session_id: &str) -> (StatusCode, JsonResponse<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({"error": format!("会话不存在: {session_id}")})),
+    )
 }
 
 /// 创建API路由
 pub fn create_routes() -> Router<AppState> {
     Router::new()
         .route("/health", get(health_handler))
+        .route("/info", get(info_handler))
         .route("/chat", post(chat_handler))
         .route("/buffer", post(buffer_handler))
+        .route("/sessions", get(list_sessions_handler))
+        .route(
+            "/sessions/:id",
+            get(get_session_handler).delete(delete_session_handler),
+        )
+        .route("/sessions/:id/llm", put(set_session_llm_handler))
+        .merge(crate::server::tasks::routes())
+        .merge(workflows::routes())
+        .merge(routing::routes())
+        .merge(capabilities::routes())
+        .merge(memories::routes())
+        .merge(auth::routes())
+        .merge(admin::routes())
+        .merge(audit::routes())
 }
 
-use axum::extract::State;
\ No newline at end of file
+/// 创建带认证中间件的应用，所有路由都经过 [`auth::authenticate`]
+pub fn create_app(state: AppState) -> Router {
+    create_routes()
+        .layer(axum::middleware::from_fn_with_state(
+            state.security.clone(),
+            auth::authenticate,
+        ))
+        .with_state(state)
+}
@@ -0,0 +1,253 @@
+//! 路由API - 查看和修改路由规则，解释消息的路由过程，评价回复
+//!
+//! 规则修改立即生效，无需重启。写入的规则先校验条件结构，动作必须是目标名称
+//! （`llm`、`mcp:<工具>`、`a2a:<智能体>`）。对回复的评价计入产生该回复的规则和
+//! 目标的成功率。
+
+use axum::{
+    extract::{FromRef, Path, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+    routing::{get, post},
+    Extension, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::agent::Agent;
+use crate::core::capabilities::Provenance;
+use crate::core::decision::{
+    DecisionContext, DecisionError, DecisionRule, LearningRecord, LearningStats, RuleEvaluation,
+};
+use crate::core::router::{validate_rule_actions, IntelligentRouter, RouteDecision, RouterError};
+use crate::services::security::{AuthToken, Permission};
+
+/// 解释路由的请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExplainRequest {
+    pub message: String,
+    #[serde(default)]
+    pub sender: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    /// 消息元数据，供字段条件使用
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+/// 路由解释：最终决策和每条规则的评估结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RouteExplanation {
+    pub decision: RouteDecision,
+    pub rules: Vec<RuleEvaluation>,
+}
+
+/// 对回复的评价
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+impl Rating {
+    /// 反馈分数：好评 1.0，差评 -1.0
+    pub fn score(self) -> f32 {
+        match self {
+            Rating::Up => 1.0,
+            Rating::Down => -1.0,
+        }
+    }
+}
+
+/// 评价回复的请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedbackRequest {
+    pub rating: Rating,
+}
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 路由端点：`GET /routing/rules` 导出规则、`PUT /routing/rules` 整体替换规则、
+/// `POST /routing/rules` 添加规则、`GET`/`DELETE /routing/rules/:id`，
+/// `POST /routing/explain`、`GET /routing/feedback` 查看反馈统计，
+/// 以及 `POST /messages/:id/feedback` 评价回复
+pub fn routes<S>() -> Router<S>
+where
+    Arc<IntelligentRouter>: FromRef<S>,
+    Arc<Agent>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route(
+            "/routing/rules",
+            get(list_rules).put(replace_rules).post(add_rule),
+        )
+        .route("/routing/rules/:id", get(get_rule).delete(delete_rule))
+        .route("/routing/explain", post(explain))
+        .route("/routing/feedback", get(feedback_stats))
+        .route("/messages/:id/feedback", post(submit_feedback))
+}
+
+/// 路由使用的决策上下文：可用的 MCP 工具和本地工具，以及提供可用能力的
+/// A2A 智能体
+pub async fn decision_context(agent: &Agent, message: &str, metadata: Value) -> DecisionContext {
+    let mut tools = Vec::new();
+    let mut agents = Vec::new();
+    for capability in agent.capabilities.available().await {
+        match &capability.provenance {
+            Provenance::A2a { agent } if !agents.contains(agent) => agents.push(agent.clone()),
+            Provenance::Mcp { .. } | Provenance::LocalTool => tools.push(capability.id),
+            _ => {}
+        }
+    }
+    DecisionContext::new(message)
+        .with_metadata(metadata)
+        .with_tools(tools)
+        .with_agents(agents)
+}
+
+async fn list_rules(
+    State(router): State<Arc<IntelligentRouter>>,
+) -> JsonResponse<Vec<DecisionRule>> {
+    JsonResponse(router.decision_engine().get_all_rules().await)
+}
+
+/// 用请求中的规则替换全部规则，任一规则无效时不做修改
+async fn replace_rules(
+    State(router): State<Arc<IntelligentRouter>>,
+    JsonResponse(rules): JsonResponse<Vec<DecisionRule>>,
+) -> Result<JsonResponse<Value>, ApiError> {
+    validate_rule_actions(&rules).map_err(router_error)?;
+    let count = router
+        .decision_engine()
+        .replace_rules(rules)
+        .await
+        .map_err(decision_error)?;
+    info!("📜 路由规则已替换，共 {} 条", count);
+    Ok(JsonResponse(json!({ "rules": count })))
+}
+
+async fn add_rule(
+    State(router): State<Arc<IntelligentRouter>>,
+    JsonResponse(rule): JsonResponse<DecisionRule>,
+) -> Result<(StatusCode, JsonResponse<DecisionRule>), ApiError> {
+    validate_rule_actions(std::slice::from_ref(&rule)).map_err(router_error)?;
+    let engine = router.decision_engine();
+    if engine.get_rule(&rule.id).await.is_some() {
+        return Err(decision_error(DecisionError::DuplicateRule(rule.id)));
+    }
+    engine
+        .register_rule(rule.clone())
+        .await
+        .map_err(decision_error)?;
+    info!("📜 添加路由规则: {}", rule.id);
+    Ok((StatusCode::CREATED, JsonResponse(rule)))
+}
+
+async fn get_rule(
+    State(router): State<Arc<IntelligentRouter>>,
+    Path(id): Path<String>,
+) -> Result<JsonResponse<DecisionRule>, ApiError> {
+    router
+        .decision_engine()
+        .get_rule(&id)
+        .await
+        .map(JsonResponse)
+        .ok_or_else(|| rule_not_found(&id))
+}
+
+async fn delete_rule(
+    State(router): State<Arc<IntelligentRouter>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if router.decision_engine().remove_rule(&id).await {
+        info!("📜 删除路由规则: {}", id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(rule_not_found(&id))
+    }
+}
+
+/// 按聊天请求的方式路由消息，并列出每条规则是否匹配及原因
+async fn explain(
+    State(router): State<Arc<IntelligentRouter>>,
+    State(agent): State<Arc<Agent>>,
+    JsonResponse(request): JsonResponse<ExplainRequest>,
+) -> JsonResponse<RouteExplanation> {
+    let mut context = decision_context(&agent, &request.message, request.metadata).await;
+    context.sender = request.sender;
+    context.role = request.role;
+    JsonResponse(RouteExplanation {
+        decision: router.route(&context).await,
+        rules: router.decision_engine().explain(&context).await,
+    })
+}
+
+/// 按规则ID和目标汇总的反馈统计
+async fn feedback_stats(State(router): State<Arc<IntelligentRouter>>) -> JsonResponse<LearningStats> {
+    JsonResponse(router.decision_engine().learning_stats().await)
+}
+
+/// 评价 `/chat` 的回复，`id` 是回复的 `details.message_id`。认证用户只能评价
+/// 自己收到的回复，管理员可以评价任何回复
+async fn submit_feedback(
+    State(router): State<Arc<IntelligentRouter>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+    JsonResponse(request): JsonResponse<FeedbackRequest>,
+) -> Result<JsonResponse<LearningRecord>, ApiError> {
+    let caller = principal
+        .as_ref()
+        .map(|Extension(principal)| principal)
+        .filter(|principal| !Permission::Admin.granted_by(&principal.permissions))
+        .map(|principal| principal.user_id.as_str());
+    let record = router
+        .record_feedback(&id, request.rating.score(), caller)
+        .await
+        .map_err(router_error)?;
+    info!("👍 收到回复评价: {} ({:?})", id, request.rating);
+    Ok(JsonResponse(record))
+}
+
+fn rule_not_found(id: &str) -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({ "error": format!("规则不存在: {id}") })),
+    )
+}
+
+fn decision_error(error: DecisionError) -> ApiError {
+    let status = match error {
+        DecisionError::DuplicateRule(_) => StatusCode::CONFLICT,
+        DecisionError::InvalidRule { .. }
+        | DecisionError::Json(_)
+        | DecisionError::InvalidExploration(_) => StatusCode::BAD_REQUEST,
+        DecisionError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, JsonResponse(json!({ "error": error.to_string() })))
+}
+
+fn router_error(error: RouterError) -> ApiError {
+    match error {
+        RouterError::Rules(error) | RouterError::Feedback(error) => decision_error(error),
+        error @ RouterError::UnknownDecision(_) => (
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({ "error": error.to_string() })),
+        ),
+        error @ RouterError::NotOwner(_) => (
+            StatusCode::FORBIDDEN,
+            JsonResponse(json!({ "error": error.to_string() })),
+        ),
+        error @ RouterError::AlreadyRated(_) => (
+            StatusCode::CONFLICT,
+            JsonResponse(json!({ "error": error.to_string() })),
+        ),
+        error => (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({ "error": error.to_string() })),
+        ),
+    }
+}
@@ -0,0 +1,243 @@
+//! 工作流API - 列出并运行已注册的工作流，查看运行记录并从指定步骤重跑
+//!
+//! 已认证请求发起的运行按调用方的权限调用工具和 A2A 智能体。
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+    routing::{get, post},
+    Extension, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::core::workflow::{
+    RunInitiator, RunSummary, StepRun, Workflow, WorkflowEngine, WorkflowError, WorkflowRun,
+};
+use crate::integrations::database::{Page, Pagination};
+use crate::services::security::AuthToken;
+
+/// 运行工作流的请求
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunWorkflowRequest {
+    #[serde(default)]
+    pub inputs: Map<String, Value>,
+}
+
+/// 运行工作流的响应，运行在后台执行，通过 `GET /workflow-runs/{run_id}` 查看结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunWorkflowResponse {
+    pub run_id: Uuid,
+    pub workflow_id: String,
+}
+
+/// 重跑请求，省略 `from_step` 时全部步骤重新执行
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RerunRequest {
+    #[serde(default)]
+    pub from_step: Option<String>,
+}
+
+/// 运行列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListRunsQuery {
+    pub workflow_id: Option<String>,
+    #[serde(default)]
+    pub offset: u32,
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+fn default_limit() -> u32 {
+    Pagination::default().limit
+}
+
+impl ListRunsQuery {
+    fn pagination(&self) -> Pagination {
+        Pagination::new(self.offset, self.limit)
+    }
+}
+
+/// 运行的步骤时间线
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunTimeline {
+    pub run_id: Uuid,
+    pub workflow_id: String,
+    pub steps: Vec<StepRun>,
+}
+
+type ApiError = (StatusCode, JsonResponse<Value>);
+
+/// 工作流端点：`GET /workflows`、`GET /workflows/:id`、`POST /workflows/:id/run`、
+/// `GET /workflows/:id/runs`，以及运行记录端点 `GET /workflow-runs`、
+/// `GET /workflow-runs/:run_id`、`GET /workflow-runs/:run_id/timeline`、
+/// `POST /workflow-runs/:run_id/rerun`
+pub fn routes<S>() -> Router<S>
+where
+    Arc<WorkflowEngine>: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/workflows", get(list_workflows))
+        .route("/workflows/:id", get(get_workflow))
+        .route("/workflows/:id/run", post(run_workflow))
+        .route("/workflows/:id/runs", get(list_workflow_runs))
+        .route("/workflow-runs", get(list_runs))
+        .route("/workflow-runs/:run_id", get(get_run))
+        .route("/workflow-runs/:run_id/timeline", get(get_run_timeline))
+        .route("/workflow-runs/:run_id/rerun", post(rerun))
+}
+
+async fn list_workflows(State(engine): State<Arc<WorkflowEngine>>) -> JsonResponse<Vec<Workflow>> {
+    JsonResponse(engine.get_all_workflows().await)
+}
+
+async fn get_workflow(
+    State(engine): State<Arc<WorkflowEngine>>,
+    Path(id): Path<String>,
+) -> Result<JsonResponse<Workflow>, ApiError> {
+    engine
+        .get_workflow(&id)
+        .await
+        .map(JsonResponse)
+        .ok_or_else(|| workflow_error(WorkflowError::NotFound(id)))
+}
+
+/// 在后台运行工作流，立即返回运行ID
+async fn run_workflow(
+    State(engine): State<Arc<WorkflowEngine>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(id): Path<String>,
+    body: Option<JsonResponse<RunWorkflowRequest>>,
+) -> Result<(StatusCode, JsonResponse<RunWorkflowResponse>), ApiError> {
+    let request = body
+        .map(|JsonResponse(request)| request)
+        .unwrap_or_default();
+    info!("🔄 API 运行工作流: {}", id);
+    let run = engine
+        .create_run(&id, request.inputs, initiator(principal))
+        .await
+        .map_err(workflow_error)?;
+    start(&engine, run).await
+}
+
+async fn list_workflow_runs(
+    State(engine): State<Arc<WorkflowEngine>>,
+    Path(id): Path<String>,
+    Query(query): Query<ListRunsQuery>,
+) -> Result<JsonResponse<Page<RunSummary>>, ApiError> {
+    engine
+        .list_runs(Some(&id), query.pagination())
+        .await
+        .map(JsonResponse)
+        .map_err(workflow_error)
+}
+
+async fn list_runs(
+    State(engine): State<Arc<WorkflowEngine>>,
+    Query(query): Query<ListRunsQuery>,
+) -> Result<JsonResponse<Page<RunSummary>>, ApiError> {
+    engine
+        .list_runs(query.workflow_id.as_deref(), query.pagination())
+        .await
+        .map(JsonResponse)
+        .map_err(workflow_error)
+}
+
+async fn get_run(
+    State(engine): State<Arc<WorkflowEngine>>,
+    Path(run_id): Path<Uuid>,
+) -> Result<JsonResponse<WorkflowRun>, ApiError> {
+    find_run(&engine, run_id).await.map(JsonResponse)
+}
+
+async fn get_run_timeline(
+    State(engine): State<Arc<WorkflowEngine>>,
+    Path(run_id): Path<Uuid>,
+) -> Result<JsonResponse<RunTimeline>, ApiError> {
+    let run = find_run(&engine, run_id).await?;
+    Ok(JsonResponse(RunTimeline {
+        run_id: run.id,
+        workflow_id: run.workflow_id.clone(),
+        steps: run.timeline().into_iter().cloned().collect(),
+    }))
+}
+
+/// 在后台从指定步骤重跑，立即返回新运行的ID
+async fn rerun(
+    State(engine): State<Arc<WorkflowEngine>>,
+    principal: Option<Extension<AuthToken>>,
+    Path(run_id): Path<Uuid>,
+    body: Option<JsonResponse<RerunRequest>>,
+) -> Result<(StatusCode, JsonResponse<RunWorkflowResponse>), ApiError> {
+    let request = body
+        .map(|JsonResponse(request)| request)
+        .unwrap_or_default();
+    info!(
+        "🔄 API 重跑工作流运行: {} (从 {:?})",
+        run_id, request.from_step
+    );
+    let run = engine
+        .create_rerun(run_id, request.from_step.as_deref(), initiator(principal))
+        .await
+        .map_err(workflow_error)?;
+    start(&engine, run).await
+}
+
+fn initiator(principal: Option<Extension<AuthToken>>) -> Option<RunInitiator> {
+    principal.map(|Extension(principal)| RunInitiator {
+        user_id: principal.user_id,
+        permissions: principal.permissions,
+    })
+}
+
+async fn find_run(engine: &WorkflowEngine, run_id: Uuid) -> Result<WorkflowRun, ApiError> {
+    engine
+        .get_run(run_id)
+        .await
+        .map_err(workflow_error)?
+        .ok_or_else(|| workflow_error(WorkflowError::RunNotFound(run_id)))
+}
+
+/// 在后台执行新建的运行，运行不随请求断开而中止
+async fn start(
+    engine: &Arc<WorkflowEngine>,
+    run: WorkflowRun,
+) -> Result<(StatusCode, JsonResponse<RunWorkflowResponse>), ApiError> {
+    match engine.start_run(run.id).await {
+        Ok(()) => Ok((
+            StatusCode::ACCEPTED,
+            JsonResponse(RunWorkflowResponse {
+                run_id: run.id,
+                workflow_id: run.workflow_id,
+            }),
+        )),
+        Err(error) => {
+            let (status, JsonResponse(mut body)) = workflow_error(error);
+            body["run_id"] = json!(run.id);
+            Err((status, JsonResponse(body)))
+        }
+    }
+}
+
+fn workflow_error(error: WorkflowError) -> ApiError {
+    let status = match error {
+        WorkflowError::NotFound(_) | WorkflowError::RunNotFound(_) => StatusCode::NOT_FOUND,
+        WorkflowError::RunFinished(_) | WorkflowError::RunActive(_) => StatusCode::CONFLICT,
+        WorkflowError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        WorkflowError::MissingInput(_)
+        | WorkflowError::UnknownInput(_)
+        | WorkflowError::UnknownStep(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let mut body = json!({ "error": error.to_string() });
+    if let WorkflowError::StepsFailed { failed, skipped } = &error {
+        body["failed"] = json!(failed);
+        body["skipped"] = json!(skipped);
+    }
+    (status, JsonResponse(body))
+}
@@ -0,0 +1,243 @@
+//! API 密钥和认证中间件测试
+//!
+//! 管理员通过 REST 接口创建服务账户和带权限范围的 API 密钥；机器客户端使用
+//! `X-API-Key` 或 `Authorization: Bearer` 访问端点。密钥以哈希保存，重启后
+//! 仍然有效。匿名请求只能在关闭 `require_auth` 时访问只读端点。
+
+use omni_agent::config::AppConfig;
+use omni_agent::integrations::database::{DataRepository, SqliteRepository};
+use omni_agent::services::security::{Permission, SecurityConfig, SecurityManager, UserRole};
+use omni_agent::ui::api::{create_app, AppState};
+use omni_agent::Agent;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+async fn serve(security: Arc<SecurityManager>, require_auth: bool) -> String {
+    let mut config = AppConfig::default();
+    config.security.require_auth = require_auth;
+    let agent = Agent::new(omni_agent::agent::AgentConfig {
+        name: "api-key-agent".to_string(),
+        description: "API 密钥测试智能体".to_string(),
+        version: "1.0.0".to_string(),
+    });
+    let app = create_app(AppState::new(agent, config).with_security(security));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+async fn open_manager(url: &str, require_auth: bool) -> Arc<SecurityManager> {
+    let repository = Arc::new(SqliteRepository::connect(url).await.unwrap());
+    let manager = SecurityManager::from_config(SecurityConfig {
+        jwt_secret: "test_secret".to_string(),
+        require_auth,
+        ..SecurityConfig::default()
+    })
+    .unwrap();
+    manager.use_store(repository).await.unwrap();
+    Arc::new(manager)
+}
+
+#[tokio::test]
+async fn test_api_key_lifecycle() {
+    let path =
+        std::env::temp_dir().join(format!("omni-agent-api-keys-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+    let security = open_manager(&url, false).await;
+    security
+        .register_user("root", "admin-password", UserRole::Admin)
+        .await
+        .unwrap();
+    let base = serve(security.clone(), false).await;
+    let client = reqwest::Client::new();
+
+    // 未启用 require_auth 时匿名请求只能访问只读端点
+    let response = client.get(format!("{base}/info")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .post(format!("{base}/chat"))
+        .json(&json!({"message": "hi"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    // 未认证和非管理员调用管理端点被拒绝
+    let response = client.get(format!("{base}/admin/api-keys")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(format!("{base}/admin/api-keys"))
+        .bearer_auth("not-a-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let login: Value = client
+        .post(format!("{base}/auth/login"))
+        .json(&json!({"username": "root", "password": "admin-password"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let admin_token = login["token"].as_str().unwrap().to_string();
+
+    let response = client
+        .post(format!("{base}/admin/service-accounts"))
+        .bearer_auth(&admin_token)
+        .json(&json!({"username": "ci-bot", "permissions": ["read", "tool:search"]}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let account: Value = response.json().await.unwrap();
+    let bot_id = account["id"].as_str().unwrap().to_string();
+    assert_eq!(account["role"], "Service");
+
+    // 服务账户不能用密码登录
+    let response = client
+        .post(format!("{base}/auth/login"))
+        .json(&json!({"username": "ci-bot", "password": ""}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    // 密钥权限不能超出所属账户
+    let response = client
+        .post(format!("{base}/admin/api-keys"))
+        .bearer_auth(&admin_token)
+        .json(&json!({"user_id": bot_id, "name": "ci", "permissions": ["admin"]}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = client
+        .post(format!("{base}/admin/api-keys"))
+        .bearer_auth(&admin_token)
+        .json(&json!({
+            "user_id": bot_id,
+            "name": "ci",
+            "permissions": ["tool:search"],
+            "expires_in_secs": 3600
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let created: Value = response.json().await.unwrap();
+    let key_id = created["id"].as_str().unwrap().to_string();
+    let secret = created["secret"].as_str().unwrap().to_string();
+    assert!(secret.starts_with(created["prefix"].as_str().unwrap()));
+    assert_eq!(created["permissions"], json!(["tool:search"]));
+
+    // 两种请求头都能认证，但服务密钥没有管理员权限
+    for request in [
+        client.get(format!("{base}/admin/api-keys")).header("X-API-Key", &secret),
+        client.get(format!("{base}/admin/api-keys")).bearer_auth(&secret),
+    ] {
+        assert_eq!(request.send().await.unwrap().status(), 403);
+    }
+    let principal = security.authenticate_api_key(&secret).await.unwrap();
+    assert_eq!(principal.user_id, bot_id);
+    assert_eq!(
+        principal.permissions,
+        vec![Permission::ToolAccess("search".to_string())]
+    );
+
+    let listed: Vec<Value> = client
+        .get(format!("{base}/admin/api-keys?user_id={bot_id}"))
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].get("secret").is_none());
+    assert!(listed[0]["last_used_at"].is_u64());
+
+    let updated: Value = client
+        .patch(format!("{base}/admin/api-keys/{key_id}"))
+        .bearer_auth(&admin_token)
+        .json(&json!({"name": "ci-renamed", "permissions": ["read"]}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(updated["name"], "ci-renamed");
+    assert_eq!(updated["permissions"], json!(["read"]));
+
+    // 密钥只以哈希保存，重启后仍然有效
+    let repository = SqliteRepository::connect(&url).await.unwrap();
+    let records = repository.list_api_keys().await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(!records[0].key_hash.contains(&secret[records[0].prefix.len()..]));
+    let restarted = open_manager(&url, false).await;
+    let principal = restarted.authenticate_api_key(&secret).await.unwrap();
+    assert_eq!(principal.permissions, vec![Permission::Read]);
+
+    let response = client
+        .delete(format!("{base}/admin/api-keys/{key_id}"))
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client
+        .get(format!("{base}/admin/api-keys/{key_id}"))
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let response = client
+        .get(format!("{base}/health"))
+        .header("X-API-Key", &secret)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_require_auth() {
+    let security = open_manager("sqlite::memory:", true).await;
+    let user_id = security
+        .create_service_account("monitor", vec![Permission::Read])
+        .await
+        .unwrap();
+    let (_, secret) = security
+        .create_api_key(&user_id, "monitor", vec![Permission::Read], None)
+        .await
+        .unwrap();
+    let base = serve(security, true).await;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{base}/health")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client.get(format!("{base}/info")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["error"].is_string());
+
+    let response = client
+        .get(format!("{base}/info"))
+        .header("X-API-Key", &secret)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
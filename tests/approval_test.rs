@@ -0,0 +1,508 @@
+//! 人工审批测试
+//!
+//! 敏感工具调用会让任务进入 input-required 状态，经批准（可修改参数）后
+//! 继续执行，被拒绝或取消时终止。认证用户只能查看和取消自己的任务与审批。
+
+use omni_agent::agent::state::AgentState;
+use omni_agent::agent::{AgentError, ApprovalDecision, LimiterConfig, TaskStatus, TurnOptions};
+use omni_agent::protocol::message::{Message, MessageContent};
+use omni_agent::services::security::{Permission, SecurityConfig, SecurityManager};
+use omni_agent::services::tools::ApprovalPolicy;
+use omni_agent::ui::api::{create_app, create_routes, AppState};
+use omni_agent::{Agent, AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// 启动提供 `mock_tool` 的 MCP 服务器，`annotations` 为工具注解
+async fn mcp_server(annotations: Value) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "tools",
+            "version": "1.0.0",
+            "description": "Test tools",
+            "capabilities": ["mock"],
+            "tools": [{
+                "name": "mock_tool",
+                "description": "Mock tool",
+                "input_schema": {"type": "object"},
+                "annotations": annotations
+            }],
+            "metadata": {}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/mock_tool/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn agent_with_tools(mcp: &MockServer, policy: ApprovalPolicy) -> Arc<Agent> {
+    Arc::new(
+        AgentBuilder::new("approval-agent", "审批测试智能体")
+            .add_mcp("tools", &mcp.uri())
+            .approval_policy(policy)
+            .build()
+            .await
+            .unwrap(),
+    )
+}
+
+fn text_message(text: &str) -> Message {
+    Message::new(
+        "user".to_string(),
+        "approval-agent".to_string(),
+        MessageContent::Text {
+            text: text.to_string(),
+        },
+        None,
+    )
+}
+
+fn options(task_id: &str) -> TurnOptions {
+    TurnOptions {
+        task_id: Some(task_id.to_string()),
+        ..Default::default()
+    }
+}
+
+async fn wait_for_approval(agent: &Agent, task_id: &str) {
+    while agent.approvals.get(task_id).await.is_none() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+async fn tool_calls(mcp: &MockServer) -> Vec<Value> {
+    mcp.received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.url.path() == "/tools/mock_tool/call")
+        .map(|r| serde_json::from_slice::<Value>(&r.body).unwrap()["parameters"].take())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_destructive_tool_waits_for_approval() {
+    let mcp = mcp_server(json!({"destructiveHint": true})).await;
+    let agent = agent_with_tools(&mcp, ApprovalPolicy::default()).await;
+
+    let turn = {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .run_turn("s1", text_message("please use a tool"), options("t1"))
+                .await
+        })
+    };
+
+    // 第一次调用：批准并修改参数
+    wait_for_approval(&agent, "t1").await;
+    let pending = agent.approvals.get("t1").await.unwrap();
+    assert_eq!(pending.tool, "mock_tool");
+    assert_eq!(pending.arguments["query"], "please use a tool");
+    assert_eq!(
+        agent.tasks.get("t1").await.unwrap().status,
+        TaskStatus::InputRequired
+    );
+    let session = agent.sessions.get("s1").await.unwrap();
+    assert_eq!(
+        *session.state_machine.read().await.get_state(),
+        AgentState::InputRequired
+    );
+    agent
+        .approvals
+        .resolve(
+            "t1",
+            ApprovalDecision::Approve {
+                arguments: Some(json!({"query": "edited"})),
+            },
+        )
+        .await
+        .unwrap();
+
+    // 模拟LLM再次请求调用工具：拒绝
+    wait_for_approval(&agent, "t1").await;
+    agent
+        .approvals
+        .resolve(
+            "t1",
+            ApprovalDecision::Deny {
+                reason: Some("too risky".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        turn.await.unwrap().unwrap_err(),
+        AgentError::ApprovalDenied {
+            tool: "mock_tool".to_string(),
+            reason: "too risky".to_string(),
+        }
+    );
+    assert_eq!(
+        agent.tasks.get("t1").await.unwrap().status,
+        TaskStatus::Rejected
+    );
+    assert_eq!(
+        *session.state_machine.read().await.get_state(),
+        AgentState::Idle
+    );
+
+    // 只有批准的调用到达了 MCP 服务器，且使用修改后的参数
+    let calls = tool_calls(&mcp).await;
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0], json!({"query": "edited"}));
+}
+
+#[tokio::test]
+async fn test_tool_access_does_not_skip_approval() {
+    let mcp = mcp_server(json!({"destructiveHint": true})).await;
+    let agent = agent_with_tools(&mcp, ApprovalPolicy::default()).await;
+
+    let turn = {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .run_turn(
+                    "s1",
+                    text_message("please use a tool"),
+                    TurnOptions {
+                        permissions: vec![Permission::ToolAccess("mock_tool".to_string())],
+                        restrict_tools: true,
+                        ..options("t1")
+                    },
+                )
+                .await
+        })
+    };
+
+    // 持有调用权限的受限调用方仍需等待审批
+    wait_for_approval(&agent, "t1").await;
+    assert_eq!(
+        agent.tasks.get("t1").await.unwrap().status,
+        TaskStatus::InputRequired
+    );
+    agent.tasks.cancel("t1").await.unwrap();
+    assert_eq!(
+        turn.await.unwrap().unwrap_err(),
+        AgentError::Canceled("t1".to_string())
+    );
+    assert!(tool_calls(&mcp).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_tool_approve_permission_pre_approves() {
+    let mcp = mcp_server(json!({"readOnlyHint": true})).await;
+    let agent = AgentBuilder::new("approval-agent", "审批测试智能体")
+        .add_mcp("tools", &mcp.uri())
+        .approval_policy(ApprovalPolicy::default().with_pattern("mock_*"))
+        .max_tool_iterations(2)
+        .build()
+        .await
+        .unwrap();
+
+    let result = agent
+        .run_turn(
+            "s1",
+            text_message("please use a tool"),
+            TurnOptions {
+                permissions: vec![
+                    Permission::ToolAccess("mock_tool".to_string()),
+                    Permission::ToolApprove("mock_*".to_string()),
+                ],
+                restrict_tools: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+    // 未经审批直接执行，直到触发工具循环上限
+    assert_eq!(result.unwrap_err(), AgentError::ToolLoopLimit(2));
+    assert_eq!(tool_calls(&mcp).await.len(), 2);
+    assert!(agent.approvals.list().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_cancel_while_waiting_for_approval() {
+    let mcp = mcp_server(json!({})).await;
+    let agent = agent_with_tools(&mcp, ApprovalPolicy::default()).await;
+
+    let turn = {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .run_turn("s1", text_message("please use a tool"), options("t1"))
+                .await
+        })
+    };
+    wait_for_approval(&agent, "t1").await;
+    agent.tasks.cancel("t1").await.unwrap();
+
+    assert_eq!(
+        turn.await.unwrap().unwrap_err(),
+        AgentError::Canceled("t1".to_string())
+    );
+    assert!(agent.approvals.get("t1").await.is_none());
+    assert!(tool_calls(&mcp).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_waiting_for_approval_frees_worker_slot() {
+    let mcp = mcp_server(json!({"destructiveHint": true})).await;
+    let agent = Arc::new(
+        AgentBuilder::new("approval-agent", "审批测试智能体")
+            .add_mcp("tools", &mcp.uri())
+            .concurrency(LimiterConfig {
+                max_in_flight: 1,
+                max_queue: 0,
+            })
+            .max_tool_iterations(1)
+            .build()
+            .await
+            .unwrap(),
+    );
+
+    let turn = {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .run_turn("s1", text_message("please use a tool"), options("t1"))
+                .await
+        })
+    };
+    wait_for_approval(&agent, "t1").await;
+    assert_eq!(agent.limiter.stats().in_flight, 0);
+
+    // 等待审批的任务不占用唯一的工作槽位
+    agent
+        .run_turn("s2", text_message("你好"), TurnOptions::default())
+        .await
+        .unwrap();
+
+    agent
+        .approvals
+        .resolve("t1", ApprovalDecision::Approve { arguments: None })
+        .await
+        .unwrap();
+    assert_eq!(
+        turn.await.unwrap().unwrap_err(),
+        AgentError::ToolLoopLimit(1)
+    );
+    assert_eq!(agent.limiter.stats().in_flight, 0);
+    assert_eq!(agent.limiter.stats().queued, 0);
+}
+
+#[tokio::test]
+async fn test_approval_api() {
+    let mcp = mcp_server(json!({"destructiveHint": true})).await;
+    Mock::given(method("POST"))
+        .and(path("/tools/mock_tool/call"))
+        .and(body_partial_json(json!({"parameters": {"query": "safe"}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mcp)
+        .await;
+    let agent = agent_with_tools(&mcp, ApprovalPolicy::default()).await;
+
+    let app = create_routes().with_state(AppState::new(agent.clone(), AppConfig::default()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+
+    let chat = {
+        let client = client.clone();
+        let base = base.clone();
+        tokio::spawn(async move {
+            client
+                .post(format!("{base}/chat"))
+                .json(&json!({"message": "please use a tool", "session_id": "s1", "task_id": "t1"}))
+                .send()
+                .await
+                .unwrap()
+                .json::<Value>()
+                .await
+                .unwrap()
+        })
+    };
+    wait_for_approval(&agent, "t1").await;
+
+    let task: Value = client
+        .get(format!("{base}/tasks/t1"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(task["status"], "input-required");
+    let pending: Value = client
+        .get(format!("{base}/tasks/t1/approval"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(pending["tool"], "mock_tool");
+    let listed: Value = client
+        .get(format!("{base}/approvals"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    let approved = client
+        .post(format!("{base}/tasks/t1/approve"))
+        .json(&json!({"arguments": {"query": "safe"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(approved.status(), reqwest::StatusCode::OK);
+
+    // 模拟LLM再次请求调用工具：拒绝
+    wait_for_approval(&agent, "t1").await;
+    let denied = client
+        .post(format!("{base}/tasks/t1/deny"))
+        .json(&json!({"reason": "enough"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(denied.status(), reqwest::StatusCode::OK);
+
+    let response = chat.await.unwrap();
+    assert_eq!(response["source"], "denied");
+    assert_eq!(response["details"]["tool"], "mock_tool");
+
+    let missing = client
+        .post(format!("{base}/tasks/t1/approve"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+    mcp.verify().await;
+}
+
+#[tokio::test]
+async fn test_tasks_are_visible_to_their_owner() {
+    let mcp = mcp_server(json!({"destructiveHint": true})).await;
+    let agent = agent_with_tools(&mcp, ApprovalPolicy::default()).await;
+    let security = Arc::new(
+        SecurityManager::from_config(SecurityConfig {
+            jwt_secret: "test_secret".to_string(),
+            require_auth: true,
+            ..SecurityConfig::default()
+        })
+        .unwrap(),
+    );
+    let mut keys = Vec::new();
+    for (name, permissions) in [
+        ("alice", vec![Permission::Read, Permission::Write]),
+        ("bob", vec![Permission::Read, Permission::Write]),
+        ("ops", vec![Permission::Admin]),
+    ] {
+        let user_id = security
+            .create_service_account(name, permissions.clone())
+            .await
+            .unwrap();
+        let key = security
+            .create_api_key(&user_id, name, permissions, None)
+            .await
+            .unwrap()
+            .1;
+        keys.push((user_id, key));
+    }
+    let [(alice_id, alice), (_, bob), (_, admin)] = <[_; 3]>::try_from(keys).unwrap();
+    let app = create_app(
+        AppState::new(agent.clone(), AppConfig::default()).with_security(security),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+
+    let turn = {
+        let agent = agent.clone();
+        let owner = alice_id.clone();
+        tokio::spawn(async move {
+            agent
+                .run_turn(
+                    "s1",
+                    text_message("please use a tool"),
+                    TurnOptions {
+                        owner: Some(owner),
+                        ..options("t1")
+                    },
+                )
+                .await
+        })
+    };
+    wait_for_approval(&agent, "t1").await;
+    let get = |key: &str, route: &str| {
+        client
+            .get(format!("{base}{route}"))
+            .header("X-API-Key", key)
+            .send()
+    };
+
+    // 其他用户看不到也不能取消该任务
+    for route in ["/tasks", "/approvals"] {
+        let listed: Vec<Value> = get(&bob, route).await.unwrap().json().await.unwrap();
+        assert!(listed.is_empty(), "{route}");
+    }
+    for route in ["/tasks/t1", "/tasks/t1/approval"] {
+        assert_eq!(get(&bob, route).await.unwrap().status(), 404);
+    }
+    let response = client
+        .delete(format!("{base}/tasks/t1"))
+        .header("X-API-Key", &bob)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let response: Value = client
+        .post(format!("{base}/tasks/cancel"))
+        .header("X-API-Key", &bob)
+        .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tasks/cancel", "params": {"id": "t1"}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["error"]["code"], -32001);
+
+    // 所有者和管理员可以查看，所有者可以取消
+    let task: Value = get(&alice, "/tasks/t1").await.unwrap().json().await.unwrap();
+    assert_eq!(task["owner"], alice_id.as_str());
+    let approvals: Vec<Value> = get(&alice, "/approvals").await.unwrap().json().await.unwrap();
+    assert_eq!(approvals[0]["owner"], alice_id.as_str());
+    let tasks: Vec<Value> = get(&admin, "/tasks").await.unwrap().json().await.unwrap();
+    assert_eq!(tasks.len(), 1);
+    let response = client
+        .delete(format!("{base}/tasks/t1"))
+        .header("X-API-Key", &alice)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(matches!(turn.await.unwrap(), Err(AgentError::Canceled(_))));
+    assert!(tool_calls(&mcp).await.is_empty());
+}
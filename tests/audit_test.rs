@@ -0,0 +1,257 @@
+//! 审计日志测试
+//!
+//! 工具调用、A2A 委托、LLM 调用、访问拒绝和管理操作都记入哈希链审计日志。
+//! 管理员通过 `/audit` 按用户、动作和时间查询，通过 `/audit/export` 导出
+//! JSON Lines，通过 `/audit/verify` 校验哈希链；日志文件重启后继续追加。
+
+use omni_agent::core::router::IntelligentRouter;
+use omni_agent::services::audit::{AuditConfig, AuditEvent, AuditTrail};
+use omni_agent::services::security::{SecurityConfig, SecurityManager, UserRole};
+use omni_agent::ui::api::{create_app, AppState};
+use omni_agent::{Agent, AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn serve(agent: Agent, security: Arc<SecurityManager>) -> String {
+    // 天气问题委托给 info_agent
+    let router = IntelligentRouter::new();
+    let rule = serde_json::from_value(json!({
+        "id": "route_weather", "name": "天气路由", "description": "", "condition": "天气",
+        "action": "a2a:info_agent", "priority": 100, "enabled": true
+    }))
+    .unwrap();
+    router.decision_engine().replace_rules(vec![rule]).await.unwrap();
+    let app = create_app(
+        AppState::new(agent, AppConfig::default())
+            .with_router(router)
+            .with_security(security),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+async fn mock_peers() -> (MockServer, MockServer) {
+    let mcp = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "tools",
+            "version": "1.0.0",
+            "description": "Test tools",
+            "capabilities": ["mock"],
+            "tools": [{"name": "mock_tool", "description": "Mock tool", "input_schema": {"type": "object"}}],
+            "metadata": {}
+        })))
+        .mount(&mcp)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/mock_tool/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .mount(&mcp)
+        .await;
+    let peer = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "info",
+            "version": "1.0.0",
+            "description": "Weather and time information",
+            "capabilities": ["weather", "time"],
+            "endpoints": ["/messages"],
+            "metadata": {}
+        })))
+        .mount(&peer)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": uuid::Uuid::new_v4(),
+            "sender": "info_agent",
+            "recipient": "audit-agent",
+            "content": {"type": "Text", "text": "晴，21°C"},
+            "timestamp": chrono::Utc::now()
+        })))
+        .mount(&peer)
+        .await;
+    (mcp, peer)
+}
+
+#[tokio::test]
+async fn test_audit_trail_endpoints() {
+    let directory = std::env::temp_dir().join(format!("omni-agent-audit-{}", uuid::Uuid::new_v4()));
+    let config = AuditConfig {
+        directory: directory.display().to_string(),
+        ..AuditConfig::default()
+    };
+    let audit = Arc::new(AuditTrail::open(config.clone()).await.unwrap());
+    let (mcp, peer) = mock_peers().await;
+    let agent = AgentBuilder::new("audit-agent", "审计测试智能体")
+        .add_mcp("tools", &mcp.uri())
+        .add_a2a("info_agent", &peer.uri())
+        .audit(audit.clone())
+        .build()
+        .await
+        .unwrap();
+    let security = SecurityManager::from_config(SecurityConfig {
+        jwt_secret: "test_secret".to_string(),
+        require_auth: true,
+        ..SecurityConfig::default()
+    })
+    .unwrap()
+    .with_audit(audit.clone());
+    let admin_id = security
+        .register_user("root", "admin-password", UserRole::Admin)
+        .await
+        .unwrap();
+    let base = serve(agent, Arc::new(security)).await;
+    let client = reqwest::Client::new();
+
+    let login: Value = client
+        .post(format!("{base}/auth/login"))
+        .json(&json!({"username": "root", "password": "admin-password"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = login["token"].as_str().unwrap().to_string();
+
+    for message in ["please use a tool", "北京今天天气怎么样"] {
+        let response = client
+            .post(format!("{base}/chat"))
+            .bearer_auth(&token)
+            .json(&json!({"message": message}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+    let response = client.get(format!("{base}/sessions")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(format!("{base}/admin/api-keys"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // 审计端点只对管理员开放
+    let response = client.get(format!("{base}/audit")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    let query = |query: &str| {
+        let request = client
+            .get(format!("{base}/audit?{query}"))
+            .bearer_auth(&token)
+            .send();
+        async move { request.await.unwrap().json::<Vec<Value>>().await.unwrap() }
+    };
+    let llm_calls = query(&format!("action=llm_call&user_id={admin_id}")).await;
+    assert!(!llm_calls.is_empty());
+    for call in &llm_calls {
+        assert!(call["metadata"]["model"].is_string());
+        assert!(call["metadata"]["usage"]["total_tokens"].is_u64());
+        // 默认不记录提示词和回复
+        assert!(call["metadata"].get("prompt").is_none());
+    }
+    let tool_calls = query("action=tool_call").await;
+    assert_eq!(tool_calls[0]["resource"], "mock_tool");
+    assert_eq!(tool_calls[0]["metadata"]["source"], "mcp");
+    assert_eq!(tool_calls[0]["user_id"], admin_id.as_str());
+    let delegations = query("action=a2a_delegation").await;
+    assert_eq!(delegations.len(), 1);
+    assert_eq!(delegations[0]["resource"], "info_agent");
+    assert!(delegations[0]["success"].as_bool().unwrap());
+    let denials = query("action=access_denied").await;
+    assert!(denials.iter().any(|log| log["resource"] == "GET /sessions"));
+    let admin_actions = query(&format!("action=admin_action&user_id={admin_id}")).await;
+    assert!(admin_actions
+        .iter()
+        .any(|log| log["resource"] == "GET /admin/api-keys" && log["metadata"]["status"] == 200));
+
+    // 时间范围和数量限制
+    let now = chrono::Utc::now().timestamp() as u64;
+    assert!(query(&format!("since={}", now + 3600)).await.is_empty());
+    let latest = query(&format!("until={}&limit=2", now + 3600)).await;
+    assert_eq!(latest.len(), 2);
+    assert!(latest[0]["seq"].as_u64() > latest[1]["seq"].as_u64());
+
+    let response = client
+        .get(format!("{base}/audit/export"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let exported: Vec<Value> = response
+        .text()
+        .await
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(exported[0]["seq"], 1);
+    for pair in exported.windows(2) {
+        assert_eq!(pair[1]["prev_hash"], pair[0]["hash"]);
+    }
+
+    let report: Value = client
+        .get(format!("{base}/audit/verify"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["valid"], true);
+
+    // 重启后从最后一条记录继续
+    let reopened = AuditTrail::open(config).await.unwrap();
+    let last = audit.recent(Some(1)).await.remove(0);
+    let next = reopened.record(AuditEvent::new("restart", "audit-agent")).await;
+    assert_eq!(next.seq, last.seq + 1);
+    assert_eq!(next.prev_hash, last.hash);
+    assert!(reopened.verify().await.unwrap().valid);
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+#[tokio::test]
+async fn test_record_content() {
+    let audit = Arc::new(AuditTrail::new(AuditConfig {
+        record_content: true,
+        ..AuditConfig::default()
+    }));
+    let agent = AgentBuilder::new("audit-agent", "审计测试智能体")
+        .audit(audit.clone())
+        .build()
+        .await
+        .unwrap();
+    agent
+        .process_message(omni_agent::protocol::message::Message::new(
+            "bob".to_string(),
+            "audit-agent".to_string(),
+            omni_agent::protocol::message::MessageContent::Text {
+                text: "你好".to_string(),
+            },
+            None,
+        ))
+        .await
+        .unwrap();
+
+    let logs = audit.recent(None).await;
+    let call = logs.iter().find(|log| log.action == "llm_call").unwrap();
+    assert_eq!(call.user_id, "bob");
+    assert_eq!(call.metadata["prompt"], "你好");
+    assert!(call.metadata["reply"].is_string());
+    assert_eq!(call.metadata["usage"]["estimated"], true);
+}
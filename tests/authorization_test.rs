@@ -0,0 +1,355 @@
+//! 授权测试
+//!
+//! 认证中间件按路由策略检查每个请求所需的权限；聊天请求委托 A2A 智能体需要
+//! `agent:<名称>` 权限，调用工具需要 `tool:<名称>` 权限。会话归首个使用它的
+//! 认证用户所有。拒绝返回统一的 401/403 JSON 错误并记入审计日志。
+
+use omni_agent::core::router::IntelligentRouter;
+use omni_agent::services::security::{
+    Permission, RouteRule, SecurityConfig, SecurityManager, UserRole,
+};
+use omni_agent::ui::api::{create_app, AppState};
+use omni_agent::{Agent, AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn manager(routes: Vec<RouteRule>) -> Arc<SecurityManager> {
+    Arc::new(
+        SecurityManager::from_config(SecurityConfig {
+            jwt_secret: "test_secret".to_string(),
+            require_auth: true,
+            routes,
+            ..SecurityConfig::default()
+        })
+        .unwrap(),
+    )
+}
+
+async fn serve(agent: Agent, security: Arc<SecurityManager>) -> String {
+    // 天气问题委托给 info_agent
+    let router = IntelligentRouter::new();
+    let rule = serde_json::from_value(json!({
+        "id": "route_weather", "name": "天气路由", "description": "", "condition": "天气",
+        "action": "a2a:info_agent", "priority": 100, "enabled": true
+    }))
+    .unwrap();
+    router.decision_engine().replace_rules(vec![rule]).await.unwrap();
+    let app = create_app(
+        AppState::new(agent, AppConfig::default())
+            .with_router(router)
+            .with_security(security),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+fn plain_agent() -> Agent {
+    Agent::new(omni_agent::agent::AgentConfig {
+        name: "authz-agent".to_string(),
+        description: "授权测试智能体".to_string(),
+        version: "1.0.0".to_string(),
+    })
+}
+
+/// 为 `permissions` 创建服务账户和 API 密钥，返回密钥
+async fn api_key(security: &SecurityManager, name: &str, permissions: &[&str]) -> String {
+    let permissions: Vec<Permission> = permissions
+        .iter()
+        .map(|p| Permission::parse(p).unwrap())
+        .collect();
+    let user_id = security
+        .create_service_account(name, permissions.clone())
+        .await
+        .unwrap();
+    security
+        .create_api_key(&user_id, name, permissions, None)
+        .await
+        .unwrap()
+        .1
+}
+
+#[tokio::test]
+async fn test_route_policy() {
+    let security = manager(Vec::new());
+    security
+        .register_user("guest", "guest-password", UserRole::Guest)
+        .await
+        .unwrap();
+    let base = serve(plain_agent(), security.clone()).await;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{base}/info")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert!(response.json::<Value>().await.unwrap()["error"].is_string());
+
+    let login: Value = client
+        .post(format!("{base}/auth/login"))
+        .json(&json!({"username": "guest", "password": "guest-password"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = login["token"].as_str().unwrap();
+
+    // 访客只有 read 权限
+    let response = client
+        .get(format!("{base}/sessions"))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    for request in [
+        client.delete(format!("{base}/sessions/s1")),
+        client.post(format!("{base}/chat")).json(&json!({"message": "hi"})),
+        client.get(format!("{base}/admin/api-keys")),
+    ] {
+        let response = request.bearer_auth(token).send().await.unwrap();
+        assert_eq!(response.status(), 403);
+        assert!(response.json::<Value>().await.unwrap()["error"].is_string());
+    }
+
+    let denials: Vec<String> = security
+        .get_audit_logs(None)
+        .await
+        .into_iter()
+        .filter(|log| log.action == "access_denied" && !log.success)
+        .map(|log| log.resource)
+        .collect();
+    for resource in ["GET /info", "DELETE /sessions/s1", "POST /chat", "GET /admin/api-keys"] {
+        assert!(denials.iter().any(|r| r == resource), "{resource}: {denials:?}");
+    }
+
+    // 配置的策略替换内置策略，未匹配的端点需要 admin 权限
+    let security = manager(vec![
+        RouteRule::public(Some("GET"), "/info"),
+        RouteRule::new(Some("GET"), "/sessions", Permission::Read),
+    ]);
+    let key = api_key(&security, "reader", &["read"]).await;
+    let base = serve(plain_agent(), security).await;
+    let response = client.get(format!("{base}/info")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .get(format!("{base}/sessions"))
+        .header("X-API-Key", &key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .get(format!("{base}/health"))
+        .header("X-API-Key", &key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+async fn test_chat_enforces_tool_and_agent_access() {
+    let mcp = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "tools",
+            "version": "1.0.0",
+            "description": "Test tools",
+            "capabilities": ["mock"],
+            "tools": [{"name": "mock_tool", "description": "Mock tool", "input_schema": {"type": "object"}}],
+            "metadata": {}
+        })))
+        .mount(&mcp)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/mock_tool/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .mount(&mcp)
+        .await;
+    let peer = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "info",
+            "version": "1.0.0",
+            "description": "Weather and time information",
+            "capabilities": ["weather", "time"],
+            "endpoints": ["/messages"],
+            "metadata": {}
+        })))
+        .mount(&peer)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": uuid::Uuid::new_v4(),
+            "sender": "info_agent",
+            "recipient": "authz-agent",
+            "content": {"type": "Text", "text": "晴，21°C"},
+            "timestamp": chrono::Utc::now()
+        })))
+        .mount(&peer)
+        .await;
+
+    let agent = AgentBuilder::new("authz-agent", "授权测试智能体")
+        .add_mcp("tools", &mcp.uri())
+        .add_a2a("info_agent", &peer.uri())
+        .build()
+        .await
+        .unwrap();
+    let security = manager(Vec::new());
+    let restricted = api_key(&security, "restricted", &["execute"]).await;
+    let trusted = api_key(
+        &security,
+        "trusted",
+        &["execute", "tool:mock_tool", "agent:info_agent"],
+    )
+    .await;
+    let base = serve(agent, security.clone()).await;
+    let client = reqwest::Client::new();
+    let chat = |key: &str, message: &str| {
+        client
+            .post(format!("{base}/chat"))
+            .header("X-API-Key", key)
+            .json(&json!({"message": message}))
+            .send()
+    };
+    let tool_calls = || async {
+        mcp.received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|r| r.url.path() == "/tools/mock_tool/call")
+            .count()
+    };
+
+    let response = chat(&restricted, "please use a tool").await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("tool:mock_tool"));
+    assert_eq!(tool_calls().await, 0);
+
+    let response = chat(&restricted, "北京今天天气怎么样").await.unwrap();
+    assert_eq!(response.status(), 403);
+    let peer_calls = || async {
+        peer.received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|r| r.url.path() == "/messages")
+            .count()
+    };
+    assert_eq!(peer_calls().await, 0);
+
+    let logs = security.get_audit_logs(None).await;
+    for resource in ["tool:mock_tool", "agent:info_agent"] {
+        assert!(logs
+            .iter()
+            .any(|log| log.action == "access_denied" && log.resource == resource));
+    }
+
+    let response = chat(&trusted, "please use a tool").await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(tool_calls().await > 0);
+    let body: Value = chat(&trusted, "北京今天天气怎么样")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["source"], "a2a_agent");
+    assert_eq!(body["message"], "晴，21°C");
+}
+
+#[tokio::test]
+async fn test_sessions_belong_to_their_owner() {
+    let security = manager(Vec::new());
+    let alice = api_key(&security, "alice", &["read", "execute", "write"]).await;
+    let bob = api_key(&security, "bob", &["read", "execute", "write"]).await;
+    let admin = api_key(&security, "ops", &["admin"]).await;
+    let base = serve(plain_agent(), security.clone()).await;
+    let client = reqwest::Client::new();
+    let chat = |key: &str, body: Value| {
+        client
+            .post(format!("{base}/chat"))
+            .header("X-API-Key", key)
+            .json(&body)
+            .send()
+    };
+
+    // 请求中的 user_id 被忽略，会话归认证用户所有
+    let response = chat(&alice, json!({"message": "hi", "session_id": "s-alice", "user_id": "bob"}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let sessions = |key: &str| {
+        let request = client
+            .get(format!("{base}/sessions"))
+            .header("X-API-Key", key)
+            .send();
+        async move { request.await.unwrap().json::<Vec<Value>>().await.unwrap() }
+    };
+    let owned = sessions(&alice).await;
+    assert_eq!(owned.len(), 1);
+    assert_eq!(owned[0]["id"], "s-alice");
+    let owner = owned[0]["owner"].as_str().unwrap().to_string();
+    assert!(sessions(&bob).await.is_empty());
+    assert_eq!(sessions(&admin).await.len(), 1);
+
+    // 其他用户不能继续、查看、修改或删除该会话
+    let response = chat(&bob, json!({"message": "hi", "session_id": "s-alice"}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    for request in [
+        client.get(format!("{base}/sessions/s-alice")),
+        client.delete(format!("{base}/sessions/s-alice")),
+        client.put(format!("{base}/sessions/s-alice/llm")).json(&json!({})),
+    ] {
+        let response = request.header("X-API-Key", &bob).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+    let response = client
+        .post(format!("{base}/buffer"))
+        .header("X-API-Key", &bob)
+        .json(&json!({"action": "clear", "session_id": "s-alice"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.json::<Value>().await.unwrap()["status"], "error");
+    assert!(security
+        .get_audit_logs(None)
+        .await
+        .iter()
+        .any(|log| log.action == "access_denied" && log.resource == "session:s-alice"));
+
+    // 所有者和管理员可以访问
+    let response = chat(&alice, json!({"message": "again", "session_id": "s-alice"}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let session: Value = client
+        .get(format!("{base}/sessions/s-alice"))
+        .header("X-API-Key", &admin)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(session["session"]["owner"], owner.as_str());
+    let response = client
+        .delete(format!("{base}/sessions/s-alice"))
+        .header("X-API-Key", &alice)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+}
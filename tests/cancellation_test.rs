@@ -0,0 +1,365 @@
+//! 取消与超时测试
+//!
+//! 覆盖任务取消 API、请求期限、工具循环终止开关，以及向 MCP 服务器和
+//! A2A 对端传播取消。
+
+use async_trait::async_trait;
+use omni_agent::a2a::client::{A2AClient, A2AContent, A2AError, A2AMessage};
+use omni_agent::agent::state::AgentState;
+use omni_agent::agent::{AgentError, TaskStatus, TurnOptions};
+use omni_agent::llm::providers::{LLMError, LLMProvider, LLMRequest, LLMResponse};
+use omni_agent::llm::LLMConfig;
+use omni_agent::mcp::client::MCPClient;
+use omni_agent::protocol::message::{Message, MessageContent};
+use omni_agent::ui::api::{create_routes, AppState};
+use omni_agent::{Agent, AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// 永远不返回的提供商，用于模拟长时间运行的LLM调用
+struct HangingProvider;
+
+#[async_trait]
+impl LLMProvider for HangingProvider {
+    async fn chat(&self, _request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        std::future::pending().await
+    }
+
+    async fn chat_stream(&self, _request: LLMRequest) -> Result<String, LLMError> {
+        std::future::pending().await
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "hanging"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+async fn hanging_agent() -> Arc<Agent> {
+    let agent = AgentBuilder::new("cancel-agent", "取消测试智能体")
+        .build()
+        .await
+        .unwrap();
+    {
+        let mut llm = agent.llm.write().await;
+        llm.manager
+            .add_provider("hanging".to_string(), Box::new(HangingProvider))
+            .await;
+        llm.config = LLMConfig {
+            provider: "hanging".to_string(),
+            model: "hanging-model".to_string(),
+            temperature: 0.0,
+            max_tokens: 16,
+            use_mock: false,
+        };
+    }
+    Arc::new(agent)
+}
+
+async fn serve(agent: Arc<Agent>) -> String {
+    let app = create_routes().with_state(AppState::new(agent, AppConfig::default()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+fn text_message(text: &str) -> Message {
+    Message::new(
+        "user".to_string(),
+        "cancel-agent".to_string(),
+        MessageContent::Text {
+            text: text.to_string(),
+        },
+        None,
+    )
+}
+
+/// 启动提供 `mock_tool` 的 MCP 服务器
+async fn mcp_server_with_tool(tool_response: ResponseTemplate) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "tools",
+            "version": "1.0.0",
+            "description": "Test tools",
+            "capabilities": ["mock"],
+            "tools": [{
+                "name": "mock_tool",
+                "description": "Mock tool",
+                "input_schema": {"type": "object"}
+            }],
+            "metadata": {}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/mock_tool/call"))
+        .respond_with(tool_response)
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_delete_task_cancels_running_request() {
+    let agent = hanging_agent().await;
+    let base = serve(agent.clone()).await;
+    let client = reqwest::Client::new();
+
+    let chat = {
+        let client = client.clone();
+        let base = base.clone();
+        tokio::spawn(async move {
+            client
+                .post(format!("{base}/chat"))
+                .json(&json!({"message": "你好", "session_id": "s1", "task_id": "task-1"}))
+                .send()
+                .await
+                .unwrap()
+                .json::<Value>()
+                .await
+                .unwrap()
+        })
+    };
+
+    // 等待任务开始运行
+    while agent.tasks.get("task-1").await.is_none() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let task: Value = client
+        .get(format!("{base}/tasks/task-1"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(task["status"], "working");
+
+    let canceled = client
+        .delete(format!("{base}/tasks/task-1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(canceled.status(), reqwest::StatusCode::OK);
+    assert_eq!(canceled.json::<Value>().await.unwrap()["status"], "canceled");
+
+    let response = chat.await.unwrap();
+    assert_eq!(response["source"], "canceled");
+    assert_eq!(response["details"]["task_id"], "task-1");
+
+    // 会话回到空闲状态，已结束的任务不能再次取消
+    let session = agent.sessions.get("s1").await.unwrap();
+    assert_eq!(*session.state_machine.read().await.get_state(), AgentState::Idle);
+    let again = client
+        .delete(format!("{base}/tasks/task-1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(again.status(), reqwest::StatusCode::CONFLICT);
+
+    let missing = client
+        .delete(format!("{base}/tasks/unknown"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_request_deadline() {
+    let agent = hanging_agent().await;
+
+    let result = agent
+        .run_turn(
+            "s1",
+            text_message("你好"),
+            TurnOptions {
+                task_id: Some("slow".to_string()),
+                timeout: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        )
+        .await;
+    assert!(matches!(result, Err(AgentError::Timeout { .. })));
+
+    let task = agent.tasks.get("slow").await.unwrap();
+    assert_eq!(task.status, TaskStatus::Failed);
+    assert_eq!(task.error.as_deref(), Some("deadline exceeded"));
+
+    let base = serve(agent).await;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/chat"))
+        .json(&json!({"message": "你好", "timeout_ms": 50}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+}
+
+#[tokio::test]
+async fn test_tool_loop_kill_switch() {
+    let mcp = mcp_server_with_tool(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .await;
+    let agent = AgentBuilder::new("cancel-agent", "取消测试智能体")
+        .add_mcp("tools", &mcp.uri())
+        .max_tool_iterations(3)
+        .build()
+        .await
+        .unwrap();
+
+    // 模拟LLM对包含 "tool" 的输入总是请求调用工具，形成失控循环
+    let result = agent.process_message(text_message("please use a tool")).await;
+    assert_eq!(result.unwrap_err(), AgentError::ToolLoopLimit(3));
+
+    let calls = mcp
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.url.path() == "/tools/mock_tool/call")
+        .count();
+    assert_eq!(calls, 3);
+
+    let task = agent.tasks.list().await.remove(0);
+    assert_eq!(task.status, TaskStatus::Failed);
+}
+
+#[tokio::test]
+async fn test_cancel_notifies_mcp_server() {
+    let mcp = mcp_server_with_tool(
+        ResponseTemplate::new(200)
+            .set_body_json(json!({"ok": true}))
+            .set_delay(Duration::from_secs(30)),
+    )
+    .await;
+    Mock::given(method("POST"))
+        .and(path("/notifications"))
+        .and(body_partial_json(json!({"method": "notifications/cancelled"})))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&mcp)
+        .await;
+
+    let client = MCPClient::new(mcp.uri());
+    let cancel = CancellationToken::new();
+    let call = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            client
+                .call_tool_with_cancel("mock_tool", json!({}), &cancel)
+                .await
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    cancel.cancel();
+
+    assert!(call.await.unwrap().is_err());
+    mcp.verify().await;
+}
+
+#[tokio::test]
+async fn test_cancel_propagates_to_a2a_peer() {
+    let peer = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+        .mount(&peer)
+        .await;
+
+    let message = A2AMessage {
+        id: uuid::Uuid::new_v4(),
+        sender: "cancel-agent".to_string(),
+        recipient: "peer".to_string(),
+        content: A2AContent::Text {
+            text: "你好".to_string(),
+        },
+        timestamp: chrono::Utc::now(),
+    };
+    Mock::given(method("POST"))
+        .and(path("/tasks/cancel"))
+        .and(body_partial_json(json!({
+            "method": "tasks/cancel",
+            "params": {"id": message.id.to_string()}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"jsonrpc": "2.0"})))
+        .expect(1)
+        .mount(&peer)
+        .await;
+
+    let client = A2AClient::new(peer.uri());
+    let cancel = CancellationToken::new();
+    let send = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move { client.send_message_with_cancel(message, &cancel).await })
+    };
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    cancel.cancel();
+
+    assert!(matches!(send.await.unwrap(), Err(A2AError::Cancelled(_))));
+    peer.verify().await;
+}
+
+#[tokio::test]
+async fn test_tasks_cancel_rpc() {
+    let agent = hanging_agent().await;
+    let base = serve(agent.clone()).await;
+
+    let turn = {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .run_turn(
+                    "peer",
+                    text_message("你好"),
+                    TurnOptions {
+                        task_id: Some("a2a-task".to_string()),
+                        timeout: None,
+                        ..Default::default()
+                    },
+                )
+                .await
+        })
+    };
+    while agent.tasks.get("a2a-task").await.is_none() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let client = reqwest::Client::new();
+    let response: Value = client
+        .post(format!("{base}/tasks/cancel"))
+        .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tasks/cancel", "params": {"id": "a2a-task"}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["result"]["status"], "canceled");
+    assert_eq!(
+        turn.await.unwrap().unwrap_err(),
+        AgentError::Canceled("a2a-task".to_string())
+    );
+
+    let response: Value = client
+        .post(format!("{base}/tasks/cancel"))
+        .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tasks/cancel", "params": {"id": "missing"}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["error"]["code"], -32001);
+}
@@ -0,0 +1,233 @@
+//! 能力注册测试
+//!
+//! 智能体从 MCP 服务器、A2A 对端名片和本地工具自动注册能力，能力带有来源、
+//! 参数结构和健康状态；能力可按类别和标签查找、通过接口禁用，并且是路由候选和
+//! 智能体名片的来源。对端不可访问时其能力标记为不健康。
+
+use async_trait::async_trait;
+use omni_agent::core::capabilities::{HealthStatus, Provenance};
+use omni_agent::core::router::{RouteCandidate, RouteTarget};
+use omni_agent::services::tools::{Tool, ToolError, ToolExecutionEngine};
+use omni_agent::ui::api::{create_routes, AppState};
+use omni_agent::{AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+struct EchoTool;
+
+#[async_trait]
+impl Tool for EchoTool {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn description(&self) -> &str {
+        "Echo the input"
+    }
+
+    async fn execute(&self, parameters: Value) -> Result<Value, ToolError> {
+        Ok(parameters)
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        Some(json!({"type": "object", "properties": {"text": {"type": "string"}}}))
+    }
+}
+
+async fn serve(state: AppState) -> String {
+    let app = create_routes().with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+async fn mount_mcp_manifest(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "files",
+            "version": "1.0.0",
+            "description": "File tools",
+            "capabilities": ["files"],
+            "tools": [{
+                "name": "read_file",
+                "description": "Read a file",
+                "input_schema": {"type": "object", "properties": {"path": {"type": "string"}}}
+            }],
+            "metadata": {}
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_capabilities_are_registered_from_peers_and_tools() {
+    let mcp = MockServer::start().await;
+    mount_mcp_manifest(&mcp).await;
+
+    let peer = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "info",
+            "version": "1.0.0",
+            "description": "Weather and time information",
+            "capabilities": ["weather"],
+            "endpoints": ["/messages"],
+            "metadata": {}
+        })))
+        .mount(&peer)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/agent.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "capabilities": {},
+            "default_input_modes": ["text/plain"],
+            "default_output_modes": ["text/plain"],
+            "description": "Weather and time information",
+            "name": "info",
+            "skills": [{
+                "id": "forecast",
+                "name": "Forecast",
+                "description": "Weather forecasts",
+                "tags": ["weather", "forecast"]
+            }],
+            "url": peer.uri(),
+            "version": "1.0.0"
+        })))
+        .mount(&peer)
+        .await;
+
+    let tools = Arc::new(ToolExecutionEngine::new());
+    tools.register_tool(Arc::new(EchoTool)).await.unwrap();
+
+    let agent = AgentBuilder::new("capability-agent", "能力测试智能体")
+        .add_mcp("files", &mcp.uri())
+        .add_a2a("info_agent", &peer.uri())
+        .tools(tools)
+        .build()
+        .await
+        .unwrap();
+
+    let capabilities = agent.get_capabilities().await;
+    let ids: Vec<&str> = capabilities.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, ["echo", "info_agent", "info_agent/forecast", "read_file"]);
+
+    let read_file = &capabilities[3];
+    assert_eq!(
+        read_file.provenance,
+        Provenance::Mcp {
+            server: "files".to_string()
+        }
+    );
+    assert_eq!(read_file.health.status, HealthStatus::Healthy);
+    assert_eq!(read_file.input_schema.as_ref().unwrap()["properties"]["path"]["type"], "string");
+    assert_eq!(capabilities[0].provenance, Provenance::LocalTool);
+    assert!(capabilities[0].input_schema.is_some());
+    assert_eq!(capabilities[2].input_modes, vec!["text/plain"]);
+
+    // 路由候选：MCP 工具和 A2A 智能体（技能也路由到智能体），本地工具不是路由目标
+    let candidates = RouteCandidate::from_capabilities(&agent.capabilities.available().await);
+    let targets: Vec<RouteTarget> = candidates.into_iter().map(|c| c.target).collect();
+    assert_eq!(
+        targets,
+        [
+            RouteTarget::A2AAgent("info_agent".to_string()),
+            RouteTarget::A2AAgent("info_agent".to_string()),
+            RouteTarget::MCPTool("read_file".to_string()),
+        ]
+    );
+
+    let card = agent.get_agent_card("http://localhost".to_string()).await;
+    assert_eq!(card.skills.len(), 4);
+
+    let agent = Arc::new(agent);
+    let base = serve(AppState::new(agent.clone(), AppConfig::default())).await;
+    let client = reqwest::Client::new();
+
+    let found: Vec<Value> = client
+        .get(format!("{base}/capabilities?tag=weather"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let ids: Vec<&str> = found.iter().map(|c| c["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["info_agent", "info_agent/forecast"]);
+    assert_eq!(found[0]["provenance"], json!({"kind": "a2a", "agent": "info_agent"}));
+
+    let found: Vec<Value> = client
+        .get(format!("{base}/capabilities?category=mcp"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+
+    // 禁用的能力不出现在名片中，重新获取后仍然禁用
+    let response = client
+        .put(format!("{base}/capabilities/read_file/enabled"))
+        .json(&json!({"enabled": false}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let card = agent.get_agent_card("http://localhost".to_string()).await;
+    assert!(card.skills.iter().all(|skill| skill.id != "read_file"));
+
+    let refreshed: Value = client
+        .post(format!("{base}/capabilities/refresh"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(refreshed["capabilities"], 4);
+    assert_eq!(refreshed["failures"], json!([]));
+    assert!(!agent.capabilities.get_capability("read_file").await.unwrap().enabled);
+
+    let response = client
+        .put(format!("{base}/capabilities/missing/enabled"))
+        .json(&json!({"enabled": true}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_unreachable_peer_marks_capabilities_unhealthy() {
+    let mcp = MockServer::start().await;
+    mount_mcp_manifest(&mcp).await;
+
+    let agent = AgentBuilder::new("capability-agent", "能力测试智能体")
+        .add_mcp("files", &mcp.uri())
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(agent.capabilities.available().await.len(), 1);
+
+    mcp.reset().await;
+    let failures = agent.refresh_capabilities().await;
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, "files");
+
+    let read_file = agent.capabilities.get_capability("read_file").await.unwrap();
+    assert_eq!(read_file.health.status, HealthStatus::Unhealthy);
+    assert!(read_file.health.error.is_some());
+    assert!(agent.capabilities.available().await.is_empty());
+
+    // 对端恢复后重新变为健康
+    mount_mcp_manifest(&mcp).await;
+    assert!(agent.refresh_capabilities().await.is_empty());
+    assert_eq!(agent.capabilities.available().await.len(), 1);
+}
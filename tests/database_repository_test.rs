@@ -0,0 +1,471 @@
+//! DataRepository 后端一致性测试
+//!
+//! 同一套 trait 级测试同时用于 SQLite 和 PostgreSQL 后端。PostgreSQL 测试需要
+//! 启用 `postgres` 特性并设置 `OMNI_AGENT_TEST_POSTGRES_URL`，否则自动跳过。
+
+use chrono::{Duration, Utc};
+use omni_agent::integrations::database::{
+    AgentRecord, ApiKeyRecord, ConversationMemoryRecord, ConversationRecord, DataRepository, FeedbackRecord, MemoryRecord,
+    MessageRecord, Pagination, RevokedTokenRecord, SqliteManager, SqliteRepository,
+    UserAccountRecord, WorkflowRunRecord,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+/// 运行完整的仓库测试套件
+///
+/// 所有断言都基于本次运行生成的唯一标记，因此可以在非空数据库上执行。
+async fn run_repository_suite(repo: &dyn DataRepository) {
+    let marker = Uuid::new_v4().simple().to_string();
+    let now = Utc::now();
+
+    // 智能体：保存、读取、更新
+    let mut agent = AgentRecord {
+        id: Uuid::new_v4(),
+        name: format!("agent-{marker}"),
+        agent_type: "assistant".to_string(),
+        config: json!({"model": "mock", "tags": ["a", "b"]}),
+        created_at: now,
+        updated_at: now,
+    };
+    repo.save_agent(&agent).await.unwrap();
+    let loaded = repo.get_agent_by_id(&agent.id).await.unwrap().unwrap();
+    assert_eq!(loaded.name, agent.name);
+    assert_eq!(loaded.config, agent.config);
+
+    agent.agent_type = "planner".to_string();
+    agent.updated_at = now + Duration::seconds(1);
+    repo.save_agent(&agent).await.unwrap();
+    let loaded = repo.get_agent_by_id(&agent.id).await.unwrap().unwrap();
+    assert_eq!(loaded.agent_type, "planner");
+    assert!(repo.get_agent_by_id(&Uuid::new_v4()).await.unwrap().is_none());
+
+    let agents = repo.list_agents(Pagination::new(0, 500)).await.unwrap();
+    assert!(agents.total >= 1);
+    assert!(agents.items.iter().any(|a| a.id == agent.id));
+
+    // 对话：保存、更新、搜索
+    let conversation = ConversationRecord {
+        id: Uuid::new_v4(),
+        title: format!("天气查询 {marker}"),
+        participants: vec!["alice".to_string(), format!("bob-{marker}")],
+        owner: Some("alice".to_string()),
+        created_at: now,
+        updated_at: now,
+    };
+    repo.save_conversation(&conversation).await.unwrap();
+    let loaded = repo
+        .get_conversation_by_id(&conversation.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.participants, conversation.participants);
+    assert_eq!(loaded.owner.as_deref(), Some("alice"));
+
+    // 所有者一经保存不再被覆盖
+    let takeover = ConversationRecord {
+        owner: Some("mallory".to_string()),
+        ..conversation.clone()
+    };
+    repo.save_conversation(&takeover).await.unwrap();
+    let loaded = repo
+        .get_conversation_by_id(&conversation.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.owner.as_deref(), Some("alice"));
+
+    let other = ConversationRecord {
+        id: Uuid::new_v4(),
+        title: "unrelated".to_string(),
+        participants: vec!["carol".to_string()],
+        owner: None,
+        created_at: now,
+        updated_at: now,
+    };
+    repo.save_conversation(&other).await.unwrap();
+
+    let by_title = repo
+        .search_conversations(&marker, Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(by_title.total, 1);
+    assert_eq!(by_title.items[0].id, conversation.id);
+
+    let by_participant = repo
+        .search_conversations(&format!("BOB-{marker}"), Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(by_participant.total, 1);
+
+    let wildcard = repo
+        .search_conversations(&format!("%{marker}"), Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(wildcard.total, 0, "通配符应当被转义");
+
+    let conversations = repo.list_conversations(Pagination::new(0, 500)).await.unwrap();
+    assert!(conversations.total >= 2);
+
+    // 消息：按时间顺序保存、分页、搜索
+    for i in 0..5 {
+        let message = MessageRecord {
+            id: Uuid::new_v4(),
+            conversation_id: conversation.id,
+            sender: if i % 2 == 0 { "user" } else { "agent" }.to_string(),
+            recipient: "omni-agent".to_string(),
+            content: json!({"type": "Text", "text": format!("消息 {i} {marker}")}),
+            message_type: "text".to_string(),
+            timestamp: now + Duration::milliseconds(i),
+            metadata: if i == 0 { Some(json!({"source": "test"})) } else { None },
+        };
+        repo.save_message(&message).await.unwrap();
+    }
+
+    let all = repo
+        .get_messages_by_conversation_id(&conversation.id)
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 5);
+    assert_eq!(all[0].metadata, Some(json!({"source": "test"})));
+    assert!(all.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    let first_page = repo
+        .list_messages(&conversation.id, Pagination::new(0, 2))
+        .await
+        .unwrap();
+    assert_eq!(first_page.total, 5);
+    assert_eq!(first_page.items.len(), 2);
+    assert!(first_page.has_more());
+    assert_eq!(first_page.items[0].id, all[0].id);
+
+    let last_page = repo
+        .list_messages(&conversation.id, Pagination::new(4, 2))
+        .await
+        .unwrap();
+    assert_eq!(last_page.items.len(), 1);
+    assert!(!last_page.has_more());
+    assert_eq!(last_page.items[0].id, all[4].id);
+
+    let found = repo
+        .search_messages(&format!("消息 3 {marker}"), None, Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(found.total, 1);
+    assert_eq!(found.items[0].id, all[3].id);
+
+    let scoped = repo
+        .search_messages(&marker, Some(&other.id), Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(scoped.total, 0);
+
+    let newest_first = repo
+        .search_messages(&marker, Some(&conversation.id), Pagination::new(0, 2))
+        .await
+        .unwrap();
+    assert_eq!(newest_first.total, 5);
+    assert_eq!(newest_first.items[0].id, all[4].id);
+
+    // 只搜索消息文本：JSON 键名和类型不参与匹配，通配符按字面量处理
+    let keys = repo
+        .search_messages("Text", Some(&conversation.id), Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(keys.total, 0, "不应匹配序列化后的 JSON");
+    let wildcard = repo
+        .search_messages(&format!("消息 _ {marker}"), None, Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(wildcard.total, 0, "通配符应当被转义");
+
+    let error = MessageRecord {
+        id: Uuid::new_v4(),
+        conversation_id: other.id,
+        sender: "omni-agent".to_string(),
+        recipient: "user".to_string(),
+        content: json!({"type": "Error", "code": "quota", "message": format!("配额已用 100% {marker}")}),
+        message_type: "Error".to_string(),
+        timestamp: now,
+        metadata: None,
+    };
+    repo.save_message(&error).await.unwrap();
+    let found = repo
+        .search_messages(&format!("100% {marker}"), None, Pagination::default())
+        .await
+        .unwrap();
+    assert_eq!(found.total, 1);
+    assert_eq!(found.items[0].id, error.id);
+
+    // 工作流运行：保存、更新状态、按工作流和状态查询
+    let workflow_id = format!("wf-{marker}");
+    let mut runs = Vec::new();
+    for i in 0..3 {
+        let run = WorkflowRunRecord {
+            id: Uuid::new_v4(),
+            workflow_id: workflow_id.clone(),
+            status: "running".to_string(),
+            data: json!({"steps": [{"step_id": "a", "status": "succeeded"}], "index": i}),
+            created_at: now + Duration::seconds(i),
+            updated_at: now + Duration::seconds(i),
+        };
+        repo.save_workflow_run(&run).await.unwrap();
+        runs.push(run);
+    }
+    runs[0].status = "completed".to_string();
+    runs[0].updated_at = now + Duration::seconds(10);
+    repo.save_workflow_run(&runs[0]).await.unwrap();
+    let loaded = repo.get_workflow_run(&runs[0].id).await.unwrap().unwrap();
+    assert_eq!(loaded.status, "completed");
+    assert_eq!(loaded.data, runs[0].data);
+    assert!(repo.get_workflow_run(&Uuid::new_v4()).await.unwrap().is_none());
+
+    let listed = repo
+        .list_workflow_runs(Some(&workflow_id), Pagination::new(0, 2))
+        .await
+        .unwrap();
+    assert_eq!(listed.total, 3);
+    assert_eq!(listed.items[0].id, runs[2].id);
+    assert_eq!(listed.items[1].id, runs[1].id);
+
+    let unfinished: Vec<Uuid> = repo
+        .list_workflow_runs_by_status(&["pending", "running"])
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|run| run.workflow_id == workflow_id)
+        .map(|run| run.id)
+        .collect();
+    assert_eq!(unfinished.len(), 2);
+    assert!(!unfinished.contains(&runs[0].id));
+
+    // 决策反馈：按创建时间顺序列出
+    let feedback: Vec<FeedbackRecord> = [(Some("route_weather"), 1.0), (None, -1.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (rule_id, score))| FeedbackRecord {
+            id: Uuid::new_v4(),
+            decision_id: format!("message-{marker}-{i}"),
+            rule_id: rule_id.map(str::to_string),
+            action: "a2a:info_agent".to_string(),
+            context: "查询天气".to_string(),
+            outcome: if score > 0.0 { "positive" } else { "negative" }.to_string(),
+            feedback: score,
+            created_at: now + Duration::seconds(i as i64),
+        })
+        .collect();
+    for record in feedback.iter().rev() {
+        repo.save_feedback(record).await.unwrap();
+    }
+    let listed: Vec<(Uuid, Option<String>, f32)> = repo
+        .list_feedback()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|record| record.decision_id.contains(&marker))
+        .map(|record| (record.id, record.rule_id, record.feedback))
+        .collect();
+    let expected: Vec<(Uuid, Option<String>, f32)> = feedback
+        .into_iter()
+        .map(|record| (record.id, record.rule_id, record.feedback))
+        .collect();
+    assert_eq!(listed, expected);
+
+    // 长期记忆：保存、更新、按用户和智能体列出、删除
+    let user = format!("user-{marker}");
+    let mut memories: Vec<MemoryRecord> = ["omni", "omni", "billing"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, agent_id)| MemoryRecord {
+            id: Uuid::new_v4(),
+            user_id: user.clone(),
+            agent_id: agent_id.to_string(),
+            content: format!("fact {i}"),
+            tags: vec!["preference".to_string()],
+            created_at: now + Duration::seconds(i as i64),
+            updated_at: now + Duration::seconds(i as i64),
+            expires_at: (i == 0).then(|| now + Duration::days(1)),
+        })
+        .collect();
+    for memory in &memories {
+        repo.save_memory(memory).await.unwrap();
+    }
+    memories[1].content = "fact 1 (updated)".to_string();
+    memories[1].tags.push("travel".to_string());
+    repo.save_memory(&memories[1]).await.unwrap();
+
+    let listed = repo.list_memories(Some(&user), Some("omni")).await.unwrap();
+    assert_eq!(listed, memories[..2]);
+    assert_eq!(repo.list_memories(Some(&user), None).await.unwrap().len(), 3);
+
+    assert!(repo.delete_memory(&memories[0].id).await.unwrap());
+    assert!(!repo.delete_memory(&memories[0].id).await.unwrap());
+    assert_eq!(repo.delete_user_memories(&user).await.unwrap(), 2);
+    assert!(repo.list_memories(Some(&user), None).await.unwrap().is_empty());
+
+    // 对话记忆：保存、更新、列出、删除单条和删除用户的全部
+    let mut summaries: Vec<ConversationMemoryRecord> = (0..3)
+        .map(|i| ConversationMemoryRecord {
+            id: Uuid::new_v4(),
+            user_id: user.clone(),
+            session_id: format!("session-{}", i % 2),
+            content: format!("summary {i}"),
+            sources: vec![Uuid::new_v4(), Uuid::new_v4()],
+            importance: 0.5,
+            created_at: now + Duration::seconds(i as i64),
+        })
+        .collect();
+    for summary in &summaries {
+        repo.save_conversation_memory(summary).await.unwrap();
+    }
+    summaries[1].importance = 1.0;
+    repo.save_conversation_memory(&summaries[1]).await.unwrap();
+
+    let listed: Vec<ConversationMemoryRecord> = repo
+        .list_conversation_memories()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|record| record.user_id == user)
+        .collect();
+    assert_eq!(listed, summaries);
+    assert!(repo.delete_conversation_memory(&summaries[0].id).await.unwrap());
+    assert!(!repo.delete_conversation_memory(&summaries[0].id).await.unwrap());
+    assert_eq!(repo.delete_user_conversation_memories(&user).await.unwrap(), 2);
+
+    // 用户账户：保存、更新、列出、删除
+    let mut account = UserAccountRecord {
+        id: format!("account-{marker}"),
+        username: format!("admin-{marker}"),
+        role: "admin".to_string(),
+        permissions: vec!["read".to_string(), "tool:search".to_string()],
+        password_hash: "$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA".to_string(),
+        failed_attempts: 0,
+        locked_until: None,
+        created_at: now,
+        last_login: None,
+        token_version: 0,
+    };
+    repo.save_user_account(&account).await.unwrap();
+    account.failed_attempts = 3;
+    account.locked_until = Some(now + Duration::minutes(15));
+    account.last_login = Some(now);
+    account.token_version = 2;
+    repo.save_user_account(&account).await.unwrap();
+
+    let listed = repo.list_user_accounts().await.unwrap();
+    assert_eq!(listed.iter().filter(|a| **a == account).count(), 1);
+    assert!(repo.delete_user_account(&account.id).await.unwrap());
+    assert!(!repo.delete_user_account(&account.id).await.unwrap());
+
+    // 令牌吊销记录：保存（重复保存无影响并返回 false）、列出、按过期时间清理
+    let revoked: Vec<RevokedTokenRecord> = [-1, 1]
+        .into_iter()
+        .map(|hours| RevokedTokenRecord {
+            jti: format!("jti-{marker}-{hours}"),
+            user_id: account.id.clone(),
+            expires_at: now + Duration::hours(hours),
+        })
+        .collect();
+    for token in &revoked {
+        assert!(repo.save_revoked_token(token).await.unwrap());
+    }
+    assert!(!repo.save_revoked_token(&revoked[0]).await.unwrap());
+    let listed = repo.list_revoked_tokens().await.unwrap();
+    assert!(revoked.iter().all(|token| listed.contains(token)));
+    assert!(repo.delete_expired_revoked_tokens(now).await.unwrap() >= 1);
+    let listed = repo.list_revoked_tokens().await.unwrap();
+    assert!(!listed.contains(&revoked[0]));
+    assert!(listed.contains(&revoked[1]));
+
+    // API 密钥：保存、更新最近使用时间、列出、删除
+    let mut key = ApiKeyRecord {
+        id: Uuid::new_v4(),
+        name: "ci".to_string(),
+        prefix: format!("oa_{}", &marker[..8]),
+        key_hash: "0".repeat(64),
+        user_id: account.id.clone(),
+        permissions: vec!["tool:search".to_string()],
+        created_at: now,
+        expires_at: Some(now + Duration::days(30)),
+        last_used_at: None,
+    };
+    repo.save_api_key(&key).await.unwrap();
+    key.last_used_at = Some(now);
+    repo.save_api_key(&key).await.unwrap();
+
+    let listed = repo.list_api_keys().await.unwrap();
+    assert_eq!(listed.iter().filter(|k| **k == key).count(), 1);
+    assert!(repo.delete_api_key(&key.id).await.unwrap());
+    assert!(!repo.delete_api_key(&key.id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_sqlite_repository_in_memory() {
+    let repo = SqliteRepository::connect("sqlite::memory:").await.unwrap();
+    run_repository_suite(&repo).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_repository_file_backed_reopen() {
+    let path = std::env::temp_dir().join(format!("omni-agent-{}.db", Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+
+    let conversation_id = {
+        let repo = SqliteRepository::connect(&url).await.unwrap();
+        run_repository_suite(&repo).await;
+        repo.list_conversations(Pagination::default())
+            .await
+            .unwrap()
+            .items[0]
+            .id
+    };
+
+    // 重新打开时迁移应当被跳过，数据仍然存在
+    let repo = SqliteRepository::connect(&url).await.unwrap();
+    assert!(repo
+        .get_conversation_by_id(&conversation_id)
+        .await
+        .unwrap()
+        .is_some());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_sqlite_migrations_are_idempotent() {
+    let manager = SqliteManager::in_memory().await.unwrap();
+    manager.run_migrations().await.unwrap();
+    manager.run_migrations().await.unwrap();
+    assert!(manager.check_connection().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_sqlite_message_requires_conversation() {
+    let repo = SqliteRepository::connect("sqlite::memory:").await.unwrap();
+    let orphan = MessageRecord {
+        id: Uuid::new_v4(),
+        conversation_id: Uuid::new_v4(),
+        sender: "user".to_string(),
+        recipient: "agent".to_string(),
+        content: json!("hi"),
+        message_type: "text".to_string(),
+        timestamp: Utc::now(),
+        metadata: None,
+    };
+    assert!(repo.save_message(&orphan).await.is_err());
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_repository() {
+    use omni_agent::integrations::database::PostgresRepository;
+
+    let Ok(url) = std::env::var("OMNI_AGENT_TEST_POSTGRES_URL") else {
+        println!("Skipping Postgres repository test: OMNI_AGENT_TEST_POSTGRES_URL not set");
+        return;
+    };
+
+    let repo = PostgresRepository::connect(&url).await.unwrap();
+    run_repository_suite(&repo).await;
+}
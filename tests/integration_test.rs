@@ -61,9 +61,11 @@ async fn test_agent_integration() {
 
     // Test manifest fetching
     let capabilities = agent.get_capabilities().await;
-    assert!(capabilities.contains(&"weather".to_string()));
-    assert!(capabilities.contains(&"calculator".to_string()));
-    assert!(capabilities.contains(&"data_analysis".to_string()));
+    let tool = capabilities.iter().find(|c| c.id == "get_weather").unwrap();
+    assert_eq!(tool.tags, vec!["weather", "calculator"]);
+    assert!(tool.input_schema.is_some());
+    let peer = capabilities.iter().find(|c| c.id == "data_analyst").unwrap();
+    assert!(peer.tags.contains(&"data_analysis".to_string()));
 
     // Test message processing
     let message = Message::new(
@@ -0,0 +1,231 @@
+//! 知识库测试
+//!
+//! 智能体启动时载入来源目录中的 Markdown、文本、HTML 和 PDF 文档，通过
+//! `kb_search`、`kb_ingest` 工具使用知识库；回答前自动检索，回复的元数据带有引用。
+
+use async_trait::async_trait;
+use omni_agent::agent::AgentError;
+use omni_agent::protocol::message::{Message, MessageContent};
+use omni_agent::services::knowledge::{ChunkingConfig, KnowledgeConfig};
+use omni_agent::services::tools::{Tool, ToolError, ToolExecutionEngine};
+use omni_agent::AgentBuilder;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const PDF: &[u8] = b"%PDF-1.4
+1 0 obj
+<< /Length 80 >>
+stream
+BT /F1 12 Tf 72 712 Td (Warranty covers hardware defects) Tj 0 -14 Td (for two years.) Tj ET
+endstream
+endobj
+%%EOF";
+
+fn docs_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("omni-agent-kb-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("policies")).unwrap();
+    std::fs::write(
+        dir.join("policies/refunds.md"),
+        "# Refund policy\n\n## Deadline\n\nRefund requests are accepted within 30 days of delivery.\n\n## Exceptions\n\nGift cards cannot be refunded.",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("shipping.txt"),
+        "Shipping is free for orders over 50 euros.",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("support.html"),
+        "<html><head><title>Support hours</title></head><body><p>Support is open Monday to Friday.</p></body></html>",
+    )
+    .unwrap();
+    std::fs::write(dir.join("warranty.pdf"), PDF).unwrap();
+    std::fs::write(dir.join("logo.png"), [0u8; 4]).unwrap();
+    dir
+}
+
+fn config(dir: &std::path::Path) -> KnowledgeConfig {
+    KnowledgeConfig {
+        enabled: true,
+        sources: vec![dir.display().to_string()],
+        chunking: ChunkingConfig::default(),
+        ..KnowledgeConfig::default()
+    }
+}
+
+fn text_message(text: &str) -> Message {
+    Message::new(
+        "user".to_string(),
+        "kb-agent".to_string(),
+        MessageContent::Text {
+            text: text.to_string(),
+        },
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_agent_answers_with_citations() {
+    let dir = docs_dir();
+    let agent = AgentBuilder::new("kb-agent", "知识库测试智能体")
+        .knowledge_base(config(&dir))
+        .build()
+        .await
+        .unwrap();
+
+    let knowledge = agent.knowledge.clone().unwrap();
+    let documents = knowledge.documents().await;
+    let titles: Vec<&str> = documents.iter().map(|d| d.title.as_str()).collect();
+    assert_eq!(
+        titles,
+        ["Refund policy", "shipping", "Support hours", "warranty"]
+    );
+
+    let capabilities = agent.get_capabilities().await;
+    assert!(capabilities.iter().any(|c| c.id == "kb_search"));
+    assert!(capabilities.iter().any(|c| c.id == "kb_ingest"));
+
+    // 回答前自动检索，引用指向退款文档的 Deadline 小节
+    let response = agent
+        .process_message(text_message(
+            "Within how many days are refund requests accepted?",
+        ))
+        .await
+        .unwrap();
+    let citations = response.metadata.as_ref().unwrap()["citations"]
+        .as_array()
+        .unwrap()
+        .clone();
+    assert!(!citations.is_empty());
+    assert_eq!(citations[0]["title"], "Refund policy");
+    assert_eq!(citations[0]["heading"], "Refund policy > Deadline");
+    assert!(citations[0]["document_id"]
+        .as_str()
+        .unwrap()
+        .ends_with("refunds.md"));
+
+    // kb_search 工具返回片段原文和引用
+    let result = agent
+        .tools
+        .execute_tool(
+            "kb_search",
+            json!({"query": "hardware warranty years", "limit": 1}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["results"][0]["citation"]["title"], "warranty");
+    assert!(result["results"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("two years"));
+    assert_eq!(result["citations"].as_array().unwrap().len(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_kb_ingest_tool() {
+    let dir = docs_dir();
+    let agent = AgentBuilder::new("kb-agent", "知识库测试智能体")
+        .knowledge_base(config(&dir))
+        .build()
+        .await
+        .unwrap();
+
+    // 来源目录以外的文件不能载入
+    let outside =
+        std::env::temp_dir().join(format!("omni-agent-outside-{}.md", uuid::Uuid::new_v4()));
+    std::fs::write(&outside, "# Secret").unwrap();
+    let error = agent
+        .tools
+        .execute_tool("kb_ingest", json!({"path": outside.display().to_string()}))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ToolError::PermissionDenied(_)));
+    std::fs::remove_file(outside).unwrap();
+
+    std::fs::write(
+        dir.join("returns.md"),
+        "# Returns\n\nReturn labels are emailed within a day.",
+    )
+    .unwrap();
+    let result = agent
+        .tools
+        .execute_tool(
+            "kb_ingest",
+            json!({"path": dir.join("returns.md").display().to_string()}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["documents"][0]["title"], "Returns");
+
+    // 同一来源的文本再次载入时替换原有片段
+    for text in ["The office is in Berlin.", "The office moved to Munich."] {
+        agent
+            .tools
+            .execute_tool(
+                "kb_ingest",
+                json!({"text": text, "title": "Office", "source": "wiki:office"}),
+            )
+            .await
+            .unwrap();
+    }
+    let knowledge = agent.knowledge.clone().unwrap();
+    let hits = knowledge.search("Where is the office?", 10).await.unwrap();
+    let office: Vec<&str> = hits
+        .iter()
+        .filter(|hit| hit.citation.document_id == "inline:wiki:office")
+        .map(|hit| hit.text.as_str())
+        .collect();
+    assert_eq!(office, ["The office moved to Munich."]);
+
+    assert!(knowledge.remove("inline:wiki:office").await.unwrap());
+    let hits = knowledge.search("Where is the office?", 10).await.unwrap();
+    assert!(hits
+        .iter()
+        .all(|hit| hit.citation.document_id != "inline:wiki:office"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+struct CountingTool {
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl Tool for CountingTool {
+    fn name(&self) -> &str {
+        "mock_tool"
+    }
+
+    fn description(&self) -> &str {
+        "Counts calls"
+    }
+
+    async fn execute(&self, _parameters: Value) -> Result<Value, ToolError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(json!({"ok": true}))
+    }
+}
+
+#[tokio::test]
+async fn test_llm_calls_local_tools() {
+    let tool = Arc::new(CountingTool {
+        calls: AtomicUsize::new(0),
+    });
+    let tools = Arc::new(ToolExecutionEngine::new());
+    tools.register_tool(tool.clone()).await.unwrap();
+    let agent = AgentBuilder::new("kb-agent", "知识库测试智能体")
+        .tools(tools)
+        .max_tool_iterations(2)
+        .build()
+        .await
+        .unwrap();
+
+    // 模拟 LLM 对含 "tool" 的输入总是调用 mock_tool
+    let result = agent.process_message(text_message("use the tool")).await;
+    assert_eq!(result.unwrap_err(), AgentError::ToolLoopLimit(2));
+    assert_eq!(tool.calls.load(Ordering::SeqCst), 2);
+}
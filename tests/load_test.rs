@@ -0,0 +1,246 @@
+//! 并发负载测试
+//!
+//! 使用带固定延迟的模拟 LLM 提供商，验证吞吐量随并发上限增长、
+//! 同一会话内请求保持串行，以及队列满时返回 429。
+
+use async_trait::async_trait;
+use omni_agent::agent::{AgentError, LimiterConfig};
+use omni_agent::llm::providers::{LLMError, LLMProvider, LLMRequest, LLMResponse};
+use omni_agent::llm::LLMConfig;
+use omni_agent::protocol::message::{Message, MessageContent};
+use omni_agent::ui::api::{create_routes, AppState};
+use omni_agent::{Agent, AgentBuilder, AppConfig};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const CALL_DELAY: Duration = Duration::from_millis(100);
+
+/// 每次调用休眠固定时间，并记录同时进行的最大调用数
+#[derive(Default)]
+struct SlowProvider {
+    active: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+#[async_trait]
+impl LLMProvider for SlowProvider {
+    async fn chat(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(active, Ordering::SeqCst);
+        tokio::time::sleep(CALL_DELAY).await;
+        self.active.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(LLMResponse {
+            content: format!("history={}", request.messages.len() - 1),
+            usage: None,
+            model: request.model,
+        })
+    }
+
+    async fn chat_stream(&self, _request: LLMRequest) -> Result<String, LLMError> {
+        Err(LLMError::InvalidRequest("streaming not supported".to_string()))
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "slow"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// 让测试在提供商注册后仍能读取统计
+struct SharedProvider(Arc<SlowProvider>);
+
+#[async_trait]
+impl LLMProvider for SharedProvider {
+    async fn chat(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        self.0.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: LLMRequest) -> Result<String, LLMError> {
+        self.0.chat_stream(request).await
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "slow"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+async fn slow_agent(max_in_flight: usize, max_queue: usize) -> (Arc<Agent>, Arc<SlowProvider>) {
+    let agent = AgentBuilder::new("load-agent", "负载测试智能体")
+        .concurrency(LimiterConfig {
+            max_in_flight,
+            max_queue,
+        })
+        .build()
+        .await
+        .unwrap();
+
+    let provider = Arc::new(SlowProvider::default());
+    {
+        let mut llm = agent.llm.write().await;
+        llm.manager
+            .add_provider("slow".to_string(), Box::new(SharedProvider(provider.clone())))
+            .await;
+        llm.config = LLMConfig {
+            provider: "slow".to_string(),
+            model: "slow-model".to_string(),
+            temperature: 0.0,
+            max_tokens: 16,
+            use_mock: false,
+        };
+    }
+    (Arc::new(agent), provider)
+}
+
+fn text_message(text: &str, session_id: &str) -> Message {
+    Message::new(
+        "user".to_string(),
+        "load-agent".to_string(),
+        MessageContent::Text {
+            text: text.to_string(),
+        },
+        Some(json!({ "session_id": session_id })),
+    )
+}
+
+/// 在 `sessions` 个会话上各发送一条消息，返回总耗时
+async fn run_load(agent: &Arc<Agent>, sessions: usize) -> Duration {
+    let started = Instant::now();
+    let tasks = (0..sessions).map(|i| {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .process_message(text_message("你好", &format!("load-{i}")))
+                .await
+                .unwrap()
+        })
+    });
+    for task in futures::future::join_all(tasks).await {
+        task.unwrap();
+    }
+    started.elapsed()
+}
+
+#[tokio::test]
+async fn test_throughput_scales_with_concurrency() {
+    const REQUESTS: usize = 16;
+
+    let (serial, serial_provider) = slow_agent(1, REQUESTS).await;
+    let serial_time = run_load(&serial, REQUESTS).await;
+    assert_eq!(serial_provider.peak.load(Ordering::SeqCst), 1);
+
+    let (parallel, parallel_provider) = slow_agent(8, REQUESTS).await;
+    let parallel_time = run_load(&parallel, REQUESTS).await;
+    assert_eq!(parallel_provider.peak.load(Ordering::SeqCst), 8);
+
+    println!(
+        "{REQUESTS} requests: max_in_flight=1 took {serial_time:?}, max_in_flight=8 took {parallel_time:?}"
+    );
+    assert!(serial_time >= CALL_DELAY * REQUESTS as u32);
+    assert!(
+        parallel_time * 4 < serial_time,
+        "expected at least 4x speedup, got {serial_time:?} vs {parallel_time:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_same_session_turns_are_serialized() {
+    let (agent, provider) = slow_agent(8, 16).await;
+
+    let tasks = (0..4).map(|_| {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .process_message(text_message("继续", "shared"))
+                .await
+                .unwrap()
+        })
+    });
+    let mut histories: Vec<String> = futures::future::join_all(tasks)
+        .await
+        .into_iter()
+        .map(|response| match response.unwrap().content {
+            MessageContent::Text { text } => text,
+            other => panic!("unexpected content: {other:?}"),
+        })
+        .collect();
+    histories.sort();
+
+    // 每一轮都看到此前所有轮次的问答
+    assert_eq!(histories, ["history=0", "history=2", "history=4", "history=6"]);
+    assert_eq!(provider.peak.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_overload_is_rejected() {
+    let (agent, _provider) = slow_agent(1, 1).await;
+
+    let tasks = (0..4).map(|i| {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            agent
+                .process_message(text_message("你好", &format!("burst-{i}")))
+                .await
+        })
+    });
+    let results: Vec<_> = futures::future::join_all(tasks)
+        .await
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect();
+
+    let rejected = results
+        .iter()
+        .filter(|r| matches!(r, Err(AgentError::Overloaded(_))))
+        .count();
+    assert_eq!(rejected, 2);
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+
+    // 被拒绝的请求不会在会话中留下痕迹
+    assert_eq!(agent.sessions.len().await, 2);
+    assert_eq!(agent.limiter.stats().in_flight, 0);
+}
+
+#[tokio::test]
+async fn test_chat_api_returns_429_when_full() {
+    let (agent, _provider) = slow_agent(1, 0).await;
+    let app = create_routes().with_state(AppState::new(agent, AppConfig::default()));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let requests = (0..3).map(|i| {
+        client
+            .post(format!("{base}/chat"))
+            .json(&json!({ "message": "你好", "session_id": format!("api-{i}") }))
+            .send()
+    });
+    let responses = futures::future::join_all(requests).await;
+
+    let mut statuses: Vec<u16> = responses
+        .iter()
+        .map(|r| r.as_ref().unwrap().status().as_u16())
+        .collect();
+    statuses.sort();
+    assert_eq!(statuses, [200, 429, 429]);
+
+    let throttled = responses
+        .into_iter()
+        .map(|r| r.unwrap())
+        .find(|r| r.status() == reqwest::StatusCode::TOO_MANY_REQUESTS)
+        .unwrap();
+    assert_eq!(throttled.headers()["retry-after"], "1");
+}
@@ -0,0 +1,490 @@
+//! 编排引擎后端测试
+//!
+//! 路由到 MCP 工具时由 LLM 提取参数并通过 McpManager 调用，路由到 A2A 智能体时
+//! 通过 A2AClient 发送消息，任务记录保存真实结果、错误和耗时；规划模式把目标
+//! 拆分为子任务，修改后按依赖顺序执行并汇总回答。LLM、工具和 A2A 调用都写入
+//! 审计日志，取消任务时进行中的 A2A 调用在对端被取消。
+
+use async_trait::async_trait;
+use omni_agent::a2a::client::A2AClient;
+use omni_agent::config::McpServerConfig;
+use omni_agent::core::capabilities::CapabilityManager;
+use omni_agent::core::orchestration::{
+    OrchestrationEngine, OrchestrationTaskStatus, PlanError, PlanStatus, PlannedTask,
+};
+use omni_agent::core::router::IntelligentRouter;
+use omni_agent::core::state::{MessageType, StateManager};
+use omni_agent::llm::providers::{LLMError, LLMProvider, LLMRequest, LLMResponse, ProviderConfig};
+use omni_agent::llm::{LLMConfig, LLMService};
+use omni_agent::mcp::manager::McpManager;
+use omni_agent::services::audit::{action, AuditTrail};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+type Reply = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// 根据提示生成回复的提供商，记录收到的每条用户消息
+struct ScriptedProvider {
+    reply: Reply,
+    prompts: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl LLMProvider for ScriptedProvider {
+    async fn chat(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let prompt = request.messages.last().unwrap().content.clone();
+        self.prompts.lock().unwrap().push(prompt.clone());
+        Ok(LLMResponse {
+            content: (self.reply)(&prompt),
+            usage: None,
+            model: "scripted".to_string(),
+        })
+    }
+
+    async fn chat_stream(&self, request: LLMRequest) -> Result<String, LLMError> {
+        self.chat(request).await.map(|response| response.content)
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "scripted"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+async fn scripted_llm(
+    reply: impl Fn(&str) -> String + Send + Sync + 'static,
+    prompts: Arc<Mutex<Vec<String>>>,
+) -> LLMService {
+    let config = LLMConfig {
+        provider: "scripted".to_string(),
+        model: "scripted".to_string(),
+        temperature: 0.0,
+        max_tokens: 64,
+        use_mock: false,
+    };
+    let llm = LLMService::new(
+        config,
+        ProviderConfig {
+            openai: None,
+            claude: None,
+            google: None,
+        },
+    );
+    let provider = ScriptedProvider {
+        reply: Box::new(reply),
+        prompts,
+    };
+    llm.manager
+        .add_provider("scripted".to_string(), Box::new(provider))
+        .await;
+    llm
+}
+
+async fn mcp_manager(server: &MockServer) -> McpManager {
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "files",
+            "version": "1.0.0",
+            "description": "File tools",
+            "capabilities": ["files"],
+            "tools": [{
+                "name": "file_processor",
+                "description": "Counts the lines of a file",
+                "input_schema": {"type": "object", "properties": {"path": {"type": "string"}}}
+            }],
+            "metadata": {}
+        })))
+        .mount(server)
+        .await;
+
+    let mut manager = McpManager::default();
+    manager
+        .add_server(
+            "files".to_string(),
+            McpServerConfig {
+                name: "files".to_string(),
+                description: "File tools".to_string(),
+                url: server.uri(),
+                timeout: 30,
+                retry_attempts: 0,
+                enabled: true,
+            },
+        )
+        .unwrap();
+    manager.connect_all().await.unwrap();
+    manager
+}
+
+/// 编排引擎使用 `config.example.json` 中的路由规则
+async fn new_engine(state_manager: Arc<StateManager>) -> OrchestrationEngine {
+    let config: serde_json::Value =
+        serde_json::from_str(include_str!("../config.example.json")).unwrap();
+    let rules = serde_json::from_value(config["router"]["rules"].clone()).unwrap();
+    let router = IntelligentRouter::new();
+    router.decision_engine().replace_rules(rules).await.unwrap();
+    OrchestrationEngine::new(
+        Arc::new(router),
+        state_manager,
+        Arc::new(CapabilityManager::new()),
+    )
+}
+
+#[tokio::test]
+async fn test_mcp_tool_called_with_extracted_arguments() {
+    let server = MockServer::start().await;
+    let manager = mcp_manager(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/tools/file_processor/call"))
+        .and(body_partial_json(
+            json!({"parameters": {"path": "/tmp/report.txt"}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"lines": 42})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let llm = scripted_llm(
+        |_| "```json\n{\"path\": \"/tmp/report.txt\"}\n```".to_string(),
+        prompts.clone(),
+    )
+    .await;
+    let state_manager = Arc::new(StateManager::new());
+    let audit = Arc::new(AuditTrail::default());
+    let engine = new_engine(state_manager.clone())
+        .await
+        .with_llm(llm)
+        .with_mcp_manager(Arc::new(manager))
+        .with_audit(audit.clone());
+
+    let result = engine
+        .orchestrate_as("alice", "统计文件 /tmp/report.txt 的行数", CancellationToken::new())
+        .await;
+    assert_eq!(result.unwrap(), r#"{"lines":42}"#);
+
+    // 提取参数的 LLM 调用和工具调用都以调用方的名义记录
+    let logs = audit.recent(None).await;
+    let actions: Vec<&str> = logs.iter().rev().map(|log| log.action.as_str()).collect();
+    assert_eq!(actions, [action::LLM_CALL, action::TOOL_CALL]);
+    assert!(logs.iter().all(|log| log.user_id == "alice" && log.success));
+    assert_eq!(logs[0].resource, "file_processor");
+
+    let prompts = prompts.lock().unwrap().clone();
+    assert!(prompts[0].contains("file_processor"));
+    assert!(prompts[0].contains("Counts the lines of a file"));
+    assert!(prompts[0].contains("统计文件 /tmp/report.txt 的行数"));
+
+    let task = &engine.get_all_tasks().await[0];
+    assert!(matches!(task.status, OrchestrationTaskStatus::Completed));
+    assert_eq!(task.result.as_deref(), Some(r#"{"lines":42}"#));
+    assert!(task.started_at.unwrap() <= task.finished_at.unwrap());
+    assert!(task.duration_ms.is_some());
+
+    let buffer = state_manager.get_buffer_messages().await;
+    assert!(matches!(buffer[1].message_type, MessageType::ToolResponse));
+}
+
+#[tokio::test]
+async fn test_unparseable_arguments_fail_the_task() {
+    let server = MockServer::start().await;
+    let manager = mcp_manager(&server).await;
+    let llm = scripted_llm(
+        |_| "I don't know".to_string(),
+        Arc::new(Mutex::new(Vec::new())),
+    )
+    .await;
+    let engine = new_engine(Arc::new(StateManager::new()))
+        .await
+        .with_llm(llm)
+        .with_mcp_manager(Arc::new(manager));
+
+    let error = engine.orchestrate("处理这个文件").await.unwrap_err();
+    assert!(error.contains("file_processor"));
+    let task = &engine.get_all_tasks().await[0];
+    assert!(matches!(task.status, OrchestrationTaskStatus::Failed));
+    assert_eq!(task.error.as_deref(), Some(error.as_str()));
+}
+
+#[tokio::test]
+async fn test_a2a_agent_receives_message() {
+    let peer = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({
+            "recipient": "info_agent",
+            "content": {"type": "Text", "text": "北京今天天气怎么样"}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": uuid::Uuid::new_v4(),
+            "sender": "info_agent",
+            "recipient": "orchestrator",
+            "content": {"type": "Text", "text": "晴，21°C"},
+            "timestamp": chrono::Utc::now()
+        })))
+        .expect(1)
+        .mount(&peer)
+        .await;
+
+    let audit = Arc::new(AuditTrail::default());
+    let engine = new_engine(Arc::new(StateManager::new()))
+        .await
+        .with_a2a_client("info_agent", A2AClient::new(peer.uri()))
+        .with_audit(audit.clone());
+    assert_eq!(
+        engine.orchestrate("北京今天天气怎么样").await.unwrap(),
+        "晴，21°C"
+    );
+    let logs = audit.recent(None).await;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].action, action::A2A_DELEGATION);
+    assert_eq!(logs[0].resource, "info_agent");
+
+    // 对端返回错误时任务失败
+    let failing = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": uuid::Uuid::new_v4(),
+            "sender": "info_agent",
+            "recipient": "orchestrator",
+            "content": {"type": "Error", "code": "busy", "message": "try later"},
+            "timestamp": chrono::Utc::now()
+        })))
+        .mount(&failing)
+        .await;
+    let engine = new_engine(Arc::new(StateManager::new()))
+        .await
+        .with_a2a_client("info_agent", A2AClient::new(failing.uri()));
+    let error = engine.orchestrate("现在是什么时间").await.unwrap_err();
+    assert!(error.contains("busy: try later"));
+}
+
+#[tokio::test]
+async fn test_canceled_a2a_delegation_is_canceled_on_the_peer() {
+    let peer = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(header("authorization", "Bearer peer-secret"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .expect(1)
+        .mount(&peer)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tasks/cancel"))
+        .and(header("authorization", "Bearer peer-secret"))
+        .and(body_partial_json(json!({"method": "tasks/cancel"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"jsonrpc": "2.0"})))
+        .expect(1)
+        .mount(&peer)
+        .await;
+
+    let client = A2AClient::new(peer.uri()).with_auth_token("peer-secret");
+    let engine = Arc::new(
+        new_engine(Arc::new(StateManager::new()))
+            .await
+            .with_a2a_client("info_agent", client),
+    );
+    let cancel = CancellationToken::new();
+    let run = {
+        let engine = engine.clone();
+        let cancel = cancel.clone();
+        tokio::spawn(async move { engine.orchestrate_with_cancel("北京今天天气怎么样", cancel).await })
+    };
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    cancel.cancel();
+
+    let error = run.await.unwrap().unwrap_err();
+    assert!(error.contains("任务已取消"), "{error}");
+    let tasks = engine.get_all_tasks().await;
+    assert!(matches!(tasks[0].status, OrchestrationTaskStatus::Canceled));
+    peer.verify().await;
+}
+
+#[tokio::test]
+async fn test_plan_is_edited_then_executed_in_dependency_order() {
+    let server = MockServer::start().await;
+    let manager = mcp_manager(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/tools/file_processor/call"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"lines": 42})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let llm = scripted_llm(
+        |prompt| {
+            if prompt.contains("Available targets") {
+                r#"{"tasks": [
+                    {"id": "count", "description": "Count the lines of /tmp/report.txt", "target": "mcp:file_processor"},
+                    {"id": "judge", "description": "Decide whether the report is long", "target": "llm", "depends_on": ["count"]}
+                ]}"#
+                .to_string()
+            } else if prompt.contains("Extract the arguments") {
+                r#"{"path": "/tmp/report.txt"}"#.to_string()
+            } else if prompt.contains("final answer") {
+                "The report has 42 lines.".to_string()
+            } else {
+                format!("handled: {}", prompt.lines().next().unwrap())
+            }
+        },
+        prompts.clone(),
+    )
+    .await;
+    let engine = new_engine(Arc::new(StateManager::new()))
+        .await
+        .with_llm(llm)
+        .with_mcp_manager(Arc::new(manager));
+
+    let targets: Vec<String> = engine
+        .available_targets()
+        .await
+        .into_iter()
+        .map(|target| target.name)
+        .collect();
+    assert_eq!(targets, ["llm", "mcp:file_processor"]);
+
+    let plan = engine.create_plan("How long is the report?").await.unwrap();
+    assert_eq!(plan.status, PlanStatus::Draft);
+    assert!(prompts.lock().unwrap()[0].contains("- mcp:file_processor: Counts the lines of a file"));
+    assert_eq!(plan.tasks.len(), 2);
+    assert_eq!(plan.tasks[1].dependencies, ["count"]);
+
+    // 执行前修改计划：增加一个同样依赖 count 的子任务
+    let mut edited: Vec<PlannedTask> = serde_json::from_value(json!([
+        {"id": "count", "description": "Count the lines of /tmp/report.txt", "target": "mcp:file_processor"},
+        {"id": "judge", "description": "Decide whether the report is long", "depends_on": ["count"]},
+        {"id": "summary", "description": "Summarize the line count", "target": "llm", "depends_on": ["count"]}
+    ]))
+    .unwrap();
+    let plan = engine.update_plan(&plan.id, edited.clone()).await.unwrap();
+    assert_eq!(plan.tasks.len(), 3);
+    edited[2].target = "a2a:nobody".to_string();
+    assert!(matches!(
+        engine.update_plan(&plan.id, edited).await,
+        Err(PlanError::Invalid(_))
+    ));
+
+    let answer = engine.execute_plan(&plan.id).await.unwrap();
+    assert_eq!(answer, "The report has 42 lines.");
+
+    let plan = engine.get_plan(&plan.id).await.unwrap();
+    assert_eq!(plan.status, PlanStatus::Completed);
+    assert_eq!(plan.answer.as_deref(), Some(answer.as_str()));
+    assert!(plan
+        .tasks
+        .iter()
+        .all(|task| matches!(task.status, OrchestrationTaskStatus::Completed)));
+    let count = &plan.tasks[0];
+    for dependent in &plan.tasks[1..] {
+        assert!(dependent.started_at.unwrap() >= count.finished_at.unwrap());
+    }
+
+    // 下游子任务拿到上游结果，汇总提示包含全部子任务结果
+    let prompts = prompts.lock().unwrap().clone();
+    let judge = prompts
+        .iter()
+        .find(|prompt| prompt.starts_with("Decide whether the report is long"))
+        .unwrap();
+    assert!(judge.contains(r#"{"lines":42}"#));
+    let synthesis = prompts.last().unwrap();
+    assert!(synthesis.contains("Goal: How long is the report?"));
+    assert!(synthesis.contains("handled: Summarize the line count"));
+
+    assert!(matches!(
+        engine.execute_plan(&plan.id).await,
+        Err(PlanError::NotDraft(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_failed_subtask_cancels_dependents() {
+    let llm = scripted_llm(
+        |prompt| {
+            if prompt.contains("Available targets") {
+                r#"{"tasks": [
+                    {"id": "ask", "description": "Ask the peer", "target": "a2a:info_agent"},
+                    {"id": "reply", "description": "Write the reply", "depends_on": ["ask"]}
+                ]}"#
+                .to_string()
+            } else {
+                "unused".to_string()
+            }
+        },
+        Arc::new(Mutex::new(Vec::new())),
+    )
+    .await;
+    let peer = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&peer)
+        .await;
+    let engine = new_engine(Arc::new(StateManager::new()))
+        .await
+        .with_llm(llm)
+        .with_a2a_client("info_agent", A2AClient::new(peer.uri()));
+
+    let result = engine.plan_and_execute("Ask the peer and reply").await;
+    assert!(matches!(result, Err(PlanError::TasksFailed(ref failed)) if failed == &["ask"]));
+
+    let plan = &engine.list_plans().await[0];
+    assert_eq!(plan.status, PlanStatus::Failed);
+    assert!(matches!(
+        plan.tasks[0].status,
+        OrchestrationTaskStatus::Failed
+    ));
+    assert!(matches!(
+        plan.tasks[1].status,
+        OrchestrationTaskStatus::Canceled
+    ));
+    assert!(plan.answer.is_none());
+}
+
+#[tokio::test]
+async fn test_plan_is_audited_for_its_user_and_oversized_plans_are_rejected() {
+    let llm = scripted_llm(
+        |prompt| {
+            if prompt.contains("Available targets") {
+                r#"{"tasks": [{"id": "answer", "description": "Answer the question"}]}"#.to_string()
+            } else {
+                "42".to_string()
+            }
+        },
+        Arc::new(Mutex::new(Vec::new())),
+    )
+    .await;
+    let audit = Arc::new(AuditTrail::default());
+    let engine = new_engine(Arc::new(StateManager::new()))
+        .await
+        .with_llm(llm)
+        .with_audit(audit.clone());
+
+    let answer = engine.plan_and_execute_as("alice", "What is the answer?").await.unwrap();
+    assert_eq!(answer, "42");
+    assert_eq!(engine.list_plans().await[0].user_id, "alice");
+    // 规划、子任务和汇总三次 LLM 调用都记在 alice 名下
+    let logs = audit.recent(None).await;
+    assert_eq!(logs.len(), 3);
+    assert!(logs.iter().all(|log| log.user_id == "alice" && log.action == action::LLM_CALL));
+
+    // 过长的 LLM 回复不解析
+    let llm = scripted_llm(
+        |_| format!(r#"{{"tasks": [], "padding": "{}"}}"#, "x".repeat(64 * 1024)),
+        Arc::new(Mutex::new(Vec::new())),
+    )
+    .await;
+    let engine = new_engine(Arc::new(StateManager::new())).await.with_llm(llm);
+    assert!(matches!(
+        engine.create_plan("What is the answer?").await,
+        Err(PlanError::Planning(reason)) if reason.contains("字节")
+    ));
+}
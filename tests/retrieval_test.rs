@@ -0,0 +1,185 @@
+//! 向量化和向量存储测试
+//!
+//! 远程向量接口（OpenAI、Gemini、Ollama）按各自的请求格式调用并保持输入顺序；
+//! 本地向量存储保存到磁盘后可以重新载入；Qdrant 后端按需创建集合并映射记录ID。
+
+use omni_agent::services::embeddings::{
+    EmbeddingConfig, EmbeddingError, EmbeddingProvider, EmbeddingProviderKind, GeminiEmbeddings,
+    OllamaEmbeddings, OpenAIEmbeddings,
+};
+use omni_agent::services::vector_store::{
+    IndexKind, LocalVectorStore, QdrantStore, VectorFilter, VectorRecord, VectorStore,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn texts() -> Vec<String> {
+    vec!["first".to_string(), "second".to_string()]
+}
+
+#[tokio::test]
+async fn test_remote_embedding_providers() {
+    let server = MockServer::start().await;
+    // OpenAI 可能不按输入顺序返回，按 index 排序
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .and(header("authorization", "Bearer sk-test"))
+        .and(body_partial_json(json!({"model": "text-embedding-3-small", "input": ["first", "second"]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                {"index": 1, "embedding": [0.0, 1.0]},
+                {"index": 0, "embedding": [1.0, 0.0]}
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/text-embedding-004:batchEmbedContents"))
+        .and(header("x-goog-api-key", "g-test"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "embeddings": [{"values": [1.0, 0.0]}, {"values": [0.0, 1.0]}]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/embed"))
+        .and(body_partial_json(json!({"model": "nomic-embed-text"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "embeddings": [[1.0, 0.0]]
+        })))
+        .mount(&server)
+        .await;
+
+    let expected = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+    let openai = OpenAIEmbeddings::new(
+        "sk-test".to_string(),
+        None,
+        Some(format!("{}/v1/", server.uri())),
+    );
+    assert_eq!(openai.embed(&texts()).await.unwrap(), expected);
+
+    let gemini = GeminiEmbeddings::new(
+        "g-test".to_string(),
+        None,
+        Some(format!("{}/v1beta", server.uri())),
+    );
+    assert_eq!(gemini.embed(&texts()).await.unwrap(), expected);
+
+    // 返回的向量数与输入不一致时报错
+    let ollama = OllamaEmbeddings::new(None, Some(server.uri()));
+    assert!(matches!(
+        ollama.embed(&texts()).await,
+        Err(EmbeddingError::InvalidResponse(_))
+    ));
+    assert_eq!(ollama.embed_one("first").await.unwrap(), vec![1.0, 0.0]);
+
+    let config = EmbeddingConfig {
+        provider: EmbeddingProviderKind::OpenAI,
+        api_key: Some("wrong".to_string()),
+        base_url: Some(format!("{}/v1", server.uri())),
+        ..EmbeddingConfig::default()
+    };
+    let error = config.build().unwrap().embed(&texts()).await.unwrap_err();
+    assert!(matches!(error, EmbeddingError::Api { status: 404, .. }));
+}
+
+#[tokio::test]
+async fn test_local_store_persists_to_disk() {
+    let path = std::env::temp_dir()
+        .join(format!("omni-agent-vectors-{}", uuid::Uuid::new_v4()))
+        .join("vectors.json");
+
+    let store = LocalVectorStore::open(&path, IndexKind::Hnsw).await.unwrap();
+    store
+        .upsert(vec![
+            VectorRecord::new("doc-1#0", vec![1.0, 0.0, 0.0]).with_payload("document", "doc-1"),
+            VectorRecord::new("doc-1#1", vec![0.8, 0.2, 0.0]).with_payload("document", "doc-1"),
+            VectorRecord::new("doc-2#0", vec![0.0, 0.0, 1.0]).with_payload("document", "doc-2"),
+        ])
+        .await
+        .unwrap();
+    store.delete(&["doc-1#1".to_string()]).await.unwrap();
+    drop(store);
+    // 写入只追加到日志，日志不长时不重写快照
+    assert!(!path.exists());
+    let log = path.with_extension("log");
+    assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 4);
+    // 写入中断留下的不完整行在重放时忽略
+    let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    std::io::Write::write_all(&mut file, b"{\"upsert\":{\"id\":").unwrap();
+    drop(file);
+
+    let store = LocalVectorStore::open(&path, IndexKind::Hnsw).await.unwrap();
+    assert_eq!(store.count().await.unwrap(), 2);
+    let matches = store.search(&[0.9, 0.1, 0.0], 2, None).await.unwrap();
+    assert_eq!(matches[0].id, "doc-1#0");
+    assert_eq!(matches[0].payload["document"], "doc-1");
+
+    store
+        .delete_matching(&VectorFilter::new().eq("document", "doc-1"))
+        .await
+        .unwrap();
+    let store = LocalVectorStore::open(&path, IndexKind::Flat).await.unwrap();
+    assert_eq!(store.count().await.unwrap(), 1);
+    assert!(store.get("doc-2#0").await.unwrap().is_some());
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn test_qdrant_store() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/collections/docs"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/docs"))
+        .and(header("api-key", "q-test"))
+        .and(body_partial_json(json!({"vectors": {"size": 2, "distance": "Cosine"}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": true})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/collections/docs/points"))
+        .and(body_partial_json(json!({"points": [{"payload": {"record_id": "a", "lang": "en"}}]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": {"status": "completed"}})))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/collections/docs/points/search"))
+        .and(body_partial_json(json!({
+            "limit": 3,
+            "filter": {"must": [{"key": "lang", "match": {"value": "en"}}]}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": [{"id": "x", "score": 0.9, "payload": {"record_id": "a", "lang": "en"}}]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/collections/docs/points/count"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"result": {"count": 1}})))
+        .mount(&server)
+        .await;
+
+    let store = QdrantStore::new(&server.uri(), "docs", Some("q-test".to_string()));
+    let record = VectorRecord::new("a", vec![1.0, 0.0]).with_payload("lang", "en");
+    store.upsert(vec![record.clone()]).await.unwrap();
+    // 集合只检查和创建一次
+    store.upsert(vec![record]).await.unwrap();
+
+    let matches = store
+        .search(&[1.0, 0.0], 3, Some(&VectorFilter::new().eq("lang", "en")))
+        .await
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].id, "a");
+    assert_eq!(matches[0].payload, json!({"lang": "en"}).as_object().unwrap().clone());
+    assert_eq!(store.count().await.unwrap(), 1);
+}
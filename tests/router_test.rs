@@ -0,0 +1,500 @@
+//! 智能路由测试
+//!
+//! LLM 分类策略在候选目标中选择并给出置信度，置信度不足或目标无效时回退到
+//! 本地LLM；路由器可由配置创建；聊天接口按路由决策把请求转发给 A2A 智能体；
+//! 示例配置中的路由规则可通过接口替换、添加、删除，并解释消息的路由过程；对回复的差评
+//! 降低规则的置信度，直到请求回退到本地LLM。
+
+use async_trait::async_trait;
+use omni_agent::config::RouterSettings;
+use omni_agent::core::decision::DecisionRule;
+use omni_agent::core::router::{
+    IntelligentRouter, LlmClassifierStrategy, RouteCandidate, RouteTarget, StrategyKind,
+};
+use omni_agent::llm::providers::{LLMError, LLMProvider, LLMRequest, LLMResponse, ProviderConfig};
+use omni_agent::llm::{LLMConfig, LLMService};
+use omni_agent::ui::api::{create_routes, AppState};
+use omni_agent::{AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// 返回预设回复的提供商，记录收到的提示
+struct ScriptedProvider {
+    reply: Arc<Mutex<String>>,
+    prompts: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl LLMProvider for ScriptedProvider {
+    async fn chat(&self, request: LLMRequest) -> Result<LLMResponse, LLMError> {
+        let prompt = request.messages.last().unwrap().content.clone();
+        self.prompts.lock().unwrap().push(prompt);
+        Ok(LLMResponse {
+            content: self.reply.lock().unwrap().clone(),
+            usage: None,
+            model: "scripted".to_string(),
+        })
+    }
+
+    async fn chat_stream(&self, request: LLMRequest) -> Result<String, LLMError> {
+        self.chat(request).await.map(|response| response.content)
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "scripted"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+async fn scripted_llm(reply: Arc<Mutex<String>>, prompts: Arc<Mutex<Vec<String>>>) -> LLMService {
+    let config = LLMConfig {
+        provider: "scripted".to_string(),
+        model: "scripted".to_string(),
+        temperature: 0.0,
+        max_tokens: 64,
+        use_mock: false,
+    };
+    let llm = LLMService::new(
+        config,
+        ProviderConfig {
+            openai: None,
+            claude: None,
+            google: None,
+        },
+    );
+    llm.manager
+        .add_provider(
+            "scripted".to_string(),
+            Box::new(ScriptedProvider { reply, prompts }),
+        )
+        .await;
+    llm
+}
+
+fn candidates() -> Vec<RouteCandidate> {
+    vec![
+        RouteCandidate::new(
+            RouteTarget::MCPTool("read_file".to_string()),
+            "Read the contents of a local file",
+        ),
+        RouteCandidate::new(
+            RouteTarget::A2AAgent("weather".to_string()),
+            "Weather forecast for a city",
+        ),
+    ]
+}
+
+/// 使用 `config.example.json` 中路由规则的路由器
+async fn example_router() -> IntelligentRouter {
+    let config: Value = serde_json::from_str(include_str!("../config.example.json")).unwrap();
+    let rules: Vec<DecisionRule> = serde_json::from_value(config["router"]["rules"].clone()).unwrap();
+    let router = IntelligentRouter::new();
+    router.decision_engine().replace_rules(rules).await.unwrap();
+    router
+}
+
+async fn serve(state: AppState) -> String {
+    let app = create_routes().with_state(state.with_router(example_router().await));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+#[tokio::test]
+async fn test_llm_classifier_decision_and_fallback() {
+    let reply = Arc::new(Mutex::new(
+        r#"{"target": "a2a:weather", "confidence": 0.92, "reasoning": "Asks for a forecast"}"#
+            .to_string(),
+    ));
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let classifier = LlmClassifierStrategy::new(scripted_llm(reply.clone(), prompts.clone()).await);
+    classifier.set_candidates(candidates()).await;
+    let router = IntelligentRouter::new().with_strategies(vec![Arc::new(classifier)]);
+
+    let decision = router
+        .route_message("Will it rain in Paris tomorrow?")
+        .await;
+    assert_eq!(
+        decision.target,
+        RouteTarget::A2AAgent("weather".to_string())
+    );
+    assert_eq!(decision.strategy, "llm");
+    assert!((decision.confidence - 0.92).abs() < f32::EPSILON);
+    assert_eq!(decision.reasoning, "Asks for a forecast");
+    let prompt = prompts.lock().unwrap().last().unwrap().clone();
+    assert!(prompt.contains("- mcp:read_file: Read the contents of a local file"));
+    assert!(prompt.contains("Will it rain in Paris tomorrow?"));
+
+    // 置信度低于阈值
+    *reply.lock().unwrap() =
+        r#"{"target": "mcp:read_file", "confidence": 0.3, "reasoning": "Unsure"}"#.to_string();
+    let decision = router.route_message("Hello").await;
+    assert_eq!(decision.target, RouteTarget::LocalLLM);
+    assert_eq!(decision.strategy, "fallback");
+    assert!(decision.reasoning.contains("mcp:read_file"));
+
+    // 不在候选中的目标和无法解析的回复都视为策略失败
+    for invalid in [
+        r#"{"target": "mcp:delete_everything", "confidence": 0.99}"#,
+        "I think the weather agent fits best.",
+    ] {
+        *reply.lock().unwrap() = invalid.to_string();
+        let decision = router.route_message("Hello").await;
+        assert_eq!(decision.target, RouteTarget::LocalLLM);
+        assert_eq!(decision.strategy, "fallback");
+    }
+}
+
+#[tokio::test]
+async fn test_router_from_settings() {
+    let llm = scripted_llm(Arc::default(), Arc::default()).await;
+    let rule = |action: &str| DecisionRule {
+        id: "invoice".to_string(),
+        name: "发票".to_string(),
+        description: "发票请求交给开票工具".to_string(),
+        condition: "发票".into(),
+        action: action.to_string(),
+        priority: 100,
+        enabled: true,
+    };
+
+    let settings = RouterSettings {
+        strategies: vec![StrategyKind::Rules, StrategyKind::Embedding],
+        confidence_threshold: 0.3,
+        rules: vec![rule("mcp:invoice")],
+        ..RouterSettings::default()
+    };
+    let router = IntelligentRouter::from_settings(&settings, llm.clone(), candidates())
+        .await
+        .unwrap();
+    assert_eq!(router.confidence_threshold(), 0.3);
+
+    let decision = router.route_message("帮我开一张发票").await;
+    assert_eq!(decision.target, RouteTarget::MCPTool("invoice".to_string()));
+    assert_eq!(decision.strategy, "rules");
+
+    let decision = router.route_message("weather forecast for Berlin").await;
+    assert_eq!(
+        decision.target,
+        RouteTarget::A2AAgent("weather".to_string())
+    );
+    assert_eq!(decision.strategy, "embedding");
+
+    // 规则动作必须是有效的目标名称
+    let settings = RouterSettings {
+        rules: vec![rule("route_to_invoice_tool")],
+        ..settings
+    };
+    assert!(
+        IntelligentRouter::from_settings(&settings, llm, candidates())
+            .await
+            .is_err()
+    );
+
+    let settings: RouterSettings = serde_json::from_value(json!({
+        "strategies": ["rules", "embedding", "llm"]
+    }))
+    .unwrap();
+    assert_eq!(settings.strategies.len(), 3);
+    assert!(settings.rules.is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_forwards_routed_requests_to_a2a_agent() {
+    let peer = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "info",
+            "version": "1.0.0",
+            "description": "Weather and time information",
+            "capabilities": ["weather", "time"],
+            "endpoints": ["/messages"],
+            "metadata": {}
+        })))
+        .mount(&peer)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({
+            "recipient": "info_agent",
+            "content": {"type": "Text", "text": "北京今天天气怎么样"}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": uuid::Uuid::new_v4(),
+            "sender": "info_agent",
+            "recipient": "router-agent",
+            "content": {"type": "Text", "text": "晴，21°C"},
+            "timestamp": chrono::Utc::now()
+        })))
+        .expect(1)
+        .mount(&peer)
+        .await;
+
+    let agent = AgentBuilder::new("router-agent", "路由测试智能体")
+        .add_a2a("info_agent", &peer.uri())
+        .build()
+        .await
+        .unwrap();
+    let candidates = RouteCandidate::from_manifests(&*agent.manifests.read().await);
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(
+        candidates[0].description,
+        "Weather and time information weather time"
+    );
+
+    let base = serve(AppState::new(agent, AppConfig::default())).await;
+    let client = reqwest::Client::new();
+
+    let body: Value = client
+        .post(format!("{base}/chat"))
+        .json(&json!({"message": "北京今天天气怎么样", "session_id": "s1"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["source"], "a2a_agent");
+    assert_eq!(body["message"], "晴，21°C");
+    assert_eq!(body["details"]["agent"], "info_agent");
+    assert_eq!(body["details"]["route"]["strategy"], "rules");
+    assert_eq!(body["details"]["session_id"], "s1");
+
+    // 没有匹配的规则时由本地LLM回答
+    let body: Value = client
+        .post(format!("{base}/chat"))
+        .json(&json!({"message": "你好", "session_id": "s1"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["source"], "local_llm");
+    assert_eq!(body["details"]["route"]["target"], "LocalLLM");
+    assert_eq!(body["details"]["route"]["strategy"], "fallback");
+}
+
+#[tokio::test]
+async fn test_routing_rules_api_and_explain() {
+    let agent = AgentBuilder::new("router-agent", "路由测试智能体")
+        .build()
+        .await
+        .unwrap();
+    let base = serve(AppState::new(agent, AppConfig::default())).await;
+    let client = reqwest::Client::new();
+
+    let rules: Vec<Value> = client
+        .get(format!("{base}/routing/rules"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rules.len(), 4);
+    assert_eq!(rules[0]["id"], "route_time");
+    assert_eq!(rules[0]["condition"], json!({"contains": "时间"}));
+
+    // 金牌用户的发票请求交给开票工具，旧的字符串条件仍然有效
+    let response = client
+        .put(format!("{base}/routing/rules"))
+        .json(&json!([
+            {
+                "id": "vip_invoice",
+                "name": "金牌发票",
+                "description": "金牌用户的发票请求",
+                "condition": {"all": [
+                    {"regex": "(?i)invoice|发票"},
+                    {"field": {"path": "user.tier", "one_of": ["gold", "platinum"]}},
+                    {"not": {"role": "guest"}}
+                ]},
+                "action": "mcp:create_invoice",
+                "priority": 100,
+                "enabled": true
+            },
+            {
+                "id": "greeting",
+                "name": "问候",
+                "description": "问候由本地LLM回答",
+                "condition": "你好",
+                "action": "llm",
+                "priority": 10,
+                "enabled": true
+            }
+        ]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<Value>().await.unwrap()["rules"], 2);
+
+    let explain = |message: &str, metadata: Value| {
+        let request = client
+            .post(format!("{base}/routing/explain"))
+            .json(&json!({"message": message, "role": "user", "metadata": metadata}));
+        async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+    };
+    let body = explain("Please send the INVOICE", json!({"user": {"tier": "gold"}})).await;
+    assert_eq!(
+        body["decision"]["target"],
+        json!({"MCPTool": "create_invoice"})
+    );
+    assert_eq!(body["decision"]["strategy"], "rules");
+    assert_eq!(body["rules"][0]["rule_id"], "vip_invoice");
+    assert_eq!(body["rules"][0]["matched"], true);
+    assert_eq!(body["rules"][1]["matched"], false);
+
+    let body = explain(
+        "Please send the invoice",
+        json!({"user": {"tier": "silver"}}),
+    )
+    .await;
+    assert_eq!(body["decision"]["strategy"], "fallback");
+    assert_eq!(body["rules"][0]["matched"], false);
+    assert!(body["rules"][0]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("user.tier"));
+
+    // 聊天请求的 context 作为元数据参与路由
+    let body: Value = client
+        .post(format!("{base}/chat"))
+        .json(&json!({"message": "发票", "context": {"user": {"tier": "platinum"}}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["details"]["route"]["strategy"], "rules");
+    assert_eq!(
+        body["details"]["route"]["target"],
+        json!({"MCPTool": "create_invoice"})
+    );
+
+    let rule = |id: &str, condition: Value, action: &str| {
+        json!({
+            "id": id,
+            "name": id,
+            "description": "",
+            "condition": condition,
+            "action": action,
+            "priority": 50,
+            "enabled": true
+        })
+    };
+    let add = |rule: Value| {
+        let request = client.post(format!("{base}/routing/rules")).json(&rule);
+        async move { request.send().await.unwrap().status() }
+    };
+    assert_eq!(
+        add(rule(
+            "weekend",
+            json!({"time_window": {"start": "22:00", "end": "06:00", "days": ["sat", "sun"]}}),
+            "a2a:night_desk"
+        ))
+        .await,
+        201
+    );
+    assert_eq!(add(rule("weekend", json!("周末"), "llm")).await, 409);
+    assert_eq!(
+        add(rule("bad_action", json!("周末"), "route_somewhere")).await,
+        400
+    );
+    assert_eq!(add(rule("empty_any", json!({"any": []}), "llm")).await, 400);
+    assert!(add(rule("bad_regex", json!({"regex": "("}), "llm"))
+        .await
+        .is_client_error());
+
+    let rules: Vec<Value> = client
+        .get(format!("{base}/routing/rules"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rules.len(), 3);
+
+    let delete = || async {
+        client
+            .delete(format!("{base}/routing/rules/weekend"))
+            .send()
+            .await
+            .unwrap()
+            .status()
+    };
+    assert_eq!(delete().await, 204);
+    assert_eq!(delete().await, 404);
+}
+
+#[tokio::test]
+async fn test_feedback_lowers_rule_confidence() {
+    let agent = AgentBuilder::new("router-agent", "路由测试智能体")
+        .build()
+        .await
+        .unwrap();
+    let base = serve(AppState::new(agent, AppConfig::default())).await;
+    let client = reqwest::Client::new();
+
+    let chat = || async {
+        client
+            .post(format!("{base}/chat"))
+            .json(&json!({"message": "查询天气"}))
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap()
+    };
+    let rate = |message_id: Value, rating: &str| {
+        let request = client
+            .post(format!("{base}/messages/{}/feedback", message_id.as_str().unwrap()))
+            .json(&json!({ "rating": rating }));
+        async move { request.send().await.unwrap() }
+    };
+
+    let body = chat().await;
+    assert_eq!(body["details"]["route"]["rule_id"], "route_weather");
+    let confidence = body["details"]["route"]["confidence"].as_f64().unwrap();
+    assert!((confidence - 0.8).abs() < 1e-6);
+    let message_id = body["details"]["message_id"].clone();
+
+    let response = rate(message_id.clone(), "down").await;
+    assert_eq!(response.status(), 200);
+    let record: Value = response.json().await.unwrap();
+    assert_eq!(record["rule_id"], "route_weather");
+    assert_eq!(record["action"], "a2a:info_agent");
+    assert_eq!(record["feedback"], -1.0);
+    assert_eq!(rate(message_id, "down").await.status(), 409);
+    assert_eq!(rate(json!("unknown"), "up").await.status(), 404);
+
+    // 一次差评后规则仍然生效，两次差评后置信度低于阈值
+    let body = chat().await;
+    assert_eq!(body["details"]["route"]["strategy"], "rules");
+    rate(body["details"]["message_id"].clone(), "down").await;
+    let body = chat().await;
+    assert_eq!(body["details"]["route"]["strategy"], "fallback");
+
+    let stats: Value = client
+        .get(format!("{base}/routing/feedback"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stats["rules"]["route_weather"]["negative"], 2);
+    assert_eq!(stats["actions"]["a2a:info_agent"]["count"], 2);
+}
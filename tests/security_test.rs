@@ -0,0 +1,112 @@
+//! 安全管理器测试
+//!
+//! 用户账户和令牌吊销列表保存在 SQLite 中，重启后密码哈希、锁定状态、权限
+//! 和吊销状态仍然有效。
+
+use omni_agent::integrations::database::{DataRepository, SqliteRepository};
+use omni_agent::services::security::{
+    Credentials, Permission, SecurityConfig, SecurityError, SecurityManager, UserRole,
+};
+use std::sync::Arc;
+
+async fn open_manager(url: &str) -> SecurityManager {
+    let repository = Arc::new(SqliteRepository::connect(url).await.unwrap());
+    let manager = SecurityManager::from_config(SecurityConfig {
+        max_failed_attempts: 2,
+        jwt_secret: "test_secret".to_string(),
+        ..SecurityConfig::default()
+    })
+    .unwrap();
+    manager.use_store(repository).await.unwrap();
+    manager
+}
+
+fn credentials(username: &str, password: &str) -> Credentials {
+    Credentials {
+        username: username.to_string(),
+        password: password.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_accounts_persist_across_restarts() {
+    let path =
+        std::env::temp_dir().join(format!("omni-agent-security-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+
+    let (admin_id, user_id) = {
+        let manager = open_manager(&url).await;
+        let admin_id = manager
+            .register_user("root", "admin-password", UserRole::Admin)
+            .await
+            .unwrap();
+        let user_id = manager
+            .register_user("alice", "alice-password", UserRole::User)
+            .await
+            .unwrap();
+        manager
+            .add_permission(&user_id, Permission::ToolAccess("search".to_string()))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            assert!(manager
+                .authenticate(&credentials("alice", "guess"))
+                .await
+                .is_err());
+        }
+        (admin_id, user_id)
+    };
+
+    // 密码以 argon2 哈希保存
+    let repository = SqliteRepository::connect(&url).await.unwrap();
+    let accounts = repository.list_user_accounts().await.unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert!(accounts.iter().all(
+        |a| a.password_hash.starts_with("$argon2id$") && !a.password_hash.contains("password")
+    ));
+
+    let manager = open_manager(&url).await;
+    assert_eq!(manager.list_users().await.len(), 2);
+    let admin = manager.find_user_by_username("root").await.unwrap();
+    assert_eq!(admin.id, admin_id);
+    assert_eq!(admin.role, UserRole::Admin);
+    assert!(manager
+        .get_user(&user_id)
+        .await
+        .unwrap()
+        .permissions
+        .contains(&Permission::ToolAccess("search".to_string())));
+    assert!(manager
+        .authenticate(&credentials("root", "admin-password"))
+        .await
+        .is_ok());
+
+    // 锁定状态在重启后保留，管理员重置密码后解除
+    let result = manager
+        .authenticate(&credentials("alice", "alice-password"))
+        .await;
+    assert!(matches!(result, Err(SecurityError::AccountLocked(_))));
+    manager
+        .reset_password(&user_id, "fresh-password")
+        .await
+        .unwrap();
+    drop(manager);
+
+    let manager = open_manager(&url).await;
+    let issued = manager
+        .authenticate(&credentials("alice", "fresh-password"))
+        .await
+        .unwrap();
+    let refresh = issued.refresh_token.clone().unwrap();
+    manager.revoke_token(&issued.token).await.unwrap();
+    let refreshed = manager.refresh_token(&refresh).await.unwrap();
+    drop(manager);
+
+    // 吊销在重启后仍然有效，同一密钥签发的其他令牌不受影响
+    let manager = open_manager(&url).await;
+    assert!(manager.validate_token(&issued.token).await.is_err());
+    assert!(manager.refresh_token(&refresh).await.is_err());
+    assert!(manager.validate_token(&refreshed.token).await.is_ok());
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,194 @@
+//! 多会话隔离测试
+//!
+//! 验证不同会话的上下文互不可见，且同一会话的历史在多轮对话间保留。
+
+use omni_agent::protocol::message::{Message, MessageContent};
+use omni_agent::services::memory::{CompressionConfig, MemoryService, SUMMARY_METADATA_KEY};
+use omni_agent::ui::api::{create_routes, AppState};
+use omni_agent::{AgentBuilder, AppConfig};
+use serde_json::{json, Value};
+
+fn text_message(text: &str, session_id: &str) -> Message {
+    Message::new(
+        "user".to_string(),
+        "session-agent".to_string(),
+        MessageContent::Text {
+            text: text.to_string(),
+        },
+        Some(json!({ "session_id": session_id })),
+    )
+}
+
+fn context_messages(response: &Message) -> u64 {
+    response.metadata.as_ref().unwrap()["context_messages"]
+        .as_u64()
+        .unwrap()
+}
+
+async fn spawn_server() -> String {
+    let agent = AgentBuilder::new("session-agent", "会话测试智能体")
+        .build()
+        .await
+        .unwrap();
+    let app = create_routes().with_state(AppState::new(agent, AppConfig::default()));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_sessions_keep_separate_history() {
+    let agent = AgentBuilder::new("session-agent", "会话测试智能体")
+        .build()
+        .await
+        .unwrap();
+
+    let first = agent.process_message(text_message("你好", "alice")).await.unwrap();
+    assert_eq!(context_messages(&first), 0);
+
+    // 另一个会话看不到 alice 的历史
+    let other = agent.process_message(text_message("你好", "bob")).await.unwrap();
+    assert_eq!(context_messages(&other), 0);
+
+    // alice 的第二轮可以看到第一轮的用户消息和回复
+    let second = agent.process_message(text_message("继续", "alice")).await.unwrap();
+    assert_eq!(context_messages(&second), 2);
+    assert_eq!(second.metadata.as_ref().unwrap()["session_id"], "alice");
+    assert_eq!(agent.sessions.len().await, 2);
+}
+
+#[tokio::test]
+async fn test_concurrent_sessions_are_isolated() {
+    let agent = std::sync::Arc::new(
+        AgentBuilder::new("session-agent", "会话测试智能体")
+            .build()
+            .await
+            .unwrap(),
+    );
+
+    let tasks = (0..8).map(|i| {
+        let agent = agent.clone();
+        tokio::spawn(async move {
+            let session_id = format!("session-{i}");
+            for turn in 0..3 {
+                let response = agent
+                    .process_message(text_message(&format!("第{turn}轮"), &session_id))
+                    .await
+                    .unwrap();
+                assert_eq!(context_messages(&response), turn * 2);
+            }
+        })
+    });
+    for task in futures::future::join_all(tasks).await {
+        task.unwrap();
+    }
+
+    assert_eq!(agent.sessions.len().await, 8);
+    for info in agent.sessions.list().await {
+        let session = agent.sessions.get(&info.id).await.unwrap();
+        assert_eq!(session.state_machine.read().await.context.len(), 6);
+    }
+}
+
+#[tokio::test]
+async fn test_long_session_context_is_compressed() {
+    let agent = AgentBuilder::new("session-agent", "会话测试智能体")
+        .context_compression(CompressionConfig {
+            context_window_tokens: 400,
+            keep_recent: 2,
+            ..CompressionConfig::default()
+        })
+        .build()
+        .await
+        .unwrap();
+
+    for turn in 0..12 {
+        let text = format!("第{turn}轮：请记住订单号 A-{turn}，这是一段较长的说明文字，用来占用上下文");
+        agent.process_message(text_message(&text, "long")).await.unwrap();
+    }
+
+    // 较早的对话被概括为一条摘要消息，上下文保持在窗口以内
+    let session = agent.sessions.get("long").await.unwrap();
+    let context = session.state_machine.read().await.get_context();
+    assert!(MemoryService::count_tokens(&context) < 400);
+    assert!(context.iter().any(|message| {
+        message
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata[SUMMARY_METADATA_KEY] == true)
+    }));
+}
+
+#[tokio::test]
+async fn test_chat_api_sessions() {
+    let base = spawn_server().await;
+    let client = reqwest::Client::new();
+
+    let chat = |session_id: &'static str, message: &'static str| {
+        let client = client.clone();
+        let url = format!("{base}/chat");
+        async move {
+            client
+                .post(url)
+                .json(&json!({ "message": message, "session_id": session_id }))
+                .send()
+                .await
+                .unwrap()
+                .json::<Value>()
+                .await
+                .unwrap()
+        }
+    };
+
+    let (a, b) = tokio::join!(chat("a", "来自 A 的消息"), chat("b", "来自 B 的消息"));
+    assert_eq!(a["details"]["session_id"], "a");
+    assert_eq!(b["details"]["session_id"], "b");
+
+    let session: Value = client
+        .get(format!("{base}/sessions/a"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let messages = session["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(!session.to_string().contains("来自 B 的消息"));
+
+    // 未指定会话ID时分配新会话并在响应中返回
+    let fresh = chat_without_session(&client, &base).await;
+    assert!(!fresh["details"]["session_id"].as_str().unwrap().is_empty());
+
+    let status = client
+        .delete(format!("{base}/sessions/b"))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, reqwest::StatusCode::NO_CONTENT);
+
+    let status = client
+        .get(format!("{base}/sessions/b"))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
+
+async fn chat_without_session(client: &reqwest::Client, base: &str) -> Value {
+    client
+        .post(format!("{base}/chat"))
+        .json(&json!({ "message": "匿名消息" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
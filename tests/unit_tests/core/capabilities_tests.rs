@@ -7,13 +7,12 @@ use tokio;
 async fn test_register_capability() {
     let capability_manager = CapabilityManager::new();
     
-    let capability = Capability {
-        id: "test_capability_1".to_string(),
-        name: "测试能力1".to_string(),
-        description: "用于测试的能力1".to_string(),
-        category: "test".to_string(),
-        enabled: true,
-    };
+    let capability = Capability::new(
+        "test_capability_1",
+        "测试能力1",
+        "用于测试的能力1",
+        "test",
+    );
     
     let result = capability_manager.register_capability(capability).await;
     assert!(result.is_ok());
@@ -23,13 +22,12 @@ async fn test_register_capability() {
 async fn test_get_capability() {
     let capability_manager = CapabilityManager::new();
     
-    let capability = Capability {
-        id: "test_capability_2".to_string(),
-        name: "测试能力2".to_string(),
-        description: "用于测试的能力2".to_string(),
-        category: "test".to_string(),
-        enabled: true,
-    };
+    let capability = Capability::new(
+        "test_capability_2",
+        "测试能力2",
+        "用于测试的能力2",
+        "test",
+    );
     
     capability_manager.register_capability(capability.clone()).await.unwrap();
     
@@ -50,13 +48,12 @@ async fn test_get_all_capabilities() {
     
     // 注册多个能力
     for i in 1..=3 {
-        let capability = Capability {
-            id: format!("test_capability_{}", i),
-            name: format!("测试能力{}", i),
-            description: format!("用于测试的能力{}", i),
-            category: "test".to_string(),
-            enabled: true,
-        };
+        let capability = Capability::new(
+            format!("test_capability_{}", i),
+            format!("测试能力{}", i),
+            format!("用于测试的能力{}", i),
+            "test",
+        );
         capability_manager.register_capability(capability).await.unwrap();
     }
     
@@ -75,13 +72,13 @@ async fn test_capability_enabled_status() {
     let capability_manager = CapabilityManager::new();
     
     // 注册一个禁用的能力
-    let disabled_capability = Capability {
-        id: "disabled_capability".to_string(),
-        name: "禁用能力".to_string(),
-        description: "一个禁用的能力".to_string(),
-        category: "test".to_string(),
-        enabled: false,
-    };
+    let mut disabled_capability = Capability::new(
+        "disabled_capability",
+        "禁用能力",
+        "一个禁用的能力",
+        "test",
+    );
+    disabled_capability.enabled = false;
     
     capability_manager.register_capability(disabled_capability).await.unwrap();
     
@@ -94,13 +91,12 @@ async fn test_capability_enabled_status() {
 async fn test_capability_fields_validation() {
     let capability_manager = CapabilityManager::new();
     
-    let capability = Capability {
-        id: "field_test_capability".to_string(),
-        name: "字段测试能力".to_string(),
-        description: "用于测试所有字段的完整能力".to_string(),
-        category: "validation".to_string(),
-        enabled: true,
-    };
+    let capability = Capability::new(
+        "field_test_capability",
+        "字段测试能力",
+        "用于测试所有字段的完整能力",
+        "validation",
+    );
     
     capability_manager.register_capability(capability.clone()).await.unwrap();
     
@@ -0,0 +1,524 @@
+//! 用户长期记忆测试
+//!
+//! 记忆通过工具和 REST 接口写入，按用户和智能体归属，持久化到 SQLite 后在
+//! 重启后仍然存在；每轮对话前召回当前用户的记忆。已认证的调用方只能访问
+//! 自己的记忆。被挤出会话上下文的消息概括为按用户和会话归属的对话记忆。
+
+use async_trait::async_trait;
+use omni_agent::agent::AgentError;
+use omni_agent::config::AppConfig;
+use omni_agent::agent::session::SessionConfig;
+use omni_agent::core::state::{MemoryConfig, MemoryScope, MemoryTier, UserMemoryConfig};
+use omni_agent::integrations::database::{DataRepository, SqliteRepository};
+use omni_agent::protocol::message::{Message, MessageContent};
+use omni_agent::services::security::{Permission, SecurityConfig, SecurityManager};
+use omni_agent::services::tools::{Tool, ToolContext, ToolError, ToolExecutionEngine};
+use omni_agent::ui::api::{create_app, create_routes, AppState};
+use omni_agent::{Agent, AgentBuilder};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+fn config() -> UserMemoryConfig {
+    UserMemoryConfig {
+        enabled: true,
+        ..UserMemoryConfig::default()
+    }
+}
+
+fn context(user_id: &str) -> ToolContext {
+    ToolContext {
+        user_id: Some(user_id.to_string()),
+        session_id: None,
+    }
+}
+
+async fn agent_with_store(url: &str) -> Agent {
+    let repository = Arc::new(SqliteRepository::connect(url).await.unwrap());
+    AgentBuilder::new("memory-agent", "用户记忆测试智能体")
+        .user_memory(config())
+        .memory_store(repository)
+        .build()
+        .await
+        .unwrap()
+}
+
+fn text_message(sender: &str, text: &str) -> Message {
+    Message::new(
+        sender.to_string(),
+        "memory-agent".to_string(),
+        MessageContent::Text {
+            text: text.to_string(),
+        },
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_memory_tools_persist_across_restarts() {
+    let path = std::env::temp_dir().join(format!("omni-agent-memory-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+
+    {
+        let agent = agent_with_store(&url).await;
+        let capabilities = agent.get_capabilities().await;
+        for tool in ["remember", "recall", "forget", "list_memories"] {
+            assert!(capabilities.iter().any(|c| c.id == tool), "missing {tool}");
+        }
+
+        // 工具调用必须属于某个用户
+        let error = agent
+            .tools
+            .execute_tool("remember", json!({"content": "likes tea"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ToolError::PermissionDenied(_)));
+
+        let remembered = context("alice")
+            .scope(agent.tools.execute_tool(
+                "remember",
+                json!({"content": "Alice is allergic to peanuts", "tags": ["health"]}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(remembered["memory"]["user_id"], "alice");
+        assert_eq!(remembered["memory"]["agent_id"], "memory-agent");
+        context("alice")
+            .scope(
+                agent
+                    .tools
+                    .execute_tool("remember", json!({"content": "Alice lives in Lyon"})),
+            )
+            .await
+            .unwrap();
+        context("bob")
+            .scope(
+                agent
+                    .tools
+                    .execute_tool("remember", json!({"content": "Bob is allergic to cats"})),
+            )
+            .await
+            .unwrap();
+
+        // 其他用户不能删除 alice 的记忆
+        let id = remembered["memory"]["id"].as_str().unwrap();
+        let result = context("bob")
+            .scope(agent.tools.execute_tool("forget", json!({"id": id})))
+            .await
+            .unwrap();
+        assert_eq!(result["forgotten"], false);
+    }
+
+    // 重启后记忆仍然存在
+    let agent = agent_with_store(&url).await;
+    let recalled = context("alice")
+        .scope(agent.tools.execute_tool(
+            "recall",
+            json!({"query": "what is alice allergic to?", "limit": 1}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        recalled["memories"][0]["memory"]["content"],
+        "Alice is allergic to peanuts"
+    );
+
+    let tagged = context("alice")
+        .scope(
+            agent
+                .tools
+                .execute_tool("list_memories", json!({"tags": ["health"]})),
+        )
+        .await
+        .unwrap();
+    assert_eq!(tagged["memories"].as_array().unwrap().len(), 1);
+
+    let id = tagged["memories"][0]["id"].as_str().unwrap();
+    let result = context("alice")
+        .scope(agent.tools.execute_tool("forget", json!({"id": id})))
+        .await
+        .unwrap();
+    assert_eq!(result["forgotten"], true);
+    drop(agent);
+
+    let agent = agent_with_store(&url).await;
+    let memory = agent.user_memory.clone().unwrap();
+    let contents: Vec<String> = memory
+        .list(&Default::default())
+        .await
+        .into_iter()
+        .map(|memory| memory.content)
+        .collect();
+    assert_eq!(contents, ["Alice lives in Lyon", "Bob is allergic to cats"]);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_memory_endpoints() {
+    let repository = Arc::new(SqliteRepository::connect("sqlite::memory:").await.unwrap());
+    let agent = AgentBuilder::new("memory-agent", "用户记忆测试智能体")
+        .user_memory(config())
+        .memory_store(repository.clone())
+        .build()
+        .await
+        .unwrap();
+    let app = create_routes().with_state(AppState::new(agent, AppConfig::default()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+
+    for (user, agent_id, content) in [
+        ("alice", None, "Alice prefers email over phone calls"),
+        ("alice", Some("billing-agent"), "Alice pays yearly"),
+        ("bob", None, "Bob prefers phone calls"),
+    ] {
+        let response = client
+            .post(format!("{base}/memories"))
+            .json(&json!({"user_id": user, "agent_id": agent_id, "content": content, "tags": ["contact"]}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+    }
+    let response = client
+        .post(format!("{base}/memories"))
+        .json(&json!({"user_id": "alice", "content": " "}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let listed: Vec<Value> = client
+        .get(format!("{base}/memories?user_id=alice"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0]["agent_id"], "memory-agent");
+
+    let hits: Vec<Value> = client
+        .post(format!("{base}/memories/recall"))
+        .json(&json!({"user_id": "bob", "query": "how to contact bob"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["memory"]["content"], "Bob prefers phone calls");
+
+    let id = hits[0]["memory"]["id"].as_str().unwrap();
+    let response = client
+        .get(format!("{base}/memories/{id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .delete(format!("{base}/memories/{id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client
+        .get(format!("{base}/memories/{id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    // 删除用户在所有智能体中的记忆，包括存储中的
+    let deleted: Value = client
+        .delete(format!("{base}/users/alice/memories"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(deleted["deleted"], 2);
+    assert!(repository
+        .list_memories(Some("alice"), None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+struct RecordingTool {
+    users: Mutex<Vec<Option<String>>>,
+}
+
+#[async_trait]
+impl Tool for RecordingTool {
+    fn name(&self) -> &str {
+        "mock_tool"
+    }
+
+    fn description(&self) -> &str {
+        "Records who called it"
+    }
+
+    async fn execute(&self, _parameters: Value) -> Result<Value, ToolError> {
+        let user = ToolContext::current().and_then(|context| context.user_id);
+        self.users.lock().unwrap().push(user);
+        Ok(json!({"ok": true}))
+    }
+}
+
+#[tokio::test]
+async fn test_turns_recall_memories_of_the_sender() {
+    let tool = Arc::new(RecordingTool {
+        users: Mutex::new(Vec::new()),
+    });
+    let tools = Arc::new(ToolExecutionEngine::new());
+    tools.register_tool(tool.clone()).await.unwrap();
+    let agent = AgentBuilder::new("memory-agent", "用户记忆测试智能体")
+        .user_memory(config())
+        .tools(tools)
+        .max_tool_iterations(1)
+        .build()
+        .await
+        .unwrap();
+    agent
+        .user_memory
+        .as_ref()
+        .unwrap()
+        .remember(
+            "alice",
+            "memory-agent",
+            "Alice's favourite city is Lyon",
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+
+    // 模拟 LLM 在元数据中报告上下文消息数，召回的记忆作为一条系统消息加入
+    let context_messages =
+        |reply: &Message| reply.metadata.as_ref().unwrap()["context_messages"].clone();
+    let reply = agent
+        .process_message(text_message("alice", "Which city is my favourite?"))
+        .await
+        .unwrap();
+    assert_eq!(context_messages(&reply), 1);
+    let reply = agent
+        .process_message(text_message("bob", "Which city is my favourite?"))
+        .await
+        .unwrap();
+    assert_eq!(context_messages(&reply), 0);
+
+    // 本地工具在调用者的上下文中执行
+    let result = agent
+        .process_message(text_message("carol", "use the tool"))
+        .await;
+    assert_eq!(result.unwrap_err(), AgentError::ToolLoopLimit(1));
+    assert_eq!(*tool.users.lock().unwrap(), [Some("carol".to_string())]);
+}
+
+async fn agent_with_conversation_memory(url: &str) -> Agent {
+    let repository = Arc::new(SqliteRepository::connect(url).await.unwrap());
+    AgentBuilder::new("memory-agent", "对话记忆测试智能体")
+        .session_config(SessionConfig {
+            max_context_size: 2,
+            ..SessionConfig::default()
+        })
+        .conversation_memory(MemoryConfig {
+            enabled: true,
+            summary_batch: 2,
+            medium_term_capacity: 1,
+            ..MemoryConfig::default()
+        })
+        .conversation_memory_store(repository)
+        .build()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_evicted_messages_become_conversation_memories() {
+    let path = std::env::temp_dir().join(format!("omni-agent-memory-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+    let alice = MemoryScope::new("alice", "s1");
+
+    {
+        let agent = agent_with_conversation_memory(&url).await;
+        let memory = agent.conversation_memory.clone().unwrap();
+        for text in ["My cat is called Mochi", "Tell me a joke", "Thanks"] {
+            agent
+                .process_in_session("s1", text_message("alice", text))
+                .await
+                .unwrap();
+        }
+
+        // 上下文只保留最近一轮，最早一轮的摘要已转入长期记忆，第二轮的摘要在中期记忆中
+        let long = memory.long_term().await;
+        assert_eq!(long.len(), 1);
+        assert_eq!(long[0].scope, alice);
+        assert_eq!(long[0].tier, MemoryTier::LongTerm);
+        assert!(long[0].content.contains("Mochi"));
+        assert_eq!(memory.medium_term(&alice).await.len(), 1);
+
+        // 召回的记忆和摘要作为一条系统消息加入上下文，只对同一用户和会话生效
+        let context_messages =
+            |reply: &Message| reply.metadata.as_ref().unwrap()["context_messages"].clone();
+        let reply = agent
+            .process_in_session("s1", text_message("alice", "What is my cat called?"))
+            .await
+            .unwrap();
+        assert_eq!(context_messages(&reply), 3);
+        let reply = agent
+            .process_in_session("s2", text_message("alice", "What is my cat called?"))
+            .await
+            .unwrap();
+        assert_eq!(context_messages(&reply), 0);
+        assert!(memory
+            .recall(&MemoryScope::new("bob", "s1"), "Mochi cat", 3)
+            .await
+            .is_empty());
+    }
+
+    // 重启后长期记忆从数据库加载，第四轮又把第二轮的摘要挤入了长期记忆
+    let agent = agent_with_conversation_memory(&url).await;
+    let memory = agent.conversation_memory.clone().unwrap();
+    assert_eq!(memory.long_term().await.len(), 2);
+    let recalled = memory.recall(&alice, "Mochi cat", 3).await;
+    assert_eq!(recalled.len(), 1);
+    assert!(recalled[0].entry.content.contains("Mochi"));
+
+    assert_eq!(memory.forget_user("alice").await.unwrap(), 2);
+    assert!(memory.long_term().await.is_empty());
+    let repository = SqliteRepository::connect(&url).await.unwrap();
+    assert!(repository.list_conversation_memories().await.unwrap().is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_memory_endpoints_are_scoped_to_the_caller() {
+    let agent = AgentBuilder::new("memory-agent", "用户记忆测试智能体")
+        .user_memory(config())
+        .build()
+        .await
+        .unwrap();
+    let security = Arc::new(
+        SecurityManager::from_config(SecurityConfig {
+            jwt_secret: "test_secret".to_string(),
+            require_auth: true,
+            ..SecurityConfig::default()
+        })
+        .unwrap(),
+    );
+    let mut keys = Vec::new();
+    for (name, permissions) in [
+        ("alice", vec![Permission::Read, Permission::Write]),
+        ("bob", vec![Permission::Read, Permission::Write]),
+        ("ops", vec![Permission::Admin]),
+    ] {
+        let user_id = security
+            .create_service_account(name, permissions.clone())
+            .await
+            .unwrap();
+        let key = security
+            .create_api_key(&user_id, name, permissions, None)
+            .await
+            .unwrap()
+            .1;
+        keys.push((user_id, key));
+    }
+    let [(alice_id, alice), (bob_id, bob), (_, admin)] = <[_; 3]>::try_from(keys).unwrap();
+    let app = create_app(AppState::new(agent, AppConfig::default()).with_security(security));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{base}/memories"))
+        .header("X-API-Key", &alice)
+        .json(&json!({"user_id": alice_id, "content": "Alice prefers email"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let memory: Value = response.json().await.unwrap();
+    let id = memory["id"].as_str().unwrap();
+
+    // 其他用户不能写入、列出、召回、查看或删除 alice 的记忆
+    let response = client
+        .post(format!("{base}/memories"))
+        .header("X-API-Key", &bob)
+        .json(&json!({"user_id": alice_id, "content": "Alice moved away"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    for request in [
+        client.get(format!("{base}/memories?user_id={alice_id}")),
+        client
+            .post(format!("{base}/memories/recall"))
+            .json(&json!({"user_id": alice_id, "query": "email"})),
+        client.delete(format!("{base}/users/{alice_id}/memories")),
+    ] {
+        let response = request.header("X-API-Key", &bob).send().await.unwrap();
+        assert_eq!(response.status(), 403);
+    }
+    for request in [
+        client.get(format!("{base}/memories/{id}")),
+        client.delete(format!("{base}/memories/{id}")),
+    ] {
+        let response = request.header("X-API-Key", &bob).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+    let listed: Vec<Value> = client
+        .get(format!("{base}/memories"))
+        .header("X-API-Key", &bob)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(listed.is_empty());
+    let response = client
+        .delete(format!("{base}/users/{bob_id}/memories"))
+        .header("X-API-Key", &bob)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // 所有者和管理员可以访问
+    let listed: Vec<Value> = client
+        .get(format!("{base}/memories"))
+        .header("X-API-Key", &alice)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    let response = client
+        .get(format!("{base}/memories/{id}"))
+        .header("X-API-Key", &admin)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .delete(format!("{base}/memories/{id}"))
+        .header("X-API-Key", &alice)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+}
@@ -0,0 +1,490 @@
+//! 声明式工作流测试
+//!
+//! 从目录加载 YAML 工作流，通过 `/workflows` API 运行，检查模板参数
+//! 传到 MCP 工具、LLM 步骤拿到上游输出，以及输入错误的处理；运行在后台
+//! 执行且同一运行不会并发执行；运行记录的查询与重跑，以及重启后从检查点
+//! 恢复未完成的运行。运行按发起者的权限调用工具。
+
+use omni_agent::core::workflow::{
+    AgentStepExecutor, RunInitiator, StepExecutor, StepInput, StepStatus, Workflow,
+    WorkflowEngine, WorkflowError, WorkflowStatus, WorkflowStep,
+};
+use omni_agent::integrations::database::{DataRepository, SqliteRepository};
+use omni_agent::services::audit::{AuditConfig, AuditTrail};
+use omni_agent::services::security::Permission;
+use omni_agent::ui::api::{create_routes, AppState};
+use omni_agent::{AgentBuilder, AppConfig};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BRIEF: &str = r#"
+id: city-brief
+name: City brief
+inputs:
+  city: { required: true }
+  units: { default: metric }
+steps:
+  - id: weather
+    target:
+      type: mcp_tool
+      tool: get_weather
+      arguments: { city: "{{inputs.city}}", units: "{{inputs.units}}" }
+  - id: brief
+    depends_on: [weather]
+    target:
+      type: llm
+      prompt: "Summarize: {{steps.weather.output.summary}}"
+output:
+  weather: "{{steps.weather.output}}"
+  brief: "{{steps.brief.output}}"
+"#;
+
+async fn mcp_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "weather",
+            "version": "1.0.0",
+            "description": "Weather tools",
+            "capabilities": ["weather"],
+            "tools": [{
+                "name": "get_weather",
+                "description": "Current weather",
+                "input_schema": {"type": "object"},
+                "annotations": {"readOnlyHint": true}
+            }],
+            "metadata": {}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tools/get_weather/call"))
+        .and(body_partial_json(
+            json!({"parameters": {"city": "Paris", "units": "metric"}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"summary": "sunny, 21C"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn serve(state: AppState) -> String {
+    let app = create_routes().with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    base
+}
+
+/// 轮询运行直到结束
+async fn wait_for_run(client: &reqwest::Client, base: &str, run_id: &str) -> Value {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let run: Value = client
+                .get(format!("{base}/workflow-runs/{run_id}"))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if run["status"] == "completed" || run["status"] == "failed" {
+                break run;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap()
+}
+
+fn workflow_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("omni-workflows-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("brief.yaml"), BRIEF).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_run_workflow_from_directory() {
+    let mcp = mcp_server().await;
+    let agent = AgentBuilder::new("workflow-agent", "工作流测试智能体")
+        .add_mcp("weather", &mcp.uri())
+        .build()
+        .await
+        .unwrap();
+
+    let state = AppState::new(agent, AppConfig::default());
+    let dir = workflow_dir();
+    let loaded = state.workflows.load_directory(&dir).await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(loaded, ["city-brief"]);
+
+    let base = serve(state).await;
+    let client = reqwest::Client::new();
+
+    let listed: Value = client
+        .get(format!("{base}/workflows"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed[0]["id"], "city-brief");
+
+    let response = client
+        .post(format!("{base}/workflows/city-brief/run"))
+        .json(&json!({"inputs": {"city": "Paris"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["workflow_id"], "city-brief");
+    let run = wait_for_run(&client, &base, body["run_id"].as_str().unwrap()).await;
+    assert_eq!(run["status"], "completed");
+    assert_eq!(run["output"]["weather"], json!({"summary": "sunny, 21C"}));
+    assert!(run["output"]["brief"]
+        .as_str()
+        .unwrap()
+        .contains("Summarize: sunny, 21C"));
+
+    let missing = client
+        .post(format!("{base}/workflows/city-brief/run"))
+        .json(&json!({"inputs": {}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 400);
+    let unknown = client
+        .post(format!("{base}/workflows/city-brief/run"))
+        .json(&json!({"inputs": {"city": "Paris", "country": "FR"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown.status(), 400);
+    let not_found = client
+        .post(format!("{base}/workflows/nope/run"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(not_found.status(), 404);
+}
+
+#[tokio::test]
+async fn test_invalid_definition_is_rejected() {
+    let agent = AgentBuilder::new("workflow-agent", "工作流测试智能体")
+        .build()
+        .await
+        .unwrap();
+    let state = AppState::new(agent, AppConfig::default());
+    let dir = workflow_dir();
+    std::fs::write(
+        dir.join("broken.yaml"),
+        "id: broken\nsteps:\n  - id: s\n    target: { type: llm, prompt: '{{steps.other.output}}' }\n",
+    )
+    .unwrap();
+    let result = state.workflows.load_directory(&dir).await;
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(result.is_err());
+    assert!(state.workflows.get_all_workflows().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_inspect_and_rerun_runs() {
+    let mcp = mcp_server().await;
+    let agent = AgentBuilder::new("workflow-agent", "工作流测试智能体")
+        .add_mcp("weather", &mcp.uri())
+        .build()
+        .await
+        .unwrap();
+    let state = AppState::new(agent, AppConfig::default());
+    let dir = workflow_dir();
+    state.workflows.load_directory(&dir).await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let base = serve(state).await;
+    let client = reqwest::Client::new();
+
+    let first: Value = client
+        .post(format!("{base}/workflows/city-brief/run"))
+        .json(&json!({"inputs": {"city": "Paris"}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let run_id = first["run_id"].as_str().unwrap().to_string();
+    let first = wait_for_run(&client, &base, &run_id).await;
+
+    let runs: Value = client
+        .get(format!("{base}/workflows/city-brief/runs"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(runs["total"], 1);
+    assert_eq!(runs["items"][0]["id"], run_id);
+    assert_eq!(runs["items"][0]["status"], "completed");
+
+    let run: Value = client
+        .get(format!("{base}/workflow-runs/{run_id}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(run["inputs"], json!({"city": "Paris", "units": "metric"}));
+    assert_eq!(run["steps"][0]["output"], json!({"summary": "sunny, 21C"}));
+
+    let timeline: Value = client
+        .get(format!("{base}/workflow-runs/{run_id}/timeline"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let order: Vec<&str> = timeline["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| step["step_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(order, ["weather", "brief"]);
+    assert!(timeline["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|step| step["status"] == "succeeded" && step["finished_at"].is_string()));
+
+    // 只重跑 brief：weather 沿用上次的输出，MCP 工具不再被调用
+    let rerun = client
+        .post(format!("{base}/workflow-runs/{run_id}/rerun"))
+        .json(&json!({"from_step": "brief"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(rerun.status(), 202);
+    let rerun: Value = rerun.json().await.unwrap();
+    assert_ne!(rerun["run_id"], run_id);
+    let rerun_run = wait_for_run(&client, &base, rerun["run_id"].as_str().unwrap()).await;
+    assert_eq!(rerun_run["output"], first["output"]);
+    assert_eq!(rerun_run["rerun_of"], run_id);
+
+    let all: Value = client
+        .get(format!(
+            "{base}/workflow-runs?workflow_id=city-brief&limit=1"
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(all["total"], 2);
+    assert_eq!(all["items"][0]["id"], rerun["run_id"]);
+
+    let unknown_step = client
+        .post(format!("{base}/workflow-runs/{run_id}/rerun"))
+        .json(&json!({"from_step": "nope"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown_step.status(), 400);
+    let missing = client
+        .get(format!("{base}/workflow-runs/{}", uuid::Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
+
+/// 记录执行过的步骤，输出为 `步骤ID(上游输出)`
+#[derive(Default)]
+struct RecordingExecutor {
+    executed: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl StepExecutor for RecordingExecutor {
+    async fn execute(&self, step: &WorkflowStep, input: StepInput) -> Result<Value, String> {
+        self.executed.lock().unwrap().push(step.id.clone());
+        Ok(Value::String(format!("{}({})", step.id, input.to_text())))
+    }
+}
+
+#[tokio::test]
+async fn test_resume_interrupted_run_after_restart() {
+    let path = std::env::temp_dir().join(format!("omni-workflow-runs-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+    let workflow = Workflow::from_yaml(
+        "id: chain\nsteps:\n  - id: a\n  - id: b\n    depends_on: [a]\n  - id: c\n    depends_on: [b]",
+    )
+    .unwrap();
+
+    // 第一个进程：a 完成后进程退出，运行停留在 running 状态
+    let run_id = {
+        let store: Arc<dyn DataRepository> =
+            Arc::new(SqliteRepository::connect(&url).await.unwrap());
+        let engine = WorkflowEngine::with_executor(Arc::new(RecordingExecutor::default()))
+            .with_store(store.clone());
+        engine.register_workflow(workflow.clone()).await.unwrap();
+        let inputs = Map::from_iter([("input".to_string(), json!("x"))]);
+        let mut run = engine.create_run("chain", inputs, None).await.unwrap();
+        run.status = WorkflowStatus::Running;
+        run.steps[0].status = StepStatus::Succeeded;
+        run.steps[0].output = Some(json!("a(saved)"));
+        run.steps[1].status = StepStatus::Running;
+        store
+            .save_workflow_run(&run.to_record().unwrap())
+            .await
+            .unwrap();
+        run.id
+    };
+
+    // 重启：新引擎从存储恢复，只执行未完成的步骤
+    let executor = Arc::new(RecordingExecutor::default());
+    let store = Arc::new(SqliteRepository::connect(&url).await.unwrap());
+    let engine = Arc::new(WorkflowEngine::with_executor(executor.clone()).with_store(store));
+    assert_eq!(engine.resume_interrupted().await.unwrap(), [run_id]);
+
+    let run = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let run = engine.get_run(run_id).await.unwrap().unwrap();
+            if run.is_finished() {
+                break run;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(run.status, WorkflowStatus::Completed);
+    assert_eq!(run.output, Some(json!("c(b(a(saved)))")));
+    assert_eq!(*executor.executed.lock().unwrap(), ["b", "c"]);
+    assert!(engine.resume_interrupted().await.unwrap().is_empty());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+/// 第一个步骤等待放行，用于观察执行中的运行
+#[derive(Default)]
+struct GatedExecutor {
+    gate: tokio::sync::Notify,
+}
+
+#[async_trait::async_trait]
+impl StepExecutor for GatedExecutor {
+    async fn execute(&self, step: &WorkflowStep, _input: StepInput) -> Result<Value, String> {
+        self.gate.notified().await;
+        Ok(Value::String(step.id.clone()))
+    }
+}
+
+#[tokio::test]
+async fn test_run_is_claimed_while_executing() {
+    let executor = Arc::new(GatedExecutor::default());
+    let engine = Arc::new(WorkflowEngine::with_executor(executor.clone()));
+    engine
+        .register_workflow(Workflow::from_yaml("id: gated\nsteps:\n  - id: only").unwrap())
+        .await
+        .unwrap();
+    let run = engine.create_run("gated", Map::new(), None).await.unwrap();
+
+    engine.start_run(run.id).await.unwrap();
+    // 执行中的运行不能再次启动或执行
+    assert!(matches!(
+        engine.start_run(run.id).await,
+        Err(WorkflowError::RunActive(id)) if id == run.id
+    ));
+    assert!(matches!(
+        engine.execute_run(run.id).await,
+        Err(WorkflowError::RunActive(_))
+    ));
+
+    executor.gate.notify_one();
+    let finished = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let run = engine.get_run(run.id).await.unwrap().unwrap();
+            if run.is_finished() {
+                break run;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(finished.status, WorkflowStatus::Completed);
+    assert!(matches!(
+        engine.start_run(run.id).await,
+        Err(WorkflowError::RunFinished(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_runs_use_the_initiators_permissions() {
+    let mcp = mcp_server().await;
+    let audit = Arc::new(AuditTrail::new(AuditConfig::default()));
+    let agent = AgentBuilder::new("workflow-agent", "工作流测试智能体")
+        .add_mcp("weather", &mcp.uri())
+        .audit(audit.clone())
+        .build()
+        .await
+        .unwrap();
+    let engine = WorkflowEngine::with_executor(Arc::new(AgentStepExecutor::new(Arc::new(agent))));
+    engine
+        .register_workflow(Workflow::from_yaml(BRIEF).unwrap())
+        .await
+        .unwrap();
+    let inputs = json!({"city": "Paris"}).as_object().unwrap().clone();
+    let initiator = |permissions: Vec<Permission>| {
+        Some(RunInitiator {
+            user_id: "alice".to_string(),
+            permissions,
+        })
+    };
+
+    // 缺少 tool:get_weather 权限时不调用工具
+    let run = engine
+        .create_run("city-brief", inputs.clone(), initiator(vec![Permission::Execute]))
+        .await
+        .unwrap();
+    assert!(engine.execute_run(run.id).await.is_err());
+    let run = engine.get_run(run.id).await.unwrap().unwrap();
+    let error = run.step("weather").unwrap().error.clone().unwrap();
+    assert!(error.contains("tool:get_weather"), "{error}");
+
+    let run = engine
+        .create_run(
+            "city-brief",
+            inputs,
+            initiator(vec![
+                Permission::Execute,
+                Permission::ToolAccess("get_weather".to_string()),
+            ]),
+        )
+        .await
+        .unwrap();
+    engine.execute_run(run.id).await.unwrap();
+    let logs = audit.recent(None).await;
+    let call = logs.iter().find(|log| log.action == "tool_call").unwrap();
+    assert_eq!(call.resource, "get_weather");
+    assert_eq!(call.user_id, "alice");
+    assert!(logs
+        .iter()
+        .filter(|log| log.action == "llm_call")
+        .all(|log| log.user_id == "alice"));
+}